@@ -1,16 +1,27 @@
-use crate::config::{BarConfig, BarPosition, MarginConfig, ModuleConfig};
-use crate::modules::{BarModuleFactory, ModuleInfo, ModuleLocation};
+use crate::config::{
+    BarConfig, BarPosition, HotCornerAction, HotCornerConfig, HotCornerPosition, MarginConfig,
+    ModuleConfig, RuleCondition, RuleConfig, SeparatorConfig, ShadowConfig, ShadowStyle,
+};
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{BarModuleFactory, ModuleInfo, ModuleLocation, PopupButton};
 use crate::popup::Popup;
-use crate::Ironbar;
-use color_eyre::Result;
+use crate::script::Script;
+use crate::style::load_scoped_css;
+use crate::{glib_recv_mpsc, Ironbar};
+use color_eyre::{Report, Result};
 use glib::Propagation;
-use gtk::gdk::Monitor;
+use gtk::gdk::{self, Monitor};
 use gtk::prelude::*;
-use gtk::{Application, ApplicationWindow, IconTheme, Orientation, Window, WindowType};
+use gtk::{
+    cairo, Application, ApplicationWindow, CssProvider, IconTheme, Label, Orientation, Separator,
+    StyleContext, Widget, Window, WindowType,
+};
 use gtk_layer_shell::LayerShell;
+use std::cell::RefCell;
 use std::rc::Rc;
 use std::time::Duration;
-use tracing::{debug, info};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info};
 
 #[derive(Debug, Clone)]
 enum Inner {
@@ -34,6 +45,8 @@ pub struct Bar {
     center: gtk::Box,
     end: gtk::Box,
 
+    has_shadow: bool,
+
     inner: Inner,
 }
 
@@ -65,18 +78,28 @@ impl Bar {
             .hexpand(false)
             .name("bar");
 
+        let height = (f64::from(config.height) * config.scale).round() as i32;
+
         let content = if orientation == Orientation::Horizontal {
-            content.height_request(config.height)
+            content.height_request(height)
         } else {
-            content.width_request(config.height)
+            content.width_request(height)
         }
         .build();
 
         content.style_context().add_class("container");
 
-        let start = create_container("start", orientation);
-        let center = create_container("center", orientation);
-        let end = create_container("end", orientation);
+        let start = create_container("start", orientation, config.spacing);
+        let center = create_container("center", orientation, config.spacing);
+        let end = create_container("end", orientation, config.spacing);
+
+        if orientation == Orientation::Horizontal {
+            start.set_margin_start(config.padding);
+            end.set_margin_end(config.padding);
+        } else {
+            start.set_margin_top(config.padding);
+            end.set_margin_bottom(config.padding);
+        }
 
         content.add(&start);
         content.set_center_widget(Some(&center));
@@ -84,6 +107,8 @@ impl Bar {
 
         window.add(&content);
 
+        let has_shadow = config.shadow.is_some();
+
         window.connect_destroy_event(|_, _| {
             info!("Shutting down");
             gtk::main_quit();
@@ -100,6 +125,7 @@ impl Bar {
             start,
             center,
             end,
+            has_shadow,
             inner: Inner::New {
                 config: Some(config),
             },
@@ -150,8 +176,31 @@ impl Bar {
             }
         }
 
+        for hot_corner in &config.hot_corners {
+            self.setup_hot_corner(hot_corner, monitor);
+        }
+
+        if let Some(shadow) = &config.shadow {
+            self.setup_shadow(shadow);
+        }
+
+        if !config.rules.is_empty() {
+            self.setup_rules(&config.rules);
+        }
+
+        let stylesheet = config.stylesheet.clone();
+        let scale = config.scale;
         let load_result = self.load_modules(config, monitor)?;
 
+        if let Some(stylesheet) = stylesheet {
+            self.load_stylesheet(&stylesheet, &load_result.popup);
+        }
+
+        if scale != 1.0 {
+            scale_text(&self.window, scale);
+            scale_text(&load_result.popup.window, scale);
+        }
+
         self.show(!start_hidden);
 
         self.inner = Inner::Loaded {
@@ -160,6 +209,23 @@ impl Bar {
         Ok(self)
     }
 
+    /// Loads a stylesheet which only applies to this bar and its popup.
+    /// The stylesheet is unloaded when the bar is destroyed.
+    fn load_stylesheet(&self, path: &str, popup: &Popup) {
+        let scope = format!("bar-style-{}", Ironbar::unique_id());
+
+        self.window.add_class(&scope);
+        popup.window.add_class(&scope);
+
+        let provider = load_scoped_css(self.ironbar.config_dir.join(path), &scope);
+
+        self.window.connect_destroy(move |_| {
+            if let Some(screen) = gdk::Screen::default() {
+                StyleContext::remove_provider_for_screen(&screen, &provider);
+            }
+        });
+    }
+
     /// Sets up GTK layer shell for a provided application window.
     fn setup_layer_shell(
         &self,
@@ -212,6 +278,67 @@ impl Bar {
         );
     }
 
+    /// Extends the window past the inner edge of the bar,
+    /// and paints the shadow or scrim into the window behind the bar content.
+    fn setup_shadow(&self, shadow: &ShadowConfig) {
+        let color = match gdk::RGBA::parse(&shadow.color) {
+            Ok(color) => color,
+            Err(err) => {
+                error!("Invalid shadow color '{}': {err}", shadow.color);
+                return;
+            }
+        };
+
+        // the window background must not be painted,
+        // and needs an alpha channel for the shadow to be translucent
+        if let Some(visual) = gdk::Screen::default().and_then(|screen| screen.rgba_visual()) {
+            self.window.set_visual(Some(&visual));
+        }
+        self.window.set_app_paintable(true);
+
+        let position = self.position;
+
+        match position {
+            BarPosition::Top => self.content.set_margin_bottom(shadow.size),
+            BarPosition::Bottom => self.content.set_margin_top(shadow.size),
+            BarPosition::Left => self.content.set_margin_end(shadow.size),
+            BarPosition::Right => self.content.set_margin_start(shadow.size),
+        }
+
+        {
+            let window = self.window.clone();
+
+            self.content.connect_size_allocate(move |_, allocation| {
+                // the automatic exclusive zone would include the shadow
+                let thickness = bar_thickness(position, allocation);
+                let exclusive_zone = window.exclusive_zone();
+                if exclusive_zone > 0 && exclusive_zone != thickness {
+                    window.set_exclusive_zone(thickness);
+                }
+
+                // let clicks through the shadow to whatever is beneath it
+                let region = cairo::Region::create_rectangle(&cairo::RectangleInt::new(
+                    allocation.x(),
+                    allocation.y(),
+                    allocation.width(),
+                    allocation.height(),
+                ));
+                window.input_shape_combine_region(Some(&region));
+            });
+        }
+
+        let content = self.content.clone();
+        let style = shadow.style;
+
+        self.window.connect_draw(move |window, cr| {
+            if let Err(err) = draw_shadow(cr, window, &content, position, style, &color) {
+                error!("Failed to draw bar shadow: {err}");
+            }
+
+            Propagation::Proceed
+        });
+    }
+
     fn setup_autohide(window: &ApplicationWindow, hotspot_window: &Window, timeout: u64) {
         hotspot_window.hide();
 
@@ -246,6 +373,161 @@ impl Bar {
         }
     }
 
+    /// Creates an invisible layer-shell window at a corner or edge of the screen,
+    /// which runs the action when the pointer enters it.
+    /// The window is closed along with the bar.
+    fn setup_hot_corner(&self, config: &HotCornerConfig, monitor: &Monitor) {
+        use gtk_layer_shell::Edge;
+
+        let window = Window::new(WindowType::Toplevel);
+        window.set_opacity(0.0);
+        window.set_decorated(false);
+
+        window.init_layer_shell();
+        window.set_monitor(monitor);
+        window.set_layer(gtk_layer_shell::Layer::Top);
+        window.set_namespace(concat!(env!("CARGO_PKG_NAME"), "-hot-corner"));
+
+        // sit right at the screen edge, ignoring the bar's own exclusive zone
+        window.set_exclusive_zone(-1);
+
+        let (top, bottom, left, right) = match config.position {
+            HotCornerPosition::TopLeft => (true, false, true, false),
+            HotCornerPosition::TopRight => (true, false, false, true),
+            HotCornerPosition::BottomLeft => (false, true, true, false),
+            HotCornerPosition::BottomRight => (false, true, false, true),
+            HotCornerPosition::Top => (true, false, true, true),
+            HotCornerPosition::Bottom => (false, true, true, true),
+            HotCornerPosition::Left => (true, true, true, false),
+            HotCornerPosition::Right => (true, true, false, true),
+        };
+
+        window.set_anchor(Edge::Top, top);
+        window.set_anchor(Edge::Bottom, bottom);
+        window.set_anchor(Edge::Left, left);
+        window.set_anchor(Edge::Right, right);
+
+        // edges stretch along their anchored sides
+        let width = if left && right { 0 } else { config.size };
+        let height = if top && bottom { 0 } else { config.size };
+        window.set_size_request(width, height);
+
+        let pending = Rc::new(RefCell::new(None::<glib::SourceId>));
+
+        {
+            let ironbar = self.ironbar.clone();
+            let name = self.name.clone();
+            let action = config.action.clone();
+            let delay = config.delay;
+            let pending = pending.clone();
+
+            window.connect_enter_notify_event(move |_, _| {
+                let run = {
+                    let ironbar = ironbar.clone();
+                    let name = name.clone();
+                    let action = action.clone();
+
+                    move || match ironbar.bar_by_name(&name) {
+                        Some(bar) => bar.run_hot_corner_action(&action),
+                        None => error!("Hot corner triggered for missing bar '{name}'"),
+                    }
+                };
+
+                if delay == 0 {
+                    run();
+                } else {
+                    let pending2 = pending.clone();
+                    let source =
+                        glib::timeout_add_local_once(Duration::from_millis(delay), move || {
+                            pending2.take();
+                            run();
+                        });
+
+                    if let Some(previous) = pending.replace(Some(source)) {
+                        previous.remove();
+                    }
+                }
+
+                Propagation::Proceed
+            });
+        }
+
+        window.connect_leave_notify_event(move |_, _| {
+            if let Some(source) = pending.take() {
+                source.remove();
+            }
+
+            Propagation::Proceed
+        });
+
+        window.show();
+
+        self.window.connect_destroy(move |_| window.close());
+    }
+
+    fn run_hot_corner_action(&self, action: &HotCornerAction) {
+        debug!(
+            "Running hot corner action on bar '{}': {action:?}",
+            self.name
+        );
+
+        match action {
+            HotCornerAction::ShowBar => self.set_visible(true),
+            HotCornerAction::ToggleBar => self.set_visible(!self.visible()),
+            HotCornerAction::ShowPopup { widget } => {
+                if !self.popup().visible() {
+                    if let Err(err) = self.show_popup(widget) {
+                        error!("{:?}", err.wrap_err("Failed to open popup from hot corner"));
+                    }
+                }
+            }
+            HotCornerAction::Command { command } => {
+                Script::from(command.clone()).run_as_oneshot(None);
+            }
+        }
+    }
+
+    /// Adds each rule's class to the bar window while its condition is met.
+    fn setup_rules(&self, rules: &[RuleConfig]) {
+        let rules = Rc::new(rules.to_vec());
+        let state = Rc::new(RefCell::new(vec![false; rules.len()]));
+
+        let set_state = {
+            let window = self.window.downgrade();
+            let rules = rules.clone();
+
+            move |index: usize, met: bool| {
+                state.borrow_mut()[index] = met;
+
+                if let Some(window) = window.upgrade() {
+                    apply_rule_classes(&window, &rules, &state.borrow());
+                }
+            }
+        };
+
+        let (tx, rx) = mpsc::channel(16);
+
+        for (index, rule) in rules.iter().enumerate() {
+            let tx = tx.clone();
+
+            match &rule.condition {
+                RuleCondition::OnBattery => watch_on_battery(index, &self.ironbar, tx),
+                RuleCondition::Offline => watch_offline(index, &self.ironbar, tx),
+                RuleCondition::Time { from, to } => watch_time(index, rule, from, to, tx),
+                // evaluated on the GTK side
+                RuleCondition::Dynamic { value } => {
+                    let set_state = set_state.clone();
+                    value.clone().subscribe(move |met| set_state(index, met));
+                }
+            }
+        }
+
+        glib_recv_mpsc!(rx, update => {
+            let (index, met) = update;
+            set_state(index, met);
+        });
+    }
+
     /// Loads the configured modules onto a bar.
     fn load_modules(&self, config: BarConfig, monitor: &Monitor) -> Result<BarLoadResult> {
         let icon_theme = IconTheme::new();
@@ -260,31 +542,57 @@ impl Bar {
                 ModuleInfo {
                     app,
                     bar_position: config.position,
+                    bar_name: &self.name,
                     monitor,
                     output_name: &self.monitor_name,
                     location: $location,
                     icon_theme: &icon_theme,
+                    icon_size: config.icon_size,
+                    scale: config.scale,
                 }
             };
         }
 
         // popup ignores module location so can bodge this for now
-        let popup = Popup::new(&info!(ModuleLocation::Left), config.popup_gap);
+        let popup = Popup::new(
+            &info!(ModuleLocation::Left),
+            config.popup_gap,
+            config.close_other_popups,
+        );
         let popup = Rc::new(popup);
 
+        let separator = config
+            .separator
+            .as_ref()
+            .filter(|separator| **separator != SeparatorConfig::Line(false));
+
         if let Some(modules) = config.start {
             let info = info!(ModuleLocation::Left);
-            add_modules(&self.start, modules, &info, &self.ironbar, &popup)?;
+            add_modules(
+                &self.start,
+                modules,
+                &info,
+                &self.ironbar,
+                &popup,
+                separator,
+            )?;
         }
 
         if let Some(modules) = config.center {
             let info = info!(ModuleLocation::Center);
-            add_modules(&self.center, modules, &info, &self.ironbar, &popup)?;
+            add_modules(
+                &self.center,
+                modules,
+                &info,
+                &self.ironbar,
+                &popup,
+                separator,
+            )?;
         }
 
         if let Some(modules) = config.end {
             let info = info!(ModuleLocation::Right);
-            add_modules(&self.end, modules, &info, &self.ironbar, &popup)?;
+            add_modules(&self.end, modules, &info, &self.ironbar, &popup, separator)?;
         }
 
         let result = BarLoadResult { popup };
@@ -325,6 +633,33 @@ impl Bar {
         }
     }
 
+    /// Opens the popup for the widget with the provided name.
+    /// Any popup already open on the bar is closed first.
+    pub fn show_popup(&self, widget_name: &str) -> Result<()> {
+        let popup = self.popup();
+
+        // only one popup per bar, so hide if open for another widget
+        popup.hide();
+
+        let data = popup
+            .container_cache
+            .borrow()
+            .iter()
+            .find(|(_, value)| value.name == widget_name)
+            .map(|(id, value)| (*id, value.content.buttons.first().cloned()));
+
+        match data {
+            Some((id, Some(button))) => {
+                let button_id = button.popup_id();
+                popup.show(id, button_id);
+
+                Ok(())
+            }
+            Some((_, None)) => Err(Report::msg("Module has no popup functionality")),
+            None => Err(Report::msg("Invalid module name")),
+        }
+    }
+
     pub fn visible(&self) -> bool {
         self.window.is_visible()
     }
@@ -335,7 +670,10 @@ impl Bar {
     }
 
     pub fn set_exclusive(&self, exclusive: bool) {
-        if exclusive {
+        if exclusive && self.has_shadow {
+            let thickness = bar_thickness(self.position, &self.content.allocation());
+            self.window.set_exclusive_zone(thickness);
+        } else if exclusive {
             self.window.auto_exclusive_zone_enable();
         } else {
             self.window.set_exclusive_zone(0);
@@ -343,11 +681,74 @@ impl Bar {
     }
 }
 
+/// Gets the size of the bar content, perpendicular to the screen edge it is on.
+fn bar_thickness(position: BarPosition, allocation: &gtk::Allocation) -> i32 {
+    if position.orientation() == Orientation::Horizontal {
+        allocation.height()
+    } else {
+        allocation.width()
+    }
+}
+
+/// Paints the shadow or scrim as a gradient
+/// running from the screen edge towards the inner edge of the window.
+fn draw_shadow(
+    cr: &cairo::Context,
+    window: &ApplicationWindow,
+    content: &gtk::Box,
+    position: BarPosition,
+    style: ShadowStyle,
+    color: &gdk::RGBA,
+) -> std::result::Result<(), cairo::Error> {
+    let width = f64::from(window.allocated_width());
+    let height = f64::from(window.allocated_height());
+
+    let content = content.allocation();
+    let bar = f64::from(bar_thickness(position, &content));
+
+    let (gradient, length) = match position {
+        BarPosition::Top => (cairo::LinearGradient::new(0.0, 0.0, 0.0, height), height),
+        BarPosition::Bottom => (cairo::LinearGradient::new(0.0, height, 0.0, 0.0), height),
+        BarPosition::Left => (cairo::LinearGradient::new(0.0, 0.0, width, 0.0), width),
+        BarPosition::Right => (cairo::LinearGradient::new(width, 0.0, 0.0, 0.0), width),
+    };
+
+    if length <= bar {
+        return Ok(());
+    }
+
+    let (red, green, blue, alpha) = (color.red(), color.green(), color.blue(), color.alpha());
+
+    match style {
+        ShadowStyle::Shadow => {
+            let start = bar / length;
+            gradient.add_color_stop_rgba(start, red, green, blue, alpha);
+            gradient.add_color_stop_rgba(1.0, red, green, blue, 0.0);
+
+            // only paint outside the bar
+            match position {
+                BarPosition::Top => cr.rectangle(0.0, bar, width, height - bar),
+                BarPosition::Bottom => cr.rectangle(0.0, 0.0, width, height - bar),
+                BarPosition::Left => cr.rectangle(bar, 0.0, width - bar, height),
+                BarPosition::Right => cr.rectangle(0.0, 0.0, width - bar, height),
+            }
+        }
+        ShadowStyle::Scrim => {
+            gradient.add_color_stop_rgba(0.0, red, green, blue, alpha);
+            gradient.add_color_stop_rgba(1.0, red, green, blue, 0.0);
+            cr.rectangle(0.0, 0.0, width, height);
+        }
+    }
+
+    cr.set_source(&gradient)?;
+    cr.fill()
+}
+
 /// Creates a `gtk::Box` container to place widgets inside.
-fn create_container(name: &str, orientation: Orientation) -> gtk::Box {
+fn create_container(name: &str, orientation: Orientation, spacing: i32) -> gtk::Box {
     let container = gtk::Box::builder()
         .orientation(orientation)
-        .spacing(0)
+        .spacing(spacing)
         .name(name)
         .build();
 
@@ -355,6 +756,151 @@ fn create_container(name: &str, orientation: Orientation) -> gtk::Box {
     container
 }
 
+/// Scales the text size of a window.
+/// Text inherits its size from the window,
+/// so this applies to everything inside it unless overridden by the stylesheet.
+fn scale_text(window: &ApplicationWindow, scale: f64) {
+    let provider = CssProvider::new();
+    let css = format!("* {{ font-size: {:.0}%; }}", scale * 100.0);
+
+    match provider.load_from_data(css.as_bytes()) {
+        // only added to the window itself, so nested widgets are not scaled again
+        Ok(()) => window
+            .style_context()
+            .add_provider(&provider, gtk::STYLE_PROVIDER_PRIORITY_APPLICATION),
+        Err(err) => error!("Failed to scale text: {err}"),
+    }
+}
+
+/// Adds or removes each rule's class on the bar window, depending on its condition.
+/// Where several rules set the same class, it is added if any of them apply.
+fn apply_rule_classes(window: &ApplicationWindow, rules: &[RuleConfig], state: &[bool]) {
+    for rule in rules {
+        let applies = rules
+            .iter()
+            .zip(state)
+            .any(|(other, met)| other.class == rule.class && *met != other.invert);
+
+        if applies {
+            window.add_class(&rule.class);
+        } else {
+            window.style_context().remove_class(&rule.class);
+        }
+    }
+}
+
+#[cfg(feature = "upower")]
+fn watch_on_battery(index: usize, ironbar: &Ironbar, tx: mpsc::Sender<(usize, bool)>) {
+    use crate::{send_async, spawn};
+
+    let mut on_battery = match ironbar.clients.borrow_mut().battery() {
+        Ok(client) => client.subscribe(),
+        Err(err) => {
+            error!("{err:?}");
+            return;
+        }
+    };
+
+    spawn(async move {
+        loop {
+            let value = *on_battery.borrow_and_update();
+            send_async!(tx, (index, value));
+
+            if on_battery.changed().await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+#[cfg(not(feature = "upower"))]
+fn watch_on_battery(_index: usize, _ironbar: &Ironbar, _tx: mpsc::Sender<(usize, bool)>) {
+    error!("The `on_battery` rule requires the `upower` feature");
+}
+
+#[cfg(feature = "networkmanager")]
+fn watch_offline(index: usize, ironbar: &Ironbar, tx: mpsc::Sender<(usize, bool)>) {
+    use crate::clients::networkmanager::state::CellularState;
+    use crate::{send_async, spawn};
+    use futures_lite::StreamExt;
+    use futures_signals::signal::SignalExt;
+
+    let client = match ironbar.clients.borrow_mut().networkmanager() {
+        Ok(client) => client,
+        Err(err) => {
+            error!("{err:?}");
+            return;
+        }
+    };
+
+    spawn(async move {
+        let mut states = client.subscribe().to_stream();
+        let mut previous = None;
+
+        while let Some(state) = states.next().await {
+            let offline = !(state.wired.connected().is_some()
+                || state.wifi.connected().is_some()
+                || matches!(state.cellular, CellularState::Connected(_)));
+
+            if previous != Some(offline) {
+                debug!("Offline: {offline}");
+                previous = Some(offline);
+                send_async!(tx, (index, offline));
+            }
+        }
+    });
+}
+
+#[cfg(not(feature = "networkmanager"))]
+fn watch_offline(_index: usize, _ironbar: &Ironbar, _tx: mpsc::Sender<(usize, bool)>) {
+    error!("The `offline` rule requires the `networkmanager` feature");
+}
+
+#[cfg(feature = "schedule")]
+fn watch_time(
+    index: usize,
+    rule: &RuleConfig,
+    from: &str,
+    to: &str,
+    tx: mpsc::Sender<(usize, bool)>,
+) {
+    use crate::dynamic_value::schedule::{
+        in_range, parse_time, until_next_change, MAX_TIME_CHECK_INTERVAL,
+    };
+    use crate::{send_async, spawn};
+    use chrono::Local;
+    use tokio::time::sleep;
+
+    let (from, to) = match (parse_time(from), parse_time(to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(err), _) | (_, Err(err)) => {
+            error!("Invalid time for rule '{}': {err}", rule.class);
+            return;
+        }
+    };
+
+    spawn(async move {
+        loop {
+            let now = Local::now().time();
+            send_async!(tx, (index, in_range(now, from, to)));
+
+            let wait = until_next_change(now, from, to);
+            sleep(wait.min(MAX_TIME_CHECK_INTERVAL)).await;
+        }
+    });
+}
+
+#[cfg(not(feature = "schedule"))]
+fn watch_time(
+    _index: usize,
+    _rule: &RuleConfig,
+    _from: &str,
+    _to: &str,
+    _tx: mpsc::Sender<(usize, bool)>,
+) {
+    error!("The `time` rule requires the `schedule` feature");
+}
+
 #[derive(Debug)]
 struct BarLoadResult {
     popup: Rc<Popup>,
@@ -368,16 +914,84 @@ fn add_modules(
     info: &ModuleInfo,
     ironbar: &Rc<Ironbar>,
     popup: &Rc<Popup>,
+    separator: Option<&SeparatorConfig>,
 ) -> Result<()> {
     let module_factory = BarModuleFactory::new(ironbar.clone(), popup.clone()).into();
 
-    for config in modules {
+    for (index, config) in modules.into_iter().enumerate() {
+        if let Some(separator) = separator.filter(|_| index > 0) {
+            content.add(&create_separator(separator, info.bar_position));
+        }
+
         config.create(&module_factory, content, info)?;
     }
 
+    if separator.is_some() {
+        update_separators(content);
+
+        // each module is wrapped in a container, which is hidden along with the module
+        for child in content.children() {
+            if child.style_context().has_class("separator") {
+                continue;
+            }
+
+            let content = content.downgrade();
+            child.connect_visible_notify(move |_| {
+                if let Some(content) = content.upgrade() {
+                    update_separators(&content);
+                }
+            });
+        }
+    }
+
     Ok(())
 }
 
+/// Creates a separator to place between two modules.
+fn create_separator(config: &SeparatorConfig, position: BarPosition) -> Widget {
+    let widget: Widget = match config {
+        SeparatorConfig::Line(_) => {
+            // the line runs across the bar
+            let orientation = match position.orientation() {
+                Orientation::Horizontal => Orientation::Vertical,
+                _ => Orientation::Horizontal,
+            };
+
+            Separator::new(orientation).upcast()
+        }
+        SeparatorConfig::Label(text) => Label::builder()
+            .label(text)
+            .angle(position.get_angle())
+            .build()
+            .upcast(),
+    };
+
+    widget.add_class("separator");
+    widget
+}
+
+/// Shows each separator only if the module after it is visible,
+/// and there is a visible module somewhere before it.
+fn update_separators(content: &gtk::Box) {
+    let mut any_visible = false;
+    let mut separator = None;
+
+    for child in content.children() {
+        if child.style_context().has_class("separator") {
+            separator = Some(child);
+            continue;
+        }
+
+        let visible = child.is_visible();
+
+        if let Some(separator) = separator.take() {
+            separator.set_visible(any_visible && visible);
+        }
+
+        any_visible |= visible;
+    }
+}
+
 pub fn create_bar(
     app: &Application,
     monitor: &Monitor,
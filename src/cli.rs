@@ -1,15 +1,19 @@
 use crate::error::ExitCode;
 use crate::ipc::commands::Command;
 use crate::ipc::responses::Response;
-use clap::{Parser, ValueEnum};
+use clap::{CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
 use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
 use std::process::exit;
 
 #[derive(Parser, Debug, Serialize, Deserialize)]
 #[command(version)]
 pub struct Args {
     #[command(subcommand)]
-    pub command: Option<Command>,
+    pub command: Option<CliCommand>,
 
     /// Prints the config JSON schema to `stdout`
     /// and exits.
@@ -22,6 +26,11 @@ pub struct Args {
     #[arg(long)]
     pub debug: bool,
 
+    /// The config profile to start with.
+    /// Only applies when starting the bar.
+    #[arg(long)]
+    pub profile: Option<String>,
+
     /// Format to output the response as.
     #[arg(short, long)]
     pub format: Option<Format>,
@@ -32,6 +41,29 @@ pub struct Args {
     sway_bar_id: Option<String>,
 }
 
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+pub enum CliCommand {
+    #[command(flatten)]
+    Ipc(Command),
+
+    /// Print a completion script for the given shell to `stdout`.
+    #[serde(skip)]
+    Completions {
+        /// The shell to generate completions for.
+        shell: Shell,
+    },
+
+    /// Generate man pages.
+    /// Prints the main page to `stdout`,
+    /// or writes a page for every command if a directory is given.
+    #[serde(skip)]
+    Man {
+        /// The directory to write pages to.
+        #[arg(long)]
+        out_dir: Option<PathBuf>,
+    },
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, ValueEnum, Clone, Copy)]
 pub enum Format {
     #[default]
@@ -58,3 +90,36 @@ pub fn handle_response(response: Response, format: Format) {
         exit(ExitCode::IpcResponseError as i32)
     }
 }
+
+pub fn print_completions(shell: Shell) {
+    let mut command = Args::command();
+    let name = command.get_name().to_string();
+
+    clap_complete::generate(shell, &mut command, name, &mut io::stdout());
+}
+
+pub fn generate_man(out_dir: Option<&Path>) -> io::Result<()> {
+    let command = Args::command();
+
+    match out_dir {
+        Some(dir) => write_man_pages(command, dir),
+        None => clap_mangen::Man::new(command).render(&mut io::stdout()),
+    }
+}
+
+/// Writes a page for the command, and recursively for each of its subcommands.
+/// Subcommand pages are named after their full path, ie `ironbar-var-set.1`.
+fn write_man_pages(command: clap::Command, dir: &Path) -> io::Result<()> {
+    for subcommand in command.get_subcommands() {
+        if subcommand.is_hide_set() {
+            continue;
+        }
+
+        let name = format!("{}-{}", command.get_name(), subcommand.get_name());
+        write_man_pages(subcommand.clone().name(name), dir)?;
+    }
+
+    let man = clap_mangen::Man::new(command);
+    let mut file = File::create(dir.join(man.get_filename()))?;
+    man.render(&mut file)
+}
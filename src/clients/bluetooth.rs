@@ -0,0 +1,161 @@
+use crate::{register_fallible_client, spawn};
+use color_eyre::Result;
+use futures_lite::StreamExt;
+use serde::Serialize;
+use tokio::sync::watch;
+use tracing::{debug, error};
+use zbus::fdo::{ManagedObjects, ObjectManagerProxy};
+use zbus::{MatchRule, MessageStream, MessageType};
+
+const BLUEZ: &str = "org.bluez";
+const DEVICE_INTERFACE: &str = "org.bluez.Device1";
+const BATTERY_INTERFACE: &str = "org.bluez.Battery1";
+
+/// A connected Bluetooth device which reports its battery level.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Device {
+    /// The D-Bus object path of the device.
+    pub path: String,
+    pub name: String,
+    /// The freedesktop icon name for the type of device, ie `audio-headphones`.
+    pub icon: Option<String>,
+    /// Battery level as a percentage.
+    pub battery: u8,
+}
+
+impl Device {
+    /// Whether the device is an audio device, such as headphones or a headset.
+    #[cfg(feature = "bar")]
+    pub fn is_audio(&self) -> bool {
+        self.icon
+            .as_deref()
+            .is_some_and(|icon| icon.starts_with("audio-"))
+    }
+}
+
+/// Tracks the battery levels of connected BlueZ devices.
+#[derive(Debug)]
+pub struct Client {
+    devices: watch::Receiver<Vec<Device>>,
+}
+
+impl Client {
+    pub async fn new() -> Result<Self> {
+        let dbus = Box::pin(zbus::Connection::system()).await?;
+
+        let object_manager = ObjectManagerProxy::builder(&dbus)
+            .destination(BLUEZ)?
+            .path("/")?
+            .build()
+            .await?;
+
+        let devices = battery_devices(object_manager.get_managed_objects().await?);
+        let (tx, rx) = watch::channel(devices);
+
+        let mut added = object_manager.receive_interfaces_added().await?;
+        let mut removed = object_manager.receive_interfaces_removed().await?;
+
+        // the object manager does not report property changes,
+        // such as a device connecting or its battery level changing
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace("/org/bluez")?
+            .build();
+        let mut changed = MessageStream::for_match_rule(rule, &dbus, None).await?;
+
+        spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(_) = added.next() => {}
+                    Some(_) = removed.next() => {}
+                    Some(_) = changed.next() => {}
+                    else => break,
+                }
+
+                // devices expose several interfaces each,
+                // so it is simplest to re-read them all on any change
+                match object_manager.get_managed_objects().await {
+                    Ok(objects) => {
+                        let devices = battery_devices(objects);
+
+                        tx.send_if_modified(|current| {
+                            if *current == devices {
+                                false
+                            } else {
+                                debug!("Bluetooth battery devices: {devices:?}");
+                                *current = devices;
+                                true
+                            }
+                        });
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+            }
+        });
+
+        Ok(Self { devices: rx })
+    }
+
+    /// Gets a receiver which holds the connected devices reporting a battery level,
+    /// sorted by name.
+    #[cfg(feature = "bar")]
+    pub fn subscribe(&self) -> watch::Receiver<Vec<Device>> {
+        self.devices.clone()
+    }
+}
+
+register_fallible_client!(Client, bluetooth);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(&*self.devices.borrow())?)
+    }
+}
+
+/// Gets the connected devices from the BlueZ objects which have a battery.
+///
+/// Headsets only expose their battery over `Battery1`
+/// if BlueZ's experimental features are enabled.
+fn battery_devices(objects: ManagedObjects) -> Vec<Device> {
+    let mut devices = objects
+        .into_iter()
+        .filter_map(|(path, interfaces)| {
+            let device = interfaces.get(DEVICE_INTERFACE)?;
+            let battery = interfaces.get(BATTERY_INTERFACE)?;
+
+            let connected = device
+                .get("Connected")
+                .and_then(|value| bool::try_from(value).ok())
+                .unwrap_or_default();
+
+            if !connected {
+                return None;
+            }
+
+            let get_str = |key| {
+                device
+                    .get(key)
+                    .and_then(|value| <&str>::try_from(value).ok())
+                    .map(ToString::to_string)
+            };
+
+            Some(Device {
+                path: path.to_string(),
+                name: get_str("Alias")
+                    .or_else(|| get_str("Name"))
+                    .or_else(|| get_str("Address"))
+                    .unwrap_or_default(),
+                icon: get_str("Icon"),
+                battery: battery
+                    .get("Percentage")
+                    .and_then(|value| u8::try_from(value).ok())?,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    devices
+}
@@ -0,0 +1,159 @@
+use crate::script::{Script, ScriptInput};
+use crate::spawn;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{interval, Instant};
+use tracing::{debug, error};
+
+/// Settings for a break timer.
+/// Modules with the same settings share a timer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TimerConfig {
+    pub interval: Duration,
+    pub duration: Duration,
+    pub snooze: Duration,
+    pub on_break: Option<ScriptInput>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BreakState {
+    /// Time until the next break starts,
+    /// or until the current break ends.
+    pub remaining: Duration,
+    pub on_break: bool,
+}
+
+/// Break timer shared between bars,
+/// so each break starts once and snoozing on any bar affects them all.
+#[derive(Debug)]
+pub struct Client {
+    state: watch::Receiver<BreakState>,
+    snooze_tx: mpsc::Sender<()>,
+}
+
+impl Client {
+    pub(crate) fn new(config: TimerConfig) -> Self {
+        let mut timer = Timer::new(config.interval, config.duration, config.snooze);
+        let on_break = config.on_break.map(Script::new_polling);
+
+        let (tx, rx) = watch::channel(timer.state());
+        let (snooze_tx, mut snooze_rx) = mpsc::channel(8);
+
+        spawn(async move {
+            let mut interval = interval(Duration::from_secs(1));
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if timer.tick() {
+                            debug!("Starting break");
+
+                            if let Some(script) = &on_break {
+                                script.run_as_oneshot(None);
+                            }
+                        }
+                    }
+                    Some(()) = snooze_rx.recv() => {
+                        debug!("Snoozing break");
+                        timer.snooze();
+                    }
+                }
+
+                tx.send_replace(timer.state());
+            }
+        });
+
+        Self {
+            state: rx,
+            snooze_tx,
+        }
+    }
+
+    /// Gets a receiver which holds the current timer state.
+    pub fn subscribe(&self) -> watch::Receiver<BreakState> {
+        self.state.clone()
+    }
+
+    /// Ends the current break early, or delays the next one.
+    pub async fn snooze(&self) {
+        if let Err(err) = self.snooze_tx.send(()).await {
+            error!("{err:?}");
+        }
+    }
+}
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> color_eyre::Result<serde_json::Value> {
+        let state = *self.state.borrow();
+
+        Ok(serde_json::json!({
+            "remaining": state.remaining.as_secs(),
+            "on_break": state.on_break,
+        }))
+    }
+}
+
+/// Tracks the time until the next break.
+struct Timer {
+    interval: Duration,
+    duration: Duration,
+    snooze: Duration,
+
+    next_break: Instant,
+    break_end: Option<Instant>,
+}
+
+impl Timer {
+    fn new(interval: Duration, duration: Duration, snooze: Duration) -> Self {
+        Self {
+            interval,
+            duration,
+            snooze,
+            next_break: Instant::now() + interval,
+            break_end: None,
+        }
+    }
+
+    /// Updates the timer, returning `true` if a break has just started.
+    fn tick(&mut self) -> bool {
+        let now = Instant::now();
+
+        match self.break_end {
+            Some(end) if now >= end => {
+                self.break_end = None;
+                self.next_break = now + self.interval;
+                false
+            }
+            None if now >= self.next_break => {
+                self.break_end = Some(now + self.duration);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Ends the current break early, or delays the next one.
+    fn snooze(&mut self) {
+        if self.break_end.take().is_some() {
+            self.next_break = Instant::now() + self.snooze;
+        } else {
+            self.next_break += self.snooze;
+        }
+    }
+
+    fn state(&self) -> BreakState {
+        let now = Instant::now();
+
+        match self.break_end {
+            Some(end) => BreakState {
+                remaining: end.saturating_duration_since(now),
+                on_break: true,
+            },
+            None => BreakState {
+                remaining: self.next_break.saturating_duration_since(now),
+                on_break: false,
+            },
+        }
+    }
+}
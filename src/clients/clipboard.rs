@@ -1,7 +1,10 @@
-use super::wayland::{self, ClipboardItem};
+use super::wayland::{self, ClipboardItem, ClipboardValue, SelectionTarget};
+#[cfg(feature = "bar")]
+use crate::Ironbar;
 use crate::{arc_mut, lock, register_client, spawn, try_send};
 use indexmap::map::Iter;
 use indexmap::IndexMap;
+use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use tracing::{debug, trace};
@@ -11,17 +14,105 @@ pub enum ClipboardEvent {
     Add(ClipboardItem),
     Remove(usize),
     Activate(usize),
+    /// A new item was copied, but ignored by the subscriber's filter.
+    Deactivate,
 }
 
 type EventSender = mpsc::Sender<ClipboardEvent>;
 
+/// The mime type used by password managers
+/// to indicate the copied value is sensitive.
+const PASSWORD_MANAGER_HINT: &str = "x-kde-passwordManagerHint";
+
+#[cfg(feature = "bar")]
+const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
+
+/// Rules for which copied items a subscriber wants to receive.
+// only constructed by the clipboard module
+#[cfg_attr(not(feature = "bar"), allow(dead_code))]
+#[derive(Debug, Clone, Default)]
+pub struct ClipboardFilter {
+    /// Items larger than this number of bytes are ignored.
+    pub max_size: Option<usize>,
+    /// Items advertising any of these mime types are ignored.
+    pub ignored_mime_types: Vec<String>,
+    /// Whether to ignore items flagged as sensitive by password managers.
+    pub ignore_passwords: bool,
+    /// Whether to also receive items copied to the primary selection.
+    pub primary_selection: bool,
+}
+
+impl ClipboardFilter {
+    /// Checks whether the subscriber tracks the selection an item was copied to.
+    /// Items from untracked selections are not passed to the subscriber at all.
+    fn tracks(&self, item: &ClipboardItem) -> bool {
+        item.selection == SelectionTarget::Clipboard || self.primary_selection
+    }
+
+    /// Checks whether an item passes the filter.
+    fn accepts(&self, item: &ClipboardItem) -> bool {
+        if self
+            .max_size
+            .is_some_and(|max_size| item.value.size() > max_size)
+        {
+            return false;
+        }
+
+        !item.offered_mime_types.iter().any(|mime_type| {
+            (self.ignore_passwords && mime_type == PASSWORD_MANAGER_HINT)
+                || self
+                    .ignored_mime_types
+                    .iter()
+                    .any(|ignored| ignored.eq_ignore_ascii_case(mime_type))
+        })
+    }
+}
+
+/// A clipboard event subscriber.
+///
+/// Each subscriber keeps track of the cached items it holds,
+/// so it can evict its own oldest item once its cache size is reached.
+// only constructed by the clipboard module
+#[cfg_attr(not(feature = "bar"), allow(dead_code))]
+#[derive(Debug)]
+struct Subscriber {
+    tx: EventSender,
+    cache_size: usize,
+    filter: ClipboardFilter,
+    items: VecDeque<usize>,
+}
+
+impl Subscriber {
+    /// Checks whether the subscriber should receive an item.
+    fn wants(&self, item: &ClipboardItem) -> bool {
+        self.filter.tracks(item) && self.filter.accepts(item)
+    }
+
+    /// Records an item as held by the subscriber and sends it.
+    ///
+    /// If the subscriber's cache size is exceeded,
+    /// its oldest item is evicted from the shared cache.
+    fn push(&mut self, item: &ClipboardItem, cache: &mut ClipboardCache) {
+        self.items.push_back(item.id);
+
+        if self.items.len() > self.cache_size {
+            if let Some(removed_id) = self.items.pop_front() {
+                cache.remove_ref(removed_id);
+                try_send!(self.tx, ClipboardEvent::Remove(removed_id));
+            }
+        }
+
+        try_send!(self.tx, ClipboardEvent::Add(item.clone()));
+    }
+}
+
 /// Clipboard client singleton,
 /// to ensure bars don't duplicate requests to the compositor.
 #[derive(Debug)]
 pub struct Client {
     wayland: Arc<wayland::Client>,
 
-    senders: Arc<Mutex<Vec<(EventSender, usize)>>>,
+    senders: Arc<Mutex<Vec<Subscriber>>>,
     cache: Arc<Mutex<ClipboardCache>>,
 }
 
@@ -29,7 +120,7 @@ impl Client {
     pub(crate) fn new(wl: Arc<wayland::Client>) -> Self {
         trace!("Initializing clipboard client");
 
-        let senders = arc_mut!(Vec::<(EventSender, usize)>::new());
+        let senders = arc_mut!(Vec::<Subscriber>::new());
 
         let cache = arc_mut!(ClipboardCache::new());
 
@@ -43,47 +134,56 @@ impl Client {
                 let mut rx = wl.subscribe_clipboard();
 
                 if let Some(item) = item {
-                    let senders = lock!(senders);
-                    let iter = senders.iter();
-                    for (tx, _) in iter {
-                        try_send!(tx, ClipboardEvent::Add(item.clone()));
+                    let mut senders = lock!(senders);
+                    let mut accepted = senders
+                        .iter_mut()
+                        .filter(|sender| sender.wants(&item))
+                        .collect::<Vec<_>>();
+
+                    if !accepted.is_empty() {
+                        let mut cache = lock!(cache);
+                        cache.insert(item.clone(), accepted.len());
+
+                        for sender in &mut accepted {
+                            sender.push(&item, &mut cache);
+                        }
                     }
-
-                    lock!(cache).insert(item, senders.len());
                 }
 
                 while let Ok(item) = rx.recv().await {
                     debug!("Received clipboard item (ID: {})", item.id);
 
-                    let (existing_id, cache_size) = {
-                        let cache = lock!(cache);
-                        (cache.contains(&item), cache.len())
-                    };
+                    let existing_id = lock!(cache).contains(&item);
 
                     existing_id.map_or_else(
                         || {
-                            {
-                                let mut cache = lock!(cache);
-                                let senders = lock!(senders);
-                                cache.insert(item.clone(), senders.len());
+                            let mut senders = lock!(senders);
+                            let (mut accepted, rejected): (Vec<_>, Vec<_>) = senders
+                                .iter_mut()
+                                .filter(|sender| sender.filter.tracks(&item))
+                                .partition(|sender| sender.filter.accepts(&item));
+
+                            for sender in rejected {
+                                try_send!(sender.tx, ClipboardEvent::Deactivate);
                             }
-                            let senders = lock!(senders);
-                            let iter = senders.iter();
-                            for (tx, sender_cache_size) in iter {
-                                if cache_size == *sender_cache_size {
-                                    let removed_id = lock!(cache)
-                                        .remove_ref_first()
-                                        .expect("Clipboard cache unexpectedly empty");
-                                    try_send!(tx, ClipboardEvent::Remove(removed_id));
-                                }
-                                try_send!(tx, ClipboardEvent::Add(item.clone()));
+
+                            if accepted.is_empty() {
+                                debug!("Ignoring clipboard item (ID: {})", item.id);
+                                return;
+                            }
+
+                            let mut cache = lock!(cache);
+                            cache.insert(item.clone(), accepted.len());
+
+                            for sender in &mut accepted {
+                                sender.push(&item, &mut cache);
                             }
                         },
                         |existing_id| {
                             let senders = lock!(senders);
-                            let iter = senders.iter();
-                            for (tx, _) in iter {
-                                try_send!(tx, ClipboardEvent::Activate(existing_id));
+                            let iter = senders.iter().filter(|sender| sender.filter.tracks(&item));
+                            for sender in iter {
+                                try_send!(sender.tx, ClipboardEvent::Activate(existing_id));
                             }
                         },
                     );
@@ -98,24 +198,47 @@ impl Client {
         }
     }
 
-    pub fn subscribe(&self, cache_size: usize) -> mpsc::Receiver<ClipboardEvent> {
-        let (tx, rx) = mpsc::channel(16);
+    #[cfg(feature = "bar")]
+    pub fn subscribe(
+        &self,
+        cache_size: usize,
+        filter: ClipboardFilter,
+    ) -> mpsc::Receiver<ClipboardEvent> {
+        let (tx, rx) = mpsc::channel(16.max(cache_size));
+
+        let mut sender = Subscriber {
+            tx,
+            cache_size,
+            filter,
+            items: VecDeque::new(),
+        };
 
         {
-            let cache = lock!(self.cache);
-
-            let iter = cache.iter();
-            for (_, (item, _)) in iter {
-                try_send!(tx, ClipboardEvent::Add(item.clone()));
+            let mut cache = lock!(self.cache);
+
+            let items = cache
+                .iter()
+                .map(|(_, (item, _))| item)
+                .filter(|item| sender.wants(item))
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let skip = items.len().saturating_sub(cache_size);
+            for item in items.into_iter().skip(skip) {
+                cache.add_ref(item.id);
+                sender.push(&item, &mut cache);
             }
         }
 
-        lock!(self.senders).push((tx, cache_size));
+        lock!(self.senders).push(sender);
 
         rx
     }
 
-    pub fn copy(&self, id: usize) {
+    /// Copies the cached item with the given id to the clipboard,
+    /// and optionally mirrors it into the primary selection.
+    #[cfg(feature = "bar")]
+    pub fn copy(&self, id: usize, primary_selection: bool) {
         debug!("Copying item with id {id}");
 
         let item = {
@@ -124,23 +247,48 @@ impl Client {
         };
 
         if let Some(item) = item {
-            self.wayland.copy_to_clipboard(item);
+            if primary_selection {
+                self.wayland
+                    .copy_to_clipboard(item.clone(), SelectionTarget::Primary);
+            }
+
+            self.wayland
+                .copy_to_clipboard(item, SelectionTarget::Clipboard);
         }
 
         let senders = lock!(self.senders);
-        let iter = senders.iter();
-        for (tx, _) in iter {
-            try_send!(tx, ClipboardEvent::Activate(id));
+        for sender in senders.iter() {
+            try_send!(sender.tx, ClipboardEvent::Activate(id));
         }
     }
 
+    /// Copies a plain text value to the clipboard.
+    ///
+    /// Subscribers receive this as a new item once it is offered back by the compositor.
+    #[cfg(feature = "bar")]
+    pub fn copy_text(&self, text: String) {
+        debug!("Copying text to clipboard");
+
+        let item = ClipboardItem {
+            id: Ironbar::unique_id(),
+            value: Arc::new(ClipboardValue::Text(text)),
+            mime_type: Arc::from(TEXT_MIME_TYPE),
+            offered_mime_types: Arc::new([]),
+            selection: SelectionTarget::Clipboard,
+        };
+
+        self.wayland
+            .copy_to_clipboard(item, SelectionTarget::Clipboard);
+    }
+
+    #[cfg(feature = "bar")]
     pub fn remove(&self, id: usize) {
         lock!(self.cache).remove(id);
 
-        let senders = lock!(self.senders);
-        let iter = senders.iter();
-        for (tx, _) in iter {
-            try_send!(tx, ClipboardEvent::Remove(id));
+        let mut senders = lock!(self.senders);
+        for sender in senders.iter_mut() {
+            sender.items.retain(|&item_id| item_id != id);
+            try_send!(sender.tx, ClipboardEvent::Remove(id));
         }
     }
 }
@@ -164,6 +312,7 @@ impl ClipboardCache {
     }
 
     /// Gets the entry with key `id` from the cache.
+    #[cfg(feature = "bar")]
     fn get(&self, id: usize) -> Option<ClipboardItem> {
         self.cache.get(&id).map(|(item, _)| item).cloned()
     }
@@ -175,8 +324,17 @@ impl ClipboardCache {
             .map(|(item, _)| item)
     }
 
+    /// Adds a reference to the entry with key `id`.
+    #[cfg(feature = "bar")]
+    fn add_ref(&mut self, id: usize) {
+        if let Some(entry) = self.cache.get_mut(&id) {
+            entry.1 += 1;
+        }
+    }
+
     /// Removes the entry with key `id`.
     /// This ignores references.
+    #[cfg(feature = "bar")]
     fn remove(&mut self, id: usize) -> Option<ClipboardItem> {
         self.cache.shift_remove(&id).map(|(item, _)| item)
     }
@@ -195,20 +353,6 @@ impl ClipboardCache {
         }
     }
 
-    /// Removes a reference to the first entry.
-    ///
-    /// If the reference count reaches zero, the entry
-    /// is removed from the cache.
-    fn remove_ref_first(&mut self) -> Option<usize> {
-        if let Some((id, _)) = self.cache.first() {
-            let id = *id;
-            self.remove_ref(id);
-            Some(id)
-        } else {
-            None
-        }
-    }
-
     /// Checks if an item with matching mime type and value
     /// already exists in the cache.
     fn contains(&self, item: &ClipboardItem) -> Option<usize> {
@@ -221,14 +365,44 @@ impl ClipboardCache {
         })
     }
 
-    /// Gets the current number of items in the cache.
-    fn len(&self) -> usize {
-        self.cache.len()
-    }
-
     fn iter(&self) -> Iter<'_, usize, (ClipboardItem, usize)> {
         self.cache.iter()
     }
 }
 
 register_client!(Client, clipboard);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> color_eyre::Result<serde_json::Value> {
+        let cache = lock!(self.cache);
+
+        Ok(serde_json::json!({
+            "current": self.wayland.clipboard_item().as_ref().map(export_item),
+            "cached": cache.iter().map(|(_, (item, _))| export_item(item)).collect::<Vec<_>>(),
+        }))
+    }
+}
+
+/// Converts a clipboard item to JSON.
+/// Images and other binary values are only summarised by their size.
+#[cfg(feature = "ipc")]
+fn export_item(item: &ClipboardItem) -> serde_json::Value {
+    let text = match item.value.as_ref() {
+        ClipboardValue::Text(text) => Some(text.as_str()),
+        _ => None,
+    };
+
+    let selection = match item.selection {
+        SelectionTarget::Clipboard => "clipboard",
+        SelectionTarget::Primary => "primary",
+    };
+
+    serde_json::json!({
+        "id": item.id,
+        "mime_type": &*item.mime_type,
+        "selection": selection,
+        "size": item.value.size(),
+        "text": text,
+    })
+}
@@ -1,7 +1,8 @@
 use super::{Visibility, Workspace, WorkspaceClient, WorkspaceUpdate};
 use crate::{arc_mut, lock, send, spawn_blocking};
 use color_eyre::Result;
-use hyprland::data::{Workspace as HWorkspace, Workspaces};
+use hyprland::data::{Clients, Workspace as HWorkspace, Workspaces};
+#[cfg(feature = "bar")]
 use hyprland::dispatch::{Dispatch, DispatchType, WorkspaceIdentifierWithSpecial};
 use hyprland::event_listener::EventListener;
 use hyprland::prelude::*;
@@ -9,6 +10,13 @@ use hyprland::shared::{HyprDataVec, WorkspaceType};
 use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tracing::{debug, error, info};
 
+#[cfg(feature = "keyboard+hyprland")]
+use super::{KeyboardLayoutClient, KeyboardLayoutUpdate};
+#[cfg(all(feature = "bar", feature = "keyboard+hyprland"))]
+use hyprland::ctl::switch_xkb_layout::{self, SwitchXKBLayoutCmdTypes};
+#[cfg(feature = "keyboard+hyprland")]
+use hyprland::data::Devices;
+
 #[derive(Debug)]
 pub struct Client {
     workspace_tx: Sender<WorkspaceUpdate>,
@@ -165,6 +173,36 @@ impl Client {
                 });
             }
 
+            {
+                let tx = tx.clone();
+                let lock = lock.clone();
+
+                event_listener.add_urgent_state_handler(move |address| {
+                    let _lock = lock!(lock);
+                    debug!("Received urgent state: {address:?}");
+
+                    // Hyprland only sends the window address,
+                    // so look up which workspace it is on.
+                    // The urgent state is cleared by the consumer on focus.
+                    let workspace = Clients::get().ok().and_then(|clients| {
+                        clients
+                            .into_iter()
+                            .find(|client| client.address == address)
+                            .map(|client| client.workspace.id)
+                    });
+
+                    if let Some(id) = workspace {
+                        send!(
+                            tx,
+                            WorkspaceUpdate::Urgent {
+                                id: id as i64,
+                                urgent: true
+                            }
+                        );
+                    }
+                });
+            }
+
             {
                 event_listener.add_workspace_destroy_handler(move |data| {
                     let _lock = lock!(lock);
@@ -223,6 +261,7 @@ impl Client {
 }
 
 impl WorkspaceClient for Client {
+    #[cfg(feature = "bar")]
     fn focus(&self, id: String) -> Result<()> {
         let identifier = id.parse::<i32>().map_or_else(
             |_| WorkspaceIdentifierWithSpecial::Name(&id),
@@ -259,6 +298,90 @@ impl WorkspaceClient for Client {
     }
 }
 
+#[cfg(feature = "keyboard+hyprland")]
+#[derive(Debug)]
+pub struct KeyboardClient {
+    layout_tx: Sender<KeyboardLayoutUpdate>,
+    _layout_rx: Receiver<KeyboardLayoutUpdate>,
+}
+
+#[cfg(feature = "keyboard+hyprland")]
+impl KeyboardClient {
+    pub(crate) fn new() -> Self {
+        let (layout_tx, layout_rx) = channel(16);
+
+        let instance = Self {
+            layout_tx,
+            _layout_rx: layout_rx,
+        };
+
+        instance.listen_keyboard_events();
+        instance
+    }
+
+    fn listen_keyboard_events(&self) {
+        info!("Starting Hyprland keyboard event listener");
+
+        let tx = self.layout_tx.clone();
+
+        spawn_blocking(move || {
+            let mut event_listener = EventListener::new();
+
+            {
+                let tx = tx.clone();
+
+                event_listener.add_keyboard_layout_change_handler(move |event| {
+                    debug!("Received layout change: {event:?}");
+                    send!(tx, KeyboardLayoutUpdate::Layout(event.layout_name));
+                });
+            }
+
+            {
+                event_listener.add_active_window_change_handler(move |_| {
+                    if let Some(layout) = get_active_layout() {
+                        send!(tx, KeyboardLayoutUpdate::Focus(layout));
+                    }
+                });
+            }
+
+            event_listener
+                .start_listener()
+                .expect("Failed to start listener");
+        });
+    }
+}
+
+#[cfg(feature = "keyboard+hyprland")]
+impl KeyboardLayoutClient for KeyboardClient {
+    #[cfg(feature = "bar")]
+    fn set_next_active(&self) -> Result<()> {
+        // `current` targets the main keyboard
+        switch_xkb_layout::call("current", SwitchXKBLayoutCmdTypes::Next)?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Receiver<KeyboardLayoutUpdate> {
+        let rx = self.layout_tx.subscribe();
+
+        if let Some(layout) = get_active_layout() {
+            send!(self.layout_tx, KeyboardLayoutUpdate::Layout(layout));
+        }
+
+        rx
+    }
+}
+
+/// Gets the active layout of the main keyboard.
+#[cfg(feature = "keyboard+hyprland")]
+fn get_active_layout() -> Option<String> {
+    Devices::get()
+        .ok()?
+        .keyboards
+        .into_iter()
+        .find(|keyboard| keyboard.main)
+        .map(|keyboard| keyboard.active_keymap)
+}
+
 fn get_workspace_name(name: WorkspaceType) -> String {
     match name {
         WorkspaceType::Regular(name) => name,
@@ -69,6 +69,24 @@ impl Compositor {
                 .note("Currently workspaces are only supported by Sway and Hyprland")),
         }
     }
+
+    /// Creates a new instance of
+    /// the keyboard layout client for the current compositor.
+    #[cfg(feature = "keyboard")]
+    pub fn create_keyboard_layout_client() -> Result<Arc<dyn KeyboardLayoutClient + Send + Sync>> {
+        let current = Self::get_current();
+        debug!("Getting keyboard layout client for: {current}");
+        match current {
+            #[cfg(feature = "keyboard+sway")]
+            Self::Sway => await_sync(async { sway::KeyboardClient::new().await })
+                .map(|client| Arc::new(client) as Arc<dyn KeyboardLayoutClient + Send + Sync>),
+            #[cfg(feature = "keyboard+hyprland")]
+            Self::Hyprland => Ok(Arc::new(hyprland::KeyboardClient::new())),
+            #[allow(unreachable_patterns)]
+            _ => Err(Report::msg("Unsupported compositor")
+                .note("Currently keyboard layouts are only supported by Sway and Hyprland")),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -113,6 +131,8 @@ impl Visibility {
     }
 }
 
+// only `Init` is read outside of the workspaces module
+#[cfg_attr(not(feature = "bar"), allow(dead_code))]
 #[derive(Debug, Clone)]
 pub enum WorkspaceUpdate {
     /// Provides an initial list of workspaces.
@@ -132,6 +152,12 @@ pub enum WorkspaceUpdate {
         name: String,
     },
 
+    /// Declares a window on the workspace has started or stopped requesting attention.
+    Urgent {
+        id: i64,
+        urgent: bool,
+    },
+
     /// An update was triggered by the compositor but this was not mapped by Ironbar.
     ///
     /// This is purely used for ergonomics within the compositor clients
@@ -141,6 +167,7 @@ pub enum WorkspaceUpdate {
 
 pub trait WorkspaceClient: Debug + Send + Sync {
     /// Requests the workspace with this name is focused.
+    #[cfg(feature = "bar")]
     fn focus(&self, name: String) -> Result<()>;
 
     /// Creates a new to workspace event receiver.
@@ -148,3 +175,72 @@ pub trait WorkspaceClient: Debug + Send + Sync {
 }
 
 register_fallible_client!(dyn WorkspaceClient, workspaces);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for dyn WorkspaceClient {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        // the full workspace list is sent to each new subscriber
+        let mut rx = self.subscribe_workspace_change();
+        let workspaces = std::iter::from_fn(|| rx.try_recv().ok())
+            .find_map(|update| match update {
+                WorkspaceUpdate::Init(workspaces) => Some(workspaces),
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        Ok(workspaces
+            .into_iter()
+            .map(|workspace| {
+                serde_json::json!({
+                    "id": workspace.id,
+                    "name": workspace.name,
+                    "monitor": workspace.monitor,
+                    "visible": workspace.visibility.is_visible(),
+                    "focused": workspace.visibility.is_focused(),
+                })
+            })
+            .collect())
+    }
+}
+
+// only `Layout` is read outside of the keyboard module
+#[cfg(feature = "keyboard")]
+#[cfg_attr(not(feature = "bar"), allow(dead_code))]
+#[derive(Debug, Clone)]
+pub enum KeyboardLayoutUpdate {
+    /// Declares the active layout changed.
+    /// This is re-sent to all subscribers when a new subscription is created.
+    Layout(String),
+    /// Declares focus moved to another window,
+    /// providing the layout active once focused.
+    ///
+    /// This allows tracking compositors which switch layouts per window.
+    Focus(String),
+}
+
+#[cfg(feature = "keyboard")]
+pub trait KeyboardLayoutClient: Debug + Send + Sync {
+    /// Switches to the next layout configured for the keyboard.
+    #[cfg(feature = "bar")]
+    fn set_next_active(&self) -> Result<()>;
+
+    /// Creates a new keyboard layout event receiver.
+    fn subscribe(&self) -> broadcast::Receiver<KeyboardLayoutUpdate>;
+}
+
+#[cfg(feature = "keyboard")]
+register_fallible_client!(dyn KeyboardLayoutClient, keyboard_layout);
+
+#[cfg(all(feature = "keyboard", feature = "ipc"))]
+impl super::ExportState for dyn KeyboardLayoutClient {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        // the active layout is sent to each new subscriber
+        let mut rx = self.subscribe();
+        let layout = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|update| match update {
+            KeyboardLayoutUpdate::Layout(layout) => Some(layout),
+            KeyboardLayoutUpdate::Focus(_) => None,
+        });
+
+        Ok(serde_json::json!({ "layout": layout }))
+    }
+}
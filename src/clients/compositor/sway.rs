@@ -8,6 +8,11 @@ use tokio::sync::broadcast::{channel, Receiver, Sender};
 use tokio::sync::Mutex;
 use tracing::{info, trace};
 
+#[cfg(feature = "keyboard+sway")]
+use super::{KeyboardLayoutClient, KeyboardLayoutUpdate};
+#[cfg(feature = "keyboard+sway")]
+use swayipc_async::{InputChange, WindowChange};
+
 #[derive(Debug)]
 pub struct Client {
     client: Arc<Mutex<Connection>>,
@@ -55,6 +60,7 @@ impl Client {
 }
 
 impl WorkspaceClient for Client {
+    #[cfg(feature = "bar")]
     fn focus(&self, id: String) -> Result<()> {
         await_sync(async move {
             let mut client = self.client.lock().await;
@@ -85,6 +91,114 @@ impl WorkspaceClient for Client {
     }
 }
 
+#[cfg(feature = "keyboard+sway")]
+#[derive(Debug)]
+pub struct KeyboardClient {
+    client: Arc<Mutex<Connection>>,
+    layout_tx: Sender<KeyboardLayoutUpdate>,
+    _layout_rx: Receiver<KeyboardLayoutUpdate>,
+}
+
+#[cfg(feature = "keyboard+sway")]
+impl KeyboardClient {
+    pub(crate) async fn new() -> Result<Self> {
+        let client = Arc::new(Mutex::new(Connection::new().await?));
+        info!("Sway IPC keyboard client connected");
+
+        let (layout_tx, layout_rx) = channel(16);
+
+        {
+            // create 2nd client as subscription takes ownership
+            let subscription = Connection::new().await?;
+            let client = client.clone();
+            let layout_tx = layout_tx.clone();
+
+            spawn(async move {
+                let event_types = [EventType::Input, EventType::Window];
+                let mut events = subscription.subscribe(event_types).await?;
+
+                while let Some(event) = events.next().await {
+                    trace!("event: {:?}", event);
+                    match event? {
+                        Event::Input(event)
+                            if matches!(
+                                event.change,
+                                InputChange::XkbLayout | InputChange::XkbKeymap
+                            ) =>
+                        {
+                            if let Some(layout) = event.input.xkb_active_layout_name {
+                                layout_tx.send(KeyboardLayoutUpdate::Layout(layout))?;
+                            }
+                        }
+                        Event::Window(event) if matches!(event.change, WindowChange::Focus) => {
+                            let mut client = client.lock().await;
+                            if let Some(layout) = get_active_layout(&mut client).await? {
+                                layout_tx.send(KeyboardLayoutUpdate::Focus(layout))?;
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                Ok::<(), Report>(())
+            });
+        }
+
+        Ok(Self {
+            client,
+            layout_tx,
+            _layout_rx: layout_rx,
+        })
+    }
+}
+
+#[cfg(feature = "keyboard+sway")]
+impl KeyboardLayoutClient for KeyboardClient {
+    #[cfg(feature = "bar")]
+    fn set_next_active(&self) -> Result<()> {
+        await_sync(async move {
+            let mut client = self.client.lock().await;
+            client
+                .run_command("input type:keyboard xkb_switch_layout next")
+                .await
+        })?;
+        Ok(())
+    }
+
+    fn subscribe(&self) -> Receiver<KeyboardLayoutUpdate> {
+        let rx = self.layout_tx.subscribe();
+
+        {
+            let tx = self.layout_tx.clone();
+            let client = self.client.clone();
+
+            await_sync(async {
+                let mut client = client.lock().await;
+                match get_active_layout(&mut client).await {
+                    Ok(Some(layout)) => send!(tx, KeyboardLayoutUpdate::Layout(layout)),
+                    Ok(None) => {}
+                    Err(err) => tracing::error!("Failed to get keyboard layout: {err:?}"),
+                }
+            });
+        }
+
+        rx
+    }
+}
+
+/// Gets the active layout of the first keyboard which reports one.
+#[cfg(feature = "keyboard+sway")]
+async fn get_active_layout(client: &mut Connection) -> Result<Option<String>> {
+    let layout = client
+        .get_inputs()
+        .await?
+        .into_iter()
+        .filter(|input| input.input_type == "keyboard")
+        .find_map(|input| input.xkb_active_layout_name);
+
+    Ok(layout)
+}
+
 impl From<Node> for Workspace {
     fn from(node: Node) -> Self {
         let visibility = Visibility::from(&node);
@@ -151,6 +265,13 @@ impl From<WorkspaceEvent> for WorkspaceUpdate {
             WorkspaceChange::Move => {
                 Self::Move(event.current.expect("Missing current workspace").into())
             }
+            WorkspaceChange::Urgent => {
+                let workspace = event.current.expect("Missing current workspace");
+                Self::Urgent {
+                    id: workspace.id,
+                    urgent: workspace.urgent,
+                }
+            }
             _ => Self::Unknown,
         }
     }
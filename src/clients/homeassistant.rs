@@ -0,0 +1,262 @@
+use crate::{arc_mut, lock, send, spawn};
+use color_eyre::{Report, Result};
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, error, warn};
+
+/// Time to wait before attempting to reconnect
+/// after the websocket connection is lost.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The state of a single Home Assistant entity,
+/// as returned by the websocket API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntityState {
+    pub entity_id: String,
+    pub state: String,
+    #[serde(default)]
+    pub attributes: Map<String, Value>,
+}
+
+impl EntityState {
+    /// Gets the entity's friendly name,
+    /// falling back to its ID if one is not set.
+    pub fn friendly_name(&self) -> &str {
+        self.attributes
+            .get("friendly_name")
+            .and_then(Value::as_str)
+            .unwrap_or(&self.entity_id)
+    }
+
+    /// Gets an attribute value as a string.
+    /// Non-string values are serialized as JSON.
+    pub fn attribute(&self, key: &str) -> Option<String> {
+        match self.attributes.get(key)? {
+            Value::Null => None,
+            Value::String(value) => Some(value.clone()),
+            value => Some(value.to_string()),
+        }
+    }
+}
+
+/// A request to call a service on a single entity.
+#[derive(Debug, Clone)]
+pub struct ServiceCall {
+    pub domain: String,
+    pub service: String,
+    pub entity_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum IncomingMessage {
+    AuthRequired,
+    AuthOk,
+    AuthInvalid {
+        message: String,
+    },
+    Result {
+        id: u64,
+        success: bool,
+        #[serde(default)]
+        result: Value,
+    },
+    Event {
+        event: StateChangedEvent,
+    },
+    #[serde(other)]
+    Unknown,
+}
+
+#[derive(Debug, Deserialize)]
+struct StateChangedEvent {
+    data: StateChangedData,
+}
+
+#[derive(Debug, Deserialize)]
+struct StateChangedData {
+    new_state: Option<EntityState>,
+}
+
+#[derive(Debug)]
+pub struct Client {
+    states: Arc<Mutex<HashMap<String, EntityState>>>,
+    tx: broadcast::Sender<EntityState>,
+    _rx: broadcast::Receiver<EntityState>,
+    service_tx: mpsc::Sender<ServiceCall>,
+}
+
+impl Client {
+    pub fn new(url: &str, token: String) -> Self {
+        let url = websocket_url(url);
+
+        let states = arc_mut!(HashMap::new());
+        let (tx, rx) = broadcast::channel(64);
+        let (service_tx, mut service_rx) = mpsc::channel(16);
+
+        {
+            let states = states.clone();
+            let tx = tx.clone();
+
+            spawn(async move {
+                loop {
+                    if let Err(err) = Self::run(&url, &token, &states, &tx, &mut service_rx).await {
+                        error!("{err:?}");
+                    }
+
+                    warn!(
+                        "Lost connection to Home Assistant, reconnecting in {}s",
+                        RECONNECT_INTERVAL.as_secs()
+                    );
+                    sleep(RECONNECT_INTERVAL).await;
+                }
+            });
+        }
+
+        Self {
+            states,
+            tx,
+            _rx: rx,
+            service_tx,
+        }
+    }
+
+    /// Connects to the websocket API, authenticates,
+    /// then processes state updates and service calls until the connection drops.
+    async fn run(
+        url: &str,
+        token: &str,
+        states: &Mutex<HashMap<String, EntityState>>,
+        tx: &broadcast::Sender<EntityState>,
+        service_rx: &mut mpsc::Receiver<ServiceCall>,
+    ) -> Result<()> {
+        let (stream, _) = connect_async(url).await?;
+        let (mut sink, mut stream) = stream.split();
+
+        debug!("Connected to Home Assistant at {url}");
+
+        let mut id = 0;
+        let mut get_states_id = None;
+
+        macro_rules! send_json {
+            ($value:expr) => {
+                sink.send(Message::Text($value.to_string())).await?
+            };
+        }
+
+        loop {
+            tokio::select! {
+                msg = stream.next() => {
+                    let text = match msg {
+                        Some(Ok(Message::Text(text))) => text,
+                        Some(Ok(Message::Close(_))) | None => return Ok(()),
+                        Some(Ok(_)) => continue,
+                        Some(Err(err)) => return Err(Report::new(err)),
+                    };
+
+                    match serde_json::from_str::<IncomingMessage>(&text)? {
+                        IncomingMessage::AuthRequired => {
+                            send_json!(json!({ "type": "auth", "access_token": token }));
+                        }
+                        IncomingMessage::AuthOk => {
+                            debug!("Authenticated with Home Assistant");
+
+                            id += 1;
+                            get_states_id = Some(id);
+                            send_json!(json!({ "id": id, "type": "get_states" }));
+
+                            id += 1;
+                            send_json!(json!({
+                                "id": id,
+                                "type": "subscribe_events",
+                                "event_type": "state_changed"
+                            }));
+                        }
+                        IncomingMessage::AuthInvalid { message } => {
+                            return Err(Report::msg(format!(
+                                "Home Assistant authentication failed: {message}"
+                            )));
+                        }
+                        IncomingMessage::Result { id: request_id, success, result } => {
+                            if !success {
+                                warn!("Home Assistant request #{request_id} failed: {result}");
+                            } else if get_states_id == Some(request_id) {
+                                let entities: Vec<EntityState> = serde_json::from_value(result)?;
+                                for entity in entities {
+                                    lock!(states).insert(entity.entity_id.clone(), entity.clone());
+                                    send!(tx, entity);
+                                }
+                            }
+                        }
+                        IncomingMessage::Event { event } => {
+                            if let Some(entity) = event.data.new_state {
+                                lock!(states).insert(entity.entity_id.clone(), entity.clone());
+                                send!(tx, entity);
+                            }
+                        }
+                        IncomingMessage::Unknown => {}
+                    }
+                }
+                Some(call) = service_rx.recv() => {
+                    debug!("Calling service {}.{} on {}", call.domain, call.service, call.entity_id);
+
+                    id += 1;
+                    send_json!(json!({
+                        "id": id,
+                        "type": "call_service",
+                        "domain": call.domain,
+                        "service": call.service,
+                        "target": { "entity_id": call.entity_id }
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Gets the last known state of an entity, if it has been received.
+    pub fn state(&self, entity_id: &str) -> Option<EntityState> {
+        lock!(self.states).get(entity_id).cloned()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<EntityState> {
+        self.tx.subscribe()
+    }
+
+    /// Queues a service call to be sent over the websocket.
+    pub async fn call_service(&self, call: ServiceCall) {
+        if let Err(err) = self.service_tx.send(call).await {
+            error!("{err:?}");
+        }
+    }
+}
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(&*lock!(self.states))?)
+    }
+}
+
+/// Converts the instance base URL
+/// into the address of its websocket API endpoint.
+fn websocket_url(url: &str) -> String {
+    let url = url.trim_end_matches('/');
+
+    let url = if let Some(host) = url.strip_prefix("https://") {
+        format!("wss://{host}")
+    } else if let Some(host) = url.strip_prefix("http://") {
+        format!("ws://{host}")
+    } else {
+        url.to_string()
+    };
+
+    format!("{url}/api/websocket")
+}
@@ -0,0 +1,574 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use cfg_if::cfg_if;
+use color_eyre::Result;
+use futures_lite::StreamExt;
+use futures_signals::signal::Mutable;
+use tracing::error;
+use zbus::dbus_proxy;
+use zbus::fdo::{ManagedObjects, ObjectManagerProxy};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue};
+use zbus::{Connection, MatchRule, MessageStream, MessageType};
+
+use crate::clients::networkmanager::filter::InterfaceFilter;
+use crate::clients::networkmanager::speedtest::SpeedTest;
+use crate::clients::networkmanager::state::{
+    CellularState, ConnectionPhase, Connectivity, InterfaceKind, InterfaceState, InterfaceStatus,
+    State, VpnState, WifiConnectedState, WifiConnectingState, WifiNetwork, WifiSecurity, WifiState,
+    WiredState,
+};
+use crate::clients::networkmanager::statistics::Throughput;
+use crate::{register_fallible_client, spawn};
+
+cfg_if! {
+    if #[cfg(feature = "bar")] {
+        use color_eyre::Report;
+        use futures_signals::signal::MutableSignalCloned;
+        use tracing::debug;
+
+        use crate::clients::networkmanager::speedtest::run_speed_test;
+    }
+}
+
+const IWD: &str = "net.connman.iwd";
+const IWD_PATH: &str = "/net/connman/iwd";
+const DEVICE_INTERFACE: &str = "net.connman.iwd.Device";
+const STATION_INTERFACE: &str = "net.connman.iwd.Station";
+const NETWORK_INTERFACE: &str = "net.connman.iwd.Network";
+const BSS_INTERFACE: &str = "net.connman.iwd.BasicServiceSet";
+
+#[dbus_proxy(
+    default_service = "net.connman.iwd",
+    interface = "net.connman.iwd.Device"
+)]
+trait DeviceDbus {
+    #[dbus_proxy(property)]
+    fn powered(&self) -> Result<bool>;
+
+    #[dbus_proxy(property)]
+    fn set_powered(&self, value: bool) -> Result<()>;
+}
+
+#[dbus_proxy(
+    default_service = "net.connman.iwd",
+    interface = "net.connman.iwd.Station"
+)]
+trait StationDbus {
+    /// Gets the networks found by the last scan, strongest first,
+    /// with their signal strength in 100 * dBm.
+    fn get_ordered_networks(&self) -> Result<Vec<(OwnedObjectPath, i16)>>;
+}
+
+#[dbus_proxy(
+    default_service = "net.connman.iwd",
+    interface = "net.connman.iwd.Network"
+)]
+trait NetworkDbus {
+    fn connect(&self) -> Result<()>;
+}
+
+/// Tracks wifi devices using iwd,
+/// for systems which run it without NetworkManager.
+///
+/// iwd only manages wifi, so the other connection types are left unknown.
+#[derive(Debug)]
+pub struct Client(Arc<ClientInner>);
+
+#[derive(Debug)]
+struct ClientInner {
+    state: Mutable<State>,
+    dbus_connection: Connection,
+    object_manager: ObjectManagerProxy<'static>,
+    /// Devices with interfaces excluded by the filter are left out of the state.
+    filter: InterfaceFilter,
+}
+
+/// A wifi device, read from iwd's objects.
+#[derive(Debug)]
+struct Device {
+    #[cfg(feature = "bar")]
+    path: OwnedObjectPath,
+    /// The name of the device's network interface, such as `wlan0`.
+    interface: String,
+    powered: bool,
+    /// Only set while the device is powered and in station mode.
+    station: Option<Station>,
+    /// The networks found by the last scan,
+    /// with their signal strength in 100 * dBm.
+    networks: Vec<(OwnedObjectPath, i16)>,
+}
+
+#[derive(Debug)]
+struct Station {
+    /// One of `connected`, `disconnected`, `connecting`, `disconnecting` or `roaming`.
+    state: String,
+    connected_network: Option<OwnedObjectPath>,
+    /// Only available in newer versions of iwd.
+    connected_access_point: Option<OwnedObjectPath>,
+}
+
+impl Device {
+    fn status(&self) -> InterfaceStatus {
+        match &self.station {
+            Some(station) if self.powered => match station.state.as_str() {
+                // roaming keeps the connection while moving to another access point
+                "connected" | "roaming" => InterfaceStatus::Connected,
+                "connecting" => InterfaceStatus::Connecting,
+                _ => InterfaceStatus::Disconnected,
+            },
+            _ => InterfaceStatus::Unavailable,
+        }
+    }
+
+    /// Gets the signal strength of a network as a percentage,
+    /// or `0` if it was not found by the last scan.
+    fn strength(&self, network: &OwnedObjectPath) -> u8 {
+        self.networks
+            .iter()
+            .find(|(path, _)| path == network)
+            .map_or(0, |(_, signal)| signal_percent(*signal))
+    }
+}
+
+impl Client {
+    async fn new(filter: InterfaceFilter) -> Result<Self> {
+        let dbus_connection = Box::pin(Connection::system()).await?;
+
+        let object_manager = ObjectManagerProxy::builder(&dbus_connection)
+            .destination(IWD)?
+            .path("/")?
+            .build()
+            .await?;
+
+        let state = Mutable::new(State {
+            wired: WiredState::Unknown,
+            wifi: WifiState::Unknown,
+            cellular: CellularState::Unknown,
+            vpn: VpnState::Unknown,
+            interfaces: vec![],
+            vpn_connections: vec![],
+            wifi_networks: vec![],
+            data_usage: vec![],
+            throughput: Throughput::default(),
+            connectivity: Connectivity::Unknown,
+            dns: None,
+            speed_test: SpeedTest::default(),
+            airplane_mode: None,
+            wifi_station: None,
+            hotspot: None,
+        });
+
+        Ok(Self(Arc::new(ClientInner {
+            state,
+            dbus_connection,
+            object_manager,
+            filter,
+        })))
+    }
+
+    async fn run(&self) -> Result<()> {
+        let client = &self.0;
+
+        client.update_state().await?;
+
+        let mut added = client.object_manager.receive_interfaces_added().await?;
+        let mut removed = client.object_manager.receive_interfaces_removed().await?;
+
+        // the object manager does not report property changes,
+        // such as a station connecting or finishing a scan
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace(IWD_PATH)?
+            .build();
+        let mut changed =
+            MessageStream::for_match_rule(rule, &client.dbus_connection, None).await?;
+
+        loop {
+            tokio::select! {
+                Some(_) = added.next() => {}
+                Some(_) = removed.next() => {}
+                Some(_) = changed.next() => {}
+                else => break,
+            }
+
+            // devices, stations and networks are separate objects,
+            // so it is simplest to re-read them all on any change
+            if let Err(err) = client.update_state().await {
+                error!("{err:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bar")]
+    pub fn subscribe(&self) -> MutableSignalCloned<State> {
+        self.0.state.signal_cloned()
+    }
+
+    /// Connects to the wifi network with the given SSID,
+    /// using the device which sees it strongest.
+    ///
+    /// Known networks connect using their saved credentials.
+    /// Otherwise, iwd asks the registered agent, such as `iwctl` or `iwgtk`, for any passphrase.
+    #[cfg(feature = "bar")]
+    pub async fn connect_wifi(&self, ssid: &str) -> Result<()> {
+        let objects = self.0.object_manager.get_managed_objects().await?;
+        let devices = self.0.read_devices(&objects).await?;
+
+        let path = devices
+            .iter()
+            .filter(|device| device.powered)
+            .flat_map(|device| &device.networks)
+            .filter(|(path, _)| network_name(&objects, path) == Some(ssid))
+            .max_by_key(|(_, signal)| *signal)
+            .map(|(path, _)| path.clone())
+            .ok_or_else(|| Report::msg(format!("No network found for '{ssid}'")))?;
+
+        debug!("Connecting to '{ssid}'");
+
+        NetworkDbusProxy::builder(&self.0.dbus_connection)
+            .path(path)?
+            .build()
+            .await?
+            .connect()
+            .await?;
+
+        Ok(())
+    }
+
+    /// Powers every wifi device on or off.
+    #[cfg(feature = "bar")]
+    pub async fn set_wifi_enabled(&self, enabled: bool) -> Result<()> {
+        debug!("Setting wifi enabled: {enabled}");
+
+        for device in self.0.device_proxies().await? {
+            device.set_powered(enabled).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Powers the wifi devices off if any are on, or on if all are off.
+    #[cfg(feature = "bar")]
+    pub async fn toggle_wifi(&self) -> Result<()> {
+        let mut enabled = false;
+        for device in self.0.device_proxies().await? {
+            enabled |= device.powered().await?;
+        }
+
+        self.set_wifi_enabled(!enabled).await
+    }
+
+    /// Runs a speed test using the given shell command,
+    /// keeping the result in the state.
+    ///
+    /// This resolves once the test completes.
+    /// Only one test runs at a time, so this does nothing if a test is already running.
+    #[cfg(feature = "bar")]
+    pub async fn run_speed_test(&self, command: &str) -> Result<()> {
+        {
+            let mut state = self.0.state.lock_mut();
+            if state.speed_test.running {
+                return Ok(());
+            }
+            state.speed_test.running = true;
+        }
+
+        let result = run_speed_test(command).await;
+
+        let mut state = self.0.state.lock_mut();
+        state.speed_test.running = false;
+
+        state.speed_test.last_result = Some(result?);
+
+        Ok(())
+    }
+}
+
+impl ClientInner {
+    /// Updates the wifi parts of the state.
+    async fn update_state(&self) -> Result<()> {
+        let objects = self.object_manager.get_managed_objects().await?;
+        let devices = self.read_devices(&objects).await?;
+
+        let wifi = determine_wifi_state(&devices, &objects);
+        let interfaces = determine_interfaces(&devices);
+        let wifi_networks = determine_wifi_networks(&devices, &objects);
+
+        let mut state = self.state.lock_mut();
+        state.wifi = wifi;
+        state.interfaces = interfaces;
+        state.wifi_networks = wifi_networks;
+
+        Ok(())
+    }
+
+    /// Reads the wifi devices which are not excluded by the filter,
+    /// ordered by interface name.
+    async fn read_devices(&self, objects: &ManagedObjects) -> Result<Vec<Device>> {
+        let mut devices = vec![];
+
+        for (path, interfaces) in objects {
+            let Some(device) = interfaces.get(DEVICE_INTERFACE) else {
+                continue;
+            };
+
+            let Some(interface) = get_str(device, "Name") else {
+                continue;
+            };
+
+            if self.filter.is_ignored(interface) {
+                continue;
+            }
+
+            let station = interfaces.get(STATION_INTERFACE).map(|station| Station {
+                state: get_str(station, "State").unwrap_or_default().to_string(),
+                connected_network: get_path(station, "ConnectedNetwork"),
+                connected_access_point: get_path(station, "ConnectedAccessPoint"),
+            });
+
+            // signal strengths are only available by calling the station
+            let networks = if station.is_some() {
+                StationDbusProxy::builder(&self.dbus_connection)
+                    .path(path.clone())?
+                    .build()
+                    .await?
+                    .get_ordered_networks()
+                    .await?
+            } else {
+                vec![]
+            };
+
+            devices.push(Device {
+                #[cfg(feature = "bar")]
+                path: path.clone(),
+                interface: interface.to_string(),
+                powered: get_bool(device, "Powered"),
+                station,
+                networks,
+            });
+        }
+
+        devices.sort_by(|a, b| a.interface.cmp(&b.interface));
+        Ok(devices)
+    }
+
+    /// Gets proxies for the wifi devices which are not excluded by the filter.
+    #[cfg(feature = "bar")]
+    async fn device_proxies(&self) -> Result<Vec<DeviceDbusProxy<'static>>> {
+        let objects = self.object_manager.get_managed_objects().await?;
+
+        let mut proxies = vec![];
+        for device in self.read_devices(&objects).await? {
+            let proxy = DeviceDbusProxy::builder(&self.dbus_connection)
+                .path(device.path)?
+                .build()
+                .await?;
+
+            proxies.push(proxy);
+        }
+
+        Ok(proxies)
+    }
+}
+
+fn determine_wifi_state(devices: &[Device], objects: &ManagedObjects) -> WifiState {
+    let mut enabled = false;
+    let mut connecting = None;
+
+    for device in devices {
+        let Some(station) = device.station.as_ref().filter(|_| device.powered) else {
+            continue;
+        };
+
+        enabled = true;
+
+        let network = station
+            .connected_network
+            .as_ref()
+            .and_then(|path| read_network(objects, device, path));
+
+        match (device.status(), network) {
+            (InterfaceStatus::Connected, Some(network)) => {
+                let bssid = station
+                    .connected_access_point
+                    .as_ref()
+                    .and_then(|path| objects.get(path)?.get(BSS_INTERFACE))
+                    .and_then(|access_point| get_str(access_point, "Address"))
+                    .map(ToString::to_string);
+
+                return WifiState::Connected(WifiConnectedState {
+                    ssid: network.ssid,
+                    bssid,
+                    strength: network.strength,
+                    security: network.security,
+                    eap: None,
+                    ip4: None,
+                    ip6: None,
+                });
+            }
+            (InterfaceStatus::Connecting, network) => {
+                connecting = Some(WifiConnectingState {
+                    connection: network.map_or_else(|| "unknown".into(), |network| network.ssid),
+                    phase: ConnectionPhase::Authenticating,
+                    eap: None,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(connecting) = connecting {
+        WifiState::Connecting(connecting)
+    } else if enabled {
+        WifiState::Disconnected
+    } else if devices.is_empty() {
+        WifiState::NotPresent
+    } else {
+        WifiState::Disabled
+    }
+}
+
+fn determine_interfaces(devices: &[Device]) -> Vec<InterfaceState> {
+    devices
+        .iter()
+        .map(|device| InterfaceState {
+            name: device.interface.clone(),
+            kind: InterfaceKind::Wifi,
+            status: device.status(),
+            connection: None,
+        })
+        .collect()
+}
+
+fn determine_wifi_networks(devices: &[Device], objects: &ManagedObjects) -> Vec<WifiNetwork> {
+    let mut networks: Vec<WifiNetwork> = vec![];
+
+    for device in devices {
+        for (path, _) in &device.networks {
+            let Some(network) = read_network(objects, device, path) else {
+                continue;
+            };
+
+            // the same network may be seen by several devices,
+            // so only keep the strongest one.
+            match networks.iter_mut().find(|n| n.ssid == network.ssid) {
+                Some(existing) if existing.strength < network.strength => *existing = network,
+                Some(_) => {}
+                None => networks.push(network),
+            }
+        }
+    }
+
+    networks.sort_by(|a, b| b.strength.cmp(&a.strength));
+    networks
+}
+
+/// Reads a network seen by the device.
+fn read_network(
+    objects: &ManagedObjects,
+    device: &Device,
+    path: &OwnedObjectPath,
+) -> Option<WifiNetwork> {
+    let network = objects.get(path)?.get(NETWORK_INTERFACE)?;
+
+    Some(WifiNetwork {
+        ssid: get_str(network, "Name")?.to_string(),
+        strength: device.strength(path),
+        security: get_str(network, "Type").map_or(WifiSecurity::Unknown, security_from_type),
+    })
+}
+
+#[cfg(feature = "bar")]
+fn network_name<'a>(objects: &'a ManagedObjects, path: &OwnedObjectPath) -> Option<&'a str> {
+    get_str(objects.get(path)?.get(NETWORK_INTERFACE)?, "Name")
+}
+
+/// Converts a signal strength in 100 * dBm, as reported by iwd, to a percentage.
+/// This treats -100 dBm as 0%, and -50 dBm or stronger as 100%.
+fn signal_percent(signal: i16) -> u8 {
+    let dbm = i32::from(signal) / 100;
+    (2 * (dbm + 100)).clamp(0, 100) as u8
+}
+
+/// Gets the security method from a network's `Type` property.
+///
+/// iwd reports WPA2 and WPA3 personal networks both as `psk`,
+/// so these are shown as WPA2.
+fn security_from_type(kind: &str) -> WifiSecurity {
+    match kind {
+        "open" => WifiSecurity::Open,
+        "wep" => WifiSecurity::Wep,
+        "psk" => WifiSecurity::Wpa2,
+        "8021x" => WifiSecurity::Enterprise,
+        _ => WifiSecurity::Unknown,
+    }
+}
+
+fn get_str<'a>(properties: &'a HashMap<String, OwnedValue>, key: &str) -> Option<&'a str> {
+    properties
+        .get(key)
+        .and_then(|value| <&str>::try_from(value).ok())
+}
+
+fn get_bool(properties: &HashMap<String, OwnedValue>, key: &str) -> bool {
+    properties
+        .get(key)
+        .and_then(|value| bool::try_from(value).ok())
+        .unwrap_or_default()
+}
+
+fn get_path(properties: &HashMap<String, OwnedValue>, key: &str) -> Option<OwnedObjectPath> {
+    properties
+        .get(key)
+        .and_then(|value| <&ObjectPath>::try_from(value).ok())
+        .map(|path| path.to_owned().into())
+}
+
+pub async fn create_client(filter: InterfaceFilter) -> Result<Arc<Client>> {
+    let client = Arc::new(Client::new(filter).await?);
+    {
+        let client = client.clone();
+        spawn(async move {
+            if let Err(err) = client.run().await {
+                error!("{:?}", err.wrap_err("Failed to start iwd client"));
+            }
+        });
+    }
+    Ok(client)
+}
+
+register_fallible_client!(Client, iwd);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(&*self.0.state.lock_ref())?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signal_percent() {
+        assert_eq!(signal_percent(-4000), 100);
+        assert_eq!(signal_percent(-5000), 100);
+        assert_eq!(signal_percent(-6700), 66);
+        assert_eq!(signal_percent(-10000), 0);
+        assert_eq!(signal_percent(-11000), 0);
+    }
+
+    #[test]
+    fn test_security_from_type() {
+        assert_eq!(security_from_type("open"), WifiSecurity::Open);
+        assert_eq!(security_from_type("psk"), WifiSecurity::Wpa2);
+        assert_eq!(security_from_type("8021x"), WifiSecurity::Enterprise);
+        assert_eq!(security_from_type("other"), WifiSecurity::Unknown);
+    }
+}
@@ -1,15 +1,27 @@
 use crate::{await_sync, Ironbar};
 use color_eyre::Result;
+#[cfg(all(feature = "bar", feature = "cairo"))]
 use std::path::Path;
+#[cfg(all(feature = "bar", feature = "cairo"))]
 use std::rc::Rc;
 use std::sync::Arc;
 
+#[cfg(feature = "bluetooth_battery")]
+pub mod bluetooth;
+#[cfg(all(feature = "bar", feature = "break_reminder"))]
+pub mod break_reminder;
 #[cfg(feature = "clipboard")]
 pub mod clipboard;
 #[cfg(feature = "workspaces")]
 pub mod compositor;
-#[cfg(feature = "cairo")]
+#[cfg(all(feature = "bar", feature = "homeassistant"))]
+pub mod homeassistant;
+#[cfg(feature = "networkmanager")]
+pub mod iwd;
+#[cfg(all(feature = "bar", feature = "cairo"))]
 pub mod lua;
+#[cfg(all(feature = "bar", feature = "mqtt"))]
+pub mod mqtt;
 #[cfg(feature = "music")]
 pub mod music;
 #[cfg(feature = "networkmanager")]
@@ -18,9 +30,11 @@ pub mod networkmanager;
 pub mod swaync;
 #[cfg(feature = "tray")]
 pub mod tray;
+#[cfg(any(feature = "disk_health", feature = "nvme_health", feature = "usb"))]
+pub mod udisks;
 #[cfg(feature = "upower")]
 pub mod upower;
-#[cfg(feature = "volume")]
+#[cfg(any(feature = "mic", feature = "volume"))]
 pub mod volume;
 pub mod wayland;
 
@@ -29,23 +43,47 @@ pub mod wayland;
 #[derive(Debug, Default)]
 pub struct Clients {
     wayland: Option<Arc<wayland::Client>>,
+    #[cfg(feature = "bluetooth_battery")]
+    bluetooth: Option<Arc<bluetooth::Client>>,
+    #[cfg(all(feature = "bar", feature = "break_reminder"))]
+    break_reminder:
+        std::collections::HashMap<break_reminder::TimerConfig, Arc<break_reminder::Client>>,
     #[cfg(feature = "workspaces")]
     workspaces: Option<Arc<dyn compositor::WorkspaceClient>>,
     #[cfg(feature = "clipboard")]
     clipboard: Option<Arc<clipboard::Client>>,
-    #[cfg(feature = "cairo")]
+    #[cfg(all(feature = "bar", feature = "homeassistant"))]
+    homeassistant: std::collections::HashMap<(Box<str>, Box<str>), Arc<homeassistant::Client>>,
+    #[cfg(feature = "networkmanager")]
+    iwd: std::collections::HashMap<networkmanager::filter::InterfaceFilter, Arc<iwd::Client>>,
+    #[cfg(feature = "keyboard")]
+    keyboard_layout: Option<Arc<dyn compositor::KeyboardLayoutClient>>,
+    #[cfg(all(feature = "bar", feature = "cairo"))]
     lua: Option<Rc<lua::LuaEngine>>,
+    #[cfg(all(feature = "bar", feature = "mqtt"))]
+    mqtt: std::collections::HashMap<(Box<str>, u16, Option<(String, String)>), Arc<mqtt::Client>>,
     #[cfg(feature = "music")]
     music: std::collections::HashMap<music::ClientType, Arc<dyn music::MusicClient>>,
     #[cfg(feature = "networkmanager")]
-    networkmanager: Option<Arc<networkmanager::Client>>,
+    networkmanager: std::collections::HashMap<
+        networkmanager::filter::InterfaceFilter,
+        Arc<networkmanager::Client>,
+    >,
     #[cfg(feature = "notifications")]
     notifications: Option<Arc<swaync::Client>>,
     #[cfg(feature = "tray")]
     tray: Option<Arc<tray::Client>>,
-    #[cfg(feature = "upower")]
+    #[cfg(any(feature = "disk_health", feature = "nvme_health", feature = "usb"))]
+    udisks: Option<Arc<udisks::Client>>,
+    #[cfg(all(feature = "bar", feature = "upower"))]
     upower: Option<Arc<zbus::fdo::PropertiesProxy<'static>>>,
-    #[cfg(feature = "volume")]
+    #[cfg(feature = "upower")]
+    battery: Option<Arc<upower::BatteryWatcher>>,
+    #[cfg(feature = "upower")]
+    peripherals: Option<Arc<upower::PeripheralWatcher>>,
+    #[cfg(all(feature = "bar", feature = "upower"))]
+    battery_hooks: Option<Arc<upower::HookRunner>>,
+    #[cfg(any(feature = "mic", feature = "volume"))]
     volume: Option<Arc<volume::Client>>,
 }
 
@@ -62,6 +100,21 @@ impl Clients {
             .clone()
     }
 
+    #[cfg(feature = "bluetooth_battery")]
+    pub fn bluetooth(&mut self) -> ClientResult<bluetooth::Client> {
+        let client = match &self.bluetooth {
+            Some(client) => client.clone(),
+            None => {
+                let client = await_sync(async { bluetooth::Client::new().await })?;
+                let client = Arc::new(client);
+                self.bluetooth.replace(client.clone());
+                client
+            }
+        };
+
+        Ok(client)
+    }
+
     #[cfg(feature = "clipboard")]
     pub fn clipboard(&mut self) -> Arc<clipboard::Client> {
         let wayland = self.wayland();
@@ -71,6 +124,19 @@ impl Clients {
             .clone()
     }
 
+    /// Gets the break timer for `config`.
+    /// Modules using the same settings share a timer.
+    #[cfg(all(feature = "bar", feature = "break_reminder"))]
+    pub fn break_reminder(
+        &mut self,
+        config: break_reminder::TimerConfig,
+    ) -> Arc<break_reminder::Client> {
+        self.break_reminder
+            .entry(config.clone())
+            .or_insert_with(|| Arc::new(break_reminder::Client::new(config)))
+            .clone()
+    }
+
     #[cfg(feature = "workspaces")]
     pub fn workspaces(&mut self) -> ClientResult<dyn compositor::WorkspaceClient> {
         let client = match &self.workspaces {
@@ -85,13 +151,70 @@ impl Clients {
         Ok(client)
     }
 
-    #[cfg(feature = "cairo")]
+    #[cfg(all(feature = "bar", feature = "homeassistant"))]
+    pub fn homeassistant(&mut self, url: &str, token: &str) -> Arc<homeassistant::Client> {
+        self.homeassistant
+            .entry((url.into(), token.into()))
+            .or_insert_with(|| Arc::new(homeassistant::Client::new(url, token.to_string())))
+            .clone()
+    }
+
+    #[cfg(feature = "networkmanager")]
+    pub fn iwd(&mut self) -> ClientResult<iwd::Client> {
+        self.iwd_with_filter(&networkmanager::filter::InterfaceFilter::default())
+    }
+
+    /// Gets the iwd client which only tracks interfaces allowed by `filter`.
+    /// Modules using the same filter share a client.
+    #[cfg(feature = "networkmanager")]
+    pub fn iwd_with_filter(
+        &mut self,
+        filter: &networkmanager::filter::InterfaceFilter,
+    ) -> ClientResult<iwd::Client> {
+        match self.iwd.get(filter) {
+            Some(client) => Ok(client.clone()),
+            None => {
+                let client = await_sync(async { iwd::create_client(filter.clone()).await })?;
+                self.iwd.insert(filter.clone(), client.clone());
+                Ok(client)
+            }
+        }
+    }
+
+    #[cfg(feature = "keyboard")]
+    pub fn keyboard_layout(&mut self) -> ClientResult<dyn compositor::KeyboardLayoutClient> {
+        let client = match &self.keyboard_layout {
+            Some(keyboard_layout) => keyboard_layout.clone(),
+            None => {
+                let client = compositor::Compositor::create_keyboard_layout_client()?;
+                self.keyboard_layout.replace(client.clone());
+                client
+            }
+        };
+
+        Ok(client)
+    }
+
+    #[cfg(all(feature = "bar", feature = "cairo"))]
     pub fn lua(&mut self, config_dir: &Path) -> Rc<lua::LuaEngine> {
         self.lua
             .get_or_insert_with(|| Rc::new(lua::LuaEngine::new(config_dir)))
             .clone()
     }
 
+    #[cfg(all(feature = "bar", feature = "mqtt"))]
+    pub fn mqtt(
+        &mut self,
+        host: &str,
+        port: u16,
+        credentials: Option<(String, String)>,
+    ) -> Arc<mqtt::Client> {
+        self.mqtt
+            .entry((host.into(), port, credentials.clone()))
+            .or_insert_with(|| Arc::new(mqtt::Client::new(host, port, credentials)))
+            .clone()
+    }
+
     #[cfg(feature = "music")]
     pub fn music(&mut self, client_type: music::ClientType) -> Arc<dyn music::MusicClient> {
         self.music
@@ -102,11 +225,22 @@ impl Clients {
 
     #[cfg(feature = "networkmanager")]
     pub fn networkmanager(&mut self) -> ClientResult<networkmanager::Client> {
-        match &self.networkmanager {
+        self.networkmanager_with_filter(&networkmanager::filter::InterfaceFilter::default())
+    }
+
+    /// Gets the NetworkManager client which only tracks interfaces allowed by `filter`.
+    /// Modules using the same filter share a client.
+    #[cfg(feature = "networkmanager")]
+    pub fn networkmanager_with_filter(
+        &mut self,
+        filter: &networkmanager::filter::InterfaceFilter,
+    ) -> ClientResult<networkmanager::Client> {
+        match self.networkmanager.get(filter) {
             Some(client) => Ok(client.clone()),
             None => {
-                let client = networkmanager::create_client()?;
-                self.networkmanager = Some(client.clone());
+                let client =
+                    await_sync(async { networkmanager::create_client(filter.clone()).await })?;
+                self.networkmanager.insert(filter.clone(), client.clone());
                 Ok(client)
             }
         }
@@ -144,7 +278,22 @@ impl Clients {
         Ok(client)
     }
 
-    #[cfg(feature = "upower")]
+    #[cfg(any(feature = "disk_health", feature = "nvme_health", feature = "usb"))]
+    pub fn udisks(&mut self) -> ClientResult<udisks::Client> {
+        let client = match &self.udisks {
+            Some(client) => client.clone(),
+            None => {
+                let client = await_sync(async { udisks::Client::new().await })?;
+                let client = Arc::new(client);
+                self.udisks.replace(client.clone());
+                client
+            }
+        };
+
+        Ok(client)
+    }
+
+    #[cfg(all(feature = "bar", feature = "upower"))]
     pub fn upower(&mut self) -> Arc<zbus::fdo::PropertiesProxy<'static>> {
         self.upower
             .get_or_insert_with(|| {
@@ -153,30 +302,207 @@ impl Clients {
             .clone()
     }
 
-    #[cfg(feature = "volume")]
+    #[cfg(feature = "upower")]
+    pub fn battery(&mut self) -> ClientResult<upower::BatteryWatcher> {
+        let client = match &self.battery {
+            Some(client) => client.clone(),
+            None => {
+                let client = await_sync(async { upower::BatteryWatcher::new().await })?;
+                let client = Arc::new(client);
+                self.battery.replace(client.clone());
+                client
+            }
+        };
+
+        Ok(client)
+    }
+
+    #[cfg(feature = "upower")]
+    pub fn peripherals(&mut self) -> ClientResult<upower::PeripheralWatcher> {
+        let client = match &self.peripherals {
+            Some(client) => client.clone(),
+            None => {
+                let client = await_sync(async { upower::PeripheralWatcher::new().await })?;
+                let client = Arc::new(client);
+                self.peripherals.replace(client.clone());
+                client
+            }
+        };
+
+        Ok(client)
+    }
+
+    #[cfg(all(feature = "bar", feature = "upower"))]
+    pub fn battery_hooks(&mut self) -> Arc<upower::HookRunner> {
+        if let Some(client) = &self.battery_hooks {
+            return client.clone();
+        }
+
+        let client = Arc::new(upower::HookRunner::new(self.upower()));
+        self.battery_hooks.replace(client.clone());
+        client
+    }
+
+    #[cfg(any(feature = "mic", feature = "volume"))]
     pub fn volume(&mut self) -> Arc<volume::Client> {
         self.volume
             .get_or_insert_with(volume::create_client)
             .clone()
     }
+
+    /// Exports the current state of the named client as JSON,
+    /// starting the client if it is not already running.
+    ///
+    /// Clients which take parameters, such as an MQTT broker address,
+    /// cannot be started this way, so only the running instances are exported.
+    #[cfg(feature = "ipc")]
+    pub fn export_state(&mut self, name: &str) -> Result<serde_json::Value> {
+        match name {
+            #[cfg(feature = "upower")]
+            "battery" => self.battery()?.export_state(),
+            #[cfg(feature = "bluetooth_battery")]
+            "bluetooth" => self.bluetooth()?.export_state(),
+            #[cfg(all(feature = "bar", feature = "break_reminder"))]
+            "break_reminder" => {
+                export_instances(self.break_reminder.iter().map(|(config, client)| {
+                    let key = format!(
+                        "{}/{}",
+                        config.interval.as_secs(),
+                        config.duration.as_secs()
+                    );
+                    (key, client)
+                }))
+            }
+            #[cfg(feature = "clipboard")]
+            "clipboard" => self.clipboard().export_state(),
+            #[cfg(any(feature = "disk_health", feature = "nvme_health", feature = "usb"))]
+            "drives" => self.udisks()?.export_state(),
+            #[cfg(all(feature = "bar", feature = "homeassistant"))]
+            "homeassistant" => export_instances(
+                self.homeassistant
+                    .iter()
+                    .map(|((url, _), client)| (url.to_string(), client)),
+            ),
+            #[cfg(feature = "networkmanager")]
+            "iwd" => self.iwd()?.export_state(),
+            #[cfg(feature = "keyboard")]
+            "keyboard_layout" => self.keyboard_layout()?.export_state(),
+            #[cfg(all(feature = "bar", feature = "mqtt"))]
+            "mqtt" => export_instances(
+                self.mqtt
+                    .iter()
+                    .map(|((host, port), client)| (format!("{host}:{port}"), client)),
+            ),
+            #[cfg(feature = "music+mpris")]
+            "music" => self.music(music::ClientType::Mpris).export_state(),
+            #[cfg(feature = "networkmanager")]
+            "network" => self.networkmanager()?.export_state(),
+            #[cfg(feature = "notifications")]
+            "notifications" => self.notifications()?.export_state(),
+            #[cfg(feature = "upower")]
+            "peripherals" => self.peripherals()?.export_state(),
+            #[cfg(feature = "tray")]
+            "tray" => self.tray()?.export_state(),
+            #[cfg(any(feature = "mic", feature = "volume"))]
+            "volume" => self.volume().export_state(),
+            #[cfg(feature = "workspaces")]
+            "workspaces" => self.workspaces()?.export_state(),
+            _ => Err(color_eyre::Report::msg(format!("Unknown client '{name}'"))),
+        }
+    }
+
+    /// Starts each client which can be exported without parameters,
+    /// so that it has received its initial state before the first export.
+    ///
+    /// The tray is not started, as registering as a tray host
+    /// makes applications send their items to a tray which is never shown.
+    #[cfg(all(feature = "ipc", not(feature = "bar")))]
+    pub fn start_exportable(&mut self) {
+        fn log_error<T: ?Sized>(name: &str, client: ClientResult<T>) {
+            if let Err(err) = client {
+                tracing::warn!(
+                    "{:?}",
+                    err.wrap_err(format!("Failed to start {name} client"))
+                );
+            }
+        }
+
+        #[cfg(feature = "upower")]
+        {
+            log_error("battery", self.battery());
+            log_error("peripherals", self.peripherals());
+        }
+        #[cfg(feature = "bluetooth_battery")]
+        log_error("bluetooth", self.bluetooth());
+        #[cfg(feature = "clipboard")]
+        self.clipboard();
+        #[cfg(any(feature = "disk_health", feature = "nvme_health", feature = "usb"))]
+        log_error("drives", self.udisks());
+        #[cfg(feature = "networkmanager")]
+        {
+            log_error("network", self.networkmanager());
+            log_error("iwd", self.iwd());
+        }
+        #[cfg(feature = "keyboard")]
+        log_error("keyboard_layout", self.keyboard_layout());
+        #[cfg(feature = "music+mpris")]
+        self.music(music::ClientType::Mpris);
+        #[cfg(feature = "notifications")]
+        log_error("notifications", self.notifications());
+        #[cfg(any(feature = "mic", feature = "volume"))]
+        self.volume();
+        #[cfg(feature = "workspaces")]
+        log_error("workspaces", self.workspaces());
+    }
+}
+
+/// Exports the state of each running instance of a client which takes parameters,
+/// keyed by a name identifying the instance.
+#[cfg(all(
+    feature = "ipc",
+    feature = "bar",
+    any(
+        feature = "break_reminder",
+        feature = "homeassistant",
+        feature = "mqtt"
+    )
+))]
+fn export_instances<'a, T: ExportState + ?Sized + 'a>(
+    instances: impl Iterator<Item = (String, &'a Arc<T>)>,
+) -> Result<serde_json::Value> {
+    instances
+        .map(|(key, client)| Ok((key, client.export_state()?)))
+        .collect::<Result<serde_json::Map<_, _>>>()
+        .map(serde_json::Value::Object)
 }
 
 /// Types implementing this trait
 /// indicate that they provide a singleton client instance of type `T`.
+#[cfg(feature = "bar")]
 pub trait ProvidesClient<T: ?Sized> {
     /// Returns a singleton client instance of type `T`.
-    fn provide(&self) -> Arc<T>;
+    fn provide(&mut self) -> Arc<T>;
 }
 
 /// Types implementing this trait
 /// indicate that they provide a singleton client instance of type `T`,
 /// which may fail to be created.
+#[cfg(feature = "bar")]
 pub trait ProvidesFallibleClient<T: ?Sized> {
     /// Returns a singleton client instance of type `T`.
-    fn try_provide(&self) -> ClientResult<T>;
+    fn try_provide(&mut self) -> ClientResult<T>;
 }
 
-/// Generates a `ProvidesClient` impl block on `WidgetContext`
+/// Types implementing this trait
+/// can export a snapshot of their current state,
+/// allowing it to be read over IPC.
+#[cfg(feature = "ipc")]
+pub trait ExportState {
+    /// Returns the current state as JSON.
+    fn export_state(&self) -> Result<serde_json::Value>;
+}
+
+/// Generates a `ProvidesClient` impl block on `Clients`
 /// for the provided `$ty` (first argument) client type.
 ///
 /// The implementation calls `$method` (second argument)
@@ -187,36 +513,30 @@ pub trait ProvidesFallibleClient<T: ?Sized> {
 #[macro_export]
 macro_rules! register_client {
     ($ty:ty, $method:ident) => {
-        impl<TSend, TReceive> $crate::clients::ProvidesClient<$ty>
-            for $crate::modules::WidgetContext<TSend, TReceive>
-        where
-            TSend: Clone,
-        {
-            fn provide(&self) -> std::sync::Arc<$ty> {
-                self.ironbar.clients.borrow_mut().$method()
+        #[cfg(feature = "bar")]
+        impl $crate::clients::ProvidesClient<$ty> for $crate::clients::Clients {
+            fn provide(&mut self) -> std::sync::Arc<$ty> {
+                self.$method()
             }
         }
     };
 }
 
-/// Generates a `ProvidesClient` impl block on `WidgetContext`
+/// Generates a `ProvidesFallibleClient` impl block on `Clients`
 /// for the provided `$ty` (first argument) client type.
 ///
 /// The implementation calls `$method` (second argument)
 /// on the `Clients` struct to obtain the client instance.
 ///
 /// # Example
-/// `register_client!(Client, clipboard);`
+/// `register_fallible_client!(Client, bluetooth);`
 #[macro_export]
 macro_rules! register_fallible_client {
     ($ty:ty, $method:ident) => {
-        impl<TSend, TReceive> $crate::clients::ProvidesFallibleClient<$ty>
-            for $crate::modules::WidgetContext<TSend, TReceive>
-        where
-            TSend: Clone,
-        {
-            fn try_provide(&self) -> color_eyre::Result<std::sync::Arc<$ty>> {
-                self.ironbar.clients.borrow_mut().$method()
+        #[cfg(feature = "bar")]
+        impl $crate::clients::ProvidesFallibleClient<$ty> for $crate::clients::Clients {
+            fn try_provide(&mut self) -> color_eyre::Result<std::sync::Arc<$ty>> {
+                self.$method()
             }
         }
     };
@@ -0,0 +1,202 @@
+use crate::{arc_mut, lock, send, spawn};
+use rumqttc::{AsyncClient, Event, Incoming, MqttOptions, QoS};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::time::sleep;
+use tracing::{debug, error, warn};
+
+/// Time to wait before polling the event loop again after a connection error.
+/// The event loop automatically reconnects on the next poll.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A message received on a subscribed topic.
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub topic: String,
+    pub payload: String,
+}
+
+#[derive(Debug)]
+pub struct Client {
+    client: AsyncClient,
+    subscriptions: Arc<Mutex<HashSet<String>>>,
+    messages: Arc<Mutex<HashMap<String, Message>>>,
+    tx: broadcast::Sender<Message>,
+    _rx: broadcast::Receiver<Message>,
+}
+
+impl Client {
+    pub fn new(host: &str, port: u16, credentials: Option<(String, String)>) -> Self {
+        // brokers drop an existing connection when another connects with the same id,
+        // so each client needs its own id
+        static CLIENT_COUNT: AtomicUsize = AtomicUsize::new(0);
+        let client_id = format!(
+            "{}-{}-{}",
+            env!("CARGO_CRATE_NAME"),
+            std::process::id(),
+            CLIENT_COUNT.fetch_add(1, Ordering::Relaxed)
+        );
+
+        let mut options = MqttOptions::new(client_id, host, port);
+        options.set_keep_alive(Duration::from_secs(30));
+
+        if let Some((username, password)) = credentials {
+            options.set_credentials(username, password);
+        }
+
+        let (client, mut event_loop) = AsyncClient::new(options, 32);
+
+        let subscriptions = arc_mut!(HashSet::<String>::new());
+        let messages = arc_mut!(HashMap::new());
+        let (tx, rx) = broadcast::channel(64);
+
+        {
+            let client = client.clone();
+            let subscriptions = subscriptions.clone();
+            let messages = messages.clone();
+            let tx = tx.clone();
+
+            spawn(async move {
+                loop {
+                    match event_loop.poll().await {
+                        Ok(Event::Incoming(Incoming::ConnAck(_))) => {
+                            debug!("Connected to MQTT broker");
+
+                            // subscriptions do not persist across reconnects
+                            let topics = lock!(subscriptions).iter().cloned().collect::<Vec<_>>();
+                            for topic in topics {
+                                if let Err(err) = client.subscribe(topic, QoS::AtMostOnce).await {
+                                    error!("{err:?}");
+                                }
+                            }
+                        }
+                        Ok(Event::Incoming(Incoming::Publish(publish))) => {
+                            let message = Message {
+                                topic: publish.topic,
+                                payload: String::from_utf8_lossy(&publish.payload).to_string(),
+                            };
+
+                            lock!(messages).insert(message.topic.clone(), message.clone());
+                            send!(tx, message);
+                        }
+                        Ok(_) => {}
+                        Err(err) => {
+                            warn!(
+                                "MQTT connection error, retrying in {}s: {err}",
+                                RECONNECT_INTERVAL.as_secs()
+                            );
+                            sleep(RECONNECT_INTERVAL).await;
+                        }
+                    }
+                }
+            });
+        }
+
+        Self {
+            client,
+            subscriptions,
+            messages,
+            tx,
+            _rx: rx,
+        }
+    }
+
+    /// Subscribes to a topic filter.
+    /// Subscriptions are automatically renewed after reconnecting.
+    pub async fn subscribe(&self, filter: &str) {
+        if !lock!(self.subscriptions).insert(filter.to_string()) {
+            return;
+        }
+
+        debug!("Subscribing to '{filter}'");
+        if let Err(err) = self.client.subscribe(filter, QoS::AtMostOnce).await {
+            error!("{err:?}");
+        }
+    }
+
+    /// Gets the last received message for each topic matching the filter.
+    pub fn last_messages(&self, filter: &str) -> Vec<Message> {
+        lock!(self.messages)
+            .values()
+            .filter(|message| topic_matches(filter, &message.topic))
+            .cloned()
+            .collect()
+    }
+
+    pub async fn publish(&self, topic: &str, payload: String, retain: bool) {
+        debug!("Publishing to '{topic}'");
+        if let Err(err) = self
+            .client
+            .publish(topic, QoS::AtLeastOnce, retain, payload)
+            .await
+        {
+            error!("{err:?}");
+        }
+    }
+
+    pub fn subscribe_messages(&self) -> broadcast::Receiver<Message> {
+        self.tx.subscribe()
+    }
+}
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> color_eyre::Result<serde_json::Value> {
+        Ok(lock!(self.messages)
+            .iter()
+            .map(|(topic, message)| (topic.clone(), message.payload.clone()))
+            .collect())
+    }
+}
+
+/// Checks whether a topic name matches a subscription filter,
+/// taking into account the `+` and `#` wildcards.
+pub fn topic_matches(filter: &str, topic: &str) -> bool {
+    let mut filter_levels = filter.split('/');
+    let mut topic_levels = topic.split('/');
+
+    loop {
+        match (filter_levels.next(), topic_levels.next()) {
+            (Some("#"), _) => return true,
+            (Some("+"), Some(_)) => {}
+            (Some(filter), Some(topic)) if filter == topic => {}
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exact_match() {
+        assert!(topic_matches("home/kitchen/temp", "home/kitchen/temp"));
+        assert!(!topic_matches("home/kitchen/temp", "home/kitchen/humidity"));
+    }
+
+    #[test]
+    fn test_single_level_wildcard() {
+        assert!(topic_matches("home/+/temp", "home/kitchen/temp"));
+        assert!(!topic_matches("home/+/temp", "home/kitchen/fridge/temp"));
+        assert!(!topic_matches("home/+", "home"));
+    }
+
+    #[test]
+    fn test_multi_level_wildcard() {
+        assert!(topic_matches("home/#", "home/kitchen/temp"));
+        assert!(topic_matches("home/#", "home"));
+        assert!(topic_matches("#", "home/kitchen"));
+        assert!(!topic_matches("home/#", "office/desk"));
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        assert!(!topic_matches("home/kitchen", "home/kitchen/temp"));
+        assert!(!topic_matches("home/kitchen/temp", "home/kitchen"));
+    }
+}
@@ -1,28 +1,35 @@
 use color_eyre::Result;
+use serde::Serialize;
 use std::fmt::Debug;
+#[cfg(feature = "bar")]
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::broadcast;
 
-#[cfg(feature = "music+mpd")]
+#[cfg(all(feature = "bar", feature = "music+mpd"))]
 pub mod mpd;
 #[cfg(feature = "music+mpris")]
 pub mod mpris;
 
 pub const TICK_INTERVAL_MS: u64 = 200;
 
+/// The maximum number of upcoming tracks to include in updates.
+pub const MAX_QUEUE_LENGTH: usize = 10;
+
 #[derive(Clone, Debug)]
 pub enum PlayerUpdate {
     /// Triggered when the track or player state notably changes,
     /// such as a new track playing, the player being paused, or a volume change.
-    Update(Box<Option<Track>>, Status),
+    ///
+    /// Includes the tracks queued to play next, where the player provides them.
+    Update(Box<Option<Track>>, Status, Vec<QueueTrack>),
     /// Triggered at regular intervals while a track is playing.
     /// Used to keep track of the progress through the current track.
     ProgressTick(ProgressTick),
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct Track {
     pub title: Option<String>,
     pub album: Option<String>,
@@ -34,7 +41,17 @@ pub struct Track {
     pub cover_path: Option<String>,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+/// A track queued to play after the current one.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct QueueTrack {
+    /// The player's ID for the track, used to jump to it.
+    pub id: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum PlayerState {
     #[default]
     Stopped,
@@ -42,7 +59,7 @@ pub enum PlayerState {
     Paused,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, Serialize)]
 pub struct Status {
     pub state: PlayerState,
     pub volume_percent: Option<u8>,
@@ -50,6 +67,8 @@ pub struct Status {
     pub playlist_length: u32,
 }
 
+// only consumed by the music module
+#[cfg_attr(not(feature = "bar"), allow(dead_code))]
 #[derive(Clone, Copy, Debug)]
 pub struct ProgressTick {
     pub duration: Option<Duration>,
@@ -57,25 +76,67 @@ pub struct ProgressTick {
 }
 
 pub trait MusicClient: Debug + Send + Sync {
+    #[cfg(feature = "bar")]
     fn play(&self) -> Result<()>;
+    #[cfg(feature = "bar")]
     fn pause(&self) -> Result<()>;
+    #[cfg(feature = "bar")]
     fn next(&self) -> Result<()>;
+    #[cfg(feature = "bar")]
     fn prev(&self) -> Result<()>;
 
+    #[cfg(feature = "bar")]
     fn set_volume_percent(&self, vol: u8) -> Result<()>;
+    #[cfg(feature = "bar")]
     fn seek(&self, duration: Duration) -> Result<()>;
 
+    /// Brings the player's window to the front, if it has one.
+    #[cfg(feature = "bar")]
+    fn raise(&self) -> Result<()>;
+
+    /// Skips to a track in the queue, using its ID.
+    #[cfg(feature = "bar")]
+    fn go_to(&self, id: &str) -> Result<()>;
+
+    /// Gets lowercase names which may identify the player's audio streams,
+    /// such as its binary name.
+    #[cfg(feature = "bar")]
+    fn player_names(&self) -> Vec<String>;
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate>;
 }
 
+#[cfg(feature = "ipc")]
+impl super::ExportState for dyn MusicClient {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        // the current track and status are sent to each new subscriber
+        let mut rx = self.subscribe_change();
+        let state = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|update| match update {
+            PlayerUpdate::Update(track, status, queue) => Some(serde_json::json!({
+                "track": *track,
+                "status": status,
+                "queue": queue,
+            })),
+            PlayerUpdate::ProgressTick(_) => None,
+        });
+
+        Ok(state.unwrap_or_default())
+    }
+}
+
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum ClientType {
-    Mpd { host: String, music_dir: PathBuf },
+    #[cfg(feature = "bar")]
+    Mpd {
+        host: String,
+        music_dir: PathBuf,
+    },
     Mpris,
 }
 
 pub fn create_client(client_type: ClientType) -> Arc<dyn MusicClient> {
     match client_type {
+        #[cfg(feature = "bar")]
         ClientType::Mpd { host, music_dir } => Arc::new(mpd::Client::new(host, music_dir)),
         ClientType::Mpris => Arc::new(mpris::Client::new()),
     }
@@ -2,6 +2,7 @@ use super::{
     MusicClient, PlayerState, PlayerUpdate, ProgressTick, Status, Track, TICK_INTERVAL_MS,
 };
 use crate::{await_sync, send, spawn, Ironbar};
+use color_eyre::eyre::eyre;
 use color_eyre::Report;
 use color_eyre::Result;
 use mpd_client::client::{ConnectionEvent, Subsystem};
@@ -96,7 +97,7 @@ impl Client {
             let track = current_song.map(|s| convert_song(&s.song, music_dir));
             let status = Status::from(status);
 
-            let update = PlayerUpdate::Update(Box::new(track), status);
+            let update = PlayerUpdate::Update(Box::new(track), status, vec![]);
             send!(tx, update);
         }
 
@@ -144,6 +145,18 @@ impl MusicClient for Client {
         command!(self, commands::Seek(SeekMode::Absolute(duration)))
     }
 
+    fn raise(&self) -> Result<()> {
+        Err(eyre!("MPD does not have a window to raise"))
+    }
+
+    fn go_to(&self, _id: &str) -> Result<()> {
+        Err(eyre!("Jumping to queued tracks is not supported for MPD"))
+    }
+
+    fn player_names(&self) -> Vec<String> {
+        vec![String::from("mpd"), String::from("music player daemon")]
+    }
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate> {
         let rx = self.tx.subscribe();
         await_sync(async move {
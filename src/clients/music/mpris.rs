@@ -1,8 +1,14 @@
-use super::{MusicClient, PlayerState, PlayerUpdate, Status, Track, TICK_INTERVAL_MS};
+use super::{
+    MusicClient, PlayerState, PlayerUpdate, QueueTrack, Status, Track, MAX_QUEUE_LENGTH,
+    TICK_INTERVAL_MS,
+};
 use crate::clients::music::ProgressTick;
 use crate::{arc_mut, lock, send, spawn_blocking};
+#[cfg(feature = "bar")]
+use color_eyre::Report;
 use color_eyre::Result;
-use mpris::{DBusError, Event, Metadata, PlaybackStatus, Player, PlayerFinder};
+use mpris::{DBusError, Event, Metadata, PlaybackStatus, Player, PlayerFinder, TrackID};
+#[cfg(feature = "bar")]
 use std::cmp;
 use std::collections::HashSet;
 use std::sync::{Arc, Mutex};
@@ -13,6 +19,7 @@ use tracing::{debug, error, trace};
 
 #[derive(Debug)]
 pub struct Client {
+    /// The bus name of the player currently being shown.
     current_player: Arc<Mutex<Option<String>>>,
     tx: broadcast::Sender<PlayerUpdate>,
     _rx: broadcast::Receiver<PlayerUpdate>,
@@ -23,6 +30,10 @@ const NO_REPLY: &str = "org.freedesktop.DBus.Error.NoReply";
 const NO_SERVICE: &str = "org.freedesktop.DBus.Error.ServiceUnknown";
 const NO_METHOD: &str = "org.freedesktop.DBus.Error.UnknownMethod";
 
+/// `playerctld` proxies whichever player was most recently active,
+/// so is followed in preference to any other players while it is running.
+const PLAYERCTLD: &str = "org.mpris.MediaPlayer2.playerctld";
+
 impl Client {
     pub(crate) fn new() -> Self {
         let (tx, rx) = broadcast::channel(32);
@@ -51,6 +62,9 @@ impl Client {
                         }
                         _ => panic!("Failed to connect to D-Bus"),
                     });
+                    let has_playerctld =
+                        players.iter().any(|player| player.bus_name() == PLAYERCTLD);
+
                     // Acquire the lock of current_player before players to avoid deadlock.
                     // There are places where we lock on current_player and players, but we always lock on current_player first.
                     // This is because we almost never need to lock on players without locking on current_player.
@@ -59,22 +73,28 @@ impl Client {
 
                         let mut players_list_val = lock!(players_list);
                         for player in players {
-                            let identity = player.identity();
+                            let bus_name = player.bus_name();
+
+                            if has_playerctld && bus_name != PLAYERCTLD {
+                                continue;
+                            }
 
-                            if current_player_lock.is_none() {
-                                debug!("Setting active player to '{identity}'");
-                                current_player_lock.replace(identity.to_string());
+                            if current_player_lock.as_deref() != Some(bus_name)
+                                && (current_player_lock.is_none() || has_playerctld)
+                            {
+                                debug!("Setting active player to '{bus_name}'");
+                                current_player_lock.replace(bus_name.to_string());
 
                                 if let Err(err) = Self::send_update(&player, &tx) {
                                     error!("{err:?}");
                                 }
                             }
-                            if !players_list_val.contains(identity) {
-                                debug!("Adding MPRIS player '{identity}'");
-                                players_list_val.insert(identity.to_string());
+                            if !players_list_val.contains(bus_name) {
+                                debug!("Adding MPRIS player '{bus_name}'");
+                                players_list_val.insert(bus_name.to_string());
 
                                 Self::listen_player_events(
-                                    identity.to_string(),
+                                    bus_name.to_string(),
                                     players_list.clone(),
                                     current_player.clone(),
                                     tx.clone(),
@@ -118,7 +138,7 @@ impl Client {
         spawn_blocking(move || {
             let player_finder = PlayerFinder::new()?;
 
-            if let Ok(player) = player_finder.find_by_name(&player_id) {
+            if let Some(player) = find_player(&player_finder, &player_id) {
                 let identity = player.identity();
                 let handle_shutdown = |current_player_lock_option: Option<
                     std::sync::MutexGuard<'_, Option<String>>,
@@ -131,9 +151,12 @@ impl Client {
                         lock!(current_player).take();
                     }
                     let mut players_locked = lock!(players);
-                    players_locked.remove(identity);
+                    players_locked.remove(&player_id);
                     if players_locked.is_empty() {
-                        send!(tx, PlayerUpdate::Update(Box::new(None), Status::default()));
+                        send!(
+                            tx,
+                            PlayerUpdate::Update(Box::new(None), Status::default(), vec![])
+                        );
                     }
                 };
 
@@ -156,11 +179,15 @@ impl Client {
                         }
                         Ok(_) => {
                             let mut current_player_lock = lock!(current_player);
-                            if matches!(event, Ok(Event::Playing)) {
-                                current_player_lock.replace(identity.to_string());
+
+                            // while following playerctld, it decides which player is active
+                            if matches!(event, Ok(Event::Playing))
+                                && current_player_lock.as_deref() != Some(PLAYERCTLD)
+                            {
+                                current_player_lock.replace(player_id.clone());
                             }
-                            if let Some(current_identity) = current_player_lock.as_ref() {
-                                if current_identity == identity {
+                            if let Some(current_bus_name) = current_player_lock.as_ref() {
+                                if current_bus_name == &player_id {
                                     if let Err(err) = Self::send_update(&player, &tx) {
                                         if let Some(DBusError::TransportError(transport_error)) =
                                             err.downcast_ref::<DBusError>()
@@ -205,21 +232,69 @@ impl Client {
             volume_percent,
         };
 
+        let queue = metadata
+            .track_id()
+            .map(|current| Self::get_queue(player, &current))
+            .unwrap_or_default();
+
         let track = Track::from(metadata);
 
-        let player_update = PlayerUpdate::Update(Box::new(Some(track)), status);
+        let player_update = PlayerUpdate::Update(Box::new(Some(track)), status, queue);
         send!(tx, player_update);
 
         Ok(())
     }
 
+    /// Gets the tracks after `current` in the player's track list.
+    /// This is empty if the player does not implement the `TrackList` interface.
+    fn get_queue(player: &Player, current: &TrackID) -> Vec<QueueTrack> {
+        let Ok(Some(track_list)) = player.checked_get_track_list() else {
+            return vec![];
+        };
+
+        let ids = track_list
+            .ids()
+            .iter()
+            .skip_while(|id| *id != current)
+            .skip(1)
+            .take(MAX_QUEUE_LENGTH)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        if ids.is_empty() {
+            return vec![];
+        }
+
+        match player.get_tracks_metadata(&ids) {
+            Ok(metadata) => ids
+                .into_iter()
+                .zip(metadata)
+                .map(|(id, metadata)| QueueTrack {
+                    id: id.to_string(),
+                    title: metadata
+                        .title()
+                        .map(ToString::to_string)
+                        .and_then(replace_empty_none),
+                    artist: metadata
+                        .artists()
+                        .map(|artists| artists.join(", "))
+                        .and_then(replace_empty_none),
+                })
+                .collect(),
+            Err(err) => {
+                error!("Failed to get track list metadata: {err:?}");
+                vec![]
+            }
+        }
+    }
+
     fn get_player(&self) -> Option<Player> {
         let player_name = lock!(self.current_player);
         let player_name = player_name.as_ref();
 
         player_name.and_then(|player_name| {
             let player_finder = PlayerFinder::new().expect("Failed to connect to D-Bus");
-            player_finder.find_by_name(player_name).ok()
+            find_player(&player_finder, player_name)
         })
     }
 
@@ -230,7 +305,7 @@ impl Client {
     ) {
         if let Some(player) = lock!(current_player)
             .as_ref()
-            .and_then(|name| player_finder.find_by_name(name).ok())
+            .and_then(|name| find_player(player_finder, name))
         {
             if let Ok(metadata) = player.get_metadata() {
                 let update = PlayerUpdate::ProgressTick(ProgressTick {
@@ -244,6 +319,19 @@ impl Client {
     }
 }
 
+/// Finds a player by its D-Bus bus name.
+///
+/// Players cannot be looked up by their identity,
+/// as `playerctld` shares the identity of the player it is proxying.
+fn find_player(player_finder: &PlayerFinder, bus_name: &str) -> Option<Player> {
+    player_finder
+        .iter_players()
+        .ok()?
+        .filter_map(Result::ok)
+        .find(|player| player.bus_name() == bus_name)
+}
+
+#[cfg(feature = "bar")]
 macro_rules! command {
     ($self:ident, $func:ident) => {
         if let Some(player) = Self::get_player($self) {
@@ -255,26 +343,31 @@ macro_rules! command {
 }
 
 impl MusicClient for Client {
+    #[cfg(feature = "bar")]
     fn play(&self) -> Result<()> {
         command!(self, play);
         Ok(())
     }
 
+    #[cfg(feature = "bar")]
     fn pause(&self) -> Result<()> {
         command!(self, pause);
         Ok(())
     }
 
+    #[cfg(feature = "bar")]
     fn next(&self) -> Result<()> {
         command!(self, next);
         Ok(())
     }
 
+    #[cfg(feature = "bar")]
     fn prev(&self) -> Result<()> {
         command!(self, previous);
         Ok(())
     }
 
+    #[cfg(feature = "bar")]
     fn set_volume_percent(&self, vol: u8) -> Result<()> {
         if let Some(player) = Self::get_player(self) {
             player.set_volume(f64::from(vol) / 100.0)?;
@@ -284,6 +377,7 @@ impl MusicClient for Client {
         Ok(())
     }
 
+    #[cfg(feature = "bar")]
     fn seek(&self, duration: Duration) -> Result<()> {
         if let Some(player) = Self::get_player(self) {
             let pos = player.get_position().unwrap_or_default();
@@ -300,6 +394,36 @@ impl MusicClient for Client {
         Ok(())
     }
 
+    #[cfg(feature = "bar")]
+    fn raise(&self) -> Result<()> {
+        command!(self, raise);
+        Ok(())
+    }
+
+    #[cfg(feature = "bar")]
+    fn go_to(&self, id: &str) -> Result<()> {
+        if let Some(player) = Self::get_player(self) {
+            let id = TrackID::new(id).map_err(Report::msg)?;
+            player.go_to(&id)?;
+        } else {
+            error!("Could not find player");
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "bar")]
+    fn player_names(&self) -> Vec<String> {
+        self.get_player()
+            .map(|player| {
+                // instanced players are named like `firefox.instance_1_23`
+                let name = player.bus_name_player_name_part();
+                let name = name.split_once('.').map_or(name, |(name, _)| name);
+
+                vec![name.to_lowercase(), player.identity().to_lowercase()]
+            })
+            .unwrap_or_default()
+    }
+
     fn subscribe_change(&self) -> broadcast::Receiver<PlayerUpdate> {
         debug!("Creating new subscription");
         let rx = self.tx.subscribe();
@@ -315,7 +439,10 @@ impl MusicClient for Client {
                 state: PlayerState::Stopped,
                 volume_percent: None,
             };
-            send!(self.tx, PlayerUpdate::Update(Box::new(None), status));
+            send!(
+                self.tx,
+                PlayerUpdate::Update(Box::new(None), status, vec![])
+            );
         }
 
         rx
@@ -0,0 +1,77 @@
+use color_eyre::{Report, Result};
+use tracing::debug;
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::clients::networkmanager::dbus::{AccessPointDbusProxy, ConnectionSettings, DeviceType};
+use crate::clients::networkmanager::state::wireless_proxy;
+use crate::clients::networkmanager::Client;
+
+impl Client {
+    /// Connects to the wifi network with the given SSID,
+    /// using the strongest access point broadcasting it.
+    ///
+    /// A saved connection for the network is activated if there is one.
+    /// Otherwise, a new connection is created from the access point's settings,
+    /// and NetworkManager asks the user's secret agent for any password.
+    pub async fn connect_wifi(&self, ssid: &str) -> Result<()> {
+        let (device, access_point) = self
+            .find_access_point(ssid)
+            .await?
+            .ok_or_else(|| Report::msg(format!("No access point found for '{ssid}'")))?;
+
+        if let Some(connection) = self.find_wifi_connection(ssid).await? {
+            debug!("Activating saved connection for '{ssid}'");
+            self.0
+                .root_object
+                .activate_connection(&connection, &device, &access_point)
+                .await?;
+        } else {
+            debug!("Adding new connection for '{ssid}'");
+            self.0
+                .root_object
+                .add_and_activate_connection(ConnectionSettings::new(), &device, &access_point)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Finds the strongest access point broadcasting the SSID,
+    /// returning the paths of its wifi device and the access point itself.
+    async fn find_access_point(
+        &self,
+        ssid: &str,
+    ) -> Result<Option<(OwnedObjectPath, OwnedObjectPath)>> {
+        let mut strongest: Option<(u8, OwnedObjectPath, OwnedObjectPath)> = None;
+
+        for (device_path, device) in self.0.devices() {
+            if device.device_type().await? != DeviceType::Wifi
+                || !device.state().await?.is_enabled()
+            {
+                continue;
+            }
+
+            let wireless = wireless_proxy(&device).await?;
+            for path in wireless.access_points().await? {
+                let access_point = AccessPointDbusProxy::builder(&self.0.dbus_connection)
+                    .path(path.clone())?
+                    .build()
+                    .await?;
+
+                if access_point.ssid().await? != ssid.as_bytes() {
+                    continue;
+                }
+
+                let strength = access_point.strength().await?;
+                if strongest
+                    .as_ref()
+                    .map_or(true, |(strongest, ..)| strength > *strongest)
+                {
+                    strongest = Some((strength, device_path.clone(), path.into()));
+                }
+            }
+        }
+
+        Ok(strongest.map(|(_, device, access_point)| (device, access_point)))
+    }
+}
@@ -1,6 +1,12 @@
+use std::collections::HashMap;
+
 use color_eyre::Result;
 use zbus::dbus_proxy;
-use zbus::zvariant::{ObjectPath, OwnedValue, Str};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Str};
+
+/// Connection settings, as a map of setting names
+/// to maps of property names against values.
+pub(super) type ConnectionSettings = HashMap<String, HashMap<String, OwnedValue>>;
 
 #[dbus_proxy(
     default_service = "org.freedesktop.NetworkManager",
@@ -8,11 +14,33 @@ use zbus::zvariant::{ObjectPath, OwnedValue, Str};
     default_path = "/org/freedesktop/NetworkManager"
 )]
 trait Dbus {
+    fn activate_connection(
+        &self,
+        connection: &ObjectPath<'_>,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> Result<OwnedObjectPath>;
+
+    fn add_and_activate_connection(
+        &self,
+        connection: ConnectionSettings,
+        device: &ObjectPath<'_>,
+        specific_object: &ObjectPath<'_>,
+    ) -> Result<(OwnedObjectPath, OwnedObjectPath)>;
+
+    fn deactivate_connection(&self, active_connection: &ObjectPath<'_>) -> Result<()>;
+
     #[dbus_proxy(property)]
-    fn active_connections(&self) -> Result<Vec<ObjectPath>>;
+    fn active_connections(&self) -> Result<Vec<OwnedObjectPath>>;
 
     #[dbus_proxy(property)]
-    fn devices(&self) -> Result<Vec<ObjectPath>>;
+    fn connectivity(&self) -> Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn connectivity_check_uri(&self) -> Result<Str>;
+
+    #[dbus_proxy(property)]
+    fn devices(&self) -> Result<Vec<OwnedObjectPath>>;
 
     // #[dbus_proxy(property)]
     // fn networking_enabled(&self) -> Result<bool>;
@@ -23,8 +51,11 @@ trait Dbus {
     // #[dbus_proxy(property)]
     // fn primary_connection_type(&self) -> Result<Str>;
 
-    // #[dbus_proxy(property)]
-    // fn wireless_enabled(&self) -> Result<bool>;
+    #[dbus_proxy(property)]
+    fn wireless_enabled(&self) -> Result<bool>;
+
+    #[dbus_proxy(property)]
+    fn set_wireless_enabled(&self, value: bool) -> Result<()>;
 }
 
 #[dbus_proxy(
@@ -32,8 +63,8 @@ trait Dbus {
     interface = "org.freedesktop.NetworkManager.Connection.Active"
 )]
 trait ActiveConnectionDbus {
-    // #[dbus_proxy(property)]
-    // fn connection(&self) -> Result<ObjectPath>;
+    #[dbus_proxy(property)]
+    fn connection(&self) -> Result<ObjectPath>;
 
     // #[dbus_proxy(property)]
     // fn default(&self) -> Result<bool>;
@@ -44,8 +75,8 @@ trait ActiveConnectionDbus {
     #[dbus_proxy(property)]
     fn devices(&self) -> Result<Vec<ObjectPath>>;
 
-    // #[dbus_proxy(property)]
-    // fn id(&self) -> Result<Str>;
+    #[dbus_proxy(property)]
+    fn id(&self) -> Result<Str>;
 
     #[dbus_proxy(property)]
     fn type_(&self) -> Result<Str>;
@@ -59,14 +90,153 @@ trait ActiveConnectionDbus {
     interface = "org.freedesktop.NetworkManager.Device"
 )]
 trait DeviceDbus {
-    // #[dbus_proxy(property)]
-    // fn active_connection(&self) -> Result<ObjectPath>;
+    #[dbus_proxy(property)]
+    fn active_connection(&self) -> Result<ObjectPath>;
 
     #[dbus_proxy(property)]
     fn device_type(&self) -> Result<DeviceType>;
 
+    #[dbus_proxy(property)]
+    fn ifindex(&self) -> Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn interface(&self) -> Result<Str>;
+
+    #[dbus_proxy(property)]
+    fn ip4_config(&self) -> Result<ObjectPath>;
+
+    /// The result of the last IPv4 connectivity check on the device,
+    /// as an `NMConnectivityState`.
+    #[dbus_proxy(property)]
+    fn ip4_connectivity(&self) -> Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn ip6_config(&self) -> Result<ObjectPath>;
+
+    /// The result of the last IPv6 connectivity check on the device,
+    /// as an `NMConnectivityState`.
+    #[dbus_proxy(property)]
+    fn ip6_connectivity(&self) -> Result<u32>;
+
     #[dbus_proxy(property)]
     fn state(&self) -> Result<DeviceState>;
+
+    /// The current state and the `NMDeviceStateReason` for entering it.
+    #[dbus_proxy(property)]
+    fn state_reason(&self) -> Result<(u32, u32)>;
+
+    /// The unique identifier of the device.
+    /// For modems, this is the ModemManager object path.
+    #[dbus_proxy(property)]
+    fn udi(&self) -> Result<Str>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.IP4Config"
+)]
+trait Ip4ConfigDbus {
+    #[dbus_proxy(property)]
+    fn address_data(&self) -> Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.IP6Config"
+)]
+trait Ip6ConfigDbus {
+    #[dbus_proxy(property)]
+    fn address_data(&self) -> Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.Device.Statistics"
+)]
+trait DeviceStatisticsDbus {
+    #[dbus_proxy(property)]
+    fn refresh_rate_ms(&self) -> Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn set_refresh_rate_ms(&self, value: u32) -> Result<()>;
+
+    #[dbus_proxy(property)]
+    fn rx_bytes(&self) -> Result<u64>;
+
+    #[dbus_proxy(property)]
+    fn tx_bytes(&self) -> Result<u64>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.Device.Wired"
+)]
+trait DeviceWiredDbus {
+    /// The negotiated link speed in Mbit/s, or `0` if unknown.
+    #[dbus_proxy(property)]
+    fn speed(&self) -> Result<u32>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.Device.Wireless"
+)]
+trait DeviceWirelessDbus {
+    #[dbus_proxy(property)]
+    fn access_points(&self) -> Result<Vec<ObjectPath>>;
+
+    #[dbus_proxy(property)]
+    fn active_access_point(&self) -> Result<ObjectPath>;
+
+    /// The operating mode of the device, as an `NM80211Mode`.
+    #[dbus_proxy(property)]
+    fn mode(&self) -> Result<u32>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.AccessPoint"
+)]
+trait AccessPointDbus {
+    #[dbus_proxy(property)]
+    fn flags(&self) -> Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn hw_address(&self) -> Result<Str>;
+
+    #[dbus_proxy(property)]
+    fn rsn_flags(&self) -> Result<u32>;
+
+    #[dbus_proxy(property)]
+    fn ssid(&self) -> Result<Vec<u8>>;
+
+    #[dbus_proxy(property)]
+    fn strength(&self) -> Result<u8>;
+
+    #[dbus_proxy(property)]
+    fn wpa_flags(&self) -> Result<u32>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.Settings",
+    default_path = "/org/freedesktop/NetworkManager/Settings"
+)]
+trait SettingsDbus {
+    fn list_connections(&self) -> Result<Vec<OwnedObjectPath>>;
+
+    #[dbus_proxy(property)]
+    fn connections(&self) -> Result<Vec<OwnedObjectPath>>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.NetworkManager",
+    interface = "org.freedesktop.NetworkManager.Settings.Connection"
+)]
+trait SettingsConnectionDbus {
+    fn get_settings(&self) -> Result<ConnectionSettings>;
+
+    fn update(&self, properties: ConnectionSettings) -> Result<()>;
 }
 
 #[derive(Clone, Debug, OwnedValue, PartialEq)]
@@ -0,0 +1,192 @@
+use color_eyre::Result;
+use serde::Serialize;
+use tracing::debug;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{dbus_proxy, Connection};
+
+use crate::clients::networkmanager::dbus::{DeviceDbusProxy, DeviceState, DeviceType};
+use crate::clients::networkmanager::PathMap;
+
+/// The object path under which systemd-resolved exposes its links.
+pub(super) const RESOLVED_PATH: &str = "/org/freedesktop/resolve1";
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.resolve1",
+    interface = "org.freedesktop.resolve1.Manager",
+    default_path = "/org/freedesktop/resolve1"
+)]
+trait ResolvedDbus {
+    fn get_link(&self, ifindex: i32) -> Result<OwnedObjectPath>;
+
+    #[dbus_proxy(property, name = "DNSOverTLS")]
+    fn dns_over_tls(&self) -> Result<String>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.resolve1",
+    interface = "org.freedesktop.resolve1.Link"
+)]
+trait ResolvedLinkDbus {
+    #[dbus_proxy(property)]
+    fn default_route(&self) -> Result<bool>;
+
+    /// The configured servers, as address families against addresses.
+    #[dbus_proxy(property, name = "DNS")]
+    fn dns(&self) -> Result<Vec<(i32, Vec<u8>)>>;
+
+    /// The link's DNS-over-TLS mode,
+    /// or an empty string if it uses the global setting.
+    #[dbus_proxy(property, name = "DNSOverTLS")]
+    fn dns_over_tls(&self) -> Result<String>;
+
+    /// Whether DNSSEC validation is in effect for the link.
+    #[dbus_proxy(property, name = "DNSSECSupported")]
+    fn dnssec_supported(&self) -> Result<bool>;
+}
+
+/// The privacy of DNS queries which are not for a specific domain,
+/// as reported by systemd-resolved.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DnsState {
+    /// Whether every link used for queries requires DNS-over-TLS.
+    /// Opportunistic mode is not counted, as it silently falls back to plain DNS.
+    pub over_tls: bool,
+    /// Whether DNSSEC validation is in effect on every link used for queries.
+    pub dnssec: bool,
+    /// Whether a VPN is connected, but queries can be sent
+    /// over a link other than the VPN.
+    pub leak: bool,
+}
+
+/// A link which systemd-resolved may send queries over.
+#[derive(Debug)]
+struct DnsLink {
+    vpn: bool,
+    over_tls: bool,
+    dnssec: bool,
+}
+
+/// Gets the DNS state of the activated devices.
+///
+/// This is `None` if systemd-resolved is not running,
+/// or if no link has servers to send queries to.
+pub(super) async fn get_dns_state(
+    connection: &Connection,
+    devices: &PathMap<DeviceDbusProxy>,
+) -> Option<DnsState> {
+    read_dns_state(connection, devices)
+        .await
+        .unwrap_or_else(|err| {
+            debug!("Failed to read DNS state from systemd-resolved: {err:?}");
+            None
+        })
+}
+
+async fn read_dns_state(
+    connection: &Connection,
+    devices: &PathMap<DeviceDbusProxy>,
+) -> Result<Option<DnsState>> {
+    let resolved = ResolvedDbusProxy::new(connection).await?;
+    let global_over_tls = resolved.dns_over_tls().await?;
+
+    let mut links = vec![];
+    let mut vpn_connected = false;
+
+    for device in devices.values() {
+        if device.state().await? != DeviceState::Activated {
+            continue;
+        }
+
+        let vpn = is_vpn_device(&device.device_type().await?);
+        vpn_connected |= vpn;
+
+        let path = resolved.get_link(device.ifindex().await? as i32).await?;
+        let link = ResolvedLinkDbusProxy::builder(connection)
+            .path(path)?
+            .build()
+            .await?;
+
+        // links without the default route only answer queries for their own domains
+        if !link.default_route().await? || link.dns().await?.is_empty() {
+            continue;
+        }
+
+        let mut over_tls = link.dns_over_tls().await?;
+        if over_tls.is_empty() {
+            over_tls.clone_from(&global_over_tls);
+        }
+
+        links.push(DnsLink {
+            vpn,
+            over_tls: over_tls == "yes",
+            dnssec: link.dnssec_supported().await?,
+        });
+    }
+
+    Ok(summarise_links(&links, vpn_connected))
+}
+
+/// Whether a device carries a VPN tunnel.
+/// Plugin-based VPNs such as OpenVPN show up as externally managed tun devices.
+fn is_vpn_device(device_type: &DeviceType) -> bool {
+    matches!(
+        device_type,
+        DeviceType::Wireguard | DeviceType::Tun | DeviceType::IpTunnel
+    )
+}
+
+fn summarise_links(links: &[DnsLink], vpn_connected: bool) -> Option<DnsState> {
+    if links.is_empty() {
+        return None;
+    }
+
+    Some(DnsState {
+        over_tls: links.iter().all(|link| link.over_tls),
+        dnssec: links.iter().all(|link| link.dnssec),
+        leak: vpn_connected && links.iter().any(|link| !link.vpn),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn link(vpn: bool, over_tls: bool) -> DnsLink {
+        DnsLink {
+            vpn,
+            over_tls,
+            dnssec: false,
+        }
+    }
+
+    #[test]
+    fn test_summarise_no_links() {
+        assert_eq!(summarise_links(&[], true), None);
+    }
+
+    #[test]
+    fn test_summarise_over_tls() {
+        let state = summarise_links(&[link(false, true), link(false, true)], false)
+            .expect("should have state");
+        assert!(state.over_tls);
+        assert!(!state.leak);
+
+        let state = summarise_links(&[link(false, true), link(false, false)], false)
+            .expect("should have state");
+        assert!(!state.over_tls);
+    }
+
+    #[test]
+    fn test_summarise_leak() {
+        let state = summarise_links(&[link(true, false)], true).expect("should have state");
+        assert!(!state.leak);
+
+        let state = summarise_links(&[link(true, false), link(false, false)], true)
+            .expect("should have state");
+        assert!(state.leak);
+
+        // the VPN does not provide its own servers
+        let state = summarise_links(&[link(false, false)], true).expect("should have state");
+        assert!(state.leak);
+    }
+}
@@ -0,0 +1,105 @@
+use std::hash::{Hash, Hasher};
+
+use color_eyre::{Help, Report, Result};
+use regex::Regex;
+
+/// Decides which network interfaces the client tracks,
+/// so that virtual devices such as container bridges
+/// do not count towards the connection state.
+#[derive(Debug, Clone, Default)]
+pub struct InterfaceFilter {
+    ignore: Vec<Regex>,
+    only: Vec<Regex>,
+}
+
+impl InterfaceFilter {
+    /// Creates a filter which ignores interfaces matching any `ignore` pattern,
+    /// and if any `only` patterns are given, ignores interfaces matching none of them.
+    pub fn new(ignore: &[String], only: &[String]) -> Result<Self> {
+        Ok(Self {
+            ignore: compile(ignore)?,
+            only: compile(only)?,
+        })
+    }
+
+    /// Whether the filter has no patterns, so allows every interface.
+    pub fn is_empty(&self) -> bool {
+        self.ignore.is_empty() && self.only.is_empty()
+    }
+
+    /// Whether the interface with the given name should be left out of the state.
+    pub fn is_ignored(&self, interface: &str) -> bool {
+        self.ignore.iter().any(|regex| regex.is_match(interface))
+            || (!self.only.is_empty() && !self.only.iter().any(|regex| regex.is_match(interface)))
+    }
+
+    fn patterns(&self) -> (Vec<&str>, Vec<&str>) {
+        (
+            self.ignore.iter().map(Regex::as_str).collect(),
+            self.only.iter().map(Regex::as_str).collect(),
+        )
+    }
+}
+
+// clients are shared between modules with the same patterns
+impl PartialEq for InterfaceFilter {
+    fn eq(&self, other: &Self) -> bool {
+        self.patterns() == other.patterns()
+    }
+}
+
+impl Eq for InterfaceFilter {}
+
+impl Hash for InterfaceFilter {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.patterns().hash(state);
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Regex>> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            Regex::new(pattern).map_err(|err| {
+                Report::new(err)
+                    .wrap_err(format!("Invalid interface pattern '{pattern}'"))
+                    .suggestion("Check the pattern is a valid regular expression")
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_filter(ignore: &[&str], only: &[&str]) -> InterfaceFilter {
+        let strings =
+            |patterns: &[&str]| patterns.iter().map(ToString::to_string).collect::<Vec<_>>();
+        InterfaceFilter::new(&strings(ignore), &strings(only)).expect("valid patterns")
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let filter = create_filter(&["^docker", "^veth"], &[]);
+        assert!(filter.is_ignored("docker0"));
+        assert!(filter.is_ignored("veth1a2b3c"));
+        assert!(!filter.is_ignored("enp3s0"));
+
+        let filter = create_filter(&["^wlp0s20f0u"], &["^en", "^wl"]);
+        assert!(!filter.is_ignored("enp3s0"));
+        assert!(!filter.is_ignored("wlan0"));
+        assert!(filter.is_ignored("wlp0s20f0u1"));
+        assert!(filter.is_ignored("tun0"));
+    }
+
+    #[test]
+    fn test_default_allows_all() {
+        assert!(!InterfaceFilter::default().is_ignored("docker0"));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(InterfaceFilter::new(&["(".to_string()], &[]).is_err());
+    }
+}
@@ -0,0 +1,105 @@
+#[cfg(feature = "bar")]
+use color_eyre::Report;
+use color_eyre::Result;
+use serde::Serialize;
+#[cfg(feature = "bar")]
+use tracing::debug;
+#[cfg(feature = "bar")]
+use zbus::zvariant::ObjectPath;
+
+use crate::clients::networkmanager::dbus::{DeviceDbusProxy, DeviceState, DeviceType};
+#[cfg(feature = "bar")]
+use crate::clients::networkmanager::settings::list_saved_connections;
+use crate::clients::networkmanager::state::{active_connection_proxy, wireless_proxy};
+#[cfg(feature = "bar")]
+use crate::clients::networkmanager::Client;
+use crate::clients::networkmanager::PathMap;
+
+/// The `NM80211Mode` of a wifi device acting as an access point.
+const MODE_AP: u32 = 3;
+
+/// A wifi device running a hotspot, sharing its connection with other devices.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct HotspotState {
+    /// The name of the hotspot connection.
+    pub connection: String,
+    /// The name of the wifi interface the hotspot is running on.
+    pub interface: String,
+    /// The number of devices connected to the hotspot, as reported by the kernel.
+    /// This is `None` until it has been read, or if it cannot be.
+    pub clients: Option<usize>,
+}
+
+#[cfg(feature = "bar")]
+impl Client {
+    /// Starts or stops the saved hotspot connection with the given name.
+    pub async fn set_hotspot_active(&self, connection: &str, active: bool) -> Result<()> {
+        let path = list_saved_connections(&self.0.dbus_connection)
+            .await?
+            .into_iter()
+            .find(|saved| saved.id == connection)
+            .map(|saved| saved.path)
+            .ok_or_else(|| Report::msg(format!("No saved connection named '{connection}'")))?;
+
+        let path = ObjectPath::try_from(path.as_str())?;
+
+        if active {
+            debug!("Starting hotspot '{connection}'");
+
+            // NetworkManager picks a wifi device which supports AP mode
+            let none = ObjectPath::from_static_str_unchecked("/");
+            self.0
+                .root_object
+                .activate_connection(&path, &none, &none)
+                .await?;
+        } else {
+            debug!("Stopping hotspot '{connection}'");
+
+            for (active_path, active) in self.0.active_connections() {
+                if active.connection().await.is_ok_and(|saved| saved == path) {
+                    self.0
+                        .root_object
+                        .deactivate_connection(&active_path)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether a wifi device is acting as an access point.
+pub(super) async fn is_hotspot(device: &DeviceDbusProxy) -> Result<bool> {
+    Ok(wireless_proxy(device).await?.mode().await? == MODE_AP)
+}
+
+/// Finds the wifi device running a hotspot, if any.
+///
+/// The number of clients is not read here,
+/// as it comes from the kernel rather than NetworkManager.
+pub(super) async fn determine_hotspot_state(
+    devices: &PathMap<DeviceDbusProxy>,
+) -> Result<Option<HotspotState>> {
+    for device in devices.values() {
+        if device.device_type().await? != DeviceType::Wifi
+            || device.state().await? != DeviceState::Activated
+            || !is_hotspot(device).await?
+        {
+            continue;
+        }
+
+        let connection = match active_connection_proxy(device).await? {
+            Some(active_connection) => active_connection.id().await?.to_string(),
+            None => continue,
+        };
+
+        return Ok(Some(HotspotState {
+            connection,
+            interface: device.interface().await?.to_string(),
+            clients: None,
+        }));
+    }
+
+    Ok(None)
+}
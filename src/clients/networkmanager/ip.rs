@@ -0,0 +1,151 @@
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::net::Ipv6Addr;
+
+use color_eyre::Result;
+use serde::Serialize;
+use tracing::debug;
+use zbus::zvariant::OwnedValue;
+
+use crate::clients::networkmanager::dbus::{
+    DeviceDbusProxy, Ip4ConfigDbusProxy, Ip6ConfigDbusProxy,
+};
+
+/// An IP address assigned to a device, and its network prefix length.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct IpAddress {
+    pub address: String,
+    pub prefix: u32,
+}
+
+impl Display for IpAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix)
+    }
+}
+
+/// Gets the first IPv4 address assigned to a device.
+///
+/// Failing to read the address does not prevent the device state being determined,
+/// as the IP configuration can be briefly missing while the device changes state.
+pub(super) async fn get_ip4_address(device: &DeviceDbusProxy) -> Option<IpAddress> {
+    read_ip4_address(device).await.unwrap_or_else(|err| {
+        debug!("Failed to read IPv4 address: {err:?}");
+        None
+    })
+}
+
+/// Gets the IPv6 address assigned to a device,
+/// preferring routable addresses over link-local ones.
+pub(super) async fn get_ip6_address(device: &DeviceDbusProxy) -> Option<IpAddress> {
+    read_ip6_address(device).await.unwrap_or_else(|err| {
+        debug!("Failed to read IPv6 address: {err:?}");
+        None
+    })
+}
+
+async fn read_ip4_address(device: &DeviceDbusProxy) -> Result<Option<IpAddress>> {
+    let path = device.ip4_config().await?;
+    if path.as_str() == "/" {
+        return Ok(None);
+    }
+
+    let config = Ip4ConfigDbusProxy::builder(device.inner().connection())
+        .path(path.into_owned())?
+        .build()
+        .await?;
+
+    Ok(parse_address_data(config.address_data().await?).next())
+}
+
+async fn read_ip6_address(device: &DeviceDbusProxy) -> Result<Option<IpAddress>> {
+    let path = device.ip6_config().await?;
+    if path.as_str() == "/" {
+        return Ok(None);
+    }
+
+    let config = Ip6ConfigDbusProxy::builder(device.inner().connection())
+        .path(path.into_owned())?
+        .build()
+        .await?;
+
+    Ok(preferred_ip6_address(parse_address_data(
+        config.address_data().await?,
+    )))
+}
+
+/// Reads the addresses from an IP config's `AddressData` property.
+fn parse_address_data(data: Vec<HashMap<String, OwnedValue>>) -> impl Iterator<Item = IpAddress> {
+    data.into_iter().filter_map(|data| {
+        let address = data
+            .get("address")
+            .and_then(|value| String::try_from(value.clone()).ok())?;
+        let prefix = data
+            .get("prefix")
+            .and_then(|value| u32::try_from(value).ok())?;
+
+        Some(IpAddress { address, prefix })
+    })
+}
+
+/// Picks the first address which is not link-local,
+/// as every IPv6 device has a link-local address regardless of the network.
+/// Falls back to the link-local address if there is nothing else.
+fn preferred_ip6_address(addresses: impl Iterator<Item = IpAddress>) -> Option<IpAddress> {
+    let mut link_local = None;
+
+    for address in addresses {
+        if is_link_local(&address.address) {
+            link_local.get_or_insert(address);
+        } else {
+            return Some(address);
+        }
+    }
+
+    link_local
+}
+
+/// Whether the address is in `fe80::/10`.
+fn is_link_local(address: &str) -> bool {
+    address
+        .parse::<Ipv6Addr>()
+        .is_ok_and(|address| address.segments()[0] & 0xffc0 == 0xfe80)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(address: &str) -> IpAddress {
+        IpAddress {
+            address: address.to_string(),
+            prefix: 64,
+        }
+    }
+
+    #[test]
+    fn test_preferred_ip6_address() {
+        let addresses = [address("fe80::1"), address("2001:db8::1")];
+        assert_eq!(
+            preferred_ip6_address(addresses.into_iter()),
+            Some(address("2001:db8::1"))
+        );
+
+        let addresses = [address("fe80::1")];
+        assert_eq!(
+            preferred_ip6_address(addresses.into_iter()),
+            Some(address("fe80::1"))
+        );
+
+        assert_eq!(preferred_ip6_address(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn test_is_link_local() {
+        assert!(is_link_local("fe80::1"));
+        assert!(is_link_local("febf::1"));
+        assert!(!is_link_local("fec0::1"));
+        assert!(!is_link_local("2001:db8::1"));
+        assert!(!is_link_local("192.168.1.1"));
+    }
+}
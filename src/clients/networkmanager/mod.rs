@@ -1,235 +1,1049 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 
+use cfg_if::cfg_if;
 use color_eyre::Result;
-use futures_signals::signal::{Mutable, MutableSignalCloned};
-use tracing::error;
-use zbus::blocking::Connection;
-use zbus::zvariant::ObjectPath;
+use futures_lite::StreamExt;
+use futures_signals::signal::Mutable;
+use tokio::time::sleep;
+use tracing::{debug, error};
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{Connection, MatchRule, MessageStream, MessageType};
 
 use crate::clients::networkmanager::dbus::{
-    ActiveConnectionDbusProxyBlocking, DbusProxyBlocking, DeviceDbusProxyBlocking,
+    ActiveConnectionDbusProxy, DbusProxy, DeviceDbusProxy, DeviceStatisticsDbusProxy, DeviceType,
+    SettingsDbusProxy,
 };
+use crate::clients::networkmanager::dns::{get_dns_state, RESOLVED_PATH};
+use crate::clients::networkmanager::filter::InterfaceFilter;
+use crate::clients::networkmanager::hotspot::determine_hotspot_state;
+use crate::clients::networkmanager::modem::MODEM_MANAGER_PATH;
+use crate::clients::networkmanager::nl80211::count_stations;
+use crate::clients::networkmanager::speedtest::SpeedTest;
 use crate::clients::networkmanager::state::{
-    determine_cellular_state, determine_vpn_state, determine_wifi_state, determine_wired_state,
-    CellularState, State, VpnState, WifiState, WiredState,
+    determine_cellular_state, determine_connectivity, determine_interfaces, determine_vpn_state,
+    determine_wifi_networks, determine_wifi_state, determine_wired_state, wireless_proxy,
+    CellularState, Connectivity, InterfaceKind, InterfaceStatus, State, VpnState, WifiState,
+    WiredState,
 };
-use crate::{
-    read_lock, register_fallible_client, spawn_blocking, spawn_blocking_result, write_lock,
+use crate::clients::networkmanager::statistics::{
+    determine_data_usage, Counters, Statistics, Throughput, REFRESH_RATE_MS,
 };
+use crate::clients::networkmanager::vpn::determine_vpn_connections;
+use crate::{read_lock, register_fallible_client, spawn, spawn_blocking, write_lock};
 
+cfg_if! {
+    if #[cfg(feature = "bar")] {
+        use std::fs::{self, File};
+        use std::io::Write;
+        use std::path::Path;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::sync::{Mutex, Weak};
+
+        use chrono::{Local, NaiveDate};
+        use futures_signals::signal::{MutableSignalCloned, SignalExt};
+
+        use crate::clients::networkmanager::nl80211::get_station_info;
+        use crate::clients::networkmanager::quota::{QuotaLevel, UsageHistory, UsageRecorder};
+        use crate::clients::networkmanager::roaming::{NetworkHistory, RoamRecorder};
+        use crate::clients::networkmanager::speedtest::run_speed_test;
+        use crate::lock;
+    }
+}
+
+#[cfg(feature = "bar")]
+mod connect;
 mod dbus;
+pub mod dns;
+pub mod filter;
+pub mod hotspot;
+pub mod ip;
+pub mod modem;
+pub mod nl80211;
+#[cfg(feature = "bar")]
+pub mod quota;
+#[cfg(feature = "bar")]
+mod radio;
+mod rfkill;
+#[cfg(feature = "bar")]
+pub mod roaming;
+pub mod settings;
+pub mod speedtest;
 pub mod state;
+pub mod statistics;
+pub mod vpn;
+
+type PathMap<ValueType> = HashMap<OwnedObjectPath, ValueType>;
+
+/// The minimum time between saving the data usage and wifi network histories.
+#[cfg(feature = "bar")]
+const HISTORY_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Clients keeping a data usage or wifi network history,
+/// which are saved by [`save_histories`] on shutdown.
+#[cfg(feature = "bar")]
+static HISTORY_CLIENTS: Mutex<Vec<Weak<ClientInner>>> = Mutex::new(Vec::new());
+
+/// The time between reading the wifi station info from the kernel.
+#[cfg(feature = "bar")]
+const STATION_INFO_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The time between counting the devices connected to a hotspot.
+const HOTSPOT_CLIENTS_INTERVAL: Duration = Duration::from_secs(5);
 
-type PathMap<'l, ValueType> = HashMap<ObjectPath<'l>, ValueType>;
+/// The time to wait before restarting a failed watcher.
+const WATCHER_RESTART_DELAY: Duration = Duration::from_secs(5);
 
 #[derive(Debug)]
-pub struct Client(Arc<ClientInner<'static>>);
+pub struct Client(Arc<ClientInner>);
 
 #[derive(Debug)]
-struct ClientInner<'l> {
+struct ClientInner {
     state: Mutable<State>,
-    root_object: &'l DbusProxyBlocking<'l>,
-    active_connections: RwLock<PathMap<'l, ActiveConnectionDbusProxyBlocking<'l>>>,
-    devices: RwLock<PathMap<'l, DeviceDbusProxyBlocking<'l>>>,
+    root_object: DbusProxy<'static>,
+    active_connections: RwLock<PathMap<ActiveConnectionDbusProxy<'static>>>,
+    devices: RwLock<PathMap<DeviceDbusProxy<'static>>>,
+    statistics: RwLock<Statistics>,
     dbus_connection: Connection,
+    /// Devices with interfaces excluded by the filter are left out of the state.
+    filter: InterfaceFilter,
+    #[cfg(feature = "bar")]
+    usage_history: Mutable<UsageHistory>,
+    #[cfg(feature = "bar")]
+    tracking_usage: AtomicBool,
+    #[cfg(feature = "bar")]
+    network_history: Mutable<NetworkHistory>,
+    #[cfg(feature = "bar")]
+    tracking_network_history: AtomicBool,
+    #[cfg(feature = "bar")]
+    tracking_station_info: AtomicBool,
+    /// The highest quota level warned about for each connection,
+    /// and the billing period it was in.
+    #[cfg(feature = "bar")]
+    quota_warnings: Mutex<HashMap<String, (NaiveDate, QuotaLevel)>>,
 }
 
 impl Client {
-    fn new() -> Result<Client> {
+    async fn new(filter: InterfaceFilter) -> Result<Client> {
         let state = Mutable::new(State {
             wired: WiredState::Unknown,
             wifi: WifiState::Unknown,
             cellular: CellularState::Unknown,
             vpn: VpnState::Unknown,
+            interfaces: vec![],
+            vpn_connections: vec![],
+            wifi_networks: vec![],
+            data_usage: vec![],
+            throughput: Throughput::default(),
+            connectivity: Connectivity::Unknown,
+            dns: None,
+            speed_test: SpeedTest::default(),
+            airplane_mode: None,
+            wifi_station: None,
+            hotspot: None,
         });
-        let dbus_connection = Connection::system()?;
-        let root_object = {
-            let root_object = DbusProxyBlocking::new(&dbus_connection)?;
-            // Workaround for the fact that zbus (unnecessarily) requires a static lifetime here
-            Box::leak(Box::new(root_object))
-        };
+        let dbus_connection = Box::pin(Connection::system()).await?;
+        let root_object = DbusProxy::new(&dbus_connection).await?;
 
         Ok(Client(Arc::new(ClientInner {
             state,
             root_object,
             active_connections: RwLock::new(HashMap::new()),
             devices: RwLock::new(HashMap::new()),
+            statistics: RwLock::new(Statistics::default()),
             dbus_connection,
+            filter,
+            #[cfg(feature = "bar")]
+            usage_history: Mutable::new(UsageHistory::default()),
+            #[cfg(feature = "bar")]
+            tracking_usage: AtomicBool::new(false),
+            #[cfg(feature = "bar")]
+            network_history: Mutable::new(NetworkHistory::default()),
+            #[cfg(feature = "bar")]
+            tracking_network_history: AtomicBool::new(false),
+            #[cfg(feature = "bar")]
+            tracking_station_info: AtomicBool::new(false),
+            #[cfg(feature = "bar")]
+            quota_warnings: Mutex::new(HashMap::new()),
         })))
     }
 
-    fn run(&self) -> Result<()> {
-        macro_rules! update_state_for_device_change {
-            ($client:ident) => {
-                $client.state.set(State {
-                    wired: determine_wired_state(&read_lock!($client.devices))?,
-                    wifi: determine_wifi_state(&read_lock!($client.devices))?,
-                    cellular: determine_cellular_state(&read_lock!($client.devices))?,
-                    vpn: $client.state.get_cloned().vpn,
-                });
-            };
+    async fn run(&self) -> Result<()> {
+        let client = &self.0;
+
+        let mut active_connections = HashMap::new();
+        for path in client.root_object.active_connections().await? {
+            let proxy = client.active_connection_proxy(path.clone()).await?;
+            if !client.is_ignored_connection(&proxy).await? {
+                active_connections.insert(path, proxy);
+            }
         }
+        *write_lock!(client.active_connections) = active_connections;
+
+        let mut devices = HashMap::new();
+        for path in client.root_object.devices().await? {
+            let proxy = client.device_proxy(path.clone()).await?;
+            if !client.is_ignored_device(&proxy).await? {
+                devices.insert(path, proxy);
+            }
+        }
+        *write_lock!(client.devices) = devices;
+
+        for device in client.devices().into_values() {
+            client.spawn_device_watchers(device);
+        }
+
+        let devices = client.devices();
+        let active_connections = client.active_connections();
+
+        client.state.set(State {
+            wired: determine_wired_state(&devices).await?,
+            wifi: determine_wifi_state(&devices, client.root_object.wireless_enabled().await?)
+                .await?,
+            cellular: determine_cellular_state(&devices).await?,
+            vpn: determine_vpn_state(&active_connections).await?,
+            interfaces: determine_interfaces(&devices).await?,
+            vpn_connections: determine_vpn_connections(
+                &client.dbus_connection,
+                &active_connections,
+            )
+            .await?,
+            wifi_networks: determine_wifi_networks(&devices).await?,
+            data_usage: determine_data_usage(&active_connections, &client.statistics).await?,
+            throughput: client.state.get_cloned().throughput,
+            connectivity: determine_connectivity(&client.root_object).await?,
+            dns: get_dns_state(&client.dbus_connection, &devices).await,
+            speed_test: client.state.get_cloned().speed_test,
+            airplane_mode: client.state.get_cloned().airplane_mode,
+            wifi_station: client.state.get_cloned().wifi_station,
+            hotspot: determine_hotspot_state(&devices).await?,
+        });
+
+        client.spawn_watcher(ClientInner::watch_active_connections);
+        client.spawn_watcher(ClientInner::watch_devices);
+        client.spawn_watcher(ClientInner::watch_wireless_enabled);
+        client.spawn_watcher(ClientInner::watch_connectivity);
+        client.spawn_watcher(ClientInner::watch_saved_connections);
+        client.spawn_watcher(ClientInner::watch_dns);
+        client.spawn_watcher(ClientInner::watch_modems);
+        client.spawn_watcher(ClientInner::watch_throughput);
+        client.spawn_watcher(ClientInner::watch_rfkill);
+        client.spawn_watcher(ClientInner::watch_hotspot_clients);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "bar")]
+    pub fn subscribe(&self) -> MutableSignalCloned<State> {
+        self.0.state.signal_cloned()
+    }
+
+    #[cfg(feature = "bar")]
+    pub fn subscribe_usage_history(&self) -> MutableSignalCloned<UsageHistory> {
+        self.0.usage_history.signal_cloned()
+    }
+
+    /// Starts keeping a daily history of the data used by each connection,
+    /// for tracking usage against quotas.
+    ///
+    /// The history is loaded from and periodically saved to the state directory,
+    /// and saved again on shutdown.
+    /// This only starts tracking once, regardless of how many times it is called.
+    #[cfg(feature = "bar")]
+    pub fn track_usage(&self) {
+        if self.0.tracking_usage.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        self.save_history_on_shutdown();
+
+        let path = quota::history_path();
+        if let Some(path) = &path {
+            self.0.usage_history.set(UsageHistory::load(path));
+        }
+
+        let client = self.0.clone();
+        spawn(async move {
+            let mut states = client.state.signal_cloned().to_stream();
+            let mut recorder = UsageRecorder::default();
+            let mut last_save = Instant::now();
 
-        macro_rules! initialise_path_map {
-            (
-                $client:expr,
-                $path_map:ident,
-                $proxy_type:ident
-                $(, |$new_path:ident| $property_watcher:expr)*
-            ) => {
-                let new_paths = $client.root_object.$path_map()?;
-                let mut path_map = HashMap::new();
-                for new_path in new_paths {
-                    let new_proxy = $proxy_type::builder(&$client.dbus_connection)
-                        .path(new_path.clone())?
-                        .build()?;
-                    path_map.insert(new_path.clone(), new_proxy);
-                    $({
-                        let $new_path = &new_path;
-                        $property_watcher;
-                    })*
+            while let Some(state) = states.next().await {
+                let mut history = client.usage_history.get_cloned();
+                let today = Local::now().date_naive();
+
+                if !recorder.record(&mut history, &state.data_usage, today) {
+                    continue;
                 }
-                *write_lock!($client.$path_map) = path_map;
-            };
+
+                if let Some(path) = &path {
+                    if last_save.elapsed() >= HISTORY_SAVE_INTERVAL {
+                        debug!("Saving data usage history to '{}'", path.display());
+
+                        if let Err(err) = history.save(path) {
+                            error!("{:?}", err.wrap_err("Failed to save data usage history"));
+                        }
+
+                        last_save = Instant::now();
+                    }
+                }
+
+                client.usage_history.set(history);
+            }
+        });
+    }
+
+    #[cfg(feature = "bar")]
+    pub fn subscribe_network_history(&self) -> MutableSignalCloned<NetworkHistory> {
+        self.0.network_history.signal_cloned()
+    }
+
+    /// Starts keeping a history of the wifi networks connected to,
+    /// and the access points and signal strengths seen on each.
+    ///
+    /// The history is loaded from the state directory,
+    /// and saved whenever a network is connected or roamed between access points,
+    /// as well as on shutdown.
+    /// This only starts tracking once, regardless of how many times it is called.
+    #[cfg(feature = "bar")]
+    pub fn track_network_history(&self) {
+        if self
+            .0
+            .tracking_network_history
+            .swap(true, Ordering::Relaxed)
+        {
+            return;
         }
 
-        macro_rules! spawn_path_list_watcher {
-            (
-                $client:expr,
-                $property:ident,
-                $property_changes:ident,
-                $proxy_type:ident,
-                |$state_client:ident| $state_update:expr
-                $(, |$property_client:ident, $new_path:ident| $property_watcher:expr)*
-            ) => {
-                let client = $client.clone();
-                spawn_blocking_result!({
-                    let changes = client.root_object.$property_changes();
-                    for _ in changes {
-                        let mut new_path_map = HashMap::new();
-                        {
-                            let new_paths = client.root_object.$property()?;
-                            let path_map = read_lock!(client.$property);
-                            for new_path in new_paths {
-                                if path_map.contains_key(&new_path) {
-                                    let proxy = path_map
-                                        .get(&new_path)
-                                        .expect("Should contain the key, guarded by runtime check");
-                                    new_path_map.insert(new_path, proxy.to_owned());
-                                } else {
-                                    let new_proxy = $proxy_type::builder(&client.dbus_connection)
-                                        .path(new_path.clone())?
-                                        .build()?;
-                                    new_path_map.insert(new_path.clone(), new_proxy);
-                                    $({
-                                        let $property_client = &client;
-                                        let $new_path = &new_path;
-                                        $property_watcher;
-                                    })*
-                                }
-                            }
+        self.save_history_on_shutdown();
+
+        let path = roaming::history_path();
+        if let Some(path) = &path {
+            self.0.network_history.set(NetworkHistory::load(path));
+        }
+
+        let client = self.0.clone();
+        spawn(async move {
+            let mut states = client.state.signal_cloned().to_stream();
+            let mut recorder = RoamRecorder::default();
+            let mut last_save = Instant::now();
+
+            while let Some(state) = states.next().await {
+                let mut history = client.network_history.get_cloned();
+                let now = Local::now().timestamp();
+
+                let counts = |history: &NetworkHistory| {
+                    state
+                        .wifi
+                        .connected()
+                        .and_then(|wifi| history.get(&wifi.ssid))
+                        .map(|network| (network.connections, network.roams))
+                };
+
+                let previous = counts(&history);
+                if !recorder.record(&mut history, &state.wifi, now) {
+                    continue;
+                }
+
+                // signal strength changes are only saved periodically,
+                // but connections and roams are saved straight away
+                let roamed = counts(&history) != previous;
+
+                if let Some(path) = &path {
+                    if roamed || last_save.elapsed() >= HISTORY_SAVE_INTERVAL {
+                        debug!("Saving wifi network history to '{}'", path.display());
+
+                        if let Err(err) = history.save(path) {
+                            error!("{:?}", err.wrap_err("Failed to save wifi network history"));
                         }
-                        *write_lock!(client.$property) = new_path_map;
-                        let $state_client = &client;
-                        $state_update;
+
+                        last_save = Instant::now();
                     }
-                    Ok(())
-                });
-            }
-        }
-
-        macro_rules! spawn_property_watcher {
-            (
-                $client:expr,
-                $path:expr,
-                $property_changes:ident,
-                $containing_list:ident,
-                |$inner_client:ident| $state_update:expr
-            ) => {
-                let client = $client.clone();
-                let path = $path.clone();
-                spawn_blocking_result!({
-                    let changes = read_lock!(client.$containing_list)
-                        .get(&path)
-                        .expect("Should contain the key upon watcher start")
-                        .$property_changes();
-                    for _ in changes {
-                        if !read_lock!(client.$containing_list).contains_key(&path) {
-                            break;
+                }
+
+                client.network_history.set(history);
+            }
+        });
+    }
+
+    /// Adds the client to those whose histories are saved on shutdown,
+    /// unless it is already added.
+    #[cfg(feature = "bar")]
+    fn save_history_on_shutdown(&self) {
+        let mut clients = lock!(HISTORY_CLIENTS);
+        if !clients
+            .iter()
+            .any(|client| client.as_ptr() == Arc::as_ptr(&self.0))
+        {
+            clients.push(Arc::downgrade(&self.0));
+        }
+    }
+
+    /// Starts reading the signal strength and bitrates of the connected wifi network
+    /// from the kernel using nl80211, keeping them in the state.
+    ///
+    /// This only starts reading once, regardless of how many times it is called.
+    #[cfg(feature = "bar")]
+    pub fn track_station_info(&self) {
+        if self.0.tracking_station_info.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        self.0.spawn_watcher(ClientInner::watch_station_info);
+    }
+
+    /// Records that a quota has reached `level` during the billing period starting `period`.
+    ///
+    /// Returns `true` if this is higher than any level previously reached in the period,
+    /// meaning a warning should be shown.
+    /// This is shared between all modules, so each warning is only shown once.
+    #[cfg(feature = "bar")]
+    pub fn raise_quota_level(
+        &self,
+        connection: &str,
+        period: NaiveDate,
+        level: QuotaLevel,
+    ) -> bool {
+        let mut warnings = lock!(self.0.quota_warnings);
+
+        let previous = warnings
+            .get(connection)
+            .filter(|(previous_period, _)| *previous_period == period)
+            .map_or(QuotaLevel::Normal, |(_, level)| *level);
+
+        if level > previous {
+            warnings.insert(connection.to_string(), (period, level));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Runs a speed test using the given shell command,
+    /// keeping the result in the state.
+    ///
+    /// This resolves once the test completes.
+    /// Only one test runs at a time, so this does nothing if a test is already running.
+    #[cfg(feature = "bar")]
+    pub async fn run_speed_test(&self, command: &str) -> Result<()> {
+        {
+            let mut state = self.0.state.lock_mut();
+            if state.speed_test.running {
+                return Ok(());
+            }
+            state.speed_test.running = true;
+        }
+
+        let result = run_speed_test(command).await;
+
+        let mut state = self.0.state.lock_mut();
+        state.speed_test.running = false;
+
+        state.speed_test.last_result = Some(result?);
+
+        Ok(())
+    }
+}
+
+impl ClientInner {
+    /// Gets the active connection proxies.
+    /// This is a copy, so can be held across awaits.
+    fn active_connections(&self) -> PathMap<ActiveConnectionDbusProxy<'static>> {
+        read_lock!(self.active_connections).clone()
+    }
+
+    /// Gets the device proxies.
+    /// This is a copy, so can be held across awaits.
+    fn devices(&self) -> PathMap<DeviceDbusProxy<'static>> {
+        read_lock!(self.devices).clone()
+    }
+
+    fn has_device(&self, path: &OwnedObjectPath) -> bool {
+        read_lock!(self.devices).contains_key(path)
+    }
+
+    async fn active_connection_proxy(
+        &self,
+        path: OwnedObjectPath,
+    ) -> Result<ActiveConnectionDbusProxy<'static>> {
+        let proxy = ActiveConnectionDbusProxy::builder(&self.dbus_connection)
+            .path(path)?
+            .build()
+            .await?;
+
+        Ok(proxy)
+    }
+
+    async fn device_proxy(&self, path: OwnedObjectPath) -> Result<DeviceDbusProxy<'static>> {
+        let proxy = DeviceDbusProxy::builder(&self.dbus_connection)
+            .path(path)?
+            .build()
+            .await?;
+
+        Ok(proxy)
+    }
+
+    /// Whether the device's interface is excluded by the filter.
+    async fn is_ignored_device(&self, device: &DeviceDbusProxy<'_>) -> Result<bool> {
+        if self.filter.is_empty() {
+            return Ok(false);
+        }
+
+        let interface = device.interface().await?;
+        let ignored = self.filter.is_ignored(&interface);
+        if ignored {
+            debug!("Ignoring device '{interface}'");
+        }
+
+        Ok(ignored)
+    }
+
+    /// Whether every device carrying an active connection is excluded by the filter,
+    /// such as a bridge created by a container runtime.
+    async fn is_ignored_connection(
+        &self,
+        connection: &ActiveConnectionDbusProxy<'_>,
+    ) -> Result<bool> {
+        if self.filter.is_empty() {
+            return Ok(false);
+        }
+
+        let devices = connection.devices().await?;
+        if devices.is_empty() {
+            return Ok(false);
+        }
+
+        for path in devices {
+            let device = self.device_proxy(path.into()).await?;
+            if !self.is_ignored_device(&device).await? {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Updates the parts of the state which come from the devices.
+    async fn update_device_state(&self) -> Result<()> {
+        let devices = self.devices();
+
+        let wired = determine_wired_state(&devices).await?;
+        let wifi =
+            determine_wifi_state(&devices, self.root_object.wireless_enabled().await?).await?;
+        let cellular = determine_cellular_state(&devices).await?;
+        let interfaces = determine_interfaces(&devices).await?;
+        let wifi_networks = determine_wifi_networks(&devices).await?;
+        let dns = get_dns_state(&self.dbus_connection, &devices).await;
+        let mut hotspot = determine_hotspot_state(&devices).await?;
+
+        let mut state = self.state.lock_mut();
+
+        // the client count is polled separately, so is kept until the next read
+        if let (Some(hotspot), Some(previous)) = (&mut hotspot, &state.hotspot) {
+            if hotspot.interface == previous.interface {
+                hotspot.clients = previous.clients;
+            }
+        }
+
+        state.wired = wired;
+        state.wifi = wifi;
+        state.cellular = cellular;
+        state.interfaces = interfaces;
+        state.wifi_networks = wifi_networks;
+        state.dns = dns;
+        state.hotspot = hotspot;
+
+        Ok(())
+    }
+
+    /// Updates the parts of the state which come from the active connections.
+    async fn update_active_connection_state(&self) -> Result<()> {
+        let active_connections = self.active_connections();
+
+        let vpn = determine_vpn_state(&active_connections).await?;
+        let vpn_connections =
+            determine_vpn_connections(&self.dbus_connection, &active_connections).await?;
+        let data_usage = determine_data_usage(&active_connections, &self.statistics).await?;
+
+        let mut state = self.state.lock_mut();
+        state.vpn = vpn;
+        state.vpn_connections = vpn_connections;
+        state.data_usage = data_usage;
+
+        Ok(())
+    }
+
+    /// Spawns a task to run a watcher.
+    ///
+    /// If the watcher fails, the error is logged and it is restarted after a delay,
+    /// as most failures are temporary D-Bus errors.
+    /// The watcher stops for good once it returns `Ok`.
+    fn spawn_watcher<F, Fut>(self: &Arc<Self>, watcher: F)
+    where
+        F: Fn(Arc<Self>) -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let client = self.clone();
+
+        spawn(async move {
+            while let Err(err) = watcher(client.clone()).await {
+                error!(
+                    "{:?}",
+                    err.wrap_err("NetworkManager watcher failed, restarting")
+                );
+                sleep(WATCHER_RESTART_DELAY).await;
+            }
+        });
+    }
+
+    fn spawn_device_watchers(self: &Arc<Self>, device: DeviceDbusProxy<'static>) {
+        {
+            let device = device.clone();
+            self.spawn_watcher(move |client| client.watch_device_state(device.clone()));
+        }
+        {
+            let device = device.clone();
+            self.spawn_watcher(move |client| client.watch_access_points(device.clone()));
+        }
+        self.spawn_watcher(move |client| client.watch_statistics(device.clone()));
+    }
+
+    async fn watch_active_connections(self: Arc<Self>) -> Result<()> {
+        let mut changes = self.root_object.receive_active_connections_changed().await;
+
+        while changes.next().await.is_some() {
+            let current = self.active_connections();
+
+            let mut active_connections = HashMap::new();
+            for path in self.root_object.active_connections().await? {
+                let proxy = match current.get(&path) {
+                    Some(proxy) => proxy.clone(),
+                    None => {
+                        let proxy = self.active_connection_proxy(path.clone()).await?;
+                        if self.is_ignored_connection(&proxy).await? {
+                            continue;
                         }
-                        let $inner_client = &client;
-                        $state_update;
+                        proxy
                     }
-                    Ok(())
-                });
+                };
+
+                active_connections.insert(path, proxy);
+            }
+
+            *write_lock!(self.active_connections) = active_connections;
+            self.update_active_connection_state().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn watch_devices(self: Arc<Self>) -> Result<()> {
+        let mut changes = self.root_object.receive_devices_changed().await;
+
+        while changes.next().await.is_some() {
+            let current = self.devices();
+
+            let mut devices = HashMap::new();
+            let mut added = vec![];
+            for path in self.root_object.devices().await? {
+                let proxy = match current.get(&path) {
+                    Some(proxy) => proxy.clone(),
+                    None => {
+                        let proxy = self.device_proxy(path.clone()).await?;
+                        if self.is_ignored_device(&proxy).await? {
+                            continue;
+                        }
+                        added.push(proxy.clone());
+                        proxy
+                    }
+                };
+
+                devices.insert(path, proxy);
+            }
+
+            *write_lock!(self.devices) = devices;
+
+            // watchers stop once their device is removed,
+            // so are only started after it is in the list.
+            for device in added {
+                self.spawn_device_watchers(device);
+            }
+
+            self.update_device_state().await?;
+        }
+
+        Ok(())
+    }
+
+    async fn watch_device_state(self: Arc<Self>, device: DeviceDbusProxy<'static>) -> Result<()> {
+        let path = OwnedObjectPath::from(device.inner().path().to_owned());
+        if !self.has_device(&path) {
+            return Ok(());
+        }
+
+        let mut changes = device.receive_state_changed().await;
+
+        while changes.next().await.is_some() {
+            if !self.has_device(&path) {
+                break;
+            }
+
+            self.update_device_state().await?;
+        }
+
+        Ok(())
+    }
+
+    /// The list of access points lives on a separate interface to the device,
+    /// so is watched separately to the device properties.
+    async fn watch_access_points(self: Arc<Self>, device: DeviceDbusProxy<'static>) -> Result<()> {
+        let path = OwnedObjectPath::from(device.inner().path().to_owned());
+        if !self.has_device(&path) || device.device_type().await? != DeviceType::Wifi {
+            return Ok(());
+        }
+
+        let wireless = wireless_proxy(&device).await?;
+        let mut changes = wireless.receive_access_points_changed().await;
+
+        while changes.next().await.is_some() {
+            if !self.has_device(&path) {
+                break;
+            }
+
+            let devices = self.devices();
+            let wifi_networks = determine_wifi_networks(&devices).await?;
+            // the access points are rescanned periodically,
+            // which also updates the connected network's signal strength
+            let wifi =
+                determine_wifi_state(&devices, self.root_object.wireless_enabled().await?).await?;
+
+            let mut state = self.state.lock_mut();
+            state.wifi_networks = wifi_networks;
+            state.wifi = wifi;
+        }
+
+        Ok(())
+    }
+
+    /// NetworkManager only emits byte counter changes
+    /// while a refresh rate is set on the device.
+    async fn watch_statistics(self: Arc<Self>, device: DeviceDbusProxy<'static>) -> Result<()> {
+        let path = OwnedObjectPath::from(device.inner().path().to_owned());
+        if !self.has_device(&path) {
+            return Ok(());
+        }
+
+        // loopback traffic never leaves the machine,
+        // so is not counted towards usage or throughput
+        let device_type = device.device_type().await?;
+        if device_type == DeviceType::Loopback {
+            return Ok(());
+        }
+
+        if matches!(
+            device_type,
+            DeviceType::Tun | DeviceType::IpTunnel | DeviceType::Wireguard
+        ) {
+            write_lock!(self.statistics).set_tunnel(path.clone());
+        }
+
+        let statistics = DeviceStatisticsDbusProxy::builder(&self.dbus_connection)
+            .path(path.clone())?
+            .build()
+            .await?;
+
+        if statistics.refresh_rate_ms().await? == 0 {
+            statistics.set_refresh_rate_ms(REFRESH_RATE_MS).await?;
+        }
+
+        let mut changes = statistics.receive_rx_bytes_changed().await;
+
+        loop {
+            let counters = Counters {
+                rx_bytes: statistics.rx_bytes().await?,
+                tx_bytes: statistics.tx_bytes().await?,
             };
+
+            write_lock!(self.statistics).set_counters(path.clone(), counters, Instant::now());
+
+            let data_usage =
+                determine_data_usage(&self.active_connections(), &self.statistics).await?;
+
+            let mut state = self.state.get_cloned();
+            if state.data_usage != data_usage {
+                state.data_usage = data_usage;
+                self.state.set(state);
+            }
+
+            if changes.next().await.is_none() || !self.has_device(&path) {
+                break;
+            }
         }
 
-        initialise_path_map!(
-            self.0,
-            active_connections,
-            ActiveConnectionDbusProxyBlocking
-        );
-        initialise_path_map!(self.0, devices, DeviceDbusProxyBlocking, |path| {
-            spawn_property_watcher!(self.0, path, receive_state_changed, devices, |client| {
-                update_state_for_device_change!(client);
-            });
-        });
-        self.0.state.set(State {
-            wired: determine_wired_state(&read_lock!(self.0.devices))?,
-            wifi: determine_wifi_state(&read_lock!(self.0.devices))?,
-            cellular: determine_cellular_state(&read_lock!(self.0.devices))?,
-            vpn: determine_vpn_state(&read_lock!(self.0.active_connections))?,
-        });
+        Ok(())
+    }
+
+    async fn watch_wireless_enabled(self: Arc<Self>) -> Result<()> {
+        let mut changes = self.root_object.receive_wireless_enabled_changed().await;
 
-        spawn_path_list_watcher!(
-            self.0,
-            active_connections,
-            receive_active_connections_changed,
-            ActiveConnectionDbusProxyBlocking,
-            |client| {
-                client.state.set(State {
-                    wired: client.state.get_cloned().wired,
-                    wifi: client.state.get_cloned().wifi,
-                    cellular: client.state.get_cloned().cellular,
-                    vpn: determine_vpn_state(&read_lock!(client.active_connections))?,
-                });
-            }
-        );
-        spawn_path_list_watcher!(
-            self.0,
-            devices,
-            receive_devices_changed,
-            DeviceDbusProxyBlocking,
-            |client| {
-                update_state_for_device_change!(client);
-            },
-            |client, path| {
-                spawn_property_watcher!(client, path, receive_state_changed, devices, |client| {
-                    update_state_for_device_change!(client);
-                });
-            }
-        );
+        while changes.next().await.is_some() {
+            self.update_device_state().await?;
+        }
 
         Ok(())
     }
 
-    pub fn subscribe(&self) -> MutableSignalCloned<State> {
-        self.0.state.signal_cloned()
+    async fn watch_connectivity(self: Arc<Self>) -> Result<()> {
+        let mut changes = self.root_object.receive_connectivity_changed().await;
+
+        while changes.next().await.is_some() {
+            let connectivity = determine_connectivity(&self.root_object).await?;
+            self.state.lock_mut().connectivity = connectivity;
+
+            // the global state follows the checks on each device,
+            // which decide whether their connections are limited or behind a portal
+            self.update_device_state().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Saved connections are only read on start and when the active connections change,
+    /// so VPNs added or removed in the meantime are picked up here.
+    async fn watch_saved_connections(self: Arc<Self>) -> Result<()> {
+        let settings = SettingsDbusProxy::new(&self.dbus_connection).await?;
+        let mut changes = settings.receive_connections_changed().await;
+
+        while changes.next().await.is_some() {
+            let vpn_connections =
+                determine_vpn_connections(&self.dbus_connection, &self.active_connections())
+                    .await?;
+
+            let mut state = self.state.get_cloned();
+            if state.vpn_connections != vpn_connections {
+                state.vpn_connections = vpn_connections;
+                self.state.set(state);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// systemd-resolved may receive a link's servers after the device is activated,
+    /// and its settings can be changed independently of NetworkManager.
+    async fn watch_dns(self: Arc<Self>) -> Result<()> {
+        let mut changes = properties_changed_stream(&self.dbus_connection, RESOLVED_PATH).await?;
+
+        while changes.next().await.is_some() {
+            let dns = get_dns_state(&self.dbus_connection, &self.devices()).await;
+
+            let mut state = self.state.get_cloned();
+            if state.dns != dns {
+                state.dns = dns;
+                self.state.set(state);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Signal quality, access technology and registration changes
+    /// are reported by ModemManager rather than NetworkManager.
+    async fn watch_modems(self: Arc<Self>) -> Result<()> {
+        let mut changes =
+            properties_changed_stream(&self.dbus_connection, MODEM_MANAGER_PATH).await?;
+
+        while changes.next().await.is_some() {
+            let cellular = determine_cellular_state(&self.devices()).await?;
+            self.state.lock_mut().cellular = cellular;
+        }
+
+        Ok(())
+    }
+
+    /// The rate is checked regularly rather than on counter changes,
+    /// so that it drops to zero once devices become idle.
+    async fn watch_throughput(self: Arc<Self>) -> Result<()> {
+        loop {
+            sleep(Duration::from_millis(u64::from(REFRESH_RATE_MS))).await;
+
+            let throughput = read_lock!(self.statistics).throughput(Instant::now());
+
+            let mut state = self.state.get_cloned();
+            if state.throughput != throughput {
+                state.throughput = throughput;
+                self.state.set(state);
+            }
+        }
+    }
+
+    /// The station info is polled,
+    /// as nl80211 does not send events when the signal or bitrate changes.
+    #[cfg(feature = "bar")]
+    async fn watch_station_info(self: Arc<Self>) -> Result<()> {
+        loop {
+            let state = self.state.get_cloned();
+            let hotspot = state.hotspot.map(|hotspot| hotspot.interface);
+
+            let interface = state
+                .interfaces
+                .into_iter()
+                .find(|interface| {
+                    interface.kind == InterfaceKind::Wifi
+                        && interface.status == InterfaceStatus::Connected
+                        && Some(&interface.name) != hotspot.as_ref()
+                })
+                .map(|interface| interface.name);
+
+            let station = match interface {
+                Some(interface) => {
+                    spawn_blocking(move || {
+                        get_station_info(&interface).unwrap_or_else(|err| {
+                            debug!("Failed to read station info for '{interface}': {err:?}");
+                            None
+                        })
+                    })
+                    .await?
+                }
+                None => None,
+            };
+
+            let mut state = self.state.get_cloned();
+            if state.wifi_station != station {
+                state.wifi_station = station;
+                self.state.set(state);
+            }
+
+            sleep(STATION_INFO_INTERVAL).await;
+        }
+    }
+
+    /// The devices connected to a hotspot are polled,
+    /// as NetworkManager does not report them.
+    async fn watch_hotspot_clients(self: Arc<Self>) -> Result<()> {
+        loop {
+            let interface = self
+                .state
+                .get_cloned()
+                .hotspot
+                .map(|hotspot| hotspot.interface);
+
+            if let Some(interface) = interface {
+                let clients = {
+                    let interface = interface.clone();
+                    spawn_blocking(move || {
+                        count_stations(&interface)
+                            .map_err(|err| {
+                                debug!("Failed to count hotspot clients on '{interface}': {err:?}");
+                            })
+                            .ok()
+                    })
+                    .await?
+                };
+
+                let mut state = self.state.get_cloned();
+                if let Some(hotspot) = &mut state.hotspot {
+                    if hotspot.interface == interface && hotspot.clients != clients {
+                        hotspot.clients = clients;
+                        self.state.set(state);
+                    }
+                }
+            }
+
+            sleep(HOTSPOT_CLIENTS_INTERVAL).await;
+        }
+    }
+
+    /// Radios are blocked through rfkill rather than NetworkManager,
+    /// which only reports the wifi and cellular radios.
+    async fn watch_rfkill(self: Arc<Self>) -> Result<()> {
+        spawn_blocking(move || self.read_rfkill_events()).await?
+    }
+}
+
+/// Gets a stream of property changes on any object under `path`.
+async fn properties_changed_stream(connection: &Connection, path: &str) -> Result<MessageStream> {
+    let rule = MatchRule::builder()
+        .msg_type(MessageType::Signal)
+        .interface("org.freedesktop.DBus.Properties")?
+        .member("PropertiesChanged")?
+        .path_namespace(path)?
+        .build();
+
+    Ok(MessageStream::for_match_rule(rule, connection, None).await?)
+}
+
+/// Writes `contents` to a temporary file next to `path`, then renames it over `path`,
+/// so that the file is never left partly written if Ironbar exits during the write.
+#[cfg(feature = "bar")]
+fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+
+    let mut file = File::create(&temp_path)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+
+    fs::rename(&temp_path, path)?;
+    Ok(())
+}
+
+/// Saves the data usage and wifi network histories of every client keeping them.
+///
+/// This must be called on shutdown,
+/// as changes since the last save are otherwise lost.
+#[cfg(feature = "bar")]
+pub fn save_histories() {
+    for client in lock!(HISTORY_CLIENTS).iter().filter_map(Weak::upgrade) {
+        if client.tracking_usage.load(Ordering::Relaxed) {
+            if let Some(path) = quota::history_path() {
+                debug!("Saving data usage history to '{}'", path.display());
+
+                if let Err(err) = client.usage_history.lock_ref().save(&path) {
+                    error!("{:?}", err.wrap_err("Failed to save data usage history"));
+                }
+            }
+        }
+
+        if client.tracking_network_history.load(Ordering::Relaxed) {
+            if let Some(path) = roaming::history_path() {
+                debug!("Saving wifi network history to '{}'", path.display());
+
+                if let Err(err) = client.network_history.lock_ref().save(&path) {
+                    error!("{:?}", err.wrap_err("Failed to save wifi network history"));
+                }
+            }
+        }
     }
 }
 
-pub fn create_client() -> Result<Arc<Client>> {
-    let client = Arc::new(Client::new()?);
+pub async fn create_client(filter: InterfaceFilter) -> Result<Arc<Client>> {
+    let client = Arc::new(Client::new(filter).await?);
     {
         let client = client.clone();
-        spawn_blocking_result!({
-            client.run()?;
-            Ok(())
+        spawn(async move {
+            if let Err(err) = client.run().await {
+                error!(
+                    "{:?}",
+                    err.wrap_err("Failed to start NetworkManager client")
+                );
+            }
         });
     }
     Ok(client)
 }
 
 register_fallible_client!(Client, networkmanager);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(&*self.0.state.lock_ref())?)
+    }
+}
@@ -0,0 +1,196 @@
+use std::fmt::{Display, Formatter};
+
+use color_eyre::Result;
+use serde::Serialize;
+use tracing::debug;
+use zbus::zvariant::Str;
+use zbus::{dbus_proxy, Connection};
+
+/// The object path under which ModemManager exports modems.
+pub(super) const MODEM_MANAGER_PATH: &str = "/org/freedesktop/ModemManager1";
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.ModemManager1",
+    interface = "org.freedesktop.ModemManager1.Modem"
+)]
+trait ModemDbus {
+    #[dbus_proxy(property)]
+    fn access_technologies(&self) -> zbus::Result<u32>;
+
+    /// The signal quality as a percentage, and whether it was recently taken.
+    #[dbus_proxy(property)]
+    fn signal_quality(&self) -> zbus::Result<(u32, bool)>;
+}
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.ModemManager1",
+    interface = "org.freedesktop.ModemManager1.Modem.Modem3gpp"
+)]
+trait Modem3gppDbus {
+    #[dbus_proxy(property)]
+    fn operator_name(&self) -> zbus::Result<Str>;
+
+    #[dbus_proxy(property)]
+    fn registration_state(&self) -> zbus::Result<u32>;
+}
+
+/// Details of a connected modem, as reported by ModemManager.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct ModemDetails {
+    /// Signal quality as a percentage.
+    pub signal: u8,
+    pub technology: AccessTechnology,
+    /// The name of the network operator, ie the carrier.
+    pub operator: Option<String>,
+    pub roaming: bool,
+}
+
+/// The generation of the radio access technology in use.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessTechnology {
+    #[default]
+    Unknown,
+    /// GSM, GPRS, EDGE or CDMA 1xRTT.
+    TwoG,
+    /// UMTS or EV-DO.
+    ThreeG,
+    /// HSPA and its variants.
+    Hspa,
+    /// LTE, including LTE Cat-M and NB-IoT.
+    Lte,
+    /// 5G New Radio.
+    FiveG,
+}
+
+// See `MMModemAccessTechnology` in the ModemManager API docs.
+const ACCESS_TECH_2G: u32 = 0x2 | 0x4 | 0x8 | 0x10 | 0x400;
+const ACCESS_TECH_3G: u32 = 0x20 | 0x800 | 0x1000 | 0x2000;
+const ACCESS_TECH_HSPA: u32 = 0x40 | 0x80 | 0x100 | 0x200;
+const ACCESS_TECH_LTE: u32 = 0x4000 | 0x10000 | 0x20000;
+const ACCESS_TECH_5GNR: u32 = 0x8000;
+
+// See `MMModem3gppRegistrationState` in the ModemManager API docs.
+const REGISTRATION_ROAMING: u32 = 5;
+const REGISTRATION_ROAMING_SMS_ONLY: u32 = 7;
+const REGISTRATION_ROAMING_CSFB_NOT_PREFERRED: u32 = 10;
+
+impl AccessTechnology {
+    /// Determines the newest technology from a modem's `AccessTechnologies` flags.
+    /// Several are set when a modem uses more than one at once, such as 5G NSA alongside LTE.
+    pub fn from_flags(flags: u32) -> Self {
+        if flags & ACCESS_TECH_5GNR != 0 {
+            Self::FiveG
+        } else if flags & ACCESS_TECH_LTE != 0 {
+            Self::Lte
+        } else if flags & ACCESS_TECH_HSPA != 0 {
+            Self::Hspa
+        } else if flags & ACCESS_TECH_3G != 0 {
+            Self::ThreeG
+        } else if flags & ACCESS_TECH_2G != 0 {
+            Self::TwoG
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+impl Display for AccessTechnology {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Unknown => "Unknown",
+                Self::TwoG => "2G",
+                Self::ThreeG => "3G",
+                Self::Hspa => "HSPA",
+                Self::Lte => "LTE",
+                Self::FiveG => "5G",
+            }
+        )
+    }
+}
+
+fn is_roaming(registration_state: u32) -> bool {
+    matches!(
+        registration_state,
+        REGISTRATION_ROAMING
+            | REGISTRATION_ROAMING_SMS_ONLY
+            | REGISTRATION_ROAMING_CSFB_NOT_PREFERRED
+    )
+}
+
+/// Gets the details of the modem NetworkManager identifies by `udi`,
+/// which is the modem's ModemManager object path.
+///
+/// ModemManager may not be running or visible to the current user,
+/// so failing to read the details does not prevent the cellular state being determined.
+pub(super) async fn get_modem_details(connection: &Connection, udi: &str) -> Option<ModemDetails> {
+    read_modem_details(connection, udi)
+        .await
+        .unwrap_or_else(|err| {
+            debug!("Failed to read modem details: {err:?}");
+            None
+        })
+}
+
+async fn read_modem_details(connection: &Connection, udi: &str) -> Result<Option<ModemDetails>> {
+    if !udi.starts_with(MODEM_MANAGER_PATH) {
+        return Ok(None);
+    }
+
+    let modem = ModemDbusProxy::builder(connection)
+        .path(udi.to_string())?
+        .build()
+        .await?;
+
+    let (signal, _) = modem.signal_quality().await?;
+
+    let mut details = ModemDetails {
+        signal: u8::try_from(signal.min(100))?,
+        technology: AccessTechnology::from_flags(modem.access_technologies().await?),
+        operator: None,
+        roaming: false,
+    };
+
+    // only available on 3GPP (GSM/UMTS/LTE/5G) modems
+    let modem_3gpp = Modem3gppDbusProxy::builder(connection)
+        .path(udi.to_string())?
+        .build()
+        .await?;
+
+    if let Ok(operator) = modem_3gpp.operator_name().await {
+        details.operator = Some(operator.to_string()).filter(|operator| !operator.is_empty());
+    }
+
+    if let Ok(registration_state) = modem_3gpp.registration_state().await {
+        details.roaming = is_roaming(registration_state);
+    }
+
+    Ok(Some(details))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_access_technology_from_flags() {
+        assert_eq!(AccessTechnology::from_flags(0), AccessTechnology::Unknown);
+        assert_eq!(AccessTechnology::from_flags(0x10), AccessTechnology::TwoG);
+        assert_eq!(AccessTechnology::from_flags(0x20), AccessTechnology::ThreeG);
+        assert_eq!(AccessTechnology::from_flags(0x200), AccessTechnology::Hspa);
+        assert_eq!(AccessTechnology::from_flags(0x4000), AccessTechnology::Lte);
+        assert_eq!(
+            AccessTechnology::from_flags(0x4000 | 0x8000),
+            AccessTechnology::FiveG
+        );
+    }
+
+    #[test]
+    fn test_is_roaming() {
+        assert!(is_roaming(REGISTRATION_ROAMING));
+        assert!(!is_roaming(1));
+    }
+}
@@ -0,0 +1,327 @@
+use std::os::fd::{AsRawFd, OwnedFd};
+
+use color_eyre::{Report, Result};
+use nix::net::if_::if_nametoindex;
+use nix::sys::socket::{
+    bind, recv, send, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol,
+    SockType,
+};
+use serde::Serialize;
+
+const NLMSG_HEADER_SIZE: usize = 16;
+const GENL_HEADER_SIZE: usize = 4;
+const NLA_HEADER_SIZE: usize = 4;
+
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+
+/// Strips the nested and byte order flags from an attribute type.
+const NLA_TYPE_MASK: u16 = 0x3fff;
+
+const GENL_ID_CTRL: u16 = 0x10;
+const CTRL_CMD_GETFAMILY: u8 = 3;
+const CTRL_ATTR_FAMILY_ID: u16 = 1;
+const CTRL_ATTR_FAMILY_NAME: u16 = 2;
+
+const NL80211_CMD_GET_STATION: u8 = 17;
+const NL80211_ATTR_IFINDEX: u16 = 3;
+const NL80211_ATTR_STA_INFO: u16 = 21;
+
+const NL80211_STA_INFO_SIGNAL: u16 = 7;
+const NL80211_STA_INFO_TX_BITRATE: u16 = 8;
+const NL80211_STA_INFO_RX_BITRATE: u16 = 14;
+
+const NL80211_RATE_INFO_BITRATE: u16 = 1;
+const NL80211_RATE_INFO_BITRATE32: u16 = 5;
+
+/// Details of the access point a wifi interface is associated with,
+/// as reported by the driver.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct StationInfo {
+    /// Signal strength of the last received frame, in dBm.
+    pub signal: Option<i8>,
+    /// The rate of the last frame sent to the access point, in Mbit/s.
+    pub tx_bitrate: Option<f64>,
+    /// The rate of the last frame received from the access point, in Mbit/s.
+    pub rx_bitrate: Option<f64>,
+}
+
+/// Gets the station info for the access point the interface is associated with.
+///
+/// This blocks until the kernel responds,
+/// so should be called from a blocking task.
+#[cfg(feature = "bar")]
+pub(super) fn get_station_info(interface: &str) -> Result<Option<StationInfo>> {
+    // a managed interface only has a station for its access point
+    Ok(get_stations(interface)?.into_iter().next())
+}
+
+/// Gets the number of devices associated with an interface running as an access point.
+///
+/// This blocks until the kernel responds,
+/// so should be called from a blocking task.
+pub(super) fn count_stations(interface: &str) -> Result<usize> {
+    Ok(get_stations(interface)?.len())
+}
+
+/// Gets the info for every station the interface is associated with.
+fn get_stations(interface: &str) -> Result<Vec<StationInfo>> {
+    let index = if_nametoindex(interface)?;
+
+    let socket = socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkGeneric,
+    )?;
+    bind(socket.as_raw_fd(), &NetlinkAddr::new(0, 0))?;
+
+    let family = resolve_family(&socket, "nl80211")?;
+
+    let mut attributes = vec![];
+    put_attribute(&mut attributes, NL80211_ATTR_IFINDEX, &index.to_ne_bytes());
+
+    let responses = request(
+        &socket,
+        family,
+        NLM_F_REQUEST | NLM_F_DUMP,
+        NL80211_CMD_GET_STATION,
+        &attributes,
+    )?;
+
+    Ok(responses
+        .iter()
+        .filter_map(|response| {
+            attributes_of(response)
+                .find(|(kind, _)| *kind == NL80211_ATTR_STA_INFO)
+                .map(|(_, info)| parse_station_info(info))
+        })
+        .collect())
+}
+
+/// Gets the ID of a generic netlink family from its name.
+fn resolve_family(socket: &OwnedFd, name: &str) -> Result<u16> {
+    let mut attributes = vec![];
+    put_attribute(
+        &mut attributes,
+        CTRL_ATTR_FAMILY_NAME,
+        format!("{name}\0").as_bytes(),
+    );
+
+    let responses = request(
+        socket,
+        GENL_ID_CTRL,
+        NLM_F_REQUEST,
+        CTRL_CMD_GETFAMILY,
+        &attributes,
+    )?;
+
+    responses
+        .iter()
+        .flat_map(|response| attributes_of(response))
+        .find(|(kind, _)| *kind == CTRL_ATTR_FAMILY_ID)
+        .and_then(|(_, value)| Some(u16::from_ne_bytes(value.get(..2)?.try_into().ok()?)))
+        .ok_or_else(|| Report::msg(format!("Netlink family '{name}' not found")))
+}
+
+/// Sends a generic netlink request,
+/// and reads the payload of each response until the end of the dump.
+fn request(
+    socket: &OwnedFd,
+    family: u16,
+    flags: u16,
+    command: u8,
+    attributes: &[u8],
+) -> Result<Vec<Vec<u8>>> {
+    let length = NLMSG_HEADER_SIZE + GENL_HEADER_SIZE + attributes.len();
+
+    let mut message = Vec::with_capacity(length);
+    message.extend_from_slice(&(length as u32).to_ne_bytes());
+    message.extend_from_slice(&family.to_ne_bytes());
+    message.extend_from_slice(&flags.to_ne_bytes());
+    message.extend_from_slice(&1u32.to_ne_bytes()); // sequence number
+    message.extend_from_slice(&0u32.to_ne_bytes()); // port ID, assigned by the kernel
+    message.extend_from_slice(&[command, 1, 0, 0]);
+    message.extend_from_slice(attributes);
+
+    send(socket.as_raw_fd(), &message, MsgFlags::empty())?;
+
+    let is_dump = flags & NLM_F_DUMP == NLM_F_DUMP;
+
+    let mut responses = vec![];
+    let mut buffer = vec![0; 32 * 1024];
+
+    loop {
+        let size = recv(socket.as_raw_fd(), &mut buffer, MsgFlags::empty())?;
+
+        for (kind, payload) in messages_of(&buffer[..size]) {
+            match kind {
+                NLMSG_DONE => return Ok(responses),
+                NLMSG_ERROR => {
+                    let code = payload
+                        .get(..4)
+                        .and_then(|code| code.try_into().ok())
+                        .map_or(0, i32::from_ne_bytes);
+
+                    // an error of 0 acknowledges the request
+                    if code != 0 {
+                        return Err(std::io::Error::from_raw_os_error(-code).into());
+                    }
+
+                    return Ok(responses);
+                }
+                _ => {
+                    responses.push(payload.get(GENL_HEADER_SIZE..).unwrap_or_default().to_vec());
+                }
+            }
+        }
+
+        if !is_dump {
+            return Ok(responses);
+        }
+    }
+}
+
+/// Appends an attribute, padded to a multiple of 4 bytes.
+fn put_attribute(buffer: &mut Vec<u8>, kind: u16, value: &[u8]) {
+    let length = NLA_HEADER_SIZE + value.len();
+
+    buffer.extend_from_slice(&(length as u16).to_ne_bytes());
+    buffer.extend_from_slice(&kind.to_ne_bytes());
+    buffer.extend_from_slice(value);
+    buffer.resize(buffer.len() + padding(length), 0);
+}
+
+fn padding(length: usize) -> usize {
+    (4 - length % 4) % 4
+}
+
+/// Splits a buffer into its netlink messages,
+/// giving the type and payload of each.
+fn messages_of(mut buffer: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        let length = u32::from_ne_bytes(buffer.get(..4)?.try_into().ok()?) as usize;
+        let kind = u16::from_ne_bytes(buffer.get(4..6)?.try_into().ok()?);
+
+        let payload = buffer.get(NLMSG_HEADER_SIZE..length)?;
+        buffer = buffer.get(length + padding(length)..).unwrap_or_default();
+
+        Some((kind, payload))
+    })
+}
+
+/// Splits a buffer into its netlink attributes,
+/// giving the type and value of each.
+fn attributes_of(mut buffer: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    std::iter::from_fn(move || {
+        let length = u16::from_ne_bytes(buffer.get(..2)?.try_into().ok()?) as usize;
+        let kind = u16::from_ne_bytes(buffer.get(2..4)?.try_into().ok()?) & NLA_TYPE_MASK;
+
+        let value = buffer.get(NLA_HEADER_SIZE..length)?;
+        buffer = buffer.get(length + padding(length)..).unwrap_or_default();
+
+        Some((kind, value))
+    })
+}
+
+/// Parses the nested `NL80211_ATTR_STA_INFO` attribute.
+fn parse_station_info(buffer: &[u8]) -> StationInfo {
+    let mut info = StationInfo::default();
+
+    for (kind, value) in attributes_of(buffer) {
+        match kind {
+            NL80211_STA_INFO_SIGNAL => {
+                info.signal = value.first().map(|&signal| signal as i8);
+            }
+            NL80211_STA_INFO_TX_BITRATE => info.tx_bitrate = parse_bitrate(value),
+            NL80211_STA_INFO_RX_BITRATE => info.rx_bitrate = parse_bitrate(value),
+            _ => {}
+        }
+    }
+
+    info
+}
+
+/// Parses a nested rate info attribute into Mbit/s.
+/// The kernel reports rates in units of 100 kbit/s,
+/// using a 32-bit value for rates which do not fit in 16 bits.
+fn parse_bitrate(buffer: &[u8]) -> Option<f64> {
+    let mut bitrate = None;
+
+    for (kind, value) in attributes_of(buffer) {
+        match kind {
+            NL80211_RATE_INFO_BITRATE32 => {
+                return Some(f64::from(u32::from_ne_bytes(value.try_into().ok()?)) / 10.0);
+            }
+            NL80211_RATE_INFO_BITRATE => {
+                bitrate = value
+                    .try_into()
+                    .ok()
+                    .map(|value| f64::from(u16::from_ne_bytes(value)) / 10.0);
+            }
+            _ => {}
+        }
+    }
+
+    bitrate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attributes_round_trip() {
+        let mut buffer = vec![];
+        put_attribute(&mut buffer, 1, &[1, 2, 3]);
+        put_attribute(&mut buffer, 2, &[4, 5, 6, 7]);
+
+        assert_eq!(buffer.len(), 16);
+
+        let attributes = attributes_of(&buffer).collect::<Vec<_>>();
+        assert_eq!(
+            attributes,
+            vec![(1, &[1, 2, 3][..]), (2, &[4, 5, 6, 7][..])]
+        );
+    }
+
+    #[test]
+    fn test_parse_station_info() {
+        let mut tx_rate = vec![];
+        put_attribute(
+            &mut tx_rate,
+            NL80211_RATE_INFO_BITRATE,
+            &8667u16.to_ne_bytes(),
+        );
+        put_attribute(
+            &mut tx_rate,
+            NL80211_RATE_INFO_BITRATE32,
+            &8667u32.to_ne_bytes(),
+        );
+
+        let mut rx_rate = vec![];
+        put_attribute(
+            &mut rx_rate,
+            NL80211_RATE_INFO_BITRATE,
+            &5200u16.to_ne_bytes(),
+        );
+
+        let mut info = vec![];
+        put_attribute(&mut info, NL80211_STA_INFO_SIGNAL, &[(-52i8) as u8]);
+        // nested attributes have the nested flag set
+        put_attribute(&mut info, NL80211_STA_INFO_TX_BITRATE | 0x8000, &tx_rate);
+        put_attribute(&mut info, NL80211_STA_INFO_RX_BITRATE | 0x8000, &rx_rate);
+
+        assert_eq!(
+            parse_station_info(&info),
+            StationInfo {
+                signal: Some(-52),
+                tx_bitrate: Some(866.7),
+                rx_bitrate: Some(520.0),
+            }
+        );
+    }
+}
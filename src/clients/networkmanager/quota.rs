@@ -0,0 +1,261 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use chrono::{Datelike, Duration, NaiveDate};
+use color_eyre::Result;
+use tracing::warn;
+
+use crate::clients::networkmanager::statistics::ConnectionUsage;
+use crate::clients::networkmanager::write_atomic;
+
+/// How many days of usage to keep for each connection.
+/// This covers the current and previous billing periods.
+const HISTORY_DAYS: i64 = 93;
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Data used by each connection per day,
+/// kept across restarts so that it can be totalled over a billing period.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UsageHistory {
+    /// Bytes downloaded and uploaded per day, keyed by connection ID.
+    days: HashMap<String, BTreeMap<NaiveDate, u64>>,
+    /// The IDs of the currently active connections.
+    active: HashSet<String>,
+}
+
+impl UsageHistory {
+    /// Loads the history saved at `path`.
+    /// An empty history is used if the file does not exist or cannot be read.
+    pub(super) fn load(path: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        match serde_json::from_str::<HashMap<String, BTreeMap<String, u64>>>(&contents) {
+            Ok(saved) => Self {
+                days: saved
+                    .into_iter()
+                    .map(|(connection, days)| {
+                        let days = days
+                            .into_iter()
+                            .filter_map(|(date, bytes)| {
+                                NaiveDate::parse_from_str(&date, DATE_FORMAT)
+                                    .ok()
+                                    .map(|date| (date, bytes))
+                            })
+                            .collect();
+
+                        (connection, days)
+                    })
+                    .collect(),
+                active: HashSet::new(),
+            },
+            Err(err) => {
+                warn!(
+                    "Failed to read data usage history from '{}': {err}",
+                    path.display()
+                );
+                Self::default()
+            }
+        }
+    }
+
+    pub(super) fn save(&self, path: &Path) -> Result<()> {
+        let saved = self
+            .days
+            .iter()
+            .map(|(connection, days)| {
+                let days = days
+                    .iter()
+                    .map(|(date, bytes)| (date.format(DATE_FORMAT).to_string(), *bytes))
+                    .collect::<BTreeMap<_, _>>();
+
+                (connection, days)
+            })
+            .collect::<HashMap<_, _>>();
+
+        write_atomic(path, &serde_json::to_string(&saved)?)
+    }
+
+    /// Gets the total bytes used by a connection on or after `since`.
+    pub fn usage_since(&self, connection: &str, since: NaiveDate) -> u64 {
+        self.days
+            .get(connection)
+            .map_or(0, |days| days.range(since..).map(|(_, bytes)| bytes).sum())
+    }
+
+    /// Whether the connection is currently active.
+    pub fn is_active(&self, connection: &str) -> bool {
+        self.active.contains(connection)
+    }
+
+    fn add(&mut self, connection: &str, date: NaiveDate, bytes: u64) {
+        *self
+            .days
+            .entry(connection.to_string())
+            .or_default()
+            .entry(date)
+            .or_default() += bytes;
+    }
+
+    /// Forgets usage older than the history length.
+    fn prune(&mut self, today: NaiveDate) {
+        let cutoff = today - Duration::days(HISTORY_DAYS);
+
+        for days in self.days.values_mut() {
+            days.retain(|date, _| *date >= cutoff);
+        }
+
+        self.days.retain(|_, days| !days.is_empty());
+    }
+}
+
+/// Adds the usage of active connections to the history.
+///
+/// Active connection usage is counted from when each connection was activated,
+/// so this keeps the last totals to work out how much was used since the previous update.
+#[derive(Debug, Default)]
+pub(super) struct UsageRecorder {
+    last: HashMap<String, u64>,
+}
+
+impl UsageRecorder {
+    /// Records the data used since the last update against `today`.
+    /// Returns whether the history changed.
+    pub fn record(
+        &mut self,
+        history: &mut UsageHistory,
+        data_usage: &[ConnectionUsage],
+        today: NaiveDate,
+    ) -> bool {
+        let mut totals = HashMap::new();
+        let mut changed = false;
+
+        for usage in data_usage {
+            let total = usage.rx_bytes + usage.tx_bytes;
+            let last = self.last.get(&usage.id).copied().unwrap_or_default();
+
+            // usage starts from zero again if the connection is re-activated
+            let used = total.checked_sub(last).unwrap_or(total);
+
+            if used > 0 {
+                history.add(&usage.id, today, used);
+                changed = true;
+            }
+
+            totals.insert(usage.id.clone(), total);
+        }
+
+        let active = totals.keys().cloned().collect::<HashSet<_>>();
+        if history.active != active {
+            history.active = active;
+            changed = true;
+        }
+
+        if changed {
+            history.prune(today);
+        }
+
+        self.last = totals;
+        changed
+    }
+}
+
+/// How much of a quota has been used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum QuotaLevel {
+    #[default]
+    Normal,
+    /// Usage is past the warning threshold.
+    Warning,
+    /// Usage is past the quota.
+    Exceeded,
+}
+
+/// Gets the path the usage history is saved to.
+pub(super) fn history_path() -> Option<PathBuf> {
+    dirs::state_dir().map(|dir| dir.join("ironbar").join("data_usage.json"))
+}
+
+/// Gets the first day of the billing period containing `today`,
+/// where periods start on `reset_day` of each month.
+///
+/// Reset days past the end of a shorter month fall on its last day.
+pub fn period_start(today: NaiveDate, reset_day: u32) -> NaiveDate {
+    let this_month = reset_date(today.year(), today.month(), reset_day);
+
+    if today >= this_month {
+        this_month
+    } else if today.month() == 1 {
+        reset_date(today.year() - 1, 12, reset_day)
+    } else {
+        reset_date(today.year(), today.month() - 1, reset_day)
+    }
+}
+
+/// Gets `day` of the month, clamped to the month's length.
+fn reset_date(year: i32, month: u32, day: u32) -> NaiveDate {
+    (1..=day.max(1))
+        .rev()
+        .find_map(|day| NaiveDate::from_ymd_opt(year, month, day))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).expect("valid date")
+    }
+
+    fn usage(id: &str, rx_bytes: u64, tx_bytes: u64) -> ConnectionUsage {
+        ConnectionUsage {
+            id: id.to_string(),
+            kind: String::from("gsm"),
+            rx_bytes,
+            tx_bytes,
+        }
+    }
+
+    #[test]
+    fn test_period_start() {
+        assert_eq!(period_start(date(2024, 5, 20), 1), date(2024, 5, 1));
+        assert_eq!(period_start(date(2024, 5, 20), 15), date(2024, 5, 15));
+        assert_eq!(period_start(date(2024, 5, 10), 15), date(2024, 4, 15));
+        assert_eq!(period_start(date(2024, 1, 10), 15), date(2023, 12, 15));
+
+        // clamped to the end of short months
+        assert_eq!(period_start(date(2024, 2, 29), 31), date(2024, 2, 29));
+        assert_eq!(period_start(date(2024, 3, 10), 31), date(2024, 2, 29));
+    }
+
+    #[test]
+    fn test_record_usage() {
+        let mut history = UsageHistory::default();
+        let mut recorder = UsageRecorder::default();
+
+        let day1 = date(2024, 5, 1);
+        let day2 = date(2024, 5, 2);
+
+        assert!(recorder.record(&mut history, &[usage("LTE", 100, 50)], day1));
+        assert!(history.is_active("LTE"));
+
+        assert!(!recorder.record(&mut history, &[usage("LTE", 100, 50)], day1));
+
+        assert!(recorder.record(&mut history, &[usage("LTE", 300, 50)], day2));
+        assert_eq!(history.usage_since("LTE", day1), 350);
+        assert_eq!(history.usage_since("LTE", day2), 200);
+
+        // disconnected
+        assert!(recorder.record(&mut history, &[], day2));
+        assert!(!history.is_active("LTE"));
+
+        // counted from zero after reconnecting
+        assert!(recorder.record(&mut history, &[usage("LTE", 10, 0)], day2));
+        assert_eq!(history.usage_since("LTE", day1), 360);
+        assert_eq!(history.usage_since("Home", day1), 0);
+    }
+}
@@ -0,0 +1,19 @@
+use color_eyre::Result;
+use tracing::debug;
+
+use crate::clients::networkmanager::Client;
+
+impl Client {
+    /// Turns the wifi radio on or off.
+    pub async fn set_wifi_enabled(&self, enabled: bool) -> Result<()> {
+        debug!("Setting wifi enabled: {enabled}");
+        self.0.root_object.set_wireless_enabled(enabled).await?;
+        Ok(())
+    }
+
+    /// Turns the wifi radio off if it is on, or on if it is off.
+    pub async fn toggle_wifi(&self) -> Result<()> {
+        let enabled = self.0.root_object.wireless_enabled().await?;
+        self.set_wifi_enabled(!enabled).await
+    }
+}
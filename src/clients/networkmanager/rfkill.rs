@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::fs::File;
+#[cfg(feature = "bar")]
+use std::fs::OpenOptions;
+#[cfg(feature = "bar")]
+use std::io::Write;
+use std::io::{ErrorKind, Read};
+
+use color_eyre::Result;
+#[cfg(feature = "bar")]
+use color_eyre::{Help, Report};
+use tracing::debug;
+
+#[cfg(feature = "bar")]
+use crate::clients::networkmanager::Client;
+use crate::clients::networkmanager::ClientInner;
+#[cfg(feature = "bar")]
+use crate::spawn_blocking;
+
+const RFKILL_PATH: &str = "/dev/rfkill";
+
+/// The size of `struct rfkill_event`.
+/// Newer kernels send a larger struct, but truncate it to the size read.
+const EVENT_SIZE: usize = 8;
+
+#[cfg(feature = "bar")]
+const TYPE_ALL: u8 = 0;
+
+const OP_ADD: u8 = 0;
+const OP_DEL: u8 = 1;
+const OP_CHANGE: u8 = 2;
+#[cfg(feature = "bar")]
+const OP_CHANGE_ALL: u8 = 3;
+
+/// An event read from or written to `/dev/rfkill`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct RfkillEvent {
+    /// The index of the radio.
+    index: u32,
+    radio_type: u8,
+    op: u8,
+    /// Whether the radio is blocked in software, such as by airplane mode.
+    soft: bool,
+    /// Whether the radio is blocked by a hardware switch.
+    hard: bool,
+}
+
+impl RfkillEvent {
+    fn parse(bytes: [u8; EVENT_SIZE]) -> Self {
+        Self {
+            index: u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+            radio_type: bytes[4],
+            op: bytes[5],
+            soft: bytes[6] != 0,
+            hard: bytes[7] != 0,
+        }
+    }
+
+    #[cfg(any(test, feature = "bar"))]
+    fn to_bytes(self) -> [u8; EVENT_SIZE] {
+        let index = self.index.to_ne_bytes();
+        [
+            index[0],
+            index[1],
+            index[2],
+            index[3],
+            self.radio_type,
+            self.op,
+            u8::from(self.soft),
+            u8::from(self.hard),
+        ]
+    }
+}
+
+/// Whether each radio is soft-blocked, by its index.
+#[derive(Debug, Default)]
+struct Radios(HashMap<u32, bool>);
+
+impl Radios {
+    fn apply(&mut self, event: RfkillEvent) {
+        match event.op {
+            OP_ADD | OP_CHANGE => {
+                self.0.insert(event.index, event.soft);
+            }
+            OP_DEL => {
+                self.0.remove(&event.index);
+            }
+            _ => {}
+        }
+    }
+
+    /// Airplane mode is on when every radio is soft-blocked.
+    /// This is `None` if there are no radios.
+    fn airplane_mode(&self) -> Option<bool> {
+        if self.0.is_empty() {
+            None
+        } else {
+            Some(self.0.values().all(|&soft| soft))
+        }
+    }
+}
+
+#[cfg(feature = "bar")]
+impl Client {
+    /// Blocks or unblocks every radio.
+    pub async fn set_airplane_mode(&self, enabled: bool) -> Result<()> {
+        debug!("Setting airplane mode: {enabled}");
+
+        let event = RfkillEvent {
+            index: 0,
+            radio_type: TYPE_ALL,
+            op: OP_CHANGE_ALL,
+            soft: enabled,
+            hard: false,
+        };
+
+        spawn_blocking(move || {
+            OpenOptions::new()
+                .write(true)
+                .open(RFKILL_PATH)
+                .and_then(|mut file| file.write_all(&event.to_bytes()))
+                .map_err(|err| {
+                    Report::new(err)
+                        .wrap_err(format!("Failed to write to '{RFKILL_PATH}'"))
+                        .suggestion("Check you have permission to write to the device")
+                })
+        })
+        .await?
+    }
+
+    /// Turns airplane mode off if it is on, or on if it is off.
+    pub async fn toggle_airplane_mode(&self) -> Result<()> {
+        let enabled = self.0.state.get_cloned().airplane_mode.unwrap_or_default();
+        self.set_airplane_mode(!enabled).await
+    }
+}
+
+impl ClientInner {
+    /// Reads radio changes from `/dev/rfkill`, which blocks until the next change.
+    /// On opening the device, an event is sent for each existing radio.
+    pub(super) fn read_rfkill_events(&self) -> Result<()> {
+        let mut file = match File::open(RFKILL_PATH) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                debug!("'{RFKILL_PATH}' not found, airplane mode will not be available");
+                return Ok(());
+            }
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut radios = Radios::default();
+        let mut buffer = [0; EVENT_SIZE];
+
+        loop {
+            file.read_exact(&mut buffer)?;
+
+            let event = RfkillEvent::parse(buffer);
+            debug!("Received rfkill event: {event:?}");
+            radios.apply(event);
+
+            let airplane_mode = radios.airplane_mode();
+
+            let mut state = self.state.get_cloned();
+            if state.airplane_mode != airplane_mode {
+                state.airplane_mode = airplane_mode;
+                self.state.set(state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(index: u32, op: u8, soft: bool) -> RfkillEvent {
+        RfkillEvent {
+            index,
+            radio_type: 1,
+            op,
+            soft,
+            hard: false,
+        }
+    }
+
+    #[test]
+    fn test_event_bytes() {
+        let event = event(3, OP_CHANGE, true);
+        assert_eq!(RfkillEvent::parse(event.to_bytes()), event);
+    }
+
+    #[test]
+    fn test_airplane_mode() {
+        let mut radios = Radios::default();
+        assert_eq!(radios.airplane_mode(), None);
+
+        radios.apply(event(0, OP_ADD, true));
+        radios.apply(event(1, OP_ADD, false));
+        assert_eq!(radios.airplane_mode(), Some(false));
+
+        radios.apply(event(1, OP_CHANGE, true));
+        assert_eq!(radios.airplane_mode(), Some(true));
+
+        radios.apply(event(2, OP_ADD, false));
+        assert_eq!(radios.airplane_mode(), Some(false));
+
+        radios.apply(event(2, OP_DEL, false));
+        assert_eq!(radios.airplane_mode(), Some(true));
+    }
+}
@@ -0,0 +1,243 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use color_eyre::Result;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::clients::networkmanager::state::WifiState;
+use crate::clients::networkmanager::write_atomic;
+
+/// How many access points to remember for each network.
+const MAX_ACCESS_POINTS: usize = 5;
+
+/// How often each wifi network has been connected to,
+/// and the access points used for it,
+/// kept across restarts to help diagnose roaming between access points.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkHistory {
+    /// Keyed by SSID.
+    networks: HashMap<String, NetworkStats>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkStats {
+    /// The number of times the network has been connected to.
+    pub connections: u32,
+    /// The number of times the connection moved to another access point of the network.
+    pub roams: u32,
+    /// When the network was last connected to, as a Unix timestamp.
+    pub last_connected: i64,
+    pub strength: StrengthSamples,
+    /// The most recently used access points, most recent first.
+    pub access_points: Vec<AccessPointStats>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccessPointStats {
+    /// The MAC address of the access point.
+    pub bssid: String,
+    /// The number of times the connection moved to the access point.
+    pub visits: u32,
+    /// When the access point was last used, as a Unix timestamp.
+    pub last_seen: i64,
+    pub strength: StrengthSamples,
+}
+
+/// Signal strengths seen while connected.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrengthSamples {
+    total: u64,
+    count: u64,
+}
+
+impl StrengthSamples {
+    fn add(&mut self, strength: u8) {
+        self.total += u64::from(strength);
+        self.count += 1;
+    }
+
+    /// Gets the mean signal strength as a percentage,
+    /// or `None` if no strength has been seen.
+    pub fn typical(&self) -> Option<u8> {
+        (self.count > 0).then(|| (self.total / self.count) as u8)
+    }
+}
+
+impl NetworkHistory {
+    /// Loads the history saved at `path`.
+    /// An empty history is used if the file does not exist or cannot be read.
+    pub(super) fn load(path: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+
+        serde_json::from_str(&contents).unwrap_or_else(|err| {
+            warn!(
+                "Failed to read wifi network history from '{}': {err}",
+                path.display()
+            );
+            Self::default()
+        })
+    }
+
+    pub(super) fn save(&self, path: &Path) -> Result<()> {
+        write_atomic(path, &serde_json::to_string(self)?)
+    }
+
+    /// Gets the history of the network with the given SSID.
+    pub fn get(&self, ssid: &str) -> Option<&NetworkStats> {
+        self.networks.get(ssid)
+    }
+}
+
+/// Adds wifi connections, roams and signal strengths to the history.
+///
+/// State updates are frequent while data is transferred,
+/// so this keeps the last connection to only record changes.
+#[derive(Debug, Default)]
+pub(super) struct RoamRecorder {
+    /// The SSID, BSSID and strength last recorded.
+    last: Option<(String, Option<String>, u8)>,
+}
+
+impl RoamRecorder {
+    /// Records the current wifi connection at `now`, as a Unix timestamp.
+    /// Returns whether the history changed.
+    pub fn record(&mut self, history: &mut NetworkHistory, wifi: &WifiState, now: i64) -> bool {
+        let Some(connected) = wifi.connected() else {
+            return self.last.take().is_some();
+        };
+
+        let current = (
+            connected.ssid.clone(),
+            connected.bssid.clone(),
+            connected.strength,
+        );
+
+        if self.last.as_ref() == Some(&current) {
+            return false;
+        }
+
+        let (ssid, bssid, strength) = &current;
+        let network = history.networks.entry(ssid.clone()).or_default();
+
+        let (new_connection, new_access_point) = match &self.last {
+            Some((last_ssid, last_bssid, _)) if last_ssid == ssid => (false, last_bssid != bssid),
+            _ => (true, true),
+        };
+
+        if new_connection {
+            network.connections += 1;
+            network.last_connected = now;
+        } else if new_access_point && bssid.is_some() {
+            network.roams += 1;
+        }
+
+        network.strength.add(*strength);
+
+        if let Some(bssid) = bssid {
+            let index = network
+                .access_points
+                .iter()
+                .position(|access_point| &access_point.bssid == bssid);
+
+            let mut access_point = match index {
+                Some(index) => network.access_points.remove(index),
+                None => AccessPointStats {
+                    bssid: bssid.clone(),
+                    ..AccessPointStats::default()
+                },
+            };
+
+            if new_access_point {
+                access_point.visits += 1;
+            }
+
+            access_point.last_seen = now;
+            access_point.strength.add(*strength);
+
+            network.access_points.insert(0, access_point);
+            network.access_points.truncate(MAX_ACCESS_POINTS);
+        }
+
+        self.last = Some(current);
+        true
+    }
+}
+
+/// Gets the path the network history is saved to.
+pub(super) fn history_path() -> Option<PathBuf> {
+    dirs::state_dir().map(|dir| dir.join("ironbar").join("wifi_history.json"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clients::networkmanager::state::{WifiConnectedState, WifiSecurity};
+
+    fn connected(ssid: &str, bssid: &str, strength: u8) -> WifiState {
+        WifiState::Connected(WifiConnectedState {
+            ssid: ssid.to_string(),
+            bssid: Some(bssid.to_string()),
+            strength,
+            security: WifiSecurity::Wpa2,
+            eap: None,
+            ip4: None,
+            ip6: None,
+        })
+    }
+
+    #[test]
+    fn test_record_roams() {
+        let mut history = NetworkHistory::default();
+        let mut recorder = RoamRecorder::default();
+
+        assert!(recorder.record(&mut history, &connected("Home", "aa", 80), 100));
+        assert!(!recorder.record(&mut history, &connected("Home", "aa", 80), 110));
+
+        // roamed to another access point, then back
+        assert!(recorder.record(&mut history, &connected("Home", "bb", 40), 120));
+        assert!(recorder.record(&mut history, &connected("Home", "aa", 60), 130));
+
+        let stats = history.get("Home").expect("network to be recorded");
+        assert_eq!(stats.connections, 1);
+        assert_eq!(stats.roams, 2);
+        assert_eq!(stats.last_connected, 100);
+        assert_eq!(stats.strength.typical(), Some(60));
+
+        let bssids = stats
+            .access_points
+            .iter()
+            .map(|access_point| (access_point.bssid.as_str(), access_point.visits))
+            .collect::<Vec<_>>();
+        assert_eq!(bssids, vec![("aa", 2), ("bb", 1)]);
+        assert_eq!(stats.access_points[0].last_seen, 130);
+        assert_eq!(stats.access_points[0].strength.typical(), Some(70));
+
+        // reconnecting counts as a new connection rather than a roam
+        assert!(recorder.record(&mut history, &WifiState::Disconnected, 140));
+        assert!(recorder.record(&mut history, &connected("Home", "aa", 70), 150));
+
+        let stats = history.get("Home").expect("network to be recorded");
+        assert_eq!(stats.connections, 2);
+        assert_eq!(stats.roams, 2);
+        assert_eq!(stats.last_connected, 150);
+    }
+
+    #[test]
+    fn test_access_points_limited() {
+        let mut history = NetworkHistory::default();
+        let mut recorder = RoamRecorder::default();
+
+        for (index, bssid) in ["a", "b", "c", "d", "e", "f"].into_iter().enumerate() {
+            recorder.record(&mut history, &connected("Office", bssid, 50), index as i64);
+        }
+
+        let stats = history.get("Office").expect("network to be recorded");
+        assert_eq!(stats.access_points.len(), MAX_ACCESS_POINTS);
+        assert_eq!(stats.access_points[0].bssid, "f");
+        assert_eq!(stats.roams, 5);
+    }
+}
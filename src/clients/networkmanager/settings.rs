@@ -0,0 +1,224 @@
+use color_eyre::Result;
+#[cfg(feature = "bar")]
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::Connection;
+
+use crate::clients::networkmanager::dbus::{SettingsConnectionDbusProxy, SettingsDbusProxy};
+#[cfg(feature = "bar")]
+use crate::clients::networkmanager::Client;
+
+/// A connection profile saved in NetworkManager.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SavedConnection {
+    /// The D-Bus object path of the connection.
+    pub path: String,
+    pub id: String,
+    /// The connection type, ie `802-11-wireless`.
+    pub kind: String,
+    /// The `connection.autoconnect-priority`.
+    /// Higher priority connections are preferred when autoconnecting.
+    pub priority: i32,
+}
+
+#[cfg(feature = "bar")]
+impl Client {
+    /// Gets all saved connections,
+    /// sorted by type and then by highest priority first.
+    pub async fn saved_connections(&self) -> Result<Vec<SavedConnection>> {
+        list_saved_connections(&self.0.dbus_connection).await
+    }
+
+    /// Finds the saved wifi connection for the network with the given SSID.
+    /// Hotspot connections are ignored.
+    pub(super) async fn find_wifi_connection(&self, ssid: &str) -> Result<Option<OwnedObjectPath>> {
+        let settings = SettingsDbusProxy::new(&self.0.dbus_connection).await?;
+
+        for path in settings.list_connections().await? {
+            let proxy = SettingsConnectionDbusProxy::builder(&self.0.dbus_connection)
+                .path(path.clone())?
+                .build()
+                .await?;
+
+            let settings = proxy.get_settings().await?;
+            let Some(wireless) = settings.get("802-11-wireless") else {
+                continue;
+            };
+
+            let is_hotspot = wireless
+                .get("mode")
+                .and_then(|value| <&str>::try_from(value).ok())
+                == Some("ap");
+
+            let matches = wireless
+                .get("ssid")
+                .and_then(|value| Vec::<u8>::try_from(value.clone()).ok())
+                .is_some_and(|saved| saved == ssid.as_bytes());
+
+            if matches && !is_hotspot {
+                return Ok(Some(path));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Sets the `connection.autoconnect-priority` of the saved connection at `path`.
+    pub async fn set_connection_priority(&self, path: &str, priority: i32) -> Result<()> {
+        let proxy = SettingsConnectionDbusProxy::builder(&self.0.dbus_connection)
+            .path(path)?
+            .build()
+            .await?;
+
+        // updates replace all settings, so the existing ones must be sent back.
+        // secrets are not included, but NetworkManager keeps the existing ones.
+        let mut settings = proxy.get_settings().await?;
+        settings
+            .entry(String::from("connection"))
+            .or_default()
+            .insert(
+                String::from("autoconnect-priority"),
+                OwnedValue::from(priority),
+            );
+
+        proxy.update(settings).await?;
+        Ok(())
+    }
+}
+
+/// Gets all saved connections, as returned by [`Client::saved_connections`].
+pub(super) async fn list_saved_connections(
+    dbus_connection: &Connection,
+) -> Result<Vec<SavedConnection>> {
+    let settings = SettingsDbusProxy::new(dbus_connection).await?;
+
+    let mut connections = vec![];
+    for path in settings.list_connections().await? {
+        let proxy = SettingsConnectionDbusProxy::builder(dbus_connection)
+            .path(path.clone())?
+            .build()
+            .await?;
+
+        let settings = proxy.get_settings().await?;
+        let Some(connection) = settings.get("connection") else {
+            continue;
+        };
+
+        let get_str = |key| {
+            connection
+                .get(key)
+                .and_then(|value| <&str>::try_from(value).ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        connections.push(SavedConnection {
+            path: path.to_string(),
+            id: get_str("id"),
+            kind: get_str("type"),
+            priority: connection
+                .get("autoconnect-priority")
+                .and_then(|value| i32::try_from(value).ok())
+                .unwrap_or_default(),
+        });
+    }
+
+    sort_connections(&mut connections);
+    Ok(connections)
+}
+
+fn sort_connections(connections: &mut [SavedConnection]) {
+    connections.sort_by(|a, b| {
+        a.kind
+            .cmp(&b.kind)
+            .then(b.priority.cmp(&a.priority))
+            .then(a.id.cmp(&b.id))
+    });
+}
+
+/// Moves the connection at `path` up (negative `offset`) or down (positive `offset`)
+/// amongst the connections of the same type.
+///
+/// `connections` must be sorted as returned by [`Client::saved_connections`].
+///
+/// Returns the paths and new priorities of connections which need updating,
+/// so that their priorities match the new order.
+#[cfg(feature = "bar")]
+pub fn reorder_connections(
+    connections: &[SavedConnection],
+    path: &str,
+    offset: isize,
+) -> Vec<(String, i32)> {
+    let Some(moved) = connections
+        .iter()
+        .find(|connection| connection.path == path)
+    else {
+        return vec![];
+    };
+
+    // priority only matters between connections of the same type,
+    // as they compete for the same devices.
+    let mut group = connections
+        .iter()
+        .filter(|connection| connection.kind == moved.kind)
+        .collect::<Vec<_>>();
+
+    let Some(index) = group.iter().position(|connection| connection.path == path) else {
+        return vec![];
+    };
+
+    let Some(new_index) = index
+        .checked_add_signed(offset)
+        .filter(|&new_index| new_index < group.len())
+    else {
+        return vec![];
+    };
+
+    let connection = group.remove(index);
+    group.insert(new_index, connection);
+
+    let len = group.len();
+    group
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, connection)| {
+            let priority = (len - 1 - index) as i32;
+            (connection.priority != priority).then(|| (connection.path.clone(), priority))
+        })
+        .collect()
+}
+
+#[cfg(all(test, feature = "bar"))]
+mod tests {
+    use super::*;
+
+    fn connection(path: &str, kind: &str, priority: i32) -> SavedConnection {
+        SavedConnection {
+            path: path.to_string(),
+            id: path.to_string(),
+            kind: kind.to_string(),
+            priority,
+        }
+    }
+
+    #[test]
+    fn test_reorder_connections() {
+        let mut connections = vec![
+            connection("home-2.4", "802-11-wireless", 0),
+            connection("home-5", "802-11-wireless", 0),
+            connection("wired", "802-3-ethernet", 0),
+            connection("cafe", "802-11-wireless", 5),
+        ];
+        sort_connections(&mut connections);
+
+        let changes = reorder_connections(&connections, "home-5", -1);
+        assert_eq!(
+            changes,
+            vec![(String::from("cafe"), 2), (String::from("home-5"), 1)]
+        );
+
+        // already at the top
+        assert!(reorder_connections(&connections, "cafe", -1).is_empty());
+        // only wired connection
+        assert!(reorder_connections(&connections, "wired", 1).is_empty());
+    }
+}
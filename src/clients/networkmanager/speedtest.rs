@@ -0,0 +1,231 @@
+use chrono::{DateTime, Local};
+use serde::{Serialize, Serializer};
+
+#[cfg(feature = "bar")]
+use color_eyre::eyre::{eyre, Result};
+#[cfg(feature = "bar")]
+use serde_json::Value;
+#[cfg(feature = "bar")]
+use std::process::Stdio;
+#[cfg(feature = "bar")]
+use tokio::process::Command;
+
+/// The speed test state kept between runs.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SpeedTest {
+    /// Whether a test is currently running.
+    pub running: bool,
+    /// The most recent successful result.
+    pub last_result: Option<SpeedTestResult>,
+}
+
+// only constructed when the networkmanager module runs a speed test
+#[cfg_attr(not(feature = "bar"), allow(dead_code))]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct SpeedTestResult {
+    /// Download speed in bits per second.
+    pub download: f64,
+    /// Upload speed in bits per second.
+    pub upload: f64,
+    /// Latency in milliseconds.
+    pub ping: f64,
+    /// When the test finished.
+    #[serde(serialize_with = "serialize_time")]
+    pub time: DateTime<Local>,
+}
+
+/// Serializes a timestamp as an RFC 3339 string.
+fn serialize_time<S: Serializer>(
+    time: &DateTime<Local>,
+    serializer: S,
+) -> std::result::Result<S::Ok, S::Error> {
+    serializer.serialize_str(&time.to_rfc3339())
+}
+
+/// The measurements parsed from a speed test command's output.
+#[cfg(feature = "bar")]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Measurements {
+    download: Option<f64>,
+    upload: Option<f64>,
+    ping: Option<f64>,
+}
+
+/// Runs the speed test command in a shell and parses its output.
+#[cfg(feature = "bar")]
+pub(crate) async fn run_speed_test(command: &str) -> Result<SpeedTestResult> {
+    let output = Command::new("sh")
+        .args(["-c", command])
+        .stdin(Stdio::null())
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(eyre!(
+            "Speed test command exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let measurements = parse_output(&stdout);
+
+    match measurements {
+        Measurements {
+            download: Some(download),
+            upload: Some(upload),
+            ping: Some(ping),
+        } => Ok(SpeedTestResult {
+            download,
+            upload,
+            ping,
+            time: Local::now(),
+        }),
+        _ => Err(eyre!("Failed to parse speed test output: {stdout}")),
+    }
+}
+
+/// Parses JSON output from `speedtest-cli --json` or Ookla's `speedtest -f json`,
+/// or otherwise the plain text output of `speedtest-cli --simple`.
+#[cfg(feature = "bar")]
+fn parse_output(output: &str) -> Measurements {
+    let output = output.trim();
+
+    if output.starts_with('{') {
+        serde_json::from_str::<Value>(output)
+            .map(|json| parse_json(&json))
+            .unwrap_or_default()
+    } else {
+        parse_text(output)
+    }
+}
+
+#[cfg(feature = "bar")]
+fn parse_json(json: &Value) -> Measurements {
+    // Ookla nests each measurement, with bandwidth in bytes per second
+    let ookla_bandwidth = |key: &str| {
+        json[key]["bandwidth"]
+            .as_f64()
+            .map(|bandwidth| bandwidth * 8.0)
+    };
+
+    Measurements {
+        download: ookla_bandwidth("download").or_else(|| json["download"].as_f64()),
+        upload: ookla_bandwidth("upload").or_else(|| json["upload"].as_f64()),
+        ping: json["ping"]["latency"]
+            .as_f64()
+            .or_else(|| json["ping"].as_f64()),
+    }
+}
+
+/// Parses `Key: value unit` lines, ie `Download: 93.21 Mbit/s`.
+#[cfg(feature = "bar")]
+fn parse_text(output: &str) -> Measurements {
+    let mut measurements = Measurements::default();
+
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        let mut parts = value.split_whitespace();
+        let Some(value) = parts.next().and_then(|value| value.parse::<f64>().ok()) else {
+            continue;
+        };
+        let unit = parts.next().unwrap_or_default();
+
+        match key.trim().to_lowercase().as_str() {
+            "download" => measurements.download = Some(value * bits_multiplier(unit)),
+            "upload" => measurements.upload = Some(value * bits_multiplier(unit)),
+            "ping" | "latency" => measurements.ping = Some(value),
+            _ => {}
+        }
+    }
+
+    measurements
+}
+
+/// Gets the number of bits per second in a unit of speed.
+/// Units without a recognised prefix are treated as bits per second.
+#[cfg(feature = "bar")]
+fn bits_multiplier(unit: &str) -> f64 {
+    let prefix = match unit.chars().next().map(|c| c.to_ascii_lowercase()) {
+        Some('k') => 1e3,
+        Some('m') => 1e6,
+        Some('g') => 1e9,
+        _ => 1.0,
+    };
+
+    // bytes are only distinguished from bits by case, ie `MB/s`
+    if unit.contains("B/s") || unit.to_lowercase().contains("byte") {
+        prefix * 8.0
+    } else {
+        prefix
+    }
+}
+
+#[cfg(all(test, feature = "bar"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_text() {
+        let output = "Ping: 12.345 ms\nDownload: 93.5 Mbit/s\nUpload: 10.5 Mbit/s\n";
+
+        assert_eq!(
+            parse_output(output),
+            Measurements {
+                download: Some(93.5e6),
+                upload: Some(10.5e6),
+                ping: Some(12.345),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_speedtest_cli_json() {
+        let output = r#"{"download": 93210000.0, "upload": 10500000.0, "ping": 12.3}"#;
+
+        assert_eq!(
+            parse_output(output),
+            Measurements {
+                download: Some(93_210_000.0),
+                upload: Some(10_500_000.0),
+                ping: Some(12.3),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_ookla_json() {
+        let output = r#"{
+            "ping": {"jitter": 0.5, "latency": 8.1},
+            "download": {"bandwidth": 12500000, "bytes": 1},
+            "upload": {"bandwidth": 2500000, "bytes": 1}
+        }"#;
+
+        assert_eq!(
+            parse_output(output),
+            Measurements {
+                download: Some(100e6),
+                upload: Some(20e6),
+                ping: Some(8.1),
+            }
+        );
+    }
+
+    #[test]
+    fn test_bits_multiplier() {
+        assert_eq!(bits_multiplier("Mbit/s"), 1e6);
+        assert_eq!(bits_multiplier("Mbps"), 1e6);
+        assert_eq!(bits_multiplier("MB/s"), 8e6);
+        assert_eq!(bits_multiplier("kbit/s"), 1e3);
+    }
+
+    #[test]
+    fn test_parse_incomplete() {
+        let measurements = parse_output("Download: 5 Mbit/s");
+        assert_eq!(measurements.upload, None);
+    }
+}
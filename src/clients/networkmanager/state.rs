@@ -1,79 +1,465 @@
+use std::fmt::{Display, Formatter};
+
 use color_eyre::Result;
+use serde::Serialize;
+use tracing::debug;
+use zbus::zvariant::ObjectPath;
+use zbus::Connection;
 
 use crate::clients::networkmanager::dbus::{
-    ActiveConnectionDbusProxyBlocking, DeviceDbusProxyBlocking, DeviceState, DeviceType,
+    AccessPointDbusProxy, ActiveConnectionDbusProxy, DbusProxy, DeviceDbusProxy, DeviceState,
+    DeviceType, DeviceWiredDbusProxy, DeviceWirelessDbusProxy, SettingsConnectionDbusProxy,
 };
+use crate::clients::networkmanager::dns::DnsState;
+use crate::clients::networkmanager::hotspot::{is_hotspot, HotspotState};
+use crate::clients::networkmanager::ip::{get_ip4_address, get_ip6_address, IpAddress};
+use crate::clients::networkmanager::modem::{get_modem_details, ModemDetails};
+use crate::clients::networkmanager::nl80211::StationInfo;
+use crate::clients::networkmanager::speedtest::SpeedTest;
+use crate::clients::networkmanager::statistics::{ConnectionUsage, Throughput};
+use crate::clients::networkmanager::vpn::{is_vpn_type, VpnConnection};
 use crate::clients::networkmanager::PathMap;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct State {
     pub wired: WiredState,
     pub wifi: WifiState,
     pub cellular: CellularState,
     pub vpn: VpnState,
+    /// The state of each wired, wifi, cellular and VPN interface,
+    /// ordered by name.
+    pub interfaces: Vec<InterfaceState>,
+    /// Saved VPN connections, and whether each is active.
+    pub vpn_connections: Vec<VpnConnection>,
+    /// Access points visible to any wifi device,
+    /// strongest first.
+    pub wifi_networks: Vec<WifiNetwork>,
+    /// Data transferred over each active connection.
+    pub data_usage: Vec<ConnectionUsage>,
+    /// The current download and upload rates across all devices.
+    pub throughput: Throughput,
+    /// Whether the internet can be reached.
+    pub connectivity: Connectivity,
+    /// The privacy of DNS queries, if systemd-resolved is in use.
+    pub dns: Option<DnsState>,
+    /// The last speed test result, and whether a test is running.
+    pub speed_test: SpeedTest,
+    /// Whether every radio is blocked,
+    /// or `None` if there are no radios or rfkill is unavailable.
+    pub airplane_mode: Option<bool>,
+    /// The signal strength and bitrates of the connected wifi network, as reported by the kernel.
+    /// This is only read after calling `Client::track_station_info`.
+    pub wifi_station: Option<StationInfo>,
+    /// The hotspot being run from a wifi device, if any.
+    pub hotspot: Option<HotspotState>,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WiredState {
-    Connected,
+    Connected(WiredConnectedState),
+    /// Connected, but the internet cannot be reached.
+    Limited(WiredConnectedState),
+    /// Connected, but traffic is blocked by a captive portal until signing in.
+    Portal(WiredConnectedState),
     Disconnected,
     NotPresent,
     Unknown,
 }
 
-#[derive(Clone, Debug)]
+impl WiredState {
+    /// Gets the details of the connection,
+    /// including while its connectivity is limited or behind a portal.
+    pub fn connected(&self) -> Option<&WiredConnectedState> {
+        match self {
+            Self::Connected(state) | Self::Limited(state) | Self::Portal(state) => Some(state),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WiredConnectedState {
+    /// The name of the device's network interface, such as `enp3s0`.
+    pub interface: String,
+    /// The negotiated link speed in Mbit/s, if known.
+    pub speed: Option<u32>,
+    pub ip4: Option<IpAddress>,
+    pub ip6: Option<IpAddress>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WifiState {
     Connected(WifiConnectedState),
+    /// Connected, but the internet cannot be reached.
+    Limited(WifiConnectedState),
+    /// Connected, but traffic is blocked by a captive portal until signing in.
+    Portal(WifiConnectedState),
+    Connecting(WifiConnectingState),
     Disconnected,
+    /// The last connection attempt failed
+    /// because credentials were missing or rejected.
+    AuthFailed,
     Disabled,
     NotPresent,
     Unknown,
 }
 
-#[derive(Clone, Debug)]
+impl WifiState {
+    /// Gets the details of the connection,
+    /// including while its connectivity is limited or behind a portal.
+    pub fn connected(&self) -> Option<&WifiConnectedState> {
+        match self {
+            Self::Connected(state) | Self::Limited(state) | Self::Portal(state) => Some(state),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
 pub struct WifiConnectedState {
     pub ssid: String,
+    /// The MAC address of the access point.
+    pub bssid: Option<String>,
+    /// Signal strength as a percentage.
+    pub strength: u8,
+    pub security: WifiSecurity,
+    /// Set for enterprise networks.
+    pub eap: Option<EapDetails>,
+    pub ip4: Option<IpAddress>,
+    pub ip6: Option<IpAddress>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WifiConnectingState {
+    /// The name of the connection being activated.
+    pub connection: String,
+    pub phase: ConnectionPhase,
+    /// Set for enterprise networks.
+    pub eap: Option<EapDetails>,
+}
+
+/// The stage an activating connection has reached.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionPhase {
+    /// Preparing the device to connect.
+    Preparing,
+    /// Associating and authenticating with the network,
+    /// including any 802.1X exchange.
+    Authenticating,
+    /// Waiting for credentials to be provided, such as by a secret agent.
+    NeedAuth,
+    /// Requesting an IP address and checking connectivity.
+    IpConfig,
+}
+
+impl ConnectionPhase {
+    fn from_device_state(state: &DeviceState) -> Option<Self> {
+        match state {
+            DeviceState::Prepare => Some(Self::Preparing),
+            DeviceState::Config => Some(Self::Authenticating),
+            DeviceState::NeedAuth => Some(Self::NeedAuth),
+            DeviceState::IpConfig | DeviceState::IpCheck | DeviceState::Secondaries => {
+                Some(Self::IpConfig)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Display for ConnectionPhase {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Preparing => "preparing",
+                Self::Authenticating => "authenticating",
+                Self::NeedAuth => "waiting for credentials",
+                Self::IpConfig => "configuring IP",
+            }
+        )
+    }
+}
+
+/// The 802.1X authentication methods of an enterprise connection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct EapDetails {
+    /// The outer EAP method, ie `peap` or `tls`.
+    pub method: String,
+    /// The inner (phase 2) authentication method, ie `mschapv2`.
+    pub phase2: Option<String>,
+}
+
+impl Display for EapDetails {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.phase2 {
+            Some(phase2) => write!(
+                f,
+                "{}/{}",
+                self.method.to_uppercase(),
+                phase2.to_uppercase()
+            ),
+            None => write!(f, "{}", self.method.to_uppercase()),
+        }
+    }
+}
+
+/// An access point found by scanning.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    /// Signal strength as a percentage.
+    pub strength: u8,
+    pub security: WifiSecurity,
 }
 
-#[derive(Clone, Debug)]
+/// The strongest security method supported by an access point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WifiSecurity {
+    Open,
+    Wep,
+    Wpa,
+    Wpa2,
+    Wpa3,
+    /// WPA/WPA2/WPA3-Enterprise, using 802.1X authentication.
+    Enterprise,
+    Unknown,
+}
+
+// See `NM80211ApFlags` and `NM80211ApSecurityFlags` in the NetworkManager API docs.
+const AP_FLAGS_PRIVACY: u32 = 0x1;
+const AP_SEC_KEY_MGMT_PSK: u32 = 0x100;
+const AP_SEC_KEY_MGMT_802_1X: u32 = 0x200;
+const AP_SEC_KEY_MGMT_SAE: u32 = 0x400;
+const AP_SEC_KEY_MGMT_OWE: u32 = 0x800;
+const AP_SEC_KEY_MGMT_OWE_TM: u32 = 0x1000;
+const AP_SEC_KEY_MGMT_EAP_SUITE_B_192: u32 = 0x2000;
+
+// See `NMDeviceStateReason` in the NetworkManager API docs.
+const REASON_NO_SECRETS: u32 = 7;
+const REASON_SUPPLICANT_DISCONNECT: u32 = 8;
+const REASON_SUPPLICANT_FAILED: u32 = 10;
+const REASON_SUPPLICANT_TIMEOUT: u32 = 11;
+
+/// Whether a device state reason means the credentials
+/// were missing or rejected by the network.
+fn is_auth_failure(reason: u32) -> bool {
+    matches!(
+        reason,
+        REASON_NO_SECRETS
+            | REASON_SUPPLICANT_DISCONNECT
+            | REASON_SUPPLICANT_FAILED
+            | REASON_SUPPLICANT_TIMEOUT
+    )
+}
+
+impl WifiSecurity {
+    /// Determines the security method from an access point's
+    /// `Flags`, `WpaFlags` and `RsnFlags` properties.
+    pub fn from_flags(flags: u32, wpa_flags: u32, rsn_flags: u32) -> Self {
+        let all_flags = wpa_flags | rsn_flags;
+
+        if all_flags & (AP_SEC_KEY_MGMT_802_1X | AP_SEC_KEY_MGMT_EAP_SUITE_B_192) != 0 {
+            Self::Enterprise
+        } else if rsn_flags & (AP_SEC_KEY_MGMT_SAE | AP_SEC_KEY_MGMT_OWE | AP_SEC_KEY_MGMT_OWE_TM)
+            != 0
+        {
+            Self::Wpa3
+        } else if rsn_flags & AP_SEC_KEY_MGMT_PSK != 0 {
+            Self::Wpa2
+        } else if wpa_flags & AP_SEC_KEY_MGMT_PSK != 0 {
+            Self::Wpa
+        } else if flags & AP_FLAGS_PRIVACY != 0 {
+            Self::Wep
+        } else {
+            Self::Open
+        }
+    }
+
+    #[cfg(feature = "bar")]
+    pub fn is_open(self) -> bool {
+        self == Self::Open
+    }
+}
+
+impl Display for WifiSecurity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Open => "Open",
+                Self::Wep => "WEP",
+                Self::Wpa => "WPA",
+                Self::Wpa2 => "WPA2",
+                Self::Wpa3 => "WPA3",
+                Self::Enterprise => "802.1X",
+                Self::Unknown => "Unknown",
+            }
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum CellularState {
-    Connected,
+    Connected(CellularConnectedState),
     Disconnected,
     Disabled,
     NotPresent,
     Unknown,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
+pub struct CellularConnectedState {
+    /// Signal, technology and carrier details.
+    /// Only set while ModemManager is available.
+    pub modem: Option<ModemDetails>,
+}
+
+/// The state of a single network interface.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct InterfaceState {
+    /// The interface name, ie `wlan0`.
+    pub name: String,
+    pub kind: InterfaceKind,
+    pub status: InterfaceStatus,
+    /// The name of the interface's active connection, if any.
+    pub connection: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceKind {
+    Wired,
+    Wifi,
+    Cellular,
+    /// A WireGuard or other tunnel interface.
+    Vpn,
+}
+
+impl InterfaceKind {
+    fn from_device_type(device_type: &DeviceType) -> Option<Self> {
+        match device_type {
+            DeviceType::Ethernet => Some(Self::Wired),
+            DeviceType::Wifi => Some(Self::Wifi),
+            DeviceType::Modem => Some(Self::Cellular),
+            DeviceType::Wireguard | DeviceType::Tun | DeviceType::IpTunnel => Some(Self::Vpn),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterfaceStatus {
+    Connected,
+    Connecting,
+    Disconnected,
+    /// The interface cannot be used, such as a wifi card with its radio off
+    /// or an ethernet port without a cable.
+    Unavailable,
+}
+
+impl InterfaceStatus {
+    fn from_device_state(state: &DeviceState) -> Self {
+        match state {
+            DeviceState::Activated => Self::Connected,
+            DeviceState::Disconnected | DeviceState::Deactivating | DeviceState::Failed => {
+                Self::Disconnected
+            }
+            DeviceState::Unknown | DeviceState::Unmanaged | DeviceState::Unavailable => {
+                Self::Unavailable
+            }
+            _ => Self::Connecting,
+        }
+    }
+}
+
+impl Display for InterfaceStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Connected => "connected",
+                Self::Connecting => "connecting",
+                Self::Disconnected => "disconnected",
+                Self::Unavailable => "unavailable",
+            }
+        )
+    }
+}
+
+/// Whether the internet can be reached, as found by NetworkManager's connectivity check.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Connectivity {
+    /// The check is disabled or has not run yet.
+    Unknown,
+    /// Not connected to any network.
+    None,
+    /// Traffic is blocked by a captive portal until signing in.
+    Portal {
+        /// The URL NetworkManager checks connectivity with.
+        /// The portal redirects this to its login page.
+        url: Option<String>,
+    },
+    /// Connected to a network, but the internet cannot be reached.
+    Limited,
+    Full,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum VpnState {
     Connected(VpnConnectedState),
     Disconnected,
     Unknown,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize)]
 pub struct VpnConnectedState {
+    /// The name of the active VPN connection.
+    /// If several are active, their names are separated by commas.
     pub name: String,
 }
 
-pub(super) fn determine_wired_state(
-    devices: &PathMap<DeviceDbusProxyBlocking>,
+pub(super) async fn determine_wired_state(
+    devices: &PathMap<DeviceDbusProxy>,
 ) -> Result<WiredState> {
     let mut present = false;
-    let mut connected = false;
+    let mut connected = None;
 
     for device in devices.values() {
-        if device.device_type()? == DeviceType::Ethernet {
+        if device.device_type().await? == DeviceType::Ethernet {
             present = true;
-            if device.state()?.is_enabled() {
-                connected = true;
-                break;
+            if device.state().await?.is_enabled() {
+                // prefer the device carrying a connection, for its address
+                if connected.is_none() || device.state().await? == DeviceState::Activated {
+                    connected = Some(device);
+                }
             }
         }
     }
 
-    if connected {
-        Ok(WiredState::Connected)
+    if let Some(device) = connected {
+        let state = WiredConnectedState {
+            interface: device.interface().await?.to_string(),
+            speed: get_link_speed(device).await,
+            ip4: get_ip4_address(device).await,
+            ip6: get_ip6_address(device).await,
+        };
+
+        Ok(match device_connectivity(device).await {
+            DeviceConnectivity::Full => WiredState::Connected(state),
+            DeviceConnectivity::Limited => WiredState::Limited(state),
+            DeviceConnectivity::Portal => WiredState::Portal(state),
+        })
     } else if present {
         Ok(WiredState::Disconnected)
     } else {
@@ -81,31 +467,108 @@ pub(super) fn determine_wired_state(
     }
 }
 
-pub(super) fn determine_wifi_state(
-    devices: &PathMap<DeviceDbusProxyBlocking>,
+pub(super) async fn determine_wifi_state(
+    devices: &PathMap<DeviceDbusProxy>,
+    wireless_enabled: bool,
 ) -> Result<WifiState> {
     let mut present = false;
     let mut enabled = false;
-    let mut connected = false;
+    let mut auth_failed = false;
+    let mut connected = None;
+    let mut connecting = None;
 
     for device in devices.values() {
-        if device.device_type()? == DeviceType::Wifi {
-            present = true;
-            if device.state()?.is_enabled() {
-                enabled = true;
-                if device.state()? == DeviceState::Activated {
-                    connected = true;
-                    break;
+        if device.device_type().await? != DeviceType::Wifi {
+            continue;
+        }
+
+        present = true;
+
+        let state = device.state().await?;
+        if !state.is_enabled() {
+            continue;
+        }
+
+        // a device running a hotspot is not connected to a network,
+        // so is shown as a hotspot instead
+        if state == DeviceState::Activated && is_hotspot(device).await? {
+            continue;
+        }
+
+        enabled = true;
+
+        match state {
+            DeviceState::Activated => {
+                connected = Some(device);
+                break;
+            }
+            // the failure reason is kept after the device returns to disconnected,
+            // until the next connection attempt
+            DeviceState::Failed | DeviceState::Disconnected => {
+                if is_auth_failure(device.state_reason().await?.1) {
+                    auth_failed = true;
+                }
+            }
+            state => {
+                if let Some(phase) = ConnectionPhase::from_device_state(&state) {
+                    connecting = Some((device, phase));
                 }
             }
         }
     }
 
-    if connected {
-        Ok(WifiState::Connected(WifiConnectedState {
-            // TODO: Implement obtaining SSID
-            ssid: "unknown".into(),
+    // devices can take a moment to disconnect after the radio is turned off
+    if present && !wireless_enabled {
+        Ok(WifiState::Disabled)
+    } else if let Some(device) = connected {
+        let wireless = wireless_proxy(device).await?;
+        let access_point = wireless.active_access_point().await?;
+        let eap = get_eap_details(device).await;
+        let ip4 = get_ip4_address(device).await;
+        let ip6 = get_ip6_address(device).await;
+
+        let state = if access_point.as_str() == "/" {
+            WifiConnectedState {
+                ssid: "unknown".into(),
+                bssid: None,
+                strength: 0,
+                security: WifiSecurity::Unknown,
+                eap,
+                ip4,
+                ip6,
+            }
+        } else {
+            let bssid = get_bssid(wireless.inner().connection(), &access_point).await;
+            let network = get_wifi_network(wireless.inner().connection(), access_point).await?;
+            WifiConnectedState {
+                ssid: network.ssid,
+                bssid,
+                strength: network.strength,
+                security: network.security,
+                eap,
+                ip4,
+                ip6,
+            }
+        };
+
+        Ok(match device_connectivity(device).await {
+            DeviceConnectivity::Full => WifiState::Connected(state),
+            DeviceConnectivity::Limited => WifiState::Limited(state),
+            DeviceConnectivity::Portal => WifiState::Portal(state),
+        })
+    } else if let Some((device, phase)) = connecting {
+        let connection = match active_connection_proxy(device).await? {
+            Some(active_connection) => active_connection.id().await?.to_string(),
+            None => "unknown".into(),
+        };
+
+        Ok(WifiState::Connecting(WifiConnectingState {
+            connection,
+            phase,
+            eap: get_eap_details(device).await,
         }))
+    } else if auth_failed {
+        Ok(WifiState::AuthFailed)
     } else if enabled {
         Ok(WifiState::Disconnected)
     } else if present {
@@ -115,28 +578,230 @@ pub(super) fn determine_wifi_state(
     }
 }
 
-pub(super) fn determine_cellular_state(
-    devices: &PathMap<DeviceDbusProxyBlocking>,
+pub(super) async fn determine_interfaces(
+    devices: &PathMap<DeviceDbusProxy>,
+) -> Result<Vec<InterfaceState>> {
+    let mut interfaces = vec![];
+
+    for device in devices.values() {
+        let Some(kind) = InterfaceKind::from_device_type(&device.device_type().await?) else {
+            continue;
+        };
+
+        // tunnels created by other tools, such as container runtimes,
+        // are left unmanaged by NetworkManager
+        let state = device.state().await?;
+        if state == DeviceState::Unmanaged {
+            continue;
+        }
+
+        let connection = match active_connection_proxy(device).await? {
+            Some(active_connection) => Some(active_connection.id().await?.to_string()),
+            None => None,
+        };
+
+        interfaces.push(InterfaceState {
+            name: device.interface().await?.to_string(),
+            kind,
+            status: InterfaceStatus::from_device_state(&state),
+            connection,
+        });
+    }
+
+    interfaces.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(interfaces)
+}
+
+pub(super) async fn determine_wifi_networks(
+    devices: &PathMap<DeviceDbusProxy>,
+) -> Result<Vec<WifiNetwork>> {
+    let mut networks: Vec<WifiNetwork> = vec![];
+
+    for device in devices.values() {
+        if device.device_type().await? != DeviceType::Wifi {
+            continue;
+        }
+
+        let wireless = wireless_proxy(device).await?;
+        for path in wireless.access_points().await? {
+            let network = get_wifi_network(wireless.inner().connection(), path).await?;
+
+            // hidden networks do not broadcast an SSID
+            if network.ssid.is_empty() {
+                continue;
+            }
+
+            // the same network may be broadcast by several access points,
+            // so only keep the strongest one.
+            match networks.iter_mut().find(|n| n.ssid == network.ssid) {
+                Some(existing) if existing.strength < network.strength => *existing = network,
+                Some(_) => {}
+                None => networks.push(network),
+            }
+        }
+    }
+
+    networks.sort_by(|a, b| b.strength.cmp(&a.strength));
+    Ok(networks)
+}
+
+/// Gets the wireless interface proxy for a wifi device.
+pub(super) async fn wireless_proxy(
+    device: &DeviceDbusProxy,
+) -> Result<DeviceWirelessDbusProxy<'static>> {
+    let proxy = DeviceWirelessDbusProxy::builder(device.inner().connection())
+        .path(device.inner().path().to_owned())?
+        .build()
+        .await?;
+
+    Ok(proxy)
+}
+
+/// Gets the negotiated link speed of an ethernet device,
+/// if the driver reports it.
+async fn get_link_speed(device: &DeviceDbusProxy) -> Option<u32> {
+    read_link_speed(device).await.unwrap_or_else(|err| {
+        debug!("Failed to read link speed: {err:?}");
+        None
+    })
+}
+
+async fn read_link_speed(device: &DeviceDbusProxy) -> Result<Option<u32>> {
+    let wired = DeviceWiredDbusProxy::builder(device.inner().connection())
+        .path(device.inner().path().to_owned())?
+        .build()
+        .await?;
+
+    // the speed is 0 while it is unknown
+    let speed = wired.speed().await?;
+    Ok((speed > 0).then_some(speed))
+}
+
+/// Gets the proxy for a device's active connection, if it has one.
+pub(super) async fn active_connection_proxy(
+    device: &DeviceDbusProxy,
+) -> Result<Option<ActiveConnectionDbusProxy<'static>>> {
+    let path = device.active_connection().await?;
+    if path.as_str() == "/" {
+        return Ok(None);
+    }
+
+    let proxy = ActiveConnectionDbusProxy::builder(device.inner().connection())
+        .path(path.into_owned())?
+        .build()
+        .await?;
+
+    Ok(Some(proxy))
+}
+
+/// Gets the 802.1X methods of a device's active connection,
+/// if it uses enterprise authentication.
+///
+/// Failing to read the connection settings does not prevent
+/// the wifi state being determined, as they may not be visible to the current user.
+async fn get_eap_details(device: &DeviceDbusProxy) -> Option<EapDetails> {
+    read_eap_details(device).await.unwrap_or_else(|err| {
+        debug!("Failed to read 802.1X settings: {err:?}");
+        None
+    })
+}
+
+async fn read_eap_details(device: &DeviceDbusProxy) -> Result<Option<EapDetails>> {
+    let Some(active_connection) = active_connection_proxy(device).await? else {
+        return Ok(None);
+    };
+
+    let settings = SettingsConnectionDbusProxy::builder(device.inner().connection())
+        .path(active_connection.connection().await?.into_owned())?
+        .build()
+        .await?
+        .get_settings()
+        .await?;
+
+    let Some(security) = settings.get("802-1x") else {
+        return Ok(None);
+    };
+
+    let Some(method) = security
+        .get("eap")
+        .and_then(|value| Vec::<String>::try_from(value.clone()).ok())
+        .and_then(|methods| methods.into_iter().next())
+    else {
+        return Ok(None);
+    };
+
+    // unused keys are usually present but empty
+    let phase2 = ["phase2-auth", "phase2-autheap"]
+        .iter()
+        .find_map(|key| {
+            security
+                .get(*key)
+                .and_then(|value| <&str>::try_from(value).ok())
+                .filter(|phase2| !phase2.is_empty())
+        })
+        .map(ToString::to_string);
+
+    Ok(Some(EapDetails { method, phase2 }))
+}
+
+async fn get_wifi_network(connection: &Connection, path: ObjectPath) -> Result<WifiNetwork> {
+    let access_point = AccessPointDbusProxy::builder(connection)
+        .path(path)?
+        .build()
+        .await?;
+
+    Ok(WifiNetwork {
+        ssid: String::from_utf8_lossy(&access_point.ssid().await?).into_owned(),
+        strength: access_point.strength().await?,
+        security: WifiSecurity::from_flags(
+            access_point.flags().await?,
+            access_point.wpa_flags().await?,
+            access_point.rsn_flags().await?,
+        ),
+    })
+}
+
+async fn get_bssid(connection: &Connection, path: &ObjectPath) -> Option<String> {
+    read_bssid(connection, path).await.unwrap_or_else(|err| {
+        debug!("Failed to read access point BSSID: {err:?}");
+        None
+    })
+}
+
+async fn read_bssid(connection: &Connection, path: &ObjectPath) -> Result<Option<String>> {
+    let access_point = AccessPointDbusProxy::builder(connection)
+        .path(path.to_owned())?
+        .build()
+        .await?;
+
+    let bssid = access_point.hw_address().await?.to_string();
+    Ok(Some(bssid).filter(|bssid| !bssid.is_empty()))
+}
+
+pub(super) async fn determine_cellular_state(
+    devices: &PathMap<DeviceDbusProxy>,
 ) -> Result<CellularState> {
     let mut present = false;
     let mut enabled = false;
-    let mut connected = false;
+    let mut connected = None;
 
     for device in devices.values() {
-        if device.device_type()? == DeviceType::Modem {
+        if device.device_type().await? == DeviceType::Modem {
             present = true;
-            if device.state()?.is_enabled() {
+            if device.state().await?.is_enabled() {
                 enabled = true;
-                if device.state()? == DeviceState::Activated {
-                    connected = true;
+                if device.state().await? == DeviceState::Activated {
+                    connected = Some(device);
                     break;
                 }
             }
         }
     }
 
-    if connected {
-        Ok(CellularState::Connected)
+    if let Some(device) = connected {
+        Ok(CellularState::Connected(CellularConnectedState {
+            modem: get_modem_details(device.inner().connection(), &device.udi().await?).await,
+        }))
     } else if enabled {
         Ok(CellularState::Disconnected)
     } else if present {
@@ -146,18 +811,125 @@ pub(super) fn determine_cellular_state(
     }
 }
 
-pub(super) fn determine_vpn_state(
-    active_connections: &PathMap<ActiveConnectionDbusProxyBlocking>,
+// See `NMConnectivityState` in the NetworkManager API docs.
+const CONNECTIVITY_UNKNOWN: u32 = 0;
+const CONNECTIVITY_NONE: u32 = 1;
+const CONNECTIVITY_PORTAL: u32 = 2;
+const CONNECTIVITY_LIMITED: u32 = 3;
+const CONNECTIVITY_FULL: u32 = 4;
+
+/// The connectivity of an activated device.
+enum DeviceConnectivity {
+    Full,
+    Limited,
+    Portal,
+}
+
+/// Gets the connectivity of an activated device,
+/// from the best result of its IPv4 and IPv6 connectivity checks.
+///
+/// Connectivity is assumed to be full if the check is disabled or has not run yet,
+/// so that connections are not shown as limited without reason.
+async fn device_connectivity(device: &DeviceDbusProxy) -> DeviceConnectivity {
+    // only available from NetworkManager 1.16
+    let ip4 = device.ip4_connectivity().await.unwrap_or_default();
+    let ip6 = device.ip6_connectivity().await.unwrap_or_default();
+
+    match ip4.max(ip6) {
+        // one check has not run yet, so the other may be for an unconfigured family
+        CONNECTIVITY_NONE if ip4.min(ip6) == CONNECTIVITY_UNKNOWN => DeviceConnectivity::Full,
+        CONNECTIVITY_NONE | CONNECTIVITY_LIMITED => DeviceConnectivity::Limited,
+        CONNECTIVITY_PORTAL => DeviceConnectivity::Portal,
+        _ => DeviceConnectivity::Full,
+    }
+}
+
+pub(super) async fn determine_connectivity(root_object: &DbusProxy) -> Result<Connectivity> {
+    Ok(match root_object.connectivity().await? {
+        CONNECTIVITY_NONE => Connectivity::None,
+        CONNECTIVITY_PORTAL => Connectivity::Portal {
+            // only available from NetworkManager 1.20
+            url: root_object
+                .connectivity_check_uri()
+                .await
+                .ok()
+                .map(|url| url.to_string())
+                .filter(|url| !url.is_empty()),
+        },
+        CONNECTIVITY_LIMITED => Connectivity::Limited,
+        CONNECTIVITY_FULL => Connectivity::Full,
+        _ => Connectivity::Unknown,
+    })
+}
+
+pub(super) async fn determine_vpn_state(
+    active_connections: &PathMap<ActiveConnectionDbusProxy>,
 ) -> Result<VpnState> {
+    let mut names = vec![];
+
     for connection in active_connections.values() {
-        match connection.type_()?.as_str() {
-            "vpn" | "wireguard" => {
-                return Ok(VpnState::Connected(VpnConnectedState {
-                    name: "unknown".into(),
-                }));
-            }
-            _ => {}
+        if is_vpn_type(&connection.type_().await?) {
+            names.push(connection.id().await?.to_string());
         }
     }
-    Ok(VpnState::Disconnected)
+
+    if names.is_empty() {
+        Ok(VpnState::Disconnected)
+    } else {
+        // the order of active connections is not stable
+        names.sort();
+
+        Ok(VpnState::Connected(VpnConnectedState {
+            name: names.join(", "),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_security_from_flags() {
+        assert_eq!(WifiSecurity::from_flags(0, 0, 0), WifiSecurity::Open);
+        assert_eq!(
+            WifiSecurity::from_flags(AP_FLAGS_PRIVACY, 0, 0),
+            WifiSecurity::Wep
+        );
+        assert_eq!(
+            WifiSecurity::from_flags(AP_FLAGS_PRIVACY, AP_SEC_KEY_MGMT_PSK, 0),
+            WifiSecurity::Wpa
+        );
+        assert_eq!(
+            WifiSecurity::from_flags(AP_FLAGS_PRIVACY, AP_SEC_KEY_MGMT_PSK, AP_SEC_KEY_MGMT_PSK),
+            WifiSecurity::Wpa2
+        );
+        assert_eq!(
+            WifiSecurity::from_flags(
+                AP_FLAGS_PRIVACY,
+                0,
+                AP_SEC_KEY_MGMT_PSK | AP_SEC_KEY_MGMT_SAE
+            ),
+            WifiSecurity::Wpa3
+        );
+        assert_eq!(
+            WifiSecurity::from_flags(AP_FLAGS_PRIVACY, 0, AP_SEC_KEY_MGMT_802_1X),
+            WifiSecurity::Enterprise
+        );
+    }
+
+    #[test]
+    fn test_eap_details_display() {
+        let eap = EapDetails {
+            method: "peap".into(),
+            phase2: Some("mschapv2".into()),
+        };
+        assert_eq!(eap.to_string(), "PEAP/MSCHAPV2");
+
+        let eap = EapDetails {
+            method: "tls".into(),
+            phase2: None,
+        };
+        assert_eq!(eap.to_string(), "TLS");
+    }
 }
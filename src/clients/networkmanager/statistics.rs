@@ -0,0 +1,267 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+use color_eyre::Result;
+use serde::Serialize;
+use zbus::zvariant::OwnedObjectPath;
+
+use crate::clients::networkmanager::dbus::ActiveConnectionDbusProxy;
+use crate::clients::networkmanager::PathMap;
+use crate::write_lock;
+
+/// How often NetworkManager should update device byte counters.
+pub(super) const REFRESH_RATE_MS: u32 = 1000;
+
+/// How long a device's transfer rate is kept without its counters changing.
+/// Counter changes are only emitted while data is being transferred,
+/// so after this the device is assumed to be idle.
+const RATE_TIMEOUT: Duration = Duration::from_millis(REFRESH_RATE_MS as u64 * 2);
+
+/// Data transferred over an active connection since it was activated.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct ConnectionUsage {
+    pub id: String,
+    /// The NetworkManager connection type, ie `802-11-wireless`.
+    pub kind: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+#[cfg(feature = "bar")]
+impl ConnectionUsage {
+    /// Whether the connection tunnels over another connection,
+    /// meaning its traffic is also counted against that.
+    pub fn is_tunnel(&self) -> bool {
+        matches!(self.kind.as_str(), "vpn" | "wireguard")
+    }
+}
+
+/// The current transfer rate, in bytes per second.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct Throughput {
+    pub rx_rate: u64,
+    pub tx_rate: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(super) struct Counters {
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
+
+/// Tracks the latest byte counters for each device,
+/// and the counters each active connection started from.
+#[derive(Debug, Default)]
+pub(super) struct Statistics {
+    devices: HashMap<OwnedObjectPath, Counters>,
+    /// Keyed by active connection and device path.
+    baselines: HashMap<(OwnedObjectPath, OwnedObjectPath), Counters>,
+    /// The transfer rate of each device, and when its counters were last updated.
+    rates: HashMap<OwnedObjectPath, (Instant, Throughput)>,
+    /// Devices which tunnel over other devices,
+    /// meaning their traffic is also counted on those.
+    tunnels: HashSet<OwnedObjectPath>,
+}
+
+impl Statistics {
+    /// Sets the latest counters for a device,
+    /// working out its transfer rate since the previous counters.
+    pub fn set_counters(&mut self, device: OwnedObjectPath, counters: Counters, now: Instant) {
+        let previous = self.devices.insert(device.clone(), counters);
+        let updated = self.rates.get(&device).map(|(updated, _)| *updated);
+
+        let throughput = match (previous, updated) {
+            (Some(previous), Some(updated)) if now > updated => {
+                let seconds = (now - updated).as_secs_f64();
+                let rate = |current: u64, previous: u64| {
+                    // counters reset if the device is re-created
+                    (current.saturating_sub(previous) as f64 / seconds) as u64
+                };
+
+                Throughput {
+                    rx_rate: rate(counters.rx_bytes, previous.rx_bytes),
+                    tx_rate: rate(counters.tx_bytes, previous.tx_bytes),
+                }
+            }
+            _ => Throughput::default(),
+        };
+
+        self.rates.insert(device, (now, throughput));
+    }
+
+    /// Excludes a tunnel device, such as a VPN, from the total transfer rate.
+    pub fn set_tunnel(&mut self, device: OwnedObjectPath) {
+        self.tunnels.insert(device);
+    }
+
+    /// Gets the total transfer rate across all devices other than tunnels.
+    /// Devices which have not updated recently are treated as idle.
+    pub fn throughput(&self, now: Instant) -> Throughput {
+        self.rates
+            .iter()
+            .filter(|(device, _)| !self.tunnels.contains(*device))
+            .map(|(_, rate)| rate)
+            .filter(|(updated, _)| now.saturating_duration_since(*updated) < RATE_TIMEOUT)
+            .fold(Throughput::default(), |total, (_, rate)| Throughput {
+                rx_rate: total.rx_rate + rate.rx_rate,
+                tx_rate: total.tx_rate + rate.tx_rate,
+            })
+    }
+
+    /// Gets the usage of an active connection across its devices,
+    /// recording the starting counters for any device not seen on it before.
+    ///
+    /// Devices which have not reported any counters yet are skipped,
+    /// so that they are not counted from zero.
+    fn usage(&mut self, connection: &OwnedObjectPath, devices: &[OwnedObjectPath]) -> Counters {
+        let mut usage = Counters::default();
+
+        for device in devices {
+            let Some(current) = self.devices.get(device).copied() else {
+                continue;
+            };
+
+            let baseline = *self
+                .baselines
+                .entry((connection.clone(), device.clone()))
+                .or_insert(current);
+
+            // counters reset if the device is re-created,
+            // in which case everything so far is lost
+            usage.rx_bytes += current.rx_bytes.saturating_sub(baseline.rx_bytes);
+            usage.tx_bytes += current.tx_bytes.saturating_sub(baseline.tx_bytes);
+        }
+
+        usage
+    }
+
+    /// Forgets the starting counters for connections which are no longer active,
+    /// so that the usage resets when they are next activated.
+    fn retain_connections(&mut self, connections: &[OwnedObjectPath]) {
+        self.baselines
+            .retain(|(connection, _), _| connections.contains(connection));
+    }
+}
+
+pub(super) async fn determine_data_usage(
+    active_connections: &PathMap<ActiveConnectionDbusProxy>,
+    statistics: &RwLock<Statistics>,
+) -> Result<Vec<ConnectionUsage>> {
+    let mut connections = vec![];
+
+    for (path, connection) in active_connections {
+        let devices = connection
+            .devices()
+            .await?
+            .into_iter()
+            .map(OwnedObjectPath::from)
+            .collect::<Vec<_>>();
+
+        connections.push((
+            path.clone(),
+            devices,
+            connection.id().await?.to_string(),
+            connection.type_().await?.to_string(),
+        ));
+    }
+
+    // the lock is only taken once everything is read,
+    // as it cannot be held across awaits
+    let mut statistics = write_lock!(statistics);
+
+    let mut usages = vec![];
+    let mut paths = vec![];
+
+    for (path, devices, id, kind) in connections {
+        let counters = statistics.usage(&path, &devices);
+        paths.push(path);
+
+        usages.push(ConnectionUsage {
+            id,
+            kind,
+            rx_bytes: counters.rx_bytes,
+            tx_bytes: counters.tx_bytes,
+        });
+    }
+
+    statistics.retain_connections(&paths);
+
+    usages.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(usages)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zbus::zvariant::ObjectPath;
+
+    fn path(path: &'static str) -> OwnedObjectPath {
+        ObjectPath::from_static_str_unchecked(path).into()
+    }
+
+    #[test]
+    fn test_usage_resets_on_reconnect() {
+        let device = path("/org/freedesktop/NetworkManager/Devices/1");
+        let first = path("/org/freedesktop/NetworkManager/ActiveConnection/1");
+        let second = path("/org/freedesktop/NetworkManager/ActiveConnection/2");
+
+        let counters = |rx_bytes, tx_bytes| Counters { rx_bytes, tx_bytes };
+
+        let mut statistics = Statistics::default();
+        let devices = [device.clone()];
+        let now = Instant::now();
+
+        // no counters reported yet
+        assert_eq!(statistics.usage(&first, &devices), Counters::default());
+
+        statistics.set_counters(device.clone(), counters(1000, 100), now);
+        assert_eq!(statistics.usage(&first, &devices), counters(0, 0));
+
+        statistics.set_counters(device.clone(), counters(1500, 300), now);
+        assert_eq!(statistics.usage(&first, &devices), counters(500, 200));
+
+        statistics.retain_connections(&[]);
+
+        statistics.set_counters(device.clone(), counters(2000, 400), now);
+        assert_eq!(statistics.usage(&second, &devices), counters(0, 0));
+
+        statistics.set_counters(device, counters(2100, 450), now);
+        assert_eq!(statistics.usage(&second, &devices), counters(100, 50));
+    }
+
+    #[test]
+    fn test_throughput() {
+        let wired = path("/org/freedesktop/NetworkManager/Devices/1");
+        let wifi = path("/org/freedesktop/NetworkManager/Devices/2");
+
+        let counters = |rx_bytes, tx_bytes| Counters { rx_bytes, tx_bytes };
+        let rate = |rx_rate, tx_rate| Throughput { rx_rate, tx_rate };
+
+        let mut statistics = Statistics::default();
+        let start = Instant::now();
+        let after = |millis| start + Duration::from_millis(millis);
+
+        // the first counters have nothing to compare against
+        statistics.set_counters(wired.clone(), counters(1000, 100), start);
+        assert_eq!(statistics.throughput(start), rate(0, 0));
+
+        statistics.set_counters(wired.clone(), counters(3000, 600), after(1000));
+        assert_eq!(statistics.throughput(after(1000)), rate(2000, 500));
+
+        statistics.set_counters(wifi.clone(), counters(0, 0), after(1000));
+        statistics.set_counters(wifi, counters(250, 50), after(1500));
+        assert_eq!(statistics.throughput(after(1500)), rate(2500, 600));
+
+        // idle devices stop updating
+        assert_eq!(statistics.throughput(after(3000)), rate(500, 100));
+        assert_eq!(statistics.throughput(after(5000)), rate(0, 0));
+
+        // tunnelled traffic is already counted on the underlying device
+        let vpn = path("/org/freedesktop/NetworkManager/Devices/3");
+        statistics.set_tunnel(vpn.clone());
+        statistics.set_counters(vpn.clone(), counters(0, 0), after(5000));
+        statistics.set_counters(vpn, counters(1000, 1000), after(6000));
+        assert_eq!(statistics.throughput(after(6000)), rate(0, 0));
+    }
+}
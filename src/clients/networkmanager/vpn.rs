@@ -0,0 +1,87 @@
+use std::collections::HashSet;
+
+use color_eyre::Result;
+use serde::Serialize;
+#[cfg(feature = "bar")]
+use tracing::debug;
+#[cfg(feature = "bar")]
+use zbus::zvariant::ObjectPath;
+use zbus::Connection;
+
+use crate::clients::networkmanager::dbus::ActiveConnectionDbusProxy;
+use crate::clients::networkmanager::settings::list_saved_connections;
+#[cfg(feature = "bar")]
+use crate::clients::networkmanager::Client;
+use crate::clients::networkmanager::PathMap;
+
+/// A saved VPN or WireGuard connection.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct VpnConnection {
+    /// The D-Bus object path of the saved connection.
+    pub path: String,
+    pub id: String,
+    /// Whether the connection is active or activating.
+    pub active: bool,
+}
+
+/// Whether a connection type is a VPN.
+pub(super) fn is_vpn_type(kind: &str) -> bool {
+    matches!(kind, "vpn" | "wireguard")
+}
+
+#[cfg(feature = "bar")]
+impl Client {
+    /// Activates or deactivates the saved VPN connection at `path`.
+    pub async fn set_vpn_active(&self, path: &str, active: bool) -> Result<()> {
+        let path = ObjectPath::try_from(path)?;
+
+        if active {
+            debug!("Activating VPN connection '{path}'");
+
+            // VPNs are not tied to a device,
+            // so NetworkManager picks the one carrying the default route
+            let none = ObjectPath::from_static_str_unchecked("/");
+            self.0
+                .root_object
+                .activate_connection(&path, &none, &none)
+                .await?;
+        } else {
+            debug!("Deactivating VPN connection '{path}'");
+
+            for (active_path, active) in self.0.active_connections() {
+                if active.connection().await.is_ok_and(|saved| saved == path) {
+                    self.0
+                        .root_object
+                        .deactivate_connection(&active_path)
+                        .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets the saved VPN connections, and whether each is active.
+pub(super) async fn determine_vpn_connections(
+    dbus_connection: &Connection,
+    active_connections: &PathMap<ActiveConnectionDbusProxy>,
+) -> Result<Vec<VpnConnection>> {
+    let mut active = HashSet::new();
+    for connection in active_connections.values() {
+        active.insert(connection.connection().await?.to_string());
+    }
+
+    let connections = list_saved_connections(dbus_connection)
+        .await?
+        .into_iter()
+        .filter(|connection| is_vpn_type(&connection.kind))
+        .map(|connection| VpnConnection {
+            active: active.contains(&connection.path),
+            path: connection.path,
+            id: connection.id,
+        })
+        .collect();
+
+    Ok(connections)
+}
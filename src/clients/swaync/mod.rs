@@ -3,13 +3,15 @@ mod dbus;
 use crate::{register_fallible_client, send, spawn};
 use color_eyre::{Report, Result};
 use dbus::SwayNcProxy;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
-use tracing::{debug, error};
+use tracing::debug;
+#[cfg(feature = "bar")]
+use tracing::error;
 use zbus::export::ordered_stream::OrderedStreamExt;
 use zbus::zvariant::Type;
 
-#[derive(Debug, Clone, Copy, Type, Deserialize)]
+#[derive(Debug, Clone, Copy, Type, Serialize, Deserialize)]
 pub struct Event {
     pub count: u32,
     pub dnd: bool,
@@ -63,6 +65,7 @@ impl Client {
         Ok(Self { proxy, tx, _rx: rx })
     }
 
+    #[cfg(feature = "bar")]
     pub fn subscribe(&self) -> broadcast::Receiver<Event> {
         self.tx.subscribe()
     }
@@ -75,6 +78,7 @@ impl Client {
         }
     }
 
+    #[cfg(feature = "bar")]
     pub async fn toggle_visibility(&self) {
         debug!("Toggling visibility");
         if let Err(err) = self.proxy.toggle_visibility().await {
@@ -84,3 +88,11 @@ impl Client {
 }
 
 register_fallible_client!(Client, notifications);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        let state = crate::await_sync(self.state())?;
+        Ok(serde_json::to_value(state)?)
+    }
+}
@@ -1,4 +1,430 @@
-use crate::register_fallible_client;
-pub use system_tray::client::Client;
+use crate::{arc_mut, lock, register_fallible_client, send, spawn};
+use cfg_if::cfg_if;
+use color_eyre::{Report, Result};
+use futures_lite::StreamExt;
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use system_tray::client::{Event, UpdateEvent};
+use system_tray::item::StatusNotifierItem;
+use system_tray::menu::TrayMenu;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::sleep;
+use tracing::{debug, error, warn};
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+
+cfg_if! {
+    if #[cfg(feature = "bar")] {
+        use std::env;
+        use std::sync::atomic::AtomicBool;
+        use system_tray::client::ActivateRequest;
+        use system_tray::item::IconPixmap;
+        use tokio::process::Command;
+        use zbus::{CacheProperties, Proxy, ProxyBuilder};
+    }
+}
+
+type TrayClient = system_tray::client::Client;
+type State = HashMap<String, (StatusNotifierItem, Option<TrayMenu>)>;
+
+/// The `ToolTip` property, as icon name, icon pixmaps, title and description.
+#[cfg(feature = "bar")]
+type RawTooltip = (String, Vec<(i32, i32, Vec<u8>)>, String, String);
+
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+#[cfg(feature = "bar")]
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+#[cfg(feature = "bar")]
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+#[cfg(feature = "bar")]
+const DEFAULT_ITEM_PATH: &str = "/StatusNotifierItem";
+
+/// Process group ID of the running XEmbed proxy,
+/// or `0` if it is not running.
+static XEMBED_PROXY_PGID: AtomicI32 = AtomicI32::new(0);
+
+/// The maximum time to wait between attempts
+/// to re-create the tray client.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Wrapper around the `system-tray` client,
+/// which re-creates it whenever the `StatusNotifierWatcher` restarts or changes owner.
+///
+/// Without this, the host registration and item list are lost when the watcher goes away,
+/// leaving the tray empty until Ironbar is restarted.
+#[derive(Debug)]
+pub struct Client {
+    inner: Arc<Mutex<Arc<TrayClient>>>,
+    tx: broadcast::Sender<Event>,
+    _rx: broadcast::Receiver<Event>,
+    #[cfg(feature = "bar")]
+    xembed_proxy_started: AtomicBool,
+    #[cfg(feature = "bar")]
+    connection: zbus::Connection,
+}
+
+/// The tooltip of a tray item.
+#[cfg(feature = "bar")]
+#[derive(Debug, Clone)]
+pub struct Tooltip {
+    pub icon_name: String,
+    pub icon_pixmap: Vec<IconPixmap>,
+    pub title: String,
+    /// The tooltip body.
+    /// This may contain a basic subset of HTML markup.
+    pub description: String,
+}
+
+#[cfg(feature = "bar")]
+impl From<RawTooltip> for Tooltip {
+    fn from((icon_name, icon_pixmap, title, description): RawTooltip) -> Self {
+        Self {
+            icon_name,
+            icon_pixmap: icon_pixmap
+                .into_iter()
+                .map(|(width, height, pixels)| IconPixmap {
+                    width,
+                    height,
+                    pixels,
+                })
+                .collect(),
+            title,
+            description,
+        }
+    }
+}
+
+#[cfg(feature = "bar")]
+impl Tooltip {
+    fn is_empty(&self) -> bool {
+        self.icon_name.is_empty()
+            && self.icon_pixmap.is_empty()
+            && self.title.is_empty()
+            && self.description.is_empty()
+    }
+}
+
+impl Client {
+    pub async fn new(service_name: &str) -> Result<Self> {
+        let (tx, rx) = broadcast::channel(32);
+
+        let client = Arc::new(TrayClient::new(service_name).await?);
+        let forwarder = forward_events(&client, tx.clone());
+
+        let inner = arc_mut!(client);
+
+        let connection = zbus::Connection::session().await?;
+        let dbus = DBusProxy::new(&connection).await?;
+
+        let mut owner_changes = dbus
+            .receive_name_owner_changed_with_args(&[(0, WATCHER_NAME)])
+            .await?;
+
+        {
+            let inner = inner.clone();
+            let tx = tx.clone();
+            let service_name = service_name.to_string();
+
+            spawn(async move {
+                let mut forwarder = forwarder;
+                let mut owner = get_watcher_owner(&dbus).await;
+
+                while let Some(change) = owner_changes.next().await {
+                    let args = change.args()?;
+                    let new_owner = args.new_owner().as_ref().map(ToString::to_string);
+
+                    // ignore the watcher name being acquired by our own client
+                    if new_owner.is_some() && new_owner == owner {
+                        continue;
+                    }
+
+                    match &new_owner {
+                        Some(new_owner) => {
+                            debug!("{WATCHER_NAME} is now owned by '{new_owner}', re-registering tray host");
+                        }
+                        None => warn!("{WATCHER_NAME} went away, restarting tray host"),
+                    }
+
+                    forwarder.abort();
+
+                    let old_client = lock!(inner).clone();
+                    for address in lock!(old_client.items()).keys() {
+                        send!(tx, Event::Remove(address.clone()));
+                    }
+
+                    let client = create_client(&service_name).await;
+                    forwarder = forward_events(&client, tx.clone());
+
+                    let items = lock!(client.items()).clone();
+                    for (address, (item, menu)) in items {
+                        send!(tx, Event::Add(address.clone(), item.into()));
+
+                        if let Some(menu) = menu {
+                            send!(tx, Event::Update(address, UpdateEvent::Menu(menu)));
+                        }
+                    }
+
+                    *lock!(inner) = client;
+                    owner = get_watcher_owner(&dbus).await;
+                }
+
+                Ok::<_, Report>(())
+            });
+        }
+
+        Ok(Self {
+            inner,
+            tx,
+            _rx: rx,
+            #[cfg(feature = "bar")]
+            xembed_proxy_started: AtomicBool::new(false),
+            #[cfg(feature = "bar")]
+            connection,
+        })
+    }
+
+    /// Subscribes to tray events.
+    /// Events continue to be received across restarts of the underlying client.
+    #[cfg(feature = "bar")]
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.tx.subscribe()
+    }
+
+    /// Gets all current items, including their menus if present.
+    pub fn items(&self) -> Arc<Mutex<State>> {
+        lock!(self.inner).items()
+    }
+
+    /// Sends an activate request for a menu item.
+    #[cfg(feature = "bar")]
+    pub async fn activate(&self, req: ActivateRequest) -> system_tray::error::Result<()> {
+        let client = lock!(self.inner).clone();
+        client.activate(req).await
+    }
+
+    /// Gets the current tooltip for the item at `address`.
+    ///
+    /// The tooltip is not tracked by the underlying client,
+    /// so this is fetched directly from the item.
+    /// Returns `None` if the item does not set a tooltip.
+    #[cfg(feature = "bar")]
+    pub async fn tooltip(&self, address: &str) -> Result<Option<Tooltip>> {
+        let path = self.item_path(address).await;
+
+        let proxy = ProxyBuilder::<Proxy>::new_bare(&self.connection)
+            .destination(address)?
+            .path(path)?
+            .interface(ITEM_INTERFACE)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        let tooltip = Tooltip::from(proxy.get_property::<RawTooltip>("ToolTip").await?);
+
+        Ok(if tooltip.is_empty() {
+            None
+        } else {
+            Some(tooltip)
+        })
+    }
+
+    /// Activates the item at `address`, as if it was clicked.
+    /// This usually opens or focuses the app's main window.
+    #[cfg(feature = "bar")]
+    pub async fn activate_item(&self, address: &str) -> Result<()> {
+        let path = self.item_path(address).await;
+
+        let proxy = ProxyBuilder::<Proxy>::new_bare(&self.connection)
+            .destination(address)?
+            .path(path)?
+            .interface(ITEM_INTERFACE)?
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        // the position is where to show any window or menu, which is left to the item
+        proxy.call_method("Activate", &(0i32, 0i32)).await?;
+
+        Ok(())
+    }
+
+    /// Gets the object path for the item at `address`.
+    ///
+    /// Items are addressed by their bus name only,
+    /// so this looks up the path they were registered with on the watcher.
+    #[cfg(feature = "bar")]
+    async fn item_path(&self, address: &str) -> String {
+        let registered = async {
+            ProxyBuilder::<Proxy>::new_bare(&self.connection)
+                .destination(WATCHER_NAME)?
+                .path(WATCHER_PATH)?
+                .interface(WATCHER_NAME)?
+                .cache_properties(CacheProperties::No)
+                .build()
+                .await?
+                .get_property::<Vec<String>>("RegisteredStatusNotifierItems")
+                .await
+        };
+
+        match registered.await {
+            Ok(registered) => find_item_path(&registered, address),
+            Err(err) => {
+                debug!("Failed to get registered tray items: {err:?}");
+                DEFAULT_ITEM_PATH.to_string()
+            }
+        }
+    }
+
+    /// Starts a proxy which re-exposes legacy XEmbed tray icons as `StatusNotifierItem`s,
+    /// such as `snixembed` or `xembedsniproxy`.
+    ///
+    /// XEmbed icons live on the X server, so this does nothing unless XWayland is running.
+    /// The proxy is only started once, regardless of how many times this is called.
+    /// It runs in its own process group, which is killed by [`stop_xembed_proxy`].
+    #[cfg(feature = "bar")]
+    pub fn start_xembed_proxy(&self, command: &str) {
+        if env::var_os("DISPLAY").is_none() {
+            debug!("No X display available, not starting XEmbed proxy");
+            return;
+        }
+
+        if self.xembed_proxy_started.swap(true, Ordering::Relaxed) {
+            return;
+        }
+
+        let command = command.to_string();
+
+        spawn(async move {
+            debug!("Starting XEmbed proxy: {command}");
+
+            let child = Command::new("sh")
+                .args(["-c", &command])
+                .process_group(0)
+                .spawn();
+
+            match child {
+                Ok(mut child) => {
+                    if let Some(pid) = child.id().and_then(|pid| i32::try_from(pid).ok()) {
+                        XEMBED_PROXY_PGID.store(pid, Ordering::Relaxed);
+                    }
+
+                    match child.wait().await {
+                        Ok(status) => warn!("XEmbed proxy '{command}' exited with {status}"),
+                        Err(err) => error!("Failed to wait for XEmbed proxy '{command}': {err:?}"),
+                    }
+
+                    XEMBED_PROXY_PGID.store(0, Ordering::Relaxed);
+                }
+                Err(err) => error!("Failed to start XEmbed proxy '{command}': {err:?}"),
+            }
+        });
+    }
+}
+
+/// Stops the XEmbed proxy started by [`Client::start_xembed_proxy`], if it is running.
+///
+/// This must be called on shutdown,
+/// as the proxy is otherwise left running after Ironbar exits.
+pub fn stop_xembed_proxy() {
+    let pgid = XEMBED_PROXY_PGID.swap(0, Ordering::Relaxed);
+    if pgid == 0 {
+        return;
+    }
+
+    debug!("Stopping XEmbed proxy");
+
+    if let Err(err) = killpg(Pid::from_raw(pgid), Signal::SIGTERM) {
+        error!("Failed to stop XEmbed proxy: {err:?}");
+    }
+}
+
+/// Creates a new tray client, retrying with an increasing delay until it succeeds.
+async fn create_client(service_name: &str) -> Arc<TrayClient> {
+    let mut delay = Duration::from_secs(1);
+
+    loop {
+        match TrayClient::new(service_name).await {
+            Ok(client) => return Arc::new(client),
+            Err(err) => {
+                error!("Failed to create tray client, retrying in {delay:?}: {err:?}");
+                sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+/// Forwards all events from the client onto `tx`.
+fn forward_events(client: &TrayClient, tx: broadcast::Sender<Event>) -> JoinHandle<()> {
+    let mut rx = client.subscribe();
+
+    spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => send!(tx, event),
+                Err(broadcast::error::RecvError::Lagged(count)) => {
+                    warn!("Tray event channel lagged, {count} events were missed");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    })
+}
+
+/// Finds the object path an item was registered with.
+/// Registrations take the form `<bus name>[/<path>]`,
+/// falling back to the default path if none is given.
+#[cfg(feature = "bar")]
+fn find_item_path(registered: &[String], address: &str) -> String {
+    registered
+        .iter()
+        .filter_map(|item| item.strip_prefix(address))
+        .find_map(|path| path.starts_with('/').then(|| path.to_string()))
+        .unwrap_or_else(|| DEFAULT_ITEM_PATH.to_string())
+}
+
+/// Gets the unique name of the current watcher owner, if any.
+async fn get_watcher_owner(dbus: &DBusProxy<'_>) -> Option<String> {
+    let name = BusName::try_from(WATCHER_NAME).expect("to be valid bus name");
+    dbus.get_name_owner(name)
+        .await
+        .ok()
+        .map(|owner| owner.to_string())
+}
 
 register_fallible_client!(Client, tray);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        use system_tray::item::Status;
+
+        let items = self.items();
+        let items = lock!(items);
+
+        Ok(items
+            .iter()
+            .map(|(address, (item, _))| {
+                let status = match item.status {
+                    Status::Unknown => "unknown",
+                    Status::Passive => "passive",
+                    Status::Active => "active",
+                    Status::NeedsAttention => "needs_attention",
+                };
+
+                serde_json::json!({
+                    "address": address,
+                    "id": item.id,
+                    "title": item.title,
+                    "icon_name": item.icon_name,
+                    "status": status,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,604 @@
+use crate::{register_fallible_client, spawn};
+use color_eyre::Result;
+use futures_lite::StreamExt;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio::sync::watch;
+use tracing::{debug, error};
+use zbus::dbus_proxy;
+use zbus::fdo::{ManagedObjects, ObjectManagerProxy};
+#[cfg(feature = "bar")]
+use zbus::zvariant::Value;
+use zbus::zvariant::{OwnedObjectPath, OwnedValue};
+use zbus::{MatchRule, MessageStream, MessageType};
+
+const UDISKS: &str = "org.freedesktop.UDisks2";
+const DRIVE_INTERFACE: &str = "org.freedesktop.UDisks2.Drive";
+const BLOCK_INTERFACE: &str = "org.freedesktop.UDisks2.Block";
+const FILESYSTEM_INTERFACE: &str = "org.freedesktop.UDisks2.Filesystem";
+#[cfg(feature = "bar")]
+const ATA_INTERFACE: &str = "org.freedesktop.UDisks2.Drive.Ata";
+#[cfg(feature = "bar")]
+const NVME_INTERFACE: &str = "org.freedesktop.UDisks2.NVMe.Controller";
+
+/// Offset between Kelvin, as reported by UDisks, and Celsius.
+#[cfg(feature = "bar")]
+const KELVIN_OFFSET: f64 = 273.15;
+
+#[cfg(feature = "bar")]
+#[dbus_proxy(
+    default_service = "org.freedesktop.UDisks2",
+    interface = "org.freedesktop.UDisks2.Filesystem"
+)]
+trait FilesystemDbus {
+    fn mount(&self, options: HashMap<&str, Value<'_>>) -> Result<String>;
+
+    fn unmount(&self, options: HashMap<&str, Value<'_>>) -> Result<()>;
+}
+
+#[cfg(feature = "bar")]
+#[dbus_proxy(
+    default_service = "org.freedesktop.UDisks2",
+    interface = "org.freedesktop.UDisks2.Drive"
+)]
+trait DriveDbus {
+    fn eject(&self, options: HashMap<&str, Value<'_>>) -> Result<()>;
+
+    fn power_off(&self, options: HashMap<&str, Value<'_>>) -> Result<()>;
+}
+
+#[cfg(feature = "bar")]
+#[dbus_proxy(
+    default_service = "org.freedesktop.UDisks2",
+    interface = "org.freedesktop.UDisks2.NVMe.Controller"
+)]
+trait NvmeControllerDbus {
+    fn smart_get_attributes(
+        &self,
+        options: HashMap<&str, Value<'_>>,
+    ) -> Result<HashMap<String, OwnedValue>>;
+}
+
+/// A removable drive, such as a USB stick or SD card reader.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Drive {
+    /// The D-Bus object path of the drive.
+    pub path: String,
+    /// The vendor and model of the drive.
+    pub name: String,
+    /// Whether the drive's media can be ejected.
+    pub ejectable: bool,
+    /// Whether the drive can be powered off, so it is safe to unplug.
+    pub can_power_off: bool,
+    /// Mountable filesystems on the drive, sorted by device.
+    pub filesystems: Vec<Filesystem>,
+}
+
+/// A mountable filesystem on a drive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Filesystem {
+    /// The D-Bus object path of the block device.
+    pub path: String,
+    /// The device file, ie `/dev/sdb1`.
+    pub device: String,
+    /// The filesystem label, if it has one.
+    pub label: Option<String>,
+    /// Size in bytes.
+    pub size: u64,
+    /// Paths the filesystem is mounted at.
+    pub mount_points: Vec<String>,
+}
+
+#[cfg(feature = "bar")]
+impl Filesystem {
+    pub fn is_mounted(&self) -> bool {
+        !self.mount_points.is_empty()
+    }
+}
+
+/// The SMART health of a drive which supports it.
+#[cfg(feature = "bar")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DriveHealth {
+    /// The D-Bus object path of the drive.
+    pub path: String,
+    /// The vendor and model of the drive.
+    pub name: String,
+    /// The drive temperature in degrees Celsius, if known.
+    pub temperature: Option<f64>,
+    /// Problems reported by the drive.
+    /// This is empty if the drive is healthy.
+    pub warnings: Vec<String>,
+}
+
+/// The wear and health of an NVMe drive.
+#[cfg(feature = "bar")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NvmeHealth {
+    /// The D-Bus object path of the drive.
+    pub path: String,
+    /// The vendor and model of the drive.
+    pub name: String,
+    /// The vendor's estimate of the drive's rated endurance used, as a percentage.
+    /// This can exceed 100.
+    pub percent_used: Option<u8>,
+    /// The remaining spare capacity, as a percentage.
+    pub available_spare: Option<u8>,
+    /// The available spare percentage below which the drive reports a critical warning.
+    pub spare_threshold: Option<u8>,
+    /// The drive temperature in degrees Celsius, if known.
+    pub temperature: Option<f64>,
+    /// Total bytes written to the drive.
+    pub data_written: Option<u64>,
+    pub power_on_hours: Option<u64>,
+    /// Problems reported by the drive.
+    /// This is empty if the drive is healthy.
+    pub warnings: Vec<String>,
+}
+
+/// Tracks removable drives and drive health using UDisks,
+/// which in turn receives hotplug events from udev.
+#[derive(Debug)]
+pub struct Client {
+    #[cfg(feature = "bar")]
+    dbus: zbus::Connection,
+    #[cfg(feature = "bar")]
+    object_manager: ObjectManagerProxy<'static>,
+    drives: watch::Receiver<Vec<Drive>>,
+}
+
+impl Client {
+    pub async fn new() -> Result<Self> {
+        let dbus = Box::pin(zbus::Connection::system()).await?;
+
+        let object_manager = ObjectManagerProxy::builder(&dbus)
+            .destination(UDISKS)?
+            .path("/org/freedesktop/UDisks2")?
+            .build()
+            .await?;
+
+        let drives = removable_drives(object_manager.get_managed_objects().await?);
+        let (tx, rx) = watch::channel(drives);
+
+        let mut added = object_manager.receive_interfaces_added().await?;
+        let mut removed = object_manager.receive_interfaces_removed().await?;
+
+        // the object manager does not report property changes,
+        // such as a filesystem being mounted
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace("/org/freedesktop/UDisks2")?
+            .build();
+        let mut changed = MessageStream::for_match_rule(rule, &dbus, None).await?;
+
+        let watcher = object_manager.clone();
+
+        spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(_) = added.next() => {}
+                    Some(_) = removed.next() => {}
+                    Some(_) = changed.next() => {}
+                    else => break,
+                }
+
+                // drives are spread across several objects,
+                // so it is simplest to re-read them all on any change
+                match watcher.get_managed_objects().await {
+                    Ok(objects) => {
+                        let drives = removable_drives(objects);
+
+                        tx.send_if_modified(|current| {
+                            if *current == drives {
+                                false
+                            } else {
+                                debug!("Removable drives: {drives:?}");
+                                *current = drives;
+                                true
+                            }
+                        });
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+            }
+        });
+
+        Ok(Self {
+            #[cfg(feature = "bar")]
+            dbus,
+            #[cfg(feature = "bar")]
+            object_manager,
+            drives: rx,
+        })
+    }
+
+    /// Gets a receiver which holds the removable drives, sorted by name.
+    #[cfg(feature = "bar")]
+    pub fn subscribe(&self) -> watch::Receiver<Vec<Drive>> {
+        self.drives.clone()
+    }
+
+    /// Gets the current SMART health of all drives which support it,
+    /// sorted by name.
+    ///
+    /// UDisks refreshes SMART data itself periodically,
+    /// so this does not wake the drives.
+    #[cfg(feature = "bar")]
+    pub async fn drive_health(&self) -> Result<Vec<DriveHealth>> {
+        let objects = self.object_manager.get_managed_objects().await?;
+        Ok(drive_health(objects))
+    }
+
+    /// Gets the current wear and health of all NVMe drives,
+    /// sorted by name.
+    ///
+    /// The detailed attributes are only refreshed by UDisks periodically,
+    /// so may be a few minutes out of date.
+    #[cfg(feature = "bar")]
+    pub async fn nvme_health(&self) -> Result<Vec<NvmeHealth>> {
+        let objects = self.object_manager.get_managed_objects().await?;
+
+        let mut drives = vec![];
+        for (path, interfaces) in objects {
+            let (Some(drive), Some(nvme)) = (
+                interfaces.get(DRIVE_INTERFACE),
+                interfaces.get(NVME_INTERFACE),
+            ) else {
+                continue;
+            };
+
+            let proxy = NvmeControllerDbusProxy::builder(&self.dbus)
+                .path(path.to_string())?
+                .build()
+                .await?;
+
+            // older versions of UDisks and some drives do not support this,
+            // in which case only the basic health is shown
+            let attributes = match proxy.smart_get_attributes(HashMap::new()).await {
+                Ok(attributes) => attributes,
+                Err(err) => {
+                    debug!("Failed to get SMART attributes for '{path}': {err:?}");
+                    HashMap::new()
+                }
+            };
+
+            let (temperature, warnings) = nvme_health(nvme);
+
+            drives.push(NvmeHealth {
+                path: path.to_string(),
+                name: drive_name(drive).unwrap_or_else(|| String::from("Unknown drive")),
+                percent_used: get_u8(&attributes, "percent_used"),
+                available_spare: get_u8(&attributes, "avail_spare"),
+                spare_threshold: get_u8(&attributes, "spare_thresh"),
+                temperature,
+                data_written: attributes
+                    .get("total_data_written")
+                    .and_then(|value| u64::try_from(value).ok()),
+                power_on_hours: nvme
+                    .get("SmartPowerOnHours")
+                    .and_then(|value| u64::try_from(value).ok())
+                    .filter(|hours| *hours > 0),
+                warnings,
+            });
+        }
+
+        drives.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(drives)
+    }
+
+    /// Mounts the filesystem at the given object path,
+    /// returning the path it was mounted at.
+    #[cfg(feature = "bar")]
+    pub async fn mount(&self, path: &str) -> Result<String> {
+        let proxy = FilesystemDbusProxy::builder(&self.dbus)
+            .path(path.to_string())?
+            .build()
+            .await?;
+
+        proxy.mount(HashMap::new()).await
+    }
+
+    /// Unmounts the filesystem at the given object path.
+    #[cfg(feature = "bar")]
+    pub async fn unmount(&self, path: &str) -> Result<()> {
+        let proxy = FilesystemDbusProxy::builder(&self.dbus)
+            .path(path.to_string())?
+            .build()
+            .await?;
+
+        proxy.unmount(HashMap::new()).await
+    }
+
+    /// Unmounts all of the drive's filesystems,
+    /// then ejects its media and powers it off where supported.
+    #[cfg(feature = "bar")]
+    pub async fn eject(&self, path: &str) -> Result<()> {
+        let drive = self
+            .drives
+            .borrow()
+            .iter()
+            .find(|drive| drive.path == path)
+            .cloned();
+
+        let Some(drive) = drive else {
+            return Ok(());
+        };
+
+        for filesystem in drive.filesystems.iter().filter(|fs| fs.is_mounted()) {
+            self.unmount(&filesystem.path).await?;
+        }
+
+        let proxy = DriveDbusProxy::builder(&self.dbus)
+            .path(path.to_string())?
+            .build()
+            .await?;
+
+        if drive.ejectable {
+            proxy.eject(HashMap::new()).await?;
+        }
+
+        if drive.can_power_off {
+            proxy.power_off(HashMap::new()).await?;
+        }
+
+        Ok(())
+    }
+}
+
+register_fallible_client!(Client, udisks);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(&*self.drives.borrow())?)
+    }
+}
+
+/// Gets the removable drives from the UDisks objects,
+/// along with the filesystems on each.
+fn removable_drives(objects: ManagedObjects) -> Vec<Drive> {
+    let mut filesystems = HashMap::<OwnedObjectPath, Vec<Filesystem>>::new();
+
+    for (path, interfaces) in &objects {
+        let (Some(block), Some(filesystem)) = (
+            interfaces.get(BLOCK_INTERFACE),
+            interfaces.get(FILESYSTEM_INTERFACE),
+        ) else {
+            continue;
+        };
+
+        if get_bool(block, "HintIgnore") {
+            continue;
+        }
+
+        let Some(drive) = block
+            .get("Drive")
+            .and_then(|value| OwnedObjectPath::try_from(value.clone()).ok())
+        else {
+            continue;
+        };
+
+        let mount_points = filesystem
+            .get("MountPoints")
+            .and_then(|value| Vec::<Vec<u8>>::try_from(value.clone()).ok())
+            .unwrap_or_default()
+            .iter()
+            .map(|bytes| byte_string(bytes))
+            .collect();
+
+        let device = block
+            .get("PreferredDevice")
+            .and_then(|value| Vec::<u8>::try_from(value.clone()).ok())
+            .map(|bytes| byte_string(&bytes))
+            .unwrap_or_default();
+
+        filesystems.entry(drive).or_default().push(Filesystem {
+            path: path.to_string(),
+            device,
+            label: get_str(block, "IdLabel"),
+            size: block
+                .get("Size")
+                .and_then(|value| u64::try_from(value).ok())
+                .unwrap_or_default(),
+            mount_points,
+        });
+    }
+
+    let mut drives = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let drive = interfaces.get(DRIVE_INTERFACE)?;
+
+            if !get_bool(drive, "Removable") {
+                return None;
+            }
+
+            let mut filesystems = filesystems.remove(path).unwrap_or_default();
+            filesystems.sort_by(|a, b| a.device.cmp(&b.device));
+
+            Some(Drive {
+                path: path.to_string(),
+                name: drive_name(drive).unwrap_or_else(|| String::from("Removable drive")),
+                ejectable: get_bool(drive, "Ejectable"),
+                can_power_off: get_bool(drive, "CanPowerOff"),
+                filesystems,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    drives.sort_by(|a, b| a.name.cmp(&b.name));
+    drives
+}
+
+/// Gets the SMART health of the drives from the UDisks objects.
+///
+/// Both ATA drives and NVMe controllers are supported.
+#[cfg(feature = "bar")]
+fn drive_health(objects: ManagedObjects) -> Vec<DriveHealth> {
+    let mut drives = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            let drive = interfaces.get(DRIVE_INTERFACE)?;
+
+            let (temperature, warnings) = if let Some(ata) = interfaces.get(ATA_INTERFACE) {
+                ata_health(ata)?
+            } else if let Some(nvme) = interfaces.get(NVME_INTERFACE) {
+                nvme_health(nvme)
+            } else {
+                return None;
+            };
+
+            Some(DriveHealth {
+                path: path.to_string(),
+                name: drive_name(drive).unwrap_or_else(|| String::from("Unknown drive")),
+                temperature,
+                warnings,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    drives.sort_by(|a, b| a.name.cmp(&b.name));
+    drives
+}
+
+/// Gets the temperature and warnings for an ATA drive,
+/// or `None` if SMART is not available for it.
+#[cfg(feature = "bar")]
+fn ata_health(ata: &HashMap<String, OwnedValue>) -> Option<(Option<f64>, Vec<String>)> {
+    if !get_bool(ata, "SmartSupported") || !get_bool(ata, "SmartEnabled") {
+        return None;
+    }
+
+    let mut warnings = vec![];
+
+    if get_bool(ata, "SmartFailing") {
+        warnings.push(String::from("Drive is predicted to fail"));
+    }
+
+    let failing_attributes = ata
+        .get("SmartNumAttributesFailing")
+        .and_then(|value| i32::try_from(value).ok())
+        .unwrap_or_default();
+
+    match failing_attributes {
+        0 => {}
+        1 => warnings.push(String::from("1 pre-fail attribute failing")),
+        count => warnings.push(format!("{count} pre-fail attributes failing")),
+    }
+
+    let bad_sectors = ata
+        .get("SmartNumBadSectors")
+        .and_then(|value| i64::try_from(value).ok())
+        .unwrap_or_default();
+
+    match bad_sectors {
+        0 => {}
+        1 => warnings.push(String::from("1 bad sector")),
+        count => warnings.push(format!("{count} bad sectors")),
+    }
+
+    let temperature = ata
+        .get("SmartTemperature")
+        .and_then(|value| f64::try_from(value).ok());
+
+    Some((kelvin_to_celsius(temperature), warnings))
+}
+
+/// Gets the temperature and warnings for an NVMe controller.
+#[cfg(feature = "bar")]
+fn nvme_health(nvme: &HashMap<String, OwnedValue>) -> (Option<f64>, Vec<String>) {
+    let warnings = nvme
+        .get("SmartCriticalWarning")
+        .and_then(|value| Vec::<String>::try_from(value.clone()).ok())
+        .unwrap_or_default()
+        .iter()
+        .map(|warning| match warning.as_str() {
+            "spare" => String::from("Available spare is low"),
+            "temperature" => String::from("Temperature is outside of the safe range"),
+            "degraded" => String::from("Reliability is degraded"),
+            "readonly" => String::from("Media is read-only"),
+            "volatile_mem" => String::from("Volatile memory backup has failed"),
+            "pmr_readonly" => String::from("Persistent memory is read-only"),
+            warning => format!("Critical warning: {warning}"),
+        })
+        .collect();
+
+    let temperature = nvme
+        .get("SmartTemperature")
+        .and_then(|value| u16::try_from(value).ok())
+        .map(f64::from);
+
+    (kelvin_to_celsius(temperature), warnings)
+}
+
+/// Converts a temperature reported by UDisks to Celsius,
+/// treating zero as unknown.
+#[cfg(feature = "bar")]
+fn kelvin_to_celsius(kelvin: Option<f64>) -> Option<f64> {
+    kelvin
+        .filter(|kelvin| *kelvin > 0.0)
+        .map(|kelvin| kelvin - KELVIN_OFFSET)
+}
+
+/// Gets the vendor and model of a drive.
+fn drive_name(drive: &HashMap<String, OwnedValue>) -> Option<String> {
+    let name = [get_str(drive, "Vendor"), get_str(drive, "Model")]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+fn get_bool(props: &HashMap<String, OwnedValue>, key: &str) -> bool {
+    props
+        .get(key)
+        .and_then(|value| bool::try_from(value).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(feature = "bar")]
+fn get_u8(props: &HashMap<String, OwnedValue>, key: &str) -> Option<u8> {
+    props.get(key).and_then(|value| u8::try_from(value).ok())
+}
+
+fn get_str(props: &HashMap<String, OwnedValue>, key: &str) -> Option<String> {
+    props
+        .get(key)
+        .and_then(|value| <&str>::try_from(value).ok())
+        .filter(|value| !value.is_empty())
+        .map(ToString::to_string)
+}
+
+/// Converts a null-terminated byte string, as used by UDisks for paths.
+fn byte_string(bytes: &[u8]) -> String {
+    let bytes = bytes.strip_suffix(&[0]).unwrap_or(bytes);
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_string() {
+        assert_eq!(byte_string(b"/run/media/user/USB\0"), "/run/media/user/USB");
+        assert_eq!(byte_string(b"/dev/sdb1"), "/dev/sdb1");
+        assert_eq!(byte_string(b""), "");
+    }
+
+    #[cfg(feature = "bar")]
+    #[test]
+    fn test_kelvin_to_celsius() {
+        assert_eq!(kelvin_to_celsius(Some(313.15)), Some(40.0));
+        assert_eq!(kelvin_to_celsius(Some(0.0)), None);
+        assert_eq!(kelvin_to_celsius(None), None);
+    }
+}
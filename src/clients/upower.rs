@@ -1,8 +1,28 @@
-use crate::register_client;
-use std::sync::Arc;
+#[cfg(feature = "bar")]
+use crate::lock;
+#[cfg(feature = "bar")]
+use crate::script::{Script, ScriptInput};
+use crate::{register_client, register_fallible_client, spawn};
+use color_eyre::Result;
+use futures_lite::StreamExt;
+use serde::Serialize;
+#[cfg(feature = "bar")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "bar")]
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+use tracing::{debug, error};
+#[cfg(feature = "bar")]
+use upower_dbus::BatteryState;
 use upower_dbus::UPowerProxy;
 use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::{OwnedValue, Value};
+use zbus::{MatchRule, MessageStream, MessageType};
 
+const DEVICE_INTERFACE: &str = "org.freedesktop.UPower.Device";
+
+#[cfg(feature = "bar")]
 pub async fn create_display_proxy() -> Arc<PropertiesProxy<'static>> {
     let dbus = Box::pin(zbus::Connection::system())
         .await
@@ -33,3 +53,692 @@ pub async fn create_display_proxy() -> Arc<PropertiesProxy<'static>> {
 }
 
 register_client!(PropertiesProxy<'static>, upower);
+
+/// Tracks whether the system is running on battery power.
+#[derive(Debug)]
+pub struct BatteryWatcher {
+    on_battery: watch::Receiver<bool>,
+}
+
+impl BatteryWatcher {
+    pub async fn new() -> Result<Self> {
+        let dbus = Box::pin(zbus::Connection::system()).await?;
+        let proxy = UPowerProxy::new(&dbus).await?;
+
+        let (tx, rx) = watch::channel(proxy.on_battery().await?);
+
+        spawn(async move {
+            let mut changes = proxy.receive_on_battery_changed().await;
+
+            while let Some(change) = changes.next().await {
+                match change.get().await {
+                    Ok(on_battery) => {
+                        debug!("On battery: {on_battery}");
+                        tx.send_replace(on_battery);
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+            }
+        });
+
+        Ok(Self { on_battery: rx })
+    }
+
+    /// Gets a receiver which holds `true` while on battery power.
+    #[cfg(feature = "bar")]
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.on_battery.clone()
+    }
+}
+
+register_fallible_client!(BatteryWatcher, battery);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for BatteryWatcher {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::json!({ "on_battery": *self.on_battery.borrow() }))
+    }
+}
+
+/// A battery-powered peripheral, such as a wireless mouse or keyboard.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Peripheral {
+    /// The D-Bus object path of the device.
+    pub path: String,
+    /// The device model, ie `MX Master 3`.
+    pub name: String,
+    /// The type of device, ie `mouse`.
+    pub kind: &'static str,
+    pub percentage: f64,
+}
+
+/// Tracks the battery levels of peripherals known to UPower.
+///
+/// Batteries which power the system, such as a laptop battery, are excluded.
+#[derive(Debug)]
+pub struct PeripheralWatcher {
+    peripherals: watch::Receiver<Vec<Peripheral>>,
+}
+
+impl PeripheralWatcher {
+    pub async fn new() -> Result<Self> {
+        let dbus = Box::pin(zbus::Connection::system()).await?;
+        let proxy = UPowerProxy::new(&dbus).await?;
+
+        let (tx, rx) = watch::channel(read_peripherals(&dbus, &proxy).await?);
+
+        let mut added = proxy.receive_device_added().await?;
+        let mut removed = proxy.receive_device_removed().await?;
+
+        let rule = MatchRule::builder()
+            .msg_type(MessageType::Signal)
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .path_namespace("/org/freedesktop/UPower/devices")?
+            .build();
+        let mut changed = MessageStream::for_match_rule(rule, &dbus, None).await?;
+
+        spawn(async move {
+            loop {
+                tokio::select! {
+                    Some(_) = added.next() => {}
+                    Some(_) = removed.next() => {}
+                    Some(_) = changed.next() => {}
+                    else => break,
+                }
+
+                match read_peripherals(&dbus, &proxy).await {
+                    Ok(peripherals) => {
+                        tx.send_if_modified(|current| {
+                            if *current == peripherals {
+                                false
+                            } else {
+                                debug!("Peripherals: {peripherals:?}");
+                                *current = peripherals;
+                                true
+                            }
+                        });
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+            }
+        });
+
+        Ok(Self { peripherals: rx })
+    }
+
+    /// Gets a receiver which holds the peripherals reporting a battery level.
+    #[cfg(feature = "bar")]
+    pub fn subscribe(&self) -> watch::Receiver<Vec<Peripheral>> {
+        self.peripherals.clone()
+    }
+}
+
+register_fallible_client!(PeripheralWatcher, peripherals);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for PeripheralWatcher {
+    fn export_state(&self) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(&*self.peripherals.borrow())?)
+    }
+}
+
+async fn read_peripherals(
+    dbus: &zbus::Connection,
+    proxy: &UPowerProxy<'_>,
+) -> Result<Vec<Peripheral>> {
+    let interface = InterfaceName::from_static_str(DEVICE_INTERFACE)?;
+
+    let mut peripherals = vec![];
+
+    for path in proxy.enumerate_devices().await? {
+        let properties = PropertiesProxy::builder(dbus)
+            .destination("org.freedesktop.UPower")?
+            .path(path.clone().into_inner())?
+            .cache_properties(zbus::CacheProperties::No)
+            .build()
+            .await?;
+
+        let properties = properties.get_all(interface.clone()).await?;
+
+        let get = |key: &str| properties.get(key).map(OwnedValue::clone);
+
+        let power_supply = get("PowerSupply")
+            .and_then(|value| bool::try_from(value).ok())
+            .unwrap_or_default();
+
+        let Some(kind) = get("Type")
+            .and_then(|value| u32::try_from(value).ok())
+            .and_then(peripheral_kind)
+        else {
+            continue;
+        };
+
+        if power_supply {
+            continue;
+        }
+
+        let Some(percentage) = get("Percentage").and_then(|value| f64::try_from(value).ok()) else {
+            continue;
+        };
+
+        let name = get("Model")
+            .and_then(|value| String::try_from(value).ok())
+            .filter(|model| !model.is_empty())
+            .unwrap_or_else(|| kind.to_string());
+
+        peripherals.push(Peripheral {
+            path: path.to_string(),
+            name,
+            kind,
+            percentage,
+        });
+    }
+
+    peripherals.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(peripherals)
+}
+
+/// Gets the name of a UPower device type,
+/// or `None` for types which are not peripherals.
+const fn peripheral_kind(device_type: u32) -> Option<&'static str> {
+    match device_type {
+        2 => Some("battery"),
+        5 => Some("mouse"),
+        6 => Some("keyboard"),
+        7 => Some("pda"),
+        8 => Some("phone"),
+        9 => Some("media_player"),
+        10 => Some("tablet"),
+        12 => Some("gaming_input"),
+        13 => Some("pen"),
+        14 => Some("touchpad"),
+        17 => Some("headset"),
+        18 => Some("speakers"),
+        19 => Some("headphones"),
+        21 => Some("other_audio"),
+        22 => Some("remote_control"),
+        26 => Some("wearable"),
+        27 => Some("toy"),
+        28 => Some("bluetooth_generic"),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "bar")]
+pub const fn u32_to_battery_state(number: u32) -> Result<BatteryState, u32> {
+    if number == (BatteryState::Unknown as u32) {
+        Ok(BatteryState::Unknown)
+    } else if number == (BatteryState::Charging as u32) {
+        Ok(BatteryState::Charging)
+    } else if number == (BatteryState::Discharging as u32) {
+        Ok(BatteryState::Discharging)
+    } else if number == (BatteryState::Empty as u32) {
+        Ok(BatteryState::Empty)
+    } else if number == (BatteryState::FullyCharged as u32) {
+        Ok(BatteryState::FullyCharged)
+    } else if number == (BatteryState::PendingCharge as u32) {
+        Ok(BatteryState::PendingCharge)
+    } else if number == (BatteryState::PendingDischarge as u32) {
+        Ok(BatteryState::PendingDischarge)
+    } else {
+        Err(number)
+    }
+}
+
+/// Scripts to run on display device battery events.
+#[cfg(feature = "bar")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BatteryHooks {
+    pub low_threshold: f64,
+    pub critical_threshold: f64,
+    pub on_low: Option<ScriptInput>,
+    pub on_critical: Option<ScriptInput>,
+    pub on_charging: Option<ScriptInput>,
+    pub on_full: Option<ScriptInput>,
+}
+
+#[cfg(feature = "bar")]
+impl BatteryHooks {
+    const fn is_empty(&self) -> bool {
+        self.on_low.is_none()
+            && self.on_critical.is_none()
+            && self.on_charging.is_none()
+            && self.on_full.is_none()
+    }
+}
+
+/// Alerts to raise when a peripheral's battery is low.
+#[cfg(feature = "bar")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeripheralHooks {
+    pub threshold: f64,
+    pub thresholds: HashMap<String, f64>,
+    pub notify: bool,
+    pub on_low: Option<ScriptInput>,
+}
+
+#[cfg(feature = "bar")]
+impl PeripheralHooks {
+    fn threshold(&self, peripheral: &Peripheral) -> f64 {
+        self.thresholds
+            .get(&peripheral.name)
+            .or_else(|| self.thresholds.get(peripheral.kind))
+            .copied()
+            .unwrap_or(self.threshold)
+    }
+}
+
+#[cfg(feature = "bar")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BatteryEvent {
+    Low,
+    Critical,
+    Charging,
+    Full,
+}
+
+/// Tracks battery changes to determine when hook scripts should run.
+///
+/// Low and critical events fire once when the threshold is crossed,
+/// and are re-armed once the battery is charging or back above the threshold.
+/// Charging and full events only fire on state changes.
+#[cfg(feature = "bar")]
+#[derive(Debug)]
+struct BatteryHookState {
+    low_threshold: f64,
+    critical_threshold: f64,
+
+    low_fired: bool,
+    critical_fired: bool,
+    last_state: Option<BatteryState>,
+}
+
+#[cfg(feature = "bar")]
+impl BatteryHookState {
+    const fn new(low_threshold: f64, critical_threshold: f64) -> Self {
+        Self {
+            low_threshold,
+            critical_threshold,
+            low_fired: false,
+            critical_fired: false,
+            last_state: None,
+        }
+    }
+
+    fn update(&mut self, percentage: f64, state: BatteryState) -> Vec<BatteryEvent> {
+        let mut events = vec![];
+
+        let discharging = matches!(
+            state,
+            BatteryState::Discharging | BatteryState::PendingDischarge | BatteryState::Empty
+        );
+
+        if !discharging || percentage > self.critical_threshold {
+            self.critical_fired = false;
+        }
+
+        if !discharging || percentage > self.low_threshold {
+            self.low_fired = false;
+        }
+
+        if discharging && percentage <= self.critical_threshold && !self.critical_fired {
+            events.push(BatteryEvent::Critical);
+
+            // no point warning about low battery after critical
+            self.critical_fired = true;
+            self.low_fired = true;
+        } else if discharging && percentage <= self.low_threshold && !self.low_fired {
+            events.push(BatteryEvent::Low);
+            self.low_fired = true;
+        }
+
+        if let Some(last_state) = self.last_state {
+            if last_state != state {
+                match state {
+                    BatteryState::Charging => events.push(BatteryEvent::Charging),
+                    BatteryState::FullyCharged => events.push(BatteryEvent::Full),
+                    _ => {}
+                }
+            }
+        }
+
+        self.last_state = Some(state);
+
+        events
+    }
+}
+
+/// Tracks peripheral battery levels to determine when to alert.
+///
+/// Each peripheral alerts once when it drops to its threshold,
+/// and is re-armed once it is back above the threshold or reconnects.
+#[cfg(feature = "bar")]
+#[derive(Debug, Default)]
+struct PeripheralHookState {
+    /// Object paths of the peripherals which have been alerted for.
+    alerted: HashSet<String>,
+}
+
+#[cfg(feature = "bar")]
+impl PeripheralHookState {
+    fn update<'a>(
+        &mut self,
+        hooks: &PeripheralHooks,
+        peripherals: &'a [Peripheral],
+    ) -> Vec<&'a Peripheral> {
+        // some devices report 0% until their level is known
+        let is_low = |peripheral: &Peripheral| {
+            peripheral.percentage > 0.0 && peripheral.percentage <= hooks.threshold(peripheral)
+        };
+
+        self.alerted.retain(|path| {
+            peripherals
+                .iter()
+                .any(|peripheral| &peripheral.path == path && is_low(peripheral))
+        });
+
+        peripherals
+            .iter()
+            .filter(|peripheral| is_low(peripheral) && self.alerted.insert(peripheral.path.clone()))
+            .collect()
+    }
+}
+
+/// Runs battery hook scripts and peripheral alerts.
+///
+/// Modules on every bar register their hooks with this shared client,
+/// and identical registrations are ignored,
+/// so each event fires once rather than once per bar.
+#[cfg(feature = "bar")]
+#[derive(Debug)]
+pub struct HookRunner {
+    display_proxy: Arc<PropertiesProxy<'static>>,
+
+    battery: Mutex<Vec<BatteryHooks>>,
+    peripherals: Mutex<Vec<PeripheralHooks>>,
+}
+
+#[cfg(feature = "bar")]
+impl HookRunner {
+    pub fn new(display_proxy: Arc<PropertiesProxy<'static>>) -> Self {
+        Self {
+            display_proxy,
+            battery: Mutex::new(vec![]),
+            peripherals: Mutex::new(vec![]),
+        }
+    }
+
+    /// Starts running the display device battery hooks,
+    /// unless identical hooks are already running.
+    pub fn register_battery(&self, hooks: BatteryHooks) {
+        if hooks.is_empty() {
+            return;
+        }
+
+        {
+            let mut registered = lock!(self.battery);
+            if registered.contains(&hooks) {
+                return;
+            }
+
+            registered.push(hooks.clone());
+        }
+
+        let proxy = self.display_proxy.clone();
+
+        spawn(async move {
+            if let Err(err) = run_battery_hooks(&proxy, hooks).await {
+                error!("{:?}", err.wrap_err("Failed to watch battery for hooks"));
+            }
+        });
+    }
+
+    /// Starts alerting for low peripheral batteries,
+    /// unless identical alerts are already running.
+    pub fn register_peripherals(&self, watcher: &PeripheralWatcher, hooks: PeripheralHooks) {
+        {
+            let mut registered = lock!(self.peripherals);
+            if registered.contains(&hooks) {
+                return;
+            }
+
+            registered.push(hooks.clone());
+        }
+
+        let mut rx = watcher.subscribe();
+        let on_low = hooks.on_low.clone().map(Script::new_polling);
+
+        spawn(async move {
+            let mut state = PeripheralHookState::default();
+
+            loop {
+                let peripherals = rx.borrow_and_update().clone();
+
+                for peripheral in state.update(&hooks, &peripherals) {
+                    debug!("Peripheral battery low: {peripheral:?}");
+
+                    if hooks.notify {
+                        if let Err(err) = notify_low(peripheral).await {
+                            error!("{:?}", err.wrap_err("Failed to send notification"));
+                        }
+                    }
+
+                    if let Some(script) = &on_low {
+                        script.run_as_oneshot(Some(&[
+                            peripheral.percentage.to_string(),
+                            peripheral.name.clone(),
+                        ]));
+                    }
+                }
+
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+register_client!(HookRunner, battery_hooks);
+
+/// Watches the display device, running hook scripts as battery events occur.
+#[cfg(feature = "bar")]
+async fn run_battery_hooks(proxy: &PropertiesProxy<'static>, hooks: BatteryHooks) -> Result<()> {
+    let interface = InterfaceName::from_static_str(DEVICE_INTERFACE)?;
+
+    let mut changes = proxy.receive_properties_changed().await?;
+    let properties = proxy.get_all(interface.clone()).await?;
+
+    let mut percentage = properties
+        .get("Percentage")
+        .and_then(|value| f64::try_from(value.clone()).ok())
+        .unwrap_or_default();
+    let mut battery_state = properties
+        .get("State")
+        .and_then(|value| u32::try_from(value.clone()).ok())
+        .and_then(|state| u32_to_battery_state(state).ok())
+        .unwrap_or(BatteryState::Unknown);
+
+    let on_low = hooks.on_low.map(Script::new_polling);
+    let on_critical = hooks.on_critical.map(Script::new_polling);
+    let on_charging = hooks.on_charging.map(Script::new_polling);
+    let on_full = hooks.on_full.map(Script::new_polling);
+
+    let mut state = BatteryHookState::new(hooks.low_threshold, hooks.critical_threshold);
+
+    loop {
+        for event in state.update(percentage, battery_state) {
+            let script = match event {
+                BatteryEvent::Low => on_low.as_ref(),
+                BatteryEvent::Critical => on_critical.as_ref(),
+                BatteryEvent::Charging => on_charging.as_ref(),
+                BatteryEvent::Full => on_full.as_ref(),
+            };
+
+            if let Some(script) = script {
+                debug!("Running {event:?} battery hook");
+                script.run_as_oneshot(Some(&[percentage.to_string()]));
+            }
+        }
+
+        let Some(signal) = changes.next().await else {
+            break;
+        };
+
+        let args = signal.args()?;
+        if args.interface_name != interface {
+            continue;
+        }
+
+        for (name, value) in args.changed_properties {
+            match name {
+                "Percentage" => {
+                    if let Some(value) = value.downcast::<f64>() {
+                        percentage = value;
+                    }
+                }
+                "State" => {
+                    if let Some(value) = value
+                        .downcast::<u32>()
+                        .and_then(|state| u32_to_battery_state(state).ok())
+                    {
+                        battery_state = value;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Sends a desktop notification that a peripheral's battery is low.
+#[cfg(feature = "bar")]
+async fn notify_low(peripheral: &Peripheral) -> Result<()> {
+    let dbus = Box::pin(zbus::Connection::session()).await?;
+
+    let summary = format!("{} battery low", peripheral.name);
+    let body = format!("{}% remaining", peripheral.percentage);
+
+    dbus.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "Ironbar",
+            0u32,
+            "battery-low",
+            summary.as_str(),
+            body.as_str(),
+            Vec::<&str>::new(),
+            HashMap::<&str, Value>::new(),
+            -1i32,
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(all(test, feature = "bar"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_low_fires_once() {
+        let mut hooks = BatteryHookState::new(20.0, 5.0);
+
+        assert!(hooks.update(25.0, BatteryState::Discharging).is_empty());
+        assert_eq!(
+            hooks.update(20.0, BatteryState::Discharging),
+            vec![BatteryEvent::Low]
+        );
+        assert!(hooks.update(19.0, BatteryState::Discharging).is_empty());
+        assert_eq!(
+            hooks.update(5.0, BatteryState::Discharging),
+            vec![BatteryEvent::Critical]
+        );
+        assert!(hooks.update(4.0, BatteryState::Discharging).is_empty());
+    }
+
+    #[test]
+    fn test_rearms_after_charging() {
+        let mut hooks = BatteryHookState::new(20.0, 5.0);
+
+        assert_eq!(
+            hooks.update(15.0, BatteryState::Discharging),
+            vec![BatteryEvent::Low]
+        );
+        assert_eq!(
+            hooks.update(15.0, BatteryState::Charging),
+            vec![BatteryEvent::Charging]
+        );
+        assert_eq!(
+            hooks.update(15.0, BatteryState::Discharging),
+            vec![BatteryEvent::Low]
+        );
+    }
+
+    fn peripheral(path: &str, kind: &'static str, percentage: f64) -> Peripheral {
+        Peripheral {
+            path: path.to_string(),
+            name: path.to_string(),
+            kind,
+            percentage,
+        }
+    }
+
+    #[test]
+    fn test_peripheral_alerts() {
+        let config = PeripheralHooks {
+            threshold: 20.0,
+            thresholds: HashMap::from([(String::from("keyboard"), 30.0)]),
+            notify: false,
+            on_low: None,
+        };
+
+        let mut hooks = PeripheralHookState::default();
+
+        let alerted = |hooks: &mut PeripheralHookState, peripherals: &[Peripheral]| {
+            hooks
+                .update(&config, peripherals)
+                .iter()
+                .map(|peripheral| peripheral.path.clone())
+                .collect::<Vec<_>>()
+        };
+
+        let peripherals = [
+            peripheral("mouse", "mouse", 25.0),
+            peripheral("keyboard", "keyboard", 25.0),
+        ];
+        assert_eq!(alerted(&mut hooks, &peripherals), ["keyboard"]);
+        assert!(alerted(&mut hooks, &peripherals).is_empty());
+
+        let peripherals = [
+            peripheral("mouse", "mouse", 15.0),
+            peripheral("keyboard", "keyboard", 20.0),
+        ];
+        assert_eq!(alerted(&mut hooks, &peripherals), ["mouse"]);
+
+        // re-armed after charging
+        let charged = [peripheral("mouse", "mouse", 50.0)];
+        assert!(alerted(&mut hooks, &charged).is_empty());
+        assert_eq!(alerted(&mut hooks, &peripherals), ["mouse", "keyboard"]);
+    }
+
+    #[test]
+    fn test_state_changes() {
+        let mut hooks = BatteryHookState::new(20.0, 5.0);
+
+        // no events for the initial state
+        assert!(hooks.update(90.0, BatteryState::Charging).is_empty());
+        assert!(hooks.update(95.0, BatteryState::Charging).is_empty());
+        assert_eq!(
+            hooks.update(100.0, BatteryState::FullyCharged),
+            vec![BatteryEvent::Full]
+        );
+    }
+}
@@ -0,0 +1,156 @@
+#[cfg(feature = "bar")]
+use super::{Client, ConnectionState};
+use crate::{lock, send};
+use libpulse_binding::context::Context;
+#[cfg(feature = "bar")]
+use libpulse_binding::context::State;
+use libpulse_binding::def::BufferAttr;
+use libpulse_binding::sample::{Format, Spec};
+use libpulse_binding::stream::{FlagSet, PeekResult, Stream};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error};
+
+/// Number of peak values reported per second.
+const PEAK_RATE: u32 = 25;
+
+/// Peak-detecting record stream on the default source,
+/// used to report the current microphone input level.
+pub struct LevelMeter {
+    enabled: bool,
+    stream: Option<Stream>,
+
+    tx: broadcast::Sender<f64>,
+    _rx: broadcast::Receiver<f64>,
+}
+
+impl Default for LevelMeter {
+    fn default() -> Self {
+        let (tx, rx) = broadcast::channel(8);
+
+        Self {
+            enabled: false,
+            stream: None,
+            tx,
+            _rx: rx,
+        }
+    }
+}
+
+impl Debug for LevelMeter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LevelMeter")
+            .field("enabled", &self.enabled)
+            .field("running", &self.stream.is_some())
+            .finish()
+    }
+}
+
+#[cfg(feature = "bar")]
+impl Client {
+    /// Gets a receiver for the input level of the default source,
+    /// as a value between `0.0` and `1.0`.
+    ///
+    /// The meter stream is only started once something subscribes,
+    /// so that the source is not kept open unnecessarily.
+    pub fn subscribe_source_level(&self) -> broadcast::Receiver<f64> {
+        let mut meter = lock!(self.data.level_meter);
+        meter.enabled = true;
+
+        let rx = meter.tx.subscribe();
+
+        if meter.stream.is_none() {
+            if let ConnectionState::Connected { context, .. } = &*lock!(self.connection) {
+                if lock!(context).get_state() == State::Ready {
+                    start(&mut meter, context, &self.data.level_meter);
+                }
+            }
+        }
+
+        rx
+    }
+}
+
+/// Starts the meter stream if it has been requested.
+/// Called once the context is ready.
+pub fn on_ready(context: &Arc<Mutex<Context>>, level_meter: &Arc<Mutex<LevelMeter>>) {
+    let mut meter = lock!(level_meter);
+    if meter.enabled && meter.stream.is_none() {
+        start(&mut meter, context, level_meter);
+    }
+}
+
+fn start(
+    meter: &mut LevelMeter,
+    context: &Arc<Mutex<Context>>,
+    level_meter: &Arc<Mutex<LevelMeter>>,
+) {
+    let spec = Spec {
+        format: Format::F32le,
+        rate: PEAK_RATE,
+        channels: 1,
+    };
+
+    let Some(mut stream) = Stream::new(&mut lock!(context), "Ironbar Level Meter", &spec, None)
+    else {
+        error!("Failed to create PA level meter stream");
+        return;
+    };
+
+    // request a single sample per fragment so every peak is delivered immediately
+    let attr = BufferAttr {
+        maxlength: u32::MAX,
+        fragsize: std::mem::size_of::<f32>() as u32,
+        ..Default::default()
+    };
+
+    stream.set_read_callback(Some(Box::new({
+        let level_meter = level_meter.clone();
+        move |_| read(&level_meter)
+    })));
+
+    // connecting without a device name follows the default source
+    if let Err(err) = stream.connect_record(
+        None,
+        Some(&attr),
+        FlagSet::PEAK_DETECT | FlagSet::ADJUST_LATENCY,
+    ) {
+        error!("Failed to connect PA level meter stream: {err:?}");
+        return;
+    }
+
+    debug!("started source level meter");
+    meter.stream = Some(stream);
+}
+
+fn read(level_meter: &Arc<Mutex<LevelMeter>>) {
+    let mut meter = lock!(level_meter);
+    let LevelMeter { stream, tx, .. } = &mut *meter;
+
+    let Some(stream) = stream.as_mut() else {
+        return;
+    };
+
+    let level = match stream.peek() {
+        Ok(PeekResult::Data(data)) => data
+            .chunks_exact(std::mem::size_of::<f32>())
+            .last()
+            .and_then(|bytes| bytes.try_into().ok())
+            .map(f32::from_le_bytes),
+        Ok(PeekResult::Hole(_)) => None,
+        Ok(PeekResult::Empty) => return,
+        Err(err) => {
+            error!("{err:?}");
+            return;
+        }
+    };
+
+    if let Err(err) = stream.discard() {
+        error!("{err:?}");
+    }
+
+    if let Some(level) = level {
+        send!(tx, f64::from(level).clamp(0.0, 1.0));
+    }
+}
@@ -1,5 +1,7 @@
+mod meter;
 mod sink;
 mod sink_input;
+mod source;
 
 use crate::{arc_mut, lock, register_client, send, spawn_blocking, APP_ID};
 use libpulse_binding::callbacks::ListResult;
@@ -16,6 +18,7 @@ use tracing::{debug, error, info, warn};
 
 pub use sink::Sink;
 pub use sink_input::SinkInput;
+pub use source::Source;
 
 type ArcMutVec<T> = Arc<Mutex<Vec<T>>>;
 
@@ -28,6 +31,10 @@ pub enum Event {
     AddInput(SinkInput),
     UpdateInput(SinkInput),
     RemoveInput(u32),
+
+    AddSource(Source),
+    UpdateSource(Source),
+    RemoveSource(String),
 }
 
 #[derive(Debug)]
@@ -46,6 +53,11 @@ struct Data {
     sink_inputs: ArcMutVec<SinkInput>,
 
     default_sink_name: Arc<Mutex<Option<String>>>,
+
+    sources: ArcMutVec<Source>,
+    default_source_name: Arc<Mutex<Option<String>>>,
+
+    level_meter: Arc<Mutex<meter::LevelMeter>>,
 }
 
 pub enum ConnectionState {
@@ -139,6 +151,7 @@ impl Client {
     }
 
     /// Gets an event receiver.
+    #[cfg(feature = "bar")]
     pub fn subscribe(&self) -> broadcast::Receiver<Event> {
         self.tx.subscribe()
     }
@@ -191,6 +204,22 @@ fn on_state_change(context: &Arc<Mutex<Context>>, data: &Data, tx: &broadcast::S
                 }
             });
 
+            introspect.get_source_info_list({
+                let sources = data.sources.clone();
+                let default_source = data.default_source_name.clone();
+                let tx = tx.clone();
+
+                move |info| source::add(info, &sources, &default_source, &tx)
+            });
+
+            lock!(context).introspect().get_server_info({
+                let sources = data.sources.clone();
+                let default_source = data.default_source_name.clone();
+                let tx = tx.clone();
+
+                move |info| set_default_source(info, &sources, &default_source, &tx)
+            });
+
             introspect.get_sink_input_info_list({
                 let inputs = data.sink_inputs.clone();
                 let tx = tx.clone();
@@ -208,9 +237,14 @@ fn on_state_change(context: &Arc<Mutex<Context>>, data: &Data, tx: &broadcast::S
 
             lock!(context).set_subscribe_callback(Some(subscribe_callback));
             lock!(context).subscribe(
-                InterestMaskSet::SERVER | InterestMaskSet::SINK_INPUT | InterestMaskSet::SINK,
+                InterestMaskSet::SERVER
+                    | InterestMaskSet::SINK_INPUT
+                    | InterestMaskSet::SINK
+                    | InterestMaskSet::SOURCE,
                 |_| (),
             );
+
+            meter::on_ready(context, &data.level_meter);
         }
         State::Failed => error!("Failed to connect to audio server"),
         State::Terminated => error!("Connection to audio server terminated"),
@@ -231,25 +265,25 @@ fn on_event(
     };
 
     match facility {
-        Facility::Server => on_server_event(context, &data.sinks, &data.default_sink_name, tx),
+        Facility::Server => on_server_event(context, data, tx),
         Facility::Sink => sink::on_event(context, &data.sinks, &data.default_sink_name, tx, op, i),
         Facility::SinkInput => sink_input::on_event(context, &data.sink_inputs, tx, op, i),
+        Facility::Source => {
+            source::on_event(context, &data.sources, &data.default_source_name, tx, op, i)
+        }
         _ => error!("Received unhandled facility: {facility:?}"),
     }
 }
 
-fn on_server_event(
-    context: &Arc<Mutex<Context>>,
-    sinks: &ArcMutVec<Sink>,
-    default_sink: &Arc<Mutex<Option<String>>>,
-    tx: &broadcast::Sender<Event>,
-) {
+fn on_server_event(context: &Arc<Mutex<Context>>, data: &Data, tx: &broadcast::Sender<Event>) {
     lock!(context).introspect().get_server_info({
-        let sinks = sinks.clone();
-        let default_sink = default_sink.clone();
+        let data = data.clone();
         let tx = tx.clone();
 
-        move |info| set_default_sink(info, &sinks, &default_sink, &tx)
+        move |info| {
+            set_default_sink(info, &data.sinks, &data.default_sink_name, &tx);
+            set_default_source(info, &data.sources, &data.default_source_name, &tx);
+        }
     });
 }
 
@@ -279,6 +313,31 @@ fn set_default_sink(
     *lock!(default_sink) = default_sink_name;
 }
 
+fn set_default_source(
+    info: &ServerInfo,
+    sources: &ArcMutVec<Source>,
+    default_source: &Arc<Mutex<Option<String>>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let default_source_name = info.default_source_name.as_ref().map(ToString::to_string);
+
+    if default_source_name == *lock!(default_source) {
+        return;
+    }
+
+    for source in lock!(sources).iter_mut() {
+        let active = default_source_name.as_deref() == Some(source.name.as_str());
+
+        if source.active != active {
+            source.active = active;
+            debug!("Set source active state: {} -> {active}", source.name);
+            send!(tx, Event::UpdateSource(source.clone()));
+        }
+    }
+
+    *lock!(default_source) = default_source_name;
+}
+
 /// Converts a Pulse `ChannelVolumes` struct into a single percentage value,
 /// representing the average value across all channels.
 fn volume_to_percent(volume: ChannelVolumes) -> f64 {
@@ -290,6 +349,7 @@ fn volume_to_percent(volume: ChannelVolumes) -> f64 {
 
 /// Converts a percentage volume into a Pulse volume value,
 /// which can be used for setting channel volumes.
+#[cfg(feature = "bar")]
 pub fn percent_to_volume(target_percent: f64) -> u32 {
     let base_delta = (Volume::NORMAL.0 as f32 - Volume::MUTED.0 as f32) / 100.0;
 
@@ -307,3 +367,16 @@ pub fn percent_to_volume(target_percent: f64) -> u32 {
 }
 
 register_client!(Client, volume);
+
+#[cfg(feature = "ipc")]
+impl super::ExportState for Client {
+    fn export_state(&self) -> color_eyre::Result<serde_json::Value> {
+        Ok(serde_json::json!({
+            "sinks": &*lock!(self.data.sinks),
+            "default_sink": &*lock!(self.data.default_sink_name),
+            "sources": &*lock!(self.data.sources),
+            "default_source": &*lock!(self.data.default_source_name),
+            "inputs": &*lock!(self.data.sink_inputs),
+        }))
+    }
+}
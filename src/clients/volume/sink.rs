@@ -1,17 +1,22 @@
-use super::{percent_to_volume, volume_to_percent, ArcMutVec, Client, ConnectionState, Event};
+#[cfg(feature = "bar")]
+use super::{percent_to_volume, Client, ConnectionState};
+use super::{volume_to_percent, ArcMutVec, Event};
 use crate::{lock, send};
 use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::introspect::SinkInfo;
 use libpulse_binding::context::subscribe::Operation;
 use libpulse_binding::context::Context;
 use libpulse_binding::def::SinkState;
-use std::sync::{mpsc, Arc, Mutex};
+use serde::Serialize;
+#[cfg(feature = "bar")]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tracing::{debug, error};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Sink {
-    index: u32,
+    pub index: u32,
     pub name: String,
     pub description: String,
     pub volume: f64,
@@ -40,6 +45,7 @@ impl From<&SinkInfo<'_>> for Sink {
     }
 }
 
+#[cfg(feature = "bar")]
 impl Client {
     pub fn sinks(&self) -> Arc<Mutex<Vec<Sink>>> {
         self.data.sinks.clone()
@@ -1,14 +1,20 @@
-use super::{percent_to_volume, volume_to_percent, ArcMutVec, Client, ConnectionState, Event};
+#[cfg(feature = "bar")]
+use super::{percent_to_volume, Client, ConnectionState};
+use super::{volume_to_percent, ArcMutVec, Event};
 use crate::{lock, send};
 use libpulse_binding::callbacks::ListResult;
 use libpulse_binding::context::introspect::SinkInputInfo;
 use libpulse_binding::context::subscribe::Operation;
 use libpulse_binding::context::Context;
-use std::sync::{mpsc, Arc, Mutex};
+use libpulse_binding::proplist::properties;
+use serde::Serialize;
+#[cfg(feature = "bar")]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tracing::{debug, error};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SinkInput {
     pub index: u32,
     pub name: String,
@@ -16,6 +22,13 @@ pub struct SinkInput {
     pub muted: bool,
 
     pub can_set_volume: bool,
+
+    /// The index of the sink the input is playing on.
+    pub sink: u32,
+    /// The `application.name` of the client playing the input.
+    pub application_name: Option<String>,
+    /// The `application.process.binary` of the client playing the input.
+    pub application_binary: Option<String>,
 }
 
 impl From<&SinkInputInfo<'_>> for SinkInput {
@@ -30,10 +43,16 @@ impl From<&SinkInputInfo<'_>> for SinkInput {
             muted: value.mute,
             volume: volume_to_percent(value.volume),
             can_set_volume: value.has_volume && value.volume_writable,
+            sink: value.sink,
+            application_name: value.proplist.get_str(properties::APPLICATION_NAME),
+            application_binary: value
+                .proplist
+                .get_str(properties::APPLICATION_PROCESS_BINARY),
         }
     }
 }
 
+#[cfg(feature = "bar")]
 impl Client {
     pub fn sink_inputs(&self) -> Arc<Mutex<Vec<SinkInput>>> {
         self.data.sink_inputs.clone()
@@ -66,6 +85,13 @@ impl Client {
             introspector.set_sink_input_mute(index, muted, None);
         }
     }
+
+    /// Moves the input to play on the sink with the given name.
+    pub fn move_input_to_sink(&self, index: u32, sink_name: &str) {
+        if let ConnectionState::Connected { introspector, .. } = &mut *lock!(self.connection) {
+            introspector.move_sink_input_by_name(index, sink_name, None);
+        }
+    }
 }
 
 pub fn on_event(
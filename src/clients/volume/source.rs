@@ -0,0 +1,179 @@
+#[cfg(feature = "bar")]
+use super::{percent_to_volume, Client, ConnectionState};
+use super::{volume_to_percent, ArcMutVec, Event};
+use crate::{lock, send};
+use libpulse_binding::callbacks::ListResult;
+use libpulse_binding::context::introspect::SourceInfo;
+use libpulse_binding::context::subscribe::Operation;
+use libpulse_binding::context::Context;
+use serde::Serialize;
+#[cfg(feature = "bar")]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Source {
+    index: u32,
+    pub name: String,
+    pub description: String,
+    pub volume: f64,
+    pub muted: bool,
+    pub active: bool,
+}
+
+impl From<&SourceInfo<'_>> for Source {
+    fn from(value: &SourceInfo) -> Self {
+        Self {
+            index: value.index,
+            name: value
+                .name
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            description: value
+                .description
+                .as_ref()
+                .map(ToString::to_string)
+                .unwrap_or_default(),
+            muted: value.mute,
+            volume: volume_to_percent(value.volume),
+            active: false,
+        }
+    }
+}
+
+#[cfg(feature = "bar")]
+impl Client {
+    pub fn sources(&self) -> Arc<Mutex<Vec<Source>>> {
+        self.data.sources.clone()
+    }
+
+    pub fn set_source_volume(&self, name: &str, volume_percent: f64) {
+        if let ConnectionState::Connected { introspector, .. } = &mut *lock!(self.connection) {
+            let (tx, rx) = mpsc::channel();
+
+            introspector.get_source_info_by_name(name, move |info| {
+                let ListResult::Item(info) = info else {
+                    return;
+                };
+                send!(tx, info.volume);
+            });
+
+            let new_volume = percent_to_volume(volume_percent);
+
+            let mut volume = rx.recv().expect("to receive info");
+            for v in volume.get_mut() {
+                v.0 = new_volume;
+            }
+
+            introspector.set_source_volume_by_name(name, &volume, None);
+        }
+    }
+
+    pub fn set_source_muted(&self, name: &str, muted: bool) {
+        if let ConnectionState::Connected { introspector, .. } = &mut *lock!(self.connection) {
+            introspector.set_source_mute_by_name(name, muted, None);
+        }
+    }
+}
+
+pub fn on_event(
+    context: &Arc<Mutex<Context>>,
+    sources: &ArcMutVec<Source>,
+    default_source: &Arc<Mutex<Option<String>>>,
+    tx: &broadcast::Sender<Event>,
+    op: Operation,
+    i: u32,
+) {
+    let introspect = lock!(context).introspect();
+
+    match op {
+        Operation::New => {
+            debug!("new source");
+            introspect.get_source_info_by_index(i, {
+                let sources = sources.clone();
+                let default_source = default_source.clone();
+                let tx = tx.clone();
+
+                move |info| add(info, &sources, &default_source, &tx)
+            });
+        }
+        Operation::Changed => {
+            debug!("source changed");
+            introspect.get_source_info_by_index(i, {
+                let sources = sources.clone();
+                let default_source = default_source.clone();
+                let tx = tx.clone();
+
+                move |info| update(info, &sources, &default_source, &tx)
+            });
+        }
+        Operation::Removed => {
+            debug!("source removed");
+            remove(i, sources, tx);
+        }
+    }
+}
+
+pub fn add(
+    info: ListResult<&SourceInfo>,
+    sources: &ArcMutVec<Source>,
+    default_source: &Arc<Mutex<Option<String>>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    // monitors of output devices are not microphones
+    if info.monitor_of_sink.is_some() {
+        return;
+    }
+
+    let mut source: Source = info.into();
+    source.active = lock!(default_source).as_deref() == Some(source.name.as_str());
+
+    lock!(sources).push(source.clone());
+    send!(tx, Event::AddSource(source));
+}
+
+fn update(
+    info: ListResult<&SourceInfo>,
+    sources: &ArcMutVec<Source>,
+    default_source: &Arc<Mutex<Option<String>>>,
+    tx: &broadcast::Sender<Event>,
+) {
+    let ListResult::Item(info) = info else {
+        return;
+    };
+
+    if info.monitor_of_sink.is_some() {
+        return;
+    }
+
+    let mut source: Source = info.into();
+    source.active = lock!(default_source).as_deref() == Some(source.name.as_str());
+
+    {
+        let mut sources = lock!(sources);
+        let Some(pos) = sources.iter().position(|s| s.index == info.index) else {
+            error!("received update to untracked source");
+            return;
+        };
+
+        sources[pos] = source.clone();
+    }
+
+    send!(tx, Event::UpdateSource(source));
+}
+
+fn remove(index: u32, sources: &ArcMutVec<Source>, tx: &broadcast::Sender<Event>) {
+    let mut sources = lock!(sources);
+
+    if let Some(pos) = sources.iter().position(|s| s.index == index) {
+        let info = sources.remove(pos);
+        send!(tx, Event::RemoveSource(info.name));
+    }
+}
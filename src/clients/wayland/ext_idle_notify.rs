@@ -0,0 +1,122 @@
+use super::{Client, Environment, Event, Request, Response};
+use crate::try_send;
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::reexports::protocols::ext::idle_notify::v1::client::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::ExtIdleNotifierV1,
+};
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::{Connection, Dispatch, QueueHandle};
+
+/// Sent when the user goes idle or resumes activity.
+#[derive(Debug, Clone, Copy)]
+pub struct IdleEvent {
+    /// The timeout of the notification, in milliseconds.
+    pub timeout: u32,
+    pub idle: bool,
+}
+
+#[derive(Debug)]
+pub struct IdleNotifierState {
+    notifier: ExtIdleNotifierV1,
+}
+
+impl IdleNotifierState {
+    pub fn bind<State>(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError>
+    where
+        State: Dispatch<ExtIdleNotifierV1, GlobalData> + 'static,
+    {
+        let notifier = globals.bind(qh, 1..=1, GlobalData)?;
+        debug!("Bound to ExtIdleNotifierV1 global");
+        Ok(Self { notifier })
+    }
+}
+
+/// User data attached to each idle notification object.
+#[derive(Debug)]
+pub struct IdleNotificationData {
+    timeout: u32,
+}
+
+impl Client {
+    /// Requests idle notifications after `timeout` milliseconds of inactivity.
+    /// Notifications for each unique timeout are only registered once.
+    pub fn register_idle_notification(&self, timeout: u32) {
+        match self.send_request(Request::IdleNotification(timeout)) {
+            Response::Ok => (),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Subscribes to idle/resume events for all registered timeouts.
+    pub fn subscribe_idle(&self) -> broadcast::Receiver<IdleEvent> {
+        self.idle_channel.0.subscribe()
+    }
+}
+
+impl Environment {
+    pub(super) fn register_idle_notification(&mut self, timeout: u32) {
+        if self.idle_notifications.iter().any(|(t, _)| *t == timeout) {
+            return;
+        }
+
+        let Some(state) = &self.idle_notifier_state else {
+            warn!("Compositor does not support ext_idle_notify_v1, idle time will not be tracked");
+            return;
+        };
+
+        debug!("Registering idle notification with {timeout}ms timeout");
+
+        let seat = self.default_seat();
+        let notification = state.notifier.get_idle_notification(
+            timeout,
+            &seat,
+            &self.queue_handle,
+            IdleNotificationData { timeout },
+        );
+
+        self.idle_notifications.push((timeout, notification));
+    }
+}
+
+impl Dispatch<ExtIdleNotifierV1, GlobalData> for Environment {
+    fn event(
+        _state: &mut Self,
+        _notifier: &ExtIdleNotifierV1,
+        _event: <ExtIdleNotifierV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // the notifier has no events
+    }
+}
+
+impl Dispatch<ExtIdleNotificationV1, IdleNotificationData> for Environment {
+    fn event(
+        state: &mut Self,
+        _notification: &ExtIdleNotificationV1,
+        event: ext_idle_notification_v1::Event,
+        data: &IdleNotificationData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        let idle = match event {
+            ext_idle_notification_v1::Event::Idled => true,
+            ext_idle_notification_v1::Event::Resumed => false,
+            _ => return,
+        };
+
+        debug!("Idle state changed ({}ms timeout): {idle}", data.timeout);
+
+        try_send!(
+            state.event_tx,
+            Event::Idle(IdleEvent {
+                timeout: data.timeout,
+                idle,
+            })
+        );
+    }
+}
@@ -26,7 +26,7 @@ use wayland_client::{Connection, QueueHandle};
 pub use wl_output::{OutputEvent, OutputEventType};
 
 cfg_if! {
-    if #[cfg(any(feature = "focused", feature = "launcher"))] {
+    if #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))] {
         mod wlr_foreign_toplevel;
         use crate::{delegate_foreign_toplevel_handle, delegate_foreign_toplevel_manager};
         use wlr_foreign_toplevel::manager::ToplevelManagerState;
@@ -35,22 +35,61 @@ cfg_if! {
     }
 }
 
+cfg_if! {
+    if #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))] {
+        mod ext_idle_notify;
+
+        use self::ext_idle_notify::IdleNotifierState;
+        use smithay_client_toolkit::reexports::protocols::ext::idle_notify::v1::client::ext_idle_notification_v1::ExtIdleNotificationV1;
+
+        pub use ext_idle_notify::IdleEvent;
+    }
+}
+
+cfg_if! {
+    if #[cfg(all(feature = "bar", feature = "workspaces"))] {
+        mod wlr_screencopy;
+
+        use self::wlr_screencopy::ScreencopyManagerState;
+        use smithay_client_toolkit::delegate_shm;
+        use smithay_client_toolkit::shm::Shm;
+
+        pub use wlr_screencopy::Screenshot;
+    }
+}
+
+cfg_if! {
+    if #[cfg(all(feature = "bar", feature = "displays"))] {
+        mod wlr_output_management;
+
+        use self::wlr_output_management::{OutputHeadEntry, OutputManagerState};
+
+        pub use wlr_output_management::{OutputChange, OutputHead, OutputMode};
+    }
+}
+
 cfg_if! {
     if #[cfg(feature = "clipboard")] {
         mod wlr_data_control;
 
-        use crate::{delegate_data_control_device, delegate_data_control_device_manager, delegate_data_control_offer, delegate_data_control_source};
+        use crate::{delegate_data_control_device, delegate_data_control_device_manager, delegate_data_control_offer};
         use self::wlr_data_control::device::DataControlDevice;
         use self::wlr_data_control::manager::DataControlDeviceManagerState;
-        use self::wlr_data_control::source::CopyPasteSource;
         use self::wlr_data_control::SelectionOfferItem;
         use wayland_client::protocol::wl_seat::WlSeat;
 
-        pub use wlr_data_control::{ClipboardItem, ClipboardValue};
+        pub use wlr_data_control::{ClipboardItem, ClipboardValue, SelectionTarget};
+
+        #[cfg(feature = "bar")]
+        use crate::delegate_data_control_source;
+        #[cfg(feature = "bar")]
+        use self::wlr_data_control::source::CopyPasteSource;
 
         #[derive(Debug)]
         pub struct DataControlDeviceEntry {
             seat: WlSeat,
+            // kept alive to receive selection events, only read when copying
+            #[cfg_attr(not(feature = "bar"), allow(dead_code))]
             device: DataControlDevice,
         }
     }
@@ -59,10 +98,16 @@ cfg_if! {
 #[derive(Debug)]
 pub enum Event {
     Output(OutputEvent),
-    #[cfg(any(feature = "focused", feature = "launcher"))]
+    #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
     Toplevel(ToplevelEvent),
     #[cfg(feature = "clipboard")]
     Clipboard(ClipboardItem),
+    #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+    Idle(IdleEvent),
+    #[cfg(all(feature = "bar", feature = "displays"))]
+    OutputHeads(Vec<OutputHead>),
+    #[cfg(all(feature = "bar", feature = "workspaces"))]
+    Screenshot(Screenshot),
 }
 
 #[derive(Debug)]
@@ -72,15 +117,26 @@ pub enum Request {
     #[cfg(feature = "ipc")]
     OutputInfoAll,
 
-    #[cfg(any(feature = "focused", feature = "launcher"))]
+    #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
     ToplevelInfoAll,
-    #[cfg(feature = "launcher")]
+    #[cfg(all(feature = "bar", feature = "launcher"))]
     ToplevelFocus(usize),
 
-    #[cfg(feature = "clipboard")]
-    CopyToClipboard(ClipboardItem),
+    #[cfg(all(feature = "bar", feature = "clipboard"))]
+    CopyToClipboard(ClipboardItem, SelectionTarget),
     #[cfg(feature = "clipboard")]
     ClipboardItem,
+
+    #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+    IdleNotification(u32),
+
+    #[cfg(all(feature = "bar", feature = "displays"))]
+    OutputHeads,
+    #[cfg(all(feature = "bar", feature = "displays"))]
+    ConfigureOutput(String, OutputChange),
+
+    #[cfg(all(feature = "bar", feature = "workspaces"))]
+    CaptureOutput(String),
 }
 
 #[derive(Debug)]
@@ -91,11 +147,14 @@ pub enum Response {
     #[cfg(feature = "ipc")]
     OutputInfoAll(Vec<smithay_client_toolkit::output::OutputInfo>),
 
-    #[cfg(any(feature = "focused", feature = "launcher"))]
+    #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
     ToplevelInfoAll(Vec<ToplevelInfo>),
 
     #[cfg(feature = "clipboard")]
     ClipboardItem(Option<ClipboardItem>),
+
+    #[cfg(all(feature = "bar", feature = "displays"))]
+    OutputHeads(Vec<OutputHead>),
 }
 
 #[derive(Debug)]
@@ -114,10 +173,16 @@ pub struct Client {
     rx: Arc<Mutex<std::sync::mpsc::Receiver<Response>>>,
 
     output_channel: BroadcastChannel<OutputEvent>,
-    #[cfg(any(feature = "focused", feature = "launcher"))]
+    #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
     toplevel_channel: BroadcastChannel<ToplevelEvent>,
     #[cfg(feature = "clipboard")]
     clipboard_channel: BroadcastChannel<ClipboardItem>,
+    #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+    idle_channel: BroadcastChannel<IdleEvent>,
+    #[cfg(all(feature = "bar", feature = "displays"))]
+    output_head_channel: BroadcastChannel<Vec<OutputHead>>,
+    #[cfg(all(feature = "bar", feature = "workspaces"))]
+    screencopy_channel: BroadcastChannel<Screenshot>,
 }
 
 impl Client {
@@ -128,12 +193,21 @@ impl Client {
         let (response_tx, response_rx) = std::sync::mpsc::channel();
 
         let output_channel = broadcast::channel(32);
-        #[cfg(any(feature = "focused", feature = "launcher"))]
+        #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
         let toplevel_channel = broadcast::channel(32);
 
         #[cfg(feature = "clipboard")]
         let clipboard_channel = broadcast::channel(32);
 
+        #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+        let idle_channel = broadcast::channel(8);
+
+        #[cfg(all(feature = "bar", feature = "displays"))]
+        let output_head_channel = broadcast::channel(8);
+
+        #[cfg(all(feature = "bar", feature = "workspaces"))]
+        let screencopy_channel = broadcast::channel(4);
+
         spawn_blocking(move || {
             Environment::spawn(event_tx, request_rx, response_tx);
         });
@@ -141,20 +215,38 @@ impl Client {
         // listen to events
         {
             let output_tx = output_channel.0.clone();
-            #[cfg(any(feature = "focused", feature = "launcher"))]
+            #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
             let toplevel_tx = toplevel_channel.0.clone();
 
             #[cfg(feature = "clipboard")]
             let clipboard_tx = clipboard_channel.0.clone();
 
+            #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+            let idle_tx = idle_channel.0.clone();
+
+            #[cfg(all(feature = "bar", feature = "displays"))]
+            let output_head_tx = output_head_channel.0.clone();
+
+            #[cfg(all(feature = "bar", feature = "workspaces"))]
+            let screencopy_tx = screencopy_channel.0.clone();
+
             spawn(async move {
                 while let Some(event) = event_rx.recv().await {
                     match event {
                         Event::Output(event) => send!(output_tx, event),
-                        #[cfg(any(feature = "focused", feature = "launcher"))]
+                        #[cfg(all(
+                            feature = "bar",
+                            any(feature = "focused", feature = "launcher")
+                        ))]
                         Event::Toplevel(event) => send!(toplevel_tx, event),
                         #[cfg(feature = "clipboard")]
                         Event::Clipboard(item) => send!(clipboard_tx, item),
+                        #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+                        Event::Idle(event) => send!(idle_tx, event),
+                        #[cfg(all(feature = "bar", feature = "displays"))]
+                        Event::OutputHeads(heads) => send!(output_head_tx, heads),
+                        #[cfg(all(feature = "bar", feature = "workspaces"))]
+                        Event::Screenshot(screenshot) => send!(screencopy_tx, screenshot),
                     };
                 }
             });
@@ -165,10 +257,16 @@ impl Client {
             rx: arc_mut!(response_rx),
 
             output_channel: output_channel.into(),
-            #[cfg(any(feature = "focused", feature = "launcher"))]
+            #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
             toplevel_channel: toplevel_channel.into(),
             #[cfg(feature = "clipboard")]
             clipboard_channel: clipboard_channel.into(),
+            #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+            idle_channel: idle_channel.into(),
+            #[cfg(all(feature = "bar", feature = "displays"))]
+            output_head_channel: output_head_channel.into(),
+            #[cfg(all(feature = "bar", feature = "workspaces"))]
+            screencopy_channel: screencopy_channel.into(),
         }
     }
 
@@ -199,7 +297,7 @@ pub struct Environment {
     response_tx: std::sync::mpsc::Sender<Response>,
 
     // local state
-    #[cfg(any(feature = "focused", feature = "launcher"))]
+    #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
     handles: Vec<ToplevelHandle>,
 
     // -- clipboard --
@@ -208,14 +306,36 @@ pub struct Environment {
 
     #[cfg(feature = "clipboard")]
     data_control_devices: Vec<DataControlDeviceEntry>,
-    #[cfg(feature = "clipboard")]
+    #[cfg(all(feature = "bar", feature = "clipboard"))]
     copy_paste_sources: Vec<CopyPasteSource>,
+    #[cfg(all(feature = "bar", feature = "clipboard"))]
+    primary_selection_sources: Vec<CopyPasteSource>,
     #[cfg(feature = "clipboard")]
     selection_offers: Vec<SelectionOfferItem>,
 
     // local state
     #[cfg(feature = "clipboard")]
     clipboard: Arc<Mutex<Option<ClipboardItem>>>,
+    #[cfg(feature = "clipboard")]
+    primary_selection: Arc<Mutex<Option<ClipboardItem>>>,
+
+    // -- idle --
+    #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+    idle_notifier_state: Option<IdleNotifierState>,
+    #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+    idle_notifications: Vec<(u32, ExtIdleNotificationV1)>,
+
+    // -- output management --
+    #[cfg(all(feature = "bar", feature = "displays"))]
+    output_manager_state: Option<OutputManagerState>,
+    #[cfg(all(feature = "bar", feature = "displays"))]
+    output_heads: Vec<OutputHeadEntry>,
+
+    // -- screencopy --
+    #[cfg(all(feature = "bar", feature = "workspaces"))]
+    shm: Shm,
+    #[cfg(all(feature = "bar", feature = "workspaces"))]
+    screencopy_manager_state: Option<ScreencopyManagerState>,
 }
 
 delegate_registry!(Environment);
@@ -224,21 +344,26 @@ delegate_output!(Environment);
 delegate_seat!(Environment);
 
 cfg_if! {
-    if #[cfg(any(feature = "focused", feature = "launcher"))] {
+    if #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))] {
         delegate_foreign_toplevel_manager!(Environment);
         delegate_foreign_toplevel_handle!(Environment);
     }
 }
 
+#[cfg(all(feature = "bar", feature = "workspaces"))]
+delegate_shm!(Environment);
+
 cfg_if! {
     if #[cfg(feature = "clipboard")] {
         delegate_data_control_device_manager!(Environment);
         delegate_data_control_device!(Environment);
         delegate_data_control_offer!(Environment);
-        delegate_data_control_source!(Environment);
     }
 }
 
+#[cfg(all(feature = "bar", feature = "clipboard"))]
+delegate_data_control_source!(Environment);
+
 impl Environment {
     pub fn spawn(
         event_tx: mpsc::Sender<Event>,
@@ -264,7 +389,7 @@ impl Environment {
 
         let output_state = OutputState::new(&globals, &qh);
         let seat_state = SeatState::new(&globals, &qh);
-        #[cfg(any(feature = "focused", feature = "launcher"))]
+        #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
         ToplevelManagerState::bind(&globals, &qh)
             .expect("to bind to wlr_foreign_toplevel_manager global");
 
@@ -272,6 +397,37 @@ impl Environment {
         let data_control_device_manager_state = DataControlDeviceManagerState::bind(&globals, &qh)
             .expect("to bind to wlr_data_control_device_manager global");
 
+        // not all compositors support this protocol, so failing to bind is not fatal
+        #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+        let idle_notifier_state = match IdleNotifierState::bind(&globals, &qh) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                debug!("Failed to bind to ext_idle_notifier_v1 global: {err}");
+                None
+            }
+        };
+
+        #[cfg(all(feature = "bar", feature = "displays"))]
+        let output_manager_state = match OutputManagerState::bind(&globals, &qh) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                debug!("Failed to bind to zwlr_output_manager_v1 global: {err}");
+                None
+            }
+        };
+
+        #[cfg(all(feature = "bar", feature = "workspaces"))]
+        let shm = Shm::bind(&globals, &qh).expect("to bind to wl_shm global");
+
+        #[cfg(all(feature = "bar", feature = "workspaces"))]
+        let screencopy_manager_state = match ScreencopyManagerState::bind(&globals, &qh) {
+            Ok(state) => Some(state),
+            Err(err) => {
+                debug!("Failed to bind to zwlr_screencopy_manager_v1 global: {err}");
+                None
+            }
+        };
+
         let mut env = Self {
             registry_state,
             output_state,
@@ -282,17 +438,33 @@ impl Environment {
             loop_handle: loop_handle.clone(),
             event_tx,
             response_tx,
-            #[cfg(any(feature = "focused", feature = "launcher"))]
+            #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
             handles: vec![],
 
             #[cfg(feature = "clipboard")]
             data_control_devices: vec![],
-            #[cfg(feature = "clipboard")]
+            #[cfg(all(feature = "bar", feature = "clipboard"))]
             copy_paste_sources: vec![],
+            #[cfg(all(feature = "bar", feature = "clipboard"))]
+            primary_selection_sources: vec![],
             #[cfg(feature = "clipboard")]
             selection_offers: vec![],
             #[cfg(feature = "clipboard")]
             clipboard: arc_mut!(None),
+            #[cfg(feature = "clipboard")]
+            primary_selection: arc_mut!(None),
+            #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+            idle_notifier_state,
+            #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+            idle_notifications: vec![],
+            #[cfg(all(feature = "bar", feature = "displays"))]
+            output_manager_state,
+            #[cfg(all(feature = "bar", feature = "displays"))]
+            output_heads: vec![],
+            #[cfg(all(feature = "bar", feature = "workspaces"))]
+            shm,
+            #[cfg(all(feature = "bar", feature = "workspaces"))]
+            screencopy_manager_state,
         };
 
         loop_handle
@@ -327,7 +499,7 @@ impl Environment {
                 let infos = env.output_info_all();
                 send!(env.response_tx, Response::OutputInfoAll(infos));
             }
-            #[cfg(any(feature = "focused", feature = "launcher"))]
+            #[cfg(all(feature = "bar", any(feature = "focused", feature = "launcher")))]
             Msg(Request::ToplevelInfoAll) => {
                 let infos = env
                     .handles
@@ -336,7 +508,7 @@ impl Environment {
                     .collect();
                 send!(env.response_tx, Response::ToplevelInfoAll(infos));
             }
-            #[cfg(feature = "launcher")]
+            #[cfg(all(feature = "bar", feature = "launcher"))]
             Msg(Request::ToplevelFocus(id)) => {
                 let handle = env
                     .handles
@@ -350,9 +522,9 @@ impl Environment {
 
                 send!(env.response_tx, Response::Ok);
             }
-            #[cfg(feature = "clipboard")]
-            Msg(Request::CopyToClipboard(item)) => {
-                env.copy_to_clipboard(item);
+            #[cfg(all(feature = "bar", feature = "clipboard"))]
+            Msg(Request::CopyToClipboard(item, target)) => {
+                env.copy_to_clipboard(item, target);
                 send!(env.response_tx, Response::Ok);
             }
             #[cfg(feature = "clipboard")]
@@ -360,6 +532,26 @@ impl Environment {
                 let item = lock!(env.clipboard).clone();
                 send!(env.response_tx, Response::ClipboardItem(item));
             }
+            #[cfg(all(feature = "bar", any(feature = "clock", feature = "worktime")))]
+            Msg(Request::IdleNotification(timeout)) => {
+                env.register_idle_notification(timeout);
+                send!(env.response_tx, Response::Ok);
+            }
+            #[cfg(all(feature = "bar", feature = "displays"))]
+            Msg(Request::OutputHeads) => {
+                let heads = env.output_heads();
+                send!(env.response_tx, Response::OutputHeads(heads));
+            }
+            #[cfg(all(feature = "bar", feature = "displays"))]
+            Msg(Request::ConfigureOutput(name, change)) => {
+                env.configure_output(&name, change);
+                send!(env.response_tx, Response::Ok);
+            }
+            #[cfg(all(feature = "bar", feature = "workspaces"))]
+            Msg(Request::CaptureOutput(output)) => {
+                env.capture_output(&output);
+                send!(env.response_tx, Response::Ok);
+            }
             calloop_channel::Event::Closed => error!("request channel unexpectedly closed"),
         }
     }
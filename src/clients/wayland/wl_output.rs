@@ -6,6 +6,8 @@ use tracing::{debug, error};
 use wayland_client::protocol::wl_output::WlOutput;
 use wayland_client::{Connection, QueueHandle};
 
+// only consumed by the bar
+#[cfg_attr(not(feature = "bar"), allow(dead_code))]
 #[derive(Debug, Clone)]
 pub struct OutputEvent {
     pub output: OutputInfo,
@@ -31,6 +33,7 @@ impl Client {
     }
 
     /// Subscribes to events from outputs.
+    #[cfg(feature = "bar")]
     pub fn subscribe_outputs(&self) -> broadcast::Receiver<OutputEvent> {
         self.output_channel.0.subscribe()
     }
@@ -6,6 +6,7 @@ use wayland_client::{Connection, QueueHandle};
 
 impl Environment {
     /// Gets the default seat.
+    #[cfg(feature = "bar")]
     pub(crate) fn default_seat(&self) -> WlSeat {
         self.seat_state.seats().next().expect("one seat to exist")
     }
@@ -17,10 +17,23 @@ pub struct DataControlDevice {
     pub device: ZwlrDataControlDeviceV1,
 }
 
+/// The selection an offer or source applies to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionTarget {
+    /// The regular clipboard, used by copy/paste.
+    #[default]
+    Clipboard,
+    /// The primary selection, set by selecting text and pasted with middle click.
+    /// This requires version 2 of the data control protocol.
+    Primary,
+}
+
 #[derive(Debug, Default)]
 pub struct DataControlDeviceInner {
     /// the active selection offer and its data
     selection_offer: Arc<Mutex<Option<ZwlrDataControlOfferV1>>>,
+    /// the active primary selection offer and its data
+    primary_selection_offer: Arc<Mutex<Option<ZwlrDataControlOfferV1>>>,
     /// the active undetermined offers and their data
     pub undetermined_offers: Arc<Mutex<Vec<ZwlrDataControlOfferV1>>>,
 }
@@ -35,9 +48,12 @@ pub trait DataControlDeviceDataExt: Send + Sync {
 
     fn data_control_device_data(&self) -> &DataControlDeviceData;
 
-    fn selection_mime_types(&self) -> Vec<String> {
+    fn selection_mime_types(&self, target: SelectionTarget) -> Vec<String> {
         let inner = self.data_control_device_data();
-        lock!(lock!(inner.inner).selection_offer)
+        let inner = lock!(inner.inner);
+        let offer = lock!(inner.offer(target));
+
+        offer
             .as_ref()
             .map(|offer| {
                 let data = offer
@@ -48,17 +64,27 @@ pub trait DataControlDeviceDataExt: Send + Sync {
             .unwrap_or_default()
     }
 
-    /// Get the active selection offer if it exists.
-    fn selection_offer(&self) -> Option<SelectionOffer> {
+    /// Get the active selection offer for the target if it exists.
+    fn selection_offer(&self, target: SelectionTarget) -> Option<SelectionOffer> {
         let inner = self.data_control_device_data();
-        lock!(lock!(inner.inner).selection_offer)
-            .as_ref()
-            .and_then(|offer| {
-                let data = offer
-                    .data::<Self::DataControlOfferInner>()
-                    .expect(ERR_WAYLAND_DATA);
-                data.as_selection_offer()
-            })
+        let inner = lock!(inner.inner);
+        let offer = lock!(inner.offer(target));
+
+        offer.as_ref().and_then(|offer| {
+            let data = offer
+                .data::<Self::DataControlOfferInner>()
+                .expect(ERR_WAYLAND_DATA);
+            data.as_selection_offer()
+        })
+    }
+}
+
+impl DataControlDeviceInner {
+    const fn offer(&self, target: SelectionTarget) -> &Arc<Mutex<Option<ZwlrDataControlOfferV1>>> {
+        match target {
+            SelectionTarget::Clipboard => &self.selection_offer,
+            SelectionTarget::Primary => &self.primary_selection_offer,
+        }
     }
 }
 
@@ -80,12 +106,13 @@ impl DataControlDeviceDataExt for DataControlDeviceData {
 ///
 /// The functions defined in this trait are called as `DataDevice` events are received from the compositor.
 pub trait DataControlDeviceHandler: Sized {
-    /// Advertises a new selection.
+    /// Advertises a new selection for the target.
     fn selection(
         &mut self,
         conn: &Connection,
         qh: &QueueHandle<Self>,
         data_device: DataControlDevice,
+        target: SelectionTarget,
     );
 }
 
@@ -129,34 +156,28 @@ where
                 drop(inner);
             }
             Event::Selection { id } => {
-                let mut selection_offer = lock!(inner.selection_offer);
-
-                if let Some(offer) = id {
-                    let mut undetermined = lock!(inner.undetermined_offers);
-                    if let Some(i) = undetermined.iter().position(|o| o == &offer) {
-                        undetermined.remove(i);
-                    }
-                    drop(undetermined);
-
-                    let data = offer
-                        .data::<V>()
-                        .expect(ERR_WAYLAND_DATA)
-                        .data_control_offer_data();
-                    data.to_selection_offer();
-                    // XXX Drop done here to prevent Mutex deadlocks.
-                    *selection_offer = Some(offer.clone());
-                    drop(selection_offer);
-                    drop(inner);
-                    state.selection(
-                        conn,
-                        qh,
-                        DataControlDevice {
-                            device: data_device.clone(),
-                        },
-                    );
-                } else {
-                    *selection_offer = None;
-                }
+                drop(inner);
+                Self::set_selection(
+                    state,
+                    data_device,
+                    data,
+                    id,
+                    SelectionTarget::Clipboard,
+                    conn,
+                    qh,
+                );
+            }
+            Event::PrimarySelection { id } => {
+                drop(inner);
+                Self::set_selection(
+                    state,
+                    data_device,
+                    data,
+                    id,
+                    SelectionTarget::Primary,
+                    conn,
+                    qh,
+                );
             }
             Event::Finished => {
                 warn!("Data control offer is no longer valid, but has not been dropped by client. This could cause clipboard issues.");
@@ -165,3 +186,53 @@ where
         }
     }
 }
+
+impl<V> DataControlDeviceManagerState<V>
+where
+    V: DataControlOfferDataExt + Default + 'static + Send + Sync,
+{
+    /// Stores the new offer for the selection target
+    /// and notifies the handler.
+    fn set_selection<D>(
+        state: &mut D,
+        data_device: &ZwlrDataControlDeviceV1,
+        data: &DataControlDeviceData,
+        id: Option<ZwlrDataControlOfferV1>,
+        target: SelectionTarget,
+        conn: &Connection,
+        qh: &QueueHandle<D>,
+    ) where
+        D: DataControlDeviceHandler,
+    {
+        let inner = lock!(data.inner);
+        let mut selection_offer = lock!(inner.offer(target));
+
+        if let Some(offer) = id {
+            let mut undetermined = lock!(inner.undetermined_offers);
+            if let Some(i) = undetermined.iter().position(|o| o == &offer) {
+                undetermined.remove(i);
+            }
+            drop(undetermined);
+
+            let data = offer
+                .data::<V>()
+                .expect(ERR_WAYLAND_DATA)
+                .data_control_offer_data();
+            data.to_selection_offer();
+            // XXX Drop done here to prevent Mutex deadlocks.
+            *selection_offer = Some(offer.clone());
+            drop(selection_offer);
+            drop(inner);
+            state.selection(
+                conn,
+                qh,
+                DataControlDevice {
+                    device: data_device.clone(),
+                },
+                target,
+            );
+        } else {
+            *selection_offer = None;
+        }
+    }
+}
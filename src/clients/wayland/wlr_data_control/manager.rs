@@ -1,5 +1,6 @@
 use super::device::{DataControlDevice, DataControlDeviceData, DataControlDeviceDataExt};
 use super::offer::DataControlOfferData;
+#[cfg(feature = "bar")]
 use super::source::{CopyPasteSource, DataControlSourceData, DataControlSourceDataExt};
 use smithay_client_toolkit::error::GlobalError;
 use smithay_client_toolkit::globals::{GlobalData, ProvidesBoundGlobal};
@@ -8,10 +9,11 @@ use tracing::debug;
 use wayland_client::globals::{BindError, GlobalList};
 use wayland_client::protocol::wl_seat::WlSeat;
 use wayland_client::{Connection, Dispatch, Proxy, QueueHandle};
+#[cfg(feature = "bar")]
+use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_source_v1::ZwlrDataControlSourceV1;
 use wayland_protocols_wlr::data_control::v1::client::{
     zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
     zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
-    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
 };
 
 #[derive(Debug)]
@@ -34,6 +36,7 @@ impl DataControlDeviceManagerState {
     }
 
     /// creates a data source for copy paste
+    #[cfg(feature = "bar")]
     pub fn create_copy_paste_source<'s, D, I>(
         &self,
         qh: &QueueHandle<D>,
@@ -49,6 +52,7 @@ impl DataControlDeviceManagerState {
     }
 
     /// creates a data source
+    #[cfg(feature = "bar")]
     fn create_data_control_source<'s, D, I>(
         &self,
         qh: &QueueHandle<D>,
@@ -69,6 +73,7 @@ impl DataControlDeviceManagerState {
     }
 
     /// create a new data source for a given seat with some user data
+    #[cfg(feature = "bar")]
     pub fn create_data_control_source_with_data<D, U>(
         &self,
         qh: &QueueHandle<D>,
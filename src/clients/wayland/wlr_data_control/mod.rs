@@ -1,30 +1,41 @@
 pub mod device;
 pub mod manager;
 pub mod offer;
+#[cfg(feature = "bar")]
 pub mod source;
 
+pub use self::device::SelectionTarget;
 use self::device::{DataControlDeviceDataExt, DataControlDeviceHandler};
 use self::offer::{DataControlDeviceOffer, DataControlOfferHandler, SelectionOffer};
-use self::source::DataControlSourceHandler;
 use super::{Client, Environment, Event, Request, Response};
 use crate::{lock, try_send, Ironbar};
+use cfg_if::cfg_if;
 use device::DataControlDevice;
-use glib::Bytes;
-use nix::fcntl::{fcntl, F_GETPIPE_SZ, F_SETPIPE_SZ};
-use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
-use smithay_client_toolkit::data_device_manager::WritePipe;
 use smithay_client_toolkit::reexports::calloop::{PostAction, RegistrationToken};
-use std::cmp::min;
 use std::fmt::{Debug, Formatter};
 use std::fs::File;
-use std::io::{ErrorKind, Read, Write};
-use std::os::fd::{AsRawFd, OwnedFd, RawFd};
-use std::sync::Arc;
-use std::{fs, io};
+use std::io;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
 use tokio::sync::broadcast;
 use tracing::{debug, error, trace};
 use wayland_client::{Connection, QueueHandle};
-use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_source_v1::ZwlrDataControlSourceV1;
+
+cfg_if! {
+    if #[cfg(feature = "bar")] {
+        use self::source::DataControlSourceHandler;
+        use nix::fcntl::{fcntl, F_GETPIPE_SZ, F_SETPIPE_SZ};
+        use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
+        use smithay_client_toolkit::data_device_manager::WritePipe;
+        use std::cmp::min;
+        use std::fs;
+        use std::io::{ErrorKind, Write};
+        use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+        use tracing::warn;
+        use wayland_client::Proxy;
+        use wayland_protocols_wlr::data_control::v1::client::zwlr_data_control_source_v1::ZwlrDataControlSourceV1;
+    }
+}
 
 const INTERNAL_MIME_TYPE: &str = "x-ironbar-internal";
 
@@ -43,6 +54,11 @@ pub struct ClipboardItem {
     pub id: usize,
     pub value: Arc<ClipboardValue>,
     pub mime_type: Arc<str>,
+    /// All mime types advertised by the source,
+    /// including any hint types which are not read.
+    pub offered_mime_types: Arc<[String]>,
+    /// The selection the item was copied to.
+    pub selection: SelectionTarget,
 }
 
 impl PartialEq<Self> for ClipboardItem {
@@ -54,10 +70,21 @@ impl PartialEq<Self> for ClipboardItem {
 #[derive(Clone, PartialEq, Eq)]
 pub enum ClipboardValue {
     Text(String),
-    Image(Bytes),
+    Image(Vec<u8>),
     Other,
 }
 
+impl ClipboardValue {
+    /// Gets the size of the value in bytes.
+    pub fn size(&self) -> usize {
+        match self {
+            Self::Text(text) => text.len(),
+            Self::Image(bytes) => bytes.len(),
+            Self::Other => 0,
+        }
+    }
+}
+
 impl Debug for ClipboardValue {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -124,9 +151,11 @@ impl Client {
         }
     }
 
-    /// Copies the provided value to the system clipboard.
-    pub fn copy_to_clipboard(&self, item: ClipboardItem) {
-        match self.send_request(Request::CopyToClipboard(item)) {
+    /// Copies the provided value to the system clipboard,
+    /// or to the primary selection.
+    #[cfg(feature = "bar")]
+    pub fn copy_to_clipboard(&self, item: ClipboardItem, target: SelectionTarget) {
+        match self.send_request(Request::CopyToClipboard(item, target)) {
             Response::Ok => (),
             _ => unreachable!(),
         }
@@ -144,9 +173,11 @@ impl Environment {
     /// seat's data control device.
     ///
     /// This provides it as an offer,
-    /// which the compositor will then treat as the current copied value.
-    pub fn copy_to_clipboard(&mut self, item: ClipboardItem) {
-        debug!("Copying item to clipboard: {item:?}");
+    /// which the compositor will then treat as the current copied value
+    /// for the target selection.
+    #[cfg(feature = "bar")]
+    pub fn copy_to_clipboard(&mut self, item: ClipboardItem, target: SelectionTarget) {
+        debug!("Copying item to {target:?} selection: {item:?}");
 
         let seat = self.default_seat();
         let Some(device) = self
@@ -157,18 +188,46 @@ impl Environment {
             return;
         };
 
+        if target == SelectionTarget::Primary && device.device.device.version() < 2 {
+            warn!("Compositor does not support primary selection through zwlr_data_control_manager_v1, item cannot be copied");
+            return;
+        }
+
         let source = self
             .data_control_device_manager_state
             .create_copy_paste_source(&self.queue_handle, [INTERNAL_MIME_TYPE, &item.mime_type]);
 
-        source.set_selection(&device.device);
-        self.copy_paste_sources.push(source);
+        match target {
+            SelectionTarget::Clipboard => {
+                source.set_selection(&device.device);
+                self.copy_paste_sources.push(source);
+
+                lock!(self.clipboard).replace(item);
+            }
+            SelectionTarget::Primary => {
+                source.set_primary_selection(&device.device);
+                self.primary_selection_sources.push(source);
+
+                lock!(self.primary_selection).replace(item);
+            }
+        }
+    }
 
-        lock!(self.clipboard).replace(item);
+    /// Gets the item last copied to the target selection.
+    fn selection_item(&self, target: SelectionTarget) -> &Arc<Mutex<Option<ClipboardItem>>> {
+        match target {
+            SelectionTarget::Clipboard => &self.clipboard,
+            SelectionTarget::Primary => &self.primary_selection,
+        }
     }
 
     /// Reads an offer file handle into a new `ClipboardItem`.
-    fn read_file(mime_type: &MimeType, file: &mut File) -> io::Result<ClipboardItem> {
+    fn read_file(
+        mime_type: &MimeType,
+        offered_mime_types: Arc<[String]>,
+        selection: SelectionTarget,
+        file: &mut File,
+    ) -> io::Result<ClipboardItem> {
         let value = match mime_type.category {
             MimeTypeCategory::Text => {
                 let mut txt = String::new();
@@ -182,8 +241,6 @@ impl Environment {
 
                 debug!("Read bytes: {}", bytes.len());
 
-                let bytes = Bytes::from(&bytes);
-
                 ClipboardValue::Image(bytes)
             }
         };
@@ -192,28 +249,33 @@ impl Environment {
             id: Ironbar::unique_id(),
             value: Arc::new(value),
             mime_type: mime_type.value.clone().into(),
+            offered_mime_types,
+            selection,
         })
     }
 }
 
 impl DataControlDeviceHandler for Environment {
     /// Called when an offer for a new value is received
-    /// (ie something has copied to the clipboard)
+    /// (ie something has copied to the clipboard or primary selection)
     fn selection(
         &mut self,
         _conn: &Connection,
         _qh: &QueueHandle<Self>,
         data_device: DataControlDevice,
+        target: SelectionTarget,
     ) {
-        debug!("Handler received selection event");
+        debug!("Handler received {target:?} selection event");
 
-        let mime_types = data_device.selection_mime_types();
+        let mime_types = data_device.selection_mime_types(target);
 
         if mime_types.contains(&INTERNAL_MIME_TYPE.to_string()) {
             return;
         }
 
-        if let Some(offer) = data_device.selection_offer() {
+        let clipboard = self.selection_item(target).clone();
+
+        if let Some(offer) = data_device.selection_offer(target) {
             self.selection_offers
                 .push(SelectionOfferItem { offer, token: None });
 
@@ -224,13 +286,15 @@ impl DataControlDeviceHandler for Environment {
 
             // clear prev
             let Some(mime_type) = MimeType::parse_multiple(&mime_types) else {
-                lock!(self.clipboard).take();
+                lock!(clipboard).take();
                 // send an event so the clipboard module is aware it's changed
                 try_send!(
                     self.event_tx,
                     Event::Clipboard(ClipboardItem {
                         id: usize::MAX,
                         mime_type: String::new().into(),
+                        offered_mime_types: mime_types.into(),
+                        selection: target,
                         value: Arc::new(ClipboardValue::Other)
                     })
                 );
@@ -243,7 +307,7 @@ impl DataControlDeviceHandler for Environment {
                 let offer_clone = cur_offer.offer.clone();
 
                 let tx = self.event_tx.clone();
-                let clipboard = self.clipboard.clone();
+                let offered_mime_types: Arc<[String]> = mime_types.into();
 
                 let token =
                     self.loop_handle
@@ -255,7 +319,12 @@ impl DataControlDeviceHandler for Environment {
                                 .map(|p| state.selection_offers.remove(p))
                                 .expect("Failed to find selection offer item");
 
-                            match Self::read_file(&mime_type, file.get_mut()) {
+                            match Self::read_file(
+                                &mime_type,
+                                offered_mime_types.clone(),
+                                target,
+                                file.get_mut(),
+                            ) {
                                 Ok(item) => {
                                     lock!(clipboard).replace(item.clone());
                                     try_send!(tx, Event::Clipboard(item));
@@ -293,6 +362,7 @@ impl DataControlOfferHandler for Environment {
     }
 }
 
+#[cfg(feature = "bar")]
 impl DataControlSourceHandler for Environment {
     // fn accept_mime(
     //     &mut self,
@@ -316,18 +386,29 @@ impl DataControlSourceHandler for Environment {
     ) {
         debug!("Handler received source send request event ({mime})");
 
-        if let Some(item) = lock!(self.clipboard).clone() {
+        let target = if self
+            .primary_selection_sources
+            .iter()
+            .any(|s| s.inner() == source)
+        {
+            SelectionTarget::Primary
+        } else {
+            SelectionTarget::Clipboard
+        };
+
+        if let Some(item) = lock!(self.selection_item(target)).clone() {
             let fd = OwnedFd::from(write_pipe);
             if self
                 .copy_paste_sources
-                .iter_mut()
+                .iter()
+                .chain(&self.primary_selection_sources)
                 .any(|s| s.inner() == source && MimeType::parse(&mime).is_some())
             {
                 trace!("Source found, writing to file");
 
                 let mut bytes = match item.value.as_ref() {
                     ClipboardValue::Text(text) => text.as_bytes(),
-                    ClipboardValue::Image(bytes) => bytes.as_ref(),
+                    ClipboardValue::Image(bytes) => bytes.as_slice(),
                     ClipboardValue::Other => panic!(
                         "{:?}",
                         io::Error::new(ErrorKind::Other, "Attempted to copy unsupported mime type")
@@ -384,10 +465,15 @@ impl DataControlSourceHandler for Environment {
     ) {
         debug!("Handler received source cancelled event");
 
-        self.copy_paste_sources
-            .iter()
-            .position(|s| s.inner() == source)
-            .map(|pos| self.copy_paste_sources.remove(pos));
+        for sources in [
+            &mut self.copy_paste_sources,
+            &mut self.primary_selection_sources,
+        ] {
+            sources
+                .iter()
+                .position(|s| s.inner() == source)
+                .map(|pos| sources.remove(pos));
+        }
         source.destroy();
     }
 }
@@ -398,6 +484,7 @@ impl DataControlSourceHandler for Environment {
 /// it will be clamped at this.
 ///
 /// Returns the new size if succeeded.
+#[cfg(feature = "bar")]
 fn set_pipe_size(fd: RawFd, size: usize) -> io::Result<i32> {
     // clamp size at kernel max
     let max_pipe_size = fs::read_to_string("/proc/sys/fs/pipe-max-size")
@@ -89,6 +89,12 @@ impl CopyPasteSource {
         device.device.set_selection(Some(&self.inner));
     }
 
+    /// Set the primary selection of the provided data device.
+    /// This requires version 2 of the data control protocol.
+    pub fn set_primary_selection(&self, device: &DataControlDevice) {
+        device.device.set_primary_selection(Some(&self.inner));
+    }
+
     pub const fn inner(&self) -> &ZwlrDataControlSourceV1 {
         &self.inner
     }
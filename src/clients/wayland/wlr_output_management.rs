@@ -0,0 +1,372 @@
+use super::{Client, Environment, Event, Request, Response};
+use crate::try_send;
+use smithay_client_toolkit::globals::GlobalData;
+use tokio::sync::broadcast;
+use tracing::{debug, error, warn};
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols_wlr::output_management::v1::client::{
+    zwlr_output_configuration_head_v1::ZwlrOutputConfigurationHeadV1,
+    zwlr_output_configuration_v1::{self, ZwlrOutputConfigurationV1},
+    zwlr_output_head_v1::{self, ZwlrOutputHeadV1},
+    zwlr_output_manager_v1::{self, ZwlrOutputManagerV1},
+    zwlr_output_mode_v1::{self, ZwlrOutputModeV1},
+};
+
+/// A video mode supported by an output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputMode {
+    pub width: i32,
+    pub height: i32,
+    /// The refresh rate in mHz, or `0` if unknown.
+    pub refresh: i32,
+    pub preferred: bool,
+}
+
+/// The current configuration of an output,
+/// as reported by the output manager.
+#[derive(Debug, Clone, Default)]
+pub struct OutputHead {
+    pub name: String,
+    pub description: String,
+    pub make: Option<String>,
+    pub model: Option<String>,
+    pub enabled: bool,
+    pub modes: Vec<OutputMode>,
+    pub current_mode: Option<OutputMode>,
+    pub scale: f64,
+}
+
+/// A change to apply to a single output.
+/// All other outputs keep their current configuration.
+#[derive(Debug, Clone)]
+pub enum OutputChange {
+    Enabled(bool),
+    Mode {
+        width: i32,
+        height: i32,
+        refresh: i32,
+    },
+    Scale(f64),
+}
+
+#[derive(Debug)]
+pub struct OutputManagerState {
+    manager: ZwlrOutputManagerV1,
+    /// The serial of the last complete configuration.
+    serial: u32,
+}
+
+impl OutputManagerState {
+    pub fn bind<State>(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError>
+    where
+        State: Dispatch<ZwlrOutputManagerV1, GlobalData> + 'static,
+    {
+        let manager = globals.bind(qh, 1..=4, GlobalData)?;
+        debug!("Bound to ZwlrOutputManagerV1 global");
+        Ok(Self { manager, serial: 0 })
+    }
+}
+
+/// A head along with the protocol objects needed to configure it.
+#[derive(Debug)]
+pub struct OutputHeadEntry {
+    head: ZwlrOutputHeadV1,
+    modes: Vec<(ZwlrOutputModeV1, OutputMode)>,
+    current_mode: Option<ZwlrOutputModeV1>,
+    info: OutputHead,
+}
+
+impl OutputHeadEntry {
+    fn info(&self) -> OutputHead {
+        let current_mode = self.current_mode.as_ref().and_then(|current| {
+            self.modes
+                .iter()
+                .find(|(mode, _)| mode == current)
+                .map(|(_, mode)| *mode)
+        });
+
+        OutputHead {
+            modes: self.modes.iter().map(|(_, mode)| *mode).collect(),
+            current_mode,
+            ..self.info.clone()
+        }
+    }
+}
+
+impl Client {
+    /// Gets the current configuration of all outputs.
+    pub fn output_heads(&self) -> Vec<OutputHead> {
+        match self.send_request(Request::OutputHeads) {
+            Response::OutputHeads(heads) => heads,
+            _ => unreachable!(),
+        }
+    }
+
+    /// Subscribes to output configuration changes.
+    /// Each event contains the full configuration of all outputs.
+    pub fn subscribe_output_heads(&self) -> broadcast::Receiver<Vec<OutputHead>> {
+        self.output_head_channel.0.subscribe()
+    }
+
+    /// Applies a change to the output with the given name.
+    pub fn configure_output(&self, name: String, change: OutputChange) {
+        match self.send_request(Request::ConfigureOutput(name, change)) {
+            Response::Ok => (),
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl Environment {
+    pub(super) fn output_heads(&self) -> Vec<OutputHead> {
+        self.output_heads
+            .iter()
+            .map(OutputHeadEntry::info)
+            .collect()
+    }
+
+    fn output_head_mut(&mut self, head: &ZwlrOutputHeadV1) -> Option<&mut OutputHeadEntry> {
+        self.output_heads
+            .iter_mut()
+            .find(|entry| &entry.head == head)
+    }
+
+    fn output_mode_mut(&mut self, mode: &ZwlrOutputModeV1) -> Option<&mut OutputMode> {
+        self.output_heads
+            .iter_mut()
+            .flat_map(|entry| entry.modes.iter_mut())
+            .find(|(proxy, _)| proxy == mode)
+            .map(|(_, mode)| mode)
+    }
+
+    pub(super) fn configure_output(&mut self, name: &str, change: OutputChange) {
+        let Some(state) = &self.output_manager_state else {
+            warn!("Compositor does not support wlr_output_management_v1, cannot configure outputs");
+            return;
+        };
+
+        debug!("Configuring output '{name}': {change:?}");
+
+        let configuration =
+            state
+                .manager
+                .create_configuration(state.serial, &self.queue_handle, GlobalData);
+
+        // every head must be either enabled or disabled in the configuration,
+        // so unchanged heads are added with their current state.
+        for entry in &self.output_heads {
+            let enabled = match (&change, entry.info.name == name) {
+                (OutputChange::Enabled(enabled), true) => *enabled,
+                _ => entry.info.enabled,
+            };
+
+            if !enabled {
+                configuration.disable_head(&entry.head);
+                continue;
+            }
+
+            let config_head = configuration.enable_head(&entry.head, &self.queue_handle, ());
+
+            if entry.info.name != name {
+                continue;
+            }
+
+            match change {
+                OutputChange::Enabled(_) => {}
+                OutputChange::Mode {
+                    width,
+                    height,
+                    refresh,
+                } => {
+                    let mode = entry.modes.iter().find(|(_, mode)| {
+                        mode.width == width && mode.height == height && mode.refresh == refresh
+                    });
+
+                    match mode {
+                        Some((mode, _)) => config_head.set_mode(mode),
+                        None => config_head.set_custom_mode(width, height, refresh),
+                    }
+                }
+                OutputChange::Scale(scale) => config_head.set_scale(scale),
+            }
+        }
+
+        configuration.apply();
+    }
+}
+
+impl Dispatch<ZwlrOutputManagerV1, GlobalData> for Environment {
+    event_created_child!(Environment, ZwlrOutputManagerV1, [
+        zwlr_output_manager_v1::EVT_HEAD_OPCODE => (ZwlrOutputHeadV1, ())
+    ]);
+
+    fn event(
+        state: &mut Self,
+        _manager: &ZwlrOutputManagerV1,
+        event: zwlr_output_manager_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_manager_v1::Event::Head { head } => {
+                debug!("Received new output head");
+                state.output_heads.push(OutputHeadEntry {
+                    head,
+                    modes: vec![],
+                    current_mode: None,
+                    info: OutputHead {
+                        scale: 1.0,
+                        ..OutputHead::default()
+                    },
+                });
+            }
+            zwlr_output_manager_v1::Event::Done { serial } => {
+                if let Some(manager_state) = &mut state.output_manager_state {
+                    manager_state.serial = serial;
+                }
+
+                try_send!(state.event_tx, Event::OutputHeads(state.output_heads()));
+            }
+            zwlr_output_manager_v1::Event::Finished => {
+                warn!(
+                    "Output manager is no longer valid, output configuration will not be available"
+                );
+                state.output_manager_state = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputHeadV1, ()> for Environment {
+    event_created_child!(Environment, ZwlrOutputHeadV1, [
+        zwlr_output_head_v1::EVT_MODE_OPCODE => (ZwlrOutputModeV1, ())
+    ]);
+
+    fn event(
+        state: &mut Self,
+        head: &ZwlrOutputHeadV1,
+        event: zwlr_output_head_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwlr_output_head_v1::Event::Finished = event {
+            state.output_heads.retain(|entry| &entry.head != head);
+
+            if head.version() >= 3 {
+                head.release();
+            }
+
+            return;
+        }
+
+        let Some(entry) = state.output_head_mut(head) else {
+            return;
+        };
+
+        match event {
+            zwlr_output_head_v1::Event::Name { name } => entry.info.name = name,
+            zwlr_output_head_v1::Event::Description { description } => {
+                entry.info.description = description;
+            }
+            zwlr_output_head_v1::Event::Make { make } => entry.info.make = Some(make),
+            zwlr_output_head_v1::Event::Model { model } => entry.info.model = Some(model),
+            zwlr_output_head_v1::Event::Enabled { enabled } => entry.info.enabled = enabled != 0,
+            zwlr_output_head_v1::Event::Scale { scale } => entry.info.scale = scale,
+            zwlr_output_head_v1::Event::Mode { mode } => {
+                entry.modes.push((
+                    mode,
+                    OutputMode {
+                        width: 0,
+                        height: 0,
+                        refresh: 0,
+                        preferred: false,
+                    },
+                ));
+            }
+            zwlr_output_head_v1::Event::CurrentMode { mode } => {
+                entry.current_mode = Some(mode);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputModeV1, ()> for Environment {
+    fn event(
+        state: &mut Self,
+        mode: &ZwlrOutputModeV1,
+        event: zwlr_output_mode_v1::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        if let zwlr_output_mode_v1::Event::Finished = event {
+            for entry in &mut state.output_heads {
+                entry.modes.retain(|(proxy, _)| proxy != mode);
+            }
+
+            if mode.version() >= 3 {
+                mode.release();
+            }
+
+            return;
+        }
+
+        let Some(output_mode) = state.output_mode_mut(mode) else {
+            return;
+        };
+
+        match event {
+            zwlr_output_mode_v1::Event::Size { width, height } => {
+                output_mode.width = width;
+                output_mode.height = height;
+            }
+            zwlr_output_mode_v1::Event::Refresh { refresh } => output_mode.refresh = refresh,
+            zwlr_output_mode_v1::Event::Preferred => output_mode.preferred = true,
+            _ => {}
+        }
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationV1, GlobalData> for Environment {
+    fn event(
+        _state: &mut Self,
+        configuration: &ZwlrOutputConfigurationV1,
+        event: zwlr_output_configuration_v1::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_output_configuration_v1::Event::Succeeded => {
+                debug!("Output configuration applied");
+            }
+            zwlr_output_configuration_v1::Event::Failed => {
+                error!("Compositor failed to apply output configuration");
+            }
+            zwlr_output_configuration_v1::Event::Cancelled => {
+                warn!("Output configuration was cancelled as outputs changed in the meantime");
+            }
+            _ => return,
+        }
+
+        configuration.destroy();
+    }
+}
+
+impl Dispatch<ZwlrOutputConfigurationHeadV1, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _head: &ZwlrOutputConfigurationHeadV1,
+        _event: <ZwlrOutputConfigurationHeadV1 as Proxy>::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // configuration heads have no events
+    }
+}
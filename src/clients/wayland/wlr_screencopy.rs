@@ -0,0 +1,253 @@
+use super::{Client, Environment, Event, Request, Response};
+use crate::{lock, try_send};
+use smithay_client_toolkit::globals::GlobalData;
+use smithay_client_toolkit::shm::raw::RawPool;
+use smithay_client_toolkit::shm::{Shm, ShmHandler};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tracing::{debug, error, warn};
+use wayland_client::globals::{BindError, GlobalList};
+use wayland_client::protocol::wl_buffer::{self, WlBuffer};
+use wayland_client::protocol::wl_shm::Format;
+use wayland_client::{Connection, Dispatch, QueueHandle, WEnum};
+use wayland_protocols_wlr::screencopy::v1::client::{
+    zwlr_screencopy_frame_v1::{self, ZwlrScreencopyFrameV1},
+    zwlr_screencopy_manager_v1::ZwlrScreencopyManagerV1,
+};
+
+/// A captured frame of an output.
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    /// The name of the captured output.
+    pub output: String,
+    pub width: i32,
+    pub height: i32,
+    /// The number of bytes in each row.
+    pub stride: i32,
+    /// Pixel data in RGBA order.
+    pub data: Arc<[u8]>,
+}
+
+#[derive(Debug)]
+pub struct ScreencopyManagerState {
+    manager: ZwlrScreencopyManagerV1,
+}
+
+impl ScreencopyManagerState {
+    pub fn bind<State>(globals: &GlobalList, qh: &QueueHandle<State>) -> Result<Self, BindError>
+    where
+        State: Dispatch<ZwlrScreencopyManagerV1, GlobalData> + 'static,
+    {
+        let manager = globals.bind(qh, 1..=1, GlobalData)?;
+        debug!("Bound to ZwlrScreencopyManagerV1 global");
+        Ok(Self { manager })
+    }
+}
+
+/// User data attached to each frame,
+/// holding the buffer being copied into.
+#[derive(Debug)]
+pub struct FrameData {
+    output: String,
+    buffer: Mutex<Option<FrameBuffer>>,
+}
+
+#[derive(Debug)]
+struct FrameBuffer {
+    pool: RawPool,
+    buffer: WlBuffer,
+    format: Format,
+    width: i32,
+    height: i32,
+    stride: i32,
+}
+
+impl Client {
+    /// Requests a capture of the output with the given name.
+    /// The result is sent to [`Client::subscribe_screencopy`] subscribers once ready.
+    pub fn capture_output(&self, output: &str) {
+        match self.send_request(Request::CaptureOutput(output.to_string())) {
+            Response::Ok => (),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Subscribes to captured output frames.
+    pub fn subscribe_screencopy(&self) -> broadcast::Receiver<Screenshot> {
+        self.screencopy_channel.0.subscribe()
+    }
+}
+
+impl Environment {
+    pub(super) fn capture_output(&mut self, name: &str) {
+        let Some(state) = &self.screencopy_manager_state else {
+            warn!("Compositor does not support zwlr_screencopy_manager_v1, outputs cannot be captured");
+            return;
+        };
+
+        let output = self.output_state.outputs().find(|output| {
+            self.output_state
+                .info(output)
+                .is_some_and(|info| info.name.as_deref() == Some(name))
+        });
+
+        let Some(output) = output else {
+            warn!("Failed to find output '{name}' to capture");
+            return;
+        };
+
+        debug!("Capturing output '{name}'");
+
+        state.manager.capture_output(
+            0,
+            &output,
+            &self.queue_handle,
+            FrameData {
+                output: name.to_string(),
+                buffer: Mutex::new(None),
+            },
+        );
+    }
+}
+
+impl ShmHandler for Environment {
+    fn shm_state(&mut self) -> &mut Shm {
+        &mut self.shm
+    }
+}
+
+impl Dispatch<ZwlrScreencopyManagerV1, GlobalData> for Environment {
+    fn event(
+        _state: &mut Self,
+        _manager: &ZwlrScreencopyManagerV1,
+        _event: <ZwlrScreencopyManagerV1 as wayland_client::Proxy>::Event,
+        _data: &GlobalData,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // the manager has no events
+    }
+}
+
+impl Dispatch<WlBuffer, ()> for Environment {
+    fn event(
+        _state: &mut Self,
+        _buffer: &WlBuffer,
+        _event: wl_buffer::Event,
+        _data: &(),
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+    ) {
+        // buffers are destroyed as soon as the copy completes,
+        // so there is no need to track releases
+    }
+}
+
+impl Dispatch<ZwlrScreencopyFrameV1, FrameData> for Environment {
+    fn event(
+        state: &mut Self,
+        frame: &ZwlrScreencopyFrameV1,
+        event: zwlr_screencopy_frame_v1::Event,
+        data: &FrameData,
+        _conn: &Connection,
+        qh: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwlr_screencopy_frame_v1::Event::Buffer {
+                format: WEnum::Value(format @ (Format::Argb8888 | Format::Xrgb8888)),
+                width,
+                height,
+                stride,
+            } => {
+                let (width, height, stride) = (width as i32, height as i32, stride as i32);
+
+                let mut pool = match RawPool::new((stride * height) as usize, &state.shm) {
+                    Ok(pool) => pool,
+                    Err(err) => {
+                        error!("Failed to create screencopy buffer: {err:?}");
+                        frame.destroy();
+                        return;
+                    }
+                };
+
+                let buffer = pool.create_buffer(0, width, height, stride, format, (), qh);
+                frame.copy(&buffer);
+
+                lock!(data.buffer).replace(FrameBuffer {
+                    pool,
+                    buffer,
+                    format,
+                    width,
+                    height,
+                    stride,
+                });
+            }
+            zwlr_screencopy_frame_v1::Event::Buffer { format, .. } => {
+                warn!("Unsupported screencopy buffer format: {format:?}");
+                frame.destroy();
+            }
+            zwlr_screencopy_frame_v1::Event::Ready { .. } => {
+                frame.destroy();
+
+                let Some(mut frame_buffer) = lock!(data.buffer).take() else {
+                    return;
+                };
+
+                let data_rgba = to_rgba(frame_buffer.pool.mmap(), frame_buffer.format);
+                frame_buffer.buffer.destroy();
+
+                try_send!(
+                    state.event_tx,
+                    Event::Screenshot(Screenshot {
+                        output: data.output.clone(),
+                        width: frame_buffer.width,
+                        height: frame_buffer.height,
+                        stride: frame_buffer.stride,
+                        data: data_rgba.into(),
+                    })
+                );
+            }
+            zwlr_screencopy_frame_v1::Event::Failed => {
+                debug!("Failed to capture output '{}'", data.output);
+                frame.destroy();
+
+                if let Some(frame_buffer) = lock!(data.buffer).take() {
+                    frame_buffer.buffer.destroy();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Converts little-endian ARGB/XRGB pixels,
+/// which are stored as BGRA in memory, to RGBA.
+fn to_rgba(data: &[u8], format: Format) -> Vec<u8> {
+    data.chunks_exact(4)
+        .flat_map(|pixel| {
+            let alpha = if format == Format::Xrgb8888 {
+                u8::MAX
+            } else {
+                pixel[3]
+            };
+
+            [pixel[2], pixel[1], pixel[0], alpha]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_rgba() {
+        let data = [1, 2, 3, 4, 5, 6, 7, 8];
+
+        assert_eq!(to_rgba(&data, Format::Argb8888), [3, 2, 1, 4, 7, 6, 5, 8]);
+        assert_eq!(
+            to_rgba(&data, Format::Xrgb8888),
+            [3, 2, 1, 255, 7, 6, 5, 255]
+        );
+    }
+}
@@ -36,6 +36,36 @@ pub struct CommonConfig {
     /// **Default**: `null`
     pub class: Option<String>,
 
+    /// Sets a list of CSS classes,
+    /// allowing you to target it in CSS using `.class`.
+    ///
+    /// These are added alongside any classes set using [class](#class).
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { classes = [ "accent" "pill" ] }
+    /// ```
+    #[serde(default)]
+    pub classes: Vec<String>,
+
+    /// Sets inline CSS declarations to apply to the widget,
+    /// allowing for one-off styling without a unique name.
+    ///
+    /// These take precedence over the stylesheet,
+    /// but only apply to the module's top-level widget.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { style = "color: red; padding: 0 10px;" }
+    /// ```
+    pub style: Option<String>,
+
     /// Shows this text on hover.
     /// Supports embedding scripts between `{{double braces}}`.
     ///
@@ -157,6 +187,51 @@ pub struct CommonConfig {
     /// Prevents the popup from opening on-click for this widget.
     #[serde(default)]
     pub disable_popup: bool,
+
+    /// The size of the gap in pixels
+    /// between the bar and this widget's popup.
+    /// Overrides the bar-level `popup_gap` option.
+    ///
+    /// **Default**: `null`
+    pub popup_gap: Option<i32>,
+
+    /// The number of pixels to move this widget's popup horizontally.
+    /// Positive values move it right.
+    ///
+    /// This is applied after the popup is positioned,
+    /// and can be used to nudge popups away from the screen edges.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    pub popup_offset_x: i32,
+
+    /// The number of pixels to move this widget's popup vertically.
+    /// Positive values move it down.
+    ///
+    /// This is applied after the popup is positioned,
+    /// and can be used to nudge popups away from the screen edges.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    pub popup_offset_y: i32,
+
+    /// Whether to show a button in the corner of this widget's popup,
+    /// which pins the popup open.
+    ///
+    /// A pinned popup stays open when the pointer leaves it,
+    /// and when popups on other bars are opened.
+    /// It is closed by clicking the widget again, or by unpinning it.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    pub popup_pin_button: bool,
+
+    /// Whether this widget's popup is pinned as soon as it opens.
+    /// See `popup_pin_button` for how pinned popups behave.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    pub popup_pinned: bool,
 }
 
 #[derive(Debug, Deserialize, Clone)]
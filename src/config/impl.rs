@@ -1,5 +1,5 @@
-use super::{BarConfig, BarPosition, MonitorConfig};
-use color_eyre::{Help, Report};
+use super::{BarConfig, BarPosition, Config, MonitorConfig};
+use color_eyre::{Help, Report, Result};
 use gtk::Orientation;
 use serde::{Deserialize, Deserializer};
 
@@ -87,3 +87,35 @@ impl BarPosition {
         }
     }
 }
+
+impl Config {
+    /// Replaces the top-level bar and monitor configs
+    /// with those from the profile with the given name.
+    pub fn apply_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self
+            .profiles
+            .as_ref()
+            .and_then(|profiles| profiles.get(name))
+            .cloned()
+            .ok_or_else(|| Report::msg(format!("Profile '{name}' not found")))?;
+
+        self.bar = profile.bar;
+        self.monitors = profile.monitors;
+
+        Ok(())
+    }
+
+    /// Gets the names of all configured profiles, sorted alphabetically.
+    #[cfg(feature = "ipc")]
+    pub fn profile_names(&self) -> Vec<&str> {
+        let mut names = self
+            .profiles
+            .iter()
+            .flat_map(|profiles| profiles.keys())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+
+        names.sort_unstable();
+        names
+    }
+}
@@ -2,37 +2,85 @@ mod common;
 mod r#impl;
 mod truncate;
 
+#[cfg(feature = "bluetooth_battery")]
+use crate::modules::bluetooth_battery::BluetoothBatteryModule;
+#[cfg(feature = "break_reminder")]
+use crate::modules::break_reminder::BreakReminderModule;
 #[cfg(feature = "cairo")]
 use crate::modules::cairo::CairoModule;
 #[cfg(feature = "clipboard")]
 use crate::modules::clipboard::ClipboardModule;
 #[cfg(feature = "clock")]
 use crate::modules::clock::ClockModule;
+#[cfg(feature = "cpu_governor")]
+use crate::modules::cpu_governor::CpuGovernorModule;
 use crate::modules::custom::CustomModule;
+#[cfg(feature = "dictation")]
+use crate::modules::dictation::DictationModule;
+#[cfg(feature = "disk_health")]
+use crate::modules::disk_health::DiskHealthModule;
+#[cfg(feature = "displays")]
+use crate::modules::displays::DisplaysModule;
+#[cfg(feature = "easyeffects")]
+use crate::modules::easyeffects::EasyEffectsModule;
+#[cfg(feature = "emoji")]
+use crate::modules::emoji::EmojiModule;
+#[cfg(feature = "firewall")]
+use crate::modules::firewall::FirewallModule;
 #[cfg(feature = "focused")]
 use crate::modules::focused::FocusedModule;
+#[cfg(feature = "homeassistant")]
+use crate::modules::homeassistant::HomeAssistantModule;
+#[cfg(feature = "job")]
+use crate::modules::job::JobModule;
+#[cfg(feature = "keyboard")]
+use crate::modules::keyboard::KeyboardModule;
 use crate::modules::label::LabelModule;
 #[cfg(feature = "launcher")]
 use crate::modules::launcher::LauncherModule;
+#[cfg(feature = "mic")]
+use crate::modules::mic::MicModule;
+#[cfg(feature = "mqtt")]
+use crate::modules::mqtt::MqttModule;
 #[cfg(feature = "music")]
 use crate::modules::music::MusicModule;
 #[cfg(feature = "networkmanager")]
 use crate::modules::networkmanager::NetworkManagerModule;
+#[cfg(feature = "next_event")]
+use crate::modules::next_event::NextEventModule;
 #[cfg(feature = "notifications")]
 use crate::modules::notifications::NotificationsModule;
+#[cfg(feature = "nvme_health")]
+use crate::modules::nvme_health::NvmeHealthModule;
+#[cfg(feature = "reboot")]
+use crate::modules::reboot::RebootModule;
+#[cfg(feature = "run")]
+use crate::modules::run::RunModule;
 use crate::modules::script::ScriptModule;
+#[cfg(feature = "sessions")]
+use crate::modules::sessions::SessionsModule;
 #[cfg(feature = "sys_info")]
 use crate::modules::sysinfo::SysInfoModule;
 #[cfg(feature = "tray")]
 use crate::modules::tray::TrayModule;
 #[cfg(feature = "upower")]
 use crate::modules::upower::UpowerModule;
+#[cfg(feature = "usb")]
+use crate::modules::usb::UsbModule;
 #[cfg(feature = "volume")]
 use crate::modules::volume::VolumeModule;
+#[cfg(feature = "weather_alerts")]
+use crate::modules::weather_alerts::WeatherAlertsModule;
+#[cfg(feature = "wine")]
+use crate::modules::wine::WineModule;
 #[cfg(feature = "workspaces")]
 use crate::modules::workspaces::WorkspacesModule;
+#[cfg(feature = "worktime")]
+use crate::modules::worktime::WorktimeModule;
 
+use crate::dynamic_value::DynamicBool;
 use crate::modules::{AnyModuleFactory, ModuleFactory, ModuleInfo};
+use crate::script::ScriptInput;
 use cfg_if::cfg_if;
 use color_eyre::Result;
 use serde::Deserialize;
@@ -48,35 +96,83 @@ pub use self::truncate::TruncateMode;
 #[serde(tag = "type", rename_all = "snake_case")]
 #[cfg_attr(feature = "schema", derive(JsonSchema))]
 pub enum ModuleConfig {
+    #[cfg(feature = "bluetooth_battery")]
+    BluetoothBattery(Box<BluetoothBatteryModule>),
+    #[cfg(feature = "break_reminder")]
+    BreakReminder(Box<BreakReminderModule>),
     #[cfg(feature = "cairo")]
     Cairo(Box<CairoModule>),
     #[cfg(feature = "clipboard")]
     Clipboard(Box<ClipboardModule>),
     #[cfg(feature = "clock")]
     Clock(Box<ClockModule>),
+    #[cfg(feature = "cpu_governor")]
+    CpuGovernor(Box<CpuGovernorModule>),
     Custom(Box<CustomModule>),
+    #[cfg(feature = "dictation")]
+    Dictation(Box<DictationModule>),
+    #[cfg(feature = "disk_health")]
+    DiskHealth(Box<DiskHealthModule>),
+    #[cfg(feature = "displays")]
+    Displays(Box<DisplaysModule>),
+    #[cfg(feature = "easyeffects")]
+    #[serde(rename = "easyeffects")]
+    EasyEffects(Box<EasyEffectsModule>),
+    #[cfg(feature = "emoji")]
+    Emoji(Box<EmojiModule>),
+    #[cfg(feature = "firewall")]
+    Firewall(Box<FirewallModule>),
     #[cfg(feature = "focused")]
     Focused(Box<FocusedModule>),
+    #[cfg(feature = "homeassistant")]
+    #[serde(rename = "homeassistant")]
+    HomeAssistant(Box<HomeAssistantModule>),
+    #[cfg(feature = "job")]
+    Job(Box<JobModule>),
+    #[cfg(feature = "keyboard")]
+    Keyboard(Box<KeyboardModule>),
     Label(Box<LabelModule>),
     #[cfg(feature = "launcher")]
     Launcher(Box<LauncherModule>),
+    #[cfg(feature = "mic")]
+    Mic(Box<MicModule>),
+    #[cfg(feature = "mqtt")]
+    Mqtt(Box<MqttModule>),
     #[cfg(feature = "music")]
     Music(Box<MusicModule>),
     #[cfg(feature = "networkmanager")]
     NetworkManager(Box<NetworkManagerModule>),
+    #[cfg(feature = "next_event")]
+    NextEvent(Box<NextEventModule>),
     #[cfg(feature = "notifications")]
     Notifications(Box<NotificationsModule>),
+    #[cfg(feature = "nvme_health")]
+    NvmeHealth(Box<NvmeHealthModule>),
+    #[cfg(feature = "reboot")]
+    Reboot(Box<RebootModule>),
+    #[cfg(feature = "run")]
+    Run(Box<RunModule>),
     Script(Box<ScriptModule>),
+    #[cfg(feature = "sessions")]
+    Sessions(Box<SessionsModule>),
     #[cfg(feature = "sys_info")]
     SysInfo(Box<SysInfoModule>),
     #[cfg(feature = "tray")]
     Tray(Box<TrayModule>),
     #[cfg(feature = "upower")]
     Upower(Box<UpowerModule>),
+    #[cfg(feature = "usb")]
+    Usb(Box<UsbModule>),
     #[cfg(feature = "volume")]
     Volume(Box<VolumeModule>),
+    #[cfg(feature = "weather_alerts")]
+    WeatherAlerts(Box<WeatherAlertsModule>),
+    #[cfg(feature = "wine")]
+    Wine(Box<WineModule>),
     #[cfg(feature = "workspaces")]
     Workspaces(Box<WorkspacesModule>),
+    #[cfg(feature = "worktime")]
+    Worktime(Box<WorktimeModule>),
 }
 
 impl ModuleConfig {
@@ -93,35 +189,81 @@ impl ModuleConfig {
         }
 
         match self {
+            #[cfg(feature = "bluetooth_battery")]
+            Self::BluetoothBattery(module) => create!(module),
+            #[cfg(feature = "break_reminder")]
+            Self::BreakReminder(module) => create!(module),
             #[cfg(feature = "cairo")]
             Self::Cairo(module) => create!(module),
             #[cfg(feature = "clipboard")]
             Self::Clipboard(module) => create!(module),
             #[cfg(feature = "clock")]
             Self::Clock(module) => create!(module),
+            #[cfg(feature = "cpu_governor")]
+            Self::CpuGovernor(module) => create!(module),
             Self::Custom(module) => create!(module),
+            #[cfg(feature = "dictation")]
+            Self::Dictation(module) => create!(module),
+            #[cfg(feature = "disk_health")]
+            Self::DiskHealth(module) => create!(module),
+            #[cfg(feature = "displays")]
+            Self::Displays(module) => create!(module),
+            #[cfg(feature = "easyeffects")]
+            Self::EasyEffects(module) => create!(module),
+            #[cfg(feature = "emoji")]
+            Self::Emoji(module) => create!(module),
+            #[cfg(feature = "firewall")]
+            Self::Firewall(module) => create!(module),
             #[cfg(feature = "focused")]
             Self::Focused(module) => create!(module),
+            #[cfg(feature = "homeassistant")]
+            Self::HomeAssistant(module) => create!(module),
+            #[cfg(feature = "job")]
+            Self::Job(module) => create!(module),
+            #[cfg(feature = "keyboard")]
+            Self::Keyboard(module) => create!(module),
             Self::Label(module) => create!(module),
             #[cfg(feature = "launcher")]
             Self::Launcher(module) => create!(module),
+            #[cfg(feature = "mic")]
+            Self::Mic(module) => create!(module),
+            #[cfg(feature = "mqtt")]
+            Self::Mqtt(module) => create!(module),
             #[cfg(feature = "music")]
             Self::Music(module) => create!(module),
             #[cfg(feature = "networkmanager")]
             Self::NetworkManager(module) => create!(module),
+            #[cfg(feature = "next_event")]
+            Self::NextEvent(module) => create!(module),
             #[cfg(feature = "notifications")]
             Self::Notifications(module) => create!(module),
+            #[cfg(feature = "nvme_health")]
+            Self::NvmeHealth(module) => create!(module),
+            #[cfg(feature = "reboot")]
+            Self::Reboot(module) => create!(module),
+            #[cfg(feature = "run")]
+            Self::Run(module) => create!(module),
             Self::Script(module) => create!(module),
+            #[cfg(feature = "sessions")]
+            Self::Sessions(module) => create!(module),
             #[cfg(feature = "sys_info")]
             Self::SysInfo(module) => create!(module),
             #[cfg(feature = "tray")]
             Self::Tray(module) => create!(module),
             #[cfg(feature = "upower")]
             Self::Upower(module) => create!(module),
+            #[cfg(feature = "usb")]
+            Self::Usb(module) => create!(module),
             #[cfg(feature = "volume")]
             Self::Volume(module) => create!(module),
+            #[cfg(feature = "weather_alerts")]
+            Self::WeatherAlerts(module) => create!(module),
+            #[cfg(feature = "wine")]
+            Self::Wine(module) => create!(module),
             #[cfg(feature = "workspaces")]
             Self::Workspaces(module) => create!(module),
+            #[cfg(feature = "worktime")]
+            Self::Worktime(module) => create!(module),
         }
     }
 }
@@ -162,6 +304,158 @@ pub struct MarginConfig {
     pub top: i32,
 }
 
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum SeparatorConfig {
+    /// Whether to show a line.
+    Line(bool),
+    /// Text to show as a label.
+    Label(String),
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ShadowConfig {
+    /// The effect to draw.
+    ///
+    /// `shadow` fades out from the inner edge of the bar.
+    /// `scrim` fades out from the screen edge, behind the bar.
+    ///
+    /// **Valid options**: `shadow`, `scrim`
+    /// <br>
+    /// **Default**: `shadow`
+    #[serde(default)]
+    pub style: ShadowStyle,
+
+    /// The size in pixels of the extra area
+    /// drawn past the inner edge of the bar.
+    ///
+    /// **Default**: `16`
+    #[serde(default = "default_shadow_size")]
+    pub size: i32,
+
+    /// The color at the most opaque point of the effect,
+    /// as a CSS color string.
+    ///
+    /// **Default**: `rgba(0, 0, 0, 0.4)`
+    #[serde(default = "default_shadow_color")]
+    pub color: String,
+}
+
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum ShadowStyle {
+    #[default]
+    Shadow,
+    Scrim,
+}
+
+/// An invisible zone at a corner or edge of the screen,
+/// which runs an action when the pointer touches it.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct HotCornerConfig {
+    /// The corner or edge of the screen to place the zone at.
+    ///
+    /// **Valid options**: `top_left`, `top_right`, `bottom_left`, `bottom_right`,
+    /// `top`, `bottom`, `left`, `right`
+    /// <br>
+    /// **Required**
+    pub position: HotCornerPosition,
+
+    /// The size of the zone in pixels.
+    /// For edges, this is the thickness of the zone, which spans the whole edge.
+    ///
+    /// **Default**: `2`
+    #[serde(default = "default_hot_corner_size")]
+    pub size: i32,
+
+    /// The duration in milliseconds the pointer must stay in the zone
+    /// before the action runs.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    pub delay: u64,
+
+    /// The action to run when the pointer enters the zone.
+    #[serde(flatten)]
+    pub action: HotCornerAction,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum HotCornerPosition {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum HotCornerAction {
+    /// Shows the bar, if it is hidden.
+    ShowBar,
+    /// Toggles the visibility of the bar.
+    ToggleBar,
+    /// Opens the popup for a widget on the bar.
+    ShowPopup {
+        /// The configured name of the widget.
+        widget: String,
+    },
+    /// Runs a script.
+    Command {
+        /// The script to run.
+        command: ScriptInput,
+    },
+}
+
+/// Adds a CSS class to the bar while a condition is met.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct RuleConfig {
+    /// The CSS class to add to the bar.
+    ///
+    /// **Required**
+    pub class: String,
+
+    /// The condition under which the class is added.
+    #[serde(flatten)]
+    pub condition: RuleCondition,
+
+    /// Whether to add the class while the condition is *not* met instead.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    pub invert: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "when", rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub enum RuleCondition {
+    /// While the system is running on battery power.
+    /// Requires the `upower` feature.
+    OnBattery,
+    /// While there is no wired, wifi or cellular connection.
+    /// Requires the `networkmanager` feature.
+    Offline,
+    /// Between two times of the day, in `HH:MM` format.
+    /// The range can cross midnight, ie `20:00` to `07:00`.
+    /// Requires the `schedule` feature.
+    Time { from: String, to: String },
+    /// While a script exits successfully, or an ironvar is truthy.
+    Dynamic { value: DynamicBool },
+}
+
 /// The following is a list of all top-level bar config options.
 ///
 /// These options can either be written at the very top object of your config,
@@ -202,6 +496,23 @@ pub struct BarConfig {
     #[serde(default = "default_bar_height")]
     pub height: i32,
 
+    /// The size to render icons at, in pixels,
+    /// for modules which do not set their own `icon_size`.
+    /// Leave unset to use each module's default.
+    ///
+    /// **Default**: `null`
+    pub icon_size: Option<i32>,
+
+    /// A factor to scale the bar and its popups by.
+    /// This applies to the bar's height, icon sizes and text size.
+    ///
+    /// Useful for making the bar larger on a TV or other distant display,
+    /// without changing each module's config.
+    ///
+    /// **Default**: `1.0`
+    #[serde(default = "default_scale")]
+    pub scale: f64,
+
     /// The margin to use on each side of the bar, in pixels.
     /// Object which takes `top`, `bottom`, `left` and `right` keys.
     ///
@@ -222,6 +533,50 @@ pub struct BarConfig {
     #[serde(default)]
     pub margin: MarginConfig,
 
+    /// The space in pixels between each module,
+    /// within the start, center and end groups.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    pub spacing: i32,
+
+    /// The space in pixels between the ends of the bar and its start and end modules.
+    /// Unlike `margin`, this is inside the bar, so is covered by its background.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    pub padding: i32,
+
+    /// A separator to place between each module,
+    /// within the start, center and end groups.
+    /// Use `true` for a line, or a string to show as a label, such as `|`.
+    ///
+    /// Separators next to hidden modules are hidden too.
+    ///
+    /// **Default**: `null`
+    pub separator: Option<SeparatorConfig>,
+
+    /// A drop shadow or gradient scrim to draw behind the bar,
+    /// so that it remains readable over busy wallpapers.
+    /// Object which takes `style`, `size` and `color` keys.
+    ///
+    /// The effect is painted by the bar window itself,
+    /// so does not rely on compositor support.
+    /// It does not take any input, or count towards the exclusive zone.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// {
+    ///     shadow.style = "scrim"
+    ///     shadow.size = 24
+    ///     shadow.color = "rgba(0, 0, 0, 0.6)"
+    /// }
+    /// ```
+    pub shadow: Option<ShadowConfig>,
+
     /// The layer-shell layer to place the bar on.
     ///
     /// Taken from the
@@ -259,6 +614,16 @@ pub struct BarConfig {
     #[serde(default = "default_popup_gap")]
     pub popup_gap: i32,
 
+    /// Whether opening a menu on this bar, such as a tray item's menu,
+    /// closes the bar's open popup.
+    ///
+    /// Module popups on the same bar always replace each other,
+    /// so with this set only one popup is open on the bar at a time.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    pub close_other_popups: bool,
+
     /// Whether the bar should be hidden when Ironbar starts.
     ///
     /// **Default**: `false`, unless `autohide` is set.
@@ -272,12 +637,66 @@ pub struct BarConfig {
     #[serde(default)]
     pub autohide: Option<u64>,
 
+    /// Invisible zones at the corners or edges of the screen,
+    /// which run an action when the pointer touches them.
+    ///
+    /// See [hot corners](#hot-corners).
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// {
+    ///     hot_corners = [
+    ///         { position = "bottom" action = "show_bar" }
+    ///         { position = "top_left" delay = 250 action = "show_popup" widget = "launcher" }
+    ///         { position = "top_right" action = "command" command = "swaync-client -t" }
+    ///     ]
+    /// }
+    /// ```
+    #[serde(default)]
+    pub hot_corners: Vec<HotCornerConfig>,
+
+    /// Rules which add CSS classes to the bar while system conditions are met,
+    /// so that the whole bar can restyle itself without external scripts.
+    ///
+    /// See [rules](#rules).
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// {
+    ///     rules = [
+    ///         { class = "on-battery" when = "on_battery" }
+    ///         { class = "offline" when = "offline" }
+    ///         { class = "night" when = "time" from = "20:00" to = "07:00" }
+    ///         { class = "recording" when = "dynamic" value = "#recording" }
+    ///     ]
+    /// }
+    /// ```
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+
     /// The name of the GTK icon theme to use.
     /// Leave unset to use the default Adwaita theme.
     ///
     /// **Default**: `null`
     pub icon_theme: Option<String>,
 
+    /// The path to an additional stylesheet for this bar.
+    /// Relative paths are resolved from the config directory.
+    ///
+    /// Rules in the stylesheet only apply to this bar and its popups,
+    /// so there is no need to prefix selectors with the bar's name.
+    /// The stylesheet is loaded on top of the main stylesheet,
+    /// and is live-reloaded in the same way.
+    ///
+    /// **Default**: `null`
+    pub stylesheet: Option<String>,
+
     /// An array of modules to append to the start of the bar.
     /// Depending on the orientation, this is either the top of the left edge.
     ///
@@ -319,13 +738,22 @@ impl Default for BarConfig {
         Self {
             position: BarPosition::default(),
             margin: MarginConfig::default(),
+            spacing: 0,
+            padding: 0,
+            separator: None,
+            shadow: None,
             name: None,
             layer: default_layer(),
             exclusive_zone: None,
             height: default_bar_height(),
+            icon_size: None,
+            scale: default_scale(),
             start_hidden: None,
             autohide: None,
+            hot_corners: vec![],
+            rules: vec![],
             icon_theme: None,
+            stylesheet: None,
             start: Some(vec![ModuleConfig::Label(
                 LabelModule::new("ℹ️ Using default config".to_string()).into(),
             )]),
@@ -333,6 +761,7 @@ impl Default for BarConfig {
             end,
             anchor_to_edges: default_true(),
             popup_gap: default_popup_gap(),
+            close_other_popups: false,
         }
     }
 }
@@ -376,6 +805,42 @@ pub struct Config {
     ///
     /// Providing this option overrides the single, global `bar` option.
     pub monitors: Option<HashMap<String, MonitorConfig>>,
+
+    /// A map of profile names to alternative configs.
+    ///
+    /// Each profile takes the same bar-level options and `monitors` map as the top-level config.
+    /// When a profile is active, it entirely replaces the top-level bar and monitor configs.
+    ///
+    /// Profiles can be selected at launch using `--profile` or the `IRONBAR_PROFILE` environment variable,
+    /// and switched at runtime [over IPC](controlling-ironbar#profile).
+    ///
+    /// **Default**: `{}`
+    pub profiles: Option<HashMap<String, ProfileConfig>>,
+
+    /// Global keyboard shortcuts to register with the compositor
+    /// through the desktop portal.
+    ///
+    /// Requires the `shortcuts` feature,
+    /// and a portal implementation supporting the `GlobalShortcuts` interface.
+    ///
+    /// **Default**: `[]`
+    #[cfg(feature = "shortcuts")]
+    pub shortcuts: Option<Vec<crate::shortcuts::ShortcutConfig>>,
+}
+
+/// An alternative bar config,
+/// which replaces the top-level bar and monitor configs while active.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(JsonSchema))]
+pub struct ProfileConfig {
+    /// The configuration for the bar.
+    /// Setting through this will enable a single identical bar on each monitor.
+    #[serde(flatten)]
+    pub bar: BarConfig,
+
+    /// A map of monitor names to configs.
+    /// Providing this option overrides the single, global `bar` option.
+    pub monitors: Option<HashMap<String, MonitorConfig>>,
 }
 
 const fn default_layer() -> gtk_layer_shell::Layer {
@@ -386,6 +851,22 @@ const fn default_bar_height() -> i32 {
     42
 }
 
+const fn default_shadow_size() -> i32 {
+    16
+}
+
+fn default_shadow_color() -> String {
+    String::from("rgba(0, 0, 0, 0.4)")
+}
+
+const fn default_hot_corner_size() -> i32 {
+    2
+}
+
+const fn default_scale() -> f64 {
+    1.0
+}
+
 const fn default_popup_gap() -> i32 {
     5
 }
@@ -18,7 +18,7 @@ fn desktop_files() -> &'static Mutex<HashMap<PathBuf, DesktopFile>> {
 fn desktop_files_look_out_keys() -> &'static HashSet<&'static str> {
     static DESKTOP_FILES_LOOK_OUT_KEYS: OnceLock<HashSet<&'static str>> = OnceLock::new();
     DESKTOP_FILES_LOOK_OUT_KEYS
-        .get_or_init(|| HashSet::from(["Name", "StartupWMClass", "Exec", "Icon"]))
+        .get_or_init(|| HashSet::from(["Name", "StartupWMClass", "Exec", "Icon", "NoDisplay"]))
 }
 
 /// Finds directories that should contain `.desktop` files
@@ -61,6 +61,65 @@ fn find_desktop_files() -> Vec<PathBuf> {
         .collect()
 }
 
+/// An application which can be launched,
+/// as read from its `.desktop` file.
+#[derive(Debug, Clone)]
+pub struct DesktopEntry {
+    pub file: PathBuf,
+    pub name: String,
+    pub icon: Option<String>,
+}
+
+/// Gets all applications which should be shown in menus.
+///
+/// Where the same file exists in multiple directories,
+/// the user's version takes precedence.
+pub fn find_applications() -> Vec<DesktopEntry> {
+    let mut entries = HashMap::new();
+
+    // later directories take precedence,
+    // so a later file with the same name replaces the earlier one.
+    for file in find_desktop_files() {
+        let Some(desktop_file) = parse_desktop_file(&file) else {
+            continue;
+        };
+
+        let Some(file_name) = file.file_name().map(ToOwned::to_owned) else {
+            continue;
+        };
+
+        let no_display = desktop_file
+            .get("NoDisplay")
+            .and_then(|values| values.first())
+            .is_some_and(|value| value == "true");
+
+        let name = desktop_file.get("Name").and_then(|names| names.first());
+
+        match name {
+            Some(name) if !no_display => {
+                let icon = desktop_file
+                    .get("Icon")
+                    .and_then(|icons| icons.first())
+                    .cloned();
+
+                entries.insert(
+                    file_name,
+                    DesktopEntry {
+                        name: name.clone(),
+                        icon,
+                        file,
+                    },
+                );
+            }
+            _ => {
+                entries.remove(&file_name);
+            }
+        }
+    }
+
+    entries.into_values().collect()
+}
+
 /// Attempts to locate a `.desktop` file for an app id
 pub fn find_desktop_file(app_id: &str) -> Option<PathBuf> {
     // this is necessary to invalidate the cache
@@ -1,3 +1,5 @@
+#[cfg(feature = "schedule")]
+use crate::dynamic_value::schedule::Schedule;
 use crate::script::Script;
 use crate::{glib_recv_mpsc, spawn, try_send};
 #[cfg(feature = "ipc")]
@@ -13,6 +15,9 @@ pub enum DynamicBool {
     /// Either a script or variable, to be determined.
     Unknown(String),
     Script(Script),
+    /// True during a time window.
+    #[cfg(feature = "schedule")]
+    Schedule(Schedule),
     #[cfg(feature = "ipc")]
     Variable(Box<str>),
 }
@@ -53,6 +58,8 @@ impl DynamicBool {
                         })
                         .await;
                 }
+                #[cfg(feature = "schedule")]
+                DynamicBool::Schedule(schedule) => schedule.watch(tx).await,
                 #[cfg(feature = "ipc")]
                 DynamicBool::Variable(variable) => {
                     let variable_manager = Ironbar::variable_manager();
@@ -2,6 +2,8 @@
 
 mod dynamic_bool;
 mod dynamic_string;
+#[cfg(feature = "schedule")]
+pub mod schedule;
 
 pub use dynamic_bool::DynamicBool;
 pub use dynamic_string::dynamic_string;
@@ -0,0 +1,205 @@
+use crate::send_async;
+use chrono::{Datelike, Local, NaiveDateTime, NaiveTime, Timelike, Weekday};
+use color_eyre::Result;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::error;
+
+/// The longest time to wait before re-checking time conditions,
+/// in case the clock changes or the system is suspended.
+pub const MAX_TIME_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A time window on certain days of the week.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Schedule {
+    /// The days on which the window applies.
+    /// If empty, the window applies every day.
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    days: Vec<Day>,
+
+    /// The start of the window, in `HH:MM` format.
+    from: String,
+
+    /// The end of the window, in `HH:MM` format.
+    /// If this is before `from`, the window crosses midnight
+    /// and counts as part of the day it starts on.
+    to: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Day {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+    /// Monday to Friday.
+    Weekdays,
+    /// Saturday and Sunday.
+    Weekends,
+}
+
+impl Day {
+    fn matches(self, weekday: Weekday) -> bool {
+        matches!(
+            (self, weekday),
+            (Self::Mon, Weekday::Mon)
+                | (Self::Tue, Weekday::Tue)
+                | (Self::Wed, Weekday::Wed)
+                | (Self::Thu, Weekday::Thu)
+                | (Self::Fri, Weekday::Fri)
+                | (Self::Sat, Weekday::Sat)
+                | (Self::Sun, Weekday::Sun)
+                | (
+                    Self::Weekdays,
+                    Weekday::Mon | Weekday::Tue | Weekday::Wed | Weekday::Thu | Weekday::Fri
+                )
+                | (Self::Weekends, Weekday::Sat | Weekday::Sun)
+        )
+    }
+}
+
+impl Schedule {
+    /// Sends whether the current time is inside the window,
+    /// each time it enters or leaves it.
+    pub async fn watch(self, tx: mpsc::Sender<bool>) {
+        let (from, to) = match (parse_time(&self.from), parse_time(&self.to)) {
+            (Ok(from), Ok(to)) => (from, to),
+            (Err(err), _) | (_, Err(err)) => {
+                error!("Invalid schedule time: {err}");
+                return;
+            }
+        };
+
+        let mut previous = None;
+
+        loop {
+            let now = Local::now().naive_local();
+
+            let active = is_active(&self.days, now, from, to);
+            if previous != Some(active) {
+                previous = Some(active);
+                send_async!(tx, active);
+            }
+
+            let wait = until_next_change(now.time(), from, to);
+            sleep(wait.min(MAX_TIME_CHECK_INTERVAL)).await;
+        }
+    }
+}
+
+pub fn parse_time(time: &str) -> Result<NaiveTime> {
+    Ok(NaiveTime::parse_from_str(time, "%H:%M")?)
+}
+
+/// Whether `now` falls between `from` and `to`.
+/// If `to` is before `from`, the range crosses midnight.
+pub fn in_range(now: NaiveTime, from: NaiveTime, to: NaiveTime) -> bool {
+    if from <= to {
+        now >= from && now < to
+    } else {
+        now >= from || now < to
+    }
+}
+
+/// Gets the time until `now` next reaches either end of the range.
+pub fn until_next_change(now: NaiveTime, from: NaiveTime, to: NaiveTime) -> Duration {
+    const DAY: u32 = 24 * 60 * 60;
+
+    let now = now.num_seconds_from_midnight();
+
+    let until = |time: NaiveTime| {
+        let time = time.num_seconds_from_midnight();
+        match (time + DAY - now) % DAY {
+            0 => DAY,
+            seconds => seconds,
+        }
+    };
+
+    Duration::from_secs(u64::from(until(from).min(until(to))))
+}
+
+/// Whether `now` falls inside the window on one of `days`.
+fn is_active(days: &[Day], now: NaiveDateTime, from: NaiveTime, to: NaiveTime) -> bool {
+    let time = now.time();
+    if !in_range(time, from, to) {
+        return false;
+    }
+
+    // after midnight, the window started the day before
+    let weekday = if from > to && time < to {
+        now.weekday().pred()
+    } else {
+        now.weekday()
+    };
+
+    days.is_empty() || days.iter().any(|day| day.matches(weekday))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).expect("valid time")
+    }
+
+    #[test]
+    fn test_in_range() {
+        assert!(in_range(time(12, 0), time(9, 0), time(17, 0)));
+        assert!(!in_range(time(17, 0), time(9, 0), time(17, 0)));
+
+        // crosses midnight
+        assert!(in_range(time(23, 0), time(20, 0), time(7, 0)));
+        assert!(in_range(time(3, 0), time(20, 0), time(7, 0)));
+        assert!(!in_range(time(12, 0), time(20, 0), time(7, 0)));
+    }
+
+    #[test]
+    fn test_until_next_change() {
+        assert_eq!(
+            until_next_change(time(12, 0), time(20, 0), time(7, 0)),
+            Duration::from_secs(8 * 60 * 60)
+        );
+        assert_eq!(
+            until_next_change(time(23, 0), time(20, 0), time(7, 0)),
+            Duration::from_secs(8 * 60 * 60)
+        );
+        assert_eq!(
+            until_next_change(time(20, 0), time(20, 0), time(7, 0)),
+            Duration::from_secs(11 * 60 * 60)
+        );
+    }
+
+    #[test]
+    fn test_is_active() {
+        // 2024-06-07 is a Friday
+        let at = |day: u32, hour: u32, minute: u32| {
+            NaiveDate::from_ymd_opt(2024, 6, day)
+                .expect("valid date")
+                .and_time(time(hour, minute))
+        };
+
+        let weekdays = [Day::Weekdays];
+        assert!(is_active(&weekdays, at(7, 9, 15), time(9, 0), time(9, 30)));
+        assert!(!is_active(&weekdays, at(8, 9, 15), time(9, 0), time(9, 30)));
+        assert!(!is_active(&weekdays, at(7, 9, 30), time(9, 0), time(9, 30)));
+
+        // Friday night continues into Saturday morning
+        let friday = [Day::Fri];
+        assert!(is_active(&friday, at(8, 1, 0), time(22, 0), time(2, 0)));
+        assert!(!is_active(&friday, at(7, 1, 0), time(22, 0), time(2, 0)));
+
+        assert!(is_active(&[], at(8, 9, 15), time(9, 0), time(9, 30)));
+    }
+}
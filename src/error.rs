@@ -1,9 +1,14 @@
 #[repr(i32)]
 pub enum ExitCode {
+    #[cfg(feature = "bar")]
     GtkDisplay = 1,
+    #[cfg(feature = "bar")]
     CreateBars = 2,
+    #[cfg(feature = "cli")]
     IpcResponseError = 3,
     WaylandDispatchError = 4,
+    #[cfg(feature = "cli")]
+    IoError = 5,
 }
 
 pub const ERR_MUTEX_LOCK: &str = "Failed to get lock on Mutex";
@@ -1,7 +1,12 @@
-#[cfg(any(feature = "music", feature = "workspaces", feature = "clipboard"))]
+#[cfg(any(
+    feature = "music",
+    feature = "workspaces",
+    feature = "clipboard",
+    feature = "usb"
+))]
 mod gtk;
 mod provider;
 
-#[cfg(any(feature = "music", feature = "workspaces"))]
+#[cfg(any(feature = "music", feature = "workspaces", feature = "usb"))]
 pub use self::gtk::*;
 pub use provider::ImageProvider;
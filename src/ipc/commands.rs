@@ -27,8 +27,46 @@ pub enum Command {
     #[command(subcommand)]
     Var(IronvarCommand),
 
+    /// Close all open popups, across every bar.
+    HidePopups,
+
+    /// Get the health of every module,
+    /// including the error for any module which failed to start.
+    State,
+
+    /// Get the current state of a client as JSON.
+    /// The client is started if it is not already running.
+    Client {
+        /// The client name, ie `battery` or `volume`.
+        name: String,
+    },
+
     /// Interact with a specific bar.
     Bar(BarCommand),
+
+    /// Get and switch the active config profile.
+    #[command(subcommand)]
+    Profile(ProfileCommand),
+
+    /// Interact with custom modules.
+    #[command(subcommand)]
+    Custom(CustomCommand),
+
+    /// Interact with launcher modules.
+    #[cfg(feature = "launcher")]
+    #[command(subcommand)]
+    Launcher(LauncherCommand),
+
+    /// Interact with networkmanager modules.
+    #[cfg(feature = "networkmanager")]
+    #[command(subcommand, name = "networkmanager")]
+    #[serde(rename = "networkmanager")]
+    NetworkManager(NetworkManagerCommand),
+
+    /// Interact with tray modules.
+    #[cfg(feature = "tray")]
+    #[command(subcommand)]
+    Tray(TrayCommand),
 }
 
 #[derive(Subcommand, Debug, Serialize, Deserialize)]
@@ -55,6 +93,102 @@ pub enum IronvarCommand {
     List,
 }
 
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(tag = "subcommand", rename_all = "snake_case")]
+pub enum ProfileCommand {
+    /// Switch to a config profile, rebuilding all bars.
+    Set {
+        /// The name of the profile.
+        name: String,
+    },
+
+    /// Stop using a profile and switch back to the top-level config,
+    /// rebuilding all bars.
+    Unset,
+
+    /// Get the name of the active profile.
+    Get,
+
+    /// Get the names of all configured profiles.
+    List,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(tag = "subcommand", rename_all = "snake_case")]
+pub enum CustomCommand {
+    /// Set the state of all custom modules with the given name.
+    SetState {
+        /// The `name` of the custom module.
+        module: String,
+        /// The name of the state to switch to.
+        state: String,
+    },
+}
+
+#[cfg(feature = "launcher")]
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(tag = "subcommand", rename_all = "snake_case")]
+pub enum LauncherCommand {
+    /// Focus or open the item at a position on a launcher.
+    /// Only one launcher is activated, so items are not opened once per bar.
+    Activate {
+        /// The position of the item on the bar, starting from 1.
+        index: usize,
+        /// The `name` of the launcher module.
+        /// If not set, the first launcher is used.
+        #[arg(long)]
+        module: Option<String>,
+    },
+}
+
+#[cfg(feature = "networkmanager")]
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(tag = "subcommand", rename_all = "snake_case")]
+pub enum NetworkManagerCommand {
+    /// Turn wifi on or off.
+    ToggleWifi {
+        /// The `name` of the networkmanager module.
+        /// If not set, the first networkmanager module is used.
+        #[arg(long)]
+        module: Option<String>,
+    },
+
+    /// Turn airplane mode on or off.
+    ToggleAirplaneMode {
+        /// The `name` of the networkmanager module.
+        /// If not set, the first networkmanager module is used.
+        #[arg(long)]
+        module: Option<String>,
+    },
+}
+
+#[cfg(feature = "tray")]
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+#[serde(tag = "subcommand", rename_all = "snake_case")]
+pub enum TrayCommand {
+    /// Activate a tray item, as if it was clicked.
+    /// This usually opens or focuses the app's main window.
+    Activate {
+        /// The id of the tray item, such as `nm-applet`.
+        id: String,
+        /// The `name` of the tray module.
+        /// If not set, the first tray is used.
+        #[arg(long)]
+        module: Option<String>,
+    },
+
+    /// Open the menu of a tray item.
+    /// Only one tray is used, so the menu is not opened once per bar.
+    OpenMenu {
+        /// The id of the tray item, such as `nm-applet`.
+        id: String,
+        /// The `name` of the tray module.
+        /// If not set, the first tray is used.
+        #[arg(long)]
+        module: Option<String>,
+    },
+}
+
 #[derive(Args, Debug, Serialize, Deserialize)]
 pub struct BarCommand {
     /// The name of the bar.
@@ -1,7 +1,6 @@
 use super::Response;
 use crate::bar::Bar;
 use crate::ipc::{BarCommand, BarCommandType};
-use crate::modules::PopupButton;
 use crate::Ironbar;
 use std::rc::Rc;
 
@@ -21,14 +20,14 @@ pub fn handle_command(command: BarCommand, ironbar: &Rc<Ironbar>) -> Response {
             value: bar.visible().to_string(),
         },
 
-        ShowPopup { widget_name } => show_popup(&bar, widget_name),
+        ShowPopup { widget_name } => show_popup(&bar, &widget_name),
         HidePopup => hide_popup(&bar),
         SetPopupVisible {
             widget_name,
             visible,
         } => {
             if visible {
-                show_popup(&bar, widget_name)
+                show_popup(&bar, &widget_name)
             } else {
                 hide_popup(&bar)
             }
@@ -37,7 +36,7 @@ pub fn handle_command(command: BarCommand, ironbar: &Rc<Ironbar>) -> Response {
             if bar.popup().visible() {
                 hide_popup(&bar)
             } else {
-                show_popup(&bar, widget_name)
+                show_popup(&bar, &widget_name)
             }
         }
         GetPopupVisible => Response::OkValue {
@@ -56,28 +55,10 @@ fn set_visible(bar: &Bar, visible: bool) -> Response {
     Response::Ok
 }
 
-fn show_popup(bar: &Bar, widget_name: String) -> Response {
-    let popup = bar.popup();
-
-    // only one popup per bar, so hide if open for another widget
-    popup.hide();
-
-    let data = popup
-        .container_cache
-        .borrow()
-        .iter()
-        .find(|(_, value)| value.name == widget_name)
-        .map(|(id, value)| (*id, value.content.buttons.first().cloned()));
-
-    match data {
-        Some((id, Some(button))) => {
-            let button_id = button.popup_id();
-            popup.show(id, button_id);
-
-            Response::Ok
-        }
-        Some((_, None)) => Response::error("Module has no popup functionality"),
-        None => Response::error("Invalid module name"),
+fn show_popup(bar: &Bar, widget_name: &str) -> Response {
+    match bar.show_popup(widget_name) {
+        Ok(()) => Response::Ok,
+        Err(err) => Response::error(&err.to_string()),
     }
 }
 
@@ -0,0 +1,12 @@
+use crate::ipc::commands::CustomCommand;
+use crate::ipc::Response;
+use crate::modules::custom;
+
+pub fn handle_command(command: CustomCommand) -> Response {
+    match command {
+        CustomCommand::SetState { module, state } => match custom::set_state(&module, &state) {
+            Ok(()) => Response::Ok,
+            Err(err) => Response::error(&format!("{err}")),
+        },
+    }
+}
@@ -0,0 +1,14 @@
+use crate::ipc::commands::LauncherCommand;
+use crate::ipc::Response;
+use crate::modules::launcher;
+
+pub fn handle_command(command: LauncherCommand) -> Response {
+    match command {
+        LauncherCommand::Activate { index, module } => {
+            match launcher::activate(index, module.as_deref()) {
+                Ok(()) => Response::Ok,
+                Err(err) => Response::error(&format!("{err}")),
+            }
+        }
+    }
+}
@@ -1,19 +1,31 @@
+#[cfg(feature = "bar")]
 mod bar;
+#[cfg(feature = "bar")]
+mod custom;
 mod ironvar;
+#[cfg(all(feature = "bar", feature = "launcher"))]
+mod launcher;
+#[cfg(all(feature = "bar", feature = "networkmanager"))]
+mod networkmanager;
+#[cfg(feature = "bar")]
+mod profile;
+#[cfg(all(feature = "bar", feature = "tray"))]
+mod tray;
 
 use std::fs;
 use std::path::Path;
 use std::rc::Rc;
 
 use color_eyre::{Report, Result};
+#[cfg(feature = "bar")]
 use gtk::prelude::*;
-use gtk::Application;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{UnixListener, UnixStream};
 use tokio::sync::mpsc::{self, Receiver, Sender};
 use tracing::{debug, error, info, warn};
 
 use crate::ipc::{Command, Response};
+#[cfg(feature = "bar")]
 use crate::style::load_css;
 use crate::{glib_recv_mpsc, send_async, spawn, try_send, Ironbar};
 
@@ -23,7 +35,7 @@ impl Ipc {
     /// Starts the IPC server on its socket.
     ///
     /// Once started, the server will begin accepting connections.
-    pub fn start(&self, application: &Application, ironbar: Rc<Ironbar>) {
+    pub fn start(&self, ironbar: Rc<Ironbar>) {
         let (cmd_tx, cmd_rx) = mpsc::channel(32);
         let (res_tx, mut res_rx) = mpsc::channel(32);
 
@@ -65,9 +77,8 @@ impl Ipc {
             }
         });
 
-        let application = application.clone();
         glib_recv_mpsc!(cmd_rx, command => {
-            let res = Self::handle_command(command, &application, &ironbar);
+            let res = Self::handle_command(command, &ironbar);
             try_send!(res_tx, res);
         });
     }
@@ -107,40 +118,29 @@ impl Ipc {
     /// Takes an input command, runs it and returns with the appropriate response.
     ///
     /// This runs on the main thread, allowing commands to interact with GTK.
-    fn handle_command(
-        command: Command,
-        application: &Application,
-        ironbar: &Rc<Ironbar>,
-    ) -> Response {
+    ///
+    /// Commands which act on bars are rejected by headless builds.
+    pub(crate) fn handle_command(command: Command, ironbar: &Rc<Ironbar>) -> Response {
         match command {
             Command::Ping => Response::Ok,
+            Command::Var(cmd) => ironvar::handle_command(cmd),
+            Command::Client { name } => match ironbar.clients.borrow_mut().export_state(&name) {
+                Ok(value) => Response::OkValue {
+                    value: value.to_string(),
+                },
+                Err(err) => Response::error(&err.to_string()),
+            },
+            #[cfg(feature = "bar")]
             Command::Inspect => {
                 gtk::Window::set_interactive_debugging(true);
                 Response::Ok
             }
+            #[cfg(feature = "bar")]
             Command::Reload => {
-                info!("Closing existing bars");
-                ironbar.bars.borrow_mut().clear();
-
-                let windows = application.windows();
-                for window in windows {
-                    window.close();
-                }
-
-                let wl = ironbar.clients.borrow_mut().wayland();
-                let outputs = wl.output_info_all();
-
-                ironbar.reload_config();
-
-                for output in outputs {
-                    match crate::load_output_bars(ironbar, application, &output) {
-                        Ok(mut bars) => ironbar.bars.borrow_mut().append(&mut bars),
-                        Err(err) => error!("{err:?}"),
-                    }
-                }
-
+                reload(ironbar);
                 Response::Ok
             }
+            #[cfg(feature = "bar")]
             Command::LoadCss { path } => {
                 if path.exists() {
                     load_css(path);
@@ -149,8 +149,29 @@ impl Ipc {
                     Response::error("File not found")
                 }
             }
-            Command::Var(cmd) => ironvar::handle_command(cmd),
+            #[cfg(feature = "bar")]
+            Command::HidePopups => {
+                ironbar.hide_popups();
+                Response::Ok
+            }
+            #[cfg(feature = "bar")]
+            Command::State => Response::OkValue {
+                value: crate::modules::health::report(),
+            },
+            #[cfg(feature = "bar")]
             Command::Bar(cmd) => bar::handle_command(cmd, ironbar),
+            #[cfg(feature = "bar")]
+            Command::Profile(cmd) => profile::handle_command(cmd, ironbar),
+            #[cfg(feature = "bar")]
+            Command::Custom(cmd) => custom::handle_command(cmd),
+            #[cfg(all(feature = "bar", feature = "launcher"))]
+            Command::Launcher(cmd) => launcher::handle_command(cmd),
+            #[cfg(all(feature = "bar", feature = "networkmanager"))]
+            Command::NetworkManager(cmd) => networkmanager::handle_command(cmd),
+            #[cfg(all(feature = "bar", feature = "tray"))]
+            Command::Tray(cmd) => tray::handle_command(cmd),
+            #[cfg(not(feature = "bar"))]
+            _ => Response::error("Not supported by headless builds"),
         }
     }
 
@@ -162,3 +183,29 @@ impl Ipc {
         fs::remove_file(&path).ok();
     }
 }
+
+/// Closes all bars, re-reads the config from disk,
+/// then re-creates the bars for every output.
+#[cfg(feature = "bar")]
+fn reload(ironbar: &Rc<Ironbar>) {
+    info!("Closing existing bars");
+    ironbar.bars.borrow_mut().clear();
+
+    let application = ironbar.application();
+    let windows = application.windows();
+    for window in windows {
+        window.close();
+    }
+
+    let wl = ironbar.clients.borrow_mut().wayland();
+    let outputs = wl.output_info_all();
+
+    ironbar.reload_config();
+
+    for output in outputs {
+        match crate::load_output_bars(ironbar, application, &output) {
+            Ok(mut bars) => ironbar.bars.borrow_mut().append(&mut bars),
+            Err(err) => error!("{err:?}"),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use crate::ipc::commands::NetworkManagerCommand;
+use crate::ipc::Response;
+use crate::modules::networkmanager;
+
+pub fn handle_command(command: NetworkManagerCommand) -> Response {
+    let res = match command {
+        NetworkManagerCommand::ToggleWifi { module } => {
+            networkmanager::toggle_wifi(module.as_deref())
+        }
+        NetworkManagerCommand::ToggleAirplaneMode { module } => {
+            networkmanager::toggle_airplane_mode(module.as_deref())
+        }
+    };
+
+    match res {
+        Ok(()) => Response::Ok,
+        Err(err) => Response::error(&format!("{err}")),
+    }
+}
@@ -0,0 +1,44 @@
+use super::reload;
+use crate::ipc::commands::ProfileCommand;
+use crate::ipc::Response;
+use crate::Ironbar;
+use std::rc::Rc;
+use tracing::info;
+
+pub fn handle_command(command: ProfileCommand, ironbar: &Rc<Ironbar>) -> Response {
+    match command {
+        ProfileCommand::Set { name } => {
+            let exists = ironbar
+                .config
+                .borrow()
+                .profiles
+                .as_ref()
+                .is_some_and(|profiles| profiles.contains_key(&name));
+
+            if !exists {
+                return Response::error("Profile not found");
+            }
+
+            info!("Switching to config profile '{name}'");
+            ironbar.profile.replace(Some(name));
+            reload(ironbar);
+
+            Response::Ok
+        }
+        ProfileCommand::Unset => {
+            info!("Switching to top-level config");
+            ironbar.profile.replace(None);
+            reload(ironbar);
+
+            Response::Ok
+        }
+        ProfileCommand::Get => match ironbar.profile.borrow().clone() {
+            Some(value) => Response::OkValue { value },
+            None => Response::error("No profile is active"),
+        },
+        ProfileCommand::List => {
+            let value = ironbar.config.borrow().profile_names().join("\n");
+            Response::OkValue { value }
+        }
+    }
+}
@@ -0,0 +1,15 @@
+use crate::ipc::commands::TrayCommand;
+use crate::ipc::Response;
+use crate::modules::tray;
+
+pub fn handle_command(command: TrayCommand) -> Response {
+    let res = match command {
+        TrayCommand::Activate { id, module } => tray::activate(&id, module.as_deref()),
+        TrayCommand::OpenMenu { id, module } => tray::open_menu(&id, module.as_deref()),
+    };
+
+    match res {
+        Ok(()) => Response::Ok,
+        Err(err) => Response::error(&format!("{err}")),
+    }
+}
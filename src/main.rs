@@ -1,50 +1,82 @@
 #![doc = include_str!("../README.md")]
 
+#[cfg(feature = "bar")]
+use std::cell::OnceCell;
 use std::cell::RefCell;
+#[cfg(feature = "bar")]
 use std::env;
 use std::future::Future;
+#[cfg(feature = "bar")]
 use std::path::PathBuf;
 use std::process::exit;
 use std::rc::Rc;
-use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(feature = "bar")]
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "bar")]
+use std::sync::Mutex;
 #[cfg(feature = "ipc")]
 use std::sync::RwLock;
-use std::sync::{mpsc, Arc, Mutex, OnceLock};
+use std::sync::{mpsc, Arc, OnceLock};
 
 use cfg_if::cfg_if;
 #[cfg(feature = "cli")]
 use clap::Parser;
+#[cfg(feature = "bar")]
 use color_eyre::eyre::Result;
+#[cfg(any(feature = "bar", feature = "cli"))]
 use color_eyre::Report;
+#[cfg(feature = "bar")]
 use dirs::config_dir;
+#[cfg(feature = "bar")]
 use glib::PropertySet;
+#[cfg(feature = "bar")]
 use gtk::gdk::Display;
+#[cfg(feature = "bar")]
 use gtk::prelude::*;
+#[cfg(feature = "bar")]
 use gtk::Application;
+#[cfg(feature = "bar")]
 use smithay_client_toolkit::output::OutputInfo;
 use tokio::runtime::Runtime;
 use tokio::task::{block_in_place, JoinHandle};
-use tracing::{debug, error, info, warn};
+#[cfg(any(feature = "bar", feature = "cli"))]
+use tracing::error;
+use tracing::info;
+#[cfg(feature = "bar")]
+use tracing::{debug, warn};
+#[cfg(feature = "bar")]
 use universal_config::ConfigLoader;
 
+#[cfg(feature = "bar")]
 use crate::bar::{create_bar, Bar};
+#[cfg(feature = "bar")]
 use crate::clients::wayland::OutputEventType;
 use crate::clients::Clients;
+#[cfg(feature = "bar")]
 use crate::config::{Config, MonitorConfig};
+#[cfg(any(feature = "bar", feature = "cli"))]
 use crate::error::ExitCode;
 #[cfg(feature = "ipc")]
 use crate::ironvar::VariableManager;
+#[cfg(feature = "bar")]
 use crate::style::load_css;
 
+#[cfg(feature = "bar")]
 mod bar;
 #[cfg(feature = "cli")]
 mod cli;
 mod clients;
+#[cfg(feature = "bar")]
 mod config;
+#[cfg(feature = "bar")]
 mod desktop_file;
+#[cfg(feature = "bar")]
 mod dynamic_value;
 mod error;
+#[cfg(feature = "bar")]
 mod gtk_helpers;
+#[cfg(feature = "bar")]
 mod image;
 #[cfg(feature = "ipc")]
 mod ipc;
@@ -52,9 +84,17 @@ mod ipc;
 mod ironvar;
 mod logging;
 mod macros;
+#[cfg(feature = "bar")]
 mod modules;
+#[cfg(feature = "bar")]
 mod popup;
+#[cfg(feature = "bar")]
+mod power;
+#[cfg(feature = "bar")]
 mod script;
+#[cfg(feature = "shortcuts")]
+mod shortcuts;
+#[cfg(feature = "bar")]
 mod style;
 
 pub const APP_ID: &str = "dev.jstanger.ironbar";
@@ -67,7 +107,7 @@ fn main() {
         if #[cfg(feature = "cli")] {
             run_with_args();
         } else {
-            start_ironbar();
+            start_ironbar(None);
         }
     }
 }
@@ -84,7 +124,17 @@ fn run_with_args() {
     }
 
     match args.command {
-        Some(command) => {
+        Some(cli::CliCommand::Completions { shell }) => cli::print_completions(shell),
+        Some(cli::CliCommand::Man { out_dir }) => {
+            if let Err(err) = cli::generate_man(out_dir.as_deref()) {
+                error!(
+                    "{:?}",
+                    Report::new(err).wrap_err("Failed to write man pages")
+                );
+                exit(ExitCode::IoError as i32);
+            }
+        }
+        Some(cli::CliCommand::Ipc(command)) => {
             if args.debug {
                 eprintln!("REQUEST: {command:?}")
             }
@@ -104,35 +154,60 @@ fn run_with_args() {
                 };
             });
         }
-        None => start_ironbar(),
+        None => start_ironbar(args.profile),
     }
 }
 
 #[derive(Debug)]
 pub struct Ironbar {
+    #[cfg(feature = "bar")]
     bars: Rc<RefCell<Vec<Bar>>>,
     clients: Rc<RefCell<Clients>>,
+    #[cfg(feature = "bar")]
     config: Rc<RefCell<Config>>,
+    #[cfg(feature = "bar")]
     config_dir: PathBuf,
+    /// The name of the active config profile, if any.
+    #[cfg(feature = "bar")]
+    profile: RefCell<Option<String>>,
+    /// The GTK application, set once started.
+    #[cfg(feature = "bar")]
+    application: OnceCell<Application>,
 }
 
 impl Ironbar {
-    fn new() -> Self {
-        let (config, config_dir) = load_config();
+    #[cfg(feature = "bar")]
+    fn new(profile: Option<String>) -> Self {
+        let (config, config_dir) = load_config(profile.as_deref());
 
         Self {
             bars: Rc::new(RefCell::new(vec![])),
             clients: Rc::new(RefCell::new(Clients::new())),
             config: Rc::new(RefCell::new(config)),
             config_dir,
+            profile: RefCell::new(profile),
+            application: OnceCell::new(),
         }
     }
 
+    /// Creates a headless instance,
+    /// which runs clients on demand without any bars.
+    #[cfg(not(feature = "bar"))]
+    fn new() -> Self {
+        Self {
+            clients: Rc::new(RefCell::new(Clients::new())),
+        }
+    }
+
+    #[cfg(feature = "bar")]
     fn start(self) {
         info!("Ironbar version {}", VERSION);
         info!("Starting application");
 
         let app = Application::builder().application_id(APP_ID).build();
+        self.application
+            .set(app.clone())
+            .expect("application to only be set once");
 
         let running = AtomicBool::new(false);
 
@@ -155,12 +230,11 @@ impl Ironbar {
 
             running.set(true);
 
-            cfg_if! {
-                if #[cfg(feature = "ipc")] {
-                    let ipc = ipc::Ipc::new();
-                    ipc.start(app, instance.clone());
-                }
-            }
+            #[cfg(feature = "ipc")]
+            ipc::Ipc::new().start(instance.clone());
+
+            #[cfg(feature = "shortcuts")]
+            shortcuts::start(instance.clone());
 
             let style_path = env::var("IRONBAR_CSS").ok().map_or_else(
                 || {
@@ -180,23 +254,7 @@ impl Ironbar {
                 load_css(style_path);
             }
 
-            let (tx, rx) = mpsc::channel();
-
-            #[cfg(feature = "ipc")]
-            let ipc_path = ipc.path().to_path_buf();
-            spawn_blocking(move || {
-                rx.recv().expect("to receive from channel");
-
-                info!("Shutting down");
-
-                #[cfg(feature = "ipc")]
-                ipc::Ipc::shutdown(ipc_path);
-
-                exit(0);
-            });
-
-            ctrlc::set_handler(move || tx.send(()).expect("Could not send signal on channel."))
-                .expect("Error setting Ctrl-C handler");
+            handle_shutdown();
 
             let hold = app.hold();
             send!(activate_tx, hold);
@@ -240,6 +298,26 @@ impl Ironbar {
         app.run_with_args(&Vec::<&str>::new());
     }
 
+    /// Starts the IPC server without GTK,
+    /// so client state can be read by other frontends.
+    #[cfg(not(feature = "bar"))]
+    fn start(self) {
+        info!("Ironbar version {}", VERSION);
+        info!("Starting headless");
+
+        // clients fetch their initial state in the background,
+        // so start them now rather than on the first export
+        #[cfg(feature = "ipc")]
+        self.clients.borrow_mut().start_exportable();
+
+        #[cfg(feature = "ipc")]
+        ipc::Ipc::new().start(Rc::new(self));
+
+        handle_shutdown();
+
+        glib::MainLoop::new(None, false).run();
+    }
+
     /// Gets the current Tokio runtime.
     #[must_use]
     pub fn runtime() -> Arc<Runtime> {
@@ -265,10 +343,19 @@ impl Ironbar {
             .clone()
     }
 
+    /// Gets the GTK application.
+    #[cfg(all(feature = "bar", feature = "ipc"))]
+    fn application(&self) -> &Application {
+        self.application
+            .get()
+            .expect("application to be set on start")
+    }
+
     /// Gets a clone of a bar by its unique name.
     ///
     /// Since the bar contains mostly GTK objects,
     /// the clone is cheap enough to not worry about.
+    #[cfg(feature = "bar")]
     #[must_use]
     pub fn bar_by_name(&self, name: &str) -> Option<Bar> {
         self.bars
@@ -278,21 +365,70 @@ impl Ironbar {
             .cloned()
     }
 
+    /// Hides the popups on all bars, including any which are pinned open.
+    #[cfg(feature = "bar")]
+    pub fn hide_popups(&self) {
+        for bar in self.bars.borrow().iter() {
+            bar.popup().hide();
+        }
+    }
+
     /// Re-reads the config file from disk and replaces the active config.
     /// Note this does *not* reload bars, which must be performed separately.
-    #[cfg(feature = "ipc")]
+    #[cfg(all(feature = "bar", feature = "ipc"))]
     fn reload_config(&self) {
-        self.config.replace(load_config().0);
+        let profile = self.profile.borrow();
+        self.config.replace(load_config(profile.as_deref()).0);
     }
 }
 
-fn start_ironbar() {
-    let ironbar = Ironbar::new();
+/// Starts Ironbar using the given config profile.
+/// If not set, the profile is read from the `IRONBAR_PROFILE` environment variable.
+#[cfg(feature = "bar")]
+fn start_ironbar(profile: Option<String>) {
+    let profile = profile.or_else(|| env::var("IRONBAR_PROFILE").ok());
+
+    let ironbar = Ironbar::new(profile);
     ironbar.start();
 }
 
-/// Loads the config file from disk.
-fn load_config() -> (Config, PathBuf) {
+/// Starts Ironbar headless.
+/// Profiles only apply to bar config, so are ignored.
+#[cfg(not(feature = "bar"))]
+fn start_ironbar(_profile: Option<String>) {
+    Ironbar::new().start();
+}
+
+/// Waits for `SIGINT` or `SIGTERM`,
+/// then cleans up and exits.
+fn handle_shutdown() {
+    let (tx, rx) = mpsc::channel();
+
+    spawn_blocking(move || {
+        rx.recv().expect("to receive from channel");
+
+        info!("Shutting down");
+
+        #[cfg(feature = "ipc")]
+        ipc::Ipc::shutdown(ipc::Ipc::new().path());
+
+        #[cfg(feature = "tray")]
+        clients::tray::stop_xembed_proxy();
+
+        #[cfg(all(feature = "bar", feature = "networkmanager"))]
+        clients::networkmanager::save_histories();
+
+        exit(0);
+    });
+
+    ctrlc::set_handler(move || tx.send(()).expect("Could not send signal on channel."))
+        .expect("Error setting Ctrl-C handler");
+}
+
+/// Loads the config file from disk,
+/// applying the named profile if set.
+#[cfg(feature = "bar")]
+fn load_config(profile: Option<&str>) -> (Config, PathBuf) {
     let config_path = env::var("IRONBAR_CONFIG");
 
     let (config, directory) = if let Ok(config_path) = config_path {
@@ -336,10 +472,21 @@ fn load_config() -> (Config, PathBuf) {
         }
     }
 
+    if let Some(profile) = profile {
+        match config.apply_profile(profile) {
+            Ok(()) => info!("Using config profile '{profile}'"),
+            Err(err) => {
+                error!("{err:?}");
+                warn!("Falling back to the top-level config");
+            }
+        }
+    }
+
     (config, directory)
 }
 
 /// Gets the GDK `Display` instance.
+#[cfg(feature = "bar")]
 fn get_display() -> Display {
     Display::default().map_or_else(
         || {
@@ -352,6 +499,7 @@ fn get_display() -> Display {
 }
 
 /// Loads all the bars associated with an output.
+#[cfg(feature = "bar")]
 fn load_output_bars(
     ironbar: &Rc<Ironbar>,
     app: &Application,
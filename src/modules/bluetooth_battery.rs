@@ -0,0 +1,158 @@
+use crate::clients::bluetooth::{self, Device};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Image, Label, Orientation};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BluetoothBatteryModule {
+    /// The format string to use for each device's label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{percentage}%`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The size to render each device icon at, in pixels.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// Whether to only show audio devices, such as headphones and headsets.
+    /// Set to `false` to also show devices such as mice and keyboards.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    audio_only: bool,
+
+    /// The battery percentage at or below which
+    /// the `.low` class is added to a device.
+    ///
+    /// **Default**: `20`
+    #[serde(default = "default_low_threshold")]
+    low_threshold: u8,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{percentage}%")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+const fn default_low_threshold() -> u8 {
+    20
+}
+
+impl Module<gtk::Box> for BluetoothBatteryModule {
+    type SendMessage = Vec<Device>;
+    type ReceiveMessage = ();
+
+    module_impl!("bluetooth_battery");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context.try_client::<bluetooth::Client>()?;
+        let mut rx = client.subscribe();
+
+        let tx = context.tx.clone();
+        let audio_only = self.audio_only;
+
+        spawn(async move {
+            loop {
+                let devices = rx
+                    .borrow_and_update()
+                    .iter()
+                    .filter(|device| !audio_only || device.is_audio())
+                    .cloned()
+                    .collect();
+
+                send_async!(tx, ModuleUpdateEvent::Update(devices));
+
+                if rx.changed().await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let container = gtk::Box::new(Orientation::Horizontal, 5);
+
+        let icon_theme = info.icon_theme.clone();
+        let icon_size = info.icon_size(self.icon_size, default_icon_size());
+        let angle = info.bar_position.get_angle();
+
+        {
+            let container = container.clone();
+
+            glib_recv!(context.subscribe(), devices => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                for device in &devices {
+                    let badge = gtk::Box::new(Orientation::Horizontal, 0);
+                    badge.add_class("device");
+                    badge.set_tooltip_text(Some(&format!("{}: {}%", device.name, device.battery)));
+
+                    if device.battery <= self.low_threshold {
+                        badge.add_class("low");
+                    }
+
+                    let icon = Image::new();
+                    icon.add_class("icon");
+
+                    let icon_name = device.icon.as_ref().map_or_else(
+                        || String::from("icon:bluetooth-active-symbolic"),
+                        |icon| format!("icon:{icon}-symbolic"),
+                    );
+
+                    ImageProvider::parse(&icon_name, &icon_theme, false, icon_size)
+                        .map(|provider| provider.load_into_image(icon.clone()));
+
+                    badge.add(&icon);
+
+                    let label = Label::builder().use_markup(true).angle(angle).build();
+                    label.add_class("label");
+                    label.set_markup(
+                        &self
+                            .format
+                            .replace("{percentage}", &device.battery.to_string())
+                            .replace("{name}", &glib::markup_escape_text(&device.name)),
+                    );
+
+                    badge.add(&label);
+                    container.add(&badge);
+                }
+
+                container.show_all();
+                container.set_visible(!devices.is_empty());
+            });
+        }
+
+        Ok(ModuleParts::new(container, None))
+    }
+}
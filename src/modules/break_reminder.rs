@@ -0,0 +1,275 @@
+use crate::clients::break_reminder::{BreakState, TimerConfig};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::script::ScriptInput;
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use glib::Propagation;
+use gtk::gdk;
+use gtk::prelude::*;
+use gtk::{ApplicationWindow, Button, EventBox, Label, Orientation};
+use gtk_layer_shell::LayerShell;
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BreakReminderModule {
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `󰈈 {remaining}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The number of minutes between breaks.
+    ///
+    /// **Default**: `20`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// The number of seconds each break lasts.
+    ///
+    /// **Default**: `20`
+    #[serde(default = "default_duration")]
+    duration: u64,
+
+    /// The number of minutes to delay the next break by
+    /// when snoozing.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_snooze")]
+    snooze: u64,
+
+    /// Whether to dim the screen with an overlay window during breaks.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    overlay: bool,
+
+    /// The message to show on the overlay window.
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `Look at something 20 feet away`
+    #[serde(default = "default_message")]
+    message: String,
+
+    /// A [script](scripts) to run when a break starts.
+    ///
+    /// **Supported script types**: `oneshot`.
+    /// <br>
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { on_break = "notify-send 'Time for a break'" }
+    /// ```
+    on_break: Option<ScriptInput>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("󰈈 {remaining}")
+}
+
+const fn default_interval() -> u64 {
+    20
+}
+
+const fn default_duration() -> u64 {
+    20
+}
+
+const fn default_snooze() -> u64 {
+    5
+}
+
+fn default_message() -> String {
+    String::from("Look at something 20 feet away")
+}
+
+impl Module<Button> for BreakReminderModule {
+    type SendMessage = BreakState;
+    type ReceiveMessage = ();
+
+    module_impl!("break_reminder");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+
+        let client = context
+            .ironbar
+            .clients
+            .borrow_mut()
+            .break_reminder(TimerConfig {
+                interval: Duration::from_secs(self.interval * 60),
+                duration: Duration::from_secs(self.duration),
+                snooze: Duration::from_secs(self.snooze * 60),
+                on_break: self.on_break.clone(),
+            });
+
+        spawn(async move {
+            let mut state = client.subscribe();
+
+            loop {
+                let current = *state.borrow_and_update();
+                send_async!(tx, ModuleUpdateEvent::Update(current));
+
+                tokio::select! {
+                    res = state.changed() => {
+                        if res.is_err() {
+                            break;
+                        }
+                    }
+                    Some(()) = rx.recv() => client.snooze().await,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        {
+            let tx = context.controller_tx.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, ());
+            });
+        }
+
+        let overlay = self
+            .overlay
+            .then(|| BreakOverlay::new(info, &self.message, context.controller_tx.clone()));
+
+        {
+            let button = button.clone();
+
+            glib_recv!(context.subscribe(), state => {
+                let text = self
+                    .format
+                    .replace("{remaining}", &format_duration(state.remaining));
+
+                label.set_markup(&text);
+
+                if state.on_break {
+                    button.add_class("break");
+                } else {
+                    button.style_context().remove_class("break");
+                }
+
+                if let Some(overlay) = &overlay {
+                    overlay.update(state);
+                }
+            });
+        }
+
+        Ok(ModuleParts {
+            widget: button,
+            popup: None,
+        })
+    }
+}
+
+/// A full-screen window shown on top of everything else during breaks.
+struct BreakOverlay {
+    window: ApplicationWindow,
+    countdown: Label,
+}
+
+impl BreakOverlay {
+    fn new(info: &ModuleInfo, message: &str, tx: mpsc::Sender<()>) -> Self {
+        use gtk_layer_shell::Edge;
+
+        let window = ApplicationWindow::builder().application(info.app).build();
+        window.add_class("break-overlay");
+
+        // allow a translucent background, so the screen is dimmed rather than hidden
+        if let Some(visual) = gdk::Screen::default().and_then(|screen| screen.rgba_visual()) {
+            window.set_visual(Some(&visual));
+        }
+
+        window.init_layer_shell();
+        window.set_monitor(info.monitor);
+        window.set_layer(gtk_layer_shell::Layer::Overlay);
+        window.set_namespace(env!("CARGO_PKG_NAME"));
+        window.set_exclusive_zone(-1);
+
+        for edge in [Edge::Top, Edge::Bottom, Edge::Left, Edge::Right] {
+            window.set_anchor(edge, true);
+        }
+
+        let event_box = EventBox::new();
+
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+        container.set_valign(gtk::Align::Center);
+        container.set_halign(gtk::Align::Center);
+
+        let message_label = Label::builder().use_markup(true).build();
+        message_label.set_markup(message);
+        message_label.add_class("message");
+        container.add(&message_label);
+
+        let countdown = Label::new(None);
+        countdown.add_class("countdown");
+        container.add(&countdown);
+
+        let hint = Label::new(Some("Click to snooze"));
+        hint.add_class("hint");
+        container.add(&hint);
+
+        event_box.add(&container);
+        window.add(&event_box);
+
+        event_box.connect_button_press_event(move |_, _| {
+            try_send!(tx, ());
+            Propagation::Stop
+        });
+
+        event_box.show_all();
+
+        Self { window, countdown }
+    }
+
+    fn update(&self, state: BreakState) {
+        if state.on_break {
+            self.countdown.set_text(&format_duration(state.remaining));
+
+            if !self.window.is_visible() {
+                self.window.show();
+            }
+        } else if self.window.is_visible() {
+            self.window.hide();
+        }
+    }
+}
+
+/// Formats a duration as minutes and seconds, ie `19:05`.
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
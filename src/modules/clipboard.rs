@@ -1,4 +1,4 @@
-use crate::clients::clipboard::{self, ClipboardEvent};
+use crate::clients::clipboard::{self, ClipboardEvent, ClipboardFilter};
 use crate::clients::wayland::{ClipboardItem, ClipboardValue};
 use crate::config::{CommonConfig, TruncateMode};
 use crate::image::new_icon_button;
@@ -6,7 +6,7 @@ use crate::modules::{
     Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
 };
 use crate::{glib_recv, module_impl, spawn, try_send};
-use glib::Propagation;
+use glib::{Bytes, Propagation};
 use gtk::gdk_pixbuf::Pixbuf;
 use gtk::gio::{Cancellable, MemoryInputStream};
 use gtk::prelude::*;
@@ -29,9 +29,8 @@ pub struct ClipboardModule {
     /// The size to render the icon at.
     /// Note this only applies to image-type icons.
     ///
-    /// **Default**: `32`
-    #[serde(default = "default_icon_size")]
-    icon_size: i32,
+    /// **Default**: The bar's `icon_size` if set, otherwise `32`
+    icon_size: Option<i32>,
 
     /// The maximum number of items to keep in the history,
     /// and to show in the popup.
@@ -40,6 +39,52 @@ pub struct ClipboardModule {
     #[serde(default = "default_max_items")]
     max_items: usize,
 
+    /// The maximum size of an item in bytes.
+    /// Larger items are not added to the history.
+    ///
+    /// **Default**: `null`
+    max_size: Option<usize>,
+
+    /// A list of mime types to ignore.
+    /// Items advertising any of these types are not added to the history.
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { ignored_mime_types = [ "image/png" ] }
+    /// ```
+    #[serde(default)]
+    ignored_mime_types: Vec<String>,
+
+    /// Whether to ignore items copied from password managers.
+    ///
+    /// This relies on the password manager advertising the
+    /// `x-kde-passwordManagerHint` type, which is supported by most popular password managers.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    ignore_passwords: bool,
+
+    /// Whether to also add text selected with the mouse (the primary selection) to the history,
+    /// rather than only items copied to the clipboard.
+    ///
+    /// This requires the compositor to support version 2 of `wlr-data-control`.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    track_primary_selection: bool,
+
+    /// Whether activating an entry in the history also copies it to the primary selection,
+    /// so that it can be pasted with a middle click.
+    ///
+    /// This requires the compositor to support version 2 of `wlr-data-control`.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    copy_to_primary_selection: bool,
+
     // -- Common --
     /// See [truncate options](module-level-options#truncate-mode).
     ///
@@ -90,13 +135,19 @@ impl Module<Button> for ClipboardModule {
         mut rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> color_eyre::Result<()> {
         let max_items = self.max_items;
+        let filter = ClipboardFilter {
+            max_size: self.max_size,
+            ignored_mime_types: self.ignored_mime_types.clone(),
+            ignore_passwords: self.ignore_passwords,
+            primary_selection: self.track_primary_selection,
+        };
 
         let tx = context.tx.clone();
         let client = context.client::<clipboard::Client>();
 
         // listen to clipboard events
         spawn(async move {
-            let mut rx = client.subscribe(max_items);
+            let mut rx = client.subscribe(max_items, filter);
 
             while let Some(event) = rx.recv().await {
                 match event {
@@ -115,6 +166,9 @@ impl Module<Button> for ClipboardModule {
                     ClipboardEvent::Activate(id) => {
                         try_send!(tx, ModuleUpdateEvent::Update(ControllerEvent::Activate(id)));
                     }
+                    ClipboardEvent::Deactivate => {
+                        try_send!(tx, ModuleUpdateEvent::Update(ControllerEvent::Deactivate));
+                    }
                 }
             }
 
@@ -122,12 +176,13 @@ impl Module<Button> for ClipboardModule {
         });
 
         let client = context.client::<clipboard::Client>();
+        let copy_to_primary_selection = self.copy_to_primary_selection;
 
         // listen to ui events
         spawn(async move {
             while let Some(event) = rx.recv().await {
                 match event {
-                    UIEvent::Copy(id) => client.copy(id),
+                    UIEvent::Copy(id) => client.copy(id, copy_to_primary_selection),
                     UIEvent::Remove(id) => client.remove(id),
                 }
             }
@@ -141,7 +196,11 @@ impl Module<Button> for ClipboardModule {
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         info: &ModuleInfo,
     ) -> color_eyre::Result<ModuleParts<Button>> {
-        let button = new_icon_button(&self.icon, info.icon_theme, self.icon_size);
+        let button = new_icon_button(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
         button.style_context().add_class("btn");
 
         let tx = context.tx.clone();
@@ -202,7 +261,7 @@ impl Module<Button> for ClipboardModule {
                                 button
                             }
                             ClipboardValue::Image(bytes) => {
-                                let stream = MemoryInputStream::from_bytes(bytes);
+                                let stream = MemoryInputStream::from_bytes(&Bytes::from(bytes));
                                 let pixbuf = Pixbuf::from_stream_at_scale(
                                     &stream,
                                     128,
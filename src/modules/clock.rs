@@ -1,4 +1,5 @@
 use std::env;
+use std::time::{Duration, Instant};
 
 use chrono::{DateTime, Local, Locale};
 use color_eyre::Result;
@@ -8,6 +9,7 @@ use serde::Deserialize;
 use tokio::sync::{broadcast, mpsc};
 use tokio::time::sleep;
 
+use crate::clients::wayland::{self, IdleEvent};
 use crate::config::{CommonConfig, ModuleOrientation};
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::modules::{
@@ -24,6 +26,8 @@ pub struct ClockModule {
     /// Detail on available tokens can be found here:
     /// <https://docs.rs/chrono/latest/chrono/format/strftime/index.html>
     ///
+    /// Additionally, `{idle}` is replaced with the time since the last user input.
+    ///
     /// **Default**: `%d/%m/%Y %H:%M`
     #[serde(default = "default_format")]
     format: String,
@@ -34,6 +38,8 @@ pub struct ClockModule {
     /// Detail on available tokens can be found here:
     /// <https://docs.rs/chrono/latest/chrono/format/strftime/index.html>
     ///
+    /// Additionally, `{idle}` is replaced with the time since the last user input.
+    ///
     /// **Default**: `%H:%M:%S`
     #[serde(default = "default_popup_format")]
     format_popup: String,
@@ -49,6 +55,16 @@ pub struct ClockModule {
     #[serde(default = "default_locale")]
     locale: String,
 
+    /// The number of seconds without input
+    /// before the `{idle}` token starts counting.
+    /// The count includes this time.
+    ///
+    /// This requires a compositor supporting the `ext_idle_notify_v1` protocol.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_idle_timeout")]
+    idle_timeout: u32,
+
     /// The orientation to display the widget contents.
     /// Setting to vertical will rotate text 90 degrees.
     ///
@@ -69,6 +85,7 @@ impl Default for ClockModule {
             format: default_format(),
             format_popup: default_popup_format(),
             locale: default_locale(),
+            idle_timeout: default_idle_timeout(),
             orientation: ModuleOrientation::Horizontal,
             common: Some(CommonConfig::default()),
         }
@@ -89,6 +106,10 @@ fn default_locale() -> String {
         .map_or_else(|_| "POSIX".to_string(), strip_tail)
 }
 
+const fn default_idle_timeout() -> u32 {
+    5
+}
+
 fn strip_tail(string: String) -> String {
     string
         .split_once('.')
@@ -96,8 +117,35 @@ fn strip_tail(string: String) -> String {
         .unwrap_or(string)
 }
 
+impl ClockModule {
+    /// Whether either format uses the `{idle}` token.
+    fn shows_idle(&self) -> bool {
+        self.format.contains("{idle}") || self.format_popup.contains("{idle}")
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ClockUpdate {
+    date: DateTime<Local>,
+    /// The time since the last user input,
+    /// or `None` while the user is active.
+    idle: Option<Duration>,
+}
+
+impl ClockUpdate {
+    fn format(&self, format: &str, locale: Locale) -> String {
+        let text = self.date.format_localized(format, locale).to_string();
+
+        if text.contains("{idle}") {
+            text.replace("{idle}", &format_idle(self.idle.unwrap_or_default()))
+        } else {
+            text
+        }
+    }
+}
+
 impl Module<Button> for ClockModule {
-    type SendMessage = DateTime<Local>;
+    type SendMessage = ClockUpdate;
     type ReceiveMessage = ();
 
     module_impl!("clock");
@@ -109,11 +157,41 @@ impl Module<Button> for ClockModule {
         _rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         let tx = context.tx.clone();
+
+        let timeout = self.idle_timeout.saturating_mul(1000);
+
+        // only track idle time when shown, to avoid connecting to the compositor otherwise
+        let mut idle_rx = self.shows_idle().then(|| {
+            let wl = context.client::<wayland::Client>();
+            let rx = wl.subscribe_idle();
+            wl.register_idle_notification(timeout);
+            rx
+        });
+
         spawn(async move {
+            let mut idle_since = None;
+
             loop {
-                let date = Local::now();
-                send_async!(tx, ModuleUpdateEvent::Update(date));
-                sleep(tokio::time::Duration::from_millis(500)).await;
+                let update = ClockUpdate {
+                    date: Local::now(),
+                    idle: idle_since.map(|since: Instant| since.elapsed()),
+                };
+                send_async!(tx, ModuleUpdateEvent::Update(update));
+
+                tokio::select! {
+                    () = sleep(Duration::from_millis(500)) => {}
+                    Some(event) = next_idle_event(idle_rx.as_mut()) => {
+                        if event.timeout == timeout {
+                            // the notification is sent once the timeout has passed,
+                            // so the last input was that long ago
+                            idle_since = event.idle.then(|| {
+                                let now = Instant::now();
+                                now.checked_sub(Duration::from_millis(u64::from(timeout)))
+                                    .unwrap_or(now)
+                            });
+                        }
+                    }
+                }
             }
         });
 
@@ -140,11 +218,19 @@ impl Module<Button> for ClockModule {
         let format = self.format.clone();
         let locale = Locale::try_from(self.locale.as_str()).unwrap_or(Locale::POSIX);
 
-        let rx = context.subscribe();
-        glib_recv!(rx, date => {
-            let date_string = format!("{}", date.format_localized(&format, locale));
-            label.set_label(&date_string);
-        });
+        {
+            let button = button.clone();
+            let rx = context.subscribe();
+            glib_recv!(rx, update => {
+                label.set_label(&update.format(&format, locale));
+
+                if update.idle.is_some() {
+                    button.add_class("idle");
+                } else {
+                    button.style_context().remove_class("idle");
+                }
+            });
+        }
 
         let popup = self
             .into_popup(
@@ -182,9 +268,8 @@ impl Module<Button> for ClockModule {
         let format = self.format_popup;
         let locale = Locale::try_from(self.locale.as_str()).unwrap_or(Locale::POSIX);
 
-        glib_recv!(rx, date => {
-            let date_string = format!("{}", date.format_localized(&format, locale));
-            clock.set_label(&date_string);
+        glib_recv!(rx, update => {
+            clock.set_label(&update.format(&format, locale));
         });
 
         container.show_all();
@@ -192,3 +277,24 @@ impl Module<Button> for ClockModule {
         Some(container)
     }
 }
+
+/// Waits for the next idle event,
+/// or forever if idle time is not tracked.
+async fn next_idle_event(rx: Option<&mut broadcast::Receiver<IdleEvent>>) -> Option<IdleEvent> {
+    match rx {
+        Some(rx) => rx.recv().await.ok(),
+        None => std::future::pending().await,
+    }
+}
+
+/// Formats a duration as `M:SS`, or `H:MM:SS` from an hour.
+fn format_idle(idle: Duration) -> String {
+    let seconds = idle.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
@@ -0,0 +1,425 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::script::{OutputStream, Script};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::eyre::{eyre, Result};
+use gtk::prelude::*;
+use gtk::{Button, Label};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+/// The cpufreq policy of the first CPU,
+/// which is taken to represent all CPUs.
+const CPUFREQ_PATH: &str = "/sys/devices/system/cpu/cpu0/cpufreq";
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct CpuGovernorModule {
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{preset}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The presets to cycle between when the widget is clicked.
+    /// If none are set, clicking cycles between all available governors.
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// {
+    ///   presets = [
+    ///     { name = "Saver" governor = "powersave" epp = "power" }
+    ///     { name = "Balanced" governor = "powersave" epp = "balance_performance" }
+    ///     { name = "Performance" governor = "performance" }
+    ///   ]
+    /// }
+    /// ```
+    #[serde(default)]
+    presets: Vec<Preset>,
+
+    /// The command used to gain the privileges needed to change the governor.
+    /// The command to apply the preset is appended to this as arguments.
+    ///
+    /// **Default**: `pkexec`
+    #[serde(default = "default_helper")]
+    helper: String,
+
+    /// Time in seconds between checking the active governor.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct Preset {
+    /// The name to show for the preset.
+    name: String,
+
+    /// The cpufreq governor to set, ie `powersave`.
+    /// The governor is left unchanged if not set.
+    ///
+    /// **Default**: `null`
+    governor: Option<String>,
+
+    /// The energy performance preference (EPP) hint to set, ie `balance_power`.
+    /// This is only supported by the `amd-pstate` and `intel_pstate` drivers.
+    /// The hint is left unchanged if not set.
+    ///
+    /// **Default**: `null`
+    epp: Option<String>,
+}
+
+fn default_format() -> String {
+    String::from("{preset}")
+}
+
+fn default_helper() -> String {
+    String::from("pkexec")
+}
+
+const fn default_interval() -> u64 {
+    5
+}
+
+/// The current cpufreq settings.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CpuFreqState {
+    governor: String,
+    /// The energy performance preference,
+    /// if supported by the driver.
+    epp: Option<String>,
+    available_governors: Vec<String>,
+}
+
+impl Preset {
+    /// Whether the preset's settings are all in effect.
+    fn matches(&self, state: &CpuFreqState) -> bool {
+        self.governor
+            .as_ref()
+            .map_or(true, |governor| governor == &state.governor)
+            && self
+                .epp
+                .as_ref()
+                .map_or(true, |epp| state.epp.as_ref() == Some(epp))
+    }
+}
+
+impl CpuGovernorModule {
+    /// Gets the first preset which is in effect.
+    fn active_preset(&self, state: &CpuFreqState) -> Option<&Preset> {
+        self.presets.iter().find(|preset| preset.matches(state))
+    }
+
+    /// Gets the preset to switch to after the current one.
+    ///
+    /// Without any configured presets, this is the next available governor.
+    fn next_preset(&self, state: &CpuFreqState) -> Option<Preset> {
+        if self.presets.is_empty() {
+            let governors = &state.available_governors;
+            let index = governors
+                .iter()
+                .position(|governor| governor == &state.governor)
+                .map_or(0, |index| (index + 1) % governors.len());
+
+            return governors.get(index).map(|governor| Preset {
+                name: governor.clone(),
+                governor: Some(governor.clone()),
+                epp: None,
+            });
+        }
+
+        let index = self
+            .presets
+            .iter()
+            .position(|preset| preset.matches(state))
+            .map_or(0, |index| (index + 1) % self.presets.len());
+
+        self.presets.get(index).cloned()
+    }
+
+    /// Runs the helper to apply the preset to every CPU.
+    async fn apply(&self, preset: &Preset) -> Result<()> {
+        let mut script = String::new();
+
+        for (file, value) in [
+            ("scaling_governor", &preset.governor),
+            ("energy_performance_preference", &preset.epp),
+        ] {
+            let Some(value) = value else {
+                continue;
+            };
+
+            // the value is written by a privileged shell, so must not contain anything else
+            if !is_valid_value(value) {
+                return Err(eyre!("Invalid value for {file}: '{value}'"));
+            }
+
+            script.push_str(&format!(
+                "for policy in /sys/devices/system/cpu/cpufreq/policy*; do echo {value} > \"$policy/{file}\"; done\n"
+            ));
+        }
+
+        if script.is_empty() {
+            return Ok(());
+        }
+
+        // the preset script is passed as an argument so that it does not need quoting
+        let helper = Script::from(format!("{} sh -c \"$1\"", self.helper).as_str());
+        let args = [String::from("sh"), script];
+
+        match helper.get_output_status(Some(&args)).await? {
+            (OutputStream::Stderr(error), status) => Err(eyre!(
+                "Failed to apply CPU governor preset '{}': helper exited with {status}: {error}",
+                preset.name
+            )),
+            (OutputStream::Stdout(_), _) => Ok(()),
+        }
+    }
+}
+
+impl Module<Button> for CpuGovernorModule {
+    type SendMessage = CpuFreqState;
+    type ReceiveMessage = ();
+
+    module_impl!("cpu_governor");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let interval = Duration::from_secs(self.interval);
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            let mut previous = None;
+
+            loop {
+                match read_state() {
+                    Ok(state) => {
+                        if previous.as_ref() != Some(&state) {
+                            debug!("CPU governor state: {state:?}");
+                            previous = Some(state.clone());
+                            send_async!(tx, ModuleUpdateEvent::Update(state));
+                        }
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+
+                sleep(interval).await;
+            }
+        });
+
+        let module = self.clone();
+        let tx = context.tx.clone();
+        spawn(async move {
+            while rx.recv().await.is_some() {
+                let state = match read_state() {
+                    Ok(state) => state,
+                    Err(err) => {
+                        error!("{err:?}");
+                        continue;
+                    }
+                };
+
+                let Some(preset) = module.next_preset(&state) else {
+                    continue;
+                };
+
+                debug!("Switching to CPU governor preset '{}'", preset.name);
+
+                match module.apply(&preset).await {
+                    Ok(()) => match read_state() {
+                        Ok(state) => send_async!(tx, ModuleUpdateEvent::Update(state)),
+                        Err(err) => error!("{err:?}"),
+                    },
+                    Err(err) => error!("{err:?}"),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        let tx = context.controller_tx.clone();
+        button.connect_clicked(move |_| {
+            try_send!(tx, ());
+        });
+
+        {
+            let button = button.clone();
+
+            glib_recv!(context.subscribe(), state => {
+                let preset = self
+                    .active_preset(&state)
+                    .map_or(state.governor.as_str(), |preset| preset.name.as_str());
+                let epp = state.epp.as_deref().unwrap_or_default();
+
+                label.set_markup(
+                    &self
+                        .format
+                        .replace("{preset}", preset)
+                        .replace("{governor}", &state.governor)
+                        .replace("{epp}", epp),
+                );
+
+                let tooltip = match &state.epp {
+                    Some(epp) => format!("Governor: {}\nEPP: {epp}", state.governor),
+                    None => format!("Governor: {}", state.governor),
+                };
+                button.set_tooltip_text(Some(&tooltip));
+            });
+        }
+
+        Ok(ModuleParts::new(button, None))
+    }
+}
+
+/// Reads the current cpufreq settings from sysfs.
+fn read_state() -> Result<CpuFreqState> {
+    let path = Path::new(CPUFREQ_PATH);
+
+    let read =
+        |file: &str| fs::read_to_string(path.join(file)).map(|value| value.trim().to_string());
+
+    Ok(CpuFreqState {
+        governor: read("scaling_governor")?,
+        epp: read("energy_performance_preference").ok(),
+        available_governors: read("scaling_available_governors")?
+            .split_whitespace()
+            .map(ToString::to_string)
+            .collect(),
+    })
+}
+
+/// Checks a governor or EPP value only contains
+/// characters used by the kernel for these names.
+fn is_valid_value(value: &str) -> bool {
+    !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(governor: &str, epp: Option<&str>) -> CpuFreqState {
+        CpuFreqState {
+            governor: governor.to_string(),
+            epp: epp.map(ToString::to_string),
+            available_governors: vec![String::from("performance"), String::from("powersave")],
+        }
+    }
+
+    fn preset(name: &str, governor: Option<&str>, epp: Option<&str>) -> Preset {
+        Preset {
+            name: name.to_string(),
+            governor: governor.map(ToString::to_string),
+            epp: epp.map(ToString::to_string),
+        }
+    }
+
+    fn module(presets: Vec<Preset>) -> CpuGovernorModule {
+        CpuGovernorModule {
+            format: default_format(),
+            presets,
+            helper: default_helper(),
+            interval: default_interval(),
+            common: None,
+        }
+    }
+
+    #[test]
+    fn test_next_governor_without_presets() {
+        let module = module(vec![]);
+
+        let next = module.next_preset(&state("performance", None));
+        assert_eq!(
+            next.and_then(|preset| preset.governor).as_deref(),
+            Some("powersave")
+        );
+
+        let next = module.next_preset(&state("powersave", None));
+        assert_eq!(
+            next.and_then(|preset| preset.governor).as_deref(),
+            Some("performance")
+        );
+    }
+
+    #[test]
+    fn test_next_preset() {
+        let module = module(vec![
+            preset("Saver", Some("powersave"), Some("power")),
+            preset("Balanced", Some("powersave"), Some("balance_performance")),
+            preset("Performance", Some("performance"), None),
+        ]);
+
+        let current = state("powersave", Some("balance_performance"));
+        assert_eq!(
+            module
+                .active_preset(&current)
+                .map(|preset| preset.name.as_str()),
+            Some("Balanced")
+        );
+        assert_eq!(
+            module.next_preset(&current).map(|preset| preset.name),
+            Some(String::from("Performance"))
+        );
+
+        // wraps around
+        let current = state("performance", Some("performance"));
+        assert_eq!(
+            module.next_preset(&current).map(|preset| preset.name),
+            Some(String::from("Saver"))
+        );
+
+        // starts from the first preset if none match
+        let current = state("schedutil", None);
+        assert_eq!(module.active_preset(&current), None);
+        assert_eq!(
+            module.next_preset(&current).map(|preset| preset.name),
+            Some(String::from("Saver"))
+        );
+    }
+
+    #[test]
+    fn test_is_valid_value() {
+        assert!(is_valid_value("balance_performance"));
+        assert!(is_valid_value("amd-pstate"));
+        assert!(!is_valid_value(""));
+        assert!(!is_valid_value("powersave; rm -rf /"));
+    }
+}
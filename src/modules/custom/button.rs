@@ -1,9 +1,10 @@
 use gtk::prelude::*;
-use gtk::{Button, Label, Orientation};
+use gtk::{Button, Label, Orientation, Popover, PositionType};
 use serde::Deserialize;
 
 use crate::config::ModuleOrientation;
 use crate::dynamic_value::dynamic_string;
+use crate::gtk_helpers::IronbarGtkExt;
 use crate::modules::PopupButton;
 use crate::{build, try_send};
 
@@ -37,6 +38,13 @@ pub struct ButtonWidget {
     /// **Default**: `null`
     on_click: Option<String>,
 
+    /// Whether to ask for confirmation before running `on_click`.
+    /// Set to `true` to use the default message,
+    /// or set to a string to use a custom message.
+    ///
+    /// **Default**: `false`
+    confirm: Option<ConfirmConfig>,
+
     /// Orientation of the button.
     ///
     /// **Valid options**: `horizontal`, `vertical`, `h`, `v`
@@ -51,6 +59,28 @@ pub struct ButtonWidget {
     widgets: Option<Vec<WidgetConfig>>,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ConfirmConfig {
+    /// Whether to confirm using the default message.
+    Enabled(bool),
+    /// Confirm using a custom message.
+    Message(String),
+}
+
+impl ConfirmConfig {
+    /// Gets the message to show when confirming,
+    /// or `None` if confirmation is disabled.
+    fn message(&self) -> Option<&str> {
+        match self {
+            Self::Enabled(true) => Some("Are you sure?"),
+            Self::Enabled(false) => None,
+            Self::Message(message) => Some(message),
+        }
+    }
+}
+
 impl CustomWidget for ButtonWidget {
     type Widget = Button;
 
@@ -82,7 +112,7 @@ impl CustomWidget for ButtonWidget {
         if let Some(exec) = self.on_click {
             let tx = context.tx.clone();
 
-            button.connect_clicked(move |button| {
+            let run = move |button: &Button| {
                 try_send!(
                     tx,
                     ExecEvent {
@@ -91,9 +121,75 @@ impl CustomWidget for ButtonWidget {
                         id: button.try_popup_id().unwrap_or(usize::MAX), // may not be a popup button
                     }
                 );
-            });
+            };
+
+            match self.confirm.as_ref().and_then(ConfirmConfig::message) {
+                Some(message) => {
+                    let popover = confirm_popover(&button, message, run);
+                    button.connect_clicked(move |_| popover.popup());
+                }
+                None => {
+                    button.connect_clicked(run);
+                }
+            }
         }
 
         button
     }
 }
+
+/// Creates a popover attached to the button
+/// which asks for confirmation before calling `on_confirm`.
+fn confirm_popover<F>(button: &Button, message: &str, on_confirm: F) -> Popover
+where
+    F: Fn(&Button) + 'static,
+{
+    let popover = Popover::new(Some(button));
+    popover.add_class("confirm");
+    popover.set_position(PositionType::Bottom);
+
+    let container = gtk::Box::new(Orientation::Vertical, 5);
+
+    let label = Label::new(None);
+    label.add_class("message");
+    label.set_markup(message);
+    container.add(&label);
+
+    let buttons = gtk::Box::new(Orientation::Horizontal, 5);
+    buttons.set_homogeneous(true);
+
+    let cancel = Button::with_label("Cancel");
+    cancel.add_class("cancel");
+
+    {
+        let popover = popover.clone();
+        cancel.connect_clicked(move |_| popover.popdown());
+    }
+
+    let confirm = Button::with_label("Confirm");
+    confirm.add_class("confirm");
+
+    {
+        let popover = popover.clone();
+        confirm.connect_clicked(move |_| {
+            popover.popdown();
+
+            // avoid holding a strong reference to the button
+            if let Some(button) = popover
+                .relative_to()
+                .and_then(|widget| widget.downcast::<Button>().ok())
+            {
+                on_confirm(&button);
+            }
+        });
+    }
+
+    buttons.add(&cancel);
+    buttons.add(&confirm);
+    container.add(&buttons);
+
+    container.show_all();
+    popover.add(&container);
+
+    popover
+}
@@ -0,0 +1,104 @@
+use super::{CustomWidget, CustomWidgetContext};
+use crate::build;
+use crate::config::ModuleOrientation;
+use crate::modules::custom::WidgetConfig;
+use gtk::prelude::*;
+use gtk::{Orientation, SelectionMode};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FlowWidget {
+    /// Widget name.
+    ///
+    /// **Default**: `null`
+    name: Option<String>,
+
+    /// Widget class name.
+    ///
+    /// **Default**: `null`
+    class: Option<String>,
+
+    /// Whether child widgets should be added in horizontal rows or vertical columns.
+    /// Rows wrap onto the next once full.
+    ///
+    /// **Valid options**: `horizontal`, `vertical`, `h`, `v`
+    /// <br />
+    /// **Default**: `horizontal`
+    orientation: Option<ModuleOrientation>,
+
+    /// The minimum number of widgets to fit on each line.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    min_per_line: u32,
+
+    /// The maximum number of widgets to fit on each line,
+    /// before wrapping onto the next.
+    ///
+    /// **Default**: `7`
+    #[serde(default = "default_max_per_line")]
+    max_per_line: u32,
+
+    /// The space between each line, in pixels.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    row_spacing: u32,
+
+    /// The space between each widget on a line, in pixels.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    column_spacing: u32,
+
+    /// Whether every widget should be given the same size.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    homogeneous: bool,
+
+    /// Modules and widgets to add to this container.
+    ///
+    /// **Default**: `null`
+    widgets: Option<Vec<WidgetConfig>>,
+}
+
+const fn default_max_per_line() -> u32 {
+    7
+}
+
+impl CustomWidget for FlowWidget {
+    type Widget = gtk::FlowBox;
+
+    fn into_widget(self, context: CustomWidgetContext) -> Self::Widget {
+        let container = build!(self, Self::Widget);
+
+        // children are buttons and modules,
+        // so should not be highlighted as a selection when clicked
+        container.set_selection_mode(SelectionMode::None);
+
+        if let Some(orientation) = self.orientation {
+            container.set_orientation(orientation.into());
+        }
+
+        container.set_min_children_per_line(self.min_per_line);
+        container.set_max_children_per_line(self.max_per_line.max(1));
+        container.set_row_spacing(self.row_spacing);
+        container.set_column_spacing(self.column_spacing);
+        container.set_homogeneous(self.homogeneous);
+
+        if let Some(widgets) = self.widgets {
+            for widget in widgets {
+                // modules can only be added to a box,
+                // so each child gets its own
+                let child = gtk::Box::new(Orientation::Horizontal, 0);
+                widget.widget.add_to(&child, &context, widget.common);
+
+                container.add(&child);
+            }
+        }
+
+        container
+    }
+}
@@ -0,0 +1,83 @@
+use super::{CustomWidget, CustomWidgetContext};
+use crate::build;
+use crate::modules::custom::WidgetConfig;
+use gtk::prelude::*;
+use gtk::Orientation;
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct GridWidget {
+    /// Widget name.
+    ///
+    /// **Default**: `null`
+    name: Option<String>,
+
+    /// Widget class name.
+    ///
+    /// **Default**: `null`
+    class: Option<String>,
+
+    /// The number of columns to lay widgets out in.
+    /// Each row is filled from left to right before starting the next.
+    ///
+    /// **Default**: `2`
+    #[serde(default = "default_columns")]
+    columns: u32,
+
+    /// The space between each row, in pixels.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    row_spacing: u32,
+
+    /// The space between each column, in pixels.
+    ///
+    /// **Default**: `0`
+    #[serde(default)]
+    column_spacing: u32,
+
+    /// Whether every cell should be given the same size.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    homogeneous: bool,
+
+    /// Modules and widgets to add to this grid.
+    ///
+    /// **Default**: `null`
+    widgets: Option<Vec<WidgetConfig>>,
+}
+
+const fn default_columns() -> u32 {
+    2
+}
+
+impl CustomWidget for GridWidget {
+    type Widget = gtk::Grid;
+
+    fn into_widget(self, context: CustomWidgetContext) -> Self::Widget {
+        let grid = build!(self, Self::Widget);
+
+        grid.set_row_spacing(self.row_spacing);
+        grid.set_column_spacing(self.column_spacing);
+        grid.set_row_homogeneous(self.homogeneous);
+        grid.set_column_homogeneous(self.homogeneous);
+
+        let columns = self.columns.max(1) as i32;
+
+        if let Some(widgets) = self.widgets {
+            for (index, widget) in widgets.into_iter().enumerate() {
+                // modules can only be added to a box,
+                // so each cell gets its own
+                let cell = gtk::Box::new(Orientation::Horizontal, 0);
+                widget.widget.add_to(&cell, &context, widget.common);
+
+                let index = index as i32;
+                grid.attach(&cell, index % columns, index / columns, 1, 1);
+            }
+        }
+
+        grid
+    }
+}
@@ -1,14 +1,28 @@
 mod r#box;
 mod button;
+mod flow;
+mod grid;
 mod image;
 mod label;
+#[cfg(feature = "ipc")]
+mod pipe;
 mod progress;
+mod select;
 mod slider;
+mod state;
 
+use self::flow::FlowWidget;
+use self::grid::GridWidget;
 use self::image::ImageWidget;
 use self::label::LabelWidget;
+#[cfg(feature = "ipc")]
+use self::pipe::PipeWidget;
 use self::r#box::BoxWidget;
+use self::select::SelectWidget;
 use self::slider::SliderWidget;
+#[cfg(feature = "ipc")]
+pub use self::state::set_state;
+use self::state::{resolve_state, set_state_classes, StateConfig, StateWidget};
 use crate::config::{CommonConfig, ModuleConfig};
 use crate::modules::custom::button::ButtonWidget;
 use crate::modules::custom::progress::ProgressWidget;
@@ -17,7 +31,7 @@ use crate::modules::{
     ModuleUpdateEvent, PopupButton, PopupModuleFactory, WidgetContext,
 };
 use crate::script::Script;
-use crate::{module_impl, send_async, spawn};
+use crate::{glib_recv, send_async, spawn};
 use color_eyre::Result;
 use gtk::prelude::*;
 use gtk::{Button, IconTheme, Orientation};
@@ -40,9 +54,25 @@ pub struct CustomModule {
     /// **Default**: `null`
     popup: Option<Vec<WidgetConfig>>,
 
+    /// Named states which the module can switch between,
+    /// each with its own icon, label and class.
+    /// The module starts in the first state.
+    ///
+    /// States are changed using `state:` [commands](#commands),
+    /// or over IPC using the module's `name`.
+    /// More on this [below](#states).
+    ///
+    /// **Default**: `null`
+    states: Option<Vec<StateConfig>>,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
+
+    /// The module's `name`, kept after the common options are taken
+    /// so that the module can be found over IPC.
+    #[serde(skip)]
+    instance_name: Option<String>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -74,6 +104,10 @@ pub enum WidgetOrModule {
 pub enum Widget {
     /// A container to place nested widgets inside.
     Box(BoxWidget),
+    /// A container which lays nested widgets out in a fixed number of columns.
+    Grid(GridWidget),
+    /// A container which wraps nested widgets onto new lines as they fill up.
+    Flow(FlowWidget),
     /// A text label. Pango markup is supported.
     Label(LabelWidget),
     /// A clickable button, which can run a command when clicked.
@@ -84,6 +118,13 @@ pub enum Widget {
     Slider(SliderWidget),
     /// A progress bar.
     Progress(ProgressWidget),
+    /// A dropdown, which can run a command when an option is selected.
+    Select(SelectWidget),
+    /// Shows the icon and label of the module's current state.
+    State(StateWidget),
+    /// Shows a label, icon and class pushed to a named pipe by external processes.
+    #[cfg(feature = "ipc")]
+    Pipe(PipeWidget),
 }
 
 #[derive(Clone)]
@@ -94,6 +135,8 @@ struct CustomWidgetContext<'a> {
     icon_theme: &'a IconTheme,
     popup_buttons: Rc<RefCell<Vec<Button>>>,
     module_factory: AnyModuleFactory,
+    states: &'a [StateConfig],
+    state_tx: &'a broadcast::Sender<String>,
 }
 
 trait CustomWidget {
@@ -163,11 +206,17 @@ impl Widget {
 
         let event_box = match self {
             Self::Box(widget) => create!(widget),
+            Self::Grid(widget) => create!(widget),
+            Self::Flow(widget) => create!(widget),
             Self::Label(widget) => create!(widget),
             Self::Button(widget) => create!(widget),
             Self::Image(widget) => create!(widget),
             Self::Slider(widget) => create!(widget),
             Self::Progress(widget) => create!(widget),
+            Self::Select(widget) => create!(widget),
+            Self::State(widget) => create!(widget),
+            #[cfg(feature = "ipc")]
+            Self::Pipe(widget) => create!(widget),
         };
 
         parent.add(&event_box);
@@ -182,10 +231,19 @@ pub struct ExecEvent {
 }
 
 impl Module<gtk::Box> for CustomModule {
-    type SendMessage = ();
+    /// The name of the current state.
+    type SendMessage = String;
     type ReceiveMessage = ExecEvent;
 
-    module_impl!("custom");
+    fn name() -> &'static str {
+        "custom"
+    }
+
+    fn take_common(&mut self) -> CommonConfig {
+        let common = self.common.take().unwrap_or_default();
+        self.instance_name.clone_from(&common.name);
+        common
+    }
 
     fn spawn_controller(
         &self,
@@ -193,8 +251,24 @@ impl Module<gtk::Box> for CustomModule {
         context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         mut rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
+        let states = self
+            .states
+            .iter()
+            .flatten()
+            .map(|state| state.name.clone())
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "ipc")]
+        if let Some(name) = &self.instance_name {
+            if !states.is_empty() {
+                state::register(name.clone(), states.clone(), &context.controller_tx);
+            }
+        }
+
         let tx = context.tx.clone();
         spawn(async move {
+            let mut current_state = 0;
+
             while let Some(event) = rx.recv().await {
                 if event.cmd.starts_with('!') {
                     let script = Script::from(&event.cmd[1..]);
@@ -212,6 +286,14 @@ impl Module<gtk::Box> for CustomModule {
                     send_async!(tx, ModuleUpdateEvent::OpenPopup(event.id));
                 } else if event.cmd == "popup:close" {
                     send_async!(tx, ModuleUpdateEvent::ClosePopup);
+                } else if let Some(command) = event.cmd.strip_prefix("state:") {
+                    match resolve_state(&states, current_state, command) {
+                        Some(index) => {
+                            current_state = index;
+                            send_async!(tx, ModuleUpdateEvent::Update(states[index].clone()));
+                        }
+                        None => error!("Received invalid state command: '{}'", event.cmd),
+                    }
                 } else {
                     error!("Received invalid command: '{}'", event.cmd);
                 }
@@ -231,6 +313,8 @@ impl Module<gtk::Box> for CustomModule {
 
         let popup_buttons = Rc::new(RefCell::new(Vec::new()));
 
+        let states = self.states.clone().unwrap_or_default();
+
         let custom_context = CustomWidgetContext {
             info,
             tx: &context.controller_tx,
@@ -239,6 +323,8 @@ impl Module<gtk::Box> for CustomModule {
             popup_buttons: popup_buttons.clone(),
             module_factory: BarModuleFactory::new(context.ironbar.clone(), context.popup.clone())
                 .into(),
+            states: &states,
+            state_tx: &context.update_tx,
         };
 
         self.bar.clone().into_iter().for_each(|widget| {
@@ -247,6 +333,15 @@ impl Module<gtk::Box> for CustomModule {
                 .add_to(&container, &custom_context, widget.common);
         });
 
+        if let Some(state) = states.first() {
+            set_state_classes(&container, &states, &state.name);
+
+            let container = container.clone();
+            glib_recv!(context.subscribe(), state => {
+                set_state_classes(&container, &states, &state);
+            });
+        }
+
         for button in popup_buttons.borrow().iter() {
             button.ensure_popup_id();
         }
@@ -284,6 +379,8 @@ impl Module<gtk::Box> for CustomModule {
         let container = gtk::Box::new(Orientation::Horizontal, 0);
 
         if let Some(popup) = self.popup {
+            let states = self.states.unwrap_or_default();
+
             let custom_context = CustomWidgetContext {
                 info,
                 tx: &tx,
@@ -296,6 +393,8 @@ impl Module<gtk::Box> for CustomModule {
                     context.button_id,
                 )
                 .into(),
+                states: &states,
+                state_tx: &context.update_tx,
             };
 
             for widget in popup {
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::fs;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use color_eyre::{Report, Result};
+use gtk::prelude::*;
+use gtk::{Image, Label};
+use nix::sys::stat::Mode;
+use nix::unistd::mkfifo;
+use serde::Deserialize;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::unix::pipe;
+use tokio::sync::watch;
+use tracing::{debug, error, warn};
+
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::{build, lock, spawn};
+
+use super::{CustomWidget, CustomWidgetContext};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PipeWidget {
+    /// Widget name.
+    ///
+    /// **Default**: `null`
+    name: Option<String>,
+
+    /// Widget class name.
+    ///
+    /// **Default**: `null`
+    class: Option<String>,
+
+    /// The name of the pipe to read updates from.
+    /// The pipe is created at `$XDG_RUNTIME_DIR/ironbar/<pipe>`.
+    ///
+    /// Widgets using the same pipe show the same content.
+    ///
+    /// **Required**
+    pipe: String,
+
+    /// The width/height to render the icon at.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+/// The content pushed to a pipe.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PipeState {
+    label: Option<String>,
+    icon: Option<String>,
+    class: Option<String>,
+}
+
+impl PipeState {
+    /// Updates the state from a `key=value` line.
+    /// An empty value clears the key.
+    fn apply(&mut self, line: &str) -> Result<()> {
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| Report::msg("Expected `key=value`"))?;
+
+        let value = (!value.is_empty()).then(|| value.to_string());
+
+        match key.trim() {
+            "label" => self.label = value,
+            "icon" => self.icon = value,
+            "class" => self.class = value,
+            key => return Err(Report::msg(format!("Unknown key '{key}'"))),
+        }
+
+        Ok(())
+    }
+}
+
+impl CustomWidget for PipeWidget {
+    type Widget = gtk::Box;
+
+    fn into_widget(self, context: CustomWidgetContext) -> Self::Widget {
+        let container = build!(self, Self::Widget);
+        container.set_spacing(5);
+
+        let icon = Image::new();
+        icon.add_class("icon");
+        container.add(&icon);
+
+        let label = Label::builder().use_markup(true).build();
+        label.add_class("label");
+        container.add(&label);
+
+        // visibility depends on what has been pushed,
+        // so must not be overridden when the parent is shown
+        icon.set_no_show_all(true);
+        label.set_no_show_all(true);
+
+        let mut rx = match subscribe(&self.pipe) {
+            Ok(rx) => rx,
+            Err(err) => {
+                error!(
+                    "{:?}",
+                    err.wrap_err(format!("Failed to open pipe '{}'", self.pipe))
+                );
+                return container;
+            }
+        };
+
+        let icon_theme = context.icon_theme.clone();
+        let icon_size = context.info.icon_size(self.icon_size, default_icon_size());
+        let mut class = None::<String>;
+
+        let mut update = {
+            let container = container.clone();
+
+            move |state: &PipeState| {
+                match &state.icon {
+                    Some(input) => {
+                        ImageProvider::parse(input, &icon_theme, false, icon_size)
+                            .map(|image| image.load_into_image(icon.clone()));
+                        icon.show();
+                    }
+                    None => icon.hide(),
+                }
+
+                match &state.label {
+                    Some(text) => {
+                        label.set_markup(text);
+                        label.show();
+                    }
+                    None => label.hide(),
+                }
+
+                if class != state.class {
+                    if let Some(class) = &class {
+                        container.style_context().remove_class(class);
+                    }
+
+                    if let Some(class) = &state.class {
+                        container.add_class(class);
+                    }
+
+                    class.clone_from(&state.class);
+                }
+            }
+        };
+
+        update(&rx.borrow_and_update());
+
+        glib::spawn_future_local(async move {
+            while rx.changed().await.is_ok() {
+                let state = rx.borrow_and_update().clone();
+                update(&state);
+            }
+        });
+
+        container
+    }
+}
+
+/// Gets the path of the pipe called `name`.
+fn pipe_path(name: &str) -> Result<PathBuf> {
+    if name.is_empty() || name.contains('/') {
+        return Err(Report::msg("Pipe names cannot be empty or contain `/`"));
+    }
+
+    let dir = std::env::var("XDG_RUNTIME_DIR")
+        .map_or_else(|_| PathBuf::from("/tmp"), PathBuf::from)
+        .join("ironbar");
+
+    Ok(dir.join(name))
+}
+
+/// Subscribes to updates pushed to the pipe called `name`,
+/// creating it and starting to read from it if not already done.
+fn subscribe(name: &str) -> Result<watch::Receiver<PipeState>> {
+    static PIPES: OnceLock<Mutex<HashMap<String, watch::Receiver<PipeState>>>> = OnceLock::new();
+
+    let mut pipes = lock!(PIPES.get_or_init(|| Mutex::new(HashMap::new())));
+
+    if let Some(rx) = pipes.get(name) {
+        return Ok(rx.clone());
+    }
+
+    let path = pipe_path(name)?;
+    create_pipe(&path)?;
+
+    let (tx, rx) = watch::channel(PipeState::default());
+
+    spawn(async move {
+        // opening for writing as well means the pipe is never closed,
+        // so it does not need re-opening after each writer finishes
+        let receiver = match pipe::OpenOptions::new()
+            .read_write(true)
+            .open_receiver(&path)
+        {
+            Ok(receiver) => receiver,
+            Err(err) => {
+                error!("Failed to open pipe '{}': {err:?}", path.display());
+                return;
+            }
+        };
+
+        debug!("Reading from pipe '{}'", path.display());
+
+        let mut lines = BufReader::new(receiver).lines();
+        loop {
+            match lines.next_line().await {
+                Ok(Some(line)) if line.trim().is_empty() => {}
+                Ok(Some(line)) => {
+                    tx.send_if_modified(|state| {
+                        let previous = state.clone();
+
+                        if let Err(err) = state.apply(&line) {
+                            warn!("Invalid line '{line}' on pipe '{}': {err}", path.display());
+                        }
+
+                        *state != previous
+                    });
+                }
+                Ok(None) => break,
+                Err(err) => {
+                    error!("Failed to read from pipe '{}': {err:?}", path.display());
+                    break;
+                }
+            }
+        }
+    });
+
+    pipes.insert(name.to_string(), rx.clone());
+    Ok(rx)
+}
+
+/// Creates a FIFO at `path`,
+/// re-using an existing one left over from a previous run.
+fn create_pipe(path: &Path) -> Result<()> {
+    if let Ok(metadata) = fs::metadata(path) {
+        return if metadata.file_type().is_fifo() {
+            Ok(())
+        } else {
+            Err(Report::msg(format!(
+                "'{}' already exists and is not a pipe",
+                path.display()
+            )))
+        };
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut state = PipeState::default();
+
+        state.apply("label=Hello").unwrap();
+        state.apply("icon=firefox").unwrap();
+        assert_eq!(state.label.as_deref(), Some("Hello"));
+        assert_eq!(state.icon.as_deref(), Some("firefox"));
+
+        // values can contain `=`
+        state.apply("label=a=b").unwrap();
+        assert_eq!(state.label.as_deref(), Some("a=b"));
+
+        state.apply("icon=").unwrap();
+        assert_eq!(state.icon, None);
+
+        assert!(state.apply("label").is_err());
+        assert!(state.apply("colour=red").is_err());
+    }
+}
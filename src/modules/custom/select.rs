@@ -0,0 +1,243 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use gtk::prelude::*;
+use gtk::ComboBoxText;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tracing::error;
+
+use crate::modules::custom::set_length;
+use crate::script::{OutputStream, Script, ScriptInput};
+use crate::{build, glib_recv_mpsc, rc_mut, spawn, try_send};
+
+use super::{CustomWidget, CustomWidgetContext, ExecEvent};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SelectWidget {
+    /// Widget name.
+    ///
+    /// **Default**: `null`
+    name: Option<String>,
+
+    /// Widget class name.
+    ///
+    /// **Default**: `null`
+    class: Option<String>,
+
+    /// Script to run to get the list of options.
+    /// More on this [below](#select).
+    ///
+    /// **Required**
+    options: ScriptInput,
+
+    /// Script to run to get the currently selected value.
+    /// The output must match the value of one of the options.
+    ///
+    /// **Default**: `null`
+    value: Option<ScriptInput>,
+
+    /// Command to execute when an option is selected.
+    /// More on this [below](#commands).
+    ///
+    /// Note that this will provide the selected value as an argument.
+    ///
+    /// **Default**: `null`
+    on_change: Option<String>,
+
+    /// The select length.
+    /// GTK will automatically determine the size if left blank.
+    ///
+    /// **Default**: `null`
+    length: Option<i32>,
+}
+
+/// A single option which can be selected,
+/// as read from the options script's JSON output.
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[serde(untagged)]
+enum SelectOption {
+    Value(String),
+    Labelled {
+        value: String,
+        label: Option<String>,
+    },
+}
+
+impl SelectOption {
+    fn value(&self) -> &str {
+        match self {
+            Self::Value(value) | Self::Labelled { value, .. } => value,
+        }
+    }
+
+    fn label(&self) -> &str {
+        match self {
+            Self::Labelled {
+                label: Some(label), ..
+            } => label,
+            _ => self.value(),
+        }
+    }
+}
+
+/// Parses the options script output.
+///
+/// If the output is a JSON array, each item is either a string
+/// or an object with a `value` and optional `label`.
+/// Otherwise, each non-empty line is treated as an option.
+fn parse_options(output: &str) -> Vec<SelectOption> {
+    let output = output.trim();
+
+    if output.starts_with('[') {
+        match serde_json::from_str(output) {
+            Ok(options) => return options,
+            Err(err) => error!("Failed to parse select options as JSON: {err:?}"),
+        }
+    }
+
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| SelectOption::Value(line.to_string()))
+        .collect()
+}
+
+impl CustomWidget for SelectWidget {
+    type Widget = ComboBoxText;
+
+    fn into_widget(self, context: CustomWidgetContext) -> Self::Widget {
+        let select = build!(self, Self::Widget);
+
+        if let Some(length) = self.length {
+            set_length(&select, length, context.bar_orientation);
+        }
+
+        // set while options or the value are being updated from scripts,
+        // so that programmatic changes do not trigger `on_change`.
+        let updating = Rc::new(Cell::new(false));
+
+        // the value script may finish before the options script,
+        // so keep the last value to re-apply when options change.
+        let value = rc_mut!(None::<String>);
+
+        if let Some(on_change) = self.on_change {
+            let tx = context.tx.clone();
+            let updating = updating.clone();
+
+            select.connect_changed(move |select| {
+                if updating.get() {
+                    return;
+                }
+
+                if let Some(value) = select.active_id() {
+                    try_send!(
+                        tx,
+                        ExecEvent {
+                            cmd: on_change.clone(),
+                            args: Some(vec![value.to_string()]),
+                            id: usize::MAX // ignored
+                        }
+                    );
+                }
+            });
+        }
+
+        {
+            let script = Script::from(self.options);
+            let select = select.clone();
+            let updating = updating.clone();
+            let value = value.clone();
+
+            let (tx, rx) = mpsc::channel(128);
+
+            spawn(async move {
+                script
+                    .run(None, move |stream, _success| match stream {
+                        OutputStream::Stdout(out) => try_send!(tx, parse_options(&out)),
+                        OutputStream::Stderr(err) => error!("{err:?}"),
+                    })
+                    .await;
+            });
+
+            glib_recv_mpsc!(rx, options => {
+                updating.set(true);
+
+                let active = select
+                    .active_id()
+                    .map(|id| id.to_string())
+                    .or_else(|| value.borrow().clone());
+
+                select.remove_all();
+
+                for option in options {
+                    select.append(Some(option.value()), option.label());
+                }
+
+                if let Some(active) = active {
+                    select.set_active_id(Some(&active));
+                }
+
+                updating.set(false);
+            });
+        }
+
+        if let Some(value_script) = self.value {
+            let script = Script::from(value_script);
+            let select = select.clone();
+
+            let (tx, rx) = mpsc::channel(128);
+
+            spawn(async move {
+                script
+                    .run(None, move |stream, _success| match stream {
+                        OutputStream::Stdout(out) => try_send!(tx, out),
+                        OutputStream::Stderr(err) => error!("{err:?}"),
+                    })
+                    .await;
+            });
+
+            glib_recv_mpsc!(rx, new_value => {
+                let new_value = new_value.trim().to_string();
+
+                updating.set(true);
+                select.set_active_id(Some(&new_value));
+                updating.set(false);
+
+                value.replace(Some(new_value));
+            });
+        }
+
+        select
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lines() {
+        let options = parse_options("one\n\n  two  \nthree\n");
+        let values = options.iter().map(SelectOption::value).collect::<Vec<_>>();
+        assert_eq!(values, vec!["one", "two", "three"]);
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let options =
+            parse_options(r#"["one", { "value": "two", "label": "Two" }, { "value": "three" }]"#);
+
+        let options = options
+            .iter()
+            .map(|option| (option.value(), option.label()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            options,
+            vec![("one", "one"), ("two", "Two"), ("three", "three")]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use gtk::prelude::*;
+use gtk::{Image, Label};
+use serde::Deserialize;
+
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::{build, glib_recv};
+
+use super::{CustomWidget, CustomWidgetContext};
+
+/// A named state which the module can be in.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StateConfig {
+    /// The unique name of the state.
+    /// While active, the module has a `state-<name>` class.
+    ///
+    /// **Required**
+    pub name: String,
+
+    /// Text to show on `state` widgets while in this state.
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `null`
+    label: Option<String>,
+
+    /// Icon to show on `state` widgets while in this state.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `null`
+    icon: Option<String>,
+
+    /// An additional class to add to the module while in this state.
+    ///
+    /// **Default**: `null`
+    class: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct StateWidget {
+    /// Widget name.
+    ///
+    /// **Default**: `null`
+    name: Option<String>,
+
+    /// Widget class name.
+    ///
+    /// **Default**: `null`
+    class: Option<String>,
+
+    /// The width/height to render the state icon at.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+impl CustomWidget for StateWidget {
+    type Widget = gtk::Box;
+
+    fn into_widget(self, context: CustomWidgetContext) -> Self::Widget {
+        let container = build!(self, Self::Widget);
+        container.set_spacing(5);
+
+        let icon = Image::new();
+        icon.add_class("icon");
+        container.add(&icon);
+
+        let label = Label::builder().use_markup(true).build();
+        label.add_class("label");
+        container.add(&label);
+
+        // visibility depends on the current state,
+        // so must not be overridden when the parent is shown
+        icon.set_no_show_all(true);
+        label.set_no_show_all(true);
+
+        let states = context.states.to_vec();
+        let icon_theme = context.icon_theme.clone();
+        let icon_size = context.info.icon_size(self.icon_size, default_icon_size());
+
+        let update = move |name: &str| {
+            let Some(state) = states.iter().find(|state| state.name == name) else {
+                return;
+            };
+
+            match &state.icon {
+                Some(input) => {
+                    ImageProvider::parse(input, &icon_theme, false, icon_size)
+                        .map(|image| image.load_into_image(icon.clone()));
+                    icon.show();
+                }
+                None => icon.hide(),
+            }
+
+            match &state.label {
+                Some(text) => {
+                    label.set_markup(text);
+                    label.show();
+                }
+                None => label.hide(),
+            }
+        };
+
+        if let Some(state) = context.states.first() {
+            update(&state.name);
+        }
+
+        glib_recv!(context.state_tx.subscribe(), state => update(&state));
+
+        container
+    }
+}
+
+/// Replaces the state classes on the widget
+/// with those for the state called `name`.
+pub fn set_state_classes<W: IsA<gtk::Widget>>(widget: &W, states: &[StateConfig], name: &str) {
+    let style_context = widget.style_context();
+
+    for state in states {
+        let active = state.name == name;
+
+        let class = format!("state-{}", state.name);
+        let classes = std::iter::once(class.as_str()).chain(state.class.as_deref());
+
+        for class in classes {
+            if active {
+                style_context.add_class(class);
+            } else {
+                style_context.remove_class(class);
+            }
+        }
+    }
+}
+
+/// Gets the index of the state to move to after running a `state:` command,
+/// given the names of all states and the index of the current one.
+///
+/// Returns `None` if the command or state name is invalid.
+pub fn resolve_state(states: &[String], current: usize, command: &str) -> Option<usize> {
+    if states.is_empty() {
+        return None;
+    }
+
+    match command {
+        "next" => Some((current + 1) % states.len()),
+        "prev" => Some((current + states.len() - 1) % states.len()),
+        _ => {
+            let name = command.strip_prefix("set:")?;
+            states.iter().position(|state| state == name)
+        }
+    }
+}
+
+#[cfg(feature = "ipc")]
+pub use registry::{register, set_state};
+
+/// Tracks named custom modules with states,
+/// so their state can be set over IPC.
+#[cfg(feature = "ipc")]
+mod registry {
+    use super::super::ExecEvent;
+    use crate::{lock, try_send};
+    use color_eyre::{Report, Result};
+    use std::sync::{Mutex, OnceLock};
+    use tokio::sync::mpsc;
+
+    struct Registration {
+        name: String,
+        states: Vec<String>,
+        tx: mpsc::WeakSender<ExecEvent>,
+    }
+
+    fn registry() -> &'static Mutex<Vec<Registration>> {
+        static REGISTRY: OnceLock<Mutex<Vec<Registration>>> = OnceLock::new();
+        REGISTRY.get_or_init(|| Mutex::new(vec![]))
+    }
+
+    /// Registers a module instance.
+    /// The same name can be registered multiple times,
+    /// for example when the module is on a bar on each monitor.
+    pub fn register(name: String, states: Vec<String>, tx: &mpsc::Sender<ExecEvent>) {
+        let mut registry = lock!(registry());
+
+        // clear out modules from closed bars
+        registry.retain(|registration| registration.tx.upgrade().is_some());
+
+        registry.push(Registration {
+            name,
+            states,
+            tx: tx.downgrade(),
+        });
+    }
+
+    /// Sets the state of all module instances with the given name.
+    pub fn set_state(name: &str, state: &str) -> Result<()> {
+        let registry = lock!(registry());
+
+        let senders = registry
+            .iter()
+            .filter(|registration| registration.name == name)
+            .filter_map(|registration| {
+                registration
+                    .tx
+                    .upgrade()
+                    .map(|tx| (registration.states.iter().any(|s| s == state), tx))
+            })
+            .collect::<Vec<_>>();
+
+        if senders.is_empty() {
+            return Err(Report::msg("Module not found"));
+        }
+
+        if senders.iter().any(|(valid, _)| !valid) {
+            return Err(Report::msg("State not found"));
+        }
+
+        for (_, tx) in senders {
+            try_send!(
+                tx,
+                ExecEvent {
+                    cmd: format!("state:set:{state}"),
+                    args: None,
+                    id: usize::MAX, // ignored
+                }
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_state() {
+        let states = vec![
+            String::from("idle"),
+            String::from("recording"),
+            String::from("paused"),
+        ];
+
+        assert_eq!(resolve_state(&states, 0, "next"), Some(1));
+        assert_eq!(resolve_state(&states, 2, "next"), Some(0));
+        assert_eq!(resolve_state(&states, 0, "prev"), Some(2));
+        assert_eq!(resolve_state(&states, 0, "set:paused"), Some(2));
+        assert_eq!(resolve_state(&states, 0, "set:missing"), None);
+        assert_eq!(resolve_state(&states, 0, "invalid"), None);
+        assert_eq!(resolve_state(&[], 0, "next"), None);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::script::Script;
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Image, Label, Orientation};
+use serde::Deserialize;
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+use tokio::process::Child;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DictationModule {
+    /// Shell command to start dictation, such as `exec nerd-dictation begin`.
+    /// The command should keep running for as long as dictation is live.
+    start_command: String,
+
+    /// Shell command to stop dictation, such as `nerd-dictation end`.
+    /// If not set, the process started by `start_command` is killed instead.
+    ///
+    /// **Default**: `null`
+    stop_command: Option<String>,
+
+    /// Shell command which exits successfully while dictation is live.
+    /// This allows dictation started outside Ironbar to be shown.
+    /// If not set, dictation is only live while `start_command` is running.
+    ///
+    /// **Default**: `null`
+    status_command: Option<String>,
+
+    /// Format string for the label while dictation is live.
+    /// `{elapsed}` is replaced with the time dictation has been live for.
+    ///
+    /// **Default**: `{elapsed}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Text to show on the label while dictation is off.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    idle_label: String,
+
+    /// Image to show while dictation is live.
+    ///
+    /// **Default**: `audio-input-microphone-symbolic`
+    #[serde(default = "default_icon_live")]
+    icon_live: String,
+
+    /// Image to show while dictation is off.
+    ///
+    /// **Default**: `microphone-disabled-symbolic`
+    #[serde(default = "default_icon_idle")]
+    icon_idle: String,
+
+    /// The size to render the icon at, in pixels.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// Time in seconds between checking whether dictation is live.
+    ///
+    /// **Default**: `1`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{elapsed}")
+}
+
+fn default_icon_live() -> String {
+    String::from("audio-input-microphone-symbolic")
+}
+
+fn default_icon_idle() -> String {
+    String::from("microphone-disabled-symbolic")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+const fn default_interval() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DictationStatus {
+    Idle,
+    Live { elapsed: Duration },
+}
+
+/// Tracks the dictation process started from the widget,
+/// and when dictation went live.
+#[derive(Debug, Default)]
+struct Dictation {
+    child: Option<Child>,
+    started: Option<SystemTime>,
+}
+
+impl Dictation {
+    /// Whether the process started from the widget is still running.
+    /// Reaps the process once it has exited.
+    fn child_running(&mut self) -> bool {
+        let Some(child) = &mut self.child else {
+            return false;
+        };
+
+        match child.try_wait() {
+            Ok(None) => true,
+            Ok(Some(status)) => {
+                debug!("Dictation command exited with {status}");
+                self.child = None;
+                false
+            }
+            Err(err) => {
+                error!("Failed to check dictation command: {err:?}");
+                self.child = None;
+                false
+            }
+        }
+    }
+}
+
+impl Module<Button> for DictationModule {
+    type SendMessage = DictationStatus;
+    type ReceiveMessage = ();
+
+    module_impl!("dictation");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+
+        let start_command = self.start_command.clone();
+        let stop_command = self.stop_command.clone();
+        let status_command = self.status_command.clone();
+        let interval = Duration::from_secs(self.interval);
+
+        spawn(async move {
+            let mut dictation = Dictation::default();
+
+            loop {
+                let live = dictation.child_running() || is_live(status_command.as_deref()).await;
+
+                let status = if live {
+                    let started = *dictation.started.get_or_insert_with(SystemTime::now);
+                    DictationStatus::Live {
+                        elapsed: started.elapsed().unwrap_or_default(),
+                    }
+                } else {
+                    dictation.started = None;
+                    DictationStatus::Idle
+                };
+
+                send_async!(tx, ModuleUpdateEvent::Update(status));
+
+                tokio::select! {
+                    msg = rx.recv() => {
+                        if msg.is_none() {
+                            break;
+                        }
+
+                        if live {
+                            stop(&mut dictation, stop_command.as_deref()).await;
+                        } else {
+                            start(&mut dictation, &start_command);
+                        }
+                    }
+                    () = sleep(interval) => {}
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let container = gtk::Box::new(Orientation::Horizontal, 5);
+        button.add(&container);
+
+        let icon = Image::new();
+        icon.add_class("icon");
+        container.add(&icon);
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        container.add(&label);
+
+        {
+            let tx = context.controller_tx.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, ());
+            });
+        }
+
+        {
+            let button = button.clone();
+            let icon_theme = info.icon_theme.clone();
+            let icon_size = info.icon_size(self.icon_size, default_icon_size());
+
+            // only reload the icon when switching between live and idle
+            let mut was_live = None;
+
+            glib_recv!(context.subscribe(), status => {
+                let live = matches!(status, DictationStatus::Live { .. });
+
+                if was_live != Some(live) {
+                    was_live = Some(live);
+
+                    let image = if live { &self.icon_live } else { &self.icon_idle };
+                    ImageProvider::parse(image, &icon_theme, false, icon_size)
+                        .map(|provider| provider.load_into_image(icon.clone()));
+
+                    if live {
+                        button.add_class("live");
+                    } else {
+                        button.style_context().remove_class("live");
+                    }
+                }
+
+                match status {
+                    DictationStatus::Live { elapsed } => {
+                        let elapsed = format_elapsed(elapsed);
+                        label.set_markup(&self.format.replace("{elapsed}", &elapsed));
+                        button.set_tooltip_text(Some(&format!("Dictation live for {elapsed}")));
+                    }
+                    DictationStatus::Idle => {
+                        label.set_markup(&self.idle_label);
+                        button.set_tooltip_text(Some("Dictation off"));
+                    }
+                }
+            });
+        }
+
+        Ok(ModuleParts::new(button, None))
+    }
+}
+
+fn start(dictation: &mut Dictation, command: &str) {
+    // the process is kept so that it can be killed if there is no stop command
+    match Script::from(command)
+        .command(None)
+        .stdin(Stdio::null())
+        .spawn()
+    {
+        Ok(child) => {
+            dictation.child = Some(child);
+            dictation.started = Some(SystemTime::now());
+        }
+        Err(err) => error!("Failed to start dictation: {err:?}"),
+    }
+}
+
+/// Runs the stop command if set,
+/// otherwise kills the process started from the widget.
+async fn stop(dictation: &mut Dictation, command: Option<&str>) {
+    match command {
+        Some(command) => match Script::from(command).get_output_status(None).await {
+            Ok((_, status)) if !status.success() => error!("Failed to stop dictation: {status}"),
+            Ok(_) => {}
+            Err(err) => error!("Failed to stop dictation: {err:?}"),
+        },
+        None => {
+            if let Some(child) = &mut dictation.child {
+                if let Err(err) = child.kill().await {
+                    error!("Failed to stop dictation: {err:?}");
+                }
+            }
+        }
+    }
+
+    // the process is reaped on the next check if it has not exited yet
+    if let Some(child) = &mut dictation.child {
+        if let Ok(Some(_)) = child.try_wait() {
+            dictation.child = None;
+        }
+    }
+}
+
+/// Runs the status command, if set.
+async fn is_live(command: Option<&str>) -> bool {
+    let Some(command) = command else {
+        return false;
+    };
+
+    match Script::from(command).get_output(None).await {
+        Ok((_, success)) => success,
+        Err(err) => {
+            debug!("Failed to run dictation status command: {err:?}");
+            false
+        }
+    }
+}
+
+/// Formats a duration as `M:SS`, or `H:MM:SS` from an hour.
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(0)), "0:00");
+        assert_eq!(format_elapsed(Duration::from_secs(754)), "12:34");
+        assert_eq!(format_elapsed(Duration::from_secs(7384)), "2:03:04");
+    }
+}
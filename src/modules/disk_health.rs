@@ -0,0 +1,236 @@
+use crate::clients::udisks::{self, DriveHealth};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DiskHealthModule {
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `󰋊 {max_temperature}°C`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Time in seconds between checking drive health.
+    ///
+    /// **Default**: `1800`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// The temperature in degrees Celsius
+    /// at or above which a drive is considered too hot.
+    ///
+    /// **Default**: `55`
+    #[serde(default = "default_max_temperature")]
+    max_temperature: u32,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("󰋊 {max_temperature}°C")
+}
+
+const fn default_interval() -> u64 {
+    1800
+}
+
+const fn default_max_temperature() -> u32 {
+    55
+}
+
+impl DiskHealthModule {
+    /// Whether the drive is reporting problems or running too hot.
+    fn is_unhealthy(&self, drive: &DriveHealth) -> bool {
+        !drive.warnings.is_empty()
+            || drive
+                .temperature
+                .is_some_and(|temp| temp >= f64::from(self.max_temperature))
+    }
+
+    /// Creates the popup row for a single drive.
+    fn drive_row(&self, drive: &DriveHealth) -> gtk::Box {
+        let row = gtk::Box::new(Orientation::Vertical, 0);
+        row.add_class("drive");
+
+        if self.is_unhealthy(drive) {
+            row.add_class("warning");
+        }
+
+        let header = gtk::Box::new(Orientation::Horizontal, 10);
+
+        let name = Label::new(Some(&drive.name));
+        name.add_class("name");
+        name.set_halign(gtk::Align::Start);
+        name.set_hexpand(true);
+        header.add(&name);
+
+        if let Some(temperature) = drive.temperature {
+            let label = Label::new(Some(&format!("{temperature:.0}°C")));
+            label.add_class("temperature");
+            header.add(&label);
+        }
+
+        row.add(&header);
+
+        let status = if drive.warnings.is_empty() {
+            String::from("Healthy")
+        } else {
+            drive.warnings.join("\n")
+        };
+
+        let status = Label::new(Some(&status));
+        status.add_class("status");
+        status.set_halign(gtk::Align::Start);
+        row.add(&status);
+
+        row
+    }
+}
+
+impl Module<Button> for DiskHealthModule {
+    type SendMessage = Vec<DriveHealth>;
+    type ReceiveMessage = ();
+
+    module_impl!("disk_health");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context.try_client::<udisks::Client>()?;
+        let interval = Duration::from_secs(self.interval);
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            loop {
+                match client.drive_health().await {
+                    Ok(drives) => {
+                        debug!("Drive health: {drives:?}");
+                        send_async!(tx, ModuleUpdateEvent::Update(drives));
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+
+                sleep(interval).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let module = self.clone();
+
+            glib_recv!(context.subscribe(), drives => {
+                let max_temperature = drives
+                    .iter()
+                    .filter_map(|drive| drive.temperature)
+                    .reduce(f64::max)
+                    .map_or_else(|| String::from("?"), |temp| format!("{temp:.0}"));
+
+                let unhealthy = drives
+                    .iter()
+                    .filter(|drive| module.is_unhealthy(drive))
+                    .count();
+
+                let status = if unhealthy == 0 { "OK" } else { "Warning" };
+
+                label.set_markup(
+                    &module
+                        .format
+                        .replace("{max_temperature}", &max_temperature)
+                        .replace("{unhealthy}", &unhealthy.to_string())
+                        .replace("{status}", status),
+                );
+
+                if unhealthy == 0 {
+                    button.style_context().remove_class("warning");
+                } else {
+                    button.add_class("warning");
+                }
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        _tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, drives => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                if drives.is_empty() {
+                    let label = Label::new(Some("No drives report SMART data"));
+                    label.add_class("empty");
+                    container.add(&label);
+                }
+
+                for drive in &drives {
+                    container.add(&self.drive_row(drive));
+                }
+
+                container.show_all();
+            });
+        }
+
+        Some(container)
+    }
+}
@@ -0,0 +1,320 @@
+use crate::clients::wayland::{self, OutputChange, OutputHead, OutputMode};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::new_icon_button;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use glib::Propagation;
+use gtk::prelude::*;
+use gtk::{Button, ComboBoxText, Label, Orientation, Switch};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DisplaysModule {
+    /// The icon to show on the bar widget button.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `󰍹`
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render the icon at.
+    /// Note this only applies to image-type icons.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `32`
+    icon_size: Option<i32>,
+
+    /// The scale factors to offer for each output.
+    /// The current scale is always included.
+    ///
+    /// **Default**: `[1.0, 1.25, 1.5, 1.75, 2.0]`
+    #[serde(default = "default_scales")]
+    scales: Vec<f64>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon() -> String {
+    String::from("󰍹")
+}
+
+const fn default_icon_size() -> i32 {
+    32
+}
+
+fn default_scales() -> Vec<f64> {
+    vec![1.0, 1.25, 1.5, 1.75, 2.0]
+}
+
+impl Module<Button> for DisplaysModule {
+    type SendMessage = Vec<OutputHead>;
+    type ReceiveMessage = (String, OutputChange);
+
+    module_impl!("displays");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let wl = context.client::<wayland::Client>();
+
+        {
+            let wl = wl.clone();
+
+            spawn(async move {
+                let mut rx = wl.subscribe_output_heads();
+
+                let heads = wl.output_heads();
+                send_async!(tx, ModuleUpdateEvent::Update(heads));
+
+                while let Ok(heads) = rx.recv().await {
+                    send_async!(tx, ModuleUpdateEvent::Update(heads));
+                }
+
+                error!("Output channel unexpectedly closed");
+            });
+        }
+
+        spawn(async move {
+            while let Some((name, change)) = rx.recv().await {
+                wl.configure_output(name, change);
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = new_icon_button(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
+        button.add_class("btn");
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, heads => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                // prevent disabling the last output, as it cannot be turned back on from here
+                let can_disable = heads.iter().filter(|head| head.enabled).count() > 1;
+
+                for head in heads {
+                    let row = build_output_row(&head, &self.scales, can_disable, &tx);
+                    container.add(&row);
+                }
+
+                container.show_all();
+            });
+        }
+
+        Some(container)
+    }
+}
+
+/// Builds the popup controls for a single output.
+fn build_output_row(
+    head: &OutputHead,
+    scales: &[f64],
+    can_disable: bool,
+    tx: &mpsc::Sender<(String, OutputChange)>,
+) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Vertical, 5);
+    row.add_class("output");
+
+    if !head.enabled {
+        row.add_class("disabled");
+    }
+
+    let header = gtk::Box::new(Orientation::Horizontal, 10);
+    header.add_class("header");
+
+    let name = Label::new(Some(&head.name));
+    name.add_class("name");
+    header.add(&name);
+
+    let description = Label::new(Some(&head.description));
+    description.add_class("description");
+    header.add(&description);
+
+    let switch = Switch::new();
+    switch.add_class("enabled");
+    switch.set_active(head.enabled);
+    switch.set_sensitive(can_disable || !head.enabled);
+    header.pack_end(&switch, false, false, 0);
+
+    row.add(&header);
+
+    let controls = gtk::Box::new(Orientation::Horizontal, 5);
+    controls.add_class("controls");
+    controls.set_sensitive(head.enabled);
+
+    let modes = sorted_modes(&head.modes);
+
+    let mode_select = ComboBoxText::new();
+    mode_select.add_class("mode");
+
+    for mode in &modes {
+        mode_select.append_text(&mode_label(mode));
+    }
+
+    if let Some(index) = head
+        .current_mode
+        .and_then(|current| modes.iter().position(|mode| same_mode(mode, &current)))
+    {
+        mode_select.set_active(Some(index as u32));
+    }
+
+    controls.add(&mode_select);
+
+    let mut scales = scales.to_vec();
+    if !scales
+        .iter()
+        .any(|scale| (scale - head.scale).abs() < f64::EPSILON)
+    {
+        scales.push(head.scale);
+    }
+    scales.sort_by(f64::total_cmp);
+
+    let scale_select = ComboBoxText::new();
+    scale_select.add_class("scale");
+
+    for scale in &scales {
+        scale_select.append_text(&format!("{scale}x"));
+    }
+
+    if let Some(index) = scales
+        .iter()
+        .position(|scale| (scale - head.scale).abs() < f64::EPSILON)
+    {
+        scale_select.set_active(Some(index as u32));
+    }
+
+    controls.add(&scale_select);
+    row.add(&controls);
+
+    // handlers are connected after setting the initial state
+    // to avoid re-applying the current configuration.
+    {
+        let tx = tx.clone();
+        let name = head.name.clone();
+
+        switch.connect_state_set(move |_, enabled| {
+            try_send!(tx, (name.clone(), OutputChange::Enabled(enabled)));
+            Propagation::Proceed
+        });
+    }
+
+    {
+        let tx = tx.clone();
+        let name = head.name.clone();
+
+        mode_select.connect_changed(move |select| {
+            let Some(mode) = select.active().and_then(|index| modes.get(index as usize)) else {
+                return;
+            };
+
+            try_send!(
+                tx,
+                (
+                    name.clone(),
+                    OutputChange::Mode {
+                        width: mode.width,
+                        height: mode.height,
+                        refresh: mode.refresh,
+                    }
+                )
+            );
+        });
+    }
+
+    {
+        let tx = tx.clone();
+        let name = head.name.clone();
+
+        scale_select.connect_changed(move |select| {
+            let Some(scale) = select.active().and_then(|index| scales.get(index as usize)) else {
+                return;
+            };
+
+            try_send!(tx, (name.clone(), OutputChange::Scale(*scale)));
+        });
+    }
+
+    row
+}
+
+/// Gets the unique modes of an output,
+/// sorted by resolution and then refresh rate, highest first.
+fn sorted_modes(modes: &[OutputMode]) -> Vec<OutputMode> {
+    let mut modes = modes.to_vec();
+
+    modes.sort_by(|a, b| (b.width * b.height, b.refresh).cmp(&(a.width * a.height, a.refresh)));
+    modes.dedup_by(|a, b| same_mode(a, b));
+
+    modes
+}
+
+/// Checks whether two modes have the same resolution and refresh rate,
+/// ignoring whether they are preferred.
+fn same_mode(a: &OutputMode, b: &OutputMode) -> bool {
+    a.width == b.width && a.height == b.height && a.refresh == b.refresh
+}
+
+fn mode_label(mode: &OutputMode) -> String {
+    let mut label = format!("{}x{}", mode.width, mode.height);
+
+    if mode.refresh > 0 {
+        label.push_str(&format!(" @ {:.2} Hz", f64::from(mode.refresh) / 1000.0));
+    }
+
+    if mode.preferred {
+        label.push_str(" *");
+    }
+
+    label
+}
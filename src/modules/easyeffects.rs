@@ -0,0 +1,450 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::eyre::{eyre, Result};
+use glib::Propagation;
+use gtk::prelude::*;
+use gtk::{Align, Button, Label, Orientation, Switch};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::rc::Rc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::{debug, error};
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+
+/// The bus name owned by EasyEffects while it is running.
+const BUS_NAME: &str = "com.github.wwmm.easyeffects";
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EasyEffectsModule {
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{icon}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The icon to show while effects are enabled.
+    ///
+    /// **Default**: `󰺢`
+    #[serde(default = "default_icon_enabled")]
+    icon_enabled: String,
+
+    /// The icon to show while effects are bypassed.
+    ///
+    /// **Default**: `󰺣`
+    #[serde(default = "default_icon_bypassed")]
+    icon_bypassed: String,
+
+    /// Time in seconds between checking the bypass state and active preset.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{icon}")
+}
+
+fn default_icon_enabled() -> String {
+    String::from("󰺢")
+}
+
+fn default_icon_bypassed() -> String {
+    String::from("󰺣")
+}
+
+const fn default_interval() -> u64 {
+    5
+}
+
+/// The state of EasyEffects' output effects.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EasyEffectsState {
+    running: bool,
+    bypassed: bool,
+    /// The name of the loaded output preset, if known.
+    preset: Option<String>,
+    /// The names of all saved output presets.
+    presets: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum EasyEffectsCommand {
+    SetBypass(bool),
+    LoadPreset(String),
+}
+
+impl Module<Button> for EasyEffectsModule {
+    type SendMessage = EasyEffectsState;
+    type ReceiveMessage = EasyEffectsCommand;
+
+    module_impl!("easyeffects");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let interval = Duration::from_secs(self.interval);
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            let dbus = match connect().await {
+                Ok(dbus) => dbus,
+                Err(err) => {
+                    error!("{:?}", err.wrap_err("Failed to connect to session bus"));
+                    return;
+                }
+            };
+
+            let mut previous = None;
+
+            loop {
+                tokio::select! {
+                    () = sleep(interval) => {},
+                    command = rx.recv() => {
+                        let Some(command) = command else {
+                            break;
+                        };
+
+                        if let Err(err) = run_command(&command).await {
+                            error!("{:?}", err.wrap_err("Failed to control EasyEffects"));
+                        }
+                    }
+                }
+
+                let state = read_state(&dbus).await;
+                if previous.as_ref() != Some(&state) {
+                    debug!("EasyEffects state: {state:?}");
+                    previous = Some(state.clone());
+                    send_async!(tx, ModuleUpdateEvent::Update(state));
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        // hidden until EasyEffects is found to be running
+        button.set_no_show_all(true);
+        label.show();
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let module = self.clone();
+
+            glib_recv!(context.subscribe(), state => {
+                button.set_visible(state.running);
+
+                let icon = if state.bypassed {
+                    &module.icon_bypassed
+                } else {
+                    &module.icon_enabled
+                };
+
+                let preset = state.preset.as_deref().unwrap_or_default();
+
+                label.set_markup(
+                    &module
+                        .format
+                        .replace("{icon}", icon)
+                        .replace("{preset}", preset),
+                );
+
+                button.set_tooltip_text(Some(&tooltip(&state)));
+
+                if state.bypassed {
+                    button.add_class("bypassed");
+                } else {
+                    button.style_context().remove_class("bypassed");
+                }
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let bypass_row = gtk::Box::new(Orientation::Horizontal, 10);
+        bypass_row.add_class("bypass");
+        container.add(&bypass_row);
+
+        let bypass_label = Label::new(Some("Bypass effects"));
+        bypass_label.set_halign(Align::Start);
+        bypass_row.pack_start(&bypass_label, true, true, 0);
+
+        let bypass_switch = Switch::new();
+        bypass_switch.set_valign(Align::Center);
+        bypass_row.add(&bypass_switch);
+
+        // the switch is also updated from the state,
+        // which must not be sent back as a command
+        let bypassed = Rc::new(Cell::new(false));
+
+        {
+            let tx = tx.clone();
+            let bypassed = bypassed.clone();
+
+            bypass_switch.connect_state_set(move |_, active| {
+                if active != bypassed.get() {
+                    try_send!(tx, EasyEffectsCommand::SetBypass(active));
+                }
+                Propagation::Proceed
+            });
+        }
+
+        let presets_container = gtk::Box::new(Orientation::Vertical, 0);
+        presets_container.add_class("presets");
+        container.add(&presets_container);
+
+        {
+            let mut shown_presets = None;
+
+            glib_recv!(rx, state => {
+                bypassed.set(state.bypassed);
+                bypass_switch.set_active(state.bypassed);
+
+                let presets = Some((state.presets, state.preset));
+                if presets != shown_presets {
+                    if let Some((presets, active)) = &presets {
+                        update_presets(&presets_container, presets, active.as_deref(), &tx);
+                    }
+                    shown_presets = presets;
+                }
+            });
+        }
+
+        container.show_all();
+
+        Some(container)
+    }
+}
+
+/// Rebuilds the popup list of presets,
+/// with a button for each which loads it.
+fn update_presets(
+    container: &gtk::Box,
+    presets: &[String],
+    active: Option<&str>,
+    tx: &mpsc::Sender<EasyEffectsCommand>,
+) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    if presets.is_empty() {
+        container.hide();
+        return;
+    }
+
+    let header = Label::new(Some("Presets"));
+    header.add_class("presets-header");
+    header.set_halign(Align::Start);
+    container.add(&header);
+
+    for preset in presets {
+        let button = Button::new();
+        button.add_class("preset");
+
+        if active == Some(preset.as_str()) {
+            button.add_class("active");
+        }
+
+        let label = Label::new(Some(preset));
+        label.set_halign(Align::Start);
+        button.add(&label);
+
+        let tx = tx.clone();
+        let preset = preset.clone();
+        button.connect_clicked(move |_| {
+            try_send!(tx, EasyEffectsCommand::LoadPreset(preset.clone()));
+        });
+
+        container.add(&button);
+    }
+
+    container.show_all();
+}
+
+fn tooltip(state: &EasyEffectsState) -> String {
+    let status = if state.bypassed {
+        "Effects bypassed"
+    } else {
+        "Effects enabled"
+    };
+
+    match &state.preset {
+        Some(preset) => format!("Preset: {preset}\n{status}"),
+        None => status.to_string(),
+    }
+}
+
+async fn connect() -> Result<DBusProxy<'static>> {
+    let dbus = Box::pin(zbus::Connection::session()).await?;
+    Ok(DBusProxy::new(&dbus).await?)
+}
+
+/// Reads the current state using the EasyEffects CLI.
+///
+/// The CLI starts EasyEffects if it is not already running,
+/// so it is only used once EasyEffects is found on the bus.
+async fn read_state(dbus: &DBusProxy<'_>) -> EasyEffectsState {
+    let name = BusName::try_from(BUS_NAME).expect("to be valid bus name");
+    if !dbus.name_has_owner(name).await.unwrap_or_default() {
+        return EasyEffectsState::default();
+    }
+
+    let bypassed = match easyeffects(&["--bypass", "3"]).await {
+        Ok(output) => parse_bypass(&output),
+        Err(err) => {
+            error!("{err:?}");
+            false
+        }
+    };
+
+    // only supported by newer versions
+    let preset = easyeffects(&["--active-preset", "output"])
+        .await
+        .ok()
+        .map(|output| output.trim().to_string())
+        .filter(|preset| !preset.is_empty());
+
+    let presets = match easyeffects(&["--presets"]).await {
+        Ok(output) => parse_presets(&output),
+        Err(err) => {
+            error!("{err:?}");
+            vec![]
+        }
+    };
+
+    EasyEffectsState {
+        running: true,
+        bypassed,
+        preset,
+        presets,
+    }
+}
+
+async fn run_command(command: &EasyEffectsCommand) -> Result<()> {
+    debug!("Sending EasyEffects command: {command:?}");
+
+    match command {
+        EasyEffectsCommand::SetBypass(bypass) => {
+            easyeffects(&["--bypass", if *bypass { "1" } else { "2" }]).await?;
+        }
+        EasyEffectsCommand::LoadPreset(preset) => {
+            easyeffects(&["--load-preset", preset]).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the EasyEffects CLI with the given arguments,
+/// returning its output.
+async fn easyeffects(args: &[&str]) -> Result<String> {
+    let output = Command::new("easyeffects").args(args).output().await?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(eyre!(
+            "easyeffects {} exited with {}",
+            args.join(" "),
+            output.status
+        ))
+    }
+}
+
+/// Parses the output of `easyeffects --bypass 3`,
+/// which is `1` while effects are bypassed.
+fn parse_bypass(output: &str) -> bool {
+    matches!(output.trim(), "1" | "true")
+}
+
+/// Parses the output presets from the output of `easyeffects --presets`,
+/// which lists each type's presets on a line, ie `Output Presets: Music,Voice,`.
+fn parse_presets(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .find_map(|line| line.strip_prefix("Output Presets:"))
+        .map(|presets| {
+            presets
+                .split(',')
+                .map(str::trim)
+                .filter(|preset| !preset.is_empty())
+                .map(ToString::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_presets() {
+        let output = "Output Presets: Music,Voice Boost,\nInput Presets: Noise Reduction,\n";
+        assert_eq!(parse_presets(output), ["Music", "Voice Boost"]);
+
+        assert!(parse_presets("Output Presets: \n").is_empty());
+        assert!(parse_presets("").is_empty());
+    }
+
+    #[test]
+    fn test_parse_bypass() {
+        assert!(parse_bypass("1\n"));
+        assert!(!parse_bypass("0\n"));
+    }
+}
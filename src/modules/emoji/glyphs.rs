@@ -0,0 +1,175 @@
+/// A small set of commonly used emoji, as glyph and name pairs,
+/// shown unless disabled with `builtin`.
+///
+/// Anything else, such as the full emoji set or Nerd Font icons,
+/// can be added from glyph files.
+pub const BUILTIN: &[(&str, &str)] = &[
+    ("😀", "grinning face"),
+    ("😃", "grinning face with big eyes"),
+    ("😄", "grinning face with smiling eyes"),
+    ("😁", "beaming face with smiling eyes"),
+    ("😆", "grinning squinting face"),
+    ("😅", "grinning face with sweat"),
+    ("🤣", "rolling on the floor laughing"),
+    ("😂", "face with tears of joy"),
+    ("🙂", "slightly smiling face"),
+    ("🙃", "upside-down face"),
+    ("😉", "winking face"),
+    ("😊", "smiling face with smiling eyes"),
+    ("😇", "smiling face with halo"),
+    ("🥰", "smiling face with hearts"),
+    ("😍", "smiling face with heart-eyes"),
+    ("🤩", "star-struck"),
+    ("😘", "face blowing a kiss"),
+    ("😋", "face savoring food"),
+    ("😛", "face with tongue"),
+    ("😜", "winking face with tongue"),
+    ("🤪", "zany face"),
+    ("🤗", "smiling face with open hands"),
+    ("🤭", "face with hand over mouth"),
+    ("🤫", "shushing face"),
+    ("🤔", "thinking face"),
+    ("🤐", "zipper-mouth face"),
+    ("😐", "neutral face"),
+    ("😑", "expressionless face"),
+    ("😶", "face without mouth"),
+    ("😏", "smirking face"),
+    ("😒", "unamused face"),
+    ("🙄", "face with rolling eyes"),
+    ("😬", "grimacing face"),
+    ("😌", "relieved face"),
+    ("😔", "pensive face"),
+    ("😪", "sleepy face"),
+    ("😴", "sleeping face"),
+    ("😷", "face with medical mask"),
+    ("🤒", "face with thermometer"),
+    ("🤢", "nauseated face"),
+    ("🤮", "face vomiting"),
+    ("🥵", "hot face"),
+    ("🥶", "cold face"),
+    ("🥴", "woozy face"),
+    ("😵", "face with crossed-out eyes"),
+    ("🤯", "exploding head"),
+    ("🥳", "partying face"),
+    ("😎", "smiling face with sunglasses"),
+    ("🤓", "nerd face"),
+    ("😕", "confused face"),
+    ("😟", "worried face"),
+    ("😮", "face with open mouth"),
+    ("😲", "astonished face"),
+    ("😳", "flushed face"),
+    ("🥺", "pleading face"),
+    ("😢", "crying face"),
+    ("😭", "loudly crying face"),
+    ("😱", "face screaming in fear"),
+    ("😩", "weary face"),
+    ("😫", "tired face"),
+    ("🥱", "yawning face"),
+    ("😤", "face with steam from nose"),
+    ("😡", "enraged face"),
+    ("😠", "angry face"),
+    ("🤬", "face with symbols on mouth"),
+    ("💀", "skull"),
+    ("💩", "pile of poo"),
+    ("🤡", "clown face"),
+    ("👻", "ghost"),
+    ("👽", "alien"),
+    ("🤖", "robot"),
+    ("🙈", "see-no-evil monkey"),
+    ("❤️", "red heart"),
+    ("🧡", "orange heart"),
+    ("💛", "yellow heart"),
+    ("💚", "green heart"),
+    ("💙", "blue heart"),
+    ("💜", "purple heart"),
+    ("🖤", "black heart"),
+    ("💔", "broken heart"),
+    ("💯", "hundred points"),
+    ("💥", "collision"),
+    ("💤", "zzz"),
+    ("👋", "waving hand"),
+    ("👌", "ok hand"),
+    ("✌️", "victory hand"),
+    ("🤞", "crossed fingers"),
+    ("🤘", "sign of the horns"),
+    ("👈", "backhand index pointing left"),
+    ("👉", "backhand index pointing right"),
+    ("👆", "backhand index pointing up"),
+    ("👇", "backhand index pointing down"),
+    ("👍", "thumbs up"),
+    ("👎", "thumbs down"),
+    ("✊", "raised fist"),
+    ("👏", "clapping hands"),
+    ("🙌", "raising hands"),
+    ("🙏", "folded hands"),
+    ("💪", "flexed biceps"),
+    ("👀", "eyes"),
+    ("🧠", "brain"),
+    ("🤷", "person shrugging"),
+    ("🤦", "person facepalming"),
+    ("🐛", "bug"),
+    ("🐶", "dog face"),
+    ("🐱", "cat face"),
+    ("🦊", "fox"),
+    ("🐧", "penguin"),
+    ("🦀", "crab"),
+    ("🌱", "seedling"),
+    ("🌈", "rainbow"),
+    ("☀️", "sun"),
+    ("🌙", "crescent moon"),
+    ("⭐", "star"),
+    ("🔥", "fire"),
+    ("💧", "droplet"),
+    ("❄️", "snowflake"),
+    ("⚡", "high voltage"),
+    ("☕", "hot beverage"),
+    ("🍺", "beer mug"),
+    ("🍕", "pizza"),
+    ("🎂", "birthday cake"),
+    ("🎉", "party popper"),
+    ("🎁", "wrapped gift"),
+    ("🏆", "trophy"),
+    ("🚀", "rocket"),
+    ("⏰", "alarm clock"),
+    ("⌛", "hourglass done"),
+    ("📌", "pushpin"),
+    ("📎", "paperclip"),
+    ("📝", "memo"),
+    ("📦", "package"),
+    ("🔒", "locked"),
+    ("🔑", "key"),
+    ("🔧", "wrench"),
+    ("🔨", "hammer"),
+    ("💡", "light bulb"),
+    ("💻", "laptop"),
+    ("📱", "mobile phone"),
+    ("🔔", "bell"),
+    ("🔗", "link"),
+    ("✅", "check mark button"),
+    ("✔️", "check mark"),
+    ("❌", "cross mark"),
+    ("❓", "red question mark"),
+    ("❗", "red exclamation mark"),
+    ("⚠️", "warning"),
+    ("🚫", "prohibited"),
+    ("➡️", "right arrow"),
+    ("⬅️", "left arrow"),
+    ("⬆️", "up arrow"),
+    ("⬇️", "down arrow"),
+    ("🔄", "counterclockwise arrows button"),
+    ("➕", "plus"),
+    ("➖", "minus"),
+    ("™️", "trade mark"),
+    ("©️", "copyright"),
+    ("°", "degree sign"),
+    ("€", "euro sign"),
+    ("£", "pound sign"),
+    ("—", "em dash"),
+    ("…", "horizontal ellipsis"),
+    ("→", "rightwards arrow"),
+    ("←", "leftwards arrow"),
+    ("×", "multiplication sign"),
+    ("±", "plus-minus sign"),
+    ("≠", "not equal to"),
+    ("≈", "almost equal to"),
+];
@@ -0,0 +1,637 @@
+mod glyphs;
+
+use self::glyphs::BUILTIN;
+use crate::clients::clipboard;
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::new_icon_button;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModulePopupParts, ModuleUpdateEvent, PopupButton,
+    WidgetContext,
+};
+use crate::{glib_recv, module_impl, rc_mut, send_async, spawn, spawn_blocking, try_send};
+use color_eyre::{Help, Report, Result};
+use glib::Propagation;
+use gtk::gdk::keys::constants as keys;
+use gtk::prelude::*;
+use gtk::{Button, Entry, FlowBox, Label, Orientation, SelectionMode};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::{debug, error, warn};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EmojiModule {
+    /// The icon to show on the bar widget button.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `icon:face-smile-symbolic`
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render the icon at.
+    /// Note this only applies to image-type icons.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `32`
+    icon_size: Option<i32>,
+
+    /// Whether to include the built-in set of common emoji.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    builtin: bool,
+
+    /// Absolute paths to files of additional glyphs, such as Nerd Font icons.
+    /// Each line is a glyph followed by its name, ie `😀 grinning face`.
+    /// Empty lines and lines starting with `#` are skipped.
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { files = [ "/home/jake/.config/ironbar/nerd-font.txt" ] }
+    /// ```
+    #[serde(default)]
+    files: Vec<PathBuf>,
+
+    /// The number of glyphs on each row of the popup grid.
+    ///
+    /// **Default**: `8`
+    #[serde(default = "default_columns")]
+    columns: u32,
+
+    /// The maximum number of glyphs to show in the popup.
+    ///
+    /// **Default**: `64`
+    #[serde(default = "default_max_results")]
+    max_results: usize,
+
+    /// The number of recently picked glyphs to remember.
+    /// These are shown first while the search is empty,
+    /// and are kept across restarts.
+    ///
+    /// **Default**: `16`
+    #[serde(default = "default_max_recent")]
+    max_recent: usize,
+
+    /// Whether to also type the picked glyph into the focused window using `wtype`,
+    /// as well as copying it to the clipboard.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    type_glyph: bool,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon() -> String {
+    String::from("icon:face-smile-symbolic")
+}
+
+const fn default_icon_size() -> i32 {
+    32
+}
+
+const fn default_columns() -> u32 {
+    8
+}
+
+const fn default_max_results() -> usize {
+    64
+}
+
+const fn default_max_recent() -> usize {
+    16
+}
+
+/// A character or sequence which can be picked, and the name it is searched by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Glyph {
+    pub glyph: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum EmojiUpdate {
+    /// Every glyph which can be picked.
+    Glyphs(Arc<[Glyph]>),
+    /// Recently picked glyphs, most recent first.
+    Recent(Vec<String>),
+}
+
+impl Module<Button> for EmojiModule {
+    type SendMessage = EmojiUpdate;
+    /// The picked glyph.
+    type ReceiveMessage = String;
+
+    module_impl!("emoji");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let clipboard = context.client::<clipboard::Client>();
+
+        let builtin = self.builtin;
+        let files = self.files.clone();
+        let max_recent = self.max_recent;
+        let type_glyph = self.type_glyph;
+
+        spawn(async move {
+            let glyphs = spawn_blocking(move || load_glyphs(builtin, &files)).await?;
+            send_async!(
+                tx,
+                ModuleUpdateEvent::Update(EmojiUpdate::Glyphs(glyphs.into()))
+            );
+
+            let path = history_path();
+            let mut recent = match path.clone() {
+                Some(path) => spawn_blocking(move || load_recent(&path)).await?,
+                None => vec![],
+            };
+            send_async!(
+                tx,
+                ModuleUpdateEvent::Update(EmojiUpdate::Recent(recent.clone()))
+            );
+
+            while let Some(glyph) = rx.recv().await {
+                send_async!(tx, ModuleUpdateEvent::ClosePopup);
+                clipboard.copy_text(glyph.clone());
+
+                if type_glyph {
+                    type_text(&glyph).await;
+                }
+
+                push_recent(&mut recent, glyph, max_recent);
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(EmojiUpdate::Recent(recent.clone()))
+                );
+
+                if let Some(path) = path.clone() {
+                    let recent = recent.clone();
+                    spawn_blocking(move || {
+                        if let Err(err) = save_recent(&path, &recent) {
+                            error!("{:?}", err.wrap_err("Failed to save recent emoji"));
+                        }
+                    });
+                }
+            }
+
+            Ok::<_, Report>(())
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = new_icon_button(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
+        button.add_class("btn");
+
+        {
+            let tx = context.tx.clone();
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button])
+            // the search entry needs keyboard input
+            .map(|parts| ModulePopupParts {
+                keyboard: true,
+                ..parts
+            });
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let entry = Entry::new();
+        entry.add_class("entry");
+        container.add(&entry);
+
+        let columns = self.columns.max(1);
+
+        let grid = FlowBox::new();
+        grid.add_class("glyphs");
+        grid.set_selection_mode(SelectionMode::Single);
+        grid.set_min_children_per_line(columns);
+        grid.set_max_children_per_line(columns);
+        grid.set_homogeneous(true);
+        container.add(&grid);
+
+        let glyphs = rc_mut!(Arc::<[Glyph]>::from([]));
+        let recent = rc_mut!(vec![]);
+        let shown = rc_mut!(Vec::<Glyph>::new());
+
+        let render = {
+            let entry = entry.clone();
+            let grid = grid.clone();
+            let glyphs = glyphs.clone();
+            let recent = recent.clone();
+            let shown = shown.clone();
+            let tx = tx.clone();
+            let max_results = self.max_results;
+
+            Rc::new(move || {
+                let results = search(
+                    &glyphs.borrow(),
+                    &recent.borrow(),
+                    &entry.text(),
+                    max_results,
+                );
+
+                for child in grid.children() {
+                    grid.remove(&child);
+                }
+
+                for glyph in &results {
+                    grid.add(&build_glyph(glyph, &tx));
+                }
+
+                grid.show_all();
+
+                if let Some(child) = grid.child_at_index(0) {
+                    grid.select_child(&child);
+                }
+
+                *shown.borrow_mut() = results;
+            })
+        };
+
+        {
+            let render = render.clone();
+            glib_recv!(rx, update => {
+                match update {
+                    EmojiUpdate::Glyphs(new_glyphs) => *glyphs.borrow_mut() = new_glyphs,
+                    EmojiUpdate::Recent(new_recent) => *recent.borrow_mut() = new_recent,
+                }
+
+                render();
+            });
+        }
+
+        entry.connect_changed(move |_| render());
+
+        // start with a fresh search each time the popup opens
+        {
+            let entry = entry.clone();
+            container.connect_map(move |_| {
+                entry.set_text("");
+                entry.grab_focus();
+            });
+        }
+
+        {
+            let grid = grid.clone();
+            let shown = shown.clone();
+
+            entry.connect_activate(move |_| {
+                let selected = grid
+                    .selected_children()
+                    .first()
+                    .and_then(|child| usize::try_from(child.index()).ok())
+                    .and_then(|index| shown.borrow().get(index).cloned());
+
+                if let Some(glyph) = selected {
+                    try_send!(tx, glyph.glyph);
+                }
+            });
+        }
+
+        {
+            let ui_tx = context.tx.clone();
+            let columns = columns as i32;
+
+            entry.connect_key_press_event(move |_, event| {
+                let key = event.keyval();
+
+                if key == keys::Escape {
+                    try_send!(ui_tx, ModuleUpdateEvent::ClosePopup);
+                    return Propagation::Stop;
+                }
+
+                let offset = if key == keys::Left {
+                    -1
+                } else if key == keys::Right {
+                    1
+                } else if key == keys::Up {
+                    -columns
+                } else if key == keys::Down {
+                    columns
+                } else {
+                    return Propagation::Proceed;
+                };
+
+                let index = grid
+                    .selected_children()
+                    .first()
+                    .map_or(0, |child| child.index() + offset);
+
+                if let Some(child) = grid.child_at_index(index) {
+                    grid.select_child(&child);
+                }
+
+                Propagation::Stop
+            });
+        }
+
+        container.show_all();
+
+        Some(container)
+    }
+}
+
+fn build_glyph(glyph: &Glyph, tx: &mpsc::Sender<String>) -> Button {
+    let button = Button::new();
+    button.add_class("glyph");
+    button.set_relief(gtk::ReliefStyle::None);
+    if !glyph.name.is_empty() {
+        button.set_tooltip_text(Some(&glyph.name));
+    }
+
+    let label = Label::new(Some(&glyph.glyph));
+    button.add(&label);
+
+    let tx = tx.clone();
+    let glyph = glyph.glyph.clone();
+    button.connect_clicked(move |_| {
+        try_send!(tx, glyph.clone());
+    });
+
+    button
+}
+
+/// Gets the built-in glyphs if enabled, followed by the glyphs from each file.
+fn load_glyphs(builtin: bool, files: &[PathBuf]) -> Vec<Glyph> {
+    let mut glyphs = if builtin {
+        BUILTIN
+            .iter()
+            .map(|(glyph, name)| Glyph {
+                glyph: (*glyph).to_string(),
+                name: (*name).to_string(),
+            })
+            .collect()
+    } else {
+        vec![]
+    };
+
+    for file in files {
+        match fs::read_to_string(file) {
+            Ok(contents) => glyphs.extend(parse_glyphs(&contents)),
+            Err(err) => error!(
+                "{:?}",
+                Report::new(err).wrap_err(format!("Failed to read '{}'", file.display()))
+            ),
+        }
+    }
+
+    glyphs
+}
+
+/// Parses a glyph file, where each line is a glyph followed by its name.
+fn parse_glyphs(contents: &str) -> impl Iterator<Item = Glyph> + '_ {
+    contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (glyph, name) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            Glyph {
+                glyph: glyph.to_string(),
+                name: name.trim().to_string(),
+            }
+        })
+}
+
+/// Gets the glyphs matching the query, best first.
+///
+/// Every word in the query must appear in the name.
+/// Names starting with the query rank above names with a word starting with it,
+/// which rank above any other match. Recently picked glyphs rank higher within each.
+///
+/// While the query is empty, recently picked glyphs are shown first, followed by the rest.
+fn search(glyphs: &[Glyph], recent: &[String], query: &str, max_results: usize) -> Vec<Glyph> {
+    let query = query.trim().to_lowercase();
+    let is_recent = |glyph: &Glyph| recent.contains(&glyph.glyph);
+
+    if query.is_empty() {
+        // recent glyphs may have since been removed from the files
+        let recent = recent.iter().map(|recent| {
+            glyphs
+                .iter()
+                .find(|glyph| &glyph.glyph == recent)
+                .cloned()
+                .unwrap_or_else(|| Glyph {
+                    glyph: recent.clone(),
+                    name: String::new(),
+                })
+        });
+
+        let rest = glyphs.iter().filter(|glyph| !is_recent(glyph)).cloned();
+
+        return recent.chain(rest).take(max_results).collect();
+    }
+
+    let words = query.split_whitespace().collect::<Vec<_>>();
+
+    let mut matches = glyphs
+        .iter()
+        .filter_map(|glyph| {
+            let name = glyph.name.to_lowercase();
+            if !words.iter().all(|word| name.contains(word)) {
+                return None;
+            }
+
+            let rank = if name.starts_with(&query) {
+                0
+            } else if name
+                .split(|c: char| !c.is_alphanumeric())
+                .any(|word| word.starts_with(words[0]))
+            {
+                1
+            } else {
+                2
+            };
+
+            Some(((rank, !is_recent(glyph)), glyph))
+        })
+        .collect::<Vec<_>>();
+
+    // stable, so glyphs of the same rank keep their file order
+    matches.sort_by_key(|(rank, _)| *rank);
+
+    matches
+        .into_iter()
+        .take(max_results)
+        .map(|(_, glyph)| glyph.clone())
+        .collect()
+}
+
+/// Moves the glyph to the front of the recently picked glyphs.
+fn push_recent(recent: &mut Vec<String>, glyph: String, max_recent: usize) {
+    recent.retain(|recent| recent != &glyph);
+    recent.insert(0, glyph);
+    recent.truncate(max_recent);
+}
+
+/// Types the glyph into the focused window.
+async fn type_text(glyph: &str) {
+    // the popup must close first, so that focus returns to the previous window
+    sleep(Duration::from_millis(200)).await;
+
+    debug!("Typing glyph with wtype");
+
+    match Command::new("wtype").arg("--").arg(glyph).status().await {
+        Ok(status) if !status.success() => {
+            error!("Failed to type glyph: wtype exited with {status}")
+        }
+        Ok(_) => {}
+        Err(err) => error!(
+            "{:?}",
+            Report::new(err)
+                .wrap_err("Failed to type glyph")
+                .suggestion("Check wtype is installed")
+        ),
+    }
+}
+
+/// Gets the path the recently picked glyphs are saved to.
+fn history_path() -> Option<PathBuf> {
+    dirs::state_dir().map(|dir| dir.join("ironbar").join("emoji_history"))
+}
+
+/// Loads the recently picked glyphs, one per line.
+fn load_recent(path: &Path) -> Vec<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => contents
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(ToString::to_string)
+            .collect(),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => vec![],
+        Err(err) => {
+            warn!(
+                "Failed to read recent emoji from '{}': {err}",
+                path.display()
+            );
+            vec![]
+        }
+    }
+}
+
+fn save_recent(path: &Path, recent: &[String]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, recent.join("\n"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph(glyph: &str, name: &str) -> Glyph {
+        Glyph {
+            glyph: glyph.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_glyphs() {
+        let contents = "# nerd font\n\n\u{f02dc} nf-md-home\n😀 grinning face\n★";
+
+        assert_eq!(
+            parse_glyphs(contents).collect::<Vec<_>>(),
+            vec![
+                glyph("\u{f02dc}", "nf-md-home"),
+                glyph("😀", "grinning face"),
+                glyph("★", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        let glyphs = vec![
+            glyph("😹", "cat with tears of joy"),
+            glyph("🐱", "cat face"),
+            glyph("😂", "face with tears of joy"),
+            glyph("🐈", "cat"),
+        ];
+
+        let results = search(&glyphs, &[], "cat", 10);
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].glyph, "😹");
+
+        let recent = vec![String::from("🐈")];
+        let results = search(&glyphs, &recent, "cat", 10);
+        assert_eq!(results[0].glyph, "🐈");
+
+        let results = search(&glyphs, &[], "tears joy", 10);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].glyph, "😹");
+    }
+
+    #[test]
+    fn test_search_empty_shows_recent_first() {
+        let glyphs = vec![glyph("😀", "grinning face"), glyph("🐈", "cat")];
+        let recent = vec![String::from("🐈"), String::from("★")];
+
+        let results = search(&glyphs, &recent, "", 10)
+            .into_iter()
+            .map(|glyph| glyph.glyph)
+            .collect::<Vec<_>>();
+
+        assert_eq!(results, vec!["🐈", "★", "😀"]);
+    }
+
+    #[test]
+    fn test_push_recent() {
+        let mut recent = vec![String::from("a"), String::from("b"), String::from("c")];
+
+        push_recent(&mut recent, String::from("c"), 3);
+        assert_eq!(recent, vec!["c", "a", "b"]);
+
+        push_recent(&mut recent, String::from("d"), 3);
+        assert_eq!(recent, vec!["d", "c", "a"]);
+    }
+}
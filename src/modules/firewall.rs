@@ -0,0 +1,351 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::script::Script;
+use crate::{glib_recv, module_impl, send_async, spawn};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Image, Orientation};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::{debug, error};
+use zbus::dbus_proxy;
+use zbus::fdo::DBusProxy;
+use zbus::names::BusName;
+
+#[dbus_proxy(
+    default_service = "org.fedoraproject.FirewallD1",
+    interface = "org.fedoraproject.FirewallD1",
+    default_path = "/org/fedoraproject/FirewallD1"
+)]
+trait FirewallD {
+    #[dbus_proxy(property)]
+    fn state(&self) -> zbus::Result<String>;
+}
+
+const FIREWALLD_BUS_NAME: &str = "org.fedoraproject.FirewallD1";
+const UFW_CONFIG_PATH: &str = "/etc/ufw/ufw.conf";
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct FirewallModule {
+    /// The firewall to check the status of.
+    /// See [below](#backends) for details on each.
+    ///
+    /// **Valid options**: `auto`, `firewalld`, `ufw`, `nftables`, `command`
+    /// <br>
+    /// **Default**: `auto`
+    #[serde(default)]
+    backend: FirewallBackend,
+
+    /// The shell command to run when using the `command` backend.
+    /// The firewall is considered active if the command exits successfully.
+    ///
+    /// **Default**: `null`
+    command: Option<String>,
+
+    /// The icon to show while the firewall is active.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `icon:security-high-symbolic`
+    #[serde(default = "default_icon_active")]
+    icon_active: String,
+
+    /// The icon to show while the firewall is disabled or not found.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `icon:security-low-symbolic`
+    #[serde(default = "default_icon_inactive")]
+    icon_inactive: String,
+
+    /// The size to render the icon at.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// Whether to hide the widget while the firewall is active,
+    /// so that it is only shown as a warning.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    hide_when_active: bool,
+
+    /// Time in seconds between checking the firewall status.
+    ///
+    /// **Default**: `10`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum FirewallBackend {
+    /// Uses the first of firewalld, ufw and nftables found on the system.
+    #[default]
+    Auto,
+    Firewalld,
+    Ufw,
+    Nftables,
+    /// Runs the configured `command`.
+    Command,
+}
+
+impl Display for FirewallBackend {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Auto => "auto",
+                Self::Firewalld => "firewalld",
+                Self::Ufw => "ufw",
+                Self::Nftables => "nftables",
+                Self::Command => "command",
+            }
+        )
+    }
+}
+
+fn default_icon_active() -> String {
+    String::from("icon:security-high-symbolic")
+}
+
+fn default_icon_inactive() -> String {
+    String::from("icon:security-low-symbolic")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+const fn default_interval() -> u64 {
+    10
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FirewallStatus {
+    /// The firewall which was checked.
+    /// `None` if no supported firewall was found.
+    backend: Option<FirewallBackend>,
+    active: bool,
+}
+
+impl FirewallStatus {
+    fn tooltip(self) -> String {
+        match (self.backend, self.active) {
+            (Some(backend), true) => format!("Firewall active ({backend})"),
+            (Some(backend), false) => format!("Firewall disabled ({backend})"),
+            (None, _) => String::from("No firewall found"),
+        }
+    }
+}
+
+impl FirewallModule {
+    async fn check(&self, dbus: &zbus::Connection) -> FirewallStatus {
+        let backend = match self.backend {
+            FirewallBackend::Auto => detect_backend(dbus).await,
+            backend => Some(backend),
+        };
+
+        let active = match backend {
+            Some(FirewallBackend::Firewalld) => is_firewalld_running(dbus).await,
+            Some(FirewallBackend::Ufw) => is_ufw_enabled(),
+            Some(FirewallBackend::Nftables) => is_nftables_active().await,
+            Some(FirewallBackend::Command) => match &self.command {
+                Some(command) => run_check_command(command).await,
+                None => {
+                    error!("The `command` firewall backend requires `command` to be set");
+                    false
+                }
+            },
+            Some(FirewallBackend::Auto) | None => false,
+        };
+
+        FirewallStatus { backend, active }
+    }
+}
+
+impl Module<gtk::Box> for FirewallModule {
+    type SendMessage = FirewallStatus;
+    type ReceiveMessage = ();
+
+    module_impl!("firewall");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let module = self.clone();
+        let interval = Duration::from_secs(self.interval);
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            let dbus = match Box::pin(zbus::Connection::system()).await {
+                Ok(dbus) => dbus,
+                Err(err) => {
+                    error!("Failed to connect to system bus: {err:?}");
+                    return;
+                }
+            };
+
+            let mut previous = None;
+
+            loop {
+                let status = module.check(&dbus).await;
+
+                if previous != Some(status) {
+                    debug!("Firewall status: {status:?}");
+                    previous = Some(status);
+                    send_async!(tx, ModuleUpdateEvent::Update(status));
+                }
+
+                sleep(interval).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let container = gtk::Box::new(Orientation::Horizontal, 0);
+
+        let icon = Image::new();
+        icon.add_class("icon");
+        container.add(&icon);
+
+        let icon_theme = info.icon_theme.clone();
+        let icon_size = info.icon_size(self.icon_size, default_icon_size());
+
+        {
+            let container = container.clone();
+
+            glib_recv!(context.subscribe(), status => {
+                let icon_name = if status.active {
+                    &self.icon_active
+                } else {
+                    &self.icon_inactive
+                };
+
+                ImageProvider::parse(icon_name, &icon_theme, false, icon_size)
+                    .map(|provider| provider.load_into_image(icon.clone()));
+
+                container.set_tooltip_text(Some(&status.tooltip()));
+
+                if status.active {
+                    container.style_context().remove_class("warning");
+                } else {
+                    container.add_class("warning");
+                }
+
+                container.set_visible(!(status.active && self.hide_when_active));
+            });
+        }
+
+        Ok(ModuleParts::new(container, None))
+    }
+}
+
+/// Finds the first supported firewall present on the system.
+async fn detect_backend(dbus: &zbus::Connection) -> Option<FirewallBackend> {
+    let name = BusName::try_from(FIREWALLD_BUS_NAME).expect("to be valid bus name");
+    let firewalld = match DBusProxy::new(dbus).await {
+        Ok(proxy) => proxy.name_has_owner(name).await.unwrap_or_default(),
+        Err(_) => false,
+    };
+
+    if firewalld {
+        Some(FirewallBackend::Firewalld)
+    } else if Path::new(UFW_CONFIG_PATH).exists() {
+        Some(FirewallBackend::Ufw)
+    } else if Command::new("nft").arg("--version").output().await.is_ok() {
+        Some(FirewallBackend::Nftables)
+    } else {
+        None
+    }
+}
+
+async fn is_firewalld_running(dbus: &zbus::Connection) -> bool {
+    let state = match FirewallDProxy::new(dbus).await {
+        Ok(proxy) => proxy.state().await,
+        Err(err) => Err(err),
+    };
+
+    match state {
+        Ok(state) => state == "RUNNING",
+        Err(err) => {
+            debug!("Failed to get firewalld state: {err:?}");
+            false
+        }
+    }
+}
+
+/// Checks whether ufw is set to be enabled.
+/// Its live status can only be read as root, so the config file is used instead.
+fn is_ufw_enabled() -> bool {
+    match std::fs::read_to_string(UFW_CONFIG_PATH) {
+        Ok(config) => parse_ufw_enabled(&config),
+        Err(err) => {
+            debug!("Failed to read ufw config: {err:?}");
+            false
+        }
+    }
+}
+
+/// Checks whether the `nftables` service, which loads the default ruleset, is active.
+/// Listing the ruleset directly requires root.
+async fn is_nftables_active() -> bool {
+    Command::new("systemctl")
+        .args(["is-active", "--quiet", "nftables.service"])
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+async fn run_check_command(command: &str) -> bool {
+    match Script::from(command).get_output(None).await {
+        Ok((_, success)) => success,
+        Err(err) => {
+            error!("Failed to run firewall check command: {err:?}");
+            false
+        }
+    }
+}
+
+/// Gets the value of `ENABLED` from the contents of `ufw.conf`.
+fn parse_ufw_enabled(config: &str) -> bool {
+    config
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("ENABLED="))
+        .last()
+        .is_some_and(|value| value.trim().trim_matches('"').eq_ignore_ascii_case("yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_ufw_enabled() {
+        assert!(parse_ufw_enabled("# comment\nENABLED=yes\nLOGLEVEL=low\n"));
+        assert!(!parse_ufw_enabled("ENABLED=no\n"));
+        assert!(!parse_ufw_enabled("# ENABLED=yes\n"));
+        assert!(!parse_ufw_enabled(""));
+    }
+}
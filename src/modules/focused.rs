@@ -1,5 +1,6 @@
 use crate::clients::wayland::{self, ToplevelEvent};
 use crate::config::{CommonConfig, TruncateMode};
+use crate::dynamic_value::dynamic_string;
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::ImageProvider;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
@@ -27,9 +28,30 @@ pub struct FocusedModule {
 
     /// Icon size in pixels.
     ///
-    /// **Default**: `32`
-    #[serde(default = "default_icon_size")]
-    icon_size: i32,
+    /// **Default**: The bar's `icon_size` if set, otherwise `32`
+    icon_size: Option<i32>,
+
+    /// Text to show in place of the title when no window is focused.
+    /// This is a [dynamic string](dynamic-values#dynamic-string),
+    /// so can be used to show the current workspace name via a script or ironvar.
+    ///
+    /// Leave unset to show nothing.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { empty_text = "Desktop" }
+    /// ```
+    empty_text: Option<String>,
+
+    /// Whether to hide the module entirely when no window is focused.
+    /// Takes precedence over `empty_text`.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    hide_when_empty: bool,
 
     // -- common --
     /// See [truncate options](module-level-options#truncate-mode).
@@ -47,7 +69,9 @@ impl Default for FocusedModule {
         Self {
             show_icon: crate::config::default_true(),
             show_title: crate::config::default_true(),
-            icon_size: default_icon_size(),
+            icon_size: None,
+            empty_text: None,
+            hide_when_empty: false,
             truncate: None,
             common: Some(CommonConfig::default()),
         }
@@ -88,6 +112,8 @@ impl Module<gtk::Box> for FocusedModule {
                     tx,
                     ModuleUpdateEvent::Update(Some((focused.title.clone(), focused.app_id)))
                 );
+            } else {
+                try_send!(tx, ModuleUpdateEvent::Update(None));
             };
 
             while let Ok(event) = wlrx.recv().await {
@@ -150,12 +176,30 @@ impl Module<gtk::Box> for FocusedModule {
 
         container.add(&label);
 
+        let empty_label = Label::new(None);
+        empty_label.add_class("empty");
+        empty_label.set_no_show_all(true);
+
+        if let Some(empty_text) = &self.empty_text {
+            let empty_label = empty_label.clone();
+            dynamic_string(empty_text, move |string| {
+                empty_label.set_label(&string);
+            });
+
+            container.add(&empty_label);
+        }
+
         {
+            let container = container.clone();
             let icon_theme = icon_theme.clone();
+            let icon_size = info.icon_size(self.icon_size, default_icon_size());
             glib_recv!(context.subscribe(), data => {
                 if let Some((name, id)) = data {
+                    container.show();
+                    empty_label.hide();
+
                     if self.show_icon {
-                        match ImageProvider::parse(&id, &icon_theme, true, self.icon_size)
+                        match ImageProvider::parse(&id, &icon_theme, true, icon_size)
                             .map(|image| image.load_into_image(icon.clone()))
                         {
                             Some(Ok(())) => icon.show(),
@@ -170,6 +214,12 @@ impl Module<gtk::Box> for FocusedModule {
                 } else {
                     icon.hide();
                     label.hide();
+
+                    if self.hide_when_empty {
+                        container.hide();
+                    } else if self.empty_text.is_some() {
+                        empty_label.show();
+                    }
                 }
             });
         }
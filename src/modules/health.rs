@@ -0,0 +1,55 @@
+//! Tracks whether each module started successfully,
+//! so that failed modules can be reported over IPC.
+
+use glib::{IsA, WeakRef};
+use gtk::prelude::*;
+use gtk::Widget;
+use std::cell::RefCell;
+
+struct Registration {
+    bar: String,
+    name: String,
+    widget: WeakRef<Widget>,
+    /// The error the module failed with, if any.
+    error: Option<String>,
+}
+
+thread_local! {
+    /// Widgets can only be referenced from the main thread,
+    /// which is also where IPC commands are handled.
+    static REGISTRY: RefCell<Vec<Registration>> = const { RefCell::new(vec![]) };
+}
+
+/// Registers a module instance,
+/// along with the error it failed with, if any.
+pub fn register(bar: &str, name: &str, widget: &impl IsA<Widget>, error: Option<String>) {
+    REGISTRY.with_borrow_mut(|registry| {
+        // clear out modules from closed bars
+        registry.retain(|registration| registration.widget.upgrade().is_some());
+
+        registry.push(Registration {
+            bar: bar.to_string(),
+            name: name.to_string(),
+            widget: widget.upcast_ref::<Widget>().downgrade(),
+            error,
+        });
+    });
+}
+
+/// Gets the health of every module, one per line,
+/// such as `main/clock: ok` or `main/tray: error: <message>`.
+pub fn report() -> String {
+    REGISTRY.with_borrow(|registry| {
+        registry
+            .iter()
+            .filter(|registration| registration.widget.upgrade().is_some())
+            .map(|registration| match &registration.error {
+                Some(error) => {
+                    format!("{}/{}: error: {error}", registration.bar, registration.name)
+                }
+                None => format!("{}/{}: ok", registration.bar, registration.name),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    })
+}
@@ -0,0 +1,286 @@
+use crate::clients::homeassistant::{EntityState, ServiceCall};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, rc_mut, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct HomeAssistantModule {
+    /// The base URL of your Home Assistant instance.
+    ///
+    /// **Required**
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { url = "http://homeassistant.local:8123" }
+    /// ```
+    url: String,
+
+    /// A long-lived access token.
+    /// These can be created from the bottom of your Home Assistant profile page.
+    ///
+    /// **Required**
+    token: String,
+
+    /// The list of entities to display.
+    /// See [entities](#entities).
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    entities: Vec<EntityConfig>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct EntityConfig {
+    /// The ID of the entity to display.
+    ///
+    /// **Required**
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { entity = "light.living_room" }
+    /// ```
+    entity: String,
+
+    /// The format string to use for the entity label.
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `{state}{unit}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The service to call when the entity is clicked,
+    /// in the form `domain.service`.
+    ///
+    /// If not set, toggleable entities such as lights, switches and locks
+    /// are toggled. Other entities do nothing when clicked.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { action = "scene.turn_on" }
+    /// ```
+    action: Option<String>,
+}
+
+fn default_format() -> String {
+    String::from("{state}{unit}")
+}
+
+/// Domains which support the generic `toggle` service.
+const TOGGLE_DOMAINS: &[&str] = &[
+    "automation",
+    "cover",
+    "fan",
+    "input_boolean",
+    "light",
+    "media_player",
+    "switch",
+];
+
+impl EntityConfig {
+    /// Gets the service call to make when the entity is clicked,
+    /// based on the configured action or the entity's domain and current state.
+    fn service_call(&self, state: &str) -> Option<ServiceCall> {
+        let (domain, service) = match &self.action {
+            Some(action) => {
+                let (domain, service) = action.split_once('.')?;
+                (domain.to_string(), service.to_string())
+            }
+            None => {
+                let (domain, _) = self.entity.split_once('.')?;
+
+                let service = match domain {
+                    "lock" if state == "locked" => "unlock",
+                    "lock" => "lock",
+                    domain if TOGGLE_DOMAINS.contains(&domain) => "toggle",
+                    _ => return None,
+                };
+
+                (domain.to_string(), service.to_string())
+            }
+        };
+
+        Some(ServiceCall {
+            domain,
+            service,
+            entity_id: self.entity.clone(),
+        })
+    }
+}
+
+impl Module<gtk::Box> for HomeAssistantModule {
+    type SendMessage = EntityState;
+    type ReceiveMessage = ServiceCall;
+
+    module_impl!("homeassistant");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context
+            .ironbar
+            .clients
+            .borrow_mut()
+            .homeassistant(&self.url, &self.token);
+
+        let entity_ids = self
+            .entities
+            .iter()
+            .map(|entity| entity.entity.clone())
+            .collect::<HashSet<_>>();
+
+        {
+            let client = client.clone();
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                let mut rx = client.subscribe();
+
+                for entity_id in &entity_ids {
+                    if let Some(state) = client.state(entity_id) {
+                        send_async!(tx, ModuleUpdateEvent::Update(state));
+                    }
+                }
+
+                while let Ok(state) = rx.recv().await {
+                    if entity_ids.contains(&state.entity_id) {
+                        send_async!(tx, ModuleUpdateEvent::Update(state));
+                    }
+                }
+            });
+        }
+
+        spawn(async move {
+            while let Some(call) = rx.recv().await {
+                client.call_service(call).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let re = Regex::new(r"\{(?:state|name|unit|attr:([^}]+))}")?;
+
+        let container = gtk::Box::new(info.bar_position.orientation(), 0);
+
+        let mut widgets = HashMap::new();
+
+        for entity in self.entities {
+            let button = Button::new();
+            button.add_class("entity");
+
+            if let Some((domain, _)) = entity.entity.split_once('.') {
+                button.add_class(domain);
+            }
+
+            let label = Label::builder()
+                .use_markup(true)
+                .angle(info.bar_position.get_angle())
+                .build();
+            label.add_class("label");
+
+            button.add(&label);
+            container.add(&button);
+
+            let state = rc_mut!(String::new());
+
+            {
+                let tx = context.controller_tx.clone();
+                let entity = entity.clone();
+                let state = state.clone();
+
+                button.connect_clicked(move |_| {
+                    if let Some(call) = entity.service_call(&state.borrow()) {
+                        try_send!(tx, call);
+                    }
+                });
+            }
+
+            widgets.insert(
+                entity.entity.clone(),
+                EntityWidget {
+                    button,
+                    label,
+                    format: entity.format,
+                    state,
+                },
+            );
+        }
+
+        glib_recv!(context.subscribe(), entity => {
+            if let Some(widget) = widgets.get(&entity.entity_id) {
+                widget.update(&entity, &re);
+            }
+        });
+
+        Ok(ModuleParts {
+            widget: container,
+            popup: None,
+        })
+    }
+}
+
+struct EntityWidget {
+    button: Button,
+    label: Label,
+    format: String,
+    state: Rc<RefCell<String>>,
+}
+
+impl EntityWidget {
+    fn update(&self, entity: &EntityState, re: &Regex) {
+        let old_state = self.state.replace(entity.state.clone());
+
+        let style = self.button.style_context();
+        style.remove_class(&state_class(&old_state));
+        style.add_class(&state_class(&entity.state));
+
+        // replace all tokens in one pass, so values containing tokens are not expanded
+        let text = re.replace_all(&self.format, |caps: &Captures| {
+            let value = match &caps[0] {
+                "{state}" => entity.state.clone(),
+                "{name}" => entity.friendly_name().to_string(),
+                "{unit}" => entity.attribute("unit_of_measurement").unwrap_or_default(),
+                _ => entity.attribute(&caps[1]).unwrap_or_default(),
+            };
+
+            glib::markup_escape_text(&value).to_string()
+        });
+
+        self.label.set_markup(&text);
+    }
+}
+
+/// Gets the CSS class used to represent an entity state.
+fn state_class(state: &str) -> String {
+    format!("state-{}", state.replace(' ', "-"))
+}
@@ -0,0 +1,485 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::script::Script;
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::{Report, Result};
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation, Spinner};
+use serde::Deserialize;
+use std::cell::Cell;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+/// How often the elapsed time is updated while a command is running.
+const TICK: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct JobModule {
+    /// The systemd user unit to watch, such as `backup.service`.
+    /// The job is running while the unit is activating, active or deactivating.
+    /// Clicking the widget starts the unit.
+    ///
+    /// **Default**: `null`
+    unit: Option<String>,
+
+    /// Shell command to run as the job when the widget is clicked.
+    /// Ignored if `unit` is set.
+    ///
+    /// **Default**: `null`
+    command: Option<String>,
+
+    /// Format string for the label while the job is running, and after it finishes.
+    /// `{elapsed}` is replaced with the time the job has been running for.
+    ///
+    /// **Default**: `{elapsed}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Text to show on the label while no job is running.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    idle_label: String,
+
+    /// Time in seconds to keep the `.success` or `.failure` class
+    /// after the job finishes, before returning to idle.
+    ///
+    /// **Default**: `10`
+    #[serde(default = "default_flash_duration")]
+    flash_duration: u64,
+
+    /// Time in seconds between checking the state of `unit`.
+    ///
+    /// **Default**: `1`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{elapsed}")
+}
+
+const fn default_flash_duration() -> u64 {
+    10
+}
+
+const fn default_interval() -> u64 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    Running {
+        elapsed: Duration,
+    },
+    Finished {
+        elapsed: Duration,
+        success: bool,
+        /// Why the job failed, such as `exit status 2`.
+        reason: Option<String>,
+    },
+}
+
+/// The state of a systemd unit, as read from `systemctl show`.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct UnitState {
+    running: bool,
+    /// The unit's `Result`, such as `success` or `exit-code`.
+    result: String,
+    exit_status: Option<i32>,
+    started: Option<SystemTime>,
+    finished: Option<SystemTime>,
+}
+
+impl UnitState {
+    fn success(&self) -> bool {
+        self.result == "success"
+    }
+
+    fn failure_reason(&self) -> Option<String> {
+        match (self.result.as_str(), self.exit_status) {
+            ("success", _) => None,
+            ("exit-code", Some(status)) => Some(format!("exit status {status}")),
+            ("signal" | "core-dump", Some(signal)) => Some(format!("killed by signal {signal}")),
+            (result, _) => Some(result.to_string()),
+        }
+    }
+}
+
+impl Module<Button> for JobModule {
+    type SendMessage = JobStatus;
+    type ReceiveMessage = ();
+
+    module_impl!("job");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+
+        match (self.unit.clone(), self.command.clone()) {
+            (Some(unit), _) => {
+                let interval = Duration::from_secs(self.interval);
+
+                {
+                    let unit = unit.clone();
+                    spawn(async move {
+                        while rx.recv().await.is_some() {
+                            start_unit(&unit).await;
+                        }
+                    });
+                }
+
+                spawn(watch_unit(unit, interval, tx));
+            }
+            (None, Some(command)) => {
+                spawn(async move {
+                    while rx.recv().await.is_some() {
+                        run_command(&command, &tx).await;
+
+                        // clicks while the job was running do not start it again
+                        while rx.try_recv().is_ok() {}
+                    }
+                });
+            }
+            (None, None) => return Err(Report::msg("Either `unit` or `command` must be set")),
+        }
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let container = gtk::Box::new(Orientation::Horizontal, 5);
+        button.add(&container);
+
+        let spinner = Spinner::new();
+        spinner.add_class("spinner");
+        spinner.set_no_show_all(true);
+        container.add(&spinner);
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        label.set_markup(&self.idle_label);
+        container.add(&label);
+
+        {
+            let tx = context.controller_tx.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, ());
+            });
+        }
+
+        let flash_duration = Duration::from_secs(self.flash_duration);
+
+        // incremented on each update,
+        // so that returning to idle is skipped if the job has since restarted
+        let generation = Rc::new(Cell::new(0u32));
+
+        {
+            let button = button.clone();
+
+            glib_recv!(context.subscribe(), status => {
+                generation.set(generation.get().wrapping_add(1));
+                set_classes(&button, None);
+
+                match status {
+                    JobStatus::Running { elapsed } => {
+                        set_classes(&button, Some("running"));
+                        spinner.show();
+                        spinner.start();
+
+                        let elapsed = format_elapsed(elapsed);
+                        label.set_markup(&self.format.replace("{elapsed}", &elapsed));
+                        button.set_tooltip_text(Some(&format!("Running for {elapsed}")));
+                    }
+                    JobStatus::Finished { elapsed, success, reason } => {
+                        set_classes(&button, Some(if success { "success" } else { "failure" }));
+                        spinner.stop();
+                        spinner.hide();
+
+                        let elapsed = format_elapsed(elapsed);
+                        label.set_markup(&self.format.replace("{elapsed}", &elapsed));
+
+                        let tooltip = match reason {
+                            Some(reason) => format!("Failed after {elapsed} ({reason})"),
+                            None => format!("Succeeded after {elapsed}"),
+                        };
+                        button.set_tooltip_text(Some(&tooltip));
+
+                        let finished_generation = generation.get();
+                        let generation = generation.clone();
+                        let button = button.clone();
+                        let label = label.clone();
+                        let idle_label = self.idle_label.clone();
+
+                        glib::timeout_add_local_once(flash_duration, move || {
+                            if generation.get() == finished_generation {
+                                set_classes(&button, None);
+                                label.set_markup(&idle_label);
+                                button.set_tooltip_text(None);
+                            }
+                        });
+                    }
+                }
+            });
+        }
+
+        Ok(ModuleParts::new(button, None))
+    }
+}
+
+/// Removes the job state classes from the widget,
+/// then adds `class` if set.
+fn set_classes(button: &Button, class: Option<&str>) {
+    for class in ["running", "success", "failure"] {
+        button.style_context().remove_class(class);
+    }
+
+    if let Some(class) = class {
+        button.add_class(class);
+    }
+}
+
+/// Runs the job command, sending its elapsed time until it exits.
+async fn run_command(command: &str, tx: &mpsc::Sender<ModuleUpdateEvent<JobStatus>>) {
+    let started = SystemTime::now();
+
+    let (mut output, mut status) = match Script::from(command).spawn_with_status() {
+        Ok(handles) => handles,
+        Err(err) => {
+            error!("Failed to run job command: {err:?}");
+            return;
+        }
+    };
+
+    // output is logged by the script, but must be read so the command does not block
+    spawn(async move { while output.recv().await.is_some() {} });
+
+    let status = loop {
+        send_async!(
+            tx,
+            ModuleUpdateEvent::Update(JobStatus::Running {
+                elapsed: elapsed_since(started)
+            })
+        );
+
+        tokio::select! {
+            status = &mut status => break status,
+            () = sleep(TICK) => {}
+        }
+    };
+
+    let status = match status {
+        Ok(status) => status,
+        Err(_) => {
+            error!("Failed to wait for job command");
+            return;
+        }
+    };
+
+    debug!("Job command exited with {status}");
+
+    send_async!(
+        tx,
+        ModuleUpdateEvent::Update(JobStatus::Finished {
+            elapsed: elapsed_since(started),
+            success: status.success(),
+            reason: failure_reason(status),
+        })
+    );
+}
+
+async fn start_unit(unit: &str) {
+    let res = Command::new("systemctl")
+        .args(["--user", "start", "--no-block", unit])
+        .status()
+        .await;
+
+    match res {
+        Ok(status) if !status.success() => error!("Failed to start unit '{unit}': {status}"),
+        Ok(_) => {}
+        Err(err) => error!("Failed to start unit '{unit}': {err:?}"),
+    }
+}
+
+/// Polls the state of the unit,
+/// sending its elapsed time while running and its result once it stops.
+async fn watch_unit(
+    unit: String,
+    interval: Duration,
+    tx: mpsc::Sender<ModuleUpdateEvent<JobStatus>>,
+) {
+    let mut running_since = None;
+
+    loop {
+        match read_unit(&unit).await {
+            Ok(state) if state.running => {
+                let started = state
+                    .started
+                    .or(running_since)
+                    .unwrap_or_else(SystemTime::now);
+                running_since = Some(started);
+
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(JobStatus::Running {
+                        elapsed: elapsed_since(started)
+                    })
+                );
+            }
+            // results from before the job was seen running are not shown
+            Ok(state) => {
+                if let Some(started) = running_since.take() {
+                    let elapsed = state.finished.map_or_else(
+                        || elapsed_since(started),
+                        |finished| finished.duration_since(started).unwrap_or_default(),
+                    );
+
+                    send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(JobStatus::Finished {
+                            elapsed,
+                            success: state.success(),
+                            reason: state.failure_reason(),
+                        })
+                    );
+                }
+            }
+            Err(err) => debug!("Failed to read state of unit '{unit}': {err:?}"),
+        }
+
+        sleep(interval).await;
+    }
+}
+
+async fn read_unit(unit: &str) -> Result<UnitState> {
+    let output = Command::new("systemctl")
+        .args([
+            "--user",
+            "show",
+            unit,
+            "--timestamp=unix",
+            "--property=ActiveState,Result,ExecMainStatus,InactiveExitTimestamp,InactiveEnterTimestamp",
+        ])
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        return Err(Report::msg(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(parse_unit_state(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses the `key=value` lines printed by `systemctl show`.
+fn parse_unit_state(output: &str) -> UnitState {
+    let mut state = UnitState::default();
+
+    for (key, value) in output.lines().filter_map(|line| line.split_once('=')) {
+        match key {
+            "ActiveState" => {
+                state.running = matches!(value, "activating" | "active" | "deactivating");
+            }
+            "Result" => state.result = value.to_string(),
+            "ExecMainStatus" => state.exit_status = value.parse().ok(),
+            "InactiveExitTimestamp" => state.started = parse_timestamp(value),
+            "InactiveEnterTimestamp" => state.finished = parse_timestamp(value),
+            _ => {}
+        }
+    }
+
+    state
+}
+
+/// Parses a timestamp printed with `--timestamp=unix`, ie `@1717660800`.
+/// Timestamps which have not been set are empty.
+fn parse_timestamp(value: &str) -> Option<SystemTime> {
+    let seconds = value.strip_prefix('@')?.parse().ok()?;
+    Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+fn failure_reason(status: ExitStatus) -> Option<String> {
+    if status.success() {
+        None
+    } else if let Some(code) = status.code() {
+        Some(format!("exit status {code}"))
+    } else {
+        status
+            .signal()
+            .map(|signal| format!("killed by signal {signal}"))
+    }
+}
+
+fn elapsed_since(started: SystemTime) -> Duration {
+    started.elapsed().unwrap_or_default()
+}
+
+/// Formats a duration as `M:SS`, or `H:MM:SS` from an hour.
+fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_secs(5)), "0:05");
+        assert_eq!(format_elapsed(Duration::from_secs(192)), "3:12");
+        assert_eq!(format_elapsed(Duration::from_secs(3723)), "1:02:03");
+    }
+
+    #[test]
+    fn test_parse_unit_state() {
+        let state = parse_unit_state(
+            "ActiveState=failed\nResult=exit-code\nExecMainStatus=2\n\
+             InactiveExitTimestamp=@1717660800\nInactiveEnterTimestamp=@1717661000\n",
+        );
+
+        assert!(!state.running);
+        assert!(!state.success());
+        assert_eq!(state.failure_reason().as_deref(), Some("exit status 2"));
+        assert_eq!(
+            state.started,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_717_660_800))
+        );
+
+        let state = parse_unit_state("ActiveState=activating\nInactiveEnterTimestamp=\n");
+        assert!(state.running);
+        assert_eq!(state.finished, None);
+    }
+}
@@ -0,0 +1,128 @@
+use crate::clients::compositor::{KeyboardLayoutClient, KeyboardLayoutUpdate};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::{Report, Result};
+use gtk::prelude::*;
+use gtk::{Button, Label};
+use serde::Deserialize;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::{debug, trace};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct KeyboardModule {
+    /// Map of layout names, as reported by the compositor,
+    /// to the text to show for them.
+    /// Layouts without an entry are shown using their full name.
+    ///
+    /// **Default**: `{}`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { layout_map.'English (US)' = "EN" layout_map.Ukrainian = "UA" }
+    /// ```
+    #[serde(default)]
+    layout_map: HashMap<String, String>,
+
+    /// Whether to re-read the active layout whenever window focus changes.
+    ///
+    /// Enable this when the compositor, or a helper such as `swaykbdd`,
+    /// keeps a separate layout for each window.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    per_window: bool,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+impl Module<Button> for KeyboardModule {
+    type SendMessage = String;
+    type ReceiveMessage = ();
+
+    module_impl!("keyboard");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context.try_client::<dyn KeyboardLayoutClient>()?;
+
+        {
+            let client = client.clone();
+            let tx = context.tx.clone();
+            let per_window = self.per_window;
+
+            spawn(async move {
+                let mut srx = client.subscribe();
+
+                trace!("Set up keyboard layout subscription");
+
+                while let Ok(update) = srx.recv().await {
+                    debug!("Received update: {update:?}");
+
+                    match update {
+                        KeyboardLayoutUpdate::Layout(layout) => {
+                            send_async!(tx, ModuleUpdateEvent::Update(layout));
+                        }
+                        KeyboardLayoutUpdate::Focus(layout) if per_window => {
+                            send_async!(tx, ModuleUpdateEvent::Update(layout));
+                        }
+                        KeyboardLayoutUpdate::Focus(_) => {}
+                    }
+                }
+            });
+        }
+
+        // Switch to next layout
+        spawn(async move {
+            while rx.recv().await.is_some() {
+                client.set_next_active()?;
+            }
+
+            Ok::<(), Report>(())
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        let tx = context.controller_tx.clone();
+        button.connect_clicked(move |_| {
+            try_send!(tx, ());
+        });
+
+        {
+            let button = button.clone();
+
+            glib_recv!(context.subscribe(), layout => {
+                let text = self.layout_map.get(&layout).unwrap_or(&layout);
+                label.set_markup(text);
+                button.set_tooltip_text(Some(&layout));
+            });
+        }
+
+        Ok(ModuleParts::new(button, None))
+    }
+}
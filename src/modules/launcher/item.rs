@@ -5,10 +5,10 @@ use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::ImageProvider;
 use crate::modules::launcher::{ItemEvent, LauncherUpdate};
 use crate::modules::ModuleUpdateEvent;
-use crate::{read_lock, try_send};
+use crate::{read_lock, try_send, write_lock};
 use glib::Propagation;
 use gtk::prelude::*;
-use gtk::{Button, IconTheme};
+use gtk::{Button, IconTheme, Label, Overlay, Widget};
 use indexmap::IndexMap;
 use std::rc::Rc;
 use std::sync::RwLock;
@@ -134,7 +134,13 @@ pub struct MenuState {
 }
 
 pub struct ItemButton {
+    /// The root widget.
+    /// This is the button, wrapped in an overlay when the window count badge
+    /// or index label are shown.
+    pub widget: Widget,
     pub button: Button,
+    badge: Option<Label>,
+    index: Option<Label>,
     pub persistent: bool,
     pub show_names: bool,
     pub menu_state: Rc<RwLock<MenuState>>,
@@ -145,6 +151,8 @@ pub struct AppearanceOptions {
     pub show_names: bool,
     pub show_icons: bool,
     pub icon_size: i32,
+    pub show_window_count: bool,
+    pub show_index: bool,
 }
 
 impl ItemButton {
@@ -266,11 +274,84 @@ impl ItemButton {
 
         button.show_all();
 
-        Self {
+        // only wrapped when needed, so `.launcher > .item` selectors work otherwise
+        let overlay = (appearance.show_window_count || appearance.show_index).then(|| {
+            let overlay = Overlay::new();
+            overlay.add(&button);
+            overlay
+        });
+
+        let badge = overlay
+            .as_ref()
+            .filter(|_| appearance.show_window_count)
+            .map(|overlay| {
+                let badge = Label::new(None);
+                badge.add_class("window-count");
+                badge.set_halign(gtk::Align::End);
+                badge.set_valign(gtk::Align::Start);
+
+                // only shown when there are multiple windows
+                badge.set_no_show_all(true);
+
+                overlay.add_overlay(&badge);
+                overlay.set_overlay_pass_through(&badge, true);
+
+                badge
+            });
+
+        let index = overlay
+            .as_ref()
+            .filter(|_| appearance.show_index)
+            .map(|overlay| {
+                let index = Label::new(None);
+                index.add_class("index");
+                index.set_halign(gtk::Align::Start);
+                index.set_valign(gtk::Align::End);
+
+                overlay.add_overlay(&index);
+                overlay.set_overlay_pass_through(&index, true);
+
+                index.show();
+                index
+            });
+
+        let widget: Widget = overlay.map_or_else(|| button.clone().upcast(), Cast::upcast);
+        widget.show();
+
+        let item_button = Self {
+            widget,
             button,
+            badge,
+            index,
             persistent: item.favorite,
             show_names: appearance.show_names,
             menu_state,
+        };
+
+        item_button.set_window_count(item.windows.len());
+        item_button
+    }
+
+    pub fn window_count(&self) -> usize {
+        read_lock!(self.menu_state).num_windows
+    }
+
+    /// Sets the number of open windows for this item,
+    /// updating the count badge.
+    pub fn set_window_count(&self, count: usize) {
+        write_lock!(self.menu_state).num_windows = count;
+
+        if let Some(badge) = &self.badge {
+            badge.set_text(&count.to_string());
+            badge.set_visible(count > 1);
+        }
+    }
+
+    /// Sets the position of this item on the bar, starting from 1,
+    /// updating the index label.
+    pub fn set_index(&self, index: usize) {
+        if let Some(label) = &self.index {
+            label.set_text(&index.to_string());
         }
     }
 
@@ -1,13 +1,17 @@
 mod item;
 mod open_state;
+#[cfg(feature = "ipc")]
+mod registry;
 
 use self::item::{AppearanceOptions, Item, ItemButton, Window};
 use self::open_state::OpenState;
+#[cfg(feature = "ipc")]
+pub use self::registry::activate;
 use super::{Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, WidgetContext};
 use crate::clients::wayland::{self, ToplevelEvent};
 use crate::config::CommonConfig;
 use crate::desktop_file::find_desktop_file;
-use crate::{arc_mut, glib_recv, lock, module_impl, send_async, spawn, try_send, write_lock};
+use crate::{arc_mut, glib_recv, lock, send_async, spawn, try_send};
 use color_eyre::{Help, Report};
 use gtk::prelude::*;
 use gtk::{Button, Orientation};
@@ -41,9 +45,8 @@ pub struct LauncherModule {
 
     /// Size in pixels to render icon at (image icons only).
     ///
-    /// **Default**: `32`
-    #[serde(default = "default_icon_size")]
-    icon_size: i32,
+    /// **Default**: The bar's `icon_size` if set, otherwise `32`
+    icon_size: Option<i32>,
 
     /// Whether items should be added from right-to-left
     /// instead of left-to-right.
@@ -54,9 +57,30 @@ pub struct LauncherModule {
     #[serde(default = "crate::config::default_false")]
     reversed: bool,
 
+    /// Whether to show a badge with the number of open windows
+    /// on items with more than one window.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    show_window_count: bool,
+
+    /// Whether to show each item's position on the bar, starting from 1.
+    ///
+    /// Items can be activated by position [over IPC](#ipc),
+    /// so this pairs well with compositor keybindings.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    show_index: bool,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
+
+    /// The module's `name`, kept after the common options are taken
+    /// so that the module can be found over IPC.
+    #[serde(skip)]
+    instance_name: Option<String>,
 }
 
 const fn default_icon_size() -> i32 {
@@ -79,6 +103,8 @@ pub enum LauncherUpdate {
     Focus(String, bool),
     /// Declares the item with `app_id` has been hovered over
     Hover(String),
+    /// Activates the item at a position on the bar, starting from 1
+    Activate(usize),
 }
 
 #[derive(Debug)]
@@ -86,6 +112,8 @@ pub enum ItemEvent {
     FocusItem(String),
     FocusWindow(usize),
     OpenItem(String),
+    /// Focuses or opens the item at a position on the bar, starting from 1.
+    Activate(usize),
 }
 
 enum ItemOrWindow {
@@ -102,7 +130,15 @@ impl Module<gtk::Box> for LauncherModule {
     type SendMessage = LauncherUpdate;
     type ReceiveMessage = ItemEvent;
 
-    module_impl!("launcher");
+    fn name() -> &'static str {
+        "launcher"
+    }
+
+    fn take_common(&mut self) -> CommonConfig {
+        let common = self.common.take().unwrap_or_default();
+        self.instance_name.clone_from(&common.name);
+        common
+    }
 
     fn spawn_controller(
         &self,
@@ -110,6 +146,9 @@ impl Module<gtk::Box> for LauncherModule {
         context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
         mut rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> crate::Result<()> {
+        #[cfg(feature = "ipc")]
+        registry::register(self.instance_name.clone(), &context.controller_tx);
+
         let items = self
             .favorites
             .as_ref()
@@ -268,7 +307,13 @@ impl Module<gtk::Box> for LauncherModule {
         let wl = context.client::<wayland::Client>();
         spawn(async move {
             while let Some(event) = rx.recv().await {
-                if let ItemEvent::OpenItem(app_id) = event {
+                if let ItemEvent::Activate(index) = event {
+                    // positions are only known to the widget
+                    send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(LauncherUpdate::Activate(index))
+                    );
+                } else if let ItemEvent::OpenItem(app_id) = event {
                     find_desktop_file(&app_id).map_or_else(
                         || error!("Could not find desktop file for {}", app_id),
                         |file| {
@@ -304,7 +349,7 @@ impl Module<gtk::Box> for LauncherModule {
                             })
                         }
                         ItemEvent::FocusWindow(id) => Some(id),
-                        ItemEvent::OpenItem(_) => unreachable!(),
+                        ItemEvent::OpenItem(_) | ItemEvent::Activate(_) => unreachable!(),
                     };
 
                     if let Some(id) = id {
@@ -341,10 +386,14 @@ impl Module<gtk::Box> for LauncherModule {
             let appearance_options = AppearanceOptions {
                 show_names: self.show_names,
                 show_icons: self.show_icons,
-                icon_size: self.icon_size,
+                icon_size: info.icon_size(self.icon_size, default_icon_size()),
+                show_window_count: self.show_window_count,
+                show_index: self.show_index,
             };
 
             let show_names = self.show_names;
+            let show_index = self.show_index;
+            let reversed = self.reversed;
             let bar_position = info.bar_position;
 
             let mut buttons = IndexMap::<String, ItemButton>::new();
@@ -359,6 +408,7 @@ impl Module<gtk::Box> for LauncherModule {
                         if let Some(button) = buttons.get(&item.app_id) {
                             button.set_open(true);
                             button.set_focused(item.open_state.is_focused());
+                            button.set_window_count(item.windows.len());
                         } else {
                             let button = ItemButton::new(
                                 &item,
@@ -369,13 +419,17 @@ impl Module<gtk::Box> for LauncherModule {
                                 &controller_tx,
                             );
 
-                            if self.reversed {
-                                container.pack_end(&button.button, false, false, 0);
+                            if reversed {
+                                container.pack_end(&button.widget, false, false, 0);
                             } else {
-                                container.add(&button.button);
+                                container.add(&button.widget);
                             }
 
                             buttons.insert(item.app_id, button);
+
+                            if show_index {
+                                update_indices(&buttons, reversed);
+                            }
                         }
                     }
                     LauncherUpdate::AddWindow(app_id, win) => {
@@ -383,7 +437,7 @@ impl Module<gtk::Box> for LauncherModule {
                             button.set_open(true);
                             button.set_focused(win.open_state.is_focused());
 
-                            write_lock!(button.menu_state).num_windows += 1;
+                            button.set_window_count(button.window_count() + 1);
                         }
                     }
                     LauncherUpdate::RemoveItem(app_id) => {
@@ -392,12 +446,17 @@ impl Module<gtk::Box> for LauncherModule {
                         if let Some(button) = buttons.get(&app_id) {
                             if button.persistent {
                                 button.set_open(false);
+                                button.set_window_count(0);
                                 if button.show_names {
                                     button.button.set_label(&app_id);
                                 }
                             } else {
-                                container.remove(&button.button);
+                                container.remove(&button.widget);
                                 buttons.shift_remove(&app_id);
+
+                                if show_index {
+                                    update_indices(&buttons, reversed);
+                                }
                             }
                         }
                     }
@@ -406,9 +465,7 @@ impl Module<gtk::Box> for LauncherModule {
 
                         if let Some(button) = buttons.get(&app_id) {
                             button.set_focused(false);
-
-                            let mut menu_state = write_lock!(button.menu_state);
-                            menu_state.num_windows -= 1;
+                            button.set_window_count(button.window_count().saturating_sub(1));
                         }
                     }
                     LauncherUpdate::Focus(app_id, focus) => {
@@ -427,6 +484,16 @@ impl Module<gtk::Box> for LauncherModule {
                             }
                         }
                     }
+                    LauncherUpdate::Activate(index) => {
+                        let button = index
+                            .checked_sub(1)
+                            .and_then(|index| ordered_buttons(&buttons, reversed).get(index).copied());
+
+                        match button {
+                            Some(button) => button.button.clicked(),
+                            None => debug!("No launcher item at position {index}"),
+                        }
+                    }
                     LauncherUpdate::Hover(_) => {}
                 };
             });
@@ -557,6 +624,25 @@ impl Module<gtk::Box> for LauncherModule {
     }
 }
 
+/// Gets the item buttons in the order they appear on the bar.
+/// When reversed, items are packed from the end, so appear in reverse order.
+fn ordered_buttons(buttons: &IndexMap<String, ItemButton>, reversed: bool) -> Vec<&ItemButton> {
+    let mut ordered = buttons.values().collect::<Vec<_>>();
+
+    if reversed {
+        ordered.reverse();
+    }
+
+    ordered
+}
+
+/// Updates the index label on each item to match its position on the bar.
+fn update_indices(buttons: &IndexMap<String, ItemButton>, reversed: bool) {
+    for (index, button) in ordered_buttons(buttons, reversed).into_iter().enumerate() {
+        button.set_index(index + 1);
+    }
+}
+
 /// Clamps a string at 24 characters.
 ///
 /// This is a hacky number derived from
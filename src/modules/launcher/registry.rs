@@ -0,0 +1,56 @@
+//! Tracks launcher modules,
+//! so their items can be activated over IPC.
+
+use super::ItemEvent;
+use crate::{lock, try_send};
+use color_eyre::{Report, Result};
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::mpsc;
+
+struct Registration {
+    name: Option<String>,
+    tx: mpsc::WeakSender<ItemEvent>,
+}
+
+fn registry() -> &'static Mutex<Vec<Registration>> {
+    static REGISTRY: OnceLock<Mutex<Vec<Registration>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(vec![]))
+}
+
+/// Registers a module instance.
+pub fn register(name: Option<String>, tx: &mpsc::Sender<ItemEvent>) {
+    let mut registry = lock!(registry());
+
+    // clear out modules from closed bars
+    registry.retain(|registration| registration.tx.upgrade().is_some());
+
+    registry.push(Registration {
+        name,
+        tx: tx.downgrade(),
+    });
+}
+
+/// Focuses or opens the item at `index` on a launcher, starting from 1.
+///
+/// Only a single launcher is activated, so that apps are not opened once per bar.
+/// This is the first launcher with the given `name`, or the first launcher if not set.
+pub fn activate(index: usize, name: Option<&str>) -> Result<()> {
+    if index == 0 {
+        return Err(Report::msg("Index starts from 1"));
+    }
+
+    let registry = lock!(registry());
+
+    let tx = registry
+        .iter()
+        .filter(|registration| name.map_or(true, |name| registration.name.as_deref() == Some(name)))
+        .find_map(|registration| registration.tx.upgrade());
+
+    match tx {
+        Some(tx) => {
+            try_send!(tx, ItemEvent::Activate(index));
+            Ok(())
+        }
+        None => Err(Report::msg("Module not found")),
+    }
+}
@@ -0,0 +1,287 @@
+use crate::clients::volume::{self, Event, Source};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, lock, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use glib::Propagation;
+use gtk::gdk::{EventMask, ScrollDirection};
+use gtk::prelude::*;
+use gtk::{Button, Image, Label, LevelBar, Orientation};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MicModule {
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{percentage}%`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The amount to change the input gain by, in percent,
+    /// for each scroll step.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_step")]
+    step: f64,
+
+    /// Maximum value the input gain can be raised to by scrolling.
+    /// Pulse supports values > 100 but this may result in distortion.
+    ///
+    /// **Default**: `100`
+    #[serde(default = "default_max_volume")]
+    max_volume: f64,
+
+    /// Whether to show a live level meter next to the button.
+    /// This opens a recording stream on the default source while Ironbar is running.
+    ///
+    /// **Default**: `false`
+    #[serde(default = "crate::config::default_false")]
+    show_meter: bool,
+
+    /// The size to render the icon at, in pixels.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{percentage}%")
+}
+
+const fn default_step() -> f64 {
+    5.0
+}
+
+const fn default_max_volume() -> f64 {
+    100.0
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+#[derive(Debug, Clone)]
+pub enum MicEvent {
+    Source(Source),
+    /// There is no default source, so the widget is hidden.
+    NoSource,
+    Level(f64),
+}
+
+#[derive(Debug, Clone)]
+pub enum Update {
+    ToggleMute,
+    ChangeVolume(f64),
+}
+
+impl Module<gtk::Box> for MicModule {
+    type SendMessage = MicEvent;
+    type ReceiveMessage = Update;
+
+    module_impl!("mic");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context.client::<volume::Client>();
+
+        {
+            let client = client.clone();
+            let mut rx = client.subscribe();
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                send_async!(tx, ModuleUpdateEvent::Update(active_source(&client)));
+
+                while let Ok(event) = rx.recv().await {
+                    match event {
+                        Event::AddSource(source) | Event::UpdateSource(source) if source.active => {
+                            send_async!(tx, ModuleUpdateEvent::Update(MicEvent::Source(source)));
+                        }
+                        // the new default source is sent with the next server update,
+                        // so only fall back to the remaining active source, if any
+                        Event::RemoveSource(_) => {
+                            send_async!(tx, ModuleUpdateEvent::Update(active_source(&client)));
+                        }
+                        _ => {}
+                    }
+                }
+            });
+        }
+
+        if self.show_meter {
+            let mut rx = client.subscribe_source_level();
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                while let Ok(level) = rx.recv().await {
+                    send_async!(tx, ModuleUpdateEvent::Update(MicEvent::Level(level)));
+                }
+            });
+        }
+
+        let max_volume = self.max_volume;
+
+        // ui events
+        spawn(async move {
+            while let Some(update) = rx.recv().await {
+                let Some(source) = lock!(client.sources()).iter().find(|s| s.active).cloned()
+                else {
+                    continue;
+                };
+
+                match update {
+                    Update::ToggleMute => client.set_source_muted(&source.name, !source.muted),
+                    Update::ChangeVolume(delta) => {
+                        let volume = (source.volume + delta).clamp(0.0, max_volume);
+                        client.set_source_volume(&source.name, volume);
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let container = gtk::Box::new(info.bar_position.orientation(), 5);
+
+        let icon = Image::new();
+        icon.add_class("icon");
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+
+        let contents = gtk::Box::new(Orientation::Horizontal, 5);
+        contents.add_class("contents");
+        contents.add(&icon);
+        contents.add(&label);
+
+        let button = Button::new();
+        button.add_class("button");
+        button.add(&contents);
+        button.add_events(EventMask::SCROLL_MASK);
+
+        container.add(&button);
+
+        {
+            let tx = context.controller_tx.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, Update::ToggleMute);
+            });
+        }
+
+        {
+            let tx = context.controller_tx.clone();
+            let step = self.step;
+
+            button.connect_scroll_event(move |_, event| {
+                let delta = match event.direction() {
+                    ScrollDirection::Up => step,
+                    ScrollDirection::Down => -step,
+                    ScrollDirection::Smooth if event.delta().1 < 0.0 => step,
+                    ScrollDirection::Smooth if event.delta().1 > 0.0 => -step,
+                    _ => return Propagation::Proceed,
+                };
+
+                try_send!(tx, Update::ChangeVolume(delta));
+                Propagation::Stop
+            });
+        }
+
+        let meter = self.show_meter.then(|| {
+            let meter = LevelBar::builder()
+                .orientation(info.bar_position.orientation())
+                .min_value(0.0)
+                .max_value(1.0)
+                .build();
+            meter.add_class("meter");
+
+            container.add(&meter);
+            meter
+        });
+
+        {
+            let icon_theme = info.icon_theme.clone();
+            let format = self.format;
+            let icon_size = info.icon_size(self.icon_size, default_icon_size());
+            let container = container.clone();
+
+            glib_recv!(context.subscribe(), event => {
+                match event {
+                    MicEvent::Source(source) => {
+                        container.show();
+
+                        ImageProvider::parse(
+                            &determine_mic_icon(source.muted, source.volume),
+                            &icon_theme,
+                            false,
+                            icon_size,
+                        ).map(|provider| provider.load_into_image(icon.clone()));
+
+                        let text = format
+                            .replace("{percentage}", &source.volume.to_string())
+                            .replace("{name}", &source.description);
+                        label.set_markup(&text);
+
+                        if source.muted {
+                            button.add_class("muted");
+                        } else {
+                            button.style_context().remove_class("muted");
+                        }
+                    }
+                    MicEvent::NoSource => container.hide(),
+                    MicEvent::Level(level) => {
+                        if let Some(meter) = &meter {
+                            meter.set_value(level);
+                        }
+                    }
+                }
+            });
+        }
+
+        Ok(ModuleParts::new(container, None))
+    }
+}
+
+/// Gets the event for the current default source,
+/// or `NoSource` if there is none, such as after it is unplugged.
+fn active_source(client: &volume::Client) -> MicEvent {
+    lock!(client.sources())
+        .iter()
+        .find(|s| s.active)
+        .cloned()
+        .map_or(MicEvent::NoSource, MicEvent::Source)
+}
+
+fn determine_mic_icon(muted: bool, volume: f64) -> String {
+    let icon_variant = if muted {
+        "muted"
+    } else if volume <= 33.3333 {
+        "low"
+    } else if volume <= 66.6667 {
+        "medium"
+    } else {
+        "high"
+    };
+    format!("microphone-sensitivity-{icon_variant}-symbolic")
+}
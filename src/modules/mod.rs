@@ -4,18 +4,26 @@ use std::sync::Arc;
 
 use color_eyre::Result;
 use glib::IsA;
+use gtk::ffi::GTK_STYLE_PROVIDER_PRIORITY_USER;
 use gtk::gdk::{EventMask, Monitor};
 use gtk::prelude::*;
-use gtk::{Application, Button, EventBox, IconTheme, Orientation, Revealer, Widget};
+use gtk::{
+    Application, Button, CssProvider, EventBox, IconSize, IconTheme, Image, Orientation, Revealer,
+    Widget,
+};
 use tokio::sync::{broadcast, mpsc};
-use tracing::debug;
+use tracing::{debug, error};
 
-use crate::clients::{ClientResult, ProvidesClient, ProvidesFallibleClient};
+use crate::clients::{ClientResult, Clients, ProvidesClient, ProvidesFallibleClient};
 use crate::config::{BarPosition, CommonConfig, TransitionType};
 use crate::gtk_helpers::{IronbarGtkExt, WidgetGeometry};
-use crate::popup::Popup;
+use crate::popup::{Popup, PopupPin, PopupPlacement};
 use crate::{glib_recv_mpsc, send, Ironbar};
 
+#[cfg(feature = "bluetooth_battery")]
+pub mod bluetooth_battery;
+#[cfg(feature = "break_reminder")]
+pub mod break_reminder;
 #[cfg(feature = "cairo")]
 pub mod cairo;
 #[cfg(feature = "clipboard")]
@@ -28,29 +36,73 @@ pub mod clipboard;
 /// with second-level precision and a calendar.
 #[cfg(feature = "clock")]
 pub mod clock;
+#[cfg(feature = "cpu_governor")]
+pub mod cpu_governor;
 pub mod custom;
+#[cfg(feature = "dictation")]
+pub mod dictation;
+#[cfg(feature = "disk_health")]
+pub mod disk_health;
+#[cfg(feature = "displays")]
+pub mod displays;
+#[cfg(feature = "easyeffects")]
+pub mod easyeffects;
+#[cfg(feature = "emoji")]
+pub mod emoji;
+#[cfg(feature = "firewall")]
+pub mod firewall;
 #[cfg(feature = "focused")]
 pub mod focused;
+#[cfg(feature = "ipc")]
+pub mod health;
+#[cfg(feature = "homeassistant")]
+pub mod homeassistant;
+#[cfg(feature = "job")]
+pub mod job;
+#[cfg(feature = "keyboard")]
+pub mod keyboard;
 pub mod label;
 #[cfg(feature = "launcher")]
 pub mod launcher;
+#[cfg(feature = "mic")]
+pub mod mic;
+#[cfg(feature = "mqtt")]
+pub mod mqtt;
 #[cfg(feature = "music")]
 pub mod music;
 #[cfg(feature = "networkmanager")]
 pub mod networkmanager;
+#[cfg(feature = "next_event")]
+pub mod next_event;
 #[cfg(feature = "notifications")]
 pub mod notifications;
+#[cfg(feature = "nvme_health")]
+pub mod nvme_health;
+#[cfg(feature = "reboot")]
+pub mod reboot;
+#[cfg(feature = "run")]
+pub mod run;
 pub mod script;
+#[cfg(feature = "sessions")]
+pub mod sessions;
 #[cfg(feature = "sys_info")]
 pub mod sysinfo;
 #[cfg(feature = "tray")]
 pub mod tray;
 #[cfg(feature = "upower")]
 pub mod upower;
+#[cfg(feature = "usb")]
+pub mod usb;
 #[cfg(feature = "volume")]
 pub mod volume;
+#[cfg(feature = "weather_alerts")]
+pub mod weather_alerts;
+#[cfg(feature = "wine")]
+pub mod wine;
 #[cfg(feature = "workspaces")]
 pub mod workspaces;
+#[cfg(feature = "worktime")]
+pub mod worktime;
 
 #[derive(Clone)]
 pub enum ModuleLocation {
@@ -64,9 +116,31 @@ pub struct ModuleInfo<'a> {
     pub app: &'a Application,
     pub location: ModuleLocation,
     pub bar_position: BarPosition,
+    /// The name of the bar the module is on.
+    pub bar_name: &'a str,
     pub monitor: &'a Monitor,
     pub output_name: &'a str,
     pub icon_theme: &'a IconTheme,
+    /// The bar's default icon size.
+    pub icon_size: Option<i32>,
+    /// The factor to scale sizes on the bar by.
+    pub scale: f64,
+}
+
+impl ModuleInfo<'_> {
+    /// Gets the size to render a module's icons at.
+    ///
+    /// The module's configured size is used if set,
+    /// followed by the bar's icon size and then the module's default.
+    /// The result is multiplied by the bar's scale.
+    pub fn icon_size(&self, size: Option<i32>, default: i32) -> i32 {
+        self.scaled(size.or(self.icon_size).unwrap_or(default))
+    }
+
+    /// Multiplies a size in pixels by the bar's scale.
+    pub fn scaled(&self, size: i32) -> i32 {
+        (f64::from(size) * self.scale).round() as i32
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -114,16 +188,16 @@ where
     /// `context.ironbar.clients`.
     pub fn client<T: ?Sized>(&self) -> Arc<T>
     where
-        WidgetContext<TSend, TReceive>: ProvidesClient<T>,
+        Clients: ProvidesClient<T>,
     {
-        ProvidesClient::provide(self)
+        ProvidesClient::<T>::provide(&mut *self.ironbar.clients.borrow_mut())
     }
 
     pub fn try_client<T: ?Sized>(&self) -> ClientResult<T>
     where
-        WidgetContext<TSend, TReceive>: ProvidesFallibleClient<T>,
+        Clients: ProvidesFallibleClient<T>,
     {
-        ProvidesFallibleClient::try_provide(self)
+        ProvidesFallibleClient::<T>::try_provide(&mut *self.ironbar.clients.borrow_mut())
     }
 
     /// Subscribes to events sent from this widget.
@@ -151,19 +225,35 @@ impl<W: IsA<Widget>> ModuleParts<W> {
             }
         }
 
-        if let Some(ref class) = common.class {
-            // gtk counts classes with spaces as the same class
-            for part in class.split(' ') {
-                self.widget.style_context().add_class(part);
-            }
+        // gtk counts classes with spaces as the same class
+        let classes = common
+            .class
+            .iter()
+            .flat_map(|class| class.split(' '))
+            .chain(common.classes.iter().map(String::as_str))
+            .filter(|class| !class.is_empty());
+
+        for class in classes {
+            self.widget.style_context().add_class(class);
 
             if let Some(ref popup) = self.popup {
-                for part in class.split(' ') {
-                    popup
-                        .container
-                        .style_context()
-                        .add_class(&format!("popup-{part}"));
-                }
+                popup
+                    .container
+                    .style_context()
+                    .add_class(&format!("popup-{class}"));
+            }
+        }
+
+        if let Some(ref style) = common.style {
+            let provider = CssProvider::new();
+
+            match provider.load_from_data(format!("* {{ {style} }}").as_bytes()) {
+                Ok(()) => self.widget.style_context().add_provider(
+                    &provider,
+                    // take precedence over the user stylesheet
+                    GTK_STYLE_PROVIDER_PRIORITY_USER as u32 + 1,
+                ),
+                Err(err) => error!("Failed to load inline style '{style}': {err}"),
             }
         }
     }
@@ -177,6 +267,9 @@ pub struct ModulePopupParts {
     /// For most modules, this will only be a single button.
     /// For some advanced modules, such as `Launcher`, this is all item buttons.
     pub buttons: Vec<Button>,
+    /// Whether the popup should receive keyboard input while open.
+    /// This is only required for popups containing text entries.
+    pub keyboard: bool,
 }
 
 pub trait ModulePopup {
@@ -190,7 +283,11 @@ impl ModulePopup for Option<gtk::Box> {
     }
 
     fn into_popup_parts_owned(self, buttons: Vec<Button>) -> Option<ModulePopupParts> {
-        self.map(|container| ModulePopupParts { container, buttons })
+        self.map(|container| ModulePopupParts {
+            container,
+            buttons,
+            keyboard: false,
+        })
     }
 }
 
@@ -303,15 +400,49 @@ pub trait ModuleFactory {
             button_id: usize::MAX, // hack :(
         };
 
-        module.spawn_controller(info, &context, controller_rx)?;
-
         let module_name = TModule::name();
         let instance_name = common
             .name
             .clone()
             .unwrap_or_else(|| module_name.to_string());
 
-        let module_parts = module.into_widget(context, info)?;
+        let module_parts = module
+            .spawn_controller(info, &context, controller_rx)
+            .and_then(|()| module.into_widget(context, info));
+
+        // a failed module is replaced with an error icon,
+        // rather than preventing the rest of the bar from loading
+        let module_parts = match module_parts {
+            Ok(module_parts) => module_parts,
+            Err(err) => {
+                let err = err.wrap_err(format!("Failed to create module '{instance_name}'"));
+                error!("{err:?}");
+
+                let message = format!("{err:#}");
+
+                let widget = create_error_widget(&message);
+                widget.add_class(module_name);
+
+                #[cfg(feature = "ipc")]
+                health::register(info.bar_name, &instance_name, &widget, Some(message));
+
+                let module_parts = ModuleParts::new(widget, None);
+                module_parts.setup_identifiers(&common);
+
+                let ev_container = wrap_widget(
+                    &module_parts.widget,
+                    common,
+                    info.bar_position.orientation(),
+                );
+                container.add(&ev_container);
+
+                return Ok(());
+            }
+        };
+
+        #[cfg(feature = "ipc")]
+        health::register(info.bar_name, &instance_name, &module_parts.widget, None);
+
         module_parts.widget.add_class("widget");
         module_parts.widget.add_class(module_name);
 
@@ -321,8 +452,19 @@ pub trait ModuleFactory {
                 .style_context()
                 .add_class(&format!("popup-{module_name}"));
 
+            let placement = PopupPlacement {
+                gap: common.popup_gap,
+                offset_x: common.popup_offset_x,
+                offset_y: common.popup_offset_y,
+            };
+
+            let pin = PopupPin {
+                button: common.popup_pin_button,
+                pinned: common.popup_pinned,
+            };
+
             self.popup()
-                .register_content(id, instance_name, popup_content);
+                .register_content(id, instance_name, popup_content, placement, pin);
         }
 
         self.setup_receiver(tx, ui_rx, module_name, id, common.disable_popup);
@@ -545,6 +687,17 @@ impl From<PopupModuleFactory> for AnyModuleFactory {
 
 /// Takes a widget and adds it into a new `gtk::EventBox`.
 /// The event box container is returned.
+/// Creates the icon shown in place of a module which failed to start,
+/// with the error in its tooltip.
+fn create_error_widget(message: &str) -> Image {
+    let image = Image::from_icon_name(Some("dialog-error-symbolic"), IconSize::Button);
+    image.add_class("widget");
+    image.add_class("error");
+    image.set_tooltip_text(Some(message));
+
+    image
+}
+
 pub fn wrap_widget<W: IsA<Widget>>(
     widget: &W,
     common: CommonConfig,
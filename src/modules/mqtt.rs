@@ -0,0 +1,240 @@
+use crate::clients::mqtt::{topic_matches, Message};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label};
+use regex::{Captures, Regex};
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::sync::mpsc;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct MqttModule {
+    /// The hostname or IP address of the MQTT broker.
+    ///
+    /// **Default**: `localhost`
+    #[serde(default = "default_host")]
+    host: String,
+
+    /// The port the MQTT broker is listening on.
+    ///
+    /// **Default**: `1883`
+    #[serde(default = "default_port")]
+    port: u16,
+
+    /// The username to authenticate with, if the broker requires it.
+    ///
+    /// **Default**: `null`
+    username: Option<String>,
+
+    /// The password to authenticate with, if the broker requires it.
+    ///
+    /// **Default**: `null`
+    password: Option<String>,
+
+    /// The list of topics to subscribe to.
+    /// Each topic is rendered as its own widget.
+    /// See [topics](#topics).
+    ///
+    /// **Default**: `[]`
+    #[serde(default)]
+    topics: Vec<TopicConfig>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct TopicConfig {
+    /// The topic filter to subscribe to.
+    /// The `+` and `#` wildcards are supported.
+    ///
+    /// **Required**
+    topic: String,
+
+    /// The template used to render received payloads.
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `{payload}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// A message to publish when the widget is clicked.
+    ///
+    /// **Default**: `null`
+    on_click: Option<PublishConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PublishConfig {
+    /// The topic to publish to.
+    ///
+    /// **Required**
+    topic: String,
+
+    /// The message payload.
+    ///
+    /// **Default**: `""`
+    #[serde(default)]
+    payload: String,
+
+    /// Whether the broker should retain the message.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    retain: bool,
+}
+
+fn default_host() -> String {
+    String::from("localhost")
+}
+
+const fn default_port() -> u16 {
+    1883
+}
+
+fn default_format() -> String {
+    String::from("{payload}")
+}
+
+/// Renders a received message using a topic's format template.
+///
+/// All tokens are replaced in a single pass,
+/// so values containing tokens are never expanded again.
+/// Values are escaped, as the format is rendered as Pango markup.
+fn render(format: &str, message: &Message, re: &Regex) -> String {
+    let json = serde_json::from_str::<Value>(&message.payload).ok();
+
+    re.replace_all(format, |caps: &Captures| {
+        let value = match &caps[0] {
+            "{topic}" => message.topic.clone(),
+            "{payload}" => message.payload.clone(),
+            _ => {
+                let pointer = format!("/{}", caps[1].replace('.', "/"));
+
+                match json.as_ref().and_then(|json| json.pointer(&pointer)) {
+                    Some(Value::String(value)) => value.clone(),
+                    Some(value) => value.to_string(),
+                    None => String::new(),
+                }
+            }
+        };
+
+        glib::markup_escape_text(&value).to_string()
+    })
+    .to_string()
+}
+
+impl Module<gtk::Box> for MqttModule {
+    type SendMessage = (usize, String);
+    type ReceiveMessage = PublishConfig;
+
+    module_impl!("mqtt");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let credentials = self.username.clone().zip(self.password.clone());
+        let client = context
+            .ironbar
+            .clients
+            .borrow_mut()
+            .mqtt(&self.host, self.port, credentials);
+
+        let re = Regex::new(r"\{(?:topic|payload|json:([^}]+))}")?;
+        let topics = self.topics.clone();
+
+        {
+            let client = client.clone();
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                let mut rx = client.subscribe_messages();
+
+                for (index, topic) in topics.iter().enumerate() {
+                    client.subscribe(&topic.topic).await;
+
+                    for message in client.last_messages(&topic.topic) {
+                        let text = render(&topic.format, &message, &re);
+                        send_async!(tx, ModuleUpdateEvent::Update((index, text)));
+                    }
+                }
+
+                while let Ok(message) = rx.recv().await {
+                    for (index, topic) in topics.iter().enumerate() {
+                        if topic_matches(&topic.topic, &message.topic) {
+                            let text = render(&topic.format, &message, &re);
+                            send_async!(tx, ModuleUpdateEvent::Update((index, text)));
+                        }
+                    }
+                }
+            });
+        }
+
+        spawn(async move {
+            while let Some(publish) = rx.recv().await {
+                client
+                    .publish(&publish.topic, publish.payload, publish.retain)
+                    .await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<gtk::Box>> {
+        let container = gtk::Box::new(info.bar_position.orientation(), 0);
+
+        let mut labels = vec![];
+
+        for topic in self.topics {
+            let button = Button::new();
+            button.add_class("topic");
+
+            let label = Label::builder()
+                .use_markup(true)
+                .angle(info.bar_position.get_angle())
+                .build();
+            label.add_class("label");
+
+            button.add(&label);
+            container.add(&button);
+
+            if let Some(publish) = topic.on_click {
+                let tx = context.controller_tx.clone();
+                button.connect_clicked(move |_| {
+                    try_send!(tx, publish.clone());
+                });
+            }
+
+            labels.push(label);
+        }
+
+        glib_recv!(context.subscribe(), update => {
+            let (index, text) = update;
+            if let Some(label) = labels.get(index) {
+                label.set_markup(&text);
+            }
+        });
+
+        Ok(ModuleParts {
+            widget: container,
+            popup: None,
+        })
+    }
+}
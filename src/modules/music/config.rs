@@ -84,6 +84,40 @@ impl Default for PlayerType {
     }
 }
 
+/// An action to perform when the bar widget is clicked.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ClickAction {
+    /// Do nothing.
+    None,
+    /// Open or close the popup.
+    Popup,
+    /// Play if paused, or pause if playing.
+    PlayPause,
+    /// Skip to the next track.
+    Next,
+    /// Go back to the previous track.
+    Previous,
+    /// Bring the player's window to the front.
+    /// Only supported by MPRIS players.
+    Raise,
+}
+
+/// An action to perform when scrolling over the bar widget.
+#[derive(Debug, Default, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ScrollAction {
+    /// Do nothing.
+    #[default]
+    None,
+    /// Seek forwards when scrolling up, and backwards when scrolling down.
+    Seek,
+    /// Raise the volume when scrolling up, and lower it when scrolling down.
+    Volume,
+}
+
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MusicModule {
@@ -114,9 +148,8 @@ pub struct MusicModule {
 
     /// Size to render the icons at, in pixels (image icons only).
     ///
-    /// **Default** `32`
-    #[serde(default = "default_icon_size")]
-    pub(crate) icon_size: i32,
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    pub(crate) icon_size: Option<i32>,
 
     /// Size to render the album art image at inside the popup, in pixels.
     ///
@@ -124,6 +157,73 @@ pub struct MusicModule {
     #[serde(default = "default_cover_image_size")]
     pub(crate) cover_image_size: i32,
 
+    /// The action to perform when left-clicking the bar widget.
+    ///
+    /// **Valid options**: `none`, `popup`, `play_pause`, `next`, `previous`, `raise`
+    /// <br />
+    /// **Default**: `popup`
+    #[serde(default = "default_left_click_action")]
+    pub(crate) left_click_action: ClickAction,
+
+    /// The action to perform when middle-clicking the bar widget.
+    ///
+    /// **Valid options**: `none`, `popup`, `play_pause`, `next`, `previous`, `raise`
+    /// <br />
+    /// **Default**: `none`
+    #[serde(default = "default_click_action")]
+    pub(crate) middle_click_action: ClickAction,
+
+    /// The action to perform when right-clicking the bar widget.
+    ///
+    /// **Valid options**: `none`, `popup`, `play_pause`, `next`, `previous`, `raise`
+    /// <br />
+    /// **Default**: `none`
+    #[serde(default = "default_click_action")]
+    pub(crate) right_click_action: ClickAction,
+
+    /// The action to perform when scrolling over the bar widget.
+    ///
+    /// **Valid options**: `none`, `seek`, `volume`
+    /// <br />
+    /// **Default**: `none`
+    #[serde(default)]
+    pub(crate) scroll_action: ScrollAction,
+
+    /// The number of seconds to seek by for each scroll step,
+    /// when `scroll_action` is `seek`.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_seek_step")]
+    pub(crate) seek_step: u64,
+
+    /// The amount to change the volume by, in percent, for each scroll step,
+    /// when `scroll_action` is `volume`.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_volume_step")]
+    pub(crate) volume_step: u8,
+
+    /// The number of upcoming tracks to show in the popup.
+    /// Clicking a track skips to it. Set to `0` to hide the queue.
+    ///
+    /// This is only available for MPRIS players implementing the `TrackList` interface,
+    /// and shows at most 10 tracks.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_queue_length")]
+    pub(crate) queue_length: usize,
+
+    /// Whether to show a dropdown in the popup
+    /// for moving the player's audio to a different output device.
+    ///
+    /// The player's audio stream is found by matching its name
+    /// against the application names of PulseAudio or PipeWire streams.
+    ///
+    /// **Default**: `false`
+    #[cfg(feature = "volume")]
+    #[serde(default)]
+    pub(crate) show_output_switcher: bool,
+
     // -- MPD --
     /// *[MPD Only]*
     /// TCP or Unix socket address of the MPD server.
@@ -196,10 +296,30 @@ fn default_music_dir() -> PathBuf {
     audio_dir().unwrap_or_else(|| home_dir().map(|dir| dir.join("Music")).unwrap_or_default())
 }
 
-const fn default_icon_size() -> i32 {
+pub(super) const fn default_icon_size() -> i32 {
     24
 }
 
 const fn default_cover_image_size() -> i32 {
     128
 }
+
+const fn default_left_click_action() -> ClickAction {
+    ClickAction::Popup
+}
+
+const fn default_click_action() -> ClickAction {
+    ClickAction::None
+}
+
+const fn default_seek_step() -> u64 {
+    5
+}
+
+const fn default_volume_step() -> u8 {
+    5
+}
+
+const fn default_queue_length() -> usize {
+    5
+}
@@ -1,11 +1,14 @@
-use std::cell::RefMut;
+use std::cell::{Cell, RefMut};
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 use color_eyre::Result;
 use glib::{markup_escape_text, Propagation, PropertySet};
+use gtk::gdk::{EventMask, ScrollDirection};
+use gtk::pango::EllipsizeMode;
 use gtk::prelude::*;
 use gtk::{Button, IconTheme, Label, Orientation, Scale};
 use regex::Regex;
@@ -13,8 +16,10 @@ use tokio::sync::{broadcast, mpsc};
 use tracing::error;
 
 use crate::clients::music::{
-    self, MusicClient, PlayerState, PlayerUpdate, ProgressTick, Status, Track,
+    self, MusicClient, PlayerState, PlayerUpdate, ProgressTick, QueueTrack, Status, Track,
 };
+#[cfg(feature = "volume")]
+use crate::clients::volume;
 use crate::clients::Clients;
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::{new_icon_button, new_icon_label, ImageProvider};
@@ -25,9 +30,13 @@ use crate::modules::{
 use crate::{glib_recv, module_impl, send_async, spawn, try_send};
 
 pub use self::config::MusicModule;
-use self::config::PlayerType;
+use self::config::{default_icon_size, ClickAction, PlayerType, ScrollAction};
+#[cfg(feature = "volume")]
+use self::output::OutputState;
 
 mod config;
+#[cfg(feature = "volume")]
+mod output;
 
 #[derive(Debug)]
 pub enum PlayerCommand {
@@ -37,6 +46,12 @@ pub enum PlayerCommand {
     Next,
     Volume(u8),
     Seek(Duration),
+    Raise,
+    /// Skips to the queued track with the given ID.
+    GoTo(String),
+    /// Moves the player's audio to the sink with the given name.
+    #[cfg(feature = "volume")]
+    MoveOutput(String),
 }
 
 /// Formats a duration given in seconds
@@ -60,12 +75,15 @@ fn get_tokens(re: &Regex, format_string: &str) -> Vec<String> {
 pub enum ControllerEvent {
     Update(Option<SongUpdate>),
     UpdateProgress(ProgressTick),
+    #[cfg(feature = "volume")]
+    Output(OutputState),
 }
 
 #[derive(Clone, Debug)]
 pub struct SongUpdate {
     song: Track,
     status: Status,
+    queue: Vec<QueueTrack>,
     display_string: String,
 }
 
@@ -118,14 +136,15 @@ impl Module<Button> for MusicModule {
 
                     while let Ok(update) = rx.recv().await {
                         match update {
-                            PlayerUpdate::Update(track, status) => match *track {
+                            PlayerUpdate::Update(track, status, queue) => match *track {
                                 Some(track) => {
                                     let display_string =
-                                        replace_tokens(format.as_str(), &tokens, &track);
+                                        replace_tokens(format.as_str(), &tokens, &track, &queue);
 
                                     let update = SongUpdate {
                                         song: track,
                                         status,
+                                        queue,
                                         display_string,
                                     };
 
@@ -153,6 +172,16 @@ impl Module<Button> for MusicModule {
             });
         }
 
+        #[cfg(feature = "volume")]
+        let volume = self
+            .show_output_switcher
+            .then(|| context.client::<volume::Client>());
+
+        #[cfg(feature = "volume")]
+        if let Some(volume) = &volume {
+            output::spawn_output_watcher(volume.clone(), client.clone(), context.tx.clone());
+        }
+
         // listen to ui events
         {
             spawn(async move {
@@ -164,6 +193,15 @@ impl Module<Button> for MusicModule {
                         PlayerCommand::Next => client.next(),
                         PlayerCommand::Volume(vol) => client.set_volume_percent(vol),
                         PlayerCommand::Seek(duration) => client.seek(duration),
+                        PlayerCommand::Raise => client.raise(),
+                        PlayerCommand::GoTo(id) => client.go_to(&id),
+                        #[cfg(feature = "volume")]
+                        PlayerCommand::MoveOutput(sink) => {
+                            if let Some(volume) = &volume {
+                                output::move_player_output(volume, &client.player_names(), &sink);
+                            }
+                            Ok(())
+                        }
                     };
 
                     if let Err(err) = res {
@@ -187,8 +225,9 @@ impl Module<Button> for MusicModule {
 
         button.add(&button_contents);
 
-        let icon_play = new_icon_label(&self.icons.play, info.icon_theme, self.icon_size);
-        let icon_pause = new_icon_label(&self.icons.pause, info.icon_theme, self.icon_size);
+        let icon_size = info.icon_size(self.icon_size, default_icon_size());
+        let icon_play = new_icon_label(&self.icons.play, info.icon_theme, icon_size);
+        let icon_pause = new_icon_label(&self.icons.pause, info.icon_theme, icon_size);
         let label = Label::new(None);
 
         label.set_use_markup(true);
@@ -202,11 +241,82 @@ impl Module<Button> for MusicModule {
         button_contents.add(&icon_play);
         button_contents.add(&label);
 
+        // latest player state, used to work out the commands for click and scroll actions
+        let status = Rc::new(Cell::new(Status::default()));
+        let elapsed = Rc::new(Cell::new(None));
+
         {
             let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+            let status = status.clone();
+            let action = self.left_click_action;
 
             button.connect_clicked(move |button| {
-                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+                run_click_action(action, button, status.get(), &tx, &controller_tx);
+            });
+        }
+
+        {
+            let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+            let status = status.clone();
+            let middle_click_action = self.middle_click_action;
+            let right_click_action = self.right_click_action;
+
+            button.connect_button_release_event(move |button, event| {
+                let action = match event.button() {
+                    2 => middle_click_action,
+                    3 => right_click_action,
+                    _ => return Propagation::Proceed,
+                };
+
+                run_click_action(action, button, status.get(), &tx, &controller_tx);
+                Propagation::Proceed
+            });
+        }
+
+        if self.scroll_action != ScrollAction::None {
+            button.add_events(EventMask::SCROLL_MASK);
+
+            let tx = context.controller_tx.clone();
+            let status = status.clone();
+            let elapsed = elapsed.clone();
+            let action = self.scroll_action;
+            let seek_step = Duration::from_secs(self.seek_step);
+            let volume_step = self.volume_step;
+
+            button.connect_scroll_event(move |_, event| {
+                let up = match event.direction() {
+                    ScrollDirection::Up => true,
+                    ScrollDirection::Down => false,
+                    ScrollDirection::Smooth if event.delta().1 < 0.0 => true,
+                    ScrollDirection::Smooth if event.delta().1 > 0.0 => false,
+                    _ => return Propagation::Proceed,
+                };
+
+                let command = match action {
+                    ScrollAction::Seek => elapsed.get().map(|elapsed: Duration| {
+                        PlayerCommand::Seek(if up {
+                            elapsed + seek_step
+                        } else {
+                            elapsed.saturating_sub(seek_step)
+                        })
+                    }),
+                    ScrollAction::Volume => status.get().volume_percent.map(|volume| {
+                        PlayerCommand::Volume(if up {
+                            volume.saturating_add(volume_step).min(100)
+                        } else {
+                            volume.saturating_sub(volume_step)
+                        })
+                    }),
+                    ScrollAction::None => None,
+                };
+
+                if let Some(command) = command {
+                    try_send!(tx, command);
+                }
+
+                Propagation::Stop
             });
         }
 
@@ -217,11 +327,18 @@ impl Module<Button> for MusicModule {
             let rx = context.subscribe();
 
             glib_recv!(rx, event => {
-                let ControllerEvent::Update(mut event) = event else {
-                    continue;
+                let mut event = match event {
+                    ControllerEvent::Update(event) => event,
+                    ControllerEvent::UpdateProgress(progress_tick) => {
+                        elapsed.set(progress_tick.elapsed);
+                        continue;
+                    }
+                    #[cfg(feature = "volume")]
+                    ControllerEvent::Output(_) => continue,
                 };
 
                 if let Some(event) = event.take() {
+                    status.set(event.status);
                     label.set_label(&event.display_string);
 
                     button.show();
@@ -246,6 +363,9 @@ impl Module<Button> for MusicModule {
                         icon_play.hide();
                     }
                 } else {
+                    status.set(Status::default());
+                    elapsed.set(None);
+
                     button.hide();
                     try_send!(tx, ModuleUpdateEvent::ClosePopup);
                 }
@@ -268,6 +388,7 @@ impl Module<Button> for MusicModule {
         info: &ModuleInfo,
     ) -> Option<gtk::Box> {
         let icon_theme = info.icon_theme;
+        let icon_size = info.icon_size(self.icon_size, default_icon_size());
 
         let container = gtk::Box::new(Orientation::Vertical, 10);
         let main_container = gtk::Box::new(Orientation::Horizontal, 10);
@@ -296,16 +417,16 @@ impl Module<Button> for MusicModule {
         let controls_box = gtk::Box::new(Orientation::Horizontal, 0);
         controls_box.add_class("controls");
 
-        let btn_prev = new_icon_button(&icons.prev, icon_theme, self.icon_size);
+        let btn_prev = new_icon_button(&icons.prev, icon_theme, icon_size);
         btn_prev.add_class("btn-prev");
 
-        let btn_play = new_icon_button(&icons.play, icon_theme, self.icon_size);
+        let btn_play = new_icon_button(&icons.play, icon_theme, icon_size);
         btn_play.add_class("btn-play");
 
-        let btn_pause = new_icon_button(&icons.pause, icon_theme, self.icon_size);
+        let btn_pause = new_icon_button(&icons.pause, icon_theme, icon_size);
         btn_pause.add_class("btn-pause");
 
-        let btn_next = new_icon_button(&icons.next, icon_theme, self.icon_size);
+        let btn_next = new_icon_button(&icons.next, icon_theme, icon_size);
         btn_next.add_class("btn-next");
 
         controls_box.add(&btn_prev);
@@ -322,7 +443,7 @@ impl Module<Button> for MusicModule {
         volume_slider.set_inverted(true);
         volume_slider.add_class("slider");
 
-        let volume_icon = new_icon_label(&icons.volume, icon_theme, self.icon_size);
+        let volume_icon = new_icon_label(&icons.volume, icon_theme, icon_size);
         volume_icon.add_class("icon");
 
         volume_box.pack_start(&volume_slider, true, true, 0);
@@ -376,6 +497,17 @@ impl Module<Button> for MusicModule {
         progress_box.add(&progress_label);
         container.add(&progress_box);
 
+        let queue_box = gtk::Box::new(Orientation::Vertical, 0);
+        queue_box.add_class("queue");
+        container.add(&queue_box);
+
+        #[cfg(feature = "volume")]
+        let output_selector = {
+            let output_selector = output::OutputSelector::new(tx.clone());
+            container.add(&output_selector.selector);
+            output_selector
+        };
+
         let drag_lock = Arc::new(AtomicBool::new(false));
         {
             let drag_lock = drag_lock.clone();
@@ -386,6 +518,7 @@ impl Module<Button> for MusicModule {
         }
 
         {
+            let tx = tx.clone();
             let drag_lock = drag_lock.clone();
             progress.connect_button_release_event(move |scale, _| {
                 let value = scale.value();
@@ -397,12 +530,15 @@ impl Module<Button> for MusicModule {
         }
 
         container.show_all();
+        queue_box.hide();
 
         {
             let icon_theme = icon_theme.clone();
-            let image_size = self.cover_image_size;
+            let image_size = info.scaled(self.cover_image_size);
+            let queue_length = self.queue_length;
 
             let mut prev_cover = None;
+            let mut prev_queue = vec![];
             glib_recv!(rx, event =>  {
                 match event {
                     ControllerEvent::Update(Some(update)) => {
@@ -466,6 +602,15 @@ impl Module<Button> for MusicModule {
                         } else {
                             volume_box.hide();
                         }
+
+                        // the queue is sent with every update, so only rebuild when it changes
+                        let mut queue = update.queue;
+                        queue.truncate(queue_length);
+
+                        if queue != prev_queue {
+                            update_queue(&queue_box, &queue, &tx);
+                            prev_queue = queue;
+                        }
                     }
                     ControllerEvent::UpdateProgress(progress_tick)
                         if !drag_lock.load(Ordering::Relaxed) =>
@@ -486,6 +631,8 @@ impl Module<Button> for MusicModule {
                             progress_box.hide();
                         }
                     }
+                    #[cfg(feature = "volume")]
+                    ControllerEvent::Output(state) => output_selector.update(&state),
                     _ => {}
                 };
             });
@@ -495,6 +642,71 @@ impl Module<Button> for MusicModule {
     }
 }
 
+/// Performs a click action on the bar widget.
+fn run_click_action(
+    action: ClickAction,
+    button: &Button,
+    status: Status,
+    tx: &mpsc::Sender<ModuleUpdateEvent<ControllerEvent>>,
+    controller_tx: &mpsc::Sender<PlayerCommand>,
+) {
+    let command = match action {
+        ClickAction::None => return,
+        ClickAction::Popup => {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            return;
+        }
+        ClickAction::PlayPause => match status.state {
+            PlayerState::Playing => PlayerCommand::Pause,
+            PlayerState::Paused | PlayerState::Stopped => PlayerCommand::Play,
+        },
+        ClickAction::Next => PlayerCommand::Next,
+        ClickAction::Previous => PlayerCommand::Previous,
+        ClickAction::Raise => PlayerCommand::Raise,
+    };
+
+    try_send!(controller_tx, command);
+}
+
+/// Rebuilds the list of upcoming tracks in the popup.
+/// Each track is a button which skips to it.
+fn update_queue(container: &gtk::Box, queue: &[QueueTrack], tx: &mpsc::Sender<PlayerCommand>) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    for track in queue {
+        let button = Button::new();
+        button.add_class("queue-track");
+
+        let text = match (&track.title, &track.artist) {
+            (Some(title), Some(artist)) => format!("{title} / {artist}"),
+            (Some(title), None) => title.clone(),
+            (None, Some(artist)) => artist.clone(),
+            (None, None) => String::from("Unknown track"),
+        };
+
+        let label = Label::new(Some(&text));
+        label.set_halign(gtk::Align::Start);
+        label.set_ellipsize(EllipsizeMode::End);
+        button.add(&label);
+
+        let tx = tx.clone();
+        let id = track.id.clone();
+        button.connect_clicked(move |_| {
+            try_send!(tx, PlayerCommand::GoTo(id.clone()));
+        });
+
+        container.add(&button);
+    }
+
+    if queue.is_empty() {
+        container.hide();
+    } else {
+        container.show_all();
+    }
+}
+
 fn update_popup_metadata_label(text: Option<String>, label: &IconLabel) {
     match text {
         Some(value) => {
@@ -509,10 +721,15 @@ fn update_popup_metadata_label(text: Option<String>, label: &IconLabel) {
 
 /// Replaces each of the formatting tokens in the formatting string
 /// with actual data pulled from the music player
-fn replace_tokens(format_string: &str, tokens: &Vec<String>, song: &Track) -> String {
+fn replace_tokens(
+    format_string: &str,
+    tokens: &Vec<String>,
+    song: &Track,
+    queue: &[QueueTrack],
+) -> String {
     let mut compiled_string = format_string.to_string();
     for token in tokens {
-        let value = get_token_value(song, token);
+        let value = get_token_value(song, queue, token);
         compiled_string = compiled_string.replace(format!("{{{token}}}").as_str(), value.as_str());
     }
     compiled_string
@@ -520,7 +737,7 @@ fn replace_tokens(format_string: &str, tokens: &Vec<String>, song: &Track) -> St
 
 /// Converts a string format token value
 /// into its respective value.
-fn get_token_value(song: &Track, token: &str) -> String {
+fn get_token_value(song: &Track, queue: &[QueueTrack], token: &str) -> String {
     match token {
         "title" => song.title.clone(),
         "album" => song.album.clone(),
@@ -529,6 +746,7 @@ fn get_token_value(song: &Track, token: &str) -> String {
         "disc" => song.disc.map(|x| x.to_string()),
         "genre" => song.genre.clone(),
         "track" => song.track.map(|x| x.to_string()),
+        "next_title" => queue.first().and_then(|next| next.title.clone()),
         _ => Some(token.to_string()),
     }
     .map(|str| markup_escape_text(str.as_str()).to_string())
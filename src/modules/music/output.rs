@@ -0,0 +1,207 @@
+use std::sync::Arc;
+
+use gtk::pango::EllipsizeMode;
+use gtk::prelude::*;
+use gtk::{CellRendererText, ComboBoxText};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+
+use super::{ControllerEvent, PlayerCommand};
+use crate::clients::music::{MusicClient, PlayerUpdate};
+use crate::clients::volume::{self, SinkInput};
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::ModuleUpdateEvent;
+use crate::{lock, send_async, spawn, try_send};
+
+/// The audio outputs available to the player,
+/// and the one its streams are currently playing on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OutputState {
+    /// Sink names against their descriptions.
+    sinks: Vec<(String, String)>,
+    /// The name of the sink the player is playing on,
+    /// or `None` if no stream was found for the player.
+    current: Option<String>,
+}
+
+/// Watches for changes to the audio sinks and streams or the active player,
+/// and sends the output state whenever it changes.
+pub(super) fn spawn_output_watcher(
+    volume: Arc<volume::Client>,
+    music: Arc<dyn MusicClient>,
+    tx: mpsc::Sender<ModuleUpdateEvent<ControllerEvent>>,
+) {
+    spawn(async move {
+        let mut volume_rx = volume.subscribe();
+        let mut music_rx = music.subscribe_change();
+
+        let mut prev_state = None;
+
+        loop {
+            tokio::select! {
+                event = volume_rx.recv() => {
+                    if let Err(RecvError::Closed) = event {
+                        break;
+                    }
+                }
+                update = music_rx.recv() => match update {
+                    Ok(PlayerUpdate::ProgressTick(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                    _ => {}
+                },
+            }
+
+            let state = output_state(&volume, &music.player_names());
+            if prev_state.as_ref() != Some(&state) {
+                prev_state = Some(state.clone());
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(ControllerEvent::Output(state))
+                );
+            }
+        }
+    });
+}
+
+/// Moves all of the player's streams to the sink with the given name.
+pub(super) fn move_player_output(volume: &volume::Client, player_names: &[String], sink: &str) {
+    let inputs = volume.sink_inputs();
+    let indexes = player_inputs(&lock!(inputs), player_names)
+        .map(|input| input.index)
+        .collect::<Vec<_>>();
+
+    for index in indexes {
+        volume.move_input_to_sink(index, sink);
+    }
+}
+
+fn output_state(volume: &volume::Client, player_names: &[String]) -> OutputState {
+    let sinks = volume.sinks();
+    let sinks = lock!(sinks);
+
+    let inputs = volume.sink_inputs();
+    let current = player_inputs(&lock!(inputs), player_names)
+        .next()
+        .and_then(|input| sinks.iter().find(|sink| sink.index == input.sink))
+        .map(|sink| sink.name.clone());
+
+    OutputState {
+        sinks: sinks
+            .iter()
+            .map(|sink| (sink.name.clone(), sink.description.clone()))
+            .collect(),
+        current,
+    }
+}
+
+/// Gets the streams which belong to the player,
+/// matching on the application name or binary of each stream.
+fn player_inputs<'a>(
+    inputs: &'a [SinkInput],
+    player_names: &'a [String],
+) -> impl Iterator<Item = &'a SinkInput> {
+    inputs.iter().filter(move |input| {
+        [&input.application_binary, &input.application_name]
+            .into_iter()
+            .flatten()
+            .any(|name| player_names.contains(&name.to_lowercase()))
+    })
+}
+
+/// Dropdown in the popup for moving the player's streams to another sink.
+pub(super) struct OutputSelector {
+    pub(super) selector: ComboBoxText,
+    handler: glib::SignalHandlerId,
+}
+
+impl OutputSelector {
+    pub(super) fn new(tx: mpsc::Sender<PlayerCommand>) -> Self {
+        let selector = ComboBoxText::new();
+        selector.add_class("output");
+
+        // shown once the player's stream is found
+        selector.set_no_show_all(true);
+
+        let renderer = selector
+            .cells()
+            .first()
+            .expect("to exist")
+            .clone()
+            .downcast::<CellRendererText>()
+            .expect("to be valid cast");
+
+        renderer.set_width_chars(20);
+        renderer.set_ellipsize(EllipsizeMode::End);
+
+        let handler = selector.connect_changed(move |selector| {
+            if let Some(name) = selector.active_id() {
+                try_send!(tx, PlayerCommand::MoveOutput(name.into()));
+            }
+        });
+
+        Self { selector, handler }
+    }
+
+    pub(super) fn update(&self, state: &OutputState) {
+        // avoid moving the streams in response to our own changes
+        self.selector.block_signal(&self.handler);
+
+        self.selector.remove_all();
+        for (name, description) in &state.sinks {
+            self.selector.append(Some(name), description);
+        }
+
+        match &state.current {
+            Some(current) => {
+                self.selector.set_active_id(Some(current));
+                self.selector.show();
+            }
+            None => self.selector.hide(),
+        }
+
+        self.selector.unblock_signal(&self.handler);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(index: u32, name: Option<&str>, binary: Option<&str>) -> SinkInput {
+        SinkInput {
+            index,
+            name: String::new(),
+            volume: 100.0,
+            muted: false,
+            can_set_volume: true,
+            sink: 0,
+            application_name: name.map(ToString::to_string),
+            application_binary: binary.map(ToString::to_string),
+        }
+    }
+
+    #[test]
+    fn test_player_inputs() {
+        let inputs = [
+            input(1, Some("Firefox"), Some("firefox")),
+            input(2, Some("Spotify"), Some("spotify")),
+            input(3, Some("Music Player Daemon"), None),
+            input(4, None, None),
+        ];
+
+        let names = [String::from("spotify"), String::from("spotify")];
+        let matched = player_inputs(&inputs, &names)
+            .map(|input| input.index)
+            .collect::<Vec<_>>();
+        assert_eq!(matched, [2]);
+
+        let names = [String::from("mpd"), String::from("music player daemon")];
+        let matched = player_inputs(&inputs, &names)
+            .map(|input| input.index)
+            .collect::<Vec<_>>();
+        assert_eq!(matched, [3]);
+
+        let matched = player_inputs(&inputs, &[]).count();
+        assert_eq!(matched, 0);
+    }
+}
@@ -0,0 +1,242 @@
+use chrono::{DateTime, Local};
+
+use super::quota::QuotaUsage;
+use crate::clients::networkmanager::dns::DnsState;
+use crate::clients::networkmanager::hotspot::HotspotState;
+use crate::clients::networkmanager::ip::IpAddress;
+use crate::clients::networkmanager::modem::ModemDetails;
+use crate::clients::networkmanager::state::{
+    CellularState, InterfaceKind, State, VpnState, WiredConnectedState,
+};
+use crate::clients::networkmanager::statistics::ConnectionUsage;
+
+/// Formats a Unix timestamp as a local date and time.
+pub fn format_timestamp(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0).map_or_else(
+        || String::from("unknown"),
+        |time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        },
+    )
+}
+
+/// Describes a running hotspot, and how many devices are connected to it.
+pub fn hotspot_tooltip(hotspot: &HotspotState) -> String {
+    match hotspot.clients {
+        Some(1) => format!("Hotspot: {}\n1 device connected", hotspot.connection),
+        Some(clients) => format!(
+            "Hotspot: {}\n{clients} devices connected",
+            hotspot.connection
+        ),
+        None => format!("Hotspot: {}", hotspot.connection),
+    }
+}
+
+/// Replaces the data usage, throughput, VPN and quota tokens in the format string.
+/// Quota tokens are replaced with an empty string if no quotas are set,
+/// and the VPN name while no VPN is connected.
+pub fn replace_tokens(format: &str, state: &State, quota: Option<&QuotaUsage>) -> String {
+    let vpn_name = match &state.vpn {
+        VpnState::Connected(vpn) => vpn.name.as_str(),
+        VpnState::Disconnected | VpnState::Unknown => "",
+    };
+
+    let format = replace_usage_tokens(format, &state.data_usage)
+        .replace("{rate_rx}", &format_rate(state.throughput.rx_rate))
+        .replace("{rate_tx}", &format_rate(state.throughput.tx_rate))
+        .replace("{vpn_name}", vpn_name);
+
+    let (used, limit, percent) = quota.map_or_else(Default::default, |quota| {
+        (
+            format_bytes(quota.used),
+            format_bytes(quota.limit),
+            format!("{:.0}", quota.percent()),
+        )
+    });
+
+    format
+        .replace("{quota_used}", &used)
+        .replace("{quota_limit}", &limit)
+        .replace("{quota_percent}", &percent)
+}
+
+/// Replaces the tokens for a single connection type in its format string.
+/// Returns `None` while that connection is not active,
+/// so that its label is hidden and its tooltip left as the default.
+pub fn replace_connection_tokens(
+    format: &str,
+    kind: InterfaceKind,
+    state: &State,
+) -> Option<String> {
+    let address = |ip: Option<&IpAddress>| ip.map(|ip| ip.address.clone()).unwrap_or_default();
+    let cidr = |ip: Option<&IpAddress>| ip.map(ToString::to_string).unwrap_or_default();
+
+    let station = state.wifi_station.clone().unwrap_or_default();
+    let station_value =
+        |value: Option<f64>| value.map(|value| value.to_string()).unwrap_or_default();
+
+    let tokens = match kind {
+        InterfaceKind::Wired => match state.wired.connected() {
+            Some(wired) => vec![
+                ("{interface}", wired.interface.clone()),
+                (
+                    "{link_speed}",
+                    wired.speed.map(format_link_speed).unwrap_or_default(),
+                ),
+                ("{ip4}", address(wired.ip4.as_ref())),
+                ("{ip4_cidr}", cidr(wired.ip4.as_ref())),
+                ("{ip6}", address(wired.ip6.as_ref())),
+                ("{ip6_cidr}", cidr(wired.ip6.as_ref())),
+            ],
+            _ => return None,
+        },
+        InterfaceKind::Wifi => match state.wifi.connected() {
+            Some(wifi) => vec![
+                ("{ssid}", wifi.ssid.clone()),
+                ("{bssid}", wifi.bssid.clone().unwrap_or_default()),
+                ("{strength}", wifi.strength.to_string()),
+                ("{security}", wifi.security.to_string()),
+                ("{ip4}", address(wifi.ip4.as_ref())),
+                ("{ip4_cidr}", cidr(wifi.ip4.as_ref())),
+                ("{ip6}", address(wifi.ip6.as_ref())),
+                ("{ip6_cidr}", cidr(wifi.ip6.as_ref())),
+                (
+                    "{signal_dbm}",
+                    station
+                        .signal
+                        .map(|signal| signal.to_string())
+                        .unwrap_or_default(),
+                ),
+                ("{tx_bitrate}", station_value(station.tx_bitrate)),
+                ("{rx_bitrate}", station_value(station.rx_bitrate)),
+            ],
+            _ => return None,
+        },
+        InterfaceKind::Cellular => match &state.cellular {
+            CellularState::Connected(cellular) => {
+                let modem = cellular.modem.clone().unwrap_or_default();
+                vec![
+                    ("{signal}", modem.signal.to_string()),
+                    ("{technology}", modem.technology.to_string()),
+                    ("{operator}", modem.operator.unwrap_or_default()),
+                ]
+            }
+            _ => return None,
+        },
+        InterfaceKind::Vpn => match &state.vpn {
+            VpnState::Connected(vpn) => vec![("{vpn_name}", vpn.name.clone())],
+            VpnState::Disconnected | VpnState::Unknown => return None,
+        },
+    };
+
+    // values such as the SSID are set by others, so must not be parsed as markup
+    let text = tokens
+        .into_iter()
+        .fold(format.to_string(), |format, (token, value)| {
+            format.replace(token, &glib::markup_escape_text(&value))
+        });
+
+    Some(text)
+}
+
+/// Replaces the data usage tokens in the format string.
+///
+/// Tunnelled connections such as VPNs are excluded from the totals,
+/// as their traffic is already counted against the underlying connection.
+fn replace_usage_tokens(format: &str, data_usage: &[ConnectionUsage]) -> String {
+    let (rx_bytes, tx_bytes) = data_usage
+        .iter()
+        .filter(|usage| !usage.is_tunnel())
+        .fold((0, 0), |(rx, tx), usage| {
+            (rx + usage.rx_bytes, tx + usage.tx_bytes)
+        });
+
+    format
+        .replace("{data_rx}", &format_bytes(rx_bytes))
+        .replace("{data_tx}", &format_bytes(tx_bytes))
+        .replace("{data_total}", &format_bytes(rx_bytes + tx_bytes))
+}
+
+/// Formats a number of bytes using the largest fitting SI unit, ie `1.4 GB`.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// Formats a transfer rate in bytes per second, ie `1.4 MB/s`.
+fn format_rate(bytes: u64) -> String {
+    format!("{}/s", format_bytes(bytes))
+}
+
+/// Formats a speed in bits per second using the largest fitting SI unit, ie `93.5 Mbit/s`.
+pub fn format_speed(bits: f64) -> String {
+    const UNITS: [&str; 4] = ["bit/s", "kbit/s", "Mbit/s", "Gbit/s"];
+
+    let mut value = bits;
+    let mut unit = 0;
+
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+/// Formats a link speed in Mbit/s, ie `1.0 Gbit/s`.
+fn format_link_speed(speed: u32) -> String {
+    format_speed(f64::from(speed) * 1_000_000.0)
+}
+
+/// Describes a wired connection by its interface and link speed,
+/// ie `enp3s0 · 1.0 Gbit/s`.
+pub fn wired_tooltip(wired: &WiredConnectedState) -> String {
+    match wired.speed {
+        Some(speed) => format!("{} · {}", wired.interface, format_link_speed(speed)),
+        None => wired.interface.clone(),
+    }
+}
+
+/// Describes the DNS privacy state, one line per property.
+pub fn dns_tooltip(dns: &DnsState) -> String {
+    let on_off = |enabled| if enabled { "on" } else { "off" };
+
+    let mut lines = vec![
+        format!("DNS-over-TLS: {}", on_off(dns.over_tls)),
+        format!("DNSSEC: {}", on_off(dns.dnssec)),
+    ];
+
+    if dns.leak {
+        lines.push(String::from("DNS queries may bypass the VPN"));
+    }
+
+    lines.join("\n")
+}
+
+pub fn modem_tooltip(modem: &ModemDetails) -> String {
+    let mut tooltip = match &modem.operator {
+        Some(operator) => format!("{operator} ({}, {}%)", modem.technology, modem.signal),
+        None => format!("{} ({}%)", modem.technology, modem.signal),
+    };
+
+    if modem.roaming {
+        tooltip.push_str("\nRoaming");
+    }
+
+    tooltip
+}
@@ -0,0 +1,1378 @@
+use std::collections::HashSet;
+
+use chrono::Local;
+use color_eyre::Result;
+use futures_lite::StreamExt;
+use futures_signals::signal::SignalExt;
+use glib::Propagation;
+use gtk::prelude::*;
+use gtk::{Align, Box as GtkBox, Button, IconTheme, Image, Label, Orientation, Overlay};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error};
+
+use self::format::{
+    dns_tooltip, hotspot_tooltip, modem_tooltip, replace_connection_tokens, replace_tokens,
+    wired_tooltip,
+};
+use self::popup::{
+    build_airplane_mode, build_hotspot, build_portal, build_speed_test, connection_details,
+    update_connections_list, update_details_list, update_usage_list, update_vpn_list,
+    update_wifi_list,
+};
+use self::quota::{
+    current_quota, notify_quota, update_quota_classes, update_quota_list, DataQuota, QuotaUsage,
+};
+use crate::clients::networkmanager::dns::DnsState;
+use crate::clients::networkmanager::filter::InterfaceFilter;
+use crate::clients::networkmanager::quota::QuotaLevel;
+use crate::clients::networkmanager::roaming::NetworkHistory;
+use crate::clients::networkmanager::settings::{reorder_connections, SavedConnection};
+use crate::clients::networkmanager::state::{
+    CellularState, InterfaceKind, InterfaceState, InterfaceStatus, State, VpnState, WifiState,
+    WiredState,
+};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::ImageProvider;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, rc_mut, send_async, spawn, try_send};
+
+mod format;
+mod popup;
+mod quota;
+#[cfg(feature = "ipc")]
+mod registry;
+
+#[cfg(feature = "ipc")]
+pub use registry::{toggle_airplane_mode, toggle_wifi};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NetworkManagerModule {
+    icon_size: Option<i32>,
+
+    /// The network daemon to get the connection state from.
+    ///
+    /// `iwd` is for systems which run iwd without NetworkManager.
+    /// As iwd only manages wifi, other connection types are not shown.
+    /// Features which need NetworkManager, such as VPNs, hotspots, data usage,
+    /// quotas, network history and airplane mode, are unavailable.
+    ///
+    /// **Valid options**: `networkmanager`, `iwd`
+    /// <br>
+    /// **Default**: `networkmanager`
+    #[serde(default)]
+    backend: Backend,
+
+    /// How to show the connection state on the bar.
+    ///
+    /// - `aggregate` shows a single icon each for wired, wifi, cellular and VPN connections.
+    /// - `devices` shows an icon for each interface, such as `eth0`, `wlan0` and `wg0`.
+    ///
+    /// **Valid options**: `aggregate`, `devices`
+    /// <br>
+    /// **Default**: `aggregate`
+    #[serde(default)]
+    mode: DisplayMode,
+
+    /// Whether to show the interface name next to each icon in `devices` mode.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    device_names: bool,
+
+    /// Regular expressions for interfaces to leave out,
+    /// such as bridges and virtual devices created by container runtimes.
+    /// Ignored interfaces do not count towards the connection state,
+    /// and are not shown in `devices` mode.
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { ignore_interfaces = [ "^docker" "^veth" "^br-" ] }
+    /// ```
+    #[serde(default)]
+    ignore_interfaces: Vec<String>,
+
+    /// Regular expressions for the only interfaces to include.
+    /// If set, interfaces matching none of these are left out as if ignored.
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { only_interfaces = [ "^en" "^wl" ] }
+    /// ```
+    #[serde(default)]
+    only_interfaces: Vec<String>,
+
+    /// Format string for a label shown after the icons.
+    /// The label is hidden if not set.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `null`
+    format: Option<String>,
+
+    /// Format strings for labels shown next to the icon for each connection type,
+    /// while that connection is active.
+    /// For available tokens, see [below](#connection-formatting-tokens).
+    ///
+    /// These are only shown in `aggregate` mode.
+    ///
+    /// **Default**: `{}`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { formats.wifi = "{ssid} {strength}% {ip4}" }
+    /// ```
+    #[serde(default)]
+    formats: ConnectionFormats,
+
+    /// Format strings for the tooltip of each connection type's icon,
+    /// while that connection is active.
+    /// These replace the default tooltips, and use the same tokens as `formats`.
+    ///
+    /// **Default**: `{}`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { tooltips.wifi = "{ssid} ({bssid})\n{ip4_cidr}" }
+    /// ```
+    #[serde(default)]
+    tooltips: ConnectionFormats,
+
+    /// Shell command to run a speed test from the popup.
+    /// The speed test button is hidden if not set.
+    ///
+    /// The command should print the download speed, upload speed and ping,
+    /// either as JSON from `speedtest-cli --json` or Ookla's `speedtest -f json`,
+    /// or as `Download: 93.5 Mbit/s` style lines as printed by `speedtest-cli --simple`.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { speed_test_command = "speedtest-cli --simple" }
+    /// ```
+    speed_test_command: Option<String>,
+
+    /// Monthly data quotas for metered connections, such as mobile broadband.
+    /// Usage is counted across restarts, and resets each month.
+    ///
+    /// **Default**: `[]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// {
+    ///   quotas = [
+    ///     { connection = "Phone hotspot" limit_gb = 10 reset_day = 15 }
+    ///   ]
+    /// }
+    /// ```
+    #[serde(default)]
+    quotas: Vec<DataQuota>,
+
+    /// Whether to send a desktop notification
+    /// when a quota passes its warning threshold, and when it is used up.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    quota_notifications: bool,
+
+    /// Whether to show a list of nearby wifi networks in the popup.
+    /// Clicking a network connects to it.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    wifi_networks: bool,
+
+    /// Whether to keep a history of the wifi networks connected to,
+    /// including how often each is connected to, its typical signal strength,
+    /// and the access points (BSSIDs) recently used for it.
+    ///
+    /// This is shown by expanding a network in the popup,
+    /// which helps to diagnose roaming between access points of the same network.
+    /// The history is kept across restarts.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    network_history: bool,
+
+    /// The name of a saved hotspot connection to start and stop from the popup.
+    /// The hotspot switch is hidden if not set.
+    ///
+    /// Whether or not this is set, an icon with the number of connected devices
+    /// is shown while any hotspot is running.
+    ///
+    /// **Default**: `null`
+    hotspot: Option<String>,
+
+    /// Whether clicking the wifi icon turns the wifi radio on or off,
+    /// instead of opening the popup.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    wifi_toggle: bool,
+
+    /// Whether to show a list of saved VPN and WireGuard connections in the popup,
+    /// with a switch to connect or disconnect each.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    vpn_connections: bool,
+
+    /// Whether to show details of the current wifi and wired connections in the popup,
+    /// such as the SSID and IP address.
+    /// When compiled with the `clipboard` feature, each has a button to copy it.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    connection_details: bool,
+
+    /// The page to open to sign in to a captive portal,
+    /// if NetworkManager does not report its connectivity check URL.
+    /// Any plain HTTP page works, as the portal redirects it to its login page.
+    ///
+    /// **Default**: `http://neverssl.com`
+    #[serde(default = "default_portal_url")]
+    portal_url: String,
+
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+
+    /// The module's `name`, kept after the common options are taken
+    /// so that the module can be found over IPC.
+    #[serde(skip)]
+    instance_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DisplayMode {
+    #[default]
+    Aggregate,
+    Devices,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Backend {
+    #[default]
+    #[serde(rename = "networkmanager")]
+    NetworkManager,
+    Iwd,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ConnectionFormats {
+    /// Format string for the wired connection.
+    ///
+    /// **Default**: `null`
+    wired: Option<String>,
+
+    /// Format string for the wifi connection.
+    ///
+    /// **Default**: `null`
+    wifi: Option<String>,
+
+    /// Format string for the cellular connection.
+    ///
+    /// **Default**: `null`
+    cellular: Option<String>,
+
+    /// Format string for the VPN connection.
+    ///
+    /// **Default**: `null`
+    vpn: Option<String>,
+}
+
+impl ConnectionFormats {
+    fn get(&self, kind: InterfaceKind) -> Option<&String> {
+        match kind {
+            InterfaceKind::Wired => self.wired.as_ref(),
+            InterfaceKind::Wifi => self.wifi.as_ref(),
+            InterfaceKind::Cellular => self.cellular.as_ref(),
+            InterfaceKind::Vpn => self.vpn.as_ref(),
+        }
+    }
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+/// Wifi tokens which are read from the kernel using nl80211, rather than from NetworkManager.
+const STATION_TOKENS: [&str; 3] = ["{signal_dbm}", "{tx_bitrate}", "{rx_bitrate}"];
+
+fn default_portal_url() -> String {
+    String::from("http://neverssl.com")
+}
+
+#[derive(Debug, Clone)]
+pub enum NetworkManagerUpdate {
+    State(State),
+    Connections(Vec<SavedConnection>),
+    Quotas(Vec<QuotaUsage>),
+    NetworkHistory(NetworkHistory),
+}
+
+#[derive(Debug, Clone)]
+pub enum NetworkManagerEvent {
+    /// Reloads the list of saved connections.
+    RefreshConnections,
+    /// Moves a saved connection up (negative) or down (positive)
+    /// in the autoconnect priority order.
+    MoveConnection { path: String, offset: isize },
+    /// Runs the configured speed test command.
+    RunSpeedTest,
+    /// Connects to the wifi network with the given SSID.
+    ConnectWifi(String),
+    /// Turns the wifi radio on or off.
+    ToggleWifi,
+    /// Blocks or unblocks every radio.
+    SetAirplaneMode(bool),
+    /// Turns airplane mode on or off.
+    ToggleAirplaneMode,
+    /// Starts or stops the configured hotspot connection.
+    SetHotspotActive(bool),
+    /// Connects or disconnects the saved VPN connection at `path`.
+    SetVpnActive { path: String, active: bool },
+    /// Copies a connection detail, such as the IP address, to the clipboard.
+    #[cfg(feature = "clipboard")]
+    CopyToClipboard(String),
+}
+
+impl NetworkManagerModule {
+    /// Runs the controller using iwd rather than NetworkManager.
+    ///
+    /// iwd only manages wifi,
+    /// so events for features which need NetworkManager are logged and ignored.
+    fn spawn_iwd_controller(
+        &self,
+        context: &WidgetContext<NetworkManagerUpdate, NetworkManagerEvent>,
+        mut rx: mpsc::Receiver<NetworkManagerEvent>,
+        filter: &InterfaceFilter,
+    ) -> Result<()> {
+        let client = context
+            .ironbar
+            .clients
+            .borrow_mut()
+            .iwd_with_filter(filter)?;
+        let mut client_signal = client.subscribe().to_stream();
+        let widget_transmitter = context.tx.clone();
+
+        spawn(async move {
+            while let Some(state) = client_signal.next().await {
+                send_async!(
+                    widget_transmitter,
+                    ModuleUpdateEvent::Update(NetworkManagerUpdate::State(state))
+                );
+            }
+        });
+
+        #[cfg(feature = "clipboard")]
+        let clipboard = context.client::<crate::clients::clipboard::Client>();
+
+        let tx = context.tx.clone();
+        let speed_test_command = self.speed_test_command.clone();
+        spawn(async move {
+            while let Some(event) = rx.recv().await {
+                #[cfg(feature = "clipboard")]
+                if let NetworkManagerEvent::CopyToClipboard(text) = event {
+                    clipboard.copy_text(text);
+                    continue;
+                }
+
+                let client = client.clone();
+
+                match event {
+                    NetworkManagerEvent::RunSpeedTest => {
+                        if let Some(command) = speed_test_command.clone() {
+                            // the result is sent with the next state update
+                            spawn(async move {
+                                if let Err(err) = client.run_speed_test(&command).await {
+                                    error!("{err:?}");
+                                }
+                            });
+                        }
+                    }
+                    NetworkManagerEvent::ToggleWifi => {
+                        // the icon is updated from the resulting state change
+                        spawn(async move {
+                            if let Err(err) = client.toggle_wifi().await {
+                                error!("{:?}", err.wrap_err("Failed to toggle wifi"));
+                            }
+                        });
+                    }
+                    NetworkManagerEvent::ConnectWifi(ssid) => {
+                        // the new connection state is sent with the next state update
+                        spawn(async move {
+                            if let Err(err) = client.connect_wifi(&ssid).await {
+                                error!("{:?}", err.wrap_err("Failed to connect to wifi network"));
+                            }
+                        });
+                    }
+                    // iwd does not order its known networks,
+                    // so there are no connections to show
+                    NetworkManagerEvent::RefreshConnections => send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(NetworkManagerUpdate::Connections(vec![]))
+                    ),
+                    event => error!("{event:?} is not supported with the iwd backend"),
+                }
+            }
+        });
+
+        Ok(())
+    }
+}
+
+impl Module<Button> for NetworkManagerModule {
+    type SendMessage = NetworkManagerUpdate;
+    type ReceiveMessage = NetworkManagerEvent;
+
+    fn spawn_controller(
+        &self,
+        _: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let filter = InterfaceFilter::new(&self.ignore_interfaces, &self.only_interfaces)?;
+
+        #[cfg(feature = "ipc")]
+        registry::register(self.instance_name.clone(), &context.controller_tx);
+
+        if self.backend == Backend::Iwd {
+            return self.spawn_iwd_controller(context, rx, &filter);
+        }
+
+        let client = context
+            .ironbar
+            .clients
+            .borrow_mut()
+            .networkmanager_with_filter(&filter)?;
+        let mut client_signal = client.subscribe().to_stream();
+        let widget_transmitter = context.tx.clone();
+
+        spawn(async move {
+            while let Some(state) = client_signal.next().await {
+                send_async!(
+                    widget_transmitter,
+                    ModuleUpdateEvent::Update(NetworkManagerUpdate::State(state))
+                );
+            }
+        });
+
+        let uses_station_info = [&self.formats.wifi, &self.tooltips.wifi]
+            .into_iter()
+            .flatten()
+            .any(|format| STATION_TOKENS.iter().any(|token| format.contains(token)));
+
+        if uses_station_info {
+            client.track_station_info();
+        }
+
+        if !self.quotas.is_empty() {
+            client.track_usage();
+
+            let client = client.clone();
+            let quotas = self.quotas.clone();
+            let notify = self.quota_notifications;
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                let mut history_signal = client.subscribe_usage_history().to_stream();
+
+                while let Some(history) = history_signal.next().await {
+                    let today = Local::now().date_naive();
+                    let usages = quotas
+                        .iter()
+                        .map(|quota| quota.usage(&history, today))
+                        .collect::<Vec<_>>();
+
+                    for usage in &usages {
+                        if usage.level > QuotaLevel::Normal
+                            && client.raise_quota_level(
+                                &usage.connection,
+                                usage.period_start,
+                                usage.level,
+                            )
+                        {
+                            debug!("Data quota level reached: {usage:?}");
+
+                            if notify {
+                                if let Err(err) = notify_quota(usage).await {
+                                    error!("{:?}", err.wrap_err("Failed to send notification"));
+                                }
+                            }
+                        }
+                    }
+
+                    send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(NetworkManagerUpdate::Quotas(usages))
+                    );
+                }
+            });
+        }
+
+        if self.network_history {
+            client.track_network_history();
+
+            let mut history_signal = client.subscribe_network_history().to_stream();
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                while let Some(history) = history_signal.next().await {
+                    send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(NetworkManagerUpdate::NetworkHistory(history))
+                    );
+                }
+            });
+        }
+
+        #[cfg(feature = "clipboard")]
+        let clipboard = context.client::<crate::clients::clipboard::Client>();
+
+        let tx = context.tx.clone();
+        let speed_test_command = self.speed_test_command.clone();
+        let hotspot = self.hotspot.clone();
+        spawn(async move {
+            while let Some(event) = rx.recv().await {
+                #[cfg(feature = "clipboard")]
+                if let NetworkManagerEvent::CopyToClipboard(text) = event {
+                    clipboard.copy_text(text);
+                    continue;
+                }
+
+                let client = client.clone();
+
+                if let NetworkManagerEvent::RunSpeedTest = event {
+                    if let Some(command) = speed_test_command.clone() {
+                        // the result is sent with the next state update
+                        spawn(async move {
+                            if let Err(err) = client.run_speed_test(&command).await {
+                                error!("{err:?}");
+                            }
+                        });
+                    }
+                    continue;
+                }
+
+                if let NetworkManagerEvent::ToggleWifi = event {
+                    // the icon is updated from the resulting state change
+                    spawn(async move {
+                        if let Err(err) = client.toggle_wifi().await {
+                            error!("{:?}", err.wrap_err("Failed to toggle wifi"));
+                        }
+                    });
+                    continue;
+                }
+
+                if let NetworkManagerEvent::ToggleAirplaneMode = event {
+                    // the icon and switch are updated from the resulting state change
+                    spawn(async move {
+                        if let Err(err) = client.toggle_airplane_mode().await {
+                            error!("{:?}", err.wrap_err("Failed to change airplane mode"));
+                        }
+                    });
+                    continue;
+                }
+
+                if let NetworkManagerEvent::SetAirplaneMode(enabled) = event {
+                    // the icon and switch are updated from the resulting state change
+                    spawn(async move {
+                        if let Err(err) = client.set_airplane_mode(enabled).await {
+                            error!("{:?}", err.wrap_err("Failed to change airplane mode"));
+                        }
+                    });
+                    continue;
+                }
+
+                if let NetworkManagerEvent::SetHotspotActive(active) = event {
+                    if let Some(hotspot) = hotspot.clone() {
+                        // the switch is updated from the resulting state change
+                        spawn(async move {
+                            if let Err(err) = client.set_hotspot_active(&hotspot, active).await {
+                                error!("{:?}", err.wrap_err("Failed to change hotspot"));
+                            }
+                        });
+                    }
+                    continue;
+                }
+
+                if let NetworkManagerEvent::SetVpnActive { path, active } = event {
+                    // the switch is updated from the resulting state change
+                    spawn(async move {
+                        if let Err(err) = client.set_vpn_active(&path, active).await {
+                            error!("{:?}", err.wrap_err("Failed to change VPN connection"));
+                        }
+                    });
+                    continue;
+                }
+
+                if let NetworkManagerEvent::ConnectWifi(ssid) = event {
+                    // the new connection state is sent with the next state update
+                    spawn(async move {
+                        if let Err(err) = client.connect_wifi(&ssid).await {
+                            error!("{:?}", err.wrap_err("Failed to connect to wifi network"));
+                        }
+                    });
+                    continue;
+                }
+
+                let connections = async {
+                    if let NetworkManagerEvent::MoveConnection { path, offset } = event {
+                        let connections = client.saved_connections().await?;
+                        for (path, priority) in reorder_connections(&connections, &path, offset) {
+                            client.set_connection_priority(&path, priority).await?;
+                        }
+                    }
+
+                    client.saved_connections().await
+                }
+                .await;
+
+                match connections {
+                    Ok(connections) => send_async!(
+                        tx,
+                        ModuleUpdateEvent::Update(NetworkManagerUpdate::Connections(connections))
+                    ),
+                    Err(err) => error!("{err:?}"),
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let icon_size = info.icon_size(self.icon_size, default_icon_size());
+
+        let button = Button::new();
+
+        let container = GtkBox::new(Orientation::Horizontal, 0);
+        button.add(&container);
+
+        // the wired, wifi, cellular and VPN icons are replaced in `devices` mode
+        let aggregate_container = GtkBox::new(Orientation::Horizontal, 0);
+        container.add(&aggregate_container);
+
+        let devices_container = GtkBox::new(Orientation::Horizontal, 0);
+        devices_container.add_class("devices");
+        container.add(&devices_container);
+
+        match self.mode {
+            DisplayMode::Aggregate => devices_container.set_no_show_all(true),
+            DisplayMode::Devices => aggregate_container.set_no_show_all(true),
+        }
+
+        {
+            let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+                try_send!(controller_tx, NetworkManagerEvent::RefreshConnections);
+            });
+        }
+
+        // Wired icon
+        let wired_icon = Image::new();
+        wired_icon.add_class("icon");
+        wired_icon.add_class("wired-icon");
+        aggregate_container.add(&wired_icon);
+
+        let bar_angle = info.bar_position.get_angle();
+        let connection_label = |class: &str| {
+            let label = Label::builder().use_markup(true).angle(bar_angle).build();
+            label.add_class("label");
+            label.add_class(class);
+            label.set_no_show_all(true);
+            label
+        };
+
+        let wired_label = connection_label("wired-label");
+        aggregate_container.add(&wired_label);
+
+        // Wifi icon
+        let wifi_overlay = Overlay::new();
+        aggregate_container.add(&wifi_overlay);
+
+        let wifi_icon = Image::new();
+        wifi_icon.add_class("icon");
+        wifi_icon.add_class("wifi-icon");
+        wifi_overlay.add(&wifi_icon);
+
+        // Shown on top of the wifi icon when connected to an unsecured network
+        let wifi_security_icon = Image::new();
+        wifi_security_icon.add_class("icon");
+        wifi_security_icon.add_class("wifi-security-icon");
+        wifi_security_icon.set_halign(Align::End);
+        wifi_security_icon.set_valign(Align::End);
+        wifi_overlay.add_overlay(&wifi_security_icon);
+        wifi_overlay.set_overlay_pass_through(&wifi_security_icon, true);
+        ImageProvider::parse(
+            "icon:security-low-symbolic",
+            info.icon_theme,
+            false,
+            icon_size / 2,
+        )
+        .map(|provider| provider.load_into_image(wifi_security_icon.clone()));
+
+        let wifi_label = connection_label("wifi-label");
+        aggregate_container.add(&wifi_label);
+
+        // the button receives all clicks on its contents,
+        // so clicks on the wifi icon are picked out by position
+        if self.wifi_toggle && self.mode == DisplayMode::Aggregate {
+            let controller_tx = context.controller_tx.clone();
+            let wifi_overlay = wifi_overlay.clone();
+            let wifi_icon = wifi_icon.clone();
+
+            button.connect_button_press_event(move |button, event| {
+                if event.button() != 1
+                    || !wifi_icon.is_visible()
+                    || !contains_point(&wifi_overlay, button, event.position())
+                {
+                    return Propagation::Proceed;
+                }
+
+                try_send!(controller_tx, NetworkManagerEvent::ToggleWifi);
+                Propagation::Stop
+            });
+        }
+
+        // Cellular icon
+        let cellular_icon = Image::new();
+        cellular_icon.add_class("icon");
+        cellular_icon.add_class("cellular-icon");
+        aggregate_container.add(&cellular_icon);
+
+        let cellular_label = connection_label("cellular-label");
+        aggregate_container.add(&cellular_label);
+
+        // VPN icon
+        let vpn_icon = Image::new();
+        vpn_icon.add_class("icon");
+        vpn_icon.add_class("vpn-icon");
+        aggregate_container.add(&vpn_icon);
+
+        let vpn_label = connection_label("vpn-label");
+        aggregate_container.add(&vpn_label);
+
+        // Airplane mode icon, only shown while every radio is blocked.
+        // Clicking it turns airplane mode off.
+        let airplane_icon = Image::new();
+        airplane_icon.add_class("icon");
+        airplane_icon.add_class("airplane-mode-icon");
+        airplane_icon.set_tooltip_text(Some("Airplane mode"));
+        container.add(&airplane_icon);
+
+        {
+            let controller_tx = context.controller_tx.clone();
+            let airplane_icon = airplane_icon.clone();
+
+            button.connect_button_press_event(move |button, event| {
+                if event.button() != 1
+                    || !airplane_icon.is_visible()
+                    || !contains_point(&airplane_icon, button, event.position())
+                {
+                    return Propagation::Proceed;
+                }
+
+                try_send!(controller_tx, NetworkManagerEvent::SetAirplaneMode(false));
+                Propagation::Stop
+            });
+        }
+
+        // Hotspot icon, only shown while a wifi device is sharing its connection,
+        // with the number of connected devices next to it
+        let hotspot_icon = Image::new();
+        hotspot_icon.add_class("icon");
+        hotspot_icon.add_class("hotspot-icon");
+        container.add(&hotspot_icon);
+
+        let hotspot_label = connection_label("hotspot-label");
+        container.add(&hotspot_label);
+
+        // DNS privacy icon, only shown while queries are encrypted or leaking outside a VPN
+        let dns_icon = Image::new();
+        dns_icon.add_class("icon");
+        dns_icon.add_class("dns-icon");
+        container.add(&dns_icon);
+
+        let label = Label::builder().use_markup(true).angle(bar_angle).build();
+        label.add_class("label");
+        container.add(&label);
+
+        if self.format.is_none() {
+            label.set_no_show_all(true);
+        }
+
+        let icon_theme = info.icon_theme.clone();
+        let format = self.format.clone();
+        let formats = self.formats.clone();
+        let tooltips = self.tooltips.clone();
+        let mode = self.mode;
+        let device_names = self.device_names;
+        let widget = button.clone();
+        let mut last_state: Option<State> = None;
+        let mut quota = None;
+        glib_recv!(context.subscribe(), update => {
+            let state = match update {
+                NetworkManagerUpdate::State(state) => state,
+                NetworkManagerUpdate::Quotas(quotas) => {
+                    quota = current_quota(quotas);
+                    update_quota_classes(&widget, quota.as_ref());
+
+                    if let (Some(format), Some(state)) = (&format, &last_state) {
+                        label.set_markup(&replace_tokens(format, state, quota.as_ref()));
+                    }
+
+                    continue;
+                }
+                NetworkManagerUpdate::Connections(_)
+                | NetworkManagerUpdate::NetworkHistory(_) => continue,
+            };
+
+            if let Some(format) = &format {
+                label.set_markup(&replace_tokens(format, &state, quota.as_ref()));
+            }
+
+            for (connection_label, kind) in [
+                (&wired_label, InterfaceKind::Wired),
+                (&wifi_label, InterfaceKind::Wifi),
+                (&cellular_label, InterfaceKind::Cellular),
+                (&vpn_label, InterfaceKind::Vpn),
+            ] {
+                let text = formats
+                    .get(kind)
+                    .and_then(|format| replace_connection_tokens(format, kind, &state));
+
+                match text {
+                    Some(text) => {
+                        connection_label.set_markup(&text);
+                        connection_label.show();
+                    }
+                    None => connection_label.hide(),
+                }
+            }
+
+            macro_rules! update_icon {
+                (
+                    $icon_var:expr,
+                    $state_type:ident,
+                    {$($state:pat => $icon_name:expr,)+}
+                ) => {
+                    let icon_name = match state.$state_type {
+                        $($state => $icon_name,)+
+                    };
+                    if icon_name.is_empty() {
+                        $icon_var.hide();
+                    } else {
+                        ImageProvider::parse(icon_name, &icon_theme, false, icon_size)
+                            .map(|provider| provider.load_into_image($icon_var.clone()));
+                        $icon_var.show();
+                    }
+                };
+            }
+
+            update_icon!(wired_icon, wired, {
+                WiredState::Connected(_) => "icon:network-wired-symbolic",
+                WiredState::Limited(_) => "icon:network-wired-no-route-symbolic",
+                WiredState::Portal(_) => "icon:web-browser-symbolic",
+                WiredState::Disconnected => "icon:network-wired-disconnected-symbolic",
+                WiredState::NotPresent | WiredState::Unknown => "",
+            });
+            update_icon!(wifi_icon, wifi, {
+                WifiState::Connected(_) => "icon:network-wireless-connected-symbolic",
+                WifiState::Limited(_) => "icon:network-wireless-no-route-symbolic",
+                WifiState::Portal(_) => "icon:web-browser-symbolic",
+                WifiState::Connecting(_) => "icon:network-wireless-acquiring-symbolic",
+                WifiState::Disconnected => "icon:network-wireless-offline-symbolic",
+                WifiState::AuthFailed => "icon:network-error-symbolic",
+                WifiState::Disabled => "icon:network-wireless-hardware-disabled-symbolic",
+                WifiState::NotPresent | WifiState::Unknown => "",
+            });
+
+            match &state.wifi {
+                WifiState::Connected(wifi) | WifiState::Limited(wifi) | WifiState::Portal(wifi) => {
+                    let mut tooltip = match &wifi.eap {
+                        Some(eap) => format!("{} ({}, {eap})", wifi.ssid, wifi.security),
+                        None => format!("{} ({})", wifi.ssid, wifi.security),
+                    };
+                    match state.wifi {
+                        WifiState::Limited(_) => tooltip.push_str("\nLimited connectivity"),
+                        WifiState::Portal(_) => tooltip.push_str("\nSign in required"),
+                        _ => {}
+                    }
+                    wifi_icon.set_tooltip_text(Some(&tooltip));
+                    wifi_security_icon.set_visible(wifi.security.is_open());
+                }
+                WifiState::Connecting(wifi) => {
+                    let tooltip = match &wifi.eap {
+                        Some(eap) => format!("{}: {} ({eap})", wifi.connection, wifi.phase),
+                        None => format!("{}: {}", wifi.connection, wifi.phase),
+                    };
+                    wifi_icon.set_tooltip_text(Some(&tooltip));
+                    wifi_security_icon.hide();
+                }
+                WifiState::AuthFailed => {
+                    wifi_icon.set_tooltip_text(Some("Authentication failed"));
+                    wifi_security_icon.hide();
+                }
+                _ => {
+                    wifi_icon.set_tooltip_text(None);
+                    wifi_security_icon.hide();
+                }
+            }
+
+            if matches!(state.wifi, WifiState::AuthFailed) {
+                wifi_icon.add_class("auth-failed");
+            } else {
+                wifi_icon.style_context().remove_class("auth-failed");
+            }
+
+            for (icon, limited, portal) in [
+                (
+                    &wired_icon,
+                    matches!(state.wired, WiredState::Limited(_)),
+                    matches!(state.wired, WiredState::Portal(_)),
+                ),
+                (
+                    &wifi_icon,
+                    matches!(state.wifi, WifiState::Limited(_)),
+                    matches!(state.wifi, WifiState::Portal(_)),
+                ),
+            ] {
+                for (class, enabled) in [("limited", limited), ("portal", portal)] {
+                    if enabled {
+                        icon.add_class(class);
+                    } else {
+                        icon.style_context().remove_class(class);
+                    }
+                }
+            }
+
+            update_icon!(cellular_icon, cellular, {
+                CellularState::Connected(ref cellular) => match &cellular.modem {
+                    Some(modem) => cellular_signal_icon_name(modem.signal),
+                    None => "icon:network-cellular-connected-symbolic",
+                },
+                CellularState::Disconnected => "icon:network-cellular-offline-symbolic",
+                CellularState::Disabled => "icon:network-cellular-hardware-disabled-symbolic",
+                CellularState::NotPresent | CellularState::Unknown => "",
+            });
+
+            let modem = match &state.cellular {
+                CellularState::Connected(cellular) => cellular.modem.as_ref(),
+                _ => None,
+            };
+
+            cellular_icon.set_tooltip_text(modem.map(modem_tooltip).as_deref());
+
+            if modem.is_some_and(|modem| modem.roaming) {
+                cellular_icon.add_class("roaming");
+            } else {
+                cellular_icon.style_context().remove_class("roaming");
+            }
+            update_icon!(vpn_icon, vpn, {
+                VpnState::Connected(_) => "icon:network-vpn-symbolic",
+                VpnState::Disconnected | VpnState::Unknown => "",
+            });
+
+            match &state.vpn {
+                VpnState::Connected(vpn) => vpn_icon.set_tooltip_text(Some(&vpn.name)),
+                VpnState::Disconnected | VpnState::Unknown => vpn_icon.set_tooltip_text(None),
+            }
+
+            let wired_tooltip = state.wired.connected().map(|wired| {
+                let mut tooltip = wired_tooltip(wired);
+
+                match &state.wired {
+                    WiredState::Limited(_) => tooltip.push_str("\nLimited connectivity"),
+                    WiredState::Portal(_) => tooltip.push_str("\nSign in required"),
+                    _ => {}
+                }
+
+                tooltip
+            });
+            wired_icon.set_tooltip_text(wired_tooltip.as_deref());
+
+            // custom tooltips replace the defaults above while their connection is active
+            for (icon, kind) in [
+                (&wired_icon, InterfaceKind::Wired),
+                (&wifi_icon, InterfaceKind::Wifi),
+                (&cellular_icon, InterfaceKind::Cellular),
+                (&vpn_icon, InterfaceKind::Vpn),
+            ] {
+                let tooltip = tooltips
+                    .get(kind)
+                    .and_then(|format| replace_connection_tokens(format, kind, &state));
+
+                if let Some(tooltip) = tooltip {
+                    icon.set_tooltip_markup(Some(&tooltip));
+                }
+            }
+
+            update_icon!(airplane_icon, airplane_mode, {
+                Some(true) => "icon:airplane-mode-symbolic",
+                Some(false) | None => "",
+            });
+
+            update_icon!(hotspot_icon, hotspot, {
+                Some(_) => "icon:network-wireless-hotspot-symbolic",
+                None => "",
+            });
+
+            hotspot_icon.set_tooltip_text(state.hotspot.as_ref().map(hotspot_tooltip).as_deref());
+
+            match state.hotspot.as_ref().and_then(|hotspot| hotspot.clients) {
+                Some(clients) => {
+                    hotspot_label.set_label(&clients.to_string());
+                    hotspot_label.show();
+                }
+                None => hotspot_label.hide(),
+            }
+
+            update_icon!(dns_icon, dns, {
+                Some(DnsState { leak: true, .. }) => "icon:security-low-symbolic",
+                Some(DnsState { over_tls: true, .. }) => "icon:security-high-symbolic",
+                _ => "",
+            });
+
+            let dns_classes = [
+                ("leak", state.dns.as_ref().is_some_and(|dns| dns.leak)),
+                ("over-tls", state.dns.as_ref().is_some_and(|dns| dns.over_tls)),
+                ("dnssec", state.dns.as_ref().is_some_and(|dns| dns.dnssec)),
+            ];
+
+            for (class, enabled) in dns_classes {
+                if enabled {
+                    dns_icon.add_class(class);
+                } else {
+                    dns_icon.style_context().remove_class(class);
+                }
+            }
+
+            dns_icon.set_tooltip_text(state.dns.as_ref().map(dns_tooltip).as_deref());
+
+            if mode == DisplayMode::Devices
+                && last_state.as_ref().map(|last| &last.interfaces) != Some(&state.interfaces)
+            {
+                update_devices(
+                    &devices_container,
+                    &state.interfaces,
+                    &icon_theme,
+                    icon_size,
+                    device_names,
+                );
+            }
+
+            last_state = Some(state);
+        });
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<GtkBox> {
+        let container = GtkBox::new(Orientation::Vertical, 5);
+
+        let portal = build_portal(self.portal_url.clone());
+        container.add(&portal.container);
+
+        let airplane_mode = build_airplane_mode(&tx);
+        container.add(&airplane_mode.container);
+
+        let hotspot = self
+            .hotspot
+            .clone()
+            .map(|connection| build_hotspot(&tx, connection));
+
+        if let Some(hotspot) = &hotspot {
+            container.add(&hotspot.container);
+        }
+
+        let usage_container = GtkBox::new(Orientation::Vertical, 0);
+        usage_container.add_class("data-usage");
+        container.add(&usage_container);
+
+        let quota_container = GtkBox::new(Orientation::Vertical, 0);
+        quota_container.add_class("quotas");
+        container.add(&quota_container);
+
+        let speed_test = self
+            .speed_test_command
+            .is_some()
+            .then(|| build_speed_test(&tx));
+
+        if let Some(speed_test) = &speed_test {
+            container.add(&speed_test.container);
+        }
+
+        let details_container = GtkBox::new(Orientation::Vertical, 0);
+        details_container.add_class("connection-details");
+        container.add(&details_container);
+
+        let wifi_container = GtkBox::new(Orientation::Vertical, 0);
+        wifi_container.add_class("wifi-networks");
+        container.add(&wifi_container);
+
+        let vpn_container = GtkBox::new(Orientation::Vertical, 0);
+        vpn_container.add_class("vpn-connections");
+        container.add(&vpn_container);
+
+        let connections_container = GtkBox::new(Orientation::Vertical, 0);
+        connections_container.add_class("connections");
+        container.add(&connections_container);
+
+        let show_wifi_networks = self.wifi_networks;
+        let mut shown_wifi_networks = None;
+        let mut network_history: Option<NetworkHistory> = None;
+        // kept across rebuilds of the list
+        let expanded_networks = rc_mut!(HashSet::new());
+
+        let show_vpn_connections = self.vpn_connections;
+        let mut shown_vpn_connections = None;
+
+        let show_connection_details = self.connection_details;
+        let mut shown_connection_details = None;
+
+        glib_recv!(rx, update => {
+            let connections = match update {
+                NetworkManagerUpdate::Connections(connections) => connections,
+                NetworkManagerUpdate::State(state) => {
+                    portal.update(&state.connectivity);
+                    airplane_mode.update(state.airplane_mode);
+                    if let Some(hotspot) = &hotspot {
+                        hotspot.update(state.hotspot.as_ref());
+                    }
+                    update_usage_list(&usage_container, &state.data_usage);
+                    if let Some(speed_test) = &speed_test {
+                        speed_test.update(&state.speed_test);
+                    }
+
+                    if show_connection_details {
+                        let details = connection_details(&state);
+                        if shown_connection_details.as_ref() != Some(&details) {
+                            update_details_list(&details_container, &details, &tx);
+                            shown_connection_details = Some(details);
+                        }
+                    }
+
+                    if show_wifi_networks {
+                        let connected = state.wifi.connected().map(|wifi| wifi.ssid.clone());
+
+                        // state updates are frequent while data is transferred,
+                        // so only rebuild the list when it changes
+                        let networks = Some((state.wifi_networks, connected));
+                        if networks != shown_wifi_networks {
+                            if let Some((networks, connected)) = &networks {
+                                update_wifi_list(
+                                    &wifi_container,
+                                    networks,
+                                    connected.as_deref(),
+                                    network_history.as_ref(),
+                                    &expanded_networks,
+                                    &tx,
+                                );
+                            }
+                            shown_wifi_networks = networks;
+                        }
+                    }
+
+                    if show_vpn_connections
+                        && shown_vpn_connections.as_ref() != Some(&state.vpn_connections)
+                    {
+                        update_vpn_list(&vpn_container, &state.vpn_connections, &tx);
+                        shown_vpn_connections = Some(state.vpn_connections);
+                    }
+
+                    continue;
+                }
+                NetworkManagerUpdate::Quotas(quotas) => {
+                    update_quota_list(&quota_container, &quotas);
+                    continue;
+                }
+                NetworkManagerUpdate::NetworkHistory(history) => {
+                    if let Some((networks, connected)) = &shown_wifi_networks {
+                        update_wifi_list(
+                            &wifi_container,
+                            networks,
+                            connected.as_deref(),
+                            Some(&history),
+                            &expanded_networks,
+                            &tx,
+                        );
+                    }
+
+                    network_history = Some(history);
+                    continue;
+                }
+            };
+
+            update_connections_list(&connections_container, &connections, &tx);
+        });
+
+        container.show_all();
+
+        Some(container)
+    }
+
+    fn name() -> &'static str {
+        "networkmanager"
+    }
+
+    fn take_common(&mut self) -> CommonConfig {
+        let common = self.common.take().unwrap_or_default();
+        self.instance_name.clone_from(&common.name);
+        common
+    }
+}
+
+/// Rebuilds the icon for each interface, shown in `devices` mode.
+fn update_devices(
+    container: &GtkBox,
+    interfaces: &[InterfaceState],
+    icon_theme: &IconTheme,
+    icon_size: i32,
+    show_names: bool,
+) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    for interface in interfaces {
+        let device = GtkBox::new(Orientation::Horizontal, 0);
+        device.add_class("device");
+        device.add_class(match interface.kind {
+            InterfaceKind::Wired => "wired",
+            InterfaceKind::Wifi => "wifi",
+            InterfaceKind::Cellular => "cellular",
+            InterfaceKind::Vpn => "vpn",
+        });
+        device.add_class(&interface.status.to_string());
+
+        let tooltip = match &interface.connection {
+            Some(connection) => format!("{}: {connection} ({})", interface.name, interface.status),
+            None => format!("{}: {}", interface.name, interface.status),
+        };
+        device.set_tooltip_text(Some(&tooltip));
+
+        let icon = Image::new();
+        icon.add_class("icon");
+        ImageProvider::parse(interface_icon_name(interface), icon_theme, false, icon_size)
+            .map(|provider| provider.load_into_image(icon.clone()));
+        device.add(&icon);
+
+        if show_names {
+            let name = Label::new(Some(&interface.name));
+            name.add_class("name");
+            device.add(&name);
+        }
+
+        container.add(&device);
+    }
+
+    container.show_all();
+}
+
+fn interface_icon_name(interface: &InterfaceState) -> &'static str {
+    match (interface.kind, interface.status) {
+        (InterfaceKind::Wired, InterfaceStatus::Connected) => "icon:network-wired-symbolic",
+        (InterfaceKind::Wired, InterfaceStatus::Connecting) => {
+            "icon:network-wired-acquiring-symbolic"
+        }
+        (InterfaceKind::Wired, InterfaceStatus::Disconnected) => {
+            "icon:network-wired-disconnected-symbolic"
+        }
+        (InterfaceKind::Wired, InterfaceStatus::Unavailable) => {
+            "icon:network-wired-offline-symbolic"
+        }
+        (InterfaceKind::Wifi, InterfaceStatus::Connected) => {
+            "icon:network-wireless-connected-symbolic"
+        }
+        (InterfaceKind::Wifi, InterfaceStatus::Connecting) => {
+            "icon:network-wireless-acquiring-symbolic"
+        }
+        (InterfaceKind::Wifi, InterfaceStatus::Disconnected) => {
+            "icon:network-wireless-offline-symbolic"
+        }
+        (InterfaceKind::Wifi, InterfaceStatus::Unavailable) => {
+            "icon:network-wireless-hardware-disabled-symbolic"
+        }
+        (InterfaceKind::Cellular, InterfaceStatus::Connected) => {
+            "icon:network-cellular-connected-symbolic"
+        }
+        (InterfaceKind::Cellular, InterfaceStatus::Connecting) => {
+            "icon:network-cellular-acquiring-symbolic"
+        }
+        (InterfaceKind::Cellular, InterfaceStatus::Disconnected) => {
+            "icon:network-cellular-offline-symbolic"
+        }
+        (InterfaceKind::Cellular, InterfaceStatus::Unavailable) => {
+            "icon:network-cellular-hardware-disabled-symbolic"
+        }
+        (InterfaceKind::Vpn, InterfaceStatus::Connected) => "icon:network-vpn-symbolic",
+        (InterfaceKind::Vpn, InterfaceStatus::Connecting) => "icon:network-vpn-acquiring-symbolic",
+        (InterfaceKind::Vpn, InterfaceStatus::Disconnected | InterfaceStatus::Unavailable) => {
+            "icon:network-vpn-disconnected-symbolic"
+        }
+    }
+}
+
+/// Gets the symbolic icon for a cellular signal quality percentage.
+fn cellular_signal_icon_name(signal: u8) -> &'static str {
+    match signal {
+        0..=19 => "icon:network-cellular-signal-none-symbolic",
+        20..=39 => "icon:network-cellular-signal-weak-symbolic",
+        40..=59 => "icon:network-cellular-signal-ok-symbolic",
+        60..=79 => "icon:network-cellular-signal-good-symbolic",
+        _ => "icon:network-cellular-signal-excellent-symbolic",
+    }
+}
+
+/// Whether a point relative to `parent` falls inside `widget`.
+fn contains_point(widget: &impl IsA<gtk::Widget>, parent: &Button, (x, y): (f64, f64)) -> bool {
+    let Some((left, top)) = widget.translate_coordinates(parent, 0, 0) else {
+        return false;
+    };
+
+    let allocation = widget.allocation();
+    let (left, top) = (f64::from(left), f64::from(top));
+
+    x >= left
+        && x < left + f64::from(allocation.width())
+        && y >= top
+        && y < top + f64::from(allocation.height())
+}
@@ -0,0 +1,737 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+use glib::Propagation;
+use gtk::gio::{AppInfo, AppLaunchContext};
+use gtk::prelude::*;
+use gtk::{
+    Align, Box as GtkBox, Button, IconSize, Image, Label, Orientation, Revealer, Switch,
+    ToggleButton,
+};
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+
+use super::format::{format_bytes, format_speed, format_timestamp};
+use super::NetworkManagerEvent;
+use crate::clients::networkmanager::hotspot::HotspotState;
+use crate::clients::networkmanager::ip::IpAddress;
+use crate::clients::networkmanager::roaming::{NetworkHistory, NetworkStats};
+use crate::clients::networkmanager::settings::SavedConnection;
+use crate::clients::networkmanager::speedtest::SpeedTest;
+use crate::clients::networkmanager::state::{Connectivity, State, WifiNetwork};
+use crate::clients::networkmanager::statistics::ConnectionUsage;
+use crate::clients::networkmanager::vpn::VpnConnection;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::try_send;
+
+/// Rebuilds the popup list of saved connections, grouped by connection type.
+pub fn update_connections_list(
+    container: &GtkBox,
+    connections: &[SavedConnection],
+    tx: &mpsc::Sender<NetworkManagerEvent>,
+) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    for (index, connection) in connections.iter().enumerate() {
+        let is_first = index == 0 || connections[index - 1].kind != connection.kind;
+        let is_last = !connections
+            .get(index + 1)
+            .is_some_and(|next| next.kind == connection.kind);
+
+        if is_first {
+            let header = Label::new(Some(connection_type_name(&connection.kind)));
+            header.add_class("connection-type");
+            header.set_halign(Align::Start);
+            container.add(&header);
+        }
+
+        let row = build_connection_row(connection, is_first, is_last, tx);
+        container.add(&row);
+    }
+
+    container.show_all();
+}
+
+/// Builds a row in the saved connections list,
+/// with buttons to move the connection up or down the priority order.
+fn build_connection_row(
+    connection: &SavedConnection,
+    is_first: bool,
+    is_last: bool,
+    tx: &mpsc::Sender<NetworkManagerEvent>,
+) -> GtkBox {
+    let row = GtkBox::new(Orientation::Horizontal, 5);
+    row.add_class("connection");
+
+    let label = Label::new(Some(&connection.id));
+    label.add_class("name");
+    label.set_halign(Align::Start);
+    row.pack_start(&label, true, true, 0);
+
+    for (icon_name, class, offset, enabled) in [
+        ("go-up-symbolic", "btn-up", -1, !is_first),
+        ("go-down-symbolic", "btn-down", 1, !is_last),
+    ] {
+        let button = Button::from_icon_name(Some(icon_name), IconSize::Button);
+        button.add_class(class);
+        button.set_sensitive(enabled);
+
+        let tx = tx.clone();
+        let path = connection.path.clone();
+        button.connect_clicked(move |_| {
+            try_send!(
+                tx,
+                NetworkManagerEvent::MoveConnection {
+                    path: path.clone(),
+                    offset
+                }
+            );
+        });
+
+        row.add(&button);
+    }
+
+    row
+}
+
+/// A detail of a current connection shown in the popup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConnectionDetail {
+    /// The name shown next to the value.
+    name: &'static str,
+    /// The value as shown.
+    value: String,
+    /// The value as copied, without any formatting.
+    #[cfg_attr(not(feature = "clipboard"), allow(dead_code))]
+    copy_value: String,
+}
+
+impl ConnectionDetail {
+    fn new(name: &'static str, value: impl ToString) -> Self {
+        let value = value.to_string();
+        Self {
+            name,
+            copy_value: value.clone(),
+            value,
+        }
+    }
+
+    /// Shows the address with its prefix length, but copies only the address.
+    fn ip(name: &'static str, ip: &IpAddress) -> Self {
+        Self {
+            copy_value: ip.address.clone(),
+            ..Self::new(name, ip)
+        }
+    }
+}
+
+/// Gets the details of the current wired and wifi connections to show in the popup.
+pub fn connection_details(state: &State) -> Vec<ConnectionDetail> {
+    let mut details = vec![];
+
+    if let Some(wired) = state.wired.connected() {
+        details.extend(
+            wired
+                .ip4
+                .as_ref()
+                .map(|ip| ConnectionDetail::ip("Wired IPv4", ip)),
+        );
+        details.extend(
+            wired
+                .ip6
+                .as_ref()
+                .map(|ip| ConnectionDetail::ip("Wired IPv6", ip)),
+        );
+    }
+
+    if let Some(wifi) = state.wifi.connected() {
+        details.push(ConnectionDetail::new("SSID", &wifi.ssid));
+
+        if let Some(bssid) = &wifi.bssid {
+            details.push(ConnectionDetail::new("BSSID", bssid));
+        }
+
+        details.extend(
+            wifi.ip4
+                .as_ref()
+                .map(|ip| ConnectionDetail::ip("Wifi IPv4", ip)),
+        );
+        details.extend(
+            wifi.ip6
+                .as_ref()
+                .map(|ip| ConnectionDetail::ip("Wifi IPv6", ip)),
+        );
+    }
+
+    details
+}
+
+/// Rebuilds the popup list of current connection details,
+/// with a button for each which copies it to the clipboard.
+#[cfg_attr(not(feature = "clipboard"), allow(unused_variables))]
+pub fn update_details_list(
+    container: &GtkBox,
+    details: &[ConnectionDetail],
+    tx: &mpsc::Sender<NetworkManagerEvent>,
+) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    for detail in details {
+        let row = GtkBox::new(Orientation::Horizontal, 5);
+        row.add_class("detail");
+
+        let name = Label::new(Some(detail.name));
+        name.add_class("name");
+        name.set_halign(Align::Start);
+        row.pack_start(&name, true, true, 0);
+
+        let value = Label::new(Some(&detail.value));
+        value.add_class("value");
+        value.set_selectable(true);
+        row.add(&value);
+
+        #[cfg(feature = "clipboard")]
+        {
+            let button = Button::from_icon_name(Some("edit-copy-symbolic"), IconSize::Button);
+            button.add_class("btn-copy");
+            button.set_tooltip_text(Some("Copy"));
+
+            let tx = tx.clone();
+            let copy_value = detail.copy_value.clone();
+            button.connect_clicked(move |_| {
+                try_send!(tx, NetworkManagerEvent::CopyToClipboard(copy_value.clone()));
+            });
+
+            row.add(&button);
+        }
+
+        container.add(&row);
+    }
+
+    container.show_all();
+}
+
+/// Rebuilds the popup list of nearby wifi networks,
+/// with a button for each which connects to it.
+///
+/// Networks with a history have a button to expand their statistics.
+pub fn update_wifi_list(
+    container: &GtkBox,
+    networks: &[WifiNetwork],
+    connected: Option<&str>,
+    history: Option<&NetworkHistory>,
+    expanded: &Rc<RefCell<HashSet<String>>>,
+    tx: &mpsc::Sender<NetworkManagerEvent>,
+) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    if networks.is_empty() {
+        container.hide();
+        return;
+    }
+
+    let header = Label::new(Some("Wi-Fi networks"));
+    header.add_class("wifi-header");
+    header.set_halign(Align::Start);
+    container.add(&header);
+
+    for network in networks {
+        let button = Button::new();
+        button.add_class("wifi-network");
+
+        let is_connected = connected == Some(network.ssid.as_str());
+        if is_connected {
+            button.add_class("connected");
+        }
+
+        let row = GtkBox::new(Orientation::Horizontal, 5);
+        button.add(&row);
+
+        let strength =
+            Image::from_icon_name(Some(signal_icon_name(network.strength)), IconSize::Button);
+        strength.add_class("strength");
+        row.add(&strength);
+
+        let ssid = Label::new(Some(&network.ssid));
+        ssid.add_class("ssid");
+        ssid.set_halign(Align::Start);
+        row.pack_start(&ssid, true, true, 0);
+
+        let security = Label::new(Some(&network.security.to_string()));
+        security.add_class("security");
+        row.add(&security);
+
+        button.set_tooltip_text(Some(&format!("Signal strength: {}%", network.strength)));
+
+        {
+            let tx = tx.clone();
+            let ssid = network.ssid.clone();
+            button.connect_clicked(move |_| {
+                if !is_connected {
+                    try_send!(tx, NetworkManagerEvent::ConnectWifi(ssid.clone()));
+                }
+            });
+        }
+
+        let Some(stats) = history.and_then(|history| history.get(&network.ssid)) else {
+            container.add(&button);
+            continue;
+        };
+
+        let row = GtkBox::new(Orientation::Horizontal, 0);
+        row.pack_start(&button, true, true, 0);
+
+        let is_expanded = expanded.borrow().contains(&network.ssid);
+
+        let expand = ToggleButton::new();
+        expand.add_class("btn-expand");
+        expand.set_tooltip_text(Some("Network history"));
+        expand.set_active(is_expanded);
+        expand.add(&Image::from_icon_name(
+            Some("pan-down-symbolic"),
+            IconSize::Button,
+        ));
+        row.add(&expand);
+
+        let revealer = Revealer::new();
+        revealer.add(&build_network_stats(stats));
+        revealer.set_reveal_child(is_expanded);
+
+        {
+            let revealer = revealer.clone();
+            let expanded = expanded.clone();
+            let ssid = network.ssid.clone();
+
+            expand.connect_toggled(move |expand| {
+                let active = expand.is_active();
+                revealer.set_reveal_child(active);
+
+                if active {
+                    expanded.borrow_mut().insert(ssid.clone());
+                } else {
+                    expanded.borrow_mut().remove(&ssid);
+                }
+            });
+        }
+
+        container.add(&row);
+        container.add(&revealer);
+    }
+
+    container.show_all();
+}
+
+/// Builds the statistics shown when expanding a network in the wifi list.
+fn build_network_stats(stats: &NetworkStats) -> GtkBox {
+    let container = GtkBox::new(Orientation::Vertical, 0);
+    container.add_class("network-history");
+
+    let add_line = |container: &GtkBox, class: &str, text: &str| {
+        let label = Label::new(Some(text));
+        label.add_class(class);
+        label.set_halign(Align::Start);
+        container.add(&label);
+    };
+
+    add_line(
+        &container,
+        "connections",
+        &format!(
+            "Connections: {} (last {})",
+            stats.connections,
+            format_timestamp(stats.last_connected)
+        ),
+    );
+
+    if let Some(strength) = stats.strength.typical() {
+        add_line(
+            &container,
+            "strength",
+            &format!("Typical signal: {strength}%"),
+        );
+    }
+
+    add_line(&container, "roams", &format!("Roams: {}", stats.roams));
+
+    for access_point in &stats.access_points {
+        let strength = access_point
+            .strength
+            .typical()
+            .map(|strength| format!(" · {strength}%"))
+            .unwrap_or_default();
+
+        add_line(
+            &container,
+            "access-point",
+            &format!(
+                "{}{strength} · {}",
+                access_point.bssid,
+                format_timestamp(access_point.last_seen)
+            ),
+        );
+    }
+
+    container
+}
+
+/// Rebuilds the popup list of saved VPN connections,
+/// with a switch for each which connects or disconnects it.
+pub fn update_vpn_list(
+    container: &GtkBox,
+    connections: &[VpnConnection],
+    tx: &mpsc::Sender<NetworkManagerEvent>,
+) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    if connections.is_empty() {
+        container.hide();
+        return;
+    }
+
+    let header = Label::new(Some("VPN"));
+    header.add_class("vpn-header");
+    header.set_halign(Align::Start);
+    container.add(&header);
+
+    for connection in connections {
+        let row = GtkBox::new(Orientation::Horizontal, 5);
+        row.add_class("vpn-connection");
+
+        if connection.active {
+            row.add_class("active");
+        }
+
+        let name = Label::new(Some(&connection.id));
+        name.add_class("name");
+        name.set_halign(Align::Start);
+        row.pack_start(&name, true, true, 0);
+
+        let switch = Switch::new();
+        switch.set_active(connection.active);
+        switch.set_valign(Align::Center);
+        row.add(&switch);
+
+        let tx = tx.clone();
+        let path = connection.path.clone();
+        switch.connect_state_set(move |_, active| {
+            try_send!(
+                tx,
+                NetworkManagerEvent::SetVpnActive {
+                    path: path.clone(),
+                    active
+                }
+            );
+
+            // the list is rebuilt with the real state once the connection changes,
+            // so don't flip the switch until then
+            Propagation::Stop
+        });
+
+        container.add(&row);
+    }
+
+    container.show_all();
+}
+
+/// Gets the symbolic icon for a wifi signal strength percentage.
+fn signal_icon_name(strength: u8) -> &'static str {
+    match strength {
+        0..=19 => "network-wireless-signal-none-symbolic",
+        20..=39 => "network-wireless-signal-weak-symbolic",
+        40..=59 => "network-wireless-signal-ok-symbolic",
+        60..=79 => "network-wireless-signal-good-symbolic",
+        _ => "network-wireless-signal-excellent-symbolic",
+    }
+}
+
+/// The popup section shown while behind a captive portal.
+pub struct PortalSection {
+    pub container: GtkBox,
+    /// The URL reported by NetworkManager to open the login page with, if any.
+    url: Rc<RefCell<Option<String>>>,
+}
+
+/// Builds the popup section with a button to open a captive portal's login page.
+/// `fallback_url` is opened if NetworkManager does not report a URL.
+pub fn build_portal(fallback_url: String) -> PortalSection {
+    let container = GtkBox::new(Orientation::Horizontal, 5);
+    container.add_class("portal");
+
+    let message = Label::new(Some("Sign in to the network"));
+    message.add_class("message");
+    message.set_halign(Align::Start);
+    container.pack_start(&message, true, true, 0);
+
+    let button = Button::with_label("Open login page");
+    button.add_class("btn-portal");
+    container.add(&button);
+
+    // only shown while behind a portal
+    message.show();
+    button.show();
+    container.set_no_show_all(true);
+
+    let url = Rc::new(RefCell::new(None::<String>));
+
+    {
+        let url = url.clone();
+        button.connect_clicked(move |_| {
+            let url = url.borrow().clone().unwrap_or_else(|| fallback_url.clone());
+            debug!("Opening captive portal login page at '{url}'");
+
+            if let Err(err) = AppInfo::launch_default_for_uri(&url, None::<&AppLaunchContext>) {
+                error!("Failed to open captive portal login page: {err:?}");
+            }
+        });
+    }
+
+    PortalSection { container, url }
+}
+
+impl PortalSection {
+    pub fn update(&self, connectivity: &Connectivity) {
+        if let Connectivity::Portal { url } = connectivity {
+            self.url.replace(url.clone());
+            self.container.show();
+        } else {
+            self.container.hide();
+        }
+    }
+}
+
+/// The popup section with a switch to turn airplane mode on or off.
+pub struct AirplaneModeSection {
+    pub container: GtkBox,
+    switch: Switch,
+    handler: glib::SignalHandlerId,
+}
+
+/// Builds the popup section with a switch to turn airplane mode on or off.
+pub fn build_airplane_mode(tx: &mpsc::Sender<NetworkManagerEvent>) -> AirplaneModeSection {
+    let container = GtkBox::new(Orientation::Horizontal, 5);
+    container.add_class("airplane-mode");
+
+    let label = Label::new(Some("Airplane mode"));
+    label.add_class("name");
+    label.set_halign(Align::Start);
+    container.pack_start(&label, true, true, 0);
+
+    let switch = Switch::new();
+    switch.set_valign(Align::Center);
+    container.add(&switch);
+
+    let handler = {
+        let tx = tx.clone();
+        switch.connect_state_set(move |_, enabled| {
+            try_send!(tx, NetworkManagerEvent::SetAirplaneMode(enabled));
+            Propagation::Proceed
+        })
+    };
+
+    // only shown if rfkill is available
+    label.show();
+    switch.show();
+    container.set_no_show_all(true);
+
+    AirplaneModeSection {
+        container,
+        switch,
+        handler,
+    }
+}
+
+impl AirplaneModeSection {
+    pub fn update(&self, airplane_mode: Option<bool>) {
+        let Some(enabled) = airplane_mode else {
+            self.container.hide();
+            return;
+        };
+
+        // the state is set from the client, so is not sent back to it
+        self.switch.block_signal(&self.handler);
+        self.switch.set_active(enabled);
+        self.switch.unblock_signal(&self.handler);
+
+        self.container.show();
+    }
+}
+
+/// The popup section with a switch to start or stop the configured hotspot.
+pub struct HotspotSection {
+    pub container: GtkBox,
+    switch: Switch,
+    handler: glib::SignalHandlerId,
+    connection: String,
+}
+
+/// Builds the popup section with a switch to start or stop the hotspot connection.
+pub fn build_hotspot(tx: &mpsc::Sender<NetworkManagerEvent>, connection: String) -> HotspotSection {
+    let container = GtkBox::new(Orientation::Horizontal, 5);
+    container.add_class("hotspot");
+
+    let label = Label::new(Some("Hotspot"));
+    label.add_class("name");
+    label.set_halign(Align::Start);
+    label.set_tooltip_text(Some(&connection));
+    container.pack_start(&label, true, true, 0);
+
+    let switch = Switch::new();
+    switch.set_valign(Align::Center);
+    container.add(&switch);
+
+    let handler = {
+        let tx = tx.clone();
+        switch.connect_state_set(move |_, active| {
+            try_send!(tx, NetworkManagerEvent::SetHotspotActive(active));
+            Propagation::Proceed
+        })
+    };
+
+    HotspotSection {
+        container,
+        switch,
+        handler,
+        connection,
+    }
+}
+
+impl HotspotSection {
+    pub fn update(&self, hotspot: Option<&HotspotState>) {
+        let active = hotspot.is_some_and(|hotspot| hotspot.connection == self.connection);
+
+        // the state is set from the client, so is not sent back to it
+        self.switch.block_signal(&self.handler);
+        self.switch.set_active(active);
+        self.switch.unblock_signal(&self.handler);
+    }
+}
+
+/// The popup section for running a speed test.
+pub struct SpeedTestSection {
+    pub container: GtkBox,
+    button: Button,
+    result: Label,
+}
+
+/// Builds the popup section with a button to run a speed test,
+/// and a label for the last result.
+pub fn build_speed_test(tx: &mpsc::Sender<NetworkManagerEvent>) -> SpeedTestSection {
+    let container = GtkBox::new(Orientation::Horizontal, 5);
+    container.add_class("speed-test");
+
+    let result = Label::new(None);
+    result.add_class("result");
+    result.set_halign(Align::Start);
+    container.pack_start(&result, true, true, 0);
+
+    let button = Button::with_label("Run speed test");
+    button.add_class("btn-speed-test");
+    container.add(&button);
+
+    let tx = tx.clone();
+    button.connect_clicked(move |_| {
+        try_send!(tx, NetworkManagerEvent::RunSpeedTest);
+    });
+
+    SpeedTestSection {
+        container,
+        button,
+        result,
+    }
+}
+
+impl SpeedTestSection {
+    pub fn update(&self, speed_test: &SpeedTest) {
+        self.button.set_sensitive(!speed_test.running);
+        self.button.set_label(if speed_test.running {
+            "Testing..."
+        } else {
+            "Run speed test"
+        });
+
+        if speed_test.running {
+            self.container.add_class("running");
+        } else {
+            self.container.style_context().remove_class("running");
+        }
+
+        match &speed_test.last_result {
+            Some(result) => {
+                self.result.set_text(&format!(
+                    "\u{2193} {}  \u{2191} {}  {:.0} ms",
+                    format_speed(result.download),
+                    format_speed(result.upload),
+                    result.ping
+                ));
+                self.result.set_tooltip_text(Some(&format!(
+                    "Last tested {}",
+                    result.time.format("%Y-%m-%d %H:%M")
+                )));
+            }
+            None => {
+                self.result.set_text("");
+                self.result.set_tooltip_text(None);
+            }
+        }
+    }
+}
+
+/// Rebuilds the popup list of data used by each active connection.
+pub fn update_usage_list(container: &GtkBox, data_usage: &[ConnectionUsage]) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    if data_usage.is_empty() {
+        container.hide();
+        return;
+    }
+
+    let header = Label::new(Some("Data usage"));
+    header.add_class("usage-header");
+    header.set_halign(Align::Start);
+    container.add(&header);
+
+    for usage in data_usage {
+        let row = GtkBox::new(Orientation::Horizontal, 5);
+        row.add_class("usage");
+
+        let name = Label::new(Some(&usage.id));
+        name.add_class("name");
+        name.set_halign(Align::Start);
+        row.pack_start(&name, true, true, 0);
+
+        let value = Label::new(Some(&format!(
+            "\u{2193} {}  \u{2191} {}",
+            format_bytes(usage.rx_bytes),
+            format_bytes(usage.tx_bytes)
+        )));
+        value.add_class("value");
+        row.add(&value);
+
+        container.add(&row);
+    }
+
+    container.show_all();
+}
+
+/// Gets a friendly name for a NetworkManager connection type.
+fn connection_type_name(kind: &str) -> &str {
+    match kind {
+        "802-3-ethernet" => "Wired",
+        "802-11-wireless" => "Wi-Fi",
+        "gsm" | "cdma" => "Mobile broadband",
+        "vpn" | "wireguard" => "VPN",
+        "bluetooth" => "Bluetooth",
+        _ => kind,
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Align, Box as GtkBox, Button, Label, Orientation, ProgressBar};
+use serde::Deserialize;
+use zbus::zvariant::Value;
+
+use super::format::format_bytes;
+use crate::clients::networkmanager::quota::{period_start, QuotaLevel, UsageHistory};
+use crate::gtk_helpers::IronbarGtkExt;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct DataQuota {
+    /// The name of the NetworkManager connection.
+    /// For wifi connections, this is usually the SSID.
+    connection: String,
+
+    /// The data included each month, in gigabytes.
+    limit_gb: f64,
+
+    /// The day of the month the quota resets on.
+    /// If the month is shorter, the quota resets on its last day.
+    ///
+    /// **Default**: `1`
+    #[serde(default = "default_reset_day")]
+    reset_day: u32,
+
+    /// The percentage of the quota used at which to warn.
+    ///
+    /// **Default**: `80`
+    #[serde(default = "default_warn_percent")]
+    warn_percent: f64,
+}
+
+const fn default_reset_day() -> u32 {
+    1
+}
+
+const fn default_warn_percent() -> f64 {
+    80.0
+}
+
+/// Data used against a quota in the current billing period.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaUsage {
+    pub connection: String,
+    /// The first day of the billing period.
+    pub period_start: NaiveDate,
+    pub used: u64,
+    pub limit: u64,
+    pub level: QuotaLevel,
+    pub active: bool,
+}
+
+impl DataQuota {
+    pub fn usage(&self, history: &UsageHistory, today: NaiveDate) -> QuotaUsage {
+        let period_start = period_start(today, self.reset_day);
+        let used = history.usage_since(&self.connection, period_start);
+        let limit = (self.limit_gb * 1_000_000_000.0) as u64;
+
+        let percent = percent(used, limit);
+        let level = if percent >= 100.0 {
+            QuotaLevel::Exceeded
+        } else if percent >= self.warn_percent {
+            QuotaLevel::Warning
+        } else {
+            QuotaLevel::Normal
+        };
+
+        QuotaUsage {
+            connection: self.connection.clone(),
+            period_start,
+            used,
+            limit,
+            level,
+            active: history.is_active(&self.connection),
+        }
+    }
+}
+
+impl QuotaUsage {
+    pub fn percent(&self) -> f64 {
+        percent(self.used, self.limit)
+    }
+}
+
+fn percent(used: u64, limit: u64) -> f64 {
+    if limit == 0 {
+        100.0
+    } else {
+        used as f64 / limit as f64 * 100.0
+    }
+}
+
+/// Rebuilds the popup list of data used against each quota.
+pub fn update_quota_list(container: &GtkBox, quotas: &[QuotaUsage]) {
+    for child in container.children() {
+        container.remove(&child);
+    }
+
+    if quotas.is_empty() {
+        container.hide();
+        return;
+    }
+
+    let header = Label::new(Some("Data quotas"));
+    header.add_class("quota-header");
+    header.set_halign(Align::Start);
+    container.add(&header);
+
+    for quota in quotas {
+        let row = GtkBox::new(Orientation::Vertical, 0);
+        row.add_class("quota");
+
+        match quota.level {
+            QuotaLevel::Normal => {}
+            QuotaLevel::Warning => row.add_class("warning"),
+            QuotaLevel::Exceeded => row.add_class("exceeded"),
+        }
+
+        let details = GtkBox::new(Orientation::Horizontal, 5);
+        row.add(&details);
+
+        let name = Label::new(Some(&quota.connection));
+        name.add_class("name");
+        name.set_halign(Align::Start);
+        details.pack_start(&name, true, true, 0);
+
+        let value = Label::new(Some(&format!(
+            "{} / {} ({:.0}%)",
+            format_bytes(quota.used),
+            format_bytes(quota.limit),
+            quota.percent()
+        )));
+        value.add_class("value");
+        details.add(&value);
+
+        let progress = ProgressBar::new();
+        progress.set_fraction((quota.percent() / 100.0).min(1.0));
+        row.add(&progress);
+
+        row.set_tooltip_text(Some(&format!(
+            "Since {}",
+            quota.period_start.format("%Y-%m-%d")
+        )));
+
+        container.add(&row);
+    }
+
+    container.show_all();
+}
+
+/// Gets the quota to show on the bar.
+/// This is the first quota for an active connection,
+/// or the first quota if none are active.
+pub fn current_quota(quotas: Vec<QuotaUsage>) -> Option<QuotaUsage> {
+    let index = quotas.iter().position(|quota| quota.active).unwrap_or(0);
+    quotas.into_iter().nth(index)
+}
+
+/// Sets the quota level classes on the widget.
+pub fn update_quota_classes(widget: &Button, quota: Option<&QuotaUsage>) {
+    let level = quota.map_or(QuotaLevel::Normal, |quota| quota.level);
+
+    for (class, enabled) in [
+        ("quota-warning", level == QuotaLevel::Warning),
+        ("quota-exceeded", level == QuotaLevel::Exceeded),
+    ] {
+        if enabled {
+            widget.add_class(class);
+        } else {
+            widget.style_context().remove_class(class);
+        }
+    }
+}
+
+/// Sends a desktop notification that a quota has passed its warning threshold or been used up.
+pub async fn notify_quota(quota: &QuotaUsage) -> Result<()> {
+    let dbus = Box::pin(zbus::Connection::session()).await?;
+
+    let summary = match quota.level {
+        QuotaLevel::Exceeded => format!("{} data quota used up", quota.connection),
+        _ => format!(
+            "{} has used {:.0}% of its data quota",
+            quota.connection,
+            quota.percent()
+        ),
+    };
+
+    let body = format!(
+        "{} of {} used since {}",
+        format_bytes(quota.used),
+        format_bytes(quota.limit),
+        quota.period_start.format("%b %-d")
+    );
+
+    dbus.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "Ironbar",
+            0u32,
+            "network-cellular-signal-weak-symbolic",
+            summary.as_str(),
+            body.as_str(),
+            Vec::<&str>::new(),
+            HashMap::<&str, Value>::new(),
+            -1i32,
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
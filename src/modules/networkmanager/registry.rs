@@ -0,0 +1,56 @@
+//! Tracks networkmanager modules,
+//! so their built-in actions can be triggered over IPC.
+
+use super::NetworkManagerEvent;
+use crate::{lock, try_send};
+use color_eyre::{Report, Result};
+use std::sync::{Mutex, OnceLock};
+use tokio::sync::mpsc;
+
+struct Registration {
+    name: Option<String>,
+    tx: mpsc::WeakSender<NetworkManagerEvent>,
+}
+
+fn registry() -> &'static Mutex<Vec<Registration>> {
+    static REGISTRY: OnceLock<Mutex<Vec<Registration>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(vec![]))
+}
+
+/// Registers a module instance.
+pub fn register(name: Option<String>, tx: &mpsc::Sender<NetworkManagerEvent>) {
+    let mut registry = lock!(registry());
+
+    // clear out modules from closed bars
+    registry.retain(|registration| registration.tx.upgrade().is_some());
+
+    registry.push(Registration {
+        name,
+        tx: tx.downgrade(),
+    });
+}
+
+/// Turns wifi on or off.
+pub fn toggle_wifi(name: Option<&str>) -> Result<()> {
+    send(name, NetworkManagerEvent::ToggleWifi)
+}
+
+/// Turns airplane mode on or off.
+pub fn toggle_airplane_mode(name: Option<&str>) -> Result<()> {
+    send(name, NetworkManagerEvent::ToggleAirplaneMode)
+}
+
+/// Sends an event to a single module, so that the toggle is not applied once per bar.
+/// This is the first module with the given `name`, or the first module if not set.
+fn send(name: Option<&str>, event: NetworkManagerEvent) -> Result<()> {
+    let registry = lock!(registry());
+
+    let tx = registry
+        .iter()
+        .filter(|registration| name.map_or(true, |name| registration.name.as_deref() == Some(name)))
+        .find_map(|registration| registration.tx.upgrade())
+        .ok_or_else(|| Report::msg("Module not found"))?;
+
+    try_send!(tx, event);
+    Ok(())
+}
@@ -0,0 +1,722 @@
+use super::Event;
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, Months, NaiveDate, NaiveDateTime, TimeDelta, TimeZone,
+    Utc, Weekday,
+};
+use std::collections::HashMap;
+use tracing::debug;
+
+/// Upper bound on the number of occurrences checked for each recurring event.
+const MAX_OCCURRENCES: usize = 10_000;
+
+/// A single content line, ie `DTSTART;TZID=Europe/London:20240101T090000`.
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A `STANDARD` or `DAYLIGHT` component of a `VTIMEZONE`.
+#[derive(Debug)]
+struct Observance {
+    /// When the observance first takes effect, in the local time before it.
+    start: NaiveDateTime,
+    offset: FixedOffset,
+    /// The yearly rule for when the observance takes effect again,
+    /// as the month, the week of the month, and the weekday.
+    /// Negative weeks count back from the end of the month.
+    rule: Option<(u32, i32, Weekday)>,
+}
+
+impl Observance {
+    /// Gets the most recent time the observance took effect, at or before `time`.
+    fn onset_before(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        if time < self.start {
+            return None;
+        }
+
+        let Some((month, week, weekday)) = self.rule else {
+            return Some(self.start);
+        };
+
+        [time.year(), time.year() - 1]
+            .into_iter()
+            .filter_map(|year| nth_weekday(year, month, week, weekday))
+            .map(|date| date.and_time(self.start.time()))
+            .find(|onset| *onset <= time && *onset >= self.start)
+    }
+}
+
+/// The time zone an event's times are given in.
+#[derive(Debug, Clone, Copy)]
+enum Zone<'a> {
+    Utc,
+    /// A time zone defined by a `VTIMEZONE` in the calendar.
+    Defined(&'a [Observance]),
+    /// Floating times, or a time zone not defined in the calendar.
+    Local,
+}
+
+impl Zone<'_> {
+    fn resolve(self, time: NaiveDateTime) -> Option<DateTime<Local>> {
+        match self {
+            Self::Utc => Some(Utc.from_utc_datetime(&time).with_timezone(&Local)),
+            Self::Defined(observances) => {
+                let offset = observances
+                    .iter()
+                    .filter_map(|observance| {
+                        observance
+                            .onset_before(time)
+                            .map(|onset| (onset, observance.offset))
+                    })
+                    .max_by_key(|(onset, _)| *onset)
+                    .map(|(_, offset)| offset)
+                    // before the first observance, assume the earliest offset
+                    .or_else(|| {
+                        observances
+                            .iter()
+                            .min_by_key(|observance| observance.start)
+                            .map(|observance| observance.offset)
+                    })?;
+
+                offset
+                    .from_local_datetime(&time)
+                    .single()
+                    .map(|time| time.with_timezone(&Local))
+            }
+            Self::Local => Local.from_local_datetime(&time).earliest(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// A supported subset of an `RRULE`.
+#[derive(Debug)]
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<DateTime<Local>>,
+    /// Weekdays to repeat on for daily and weekly rules.
+    by_day: Vec<Weekday>,
+}
+
+impl Rule {
+    fn parse(value: &str, zone: Zone) -> Option<Self> {
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut count = None;
+        let mut until = None;
+        let mut by_day = vec![];
+
+        for part in value.split(';') {
+            let (key, value) = part.split_once('=')?;
+
+            match key {
+                "FREQ" => {
+                    frequency = match value {
+                        "DAILY" => Some(Frequency::Daily),
+                        "WEEKLY" => Some(Frequency::Weekly),
+                        "MONTHLY" => Some(Frequency::Monthly),
+                        "YEARLY" => Some(Frequency::Yearly),
+                        _ => None,
+                    };
+                }
+                "INTERVAL" => interval = value.parse().ok().filter(|interval| *interval > 0)?,
+                "COUNT" => count = value.parse().ok(),
+                "UNTIL" => until = parse_time(value, zone),
+                // ordinal weekdays such as `2TU` are not supported
+                "BYDAY" => {
+                    by_day = value
+                        .split(',')
+                        .map(parse_weekday)
+                        .collect::<Option<Vec<_>>>()?;
+                }
+                "BYMONTH" | "BYMONTHDAY" | "BYYEARDAY" | "BYWEEKNO" | "BYSETPOS" | "BYHOUR"
+                | "BYMINUTE" | "BYSECOND" => return None,
+                _ => {}
+            }
+        }
+
+        let frequency = frequency?;
+
+        if !by_day.is_empty() && matches!(frequency, Frequency::Monthly | Frequency::Yearly) {
+            return None;
+        }
+
+        Some(Self {
+            frequency,
+            interval,
+            count,
+            until,
+            by_day,
+        })
+    }
+
+    /// Gets the occurrences of the rule, starting at `start`.
+    ///
+    /// Where there is no count to respect,
+    /// daily and weekly rules skip straight to the periods around `from`.
+    fn occurrences(
+        &self,
+        start: NaiveDateTime,
+        from: NaiveDateTime,
+    ) -> impl Iterator<Item = NaiveDateTime> + '_ {
+        let period_days = match self.frequency {
+            Frequency::Daily => i64::from(self.interval),
+            Frequency::Weekly => 7 * i64::from(self.interval),
+            Frequency::Monthly | Frequency::Yearly => 0,
+        };
+
+        let skip = if self.count.is_none() && period_days > 0 && from > start {
+            // leave a period of slack for the weekdays in the first week
+            ((from - start).num_days() / period_days - 1).max(0)
+        } else {
+            0
+        };
+
+        // weekly rules start from the Monday of the first week
+        let week_start =
+            start.date() - TimeDelta::days(i64::from(start.weekday().num_days_from_monday()));
+
+        (skip..)
+            .take(MAX_OCCURRENCES)
+            .flat_map(move |period| {
+                let period_u32 = u32::try_from(period).unwrap_or(u32::MAX);
+
+                let dates = match self.frequency {
+                    Frequency::Daily => {
+                        let date = start.date() + TimeDelta::days(period * period_days);
+
+                        if self.by_day.is_empty() || self.by_day.contains(&date.weekday()) {
+                            vec![date]
+                        } else {
+                            vec![]
+                        }
+                    }
+                    Frequency::Weekly if self.by_day.is_empty() => {
+                        vec![start.date() + TimeDelta::days(period * period_days)]
+                    }
+                    Frequency::Weekly => {
+                        let week = week_start + TimeDelta::days(period * period_days);
+                        let mut days = self
+                            .by_day
+                            .iter()
+                            .map(|day| {
+                                week + TimeDelta::days(i64::from(day.num_days_from_monday()))
+                            })
+                            .collect::<Vec<_>>();
+                        days.sort();
+                        days
+                    }
+                    // skips months without the day, ie the 31st
+                    Frequency::Monthly => start
+                        .date()
+                        .checked_add_months(Months::new(period_u32.saturating_mul(self.interval)))
+                        .filter(|date| date.day() == start.day())
+                        .into_iter()
+                        .collect(),
+                    Frequency::Yearly => start
+                        .date()
+                        .checked_add_months(Months::new(
+                            period_u32.saturating_mul(self.interval).saturating_mul(12),
+                        ))
+                        .filter(|date| date.day() == start.day())
+                        .into_iter()
+                        .collect(),
+                };
+
+                dates
+                    .into_iter()
+                    .map(move |date| date.and_time(start.time()))
+            })
+            .filter(move |time| *time >= start)
+            .take(self.count.unwrap_or(usize::MAX))
+    }
+}
+
+/// Gets the next occurrence of each event in the calendar
+/// which starts within the given range.
+///
+/// All-day and cancelled events are skipped.
+pub fn parse_events(input: &str, from: DateTime<Local>, to: DateTime<Local>) -> Vec<Event> {
+    let lines = unfold(input);
+
+    let mut timezones = HashMap::new();
+    let mut events = vec![];
+
+    let mut component = vec![];
+    let mut in_alarm = false;
+    let mut observance: Option<Vec<Property>> = None;
+    let mut timezone: Option<(String, Vec<Observance>)> = None;
+
+    for property in lines.iter().filter_map(|line| parse_property(line)) {
+        match (property.name.as_str(), property.value.as_str()) {
+            ("BEGIN", "VEVENT") => component.clear(),
+            ("END", "VEVENT") => events.push(std::mem::take(&mut component)),
+            ("BEGIN", "VALARM") => in_alarm = true,
+            ("END", "VALARM") => in_alarm = false,
+            ("BEGIN", "VTIMEZONE") => timezone = Some((String::new(), vec![])),
+            ("END", "VTIMEZONE") => {
+                if let Some((id, observances)) = timezone.take() {
+                    timezones.insert(id, observances);
+                }
+            }
+            ("BEGIN", "STANDARD" | "DAYLIGHT") => observance = Some(vec![]),
+            ("END", "STANDARD" | "DAYLIGHT") => {
+                let parsed = observance.take().and_then(|props| parse_observance(&props));
+
+                if let (Some((_, observances)), Some(parsed)) = (&mut timezone, parsed) {
+                    observances.push(parsed);
+                }
+            }
+            ("TZID", id) if observance.is_none() => {
+                if let Some((tz_id, _)) = &mut timezone {
+                    *tz_id = id.to_string();
+                }
+            }
+            _ => {
+                if let Some(observance) = &mut observance {
+                    observance.push(property);
+                } else if timezone.is_none() && !in_alarm {
+                    component.push(property);
+                }
+            }
+        }
+    }
+
+    // moved or cancelled instances of recurring events,
+    // which replace the original occurrence
+    let mut overridden: HashMap<String, Vec<DateTime<Local>>> = HashMap::new();
+    for props in &events {
+        let uid = find(props, "UID").map(|prop| prop.value.clone());
+        let recurrence_id = find(props, "RECURRENCE-ID")
+            .and_then(|prop| parse_time(&prop.value, zone_for(prop, &timezones)));
+
+        if let (Some(uid), Some(recurrence_id)) = (uid, recurrence_id) {
+            overridden.entry(uid).or_default().push(recurrence_id);
+        }
+    }
+
+    events
+        .iter()
+        .filter(|props| find(props, "STATUS").map_or(true, |prop| prop.value != "CANCELLED"))
+        .filter_map(|props| {
+            let start_prop = find(props, "DTSTART")?;
+            let zone = zone_for(start_prop, &timezones);
+
+            // all-day events have a date without a time
+            let start = NaiveDateTime::parse_from_str(
+                start_prop.value.trim_end_matches('Z'),
+                "%Y%m%dT%H%M%S",
+            )
+            .ok()?;
+
+            let mut excluded = props
+                .iter()
+                .filter(|prop| prop.name == "EXDATE")
+                .flat_map(|prop| {
+                    let zone = zone_for(prop, &timezones);
+                    prop.value
+                        .split(',')
+                        .filter_map(move |value| parse_time(value, zone))
+                })
+                .collect::<Vec<_>>();
+
+            let is_override = find(props, "RECURRENCE-ID").is_some();
+            if !is_override {
+                if let Some(times) = find(props, "UID").and_then(|uid| overridden.get(&uid.value)) {
+                    excluded.extend(times);
+                }
+            }
+
+            let rule = find(props, "RRULE").and_then(|prop| Rule::parse(&prop.value, zone));
+
+            let start = match rule {
+                Some(rule) => rule
+                    .occurrences(start, from.naive_local())
+                    .filter_map(|time| zone.resolve(time))
+                    .take_while(|time| {
+                        *time <= to && rule.until.map_or(true, |until| *time <= until)
+                    })
+                    .find(|time| *time >= from && !excluded.contains(time)),
+                None => zone
+                    .resolve(start)
+                    .filter(|time| *time >= from && *time <= to),
+            }?;
+
+            let title =
+                find(props, "SUMMARY").map_or_else(String::new, |prop| unescape(&prop.value));
+
+            let url = find(props, "URL")
+                .map(|prop| prop.value.clone())
+                .or_else(|| {
+                    ["LOCATION", "DESCRIPTION"]
+                        .into_iter()
+                        .filter_map(|name| find(props, name))
+                        .find_map(|prop| super::find_url(&unescape(&prop.value)))
+                });
+
+            Some(Event { title, start, url })
+        })
+        .collect()
+}
+
+/// Joins lines folded onto multiple lines,
+/// which continue with a leading space or tab.
+fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+
+    for line in input.lines() {
+        let line = line.trim_end_matches('\r');
+
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some(last)) => last.push_str(continuation),
+            _ => lines.push(line.to_string()),
+        }
+    }
+
+    lines
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // parameter values may be quoted and contain colons
+    let mut in_quotes = false;
+    let split = line.char_indices().find_map(|(i, c)| match c {
+        '"' => {
+            in_quotes = !in_quotes;
+            None
+        }
+        ':' if !in_quotes => Some(i),
+        _ => None,
+    })?;
+
+    let (key, value) = (&line[..split], &line[split + 1..]);
+
+    let mut parts = key.split(';');
+    let name = parts.next()?.to_uppercase();
+    let params = parts
+        .filter_map(|param| param.split_once('='))
+        .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+        .collect();
+
+    Some(Property {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+fn parse_observance(props: &[Property]) -> Option<Observance> {
+    let start =
+        NaiveDateTime::parse_from_str(&find(props, "DTSTART")?.value, "%Y%m%dT%H%M%S").ok()?;
+    let offset = parse_offset(&find(props, "TZOFFSETTO")?.value)?;
+
+    let rule = find(props, "RRULE").and_then(|prop| {
+        let mut month = None;
+        let mut day = None;
+
+        for part in prop.value.split(';') {
+            match part.split_once('=')? {
+                ("BYMONTH", value) => month = value.parse().ok(),
+                ("BYDAY", value) => {
+                    let split = value.len().checked_sub(2)?;
+                    let (week, weekday) = (value.get(..split)?, value.get(split..)?);
+                    day = Some((week.parse().ok()?, parse_weekday(weekday)?));
+                }
+                _ => {}
+            }
+        }
+
+        let (week, weekday) = day?;
+        Some((month?, week, weekday))
+    });
+
+    Some(Observance {
+        start,
+        offset,
+        rule,
+    })
+}
+
+/// Parses a UTC offset, ie `+0100` or `-0530`.
+fn parse_offset(value: &str) -> Option<FixedOffset> {
+    let (sign, value) = if let Some(value) = value.strip_prefix('+') {
+        (1, value)
+    } else {
+        (-1, value.strip_prefix('-')?)
+    };
+
+    let hours = value.get(0..2)?.parse::<i32>().ok()?;
+    let minutes = value.get(2..4)?.parse::<i32>().ok()?;
+    let seconds = value
+        .get(4..6)
+        .and_then(|s| s.parse::<i32>().ok())
+        .unwrap_or_default();
+
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60 + seconds))
+}
+
+fn parse_weekday(value: &str) -> Option<Weekday> {
+    match value {
+        "MO" => Some(Weekday::Mon),
+        "TU" => Some(Weekday::Tue),
+        "WE" => Some(Weekday::Wed),
+        "TH" => Some(Weekday::Thu),
+        "FR" => Some(Weekday::Fri),
+        "SA" => Some(Weekday::Sat),
+        "SU" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+/// Parses a date-time value, which is in UTC if it ends with `Z`.
+fn parse_time(value: &str, zone: Zone) -> Option<DateTime<Local>> {
+    let (value, zone) = match value.strip_suffix('Z') {
+        Some(value) => (value, Zone::Utc),
+        None => (value, zone),
+    };
+
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .and_then(|time| zone.resolve(time))
+}
+
+fn zone_for<'a>(prop: &Property, timezones: &'a HashMap<String, Vec<Observance>>) -> Zone<'a> {
+    match prop.param("TZID") {
+        Some(id) => timezones.get(id).map_or_else(
+            || {
+                debug!("Time zone '{id}' is not defined in calendar, assuming local time");
+                Zone::Local
+            },
+            |observances| Zone::Defined(observances),
+        ),
+        None if prop.value.ends_with('Z') => Zone::Utc,
+        None => Zone::Local,
+    }
+}
+
+fn find<'a>(props: &'a [Property], name: &str) -> Option<&'a Property> {
+    props.iter().find(|prop| prop.name == name)
+}
+
+/// Gets the date of the nth weekday in a month,
+/// counting back from the end of the month if negative.
+fn nth_weekday(year: i32, month: u32, n: i32, weekday: Weekday) -> Option<NaiveDate> {
+    if n > 0 {
+        NaiveDate::from_weekday_of_month_opt(year, month, weekday, u8::try_from(n).ok()?)
+    } else {
+        let last = NaiveDate::from_ymd_opt(year, month, 1)?.checked_add_months(Months::new(1))?
+            - TimeDelta::days(1);
+
+        let back = (7 + last.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+        Some(last - TimeDelta::days(i64::from(back) + 7 * i64::from(-n - 1)))
+    }
+}
+
+/// Removes the escaping from a text value.
+fn unescape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => output.push('\n'),
+                Some(c) => output.push(c),
+                None => {}
+            }
+        } else {
+            output.push(c);
+        }
+    }
+
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(value: &str) -> DateTime<Local> {
+        DateTime::parse_from_rfc3339(value)
+            .expect("valid time")
+            .with_timezone(&Local)
+    }
+
+    const TIMEZONE: &str = "BEGIN:VTIMEZONE\r
+TZID:Europe/London\r
+BEGIN:DAYLIGHT\r
+TZOFFSETFROM:+0000\r
+TZOFFSETTO:+0100\r
+DTSTART:19810329T010000\r
+RRULE:FREQ=YEARLY;BYMONTH=3;BYDAY=-1SU\r
+END:DAYLIGHT\r
+BEGIN:STANDARD\r
+TZOFFSETFROM:+0100\r
+TZOFFSETTO:+0000\r
+DTSTART:19961027T020000\r
+RRULE:FREQ=YEARLY;BYMONTH=10;BYDAY=-1SU\r
+END:STANDARD\r
+END:VTIMEZONE\r
+";
+
+    #[test]
+    fn test_single_event() {
+        let input = "BEGIN:VCALENDAR\r
+BEGIN:VEVENT\r
+UID:1\r
+SUMMARY:Planning\\, Q3\r
+DTSTART:20240610T140000Z\r
+DESCRIPTION:Join at https://meet.example.com/abc-d\r
+ ef\r
+END:VEVENT\r
+END:VCALENDAR\r
+";
+
+        let events = parse_events(
+            input,
+            utc("2024-06-10T00:00:00Z"),
+            utc("2024-06-11T00:00:00Z"),
+        );
+
+        assert_eq!(
+            events,
+            [Event {
+                title: String::from("Planning, Q3"),
+                start: utc("2024-06-10T14:00:00Z"),
+                url: Some(String::from("https://meet.example.com/abc-def")),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_time_zone() {
+        let input = format!(
+            "BEGIN:VCALENDAR\r
+{TIMEZONE}BEGIN:VEVENT\r
+SUMMARY:Summer\r
+DTSTART;TZID=Europe/London:20240610T090000\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Winter\r
+DTSTART;TZID=Europe/London:20241210T090000\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+        );
+
+        let events = parse_events(
+            &input,
+            utc("2024-01-01T00:00:00Z"),
+            utc("2025-01-01T00:00:00Z"),
+        );
+
+        assert_eq!(events[0].start, utc("2024-06-10T08:00:00Z"));
+        assert_eq!(events[1].start, utc("2024-12-10T09:00:00Z"));
+    }
+
+    #[test]
+    fn test_weekly_recurrence() {
+        let input = format!(
+            "BEGIN:VCALENDAR\r
+{TIMEZONE}BEGIN:VEVENT\r
+UID:standup\r
+SUMMARY:Standup\r
+DTSTART;TZID=Europe/London:20230102T093000\r
+RRULE:FREQ=WEEKLY;BYDAY=MO,WE,FR\r
+EXDATE;TZID=Europe/London:20240612T093000\r
+END:VEVENT\r
+END:VCALENDAR\r
+"
+        );
+
+        // Tuesday 11th June, with Wednesday excluded
+        let events = parse_events(
+            &input,
+            utc("2024-06-11T10:00:00Z"),
+            utc("2024-06-20T00:00:00Z"),
+        );
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].start, utc("2024-06-14T08:30:00Z"));
+    }
+
+    #[test]
+    fn test_recurrence_count() {
+        let input = "BEGIN:VEVENT\r
+SUMMARY:Course\r
+DTSTART:20240601T100000Z\r
+RRULE:FREQ=DAILY;COUNT=3\r
+END:VEVENT\r
+";
+
+        let in_range = parse_events(
+            input,
+            utc("2024-06-03T00:00:00Z"),
+            utc("2024-06-10T00:00:00Z"),
+        );
+        assert_eq!(in_range[0].start, utc("2024-06-03T10:00:00Z"));
+
+        let after_end = parse_events(
+            input,
+            utc("2024-06-04T00:00:00Z"),
+            utc("2024-06-10T00:00:00Z"),
+        );
+        assert!(after_end.is_empty());
+    }
+
+    #[test]
+    fn test_skips_all_day_and_cancelled() {
+        let input = "BEGIN:VEVENT\r
+SUMMARY:Holiday\r
+DTSTART;VALUE=DATE:20240610\r
+END:VEVENT\r
+BEGIN:VEVENT\r
+SUMMARY:Cancelled\r
+STATUS:CANCELLED\r
+DTSTART:20240610T100000Z\r
+END:VEVENT\r
+";
+
+        let events = parse_events(
+            input,
+            utc("2024-06-09T00:00:00Z"),
+            utc("2024-06-11T00:00:00Z"),
+        );
+        assert!(events.is_empty());
+    }
+
+    #[test]
+    fn test_nth_weekday() {
+        assert_eq!(
+            nth_weekday(2024, 3, -1, Weekday::Sun),
+            NaiveDate::from_ymd_opt(2024, 3, 31)
+        );
+        assert_eq!(
+            nth_weekday(2024, 10, -1, Weekday::Sun),
+            NaiveDate::from_ymd_opt(2024, 10, 27)
+        );
+        assert_eq!(
+            nth_weekday(2024, 3, 2, Weekday::Sun),
+            NaiveDate::from_ymd_opt(2024, 3, 10)
+        );
+    }
+}
@@ -0,0 +1,465 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::script::{OutputStream, Script};
+use crate::{glib_recv, module_impl, rc_mut, send_async, spawn};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use color_eyre::eyre::eyre;
+use color_eyre::Result;
+use glib::markup_escape_text;
+use gtk::gio::{AppInfo, AppLaunchContext};
+use gtk::prelude::*;
+use gtk::{Button, Label};
+use serde::Deserialize;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+use tracing::{debug, error, warn};
+
+mod ics;
+
+/// Time between updating the countdown.
+const TICK_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NextEventModule {
+    /// The calendar to read events from.
+    /// See [sources](#sources).
+    #[serde(flatten)]
+    source: EventSource,
+
+    /// Format string to use for the widget button label before the event starts.
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `{title} in {countdown}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Format string to use for the widget button label once the event has started.
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `{title} now`
+    #[serde(default = "default_format_started")]
+    format_started: String,
+
+    /// Number of minutes before the event starts to add the `urgent` class.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_urgent_minutes")]
+    urgent_minutes: u32,
+
+    /// Number of minutes to keep showing the event for once it has started.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_show_started_for")]
+    show_started_for: u32,
+
+    /// Number of hours ahead to look for events.
+    /// The widget is hidden if there are no events in this time.
+    ///
+    /// **Default**: `24`
+    #[serde(default = "default_lookahead")]
+    lookahead: u32,
+
+    /// Time in seconds between reading the calendar.
+    ///
+    /// **Default**: `300`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "source", rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum EventSource {
+    /// An iCalendar (`.ics`) file.
+    Ics {
+        /// A `http(s)` or `webcal` URL, or the path to a local file.
+        ///
+        /// **Required**
+        url: String,
+    },
+    /// A command which lists events, such as `khal` or `gcalcli`.
+    Command {
+        /// The command to run, using `sh`.
+        ///
+        /// **Required**
+        command: String,
+    },
+}
+
+fn default_format() -> String {
+    String::from("{title} in {countdown}")
+}
+
+fn default_format_started() -> String {
+    String::from("{title} now")
+}
+
+const fn default_urgent_minutes() -> u32 {
+    5
+}
+
+const fn default_show_started_for() -> u32 {
+    5
+}
+
+const fn default_lookahead() -> u32 {
+    24
+}
+
+const fn default_interval() -> u64 {
+    300
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    title: String,
+    start: DateTime<Local>,
+    /// The link to join the meeting.
+    url: Option<String>,
+}
+
+impl EventSource {
+    /// Gets the events starting within the given range.
+    async fn fetch(
+        &self,
+        client: &reqwest::Client,
+        from: DateTime<Local>,
+        to: DateTime<Local>,
+    ) -> Result<Vec<Event>> {
+        match self {
+            Self::Ics { url } => {
+                let input = if let Some(url) = url.strip_prefix("webcal://") {
+                    fetch_url(client, &format!("https://{url}")).await?
+                } else if url.starts_with("http://") || url.starts_with("https://") {
+                    fetch_url(client, url).await?
+                } else {
+                    let path = url.strip_prefix("file://").unwrap_or(url);
+                    tokio::fs::read_to_string(path).await?
+                };
+
+                Ok(ics::parse_events(&input, from, to))
+            }
+            Self::Command { command } => {
+                let script = Script::from(command.as_str());
+
+                match script.get_output_status(None).await? {
+                    (OutputStream::Stdout(output), _) => Ok(parse_command_output(&output)),
+                    (OutputStream::Stderr(error), status) => {
+                        Err(eyre!("Calendar command exited with {status}: {error}"))
+                    }
+                }
+            }
+        }
+    }
+}
+
+async fn fetch_url(client: &reqwest::Client, url: &str) -> Result<String> {
+    let res = client.get(url).send().await?.error_for_status()?;
+    Ok(res.text().await?)
+}
+
+/// Parses tab-separated lines of events,
+/// starting with the start time as either a single field or separate date and time fields.
+///
+/// Any following date or time fields, such as the end time, are skipped.
+/// The first remaining field is used as the title,
+/// and the first link in any field is used as the meeting URL.
+///
+/// This matches the output of `gcalcli agenda --tsv`.
+/// Lines which do not start with a time, such as headers or all-day events, are skipped.
+fn parse_command_output(output: &str) -> Vec<Event> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields = line.split('\t').map(str::trim).collect::<Vec<_>>();
+
+            let (start, rest) = match fields.as_slice() {
+                [start, rest @ ..] if parse_date_time(start).is_some() => {
+                    (parse_date_time(start)?, rest)
+                }
+                [date, time, rest @ ..] => (
+                    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                        .ok()?
+                        .and_time(parse_time(time)?),
+                    rest,
+                ),
+                _ => return None,
+            };
+
+            let start = start.and_local_timezone(Local).earliest()?;
+
+            let rest = rest
+                .iter()
+                .skip_while(|field| {
+                    parse_date_time(field).is_some()
+                        || NaiveDate::parse_from_str(field, "%Y-%m-%d").is_ok()
+                        || parse_time(field).is_some()
+                })
+                .collect::<Vec<_>>();
+
+            let url = rest.iter().find_map(|field| find_url(field));
+            let title = rest
+                .iter()
+                .find(|field| !field.is_empty() && find_url(field).is_none())
+                .map_or_else(String::new, ToString::to_string);
+
+            Some(Event { title, start, url })
+        })
+        .collect()
+}
+
+fn parse_date_time(value: &str) -> Option<NaiveDateTime> {
+    [
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%dT%H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+    ]
+    .into_iter()
+    .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+}
+
+fn parse_time(value: &str) -> Option<NaiveTime> {
+    ["%H:%M", "%H:%M:%S"]
+        .into_iter()
+        .find_map(|format| NaiveTime::parse_from_str(value, format).ok())
+}
+
+/// Finds the first `http(s)` link in some text.
+fn find_url(text: &str) -> Option<String> {
+    let start = text.find("https://").or_else(|| text.find("http://"))?;
+
+    let url = text[start..]
+        .split(|c: char| c.is_whitespace() || matches!(c, '<' | '>' | '"' | '\''))
+        .next()?
+        .trim_end_matches(['.', ',', ')', ';']);
+
+    Some(url.to_string())
+}
+
+/// Formats the time until an event starts, ie `1h 5m`.
+fn format_countdown(remaining: TimeDelta) -> String {
+    // round up, so that the countdown only reaches 0 as the event starts
+    let minutes = (remaining.num_seconds() + 59).div_euclid(60).max(0);
+
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+
+    match (days, hours, minutes) {
+        (0, 0, minutes) => format!("{minutes}m"),
+        (0, hours, 0) => format!("{hours}h"),
+        (0, hours, minutes) => format!("{hours}h {minutes}m"),
+        (days, 0, _) => format!("{days}d"),
+        (days, hours, _) => format!("{days}d {hours}h"),
+    }
+}
+
+impl Module<Button> for NextEventModule {
+    type SendMessage = Option<Event>;
+    type ReceiveMessage = ();
+
+    module_impl!("next_event");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let source = self.source.clone();
+        let interval = Duration::from_secs(self.interval);
+        let show_started_for = TimeDelta::minutes(i64::from(self.show_started_for));
+        let lookahead = TimeDelta::hours(i64::from(self.lookahead));
+
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("ironbar/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            let mut events = vec![];
+            let mut last_fetch: Option<Instant> = None;
+
+            loop {
+                if last_fetch.map_or(true, |time| time.elapsed() >= interval) {
+                    let now = Local::now();
+
+                    // look far enough ahead to cover events starting before the next fetch
+                    let to = now
+                        + lookahead
+                        + TimeDelta::from_std(interval).unwrap_or(TimeDelta::zero());
+
+                    match source.fetch(&client, now - show_started_for, to).await {
+                        Ok(fetched) => {
+                            debug!("Fetched {} calendar events", fetched.len());
+                            events = fetched;
+                        }
+                        Err(err) => warn!("{:?}", err.wrap_err("Failed to read calendar")),
+                    }
+
+                    last_fetch = Some(Instant::now());
+                }
+
+                let now = Local::now();
+
+                let next = events
+                    .iter()
+                    .filter(|event| {
+                        event.start + show_started_for > now && event.start <= now + lookahead
+                    })
+                    .min_by_key(|event| event.start)
+                    .cloned();
+
+                send_async!(tx, ModuleUpdateEvent::Update(next));
+
+                sleep(TICK_INTERVAL).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        let url = rc_mut!(None::<String>);
+
+        {
+            let url = url.clone();
+            button.connect_clicked(move |_| {
+                let Some(url) = url.borrow().clone() else {
+                    return;
+                };
+
+                if let Err(err) = AppInfo::launch_default_for_uri(&url, None::<&AppLaunchContext>) {
+                    error!("Failed to open meeting URL '{url}': {err:?}");
+                }
+            });
+        }
+
+        {
+            let button = button.clone();
+            let urgent = TimeDelta::minutes(i64::from(self.urgent_minutes));
+
+            glib_recv!(context.subscribe(), event => {
+                let style_context = button.style_context();
+                style_context.remove_class("urgent");
+                style_context.remove_class("started");
+
+                let Some(event) = event else {
+                    url.replace(None);
+                    button.hide();
+                    continue;
+                };
+
+                let remaining = event.start - Local::now();
+                let started = remaining <= TimeDelta::zero();
+
+                let format = if started {
+                    &self.format_started
+                } else {
+                    &self.format
+                };
+
+                let text = format
+                    .replace("{title}", &markup_escape_text(&event.title))
+                    .replace("{countdown}", &format_countdown(remaining))
+                    .replace("{time}", &event.start.format("%H:%M").to_string());
+
+                label.set_markup(&text);
+
+                if started {
+                    button.add_class("started");
+                } else if remaining <= urgent {
+                    button.add_class("urgent");
+                }
+
+                let mut tooltip = format!("{}\n{}", event.title, event.start.format("%a %d %b, %H:%M"));
+                if let Some(url) = &event.url {
+                    tooltip.push('\n');
+                    tooltip.push_str(url);
+                }
+                button.set_tooltip_text(Some(&tooltip));
+
+                url.replace(event.url);
+                button.show();
+            });
+        }
+
+        Ok(ModuleParts::new(button, None))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_gcalcli_tsv() {
+        let output = "start_date\tstart_time\tend_date\tend_time\thtml_link\ttitle\n\
+            2024-06-10\t09:30\t2024-06-10\t09:45\thttps://meet.example.com/standup\tStandup\n\
+            2024-06-11\t\t2024-06-12\t\t\tHoliday\n";
+
+        let events = parse_command_output(output);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Standup");
+        assert_eq!(
+            events[0].url.as_deref(),
+            Some("https://meet.example.com/standup")
+        );
+        assert_eq!(
+            events[0].start.naive_local(),
+            NaiveDate::from_ymd_opt(2024, 6, 10)
+                .and_then(|date| date.and_hms_opt(9, 30, 0))
+                .expect("valid date")
+        );
+    }
+
+    #[test]
+    fn test_parse_single_field_start() {
+        let events = parse_command_output("2024-06-10 14:00\tPlanning\n");
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Planning");
+        assert_eq!(events[0].url, None);
+    }
+
+    #[test]
+    fn test_find_url() {
+        assert_eq!(
+            find_url("Join: <https://meet.example.com/abc>."),
+            Some(String::from("https://meet.example.com/abc"))
+        );
+        assert_eq!(find_url("Room 4"), None);
+    }
+
+    #[test]
+    fn test_format_countdown() {
+        assert_eq!(format_countdown(TimeDelta::seconds(30)), "1m");
+        assert_eq!(format_countdown(TimeDelta::minutes(12)), "12m");
+        assert_eq!(format_countdown(TimeDelta::minutes(60)), "1h");
+        assert_eq!(format_countdown(TimeDelta::minutes(65)), "1h 5m");
+        assert_eq!(format_countdown(TimeDelta::hours(50)), "2d 2h");
+        assert_eq!(format_countdown(TimeDelta::minutes(-3)), "0m");
+    }
+}
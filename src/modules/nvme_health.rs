@@ -0,0 +1,379 @@
+use crate::clients::udisks::{self, NvmeHealth};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation, ProgressBar};
+use serde::Deserialize;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct NvmeHealthModule {
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `󰋊 {percent_used}%`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Time in seconds between checking drive health.
+    ///
+    /// **Default**: `3600`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// The percentage of rated endurance used
+    /// at or above which a drive is considered worn.
+    ///
+    /// **Default**: `80`
+    #[serde(default = "default_warn_percent_used")]
+    warn_percent_used: u8,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("󰋊 {percent_used}%")
+}
+
+const fn default_interval() -> u64 {
+    3600
+}
+
+const fn default_warn_percent_used() -> u8 {
+    80
+}
+
+impl NvmeHealthModule {
+    /// Whether the drive is reporting problems,
+    /// is low on spare capacity or has used most of its endurance.
+    fn is_unhealthy(&self, drive: &NvmeHealth) -> bool {
+        let spare_low = drive
+            .available_spare
+            .zip(drive.spare_threshold)
+            .is_some_and(|(spare, threshold)| spare <= threshold);
+
+        !drive.warnings.is_empty()
+            || spare_low
+            || drive
+                .percent_used
+                .is_some_and(|used| used >= self.warn_percent_used)
+    }
+
+    /// Creates the popup row for a single drive.
+    fn drive_row(&self, drive: &NvmeHealth) -> gtk::Box {
+        let row = gtk::Box::new(Orientation::Vertical, 0);
+        row.add_class("drive");
+
+        if self.is_unhealthy(drive) {
+            row.add_class("warning");
+        }
+
+        let name = Label::new(Some(&drive.name));
+        name.add_class("name");
+        name.set_halign(gtk::Align::Start);
+        row.add(&name);
+
+        if let Some(used) = drive.percent_used {
+            let progress = ProgressBar::new();
+            progress.set_fraction((f64::from(used) / 100.0).min(1.0));
+            row.add(&progress);
+        }
+
+        let details = [
+            (
+                "percent-used",
+                "Endurance used",
+                drive.percent_used.map(|used| format!("{used}%")),
+            ),
+            (
+                "spare",
+                "Available spare",
+                drive.available_spare.map(|spare| {
+                    drive.spare_threshold.map_or_else(
+                        || format!("{spare}%"),
+                        |threshold| format!("{spare}% (threshold {threshold}%)"),
+                    )
+                }),
+            ),
+            (
+                "data-written",
+                "Data written",
+                drive.data_written.map(format_bytes),
+            ),
+            (
+                "temperature",
+                "Temperature",
+                drive.temperature.map(|temp| format!("{temp:.0}°C")),
+            ),
+            (
+                "power-on-hours",
+                "Power-on time",
+                drive.power_on_hours.map(|hours| format!("{hours} hours")),
+            ),
+        ];
+
+        for (class, title, value) in details {
+            let Some(value) = value else {
+                continue;
+            };
+
+            let detail = gtk::Box::new(Orientation::Horizontal, 10);
+            detail.add_class("detail");
+            detail.add_class(class);
+
+            let title = Label::new(Some(title));
+            title.add_class("title");
+            title.set_halign(gtk::Align::Start);
+            title.set_hexpand(true);
+            detail.add(&title);
+
+            let value = Label::new(Some(&value));
+            value.add_class("value");
+            detail.add(&value);
+
+            row.add(&detail);
+        }
+
+        if !drive.warnings.is_empty() {
+            let warnings = Label::new(Some(&drive.warnings.join("\n")));
+            warnings.add_class("status");
+            warnings.set_halign(gtk::Align::Start);
+            row.add(&warnings);
+        }
+
+        row
+    }
+}
+
+impl Module<Button> for NvmeHealthModule {
+    type SendMessage = Vec<NvmeHealth>;
+    type ReceiveMessage = ();
+
+    module_impl!("nvme_health");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context.try_client::<udisks::Client>()?;
+        let interval = Duration::from_secs(self.interval);
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            loop {
+                match client.nvme_health().await {
+                    Ok(drives) => {
+                        debug!("NVMe health: {drives:?}");
+                        send_async!(tx, ModuleUpdateEvent::Update(drives));
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+
+                sleep(interval).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let module = self.clone();
+
+            glib_recv!(context.subscribe(), drives => {
+                let percent_used = drives
+                    .iter()
+                    .filter_map(|drive| drive.percent_used)
+                    .max()
+                    .map_or_else(|| String::from("?"), |used| used.to_string());
+
+                let spare = drives
+                    .iter()
+                    .filter_map(|drive| drive.available_spare)
+                    .min()
+                    .map_or_else(|| String::from("?"), |spare| spare.to_string());
+
+                let temperature = drives
+                    .iter()
+                    .filter_map(|drive| drive.temperature)
+                    .reduce(f64::max)
+                    .map_or_else(|| String::from("?"), |temp| format!("{temp:.0}"));
+
+                let data_written = format_bytes(
+                    drives.iter().filter_map(|drive| drive.data_written).sum(),
+                );
+
+                let unhealthy = drives
+                    .iter()
+                    .filter(|drive| module.is_unhealthy(drive))
+                    .count();
+
+                let status = if unhealthy == 0 { "OK" } else { "Warning" };
+
+                label.set_markup(
+                    &module
+                        .format
+                        .replace("{percent_used}", &percent_used)
+                        .replace("{spare}", &spare)
+                        .replace("{temperature}", &temperature)
+                        .replace("{data_written}", &data_written)
+                        .replace("{unhealthy}", &unhealthy.to_string())
+                        .replace("{status}", status),
+                );
+
+                if unhealthy == 0 {
+                    button.style_context().remove_class("warning");
+                } else {
+                    button.add_class("warning");
+                }
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        _tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, drives => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                if drives.is_empty() {
+                    let label = Label::new(Some("No NVMe drives found"));
+                    label.add_class("empty");
+                    container.add(&label);
+                }
+
+                for drive in &drives {
+                    container.add(&self.drive_row(drive));
+                }
+
+                container.show_all();
+            });
+        }
+
+        Some(container)
+    }
+}
+
+/// Formats a number of bytes using the largest fitting SI unit, ie `52.3 TB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+
+    while value >= 1000.0 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drive() -> NvmeHealth {
+        NvmeHealth {
+            path: String::new(),
+            name: String::from("Samsung SSD 980"),
+            percent_used: Some(12),
+            available_spare: Some(100),
+            spare_threshold: Some(10),
+            temperature: Some(41.0),
+            data_written: Some(52_300_000_000_000),
+            power_on_hours: Some(8123),
+            warnings: vec![],
+        }
+    }
+
+    fn module() -> NvmeHealthModule {
+        NvmeHealthModule {
+            format: default_format(),
+            interval: default_interval(),
+            warn_percent_used: default_warn_percent_used(),
+            common: None,
+        }
+    }
+
+    #[test]
+    fn test_is_unhealthy() {
+        let module = module();
+        assert!(!module.is_unhealthy(&drive()));
+
+        let worn = NvmeHealth {
+            percent_used: Some(85),
+            ..drive()
+        };
+        assert!(module.is_unhealthy(&worn));
+
+        let low_spare = NvmeHealth {
+            available_spare: Some(10),
+            ..drive()
+        };
+        assert!(module.is_unhealthy(&low_spare));
+    }
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(52_300_000_000_000), "52.3 TB");
+    }
+}
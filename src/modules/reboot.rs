@@ -0,0 +1,385 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::new_icon_button;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::{Report, Result};
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error};
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.login1",
+    interface = "org.freedesktop.login1.Manager",
+    default_path = "/org/freedesktop/login1"
+)]
+trait Login1Manager {
+    fn can_reboot_to_firmware_setup(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(allow_interactive_auth)]
+    fn set_reboot_to_firmware_setup(&self, enable: bool) -> zbus::Result<()>;
+
+    fn can_reboot_to_boot_loader_entry(&self) -> zbus::Result<String>;
+
+    #[dbus_proxy(allow_interactive_auth)]
+    fn set_reboot_to_boot_loader_entry(&self, boot_loader_entry: &str) -> zbus::Result<()>;
+
+    #[dbus_proxy(allow_interactive_auth)]
+    fn reboot(&self, interactive: bool) -> zbus::Result<()>;
+
+    #[dbus_proxy(property)]
+    fn boot_loader_entries(&self) -> zbus::Result<Vec<String>>;
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RebootModule {
+    /// The icon to show on the bar widget button.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `󰜉`
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render the icon at.
+    /// Note this only applies to image-type icons.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// Whether to show an entry to reboot normally.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    show_reboot: bool,
+
+    /// Whether to show an entry to reboot into the UEFI firmware setup.
+    /// This is only shown if the firmware supports it.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    firmware_setup: bool,
+
+    /// The boot loader entries to show an entry for.
+    /// Each is only shown if the boot loader reports it.
+    ///
+    /// Entry IDs can be found using `bootctl list`.
+    /// Requires a boot loader implementing the
+    /// [boot loader interface](https://systemd.io/BOOT_LOADER_INTERFACE), such as systemd-boot.
+    ///
+    /// **Default**: `[{ id = "auto-windows" label = "Windows" }]`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// {
+    ///   entries = [
+    ///     { id = "auto-windows" label = "Windows" }
+    ///     { id = "arch-lts.conf" label = "Arch (LTS)" }
+    ///   ]
+    /// }
+    /// ```
+    #[serde(default = "default_entries")]
+    entries: Vec<BootEntry>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct BootEntry {
+    /// The boot loader entry ID, ie `auto-windows`.
+    id: String,
+
+    /// The name to show for the entry.
+    label: String,
+}
+
+fn default_icon() -> String {
+    String::from("󰜉")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+fn default_entries() -> Vec<BootEntry> {
+    vec![BootEntry {
+        id: String::from("auto-windows"),
+        label: String::from("Windows"),
+    }]
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebootTarget {
+    /// The default boot entry.
+    Default,
+    /// The UEFI firmware setup.
+    FirmwareSetup,
+    /// A specific boot loader entry.
+    Entry(BootEntry),
+}
+
+impl RebootTarget {
+    fn label(&self) -> String {
+        match self {
+            Self::Default => String::from("Reboot"),
+            Self::FirmwareSetup => String::from("Reboot to firmware setup"),
+            Self::Entry(entry) => format!("Reboot to {}", entry.label),
+        }
+    }
+}
+
+impl RebootModule {
+    /// Gets the targets which can be rebooted into,
+    /// filtering out any the system does not support.
+    async fn available_targets(&self, proxy: &Login1ManagerProxy<'_>) -> Vec<RebootTarget> {
+        let mut targets = vec![];
+
+        if self.show_reboot {
+            targets.push(RebootTarget::Default);
+        }
+
+        if self.firmware_setup
+            && is_supported(proxy.can_reboot_to_firmware_setup().await, "firmware setup")
+        {
+            targets.push(RebootTarget::FirmwareSetup);
+        }
+
+        if !self.entries.is_empty()
+            && is_supported(
+                proxy.can_reboot_to_boot_loader_entry().await,
+                "boot loader entry",
+            )
+        {
+            match proxy.boot_loader_entries().await {
+                Ok(available) => targets
+                    .extend(filter_entries(&self.entries, &available).map(RebootTarget::Entry)),
+                Err(err) => error!("Failed to get boot loader entries: {err:?}"),
+            }
+        }
+
+        targets
+    }
+}
+
+impl Module<Button> for RebootModule {
+    type SendMessage = Vec<RebootTarget>;
+    type ReceiveMessage = RebootTarget;
+
+    module_impl!("reboot");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let module = self.clone();
+        let tx = context.tx.clone();
+
+        spawn(async move {
+            let proxy = match connect().await {
+                Ok(proxy) => proxy,
+                Err(err) => {
+                    error!("{:?}", err.wrap_err("Failed to connect to logind"));
+                    return;
+                }
+            };
+
+            let targets = module.available_targets(&proxy).await;
+            debug!("Reboot targets: {targets:?}");
+            send_async!(tx, ModuleUpdateEvent::Update(targets));
+
+            while let Some(target) = rx.recv().await {
+                if let Err(err) = reboot(&proxy, &target).await {
+                    error!("{:?}", err.wrap_err("Failed to reboot"));
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = new_icon_button(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, targets => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                for target in targets {
+                    container.add(&target_button(target, &tx));
+                }
+
+                container.show_all();
+            });
+        }
+
+        Some(container)
+    }
+}
+
+/// Creates a popup button which reboots into the target when clicked.
+fn target_button(target: RebootTarget, tx: &mpsc::Sender<RebootTarget>) -> Button {
+    let button = Button::new();
+    button.add_class("target");
+
+    match &target {
+        RebootTarget::Default => button.add_class("default"),
+        RebootTarget::FirmwareSetup => button.add_class("firmware-setup"),
+        RebootTarget::Entry(_) => button.add_class("entry"),
+    }
+
+    let label = Label::new(Some(&target.label()));
+    label.set_halign(gtk::Align::Start);
+    button.add(&label);
+
+    let tx = tx.clone();
+    button.connect_clicked(move |_| {
+        try_send!(tx, target.clone());
+    });
+
+    button
+}
+
+async fn connect() -> Result<Login1ManagerProxy<'static>> {
+    let dbus = Box::pin(zbus::Connection::system()).await?;
+    Ok(Login1ManagerProxy::new(&dbus).await?)
+}
+
+/// Sets the target for the next boot, then reboots.
+async fn reboot(proxy: &Login1ManagerProxy<'_>, target: &RebootTarget) -> Result<()> {
+    debug!("Rebooting to {target:?}");
+
+    match target {
+        RebootTarget::Default => {}
+        RebootTarget::FirmwareSetup => proxy.set_reboot_to_firmware_setup(true).await?,
+        RebootTarget::Entry(entry) => proxy.set_reboot_to_boot_loader_entry(&entry.id).await?,
+    }
+
+    if let Err(err) = proxy.reboot(true).await {
+        // do not leave the one-off target set if the reboot is cancelled
+        let reset = match target {
+            RebootTarget::Default => Ok(()),
+            RebootTarget::FirmwareSetup => proxy.set_reboot_to_firmware_setup(false).await,
+            RebootTarget::Entry(_) => proxy.set_reboot_to_boot_loader_entry("").await,
+        };
+
+        if let Err(reset_err) = reset {
+            error!("Failed to reset reboot target: {reset_err:?}");
+        }
+
+        return Err(Report::new(err));
+    }
+
+    Ok(())
+}
+
+/// Checks the result of a logind `Can*` method.
+/// `challenge` means the action is supported, but requires authentication.
+fn is_supported(result: zbus::Result<String>, name: &str) -> bool {
+    match result {
+        Ok(value) => {
+            debug!("Reboot to {name} support: {value}");
+            matches!(value.as_str(), "yes" | "challenge")
+        }
+        Err(err) => {
+            error!("Failed to check support for rebooting to {name}: {err:?}");
+            false
+        }
+    }
+}
+
+/// Gets the configured entries which are reported by the boot loader,
+/// keeping the configured order.
+fn filter_entries<'a>(
+    entries: &'a [BootEntry],
+    available: &'a [String],
+) -> impl Iterator<Item = BootEntry> + 'a {
+    entries
+        .iter()
+        .filter(|entry| available.contains(&entry.id))
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_entries() {
+        let entries = vec![
+            BootEntry {
+                id: String::from("auto-windows"),
+                label: String::from("Windows"),
+            },
+            BootEntry {
+                id: String::from("arch-lts.conf"),
+                label: String::from("Arch (LTS)"),
+            },
+        ];
+
+        let available = vec![
+            String::from("arch.conf"),
+            String::from("arch-lts.conf"),
+            String::from("auto-efi-shell"),
+        ];
+
+        let filtered = filter_entries(&entries, &available).collect::<Vec<_>>();
+        assert_eq!(filtered, [entries[1].clone()]);
+    }
+
+    #[test]
+    fn test_target_label() {
+        assert_eq!(RebootTarget::Default.label(), "Reboot");
+        assert_eq!(
+            RebootTarget::Entry(default_entries().remove(0)).label(),
+            "Reboot to Windows"
+        );
+    }
+}
@@ -0,0 +1,522 @@
+use crate::config::CommonConfig;
+use crate::desktop_file::find_applications;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::{new_icon_button, ImageProvider};
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModulePopupParts, ModuleUpdateEvent, PopupButton,
+    WidgetContext,
+};
+use crate::{glib_recv, module_impl, rc_mut, send_async, spawn, spawn_blocking, try_send};
+use color_eyre::{Report, Result};
+use glib::Propagation;
+use gtk::gdk::keys::constants as keys;
+use gtk::prelude::*;
+use gtk::{Button, Entry, Image, Label, ListBox, ListBoxRow, Orientation};
+use serde::Deserialize;
+use std::env;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use tokio::sync::{broadcast, mpsc};
+use tracing::error;
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct RunModule {
+    /// The icon to show on the bar widget button.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: ``
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render the icon at.
+    /// Note this only applies to image-type icons.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `32`
+    icon_size: Option<i32>,
+
+    /// The size to render application icons at in the results list.
+    ///
+    /// **Default**: `24`
+    #[serde(default = "default_result_icon_size")]
+    result_icon_size: i32,
+
+    /// The maximum number of results to show.
+    ///
+    /// **Default**: `10`
+    #[serde(default = "default_max_results")]
+    max_results: usize,
+
+    /// Whether to include executables on the `PATH` in the results,
+    /// as well as applications.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    include_path: bool,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon() -> String {
+    String::from("")
+}
+
+const fn default_icon_size() -> i32 {
+    32
+}
+
+const fn default_result_icon_size() -> i32 {
+    24
+}
+
+const fn default_max_results() -> usize {
+    10
+}
+
+#[derive(Debug, Clone)]
+pub enum RunItem {
+    /// An application with a `.desktop` file.
+    Application {
+        name: String,
+        file: PathBuf,
+        icon: Option<String>,
+    },
+    /// An executable on the `PATH`.
+    Binary(String),
+}
+
+impl RunItem {
+    fn name(&self) -> &str {
+        match self {
+            Self::Application { name, .. } | Self::Binary(name) => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum RunEvent {
+    /// Reloads the list of applications and binaries.
+    Refresh,
+    /// Launches a search result.
+    Launch(RunItem),
+    /// Runs the entered text as a shell command.
+    Command(String),
+}
+
+impl Module<Button> for RunModule {
+    type SendMessage = Vec<RunItem>;
+    type ReceiveMessage = RunEvent;
+
+    module_impl!("run");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let include_path = self.include_path;
+
+        spawn(async move {
+            // load once up-front, so results are ready the first time the popup opens
+            let items = spawn_blocking(move || find_items(include_path)).await?;
+            send_async!(tx, ModuleUpdateEvent::Update(items));
+
+            while let Some(event) = rx.recv().await {
+                match event {
+                    RunEvent::Refresh => {
+                        let items = spawn_blocking(move || find_items(include_path)).await?;
+                        send_async!(tx, ModuleUpdateEvent::Update(items));
+                    }
+                    RunEvent::Launch(item) => {
+                        send_async!(tx, ModuleUpdateEvent::ClosePopup);
+                        launch(&item);
+                    }
+                    RunEvent::Command(command) => {
+                        send_async!(tx, ModuleUpdateEvent::ClosePopup);
+                        spawn_command(Command::new("sh").arg("-c").arg(&command));
+                    }
+                }
+            }
+
+            Ok::<_, Report>(())
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = new_icon_button(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
+        button.add_class("btn");
+
+        {
+            let tx = context.tx.clone();
+            let controller_tx = context.controller_tx.clone();
+
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+                try_send!(controller_tx, RunEvent::Refresh);
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button])
+            // the search entry needs keyboard input
+            .map(|parts| ModulePopupParts {
+                keyboard: true,
+                ..parts
+            });
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        let entry = Entry::new();
+        entry.add_class("entry");
+        container.add(&entry);
+
+        let results = ListBox::new();
+        results.add_class("results");
+        container.add(&results);
+
+        let items = rc_mut!(vec![]);
+        let matches = rc_mut!(Vec::<RunItem>::new());
+
+        {
+            let items = items.clone();
+            glib_recv!(rx, new_items => {
+                *items.borrow_mut() = new_items;
+            });
+        }
+
+        // start with a fresh search each time the popup opens
+        {
+            let entry = entry.clone();
+            container.connect_map(move |_| {
+                entry.set_text("");
+                entry.grab_focus();
+            });
+        }
+
+        {
+            let results = results.clone();
+            let matches = matches.clone();
+            let icon_theme = info.icon_theme.clone();
+            let result_icon_size = info.scaled(self.result_icon_size);
+
+            entry.connect_changed(move |entry| {
+                let query = entry.text();
+                let new_matches = search(&items.borrow(), &query, self.max_results);
+
+                for child in results.children() {
+                    results.remove(&child);
+                }
+
+                for item in &new_matches {
+                    let row = build_row(item, &icon_theme, result_icon_size);
+                    results.add(&row);
+                }
+
+                results.show_all();
+                results.select_row(results.row_at_index(0).as_ref());
+
+                *matches.borrow_mut() = new_matches;
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            let results = results.clone();
+            let matches = matches.clone();
+
+            entry.connect_activate(move |entry| {
+                let selected = results
+                    .selected_row()
+                    .and_then(|row| usize::try_from(row.index()).ok())
+                    .and_then(|index| matches.borrow().get(index).cloned());
+
+                match selected {
+                    Some(item) => try_send!(tx, RunEvent::Launch(item)),
+                    None => {
+                        let command = entry.text().trim().to_string();
+                        if !command.is_empty() {
+                            try_send!(tx, RunEvent::Command(command));
+                        }
+                    }
+                }
+            });
+        }
+
+        {
+            let results = results.clone();
+            let ui_tx = context.tx.clone();
+
+            entry.connect_key_press_event(move |_, event| {
+                let key = event.keyval();
+
+                if key == keys::Escape {
+                    try_send!(ui_tx, ModuleUpdateEvent::ClosePopup);
+                    return Propagation::Stop;
+                }
+
+                let offset = if key == keys::Down {
+                    1
+                } else if key == keys::Up {
+                    -1
+                } else {
+                    return Propagation::Proceed;
+                };
+
+                let index = results.selected_row().map_or(0, |row| row.index() + offset);
+                if let Some(row) = results.row_at_index(index) {
+                    results.select_row(Some(&row));
+                }
+
+                Propagation::Stop
+            });
+        }
+
+        results.connect_row_activated(move |_, row| {
+            let item = usize::try_from(row.index())
+                .ok()
+                .and_then(|index| matches.borrow().get(index).cloned());
+
+            if let Some(item) = item {
+                try_send!(tx, RunEvent::Launch(item));
+            }
+        });
+
+        container.show_all();
+
+        Some(container)
+    }
+}
+
+fn build_row(item: &RunItem, icon_theme: &gtk::IconTheme, icon_size: i32) -> ListBoxRow {
+    let row = ListBoxRow::new();
+    row.add_class("result");
+
+    let content = gtk::Box::new(Orientation::Horizontal, 5);
+
+    match item {
+        RunItem::Application { icon, .. } => {
+            row.add_class("application");
+
+            let image = Image::new();
+            image.add_class("icon");
+
+            if let Some(provider) = icon
+                .as_ref()
+                .and_then(|icon| ImageProvider::parse(icon, icon_theme, true, icon_size))
+            {
+                if let Err(err) = provider.load_into_image(image.clone()) {
+                    error!("{err:?}");
+                }
+            }
+
+            content.add(&image);
+        }
+        RunItem::Binary(_) => row.add_class("binary"),
+    }
+
+    let label = Label::new(Some(item.name()));
+    label.add_class("label");
+    content.add(&label);
+
+    row.add(&content);
+    row
+}
+
+/// Gets all applications, and optionally all executables on the `PATH`.
+fn find_items(include_path: bool) -> Vec<RunItem> {
+    let mut items = find_applications()
+        .into_iter()
+        .map(|entry| RunItem::Application {
+            name: entry.name,
+            file: entry.file,
+            icon: entry.icon,
+        })
+        .collect::<Vec<_>>();
+
+    if include_path {
+        items.extend(find_binaries().into_iter().map(RunItem::Binary));
+    }
+
+    items
+}
+
+/// Gets the names of all executable files in `PATH` directories.
+fn find_binaries() -> Vec<String> {
+    let Some(path) = env::var_os("PATH") else {
+        return vec![];
+    };
+
+    let mut names = env::split_paths(&path)
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            // follow symlinks, which are common in bin directories
+            fs::metadata(entry.path())
+                .is_ok_and(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+        })
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<Vec<_>>();
+
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Gets the best matching items for the query, best first.
+/// Applications are ranked above binaries with the same score.
+fn search(items: &[RunItem], query: &str, max_results: usize) -> Vec<RunItem> {
+    let query = query.trim();
+    if query.is_empty() {
+        return vec![];
+    }
+
+    let mut matches = items
+        .iter()
+        .filter_map(|item| {
+            fuzzy_score(query, item.name()).map(|score| {
+                let is_app = matches!(item, RunItem::Application { .. });
+                ((score, is_app), item)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    matches.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+    matches
+        .into_iter()
+        .take(max_results)
+        .map(|(_, item)| item.clone())
+        .collect()
+}
+
+/// Scores how well `query` fuzzily matches `candidate`, ignoring case.
+/// Higher scores are better.
+///
+/// Every character in the query must appear in the candidate in the same order.
+/// Consecutive characters and characters at the start of words score higher,
+/// and shorter candidates are preferred.
+///
+/// Returns `None` if the candidate does not match.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    const MATCH: i64 = 100;
+    const CONSECUTIVE_BONUS: i64 = 500;
+    const WORD_START_BONUS: i64 = 300;
+
+    let candidate = candidate.to_lowercase().chars().collect::<Vec<_>>();
+
+    let mut score = 0;
+    let mut position = 0;
+    let mut last_match: Option<usize> = None;
+
+    for char in query.to_lowercase().chars() {
+        let index = candidate[position..].iter().position(|&c| c == char)? + position;
+
+        score += MATCH;
+
+        if last_match.is_some_and(|last| last + 1 == index) {
+            score += CONSECUTIVE_BONUS;
+        }
+
+        if index == 0 || !candidate[index - 1].is_alphanumeric() {
+            score += WORD_START_BONUS;
+        }
+
+        last_match = Some(index);
+        position = index + 1;
+    }
+
+    Some(score - candidate.len() as i64)
+}
+
+fn launch(item: &RunItem) {
+    match item {
+        RunItem::Application { file, .. } => {
+            let Some(file_name) = file.file_name() else {
+                error!("Invalid desktop file path: {}", file.display());
+                return;
+            };
+
+            spawn_command(Command::new("gtk-launch").arg(file_name));
+        }
+        RunItem::Binary(name) => spawn_command(&mut Command::new(name)),
+    }
+}
+
+fn spawn_command(command: &mut Command) {
+    if let Err(err) = command.stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+        error!(
+            "{:?}",
+            Report::new(err).wrap_err(format!("Failed to run command: {command:?}"))
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score_matches_subsequence() {
+        assert!(fuzzy_score("ffx", "Firefox").is_some());
+        assert!(fuzzy_score("fox", "Firefox").is_some());
+        assert!(fuzzy_score("xof", "Firefox").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_score_prefers_prefix() {
+        let prefix = fuzzy_score("fire", "Firefox").unwrap();
+        let scattered = fuzzy_score("fire", "File Reader").unwrap();
+        assert!(prefix > scattered);
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        let items = vec![
+            RunItem::Binary(String::from("firefox")),
+            RunItem::Binary(String::from("fzf")),
+            RunItem::Application {
+                name: String::from("Firefox"),
+                file: PathBuf::from("firefox.desktop"),
+                icon: None,
+            },
+        ];
+
+        let results = search(&items, "firef", 10);
+
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], RunItem::Application { .. }));
+        assert!(search(&items, "", 10).is_empty());
+    }
+}
@@ -1,13 +1,18 @@
 use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::power::BatteryScale;
 use crate::script::{OutputStream, Script, ScriptMode};
-use crate::{glib_recv, module_impl, spawn, try_send};
+use crate::{glib_recv, module_impl, send_async, spawn};
 use color_eyre::{Help, Report, Result};
 use gtk::prelude::*;
 use gtk::Label;
 use serde::Deserialize;
+use std::process::ExitStatus;
+use std::time::Duration;
 use tokio::sync::mpsc;
-use tracing::error;
+use tokio::time::sleep;
+use tracing::{error, warn};
 
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -35,6 +40,57 @@ pub struct ScriptModule {
     #[serde(default = "default_interval")]
     interval: u64,
 
+    /// The factor to multiply `interval` by
+    /// while the system is running on battery power.
+    ///
+    /// Requires the `upower` feature.
+    /// Leave unset to use the same interval regardless of power source.
+    ///
+    /// **Default**: `null`
+    battery_interval_multiplier: Option<f64>,
+
+    /// Text to show when the script fails.
+    /// A polled script fails when it exits with a non-zero code,
+    /// and a watched script fails whenever its process exits.
+    ///
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// Leave unset to keep showing the last successful output.
+    /// The `error` class is added to the label either way.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { error_text = "⚠ {exit_code}" }
+    /// ```
+    error_text: Option<String>,
+
+    /// The maximum time in milliseconds to wait between retries
+    /// after the script fails.
+    ///
+    /// Each consecutive failure doubles the wait, starting from `interval`,
+    /// until this limit is reached.
+    ///
+    /// **Default**: `300000`
+    #[serde(default = "default_max_backoff")]
+    max_backoff: u64,
+
+    /// Whether lines in the form `@name value` set the `name` [ironvar](ironvars)
+    /// instead of being shown on the label.
+    ///
+    /// This allows a single long-running script to feed several widgets,
+    /// which can reference each value using `#name`.
+    /// Any other lines are shown on the label as usual.
+    ///
+    /// Requires the `ipc` feature.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    named_outputs: bool,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -50,6 +106,11 @@ const fn default_interval() -> u64 {
     5000
 }
 
+/// 5 minutes
+const fn default_max_backoff() -> u64 {
+    300_000
+}
+
 impl From<&ScriptModule> for Script {
     fn from(module: &ScriptModule) -> Self {
         Self {
@@ -60,8 +121,104 @@ impl From<&ScriptModule> for Script {
     }
 }
 
+#[derive(Debug, Clone)]
+pub enum ScriptUpdate {
+    /// The script ran successfully.
+    /// The output is `None` if it only contained named outputs.
+    Output(Option<String>),
+    Failed {
+        exit_code: Option<i32>,
+        stderr: String,
+    },
+}
+
+impl ScriptUpdate {
+    fn failed(status: ExitStatus, stderr: String) -> Self {
+        Self::Failed {
+            exit_code: status.code(),
+            stderr,
+        }
+    }
+}
+
+/// Gets the time to wait before retrying,
+/// after the given number of consecutive failures.
+fn backoff(interval: u64, failures: u32, max_backoff: u64) -> Duration {
+    let multiplier = 2u64.saturating_pow(failures.saturating_sub(1));
+    let delay = interval
+        .saturating_mul(multiplier)
+        .min(max_backoff.max(interval));
+
+    Duration::from_millis(delay)
+}
+
+/// Splits `@name value` lines out of the script output,
+/// returning them alongside the remaining text.
+///
+/// The remaining text is `None` if every line was a named output.
+fn split_named_outputs(output: &str) -> (Vec<(&str, &str)>, Option<String>) {
+    let mut named = vec![];
+    let mut rest = vec![];
+
+    for line in output.lines() {
+        let named_output = line
+            .strip_prefix('@')
+            .and_then(|line| line.split_once(' '))
+            .filter(|(name, _)| !name.is_empty());
+
+        match named_output {
+            Some((name, value)) => named.push((name, value)),
+            None => rest.push(line),
+        }
+    }
+
+    let rest = if rest.is_empty() {
+        None
+    } else {
+        Some(rest.join("\n"))
+    };
+
+    (named, rest)
+}
+
+/// Sets the ironvar for each named output in the script output,
+/// returning the remaining text to show on the label.
+fn apply_named_outputs(output: &str) -> Option<String> {
+    let (named, rest) = split_named_outputs(output);
+
+    #[cfg(feature = "ipc")]
+    {
+        let variable_manager = crate::Ironbar::variable_manager();
+        let mut variable_manager = crate::write_lock!(variable_manager);
+
+        for (name, value) in named {
+            if variable_manager
+                .set(name.into(), value.to_string())
+                .is_err()
+            {
+                warn!("Ignoring named output with invalid name: '{name}'");
+            }
+        }
+    }
+
+    #[cfg(not(feature = "ipc"))]
+    if !named.is_empty() {
+        warn!("Named script outputs require the `ipc` feature");
+    }
+
+    rest
+}
+
+fn log_stderr(stderr: String) {
+    error!("{:?}", Report::msg(stderr)
+        .wrap_err("Watched script error:")
+        .suggestion("Check the path to your script")
+        .suggestion("Check the script for errors")
+        .suggestion("If you expect the script to write to stderr, consider redirecting its output to /dev/null to suppress these messages"));
+}
+
 impl Module<Label> for ScriptModule {
-    type SendMessage = String;
+    type SendMessage = ScriptUpdate;
     type ReceiveMessage = ();
 
     module_impl!("script");
@@ -73,21 +230,95 @@ impl Module<Label> for ScriptModule {
         _rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         let script: Script = self.into();
+        let max_backoff = self.max_backoff;
+        let named_outputs = self.named_outputs;
+        let scale = BatteryScale::new(self.battery_interval_multiplier, context);
 
         let tx = context.tx.clone();
         spawn(async move {
-            script.run(None, move |out, _| match out {
-               OutputStream::Stdout(stdout) => {
-                   try_send!(tx, ModuleUpdateEvent::Update(stdout));
-               },
-               OutputStream::Stderr(stderr) => {
-                   error!("{:?}", Report::msg(stderr)
-                                    .wrap_err("Watched script error:")
-                                    .suggestion("Check the path to your script")
-                                    .suggestion("Check the script for errors")
-                                    .suggestion("If you expect the script to write to stderr, consider redirecting its output to /dev/null to suppress these messages"));
-               }
-           }).await;
+            let mut failures = 0;
+
+            let output = |stdout: String| {
+                if named_outputs {
+                    ScriptUpdate::Output(apply_named_outputs(&stdout))
+                } else {
+                    ScriptUpdate::Output(Some(stdout))
+                }
+            };
+
+            loop {
+                let update = match script.mode {
+                    ScriptMode::Poll => match script.get_output_status(None).await {
+                        Ok((OutputStream::Stdout(stdout), status)) if status.success() => {
+                            output(stdout)
+                        }
+                        Ok((OutputStream::Stdout(stdout), status)) => {
+                            ScriptUpdate::failed(status, stdout)
+                        }
+                        Ok((OutputStream::Stderr(stderr), status)) => {
+                            log_stderr(stderr.clone());
+                            ScriptUpdate::failed(status, stderr)
+                        }
+                        Err(err) => {
+                            error!("{err:?}");
+                            ScriptUpdate::Failed {
+                                exit_code: None,
+                                stderr: err.to_string(),
+                            }
+                        }
+                    },
+                    ScriptMode::Watch => match script.spawn_with_status() {
+                        Ok((mut rx, status_rx)) => {
+                            let mut last_stderr = String::new();
+
+                            while let Some(out) = rx.recv().await {
+                                match out {
+                                    OutputStream::Stdout(stdout) => {
+                                        failures = 0;
+                                        send_async!(tx, ModuleUpdateEvent::Update(output(stdout)));
+                                    }
+                                    OutputStream::Stderr(stderr) => {
+                                        log_stderr(stderr.clone());
+                                        last_stderr = stderr;
+                                    }
+                                }
+                            }
+
+                            let exit_code = status_rx.await.ok().and_then(|status| status.code());
+                            warn!(
+                                "Watched script '{}' exited with code {exit_code:?}",
+                                script.cmd
+                            );
+
+                            ScriptUpdate::Failed {
+                                exit_code,
+                                stderr: last_stderr,
+                            }
+                        }
+                        Err(err) => {
+                            error!("{err:?}");
+                            ScriptUpdate::Failed {
+                                exit_code: None,
+                                stderr: err.to_string(),
+                            }
+                        }
+                    },
+                };
+
+                let delay = match update {
+                    ScriptUpdate::Output(_) => {
+                        failures = 0;
+                        Duration::from_millis(script.interval)
+                    }
+                    ScriptUpdate::Failed { .. } => {
+                        failures += 1;
+                        backoff(script.interval, failures, max_backoff)
+                    }
+                };
+
+                send_async!(tx, ModuleUpdateEvent::Update(update));
+                sleep(scale.apply(delay)).await;
+            }
         });
 
         Ok(())
@@ -103,7 +334,31 @@ impl Module<Label> for ScriptModule {
 
         {
             let label = label.clone();
-            glib_recv!(context.subscribe(), s => label.set_markup(s.as_str()));
+            let error_text = self.error_text;
+
+            glib_recv!(context.subscribe(), update => {
+                match update {
+                    ScriptUpdate::Output(output) => {
+                        label.style_context().remove_class("error");
+
+                        if let Some(output) = output {
+                            label.set_markup(&output);
+                        }
+                    }
+                    ScriptUpdate::Failed { exit_code, stderr } => {
+                        label.add_class("error");
+
+                        if let Some(error_text) = &error_text {
+                            let exit_code = exit_code.map(|code| code.to_string()).unwrap_or_default();
+                            let text = error_text
+                                .replace("{exit_code}", &exit_code)
+                                .replace("{stderr}", &glib::markup_escape_text(&stderr));
+
+                            label.set_markup(&text);
+                        }
+                    }
+                }
+            });
         }
 
         Ok(ModuleParts {
@@ -112,3 +367,28 @@ impl Module<Label> for ScriptModule {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(backoff(1000, 1, 10_000), Duration::from_millis(1000));
+        assert_eq!(backoff(1000, 2, 10_000), Duration::from_millis(2000));
+        assert_eq!(backoff(1000, 4, 10_000), Duration::from_millis(8000));
+        assert_eq!(backoff(1000, 5, 10_000), Duration::from_millis(10_000));
+        assert_eq!(backoff(1000, 100, 10_000), Duration::from_millis(10_000));
+    }
+
+    #[test]
+    fn test_split_named_outputs() {
+        let (named, rest) = split_named_outputs("@temp 42\n@fan 2000 rpm\nhello\n@ invalid");
+        assert_eq!(named, vec![("temp", "42"), ("fan", "2000 rpm")]);
+        assert_eq!(rest.as_deref(), Some("hello\n@ invalid"));
+
+        let (named, rest) = split_named_outputs("@temp 42");
+        assert_eq!(named, vec![("temp", "42")]);
+        assert_eq!(rest, None);
+    }
+}
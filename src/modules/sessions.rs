@@ -0,0 +1,430 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::new_icon_label;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::{Report, Result};
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use serde::Deserialize;
+use std::fmt::{Display, Formatter};
+use std::io::ErrorKind;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct SessionsModule {
+    /// The icon to show on the bar widget button.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: ``
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render the icon at.
+    /// Note this only applies to image-type icons.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{count}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The command used to open a terminal.
+    /// The multiplexer's attach command is appended to this as arguments.
+    ///
+    /// **Default**: `xdg-terminal-exec`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { terminal = "foot" }
+    /// ```
+    #[serde(default = "default_terminal")]
+    terminal: String,
+
+    /// Time in seconds between checking for sessions.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// Whether to show the widget while no sessions are running.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    show_when_empty: bool,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon() -> String {
+    String::from("")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+fn default_format() -> String {
+    String::from("{count}")
+}
+
+fn default_terminal() -> String {
+    String::from("xdg-terminal-exec")
+}
+
+const fn default_interval() -> u64 {
+    5
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Zellij,
+}
+
+impl Multiplexer {
+    /// Gets the command to attach to the named session.
+    fn attach_args(self, name: &str) -> Vec<&str> {
+        match self {
+            Self::Tmux => vec!["tmux", "attach-session", "-t", name],
+            Self::Zellij => vec!["zellij", "attach", name],
+        }
+    }
+}
+
+impl Display for Multiplexer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Tmux => "tmux",
+                Self::Zellij => "zellij",
+            }
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Session {
+    pub multiplexer: Multiplexer,
+    pub name: String,
+    /// Whether any client is attached to the session.
+    /// Zellij does not report this, so it is always `false`.
+    pub attached: bool,
+}
+
+impl Module<Button> for SessionsModule {
+    type SendMessage = Vec<Session>;
+    type ReceiveMessage = Session;
+
+    module_impl!("sessions");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let interval = Duration::from_secs(self.interval);
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            let mut previous = None;
+
+            loop {
+                let mut sessions = list_tmux_sessions().await;
+                sessions.extend(list_zellij_sessions().await);
+
+                if previous.as_ref() != Some(&sessions) {
+                    debug!("Sessions: {sessions:?}");
+                    previous = Some(sessions.clone());
+                    send_async!(tx, ModuleUpdateEvent::Update(sessions));
+                }
+
+                sleep(interval).await;
+            }
+        });
+
+        let terminal = self.terminal.clone();
+        spawn(async move {
+            while let Some(session) = rx.recv().await {
+                let mut args = terminal.split_whitespace();
+                let Some(program) = args.next() else {
+                    error!("No terminal configured to attach to '{}'", session.name);
+                    continue;
+                };
+
+                let res = Command::new(program)
+                    .args(args)
+                    .args(session.multiplexer.attach_args(&session.name))
+                    .stdout(Stdio::null())
+                    .stderr(Stdio::null())
+                    .spawn();
+
+                if let Err(err) = res {
+                    error!(
+                        "{:?}",
+                        Report::new(err).wrap_err(format!("Failed to run terminal '{terminal}'"))
+                    );
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let container = gtk::Box::new(Orientation::Horizontal, 5);
+
+        let icon = new_icon_label(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
+        container.add(&icon);
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        container.add(&label);
+
+        button.add(&container);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let format = self.format.clone();
+            let show_when_empty = self.show_when_empty;
+
+            glib_recv!(context.subscribe(), sessions => {
+                let attached = sessions.iter().filter(|session| session.attached).count();
+
+                label.set_markup(
+                    &format
+                        .replace("{count}", &sessions.len().to_string())
+                        .replace("{attached}", &attached.to_string()),
+                );
+
+                button.set_visible(show_when_empty || !sessions.is_empty());
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, sessions => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                if sessions.is_empty() {
+                    let label = Label::new(Some("No sessions running"));
+                    label.add_class("empty");
+                    container.add(&label);
+                }
+
+                for session in sessions {
+                    container.add(&session_row(session, &tx));
+                }
+
+                container.show_all();
+            });
+        }
+
+        Some(container)
+    }
+}
+
+/// Creates a popup button for a session,
+/// which opens a terminal attached to it when clicked.
+fn session_row(session: Session, tx: &mpsc::Sender<Session>) -> Button {
+    let button = Button::new();
+    button.add_class("session");
+
+    if session.attached {
+        button.add_class("attached");
+    }
+
+    let row = gtk::Box::new(Orientation::Horizontal, 10);
+
+    let name = Label::new(Some(&session.name));
+    name.add_class("name");
+    name.set_halign(gtk::Align::Start);
+    name.set_hexpand(true);
+    row.add(&name);
+
+    let multiplexer = Label::new(Some(&session.multiplexer.to_string()));
+    multiplexer.add_class("multiplexer");
+    row.add(&multiplexer);
+
+    button.add(&row);
+
+    let tx = tx.clone();
+    button.connect_clicked(move |_| {
+        try_send!(tx, session.clone());
+    });
+
+    button
+}
+
+/// Runs a multiplexer command and gets its output,
+/// or `None` if the multiplexer is not installed or has no server running.
+async fn run_command(program: &str, args: &[&str]) -> Option<String> {
+    match Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .await
+    {
+        Ok(output) if output.status.success() => {
+            Some(String::from_utf8_lossy(&output.stdout).into_owned())
+        }
+        // tmux exits with an error when no server is running
+        Ok(_) => None,
+        Err(err) if err.kind() == ErrorKind::NotFound => None,
+        Err(err) => {
+            error!("Failed to run {program}: {err:?}");
+            None
+        }
+    }
+}
+
+async fn list_tmux_sessions() -> Vec<Session> {
+    run_command(
+        "tmux",
+        &[
+            "list-sessions",
+            "-F",
+            "#{session_name}\t#{session_attached}",
+        ],
+    )
+    .await
+    .map(|output| parse_tmux_sessions(&output))
+    .unwrap_or_default()
+}
+
+async fn list_zellij_sessions() -> Vec<Session> {
+    run_command("zellij", &["list-sessions", "--no-formatting"])
+        .await
+        .map(|output| parse_zellij_sessions(&output))
+        .unwrap_or_default()
+}
+
+/// Parses the output of `tmux list-sessions`,
+/// formatted as the session name and number of attached clients.
+fn parse_tmux_sessions(output: &str) -> Vec<Session> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (name, attached) = line.rsplit_once('\t')?;
+
+            Some(Session {
+                multiplexer: Multiplexer::Tmux,
+                name: name.to_string(),
+                attached: attached.parse::<u32>().is_ok_and(|clients| clients > 0),
+            })
+        })
+        .collect()
+}
+
+/// Parses the output of `zellij list-sessions`,
+/// which lists each session's name followed by details in brackets.
+///
+/// Exited sessions are skipped, as they cannot be attached to without resurrecting them.
+fn parse_zellij_sessions(output: &str) -> Vec<Session> {
+    output
+        .lines()
+        .filter(|line| !line.contains("(EXITED"))
+        .filter_map(|line| line.split_whitespace().next())
+        .map(|name| Session {
+            multiplexer: Multiplexer::Zellij,
+            name: name.to_string(),
+            attached: false,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_tmux_sessions() {
+        let sessions = parse_tmux_sessions("main\t1\nwork stuff\t0\n");
+
+        assert_eq!(
+            sessions,
+            [
+                Session {
+                    multiplexer: Multiplexer::Tmux,
+                    name: "main".to_string(),
+                    attached: true,
+                },
+                Session {
+                    multiplexer: Multiplexer::Tmux,
+                    name: "work stuff".to_string(),
+                    attached: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_zellij_sessions() {
+        let output = "dev [Created 2h 3m ago] (current)\n\
+            old [Created 1day ago] (EXITED - attach to resurrect)\n";
+
+        let sessions = parse_zellij_sessions(output);
+
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].name, "dev");
+    }
+}
@@ -1,17 +1,29 @@
 use crate::config::{CommonConfig, ModuleOrientation};
 use crate::gtk_helpers::IronbarGtkExt;
-use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
-use crate::{glib_recv, module_impl, send_async, spawn};
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::power::BatteryScale;
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
 use color_eyre::Result;
+use gtk::pango::EllipsizeMode;
 use gtk::prelude::*;
-use gtk::Label;
+use gtk::{Button, Label, Orientation};
 use regex::{Captures, Regex};
 use serde::Deserialize;
 use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
-use sysinfo::{ComponentExt, CpuExt, DiskExt, NetworkExt, RefreshKind, System, SystemExt};
-use tokio::sync::mpsc;
-use tokio::time::sleep;
+use sysinfo::{
+    ComponentExt, CpuExt, DiskExt, NetworkExt, Pid, PidExt, ProcessExt, ProcessRefreshKind,
+    RefreshKind, Signal, System, SystemExt,
+};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, warn};
 
 #[derive(Debug, Deserialize, Clone)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -31,6 +43,55 @@ pub struct SysInfoModule {
     #[serde(default = "Interval::default")]
     interval: Interval,
 
+    /// The factor to multiply all refresh intervals by
+    /// while the system is running on battery power.
+    ///
+    /// Requires the `upower` feature.
+    /// Leave unset to use the same intervals regardless of power source.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { battery_interval_multiplier = 3 }
+    /// ```
+    battery_interval_multiplier: Option<f64>,
+
+    /// How used and free memory are calculated.
+    ///
+    /// `available` counts all memory the kernel reports as available as free,
+    /// including caches it is able to reclaim.
+    /// `free` matches the classic `used` and `free` columns of `free -m`,
+    /// where caches count as neither used nor free.
+    ///
+    /// See [memory](#memory).
+    ///
+    /// **Valid options**: `available`, `free`
+    /// <br>
+    /// **Default**: `available`
+    #[serde(default)]
+    memory_mode: MemoryMode,
+
+    /// The cgroup to report memory usage for,
+    /// in addition to the system as a whole.
+    ///
+    /// This is either a path relative to `/sys/fs/cgroup`,
+    /// a systemd slice name such as `user-1000.slice`,
+    /// or `self` for the cgroup Ironbar is running in.
+    ///
+    /// Requires cgroups v2.
+    /// Leave unset to disable the `cgroup_memory_*` tokens.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { memory_cgroup = "user-1000.slice" }
+    /// ```
+    memory_cgroup: Option<String>,
+
     /// The orientation of text for the labels.
     ///
     /// **Valid options**: `horizontal`, `vertical, `h`, `v`
@@ -46,6 +107,15 @@ pub struct SysInfoModule {
     /// **Default** : `horizontal`
     direction: Option<ModuleOrientation>,
 
+    /// Shows a popup listing the processes using the most resources
+    /// when the module is clicked.
+    /// See [processes](#processes).
+    ///
+    /// Leave unset to disable the popup.
+    ///
+    /// **Default**: `null`
+    processes: Option<ProcessesConfig>,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
@@ -66,7 +136,7 @@ pub struct Intervals {
     #[serde(default = "default_interval")]
     cpu: u64,
 
-    /// The number of seconds between refreshing temperature data.
+    /// The number of seconds between refreshing temperature, fan and throttling data.
     ///
     /// **Default**: `5`
     #[serde(default = "default_interval")]
@@ -91,6 +161,64 @@ pub struct Intervals {
     system: u64,
 }
 
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum MemoryMode {
+    #[default]
+    Available,
+    Free,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ProcessesConfig {
+    /// The number of processes to list.
+    ///
+    /// **Default**: `10`
+    #[serde(default = "default_process_count")]
+    count: usize,
+
+    /// The resource to sort processes by.
+    ///
+    /// **Valid options**: `cpu`, `memory`
+    /// <br>
+    /// **Default**: `cpu`
+    #[serde(default)]
+    sort: ProcessSort,
+
+    /// Whether to show a button next to each process
+    /// which terminates it by sending `SIGTERM`.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    kill_buttons: bool,
+
+    /// The number of seconds between refreshing the list
+    /// while the popup is open.
+    ///
+    /// **Default**: `2`
+    #[serde(default = "default_process_interval")]
+    interval: u64,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ProcessSort {
+    #[default]
+    Cpu,
+    Memory,
+}
+
+const fn default_process_count() -> usize {
+    10
+}
+
+const fn default_process_interval() -> u64 {
+    2
+}
+
 #[derive(Debug, Deserialize, Copy, Clone)]
 #[serde(untagged)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
@@ -161,11 +289,37 @@ enum RefreshType {
     Disks,
     Network,
     System,
+    Processes,
+    /// Terminates a process.
+    /// This is handled alongside refreshes as it requires access to the system.
+    Kill(Pid),
+}
+
+#[derive(Debug, Clone)]
+pub enum SysInfoUpdate {
+    Tokens(HashMap<String, String>),
+    Processes(Vec<ProcessInfo>),
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessInfo {
+    pid: Pid,
+    name: String,
+    /// Percentage of total CPU time across all cores.
+    cpu_usage: f32,
+    /// Bytes
+    memory: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum SysInfoCommand {
+    PopupVisible(bool),
+    Kill(Pid),
 }
 
 impl Module<gtk::Box> for SysInfoModule {
-    type SendMessage = HashMap<String, String>;
-    type ReceiveMessage = ();
+    type SendMessage = SysInfoUpdate;
+    type ReceiveMessage = SysInfoCommand;
 
     module_impl!("sysinfo");
 
@@ -173,9 +327,13 @@ impl Module<gtk::Box> for SysInfoModule {
         &self,
         _info: &ModuleInfo,
         context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
-        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
     ) -> Result<()> {
         let interval = self.interval;
+        let processes = self.processes.clone();
+        let memory_mode = self.memory_mode;
+        let memory_cgroup = self.memory_cgroup.as_deref().and_then(cgroup_path);
+        let scale = BatteryScale::new(self.battery_interval_multiplier, context);
 
         let refresh_kind = RefreshKind::everything()
             .without_processes()
@@ -191,10 +349,11 @@ impl Module<gtk::Box> for SysInfoModule {
         macro_rules! spawn_refresh {
             ($refresh_type:expr, $func:ident) => {{
                 let tx = refresh_tx.clone();
+                let scale = scale.clone();
                 spawn(async move {
                     loop {
                         send_async!(tx, $refresh_type);
-                        sleep(Duration::from_secs(interval.$func())).await;
+                        sleep(scale.apply(Duration::from_secs(interval.$func()))).await;
                     }
                 });
             }};
@@ -207,23 +366,97 @@ impl Module<gtk::Box> for SysInfoModule {
         spawn_refresh!(RefreshType::Network, networks);
         spawn_refresh!(RefreshType::System, system);
 
+        // processes are only refreshed while the popup is open
+        if let Some(processes) = &processes {
+            let popup_open = Arc::new(AtomicBool::new(false));
+
+            {
+                let tx = refresh_tx.clone();
+                let popup_open = popup_open.clone();
+                let interval = Duration::from_secs(processes.interval);
+
+                spawn(async move {
+                    loop {
+                        sleep(interval).await;
+
+                        if popup_open.load(Ordering::Relaxed) {
+                            send_async!(tx, RefreshType::Processes);
+                        }
+                    }
+                });
+            }
+
+            let tx = refresh_tx.clone();
+            spawn(async move {
+                while let Some(cmd) = rx.recv().await {
+                    match cmd {
+                        SysInfoCommand::PopupVisible(visible) => {
+                            popup_open.store(visible, Ordering::Relaxed);
+
+                            if visible {
+                                send_async!(tx, RefreshType::Processes);
+                            }
+                        }
+                        SysInfoCommand::Kill(pid) => send_async!(tx, RefreshType::Kill(pid)),
+                    }
+                }
+            });
+        }
+
         let tx = context.tx.clone();
         spawn(async move {
             let mut format_info = HashMap::new();
 
+            // the network interval can change with the power source,
+            // so track the actual time between refreshes to calculate rates.
+            let mut last_network_refresh = Instant::now();
+
+            let mut last_throttle_count = None;
+
             while let Some(refresh) = refresh_rx.recv().await {
                 match refresh {
-                    RefreshType::Memory => refresh_memory_tokens(&mut format_info, &mut sys),
+                    RefreshType::Memory => {
+                        refresh_memory_tokens(&mut format_info, &mut sys, memory_mode);
+
+                        if let Some(cgroup) = &memory_cgroup {
+                            refresh_cgroup_tokens(&mut format_info, cgroup, sys.total_memory());
+                        }
+                    }
                     RefreshType::Cpu => refresh_cpu_tokens(&mut format_info, &mut sys),
-                    RefreshType::Temps => refresh_temp_tokens(&mut format_info, &mut sys),
+                    RefreshType::Temps => {
+                        refresh_temp_tokens(&mut format_info, &mut sys);
+                        refresh_thermal_tokens(&mut format_info, &mut last_throttle_count);
+                    }
                     RefreshType::Disks => refresh_disk_tokens(&mut format_info, &mut sys),
                     RefreshType::Network => {
-                        refresh_network_tokens(&mut format_info, &mut sys, interval.networks());
+                        let elapsed = last_network_refresh.elapsed();
+                        last_network_refresh = Instant::now();
+
+                        refresh_network_tokens(&mut format_info, &mut sys, elapsed);
                     }
                     RefreshType::System => refresh_system_tokens(&mut format_info, &sys),
+                    RefreshType::Processes => {
+                        if let Some(processes) = &processes {
+                            let processes = top_processes(&mut sys, processes);
+
+                            send_async!(
+                                tx,
+                                ModuleUpdateEvent::Update(SysInfoUpdate::Processes(processes))
+                            );
+                        }
+
+                        continue;
+                    }
+                    RefreshType::Kill(pid) => {
+                        kill_process(&sys, pid);
+                        continue;
+                    }
                 };
 
-                send_async!(tx, ModuleUpdateEvent::Update(format_info.clone()));
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(SysInfoUpdate::Tokens(format_info.clone()))
+                );
             }
         });
 
@@ -233,7 +466,7 @@ impl Module<gtk::Box> for SysInfoModule {
     fn into_widget(
         self,
         context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
-        _info: &ModuleInfo,
+        info: &ModuleInfo,
     ) -> Result<ModuleParts<gtk::Box>> {
         let re = Regex::new(r"\{([^}]+)}")?;
 
@@ -244,6 +477,25 @@ impl Module<gtk::Box> for SysInfoModule {
 
         let container = gtk::Box::new(layout.into(), 10);
 
+        // labels are wrapped in a button to open the popup, if enabled
+        let (button, label_container) = if self.processes.is_some() {
+            let button = Button::new();
+            button.add_class("button");
+
+            let label_container = gtk::Box::new(layout.into(), 10);
+            button.add(&label_container);
+            container.add(&button);
+
+            let tx = context.tx.clone();
+            button.connect_clicked(move |button| {
+                try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+            });
+
+            (Some(button), label_container)
+        } else {
+            (None, container.clone())
+        };
+
         let mut labels = Vec::new();
 
         for format in &self.format {
@@ -252,13 +504,25 @@ impl Module<gtk::Box> for SysInfoModule {
             label.add_class("item");
             label.set_angle(self.orientation.to_angle());
 
-            container.add(&label);
+            label_container.add(&label);
             labels.push(label);
         }
 
         {
-            let formats = self.format;
-            glib_recv!(context.subscribe(), info => {
+            let formats = self.format.clone();
+            let container = container.clone();
+
+            glib_recv!(context.subscribe(), update => {
+                let SysInfoUpdate::Tokens(info) = update else {
+                    continue;
+                };
+
+                if info.get("throttling").is_some_and(|throttling| throttling == "true") {
+                    container.add_class("throttling");
+                } else {
+                    container.style_context().remove_class("throttling");
+                }
+
                 for (format, label) in formats.iter().zip(labels.clone()) {
                     let format_compiled = re.replace_all(format, |caps: &Captures| {
                         info.get(&caps[1])
@@ -271,25 +535,192 @@ impl Module<gtk::Box> for SysInfoModule {
             });
         }
 
-        Ok(ModuleParts {
-            widget: container,
-            popup: None,
+        let popup = button.and_then(|button| {
+            let rx = context.subscribe();
+            self.into_popup(context.controller_tx.clone(), rx, context, info)
+                .into_popup_parts(vec![&button])
+        });
+
+        Ok(ModuleParts::new(container, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let config = self.processes?;
+
+        let container = gtk::Box::new(Orientation::Vertical, 5);
+        container.add_class("processes");
+
+        {
+            let tx = tx.clone();
+            container.connect_map(move |_| {
+                try_send!(tx, SysInfoCommand::PopupVisible(true));
+            });
+        }
+
+        {
+            let tx = tx.clone();
+            container.connect_unmap(move |_| {
+                try_send!(tx, SysInfoCommand::PopupVisible(false));
+            });
+        }
+
+        let header = process_row("Name", "CPU", "Memory");
+        header.add_class("header");
+        container.add(&header);
+
+        let list = gtk::Box::new(Orientation::Vertical, 5);
+        list.add_class("list");
+        container.add(&list);
+
+        glib_recv!(rx, update => {
+            let SysInfoUpdate::Processes(processes) = update else {
+                continue;
+            };
+
+            for child in list.children() {
+                list.remove(&child);
+            }
+
+            for process in processes {
+                let row = process_row(
+                    &process.name,
+                    &format!("{:.1}%", process.cpu_usage),
+                    &format!("{} MB", bytes_to_megabytes(process.memory)),
+                );
+                row.add_class("process");
+
+                if config.kill_buttons {
+                    let button = Button::with_label("✕");
+                    button.add_class("kill");
+                    button.set_tooltip_text(Some("Terminate process"));
+
+                    let tx = tx.clone();
+                    let pid = process.pid;
+                    button.connect_clicked(move |_| {
+                        try_send!(tx, SysInfoCommand::Kill(pid));
+                    });
+
+                    row.add(&button);
+                }
+
+                list.add(&row);
+            }
+
+            list.show_all();
+        });
+
+        container.show_all();
+
+        Some(container)
+    }
+}
+
+/// Creates a row in the processes popup,
+/// with a label for each column.
+fn process_row(name: &str, cpu: &str, memory: &str) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Horizontal, 10);
+
+    let name = Label::builder()
+        .label(name)
+        .hexpand(true)
+        .xalign(0.0)
+        .ellipsize(EllipsizeMode::End)
+        .width_chars(24)
+        .max_width_chars(24)
+        .build();
+    name.add_class("name");
+    row.add(&name);
+
+    let cpu = Label::builder()
+        .label(cpu)
+        .xalign(1.0)
+        .width_chars(7)
+        .build();
+    cpu.add_class("cpu");
+    row.add(&cpu);
+
+    let memory = Label::builder()
+        .label(memory)
+        .xalign(1.0)
+        .width_chars(9)
+        .build();
+    memory.add_class("memory");
+    row.add(&memory);
+
+    row
+}
+
+/// Refreshes the process list,
+/// returning the processes using the most of the configured resource.
+fn top_processes(sys: &mut System, config: &ProcessesConfig) -> Vec<ProcessInfo> {
+    sys.refresh_processes_specifics(ProcessRefreshKind::new().with_cpu());
+
+    let num_cpus = sys.cpus().len().max(1) as f32;
+
+    let mut processes = sys
+        .processes()
+        .iter()
+        .map(|(&pid, process)| ProcessInfo {
+            pid,
+            name: process.name().to_string(),
+            cpu_usage: process.cpu_usage() / num_cpus,
+            memory: process.memory(),
         })
+        .collect::<Vec<_>>();
+
+    match config.sort {
+        ProcessSort::Cpu => processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage)),
+        ProcessSort::Memory => processes.sort_by(|a, b| b.memory.cmp(&a.memory)),
     }
+
+    processes.truncate(config.count);
+    processes
 }
 
-fn refresh_memory_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
+fn kill_process(sys: &System, pid: Pid) {
+    match sys
+        .process(pid)
+        .and_then(|process| process.kill_with(Signal::Term))
+    {
+        Some(true) => debug!("Sent SIGTERM to process {}", pid.as_u32()),
+        _ => warn!("Failed to terminate process {}", pid.as_u32()),
+    }
+}
+
+fn refresh_memory_tokens(
+    format_info: &mut HashMap<String, String>,
+    sys: &mut System,
+    mode: MemoryMode,
+) {
     sys.refresh_memory();
 
-    let total_memory = sys.total_memory();
-    let available_memory = sys.available_memory();
+    let mem_info = fs::read_to_string("/proc/meminfo")
+        .map(|contents| parse_meminfo(&contents))
+        .unwrap_or_else(|_| MemInfo {
+            total: sys.total_memory(),
+            free: sys.free_memory(),
+            available: sys.available_memory(),
+            cache: 0,
+        });
+
+    let total_memory = mem_info.total;
+    let free_memory = mem_info.unused(mode);
 
-    let actual_used_memory = total_memory - available_memory;
+    let actual_used_memory = mem_info.used(mode);
     let memory_percent = actual_used_memory as f64 / total_memory as f64 * 100.0;
 
     format_info.insert(
         String::from("memory_free"),
-        (bytes_to_gigabytes(available_memory)).to_string(),
+        (bytes_to_gigabytes(free_memory)).to_string(),
     );
     format_info.insert(
         String::from("memory_used"),
@@ -303,6 +734,14 @@ fn refresh_memory_tokens(format_info: &mut HashMap<String, String>, sys: &mut Sy
         String::from("memory_percent"),
         format!("{memory_percent:0>2.0}"),
     );
+    format_info.insert(
+        String::from("memory_available"),
+        (bytes_to_gigabytes(mem_info.available)).to_string(),
+    );
+    format_info.insert(
+        String::from("memory_cache"),
+        (bytes_to_gigabytes(mem_info.cache)).to_string(),
+    );
 
     let used_swap = sys.used_swap();
     let total_swap = sys.total_swap();
@@ -325,6 +764,163 @@ fn refresh_memory_tokens(format_info: &mut HashMap<String, String>, sys: &mut Sy
     );
 }
 
+/// System memory figures from `/proc/meminfo`, in bytes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct MemInfo {
+    total: u64,
+    /// Memory which is not in use at all.
+    free: u64,
+    /// The kernel's estimate of memory available for new allocations,
+    /// including reclaimable caches.
+    available: u64,
+    /// Buffers, page cache and reclaimable slab memory,
+    /// which makes up the `buff/cache` column of `free`.
+    cache: u64,
+}
+
+impl MemInfo {
+    fn used(self, mode: MemoryMode) -> u64 {
+        match mode {
+            MemoryMode::Available => self.total.saturating_sub(self.available),
+            MemoryMode::Free => self.total.saturating_sub(self.free + self.cache),
+        }
+    }
+
+    const fn unused(self, mode: MemoryMode) -> u64 {
+        match mode {
+            MemoryMode::Available => self.available,
+            MemoryMode::Free => self.free,
+        }
+    }
+}
+
+fn parse_meminfo(contents: &str) -> MemInfo {
+    let mut mem_info = MemInfo::default();
+
+    for line in contents.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+
+        // values are in kibibytes, despite the `kB` suffix
+        let Some(bytes) = value
+            .split_whitespace()
+            .next()
+            .and_then(|value| value.parse::<u64>().ok())
+            .map(|value| value * 1024)
+        else {
+            continue;
+        };
+
+        match key {
+            "MemTotal" => mem_info.total = bytes,
+            "MemFree" => mem_info.free = bytes,
+            "MemAvailable" => mem_info.available = bytes,
+            "Buffers" | "Cached" | "SReclaimable" => mem_info.cache += bytes,
+            _ => {}
+        }
+    }
+
+    mem_info
+}
+
+/// Gets the directory of a cgroup under `/sys/fs/cgroup`.
+/// See the `memory_cgroup` option for accepted names.
+///
+/// Returns `None` if `self` is used
+/// but the current cgroup cannot be determined.
+fn cgroup_path(name: &str) -> Option<PathBuf> {
+    const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+    let relative = if name == "self" {
+        // cgroups v2 has a single hierarchy, listed as `0::/path`
+        fs::read_to_string("/proc/self/cgroup")
+            .ok()?
+            .lines()
+            .find_map(|line| line.strip_prefix("0::"))
+            .map(ToString::to_string)?
+    } else if !name.contains('/') && name.ends_with(".slice") {
+        slice_path(name)
+    } else {
+        name.to_string()
+    };
+
+    Some(Path::new(CGROUP_ROOT).join(relative.trim_start_matches('/')))
+}
+
+/// Expands a systemd slice name into its path in the cgroup tree.
+/// Slices are nested according to the dashes in their name,
+/// so `user-1000.slice` is within `user.slice`.
+fn slice_path(name: &str) -> String {
+    let Some(stem) = name.strip_suffix(".slice") else {
+        return name.to_string();
+    };
+
+    // the root slice
+    if stem == "-" {
+        return String::new();
+    }
+
+    let mut path = vec![];
+    let mut prefix = String::new();
+
+    for part in stem.split('-') {
+        if !prefix.is_empty() {
+            prefix.push('-');
+        }
+        prefix.push_str(part);
+
+        path.push(format!("{prefix}.slice"));
+    }
+
+    path.join("/")
+}
+
+/// Reads the memory usage of a cgroup.
+///
+/// Inactive file cache is excluded from the usage,
+/// as it is the first to be reclaimed under pressure.
+/// The limit falls back to the total system memory if the cgroup is unlimited.
+fn refresh_cgroup_tokens(format_info: &mut HashMap<String, String>, cgroup: &Path, total: u64) {
+    let Some(current) =
+        read_sysfs(&cgroup.join("memory.current")).and_then(|current| current.parse::<u64>().ok())
+    else {
+        warn!(
+            "Failed to read memory usage for cgroup at '{}'",
+            cgroup.display()
+        );
+        return;
+    };
+
+    let inactive_file = read_sysfs(&cgroup.join("memory.stat"))
+        .and_then(|stat| {
+            stat.lines().find_map(|line| {
+                line.strip_prefix("inactive_file ")
+                    .and_then(|value| value.parse::<u64>().ok())
+            })
+        })
+        .unwrap_or_default();
+
+    let limit = read_sysfs(&cgroup.join("memory.max"))
+        .and_then(|max| max.parse::<u64>().ok())
+        .unwrap_or(total);
+
+    let used = current.saturating_sub(inactive_file);
+
+    format_info.insert(
+        String::from("cgroup_memory_used"),
+        bytes_to_megabytes(used).to_string(),
+    );
+    format_info.insert(
+        String::from("cgroup_memory_limit"),
+        bytes_to_megabytes(limit).to_string(),
+    );
+    format_info.insert(
+        String::from("cgroup_memory_percent"),
+        format!("{:0>2.0}", used as f64 / limit as f64 * 100.0),
+    );
+}
+
 fn refresh_cpu_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
     sys.refresh_cpu();
 
@@ -347,6 +943,115 @@ fn refresh_temp_tokens(format_info: &mut HashMap<String, String>, sys: &mut Syst
     }
 }
 
+/// Reads fan speeds from hwmon,
+/// and CPU throttling counters from `thermal_throttle`.
+///
+/// The `throttling` token is set if the CPU has throttled
+/// since `last_throttle_count` was read.
+fn refresh_thermal_tokens(
+    format_info: &mut HashMap<String, String>,
+    last_throttle_count: &mut Option<u64>,
+) {
+    for (key, rpm) in read_fan_speeds() {
+        format_info.insert(format!("fan:{key}"), rpm.to_string());
+    }
+
+    if let Some(count) = read_throttle_count() {
+        let throttling = last_throttle_count.is_some_and(|last| count > last);
+        *last_throttle_count = Some(count);
+
+        format_info.insert(String::from("throttle_count"), count.to_string());
+        format_info.insert(String::from("throttling"), throttling.to_string());
+    }
+}
+
+/// Gets the speed of each fan in RPM,
+/// keyed by the hwmon device name and fan label, ie `thinkpad-fan1`.
+fn read_fan_speeds() -> Vec<(String, u64)> {
+    let Ok(devices) = fs::read_dir("/sys/class/hwmon") else {
+        return vec![];
+    };
+
+    let mut fans = vec![];
+
+    for device in devices.filter_map(Result::ok) {
+        let path = device.path();
+        let name = read_sysfs(&path.join("name")).unwrap_or_default();
+
+        let Ok(files) = fs::read_dir(&path) else {
+            continue;
+        };
+
+        for file in files.filter_map(Result::ok) {
+            let file_name = file.file_name();
+            let Some(index) = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_prefix("fan"))
+                .and_then(|file_name| file_name.strip_suffix("_input"))
+            else {
+                continue;
+            };
+
+            let Some(rpm) = read_sysfs(&file.path()).and_then(|rpm| rpm.parse().ok()) else {
+                continue;
+            };
+
+            let label = read_sysfs(&path.join(format!("fan{index}_label")))
+                .unwrap_or_else(|| format!("fan{index}"));
+
+            fans.push((format!("{name} {label}").replace(' ', "-"), rpm));
+        }
+    }
+
+    fans
+}
+
+/// Gets the total number of times the CPU has been thermally throttled since boot.
+/// Each core and package is only counted once, regardless of the number of threads.
+///
+/// Returns `None` if the CPU does not report throttling (ie non-Intel CPUs).
+fn read_throttle_count() -> Option<u64> {
+    let cpus = fs::read_dir("/sys/devices/system/cpu").ok()?;
+
+    let mut cores = HashMap::new();
+    let mut packages = HashMap::new();
+
+    for cpu in cpus.filter_map(Result::ok) {
+        let path = cpu.path();
+        let throttle = path.join("thermal_throttle");
+
+        let Some(core_count) = read_sysfs(&throttle.join("core_throttle_count"))
+            .and_then(|count| count.parse::<u64>().ok())
+        else {
+            continue;
+        };
+
+        let package = read_sysfs(&path.join("topology/physical_package_id")).unwrap_or_default();
+        let core = read_sysfs(&path.join("topology/core_id")).unwrap_or_default();
+
+        if let Some(package_count) = read_sysfs(&throttle.join("package_throttle_count"))
+            .and_then(|count| count.parse::<u64>().ok())
+        {
+            packages.insert(package.clone(), package_count);
+        }
+
+        cores.insert((package, core), core_count);
+    }
+
+    if cores.is_empty() {
+        None
+    } else {
+        Some(cores.values().sum::<u64>() + packages.values().sum::<u64>())
+    }
+}
+
+/// Reads a sysfs file, trimming the trailing newline.
+fn read_sysfs(path: &Path) -> Option<String> {
+    fs::read_to_string(path)
+        .ok()
+        .map(|value| value.trim().to_string())
+}
+
 fn refresh_disk_tokens(format_info: &mut HashMap<String, String>, sys: &mut System) {
     sys.refresh_disks();
 
@@ -388,21 +1093,27 @@ fn refresh_disk_tokens(format_info: &mut HashMap<String, String>, sys: &mut Syst
 fn refresh_network_tokens(
     format_info: &mut HashMap<String, String>,
     sys: &mut System,
-    interval: u64,
+    elapsed: Duration,
 ) {
     sys.refresh_networks();
 
+    // avoid dividing by zero on the first refresh
+    let seconds = elapsed.as_secs_f64().max(1.0);
+
     for (iface, network) in sys.networks() {
         format_info.insert(
             format!("net_down:{iface}"),
-            format!("{:0>2.0}", bytes_to_megabits(network.received()) / interval),
+            format!(
+                "{:0>2.0}",
+                bytes_to_megabits(network.received()) as f64 / seconds
+            ),
         );
 
         format_info.insert(
             format!("net_up:{iface}"),
             format!(
                 "{:0>2.0}",
-                bytes_to_megabits(network.transmitted()) / interval
+                bytes_to_megabits(network.transmitted()) as f64 / seconds
             ),
         );
     }
@@ -445,7 +1156,68 @@ const fn bytes_to_gigabytes(b: u64) -> u64 {
     b / BYTES_IN_GIGABYTE
 }
 
+const fn bytes_to_megabytes(b: u64) -> u64 {
+    const BYTES_IN_MEGABYTE: u64 = 1_000_000;
+    b / BYTES_IN_MEGABYTE
+}
+
 const fn bytes_to_megabits(b: u64) -> u64 {
     const BYTES_IN_MEGABIT: u64 = 125_000;
     b / BYTES_IN_MEGABIT
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MEMINFO: &str = "MemTotal:       16000000 kB
+MemFree:         2000000 kB
+MemAvailable:    9000000 kB
+Buffers:          500000 kB
+Cached:          6000000 kB
+SwapCached:        10000 kB
+SReclaimable:     700000 kB
+";
+
+    #[test]
+    fn test_parse_meminfo() {
+        let mem_info = parse_meminfo(MEMINFO);
+
+        assert_eq!(
+            mem_info,
+            MemInfo {
+                total: 16_000_000 * 1024,
+                free: 2_000_000 * 1024,
+                available: 9_000_000 * 1024,
+                cache: 7_200_000 * 1024,
+            }
+        );
+
+        assert_eq!(mem_info.used(MemoryMode::Available), 7_000_000 * 1024);
+        assert_eq!(mem_info.used(MemoryMode::Free), 6_800_000 * 1024);
+        assert_eq!(mem_info.unused(MemoryMode::Free), 2_000_000 * 1024);
+    }
+
+    #[test]
+    fn test_slice_path() {
+        assert_eq!(slice_path("user.slice"), "user.slice");
+        assert_eq!(slice_path("user-1000.slice"), "user.slice/user-1000.slice");
+        assert_eq!(
+            slice_path("app-flatpak.slice"),
+            "app.slice/app-flatpak.slice"
+        );
+        assert_eq!(slice_path("-.slice"), "");
+    }
+
+    #[test]
+    fn test_cgroup_path() {
+        assert_eq!(
+            cgroup_path("user-1000.slice"),
+            Some(PathBuf::from("/sys/fs/cgroup/user.slice/user-1000.slice"))
+        );
+        assert_eq!(
+            cgroup_path("/system.slice/docker.service"),
+            Some(PathBuf::from("/sys/fs/cgroup/system.slice/docker.service"))
+        );
+    }
+}
@@ -11,6 +11,7 @@ use std::collections::HashSet;
 use std::ffi::CStr;
 use std::os::raw::{c_char, c_int};
 use std::ptr;
+use system_tray::item::IconPixmap;
 
 /// Gets the GTK icon theme search paths by calling the FFI function.
 /// Conveniently returns the result as a `HashSet`.
@@ -76,20 +77,29 @@ fn get_image_from_icon_name(item: &TrayMenu, icon_theme: &IconTheme, size: u32)
 }
 
 /// Attempts to get an image from the item pixmap.
-///
-/// The pixmap is supplied in ARGB32 format,
-/// which has 8 bits per sample and a bit stride of `4*width`.
-/// The Pixbuf expects RGBA32 format, so some channel shuffling
-/// is required.
 fn get_image_from_pixmap(item: &TrayMenu, size: u32) -> Result<Image> {
-    const BITS_PER_SAMPLE: i32 = 8;
-
     let pixmap = item
         .icon_pixmap
         .as_ref()
         .and_then(|pixmap| pixmap.first())
         .ok_or_else(|| Report::msg("Failed to get pixmap from tray icon"))?;
 
+    let pixbuf = get_pixbuf_from_pixmap(pixmap, size);
+
+    let image = Image::new();
+    ImageProvider::create_and_load_surface(&pixbuf, &image)?;
+    Ok(image)
+}
+
+/// Converts an SNI pixmap into a `Pixbuf`, scaled to `size`.
+///
+/// The pixmap is supplied in ARGB32 format,
+/// which has 8 bits per sample and a bit stride of `4*width`.
+/// The Pixbuf expects RGBA32 format, so some channel shuffling
+/// is required.
+pub fn get_pixbuf_from_pixmap(pixmap: &IconPixmap, size: u32) -> Pixbuf {
+    const BITS_PER_SAMPLE: i32 = 8;
+
     let mut pixels = pixmap.pixels.to_vec();
 
     for i in (0..pixels.len()).step_by(4) {
@@ -113,11 +123,7 @@ fn get_image_from_pixmap(item: &TrayMenu, size: u32) -> Result<Image> {
         row_stride,
     );
 
-    let pixbuf = pixbuf
+    pixbuf
         .scale_simple(size as i32, size as i32, InterpType::Bilinear)
-        .unwrap_or(pixbuf);
-
-    let image = Image::new();
-    ImageProvider::create_and_load_surface(&pixbuf, &image)?;
-    Ok(image)
+        .unwrap_or(pixbuf)
 }
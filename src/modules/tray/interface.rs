@@ -1,11 +1,16 @@
 use super::diff::{Diff, MenuItemDiff};
+use super::icon;
+use super::TrayRequest;
+use crate::clients::tray::Tooltip;
 use crate::{spawn, try_send};
 use glib::Propagation;
 use gtk::prelude::*;
-use gtk::{CheckMenuItem, Image, Label, Menu, MenuItem, SeparatorMenuItem};
+use gtk::{pango, CheckMenuItem, IconSize, Image, Label, Menu, MenuItem, SeparatorMenuItem};
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::rc::Rc;
 use system_tray::client::ActivateRequest;
-use system_tray::item::{IconPixmap, StatusNotifierItem};
+use system_tray::item::{IconPixmap, Status, StatusNotifierItem};
 use system_tray::menu::{MenuItem as MenuItemInfo, MenuType, ToggleState, ToggleType};
 use tokio::sync::mpsc;
 
@@ -64,30 +69,34 @@ pub(crate) struct TrayMenu {
     pub icon_theme_path: Option<String>,
     pub icon_pixmap: Option<Vec<IconPixmap>>,
 
+    tooltip: Rc<RefCell<Option<Tooltip>>>,
+
     tx: mpsc::Sender<i32>,
 }
 
+/// Size in pixels to display pixmap tooltip icons as.
+const TOOLTIP_ICON_SIZE: u32 = 32;
+
 impl TrayMenu {
-    pub fn new(
-        tx: mpsc::Sender<ActivateRequest>,
-        address: String,
-        item: StatusNotifierItem,
-    ) -> Self {
+    pub fn new(tx: mpsc::Sender<TrayRequest>, address: String, item: StatusNotifierItem) -> Self {
         let widget = MenuItem::new();
         widget.style_context().add_class("item");
 
         let (item_tx, mut item_rx) = mpsc::channel(8);
 
         if let Some(menu) = item.menu {
+            let tx = tx.clone();
+            let address = address.clone();
+
             spawn(async move {
                 while let Some(id) = item_rx.recv().await {
                     try_send!(
                         tx,
-                        ActivateRequest {
+                        TrayRequest::Activate(ActivateRequest {
                             submenu_id: id,
                             menu_path: menu.clone(),
                             address: address.clone(),
-                        }
+                        })
                     );
                 }
             });
@@ -96,6 +105,41 @@ impl TrayMenu {
         let menu = Menu::new();
         widget.set_submenu(Some(&menu));
 
+        // tooltips are fetched on hover, as items do not reliably signal changes
+        widget.connect_enter_notify_event(move |_, _| {
+            try_send!(tx, TrayRequest::Tooltip(address.clone()));
+            Propagation::Proceed
+        });
+
+        let tooltip = Rc::new(RefCell::new(None::<Tooltip>));
+
+        {
+            let tooltip = tooltip.clone();
+
+            widget.set_has_tooltip(true);
+            widget.connect_query_tooltip(move |_, _, _, _, gtk_tooltip| {
+                let tooltip = tooltip.borrow();
+                let Some(tooltip) = tooltip.as_ref() else {
+                    return false;
+                };
+
+                let Some(markup) = tooltip_markup(tooltip) else {
+                    return false;
+                };
+
+                gtk_tooltip.set_markup(Some(&markup));
+
+                if !tooltip.icon_name.is_empty() {
+                    gtk_tooltip.set_icon_from_icon_name(Some(&tooltip.icon_name), IconSize::Dnd);
+                } else if let Some(pixmap) = tooltip.icon_pixmap.first() {
+                    let pixbuf = icon::get_pixbuf_from_pixmap(pixmap, TOOLTIP_ICON_SIZE);
+                    gtk_tooltip.set_icon(Some(&pixbuf));
+                }
+
+                true
+            });
+        }
+
         Self {
             widget,
             menu_widget: menu,
@@ -106,6 +150,7 @@ impl TrayMenu {
             icon_name: item.icon_name,
             icon_theme_path: item.icon_theme_path,
             icon_pixmap: item.icon_pixmap,
+            tooltip,
             menu: HashMap::new(),
             tx: item_tx,
         }
@@ -153,6 +198,30 @@ impl TrayMenu {
         image.show();
     }
 
+    /// Replaces the status class on the item,
+    /// hiding it if passive and `hide_passive` is set.
+    pub fn set_status(&self, status: Status, hide_passive: bool) {
+        let style_context = self.widget.style_context();
+
+        for class in ["active", "passive", "needs-attention"] {
+            style_context.remove_class(class);
+        }
+
+        let class = match status {
+            Status::Active => Some("active"),
+            Status::Passive => Some("passive"),
+            Status::NeedsAttention => Some("needs-attention"),
+            Status::Unknown => None,
+        };
+
+        if let Some(class) = class {
+            style_context.add_class(class);
+        }
+
+        self.widget
+            .set_visible(!(hide_passive && matches!(status, Status::Passive)));
+    }
+
     /// Applies a diff set to the submenu.
     pub fn apply_diffs(&mut self, diffs: Vec<Diff>) {
         for diff in diffs {
@@ -196,6 +265,44 @@ impl TrayMenu {
     pub fn set_icon_name(&mut self, icon_name: Option<String>) {
         self.icon_name = icon_name;
     }
+
+    /// Updates the tooltip,
+    /// refreshing it if currently shown.
+    pub fn set_tooltip(&self, tooltip: Option<Tooltip>) {
+        *self.tooltip.borrow_mut() = tooltip;
+        self.widget.trigger_tooltip_query();
+    }
+}
+
+/// Builds the Pango markup for a tooltip,
+/// with the title in bold above the description.
+///
+/// Returns `None` if there is no text to show.
+fn tooltip_markup(tooltip: &Tooltip) -> Option<String> {
+    let title = glib::markup_escape_text(tooltip.title.trim());
+    let description = description_markup(tooltip.description.trim());
+
+    match (title.is_empty(), description.is_empty()) {
+        (true, true) => None,
+        (false, true) => Some(format!("<b>{title}</b>")),
+        (true, false) => Some(description),
+        (false, false) => Some(format!("<b>{title}</b>\n{description}")),
+    }
+}
+
+/// Converts the subset of HTML allowed in tooltip descriptions to Pango markup.
+/// If the result is not valid markup, the description is shown as plain text instead.
+fn description_markup(description: &str) -> String {
+    let markup = description
+        .replace("<br>", "\n")
+        .replace("<br/>", "\n")
+        .replace("<br />", "\n");
+
+    if pango::parse_markup(&markup, '\0').is_ok() {
+        markup
+    } else {
+        glib::markup_escape_text(&markup).to_string()
+    }
 }
 
 #[derive(Debug)]
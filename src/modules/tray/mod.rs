@@ -1,16 +1,20 @@
 mod diff;
 mod icon;
 mod interface;
+#[cfg(feature = "ipc")]
+mod registry;
 
-use crate::clients::tray;
+use crate::clients::tray::{self, Tooltip};
 use crate::config::CommonConfig;
 use crate::modules::tray::diff::get_diffs;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
-use crate::{glib_recv, lock, module_impl, send_async, spawn};
+use crate::{glib_recv, lock, send_async, spawn};
 use color_eyre::{Report, Result};
 use gtk::{prelude::*, PackDirection};
 use gtk::{IconTheme, MenuBar};
 use interface::TrayMenu;
+#[cfg(feature = "ipc")]
+pub use registry::{activate, open_menu};
 use serde::Deserialize;
 use std::collections::HashMap;
 use system_tray::client::Event;
@@ -30,9 +34,8 @@ pub struct TrayModule {
 
     /// Size in pixels to display the tray icons as.
     ///
-    /// **Default**: `16`
-    #[serde(default = "default_icon_size")]
-    icon_size: u32,
+    /// **Default**: The bar's `icon_size` if set, otherwise `16`
+    icon_size: Option<u32>,
 
     /// Direction to display the tray items.
     ///
@@ -43,9 +46,39 @@ pub struct TrayModule {
     #[cfg_attr(feature = "schema", schemars(schema_with = "schema_pack_direction"))]
     direction: Option<PackDirection>,
 
+    /// Whether to hide items with a `Passive` status.
+    /// Items are shown again as soon as their status changes.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    hide_passive: bool,
+
+    /// Command to run a proxy which shows legacy XEmbed tray icons on the tray,
+    /// for older X11 apps running under XWayland.
+    ///
+    /// The proxy must re-expose the icons as `StatusNotifierItem`s.
+    /// Both [snixembed](https://git.sr.ht/~steef/snixembed)
+    /// and KDE's `xembedsniproxy` are known to work.
+    /// It is started once when the first tray module loads,
+    /// and only if an X display is available.
+    ///
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { xembed_proxy = "snixembed" }
+    /// ```
+    xembed_proxy: Option<String>,
+
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
     pub common: Option<CommonConfig>,
+
+    /// The module's `name`, kept after the common options are taken
+    /// so that the module can be found over IPC.
+    #[serde(skip)]
+    instance_name: Option<String>,
 }
 
 const fn default_icon_size() -> u32 {
@@ -68,6 +101,26 @@ where
         .transpose()
 }
 
+#[derive(Debug, Clone)]
+pub enum TrayUpdate {
+    Event(Event),
+    /// The tooltip for the item at the address was fetched.
+    Tooltip(String, Option<Tooltip>),
+    /// Opens the menu of the item at the address.
+    OpenMenu(String),
+}
+
+#[derive(Debug)]
+pub enum TrayRequest {
+    Activate(ActivateRequest),
+    /// Fetches the tooltip for the item at the address.
+    Tooltip(String),
+    /// Activates the item at the address, as if it was clicked.
+    ActivateItem(String),
+    /// Opens the menu of the item at the address.
+    OpenMenu(String),
+}
+
 #[cfg(feature = "schema")]
 fn schema_pack_direction(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
     use schemars::JsonSchema;
@@ -82,10 +135,18 @@ fn schema_pack_direction(gen: &mut schemars::gen::SchemaGenerator) -> schemars::
 }
 
 impl Module<MenuBar> for TrayModule {
-    type SendMessage = Event;
-    type ReceiveMessage = ActivateRequest;
+    type SendMessage = TrayUpdate;
+    type ReceiveMessage = TrayRequest;
+
+    fn name() -> &'static str {
+        "tray"
+    }
 
-    module_impl!("tray");
+    fn take_common(&mut self) -> CommonConfig {
+        let common = self.common.take().unwrap_or_default();
+        self.instance_name.clone_from(&common.name);
+        common
+    }
 
     fn spawn_controller(
         &self,
@@ -98,33 +159,76 @@ impl Module<MenuBar> for TrayModule {
         let client = context.try_client::<tray::Client>()?;
         let mut tray_rx = client.subscribe();
 
+        #[cfg(feature = "ipc")]
+        registry::register(
+            self.instance_name.clone(),
+            &context.controller_tx,
+            client.clone(),
+        );
+
+        if let Some(command) = &self.xembed_proxy {
+            client.start_xembed_proxy(command);
+        }
+
         let initial_items = lock!(client.items()).clone();
 
         // listen to tray updates
-        spawn(async move {
-            for (key, (item, menu)) in initial_items {
-                send_async!(
-                    tx,
-                    ModuleUpdateEvent::Update(Event::Add(key.clone(), item.into()))
-                );
+        {
+            let tx = tx.clone();
 
-                if let Some(menu) = menu.clone() {
+            spawn(async move {
+                for (key, (item, menu)) in initial_items {
                     send_async!(
                         tx,
-                        ModuleUpdateEvent::Update(Event::Update(key, UpdateEvent::Menu(menu)))
+                        ModuleUpdateEvent::Update(TrayUpdate::Event(Event::Add(
+                            key.clone(),
+                            item.into()
+                        )))
                     );
+
+                    if let Some(menu) = menu.clone() {
+                        send_async!(
+                            tx,
+                            ModuleUpdateEvent::Update(TrayUpdate::Event(Event::Update(
+                                key,
+                                UpdateEvent::Menu(menu)
+                            )))
+                        );
+                    }
                 }
-            }
 
-            while let Ok(message) = tray_rx.recv().await {
-                send_async!(tx, ModuleUpdateEvent::Update(message));
-            }
-        });
+                while let Ok(message) = tray_rx.recv().await {
+                    send_async!(tx, ModuleUpdateEvent::Update(TrayUpdate::Event(message)));
+                }
+            });
+        }
 
         // send tray commands
         spawn(async move {
             while let Some(cmd) = rx.recv().await {
-                client.activate(cmd).await?;
+                match cmd {
+                    TrayRequest::Activate(req) => client.activate(req).await?,
+                    TrayRequest::Tooltip(address) => {
+                        let tooltip = client.tooltip(&address).await.unwrap_or_else(|err| {
+                            debug!("Failed to get tooltip for '{address}': {err:?}");
+                            None
+                        });
+
+                        send_async!(
+                            tx,
+                            ModuleUpdateEvent::Update(TrayUpdate::Tooltip(address, tooltip))
+                        );
+                    }
+                    TrayRequest::ActivateItem(address) => {
+                        if let Err(err) = client.activate_item(&address).await {
+                            error!("{:?}", err.wrap_err("Failed to activate tray item"));
+                        }
+                    }
+                    // menus are only known to the widget
+                    TrayRequest::OpenMenu(address) => {
+                        send_async!(tx, ModuleUpdateEvent::Update(TrayUpdate::OpenMenu(address)));
+                    }
+                }
             }
 
             Ok::<_, Report>(())
@@ -155,11 +259,28 @@ impl Module<MenuBar> for TrayModule {
             let container = container.clone();
             let mut menus = HashMap::new();
             let icon_theme = info.icon_theme.clone();
+            let icon_size = info.icon_size(
+                self.icon_size.map(|size| size as i32),
+                default_icon_size() as i32,
+            ) as u32;
 
             // listen for UI updates
-            glib_recv!(context.subscribe(), update =>
-                on_update(update, &container, &mut menus, &icon_theme, self.icon_size, self.prefer_theme_icons, &context.controller_tx)
-            );
+            glib_recv!(context.subscribe(), update => match update {
+                TrayUpdate::Event(event) => {
+                    on_update(event, &container, &mut menus, &icon_theme, icon_size, &self, &context);
+                }
+                TrayUpdate::Tooltip(address, tooltip) => {
+                    if let Some(menu_item) = menus.get(address.as_str()) {
+                        menu_item.set_tooltip(tooltip);
+                    }
+                }
+                TrayUpdate::OpenMenu(address) => {
+                    // activating an item with a submenu pops it up and selects its first entry
+                    if let Some(menu_item) = menus.get(address.as_str()) {
+                        menu_item.widget.activate();
+                    }
+                }
+            });
         };
 
         Ok(ModuleParts {
@@ -177,13 +298,23 @@ fn on_update(
     menus: &mut HashMap<Box<str>, TrayMenu>,
     icon_theme: &IconTheme,
     icon_size: u32,
-    prefer_icons: bool,
-    tx: &mpsc::Sender<ActivateRequest>,
+    config: &TrayModule,
+    context: &WidgetContext<TrayUpdate, TrayRequest>,
 ) {
+    let prefer_icons = config.prefer_theme_icons;
+    let tx = &context.controller_tx;
+
     match update {
         Event::Add(address, item) => {
             debug!("Received new tray item at '{address}': {item:?}");
 
+            // items are re-sent when the tray host restarts
+            if let Some(menu) = menus.remove(address.as_str()) {
+                container.remove(&menu.widget);
+            }
+
+            let status = item.status;
+
             let mut menu_item = TrayMenu::new(tx.clone(), address.clone(), *item);
             container.add(&menu_item.widget);
 
@@ -194,7 +325,16 @@ fn on_update(
                 menu_item.set_label(&label);
             };
 
+            // only one popup should be open on the bar at a time
+            if context.popup.closes_others() {
+                if let Some(menu) = menu_item.widget.submenu() {
+                    let popup = context.popup.clone();
+                    menu.connect_show(move |_| popup.hide());
+                }
+            }
+
             menu_item.widget.show();
+            menu_item.set_status(status, config.hide_passive);
             menus.insert(address.into(), menu_item);
         }
         Event::Update(address, update) => {
@@ -222,8 +362,8 @@ fn on_update(
                 UpdateEvent::OverlayIcon(_icon) => {
                     warn!("received unimplemented NewOverlayIcon event");
                 }
-                UpdateEvent::Status(_status) => {
-                    warn!("received unimplemented NewStatus event");
+                UpdateEvent::Status(status) => {
+                    menu_item.set_status(status, config.hide_passive);
                 }
                 UpdateEvent::Title(title) => {
                     if let Some(label_widget) = menu_item.label_widget() {
@@ -246,7 +386,7 @@ fn on_update(
         Event::Remove(address) => {
             debug!("Removing tray item at '{address}'");
 
-            if let Some(menu) = menus.get(address.as_str()) {
+            if let Some(menu) = menus.remove(address.as_str()) {
                 container.remove(&menu.widget);
             }
         }
@@ -0,0 +1,72 @@
+//! Tracks tray modules,
+//! so their items can be activated over IPC.
+
+use super::TrayRequest;
+use crate::clients::tray;
+use crate::{lock, try_send};
+use color_eyre::{Report, Result};
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::sync::mpsc;
+
+struct Registration {
+    name: Option<String>,
+    tx: mpsc::WeakSender<TrayRequest>,
+    client: Arc<tray::Client>,
+}
+
+fn registry() -> &'static Mutex<Vec<Registration>> {
+    static REGISTRY: OnceLock<Mutex<Vec<Registration>>> = OnceLock::new();
+    REGISTRY.get_or_init(|| Mutex::new(vec![]))
+}
+
+/// Registers a module instance.
+pub fn register(name: Option<String>, tx: &mpsc::Sender<TrayRequest>, client: Arc<tray::Client>) {
+    let mut registry = lock!(registry());
+
+    // clear out modules from closed bars
+    registry.retain(|registration| registration.tx.upgrade().is_some());
+
+    registry.push(Registration {
+        name,
+        tx: tx.downgrade(),
+        client,
+    });
+}
+
+/// Activates the tray item with the given `id`, as if it was clicked.
+/// This usually opens or focuses the app's main window.
+pub fn activate(id: &str, name: Option<&str>) -> Result<()> {
+    send(id, name, TrayRequest::ActivateItem)
+}
+
+/// Opens the menu of the tray item with the given `id`.
+pub fn open_menu(id: &str, name: Option<&str>) -> Result<()> {
+    send(id, name, TrayRequest::OpenMenu)
+}
+
+/// Sends a request for the item with the given `id` to a single tray,
+/// so that the item is not activated once per bar.
+/// This is the first tray with the given `name`, or the first tray if not set.
+fn send(id: &str, name: Option<&str>, request: fn(String) -> TrayRequest) -> Result<()> {
+    let registry = lock!(registry());
+
+    let (tx, client) = registry
+        .iter()
+        .filter(|registration| name.map_or(true, |name| registration.name.as_deref() == Some(name)))
+        .find_map(|registration| {
+            registration
+                .tx
+                .upgrade()
+                .map(|tx| (tx, registration.client.clone()))
+        })
+        .ok_or_else(|| Report::msg("Module not found"))?;
+
+    let address = lock!(client.items())
+        .iter()
+        .find(|(_, (item, _))| item.id == id)
+        .map(|(address, _)| address.clone())
+        .ok_or_else(|| Report::msg("Item not found"))?;
+
+    try_send!(tx, request(address));
+    Ok(())
+}
@@ -3,11 +3,15 @@ use futures_lite::stream::StreamExt;
 use gtk::{prelude::*, Button};
 use gtk::{Label, Orientation};
 use serde::Deserialize;
+use std::collections::HashMap;
 use tokio::sync::{broadcast, mpsc};
 use upower_dbus::BatteryState;
 use zbus;
 use zbus::fdo::PropertiesProxy;
 
+use crate::clients::upower::{
+    u32_to_battery_state, BatteryHooks, HookRunner, PeripheralHooks, PeripheralWatcher,
+};
 use crate::config::CommonConfig;
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::ImageProvider;
@@ -15,7 +19,9 @@ use crate::modules::PopupButton;
 use crate::modules::{
     Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, WidgetContext,
 };
+use crate::script::ScriptInput;
 use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use tracing::error;
 
 const DAY: i64 = 24 * 60 * 60;
 const HOUR: i64 = 60 * 60;
@@ -33,9 +39,66 @@ pub struct UpowerModule {
 
     /// The size to render the icon at, in pixels.
     ///
-    /// **Default**: `24`
-    #[serde(default = "default_icon_size")]
-    icon_size: i32,
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// The battery percentage at or below which `on_low` runs.
+    ///
+    /// **Default**: `20`
+    #[serde(default = "default_low_threshold")]
+    low_threshold: f64,
+
+    /// The battery percentage at or below which `on_critical` runs.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_critical_threshold")]
+    critical_threshold: f64,
+
+    /// A [script](scripts) to run when the battery drops to `low_threshold` while discharging.
+    /// The battery percentage is passed as an argument, accessible using `$0`.
+    ///
+    /// **Supported script types**: `oneshot`.
+    /// <br>
+    /// **Default**: `null`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { on_low = "notify-send 'Battery low' \"$0% remaining\"" }
+    /// ```
+    on_low: Option<ScriptInput>,
+
+    /// A [script](scripts) to run when the battery drops to `critical_threshold` while discharging.
+    /// The battery percentage is passed as an argument, accessible using `$0`.
+    ///
+    /// **Supported script types**: `oneshot`.
+    /// <br>
+    /// **Default**: `null`
+    on_critical: Option<ScriptInput>,
+
+    /// A [script](scripts) to run when the battery starts charging.
+    /// The battery percentage is passed as an argument, accessible using `$0`.
+    ///
+    /// **Supported script types**: `oneshot`.
+    /// <br>
+    /// **Default**: `null`
+    on_charging: Option<ScriptInput>,
+
+    /// A [script](scripts) to run when the battery becomes fully charged.
+    /// The battery percentage is passed as an argument, accessible using `$0`.
+    ///
+    /// **Supported script types**: `oneshot`.
+    /// <br>
+    /// **Default**: `null`
+    on_full: Option<ScriptInput>,
+
+    /// Low battery alerts for peripherals, such as wireless mice and keyboards.
+    /// Alerts are disabled if not set.
+    ///
+    /// See [peripheral alerts](#peripheral-alerts).
+    ///
+    /// **Default**: `null`
+    peripheral_alerts: Option<PeripheralAlerts>,
 
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
@@ -50,6 +113,64 @@ const fn default_icon_size() -> i32 {
     24
 }
 
+const fn default_low_threshold() -> f64 {
+    20.0
+}
+
+const fn default_critical_threshold() -> f64 {
+    5.0
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PeripheralAlerts {
+    /// The battery percentage at or below which to alert for a peripheral.
+    ///
+    /// **Default**: `20`
+    #[serde(default = "default_low_threshold")]
+    threshold: f64,
+
+    /// Thresholds for specific peripherals, which take priority over `threshold`.
+    /// Each key is either the device model, as shown by `upower --dump`,
+    /// or the type of device, such as `mouse` or `keyboard`.
+    ///
+    /// **Default**: `{}`
+    ///
+    /// # Example
+    ///
+    /// ```corn
+    /// { thresholds.'MX Master 3' = 10 thresholds.keyboard = 30 }
+    /// ```
+    #[serde(default)]
+    thresholds: HashMap<String, f64>,
+
+    /// Whether to send a desktop notification when a peripheral's battery is low.
+    ///
+    /// **Default**: `true`
+    #[serde(default = "crate::config::default_true")]
+    notify: bool,
+
+    /// A [script](scripts) to run when a peripheral's battery drops to its threshold.
+    /// The battery percentage is passed as the first argument, accessible using `$0`,
+    /// and the device name as the second, accessible using `$1`.
+    ///
+    /// **Supported script types**: `oneshot`.
+    /// <br>
+    /// **Default**: `null`
+    on_low: Option<ScriptInput>,
+}
+
+impl From<&PeripheralAlerts> for PeripheralHooks {
+    fn from(alerts: &PeripheralAlerts) -> Self {
+        Self {
+            threshold: alerts.threshold,
+            thresholds: alerts.thresholds.clone(),
+            notify: alerts.notify,
+            on_low: alerts.on_low.clone(),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UpowerProperties {
     percentage: f64,
@@ -75,6 +196,23 @@ impl Module<gtk::Button> for UpowerModule {
 
         let display_proxy = context.client::<PropertiesProxy>();
 
+        let hooks = context.client::<HookRunner>();
+        hooks.register_battery(BatteryHooks {
+            low_threshold: self.low_threshold,
+            critical_threshold: self.critical_threshold,
+            on_low: self.on_low.clone(),
+            on_critical: self.on_critical.clone(),
+            on_charging: self.on_charging.clone(),
+            on_full: self.on_full.clone(),
+        });
+
+        if let Some(alerts) = &self.peripheral_alerts {
+            match context.try_client::<PeripheralWatcher>() {
+                Ok(watcher) => hooks.register_peripherals(&watcher, PeripheralHooks::from(alerts)),
+                Err(err) => error!("{:?}", err.wrap_err("Failed to watch peripherals")),
+            }
+        }
+
         spawn(async move {
             let mut prop_changed_stream = display_proxy.receive_properties_changed().await?;
 
@@ -166,6 +304,7 @@ impl Module<gtk::Button> for UpowerModule {
         info: &ModuleInfo,
     ) -> Result<ModuleParts<Button>> {
         let icon_theme = info.icon_theme.clone();
+        let icon_size = info.icon_size(self.icon_size, default_icon_size());
         let icon = gtk::Image::new();
         icon.add_class("icon");
 
@@ -209,7 +348,7 @@ impl Module<gtk::Button> for UpowerModule {
             let mut icon_name = String::from("icon:");
             icon_name.push_str(&properties.icon_name);
 
-            ImageProvider::parse(&icon_name, &icon_theme, false, self.icon_size)
+            ImageProvider::parse(&icon_name, &icon_theme, false, icon_size)
                     .map(|provider| provider.load_into_image(icon.clone()));
 
             label.set_markup(format.as_ref());
@@ -289,26 +428,6 @@ fn seconds_to_string(seconds: i64) -> String {
     time_string.trim_start().to_string()
 }
 
-const fn u32_to_battery_state(number: u32) -> Result<BatteryState, u32> {
-    if number == (BatteryState::Unknown as u32) {
-        Ok(BatteryState::Unknown)
-    } else if number == (BatteryState::Charging as u32) {
-        Ok(BatteryState::Charging)
-    } else if number == (BatteryState::Discharging as u32) {
-        Ok(BatteryState::Discharging)
-    } else if number == (BatteryState::Empty as u32) {
-        Ok(BatteryState::Empty)
-    } else if number == (BatteryState::FullyCharged as u32) {
-        Ok(BatteryState::FullyCharged)
-    } else if number == (BatteryState::PendingCharge as u32) {
-        Ok(BatteryState::PendingCharge)
-    } else if number == (BatteryState::PendingDischarge as u32) {
-        Ok(BatteryState::PendingDischarge)
-    } else {
-        Err(number)
-    }
-}
-
 fn battery_state_to_string(state: BatteryState) -> &'static str {
     match state {
         BatteryState::Unknown => "Unknown",
@@ -0,0 +1,314 @@
+use crate::clients::udisks::{self, Drive, Filesystem};
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::new_icon_label;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct UsbModule {
+    /// The icon to show on the bar widget button.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `󰕓`
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render the icon at.
+    /// Note this only applies to image-type icons.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{count}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Whether to show the widget while no removable devices are connected.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    show_when_empty: bool,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon() -> String {
+    String::from("󰕓")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+fn default_format() -> String {
+    String::from("{count}")
+}
+
+#[derive(Debug, Clone)]
+pub enum UsbCommand {
+    /// Mounts the filesystem with the given object path.
+    Mount(String),
+    /// Unmounts the filesystem with the given object path.
+    Unmount(String),
+    /// Unmounts and ejects the drive with the given object path.
+    Eject(String),
+}
+
+impl Module<Button> for UsbModule {
+    type SendMessage = Vec<Drive>;
+    type ReceiveMessage = UsbCommand;
+
+    module_impl!("usb");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let client = context.try_client::<udisks::Client>()?;
+
+        {
+            let mut drives = client.subscribe();
+            let tx = context.tx.clone();
+
+            spawn(async move {
+                loop {
+                    let current = drives.borrow_and_update().clone();
+                    send_async!(tx, ModuleUpdateEvent::Update(current));
+
+                    if drives.changed().await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        spawn(async move {
+            while let Some(command) = rx.recv().await {
+                let res = match command {
+                    UsbCommand::Mount(path) => client
+                        .mount(&path)
+                        .await
+                        .map(|mount_point| debug!("Mounted {path} at {mount_point}")),
+                    UsbCommand::Unmount(path) => client.unmount(&path).await,
+                    UsbCommand::Eject(path) => client.eject(&path).await,
+                };
+
+                if let Err(err) = res {
+                    error!("{err:?}");
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let container = gtk::Box::new(Orientation::Horizontal, 5);
+
+        let icon = new_icon_label(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
+        container.add(&icon);
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        container.add(&label);
+
+        button.add(&container);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let format = self.format.clone();
+            let show_when_empty = self.show_when_empty;
+
+            glib_recv!(context.subscribe(), drives => {
+                let mounted = drives
+                    .iter()
+                    .flat_map(|drive| &drive.filesystems)
+                    .filter(|filesystem| filesystem.is_mounted())
+                    .count();
+
+                label.set_markup(
+                    &format
+                        .replace("{count}", &drives.len().to_string())
+                        .replace("{mounted}", &mounted.to_string()),
+                );
+
+                let names = drives
+                    .iter()
+                    .map(|drive| drive.name.as_str())
+                    .collect::<Vec<_>>();
+                button.set_tooltip_text(Some(&names.join("\n")));
+
+                button.set_visible(show_when_empty || !drives.is_empty());
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, drives => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                if drives.is_empty() {
+                    let label = Label::new(Some("No removable devices"));
+                    label.add_class("empty");
+                    container.add(&label);
+                }
+
+                for drive in &drives {
+                    container.add(&drive_row(drive, &tx));
+                }
+
+                container.show_all();
+            });
+        }
+
+        Some(container)
+    }
+}
+
+/// Creates the popup section for a single drive,
+/// with controls for each of its filesystems.
+fn drive_row(drive: &Drive, tx: &mpsc::Sender<UsbCommand>) -> gtk::Box {
+    let container = gtk::Box::new(Orientation::Vertical, 5);
+    container.add_class("device");
+
+    let header = gtk::Box::new(Orientation::Horizontal, 5);
+
+    let name = Label::new(Some(&drive.name));
+    name.add_class("name");
+    name.set_halign(gtk::Align::Start);
+    name.set_hexpand(true);
+    header.add(&name);
+
+    let eject = Button::with_label("Eject");
+    eject.add_class("btn-eject");
+
+    {
+        let tx = tx.clone();
+        let path = drive.path.clone();
+        eject.connect_clicked(move |_| {
+            try_send!(tx, UsbCommand::Eject(path.clone()));
+        });
+    }
+
+    header.add(&eject);
+    container.add(&header);
+
+    for filesystem in &drive.filesystems {
+        container.add(&filesystem_row(filesystem, tx));
+    }
+
+    container
+}
+
+fn filesystem_row(filesystem: &Filesystem, tx: &mpsc::Sender<UsbCommand>) -> gtk::Box {
+    let row = gtk::Box::new(Orientation::Horizontal, 5);
+    row.add_class("filesystem");
+
+    let details = gtk::Box::new(Orientation::Vertical, 0);
+    details.set_hexpand(true);
+
+    let label = Label::new(Some(&format!(
+        "{} ({})",
+        filesystem.label.as_deref().unwrap_or(&filesystem.device),
+        glib::format_size(filesystem.size)
+    )));
+    label.add_class("label");
+    label.set_halign(gtk::Align::Start);
+    details.add(&label);
+
+    if let Some(mount_point) = filesystem.mount_points.first() {
+        let mount_point = Label::new(Some(mount_point));
+        mount_point.add_class("mount-point");
+        mount_point.set_halign(gtk::Align::Start);
+        details.add(&mount_point);
+    }
+
+    row.add(&details);
+
+    let (text, class) = if filesystem.is_mounted() {
+        ("Unmount", "btn-unmount")
+    } else {
+        ("Mount", "btn-mount")
+    };
+
+    let button = Button::with_label(text);
+    button.add_class(class);
+
+    {
+        let tx = tx.clone();
+        let path = filesystem.path.clone();
+        let mounted = filesystem.is_mounted();
+
+        button.connect_clicked(move |_| {
+            let command = if mounted {
+                UsbCommand::Unmount(path.clone())
+            } else {
+                UsbCommand::Mount(path.clone())
+            };
+
+            try_send!(tx, command);
+        });
+    }
+
+    row.add(&button);
+
+    row
+}
@@ -27,8 +27,7 @@ pub struct VolumeModule {
     #[serde(default = "default_max_volume")]
     max_volume: f64,
 
-    #[serde(default = "default_icon_size")]
-    icon_size: i32,
+    icon_size: Option<i32>,
 
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
@@ -144,6 +143,7 @@ impl Module<Button> for VolumeModule {
         {
             let rx = context.subscribe();
             let icon_theme = info.icon_theme.clone();
+            let icon_size = info.icon_size(self.icon_size, default_icon_size());
 
             let image_icon = Image::new();
             image_icon.add_class("icon");
@@ -156,7 +156,7 @@ impl Module<Button> for VolumeModule {
                             &determine_volume_icon(sink.muted, sink.volume),
                             &icon_theme,
                             false,
-                            self.icon_size,
+                            icon_size,
                         ).map(|provider| provider.load_into_image(image_icon.clone()));
                     },
                     _ => {},
@@ -273,6 +273,7 @@ impl Module<Button> for VolumeModule {
 
         {
             let icon_theme = info.icon_theme.clone();
+            let icon_size = info.icon_size(self.icon_size, default_icon_size());
             let input_container = input_container.clone();
 
             let mut sinks = vec![];
@@ -291,7 +292,7 @@ impl Module<Button> for VolumeModule {
                                 &determine_volume_icon(info.muted, info.volume),
                                 &icon_theme,
                                 false,
-                                self.icon_size,
+                                icon_size,
                             ).map(|provider| provider.load_into_image(btn_mute_icon.clone()));
                         }
 
@@ -308,7 +309,7 @@ impl Module<Button> for VolumeModule {
                                     &determine_volume_icon(info.muted, info.volume),
                                     &icon_theme,
                                     false,
-                                    self.icon_size,
+                                    icon_size,
                                 ).map(|provider| provider.load_into_image(btn_mute_icon.clone()));
                             }
                         }
@@ -355,7 +356,7 @@ impl Module<Button> for VolumeModule {
                             &determine_volume_icon(info.muted, info.volume),
                             &icon_theme,
                             false,
-                            self.icon_size,
+                            icon_size,
                         ).map(|provider| provider.load_into_image(btn_mute_icon.clone()));
 
                         {
@@ -389,7 +390,7 @@ impl Module<Button> for VolumeModule {
                                 &determine_volume_icon(info.muted, info.volume),
                                 &icon_theme,
                                 false,
-                                self.icon_size,
+                                icon_size,
                             ).map(|provider| provider.load_into_image(ui.btn_mute_icon.clone()));
                         }
                     }
@@ -398,6 +399,7 @@ impl Module<Button> for VolumeModule {
                             input_container.remove(&ui.container);
                         }
                     }
+                    Event::AddSource(_) | Event::UpdateSource(_) | Event::RemoveSource(_) => {}
                 }
             });
         }
@@ -0,0 +1,676 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::PopupButton;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn, try_send};
+use chrono::{DateTime, Local};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::{debug, warn};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WeatherAlertsModule {
+    /// The feed to fetch alerts from.
+    /// See [sources](#sources).
+    #[serde(flatten)]
+    source: AlertSource,
+
+    /// Time in seconds between fetching alerts.
+    ///
+    /// **Default**: `900`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// The lowest severity of alert to show.
+    ///
+    /// **Valid options**: `unknown`, `minor`, `moderate`, `severe`, `extreme`
+    /// <br>
+    /// **Default**: `minor`
+    #[serde(default = "default_min_severity")]
+    min_severity: Severity,
+
+    /// Format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `⚠ {count}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "source", rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum AlertSource {
+    /// The US National Weather Service.
+    Nws {
+        /// The latitude of the location to get alerts for.
+        ///
+        /// **Required**
+        latitude: f64,
+
+        /// The longitude of the location to get alerts for.
+        ///
+        /// **Required**
+        longitude: f64,
+    },
+    /// MeteoAlarm, covering most European countries.
+    #[serde(rename = "meteoalarm")]
+    MeteoAlarm {
+        /// The country feed to use, as written in the feed URL,
+        /// such as `germany` or `united-kingdom`.
+        ///
+        /// **Required**
+        country: String,
+
+        /// Only show alerts with an area containing this text.
+        /// Leave unset to show alerts for the whole country.
+        ///
+        /// **Default**: `null`
+        region: Option<String>,
+
+        /// The language to show alerts in, such as `en` or `de-DE`.
+        /// Alerts are shown in the first available language if this is not available.
+        ///
+        /// **Default**: `en`
+        #[serde(default = "default_language")]
+        language: String,
+    },
+}
+
+const fn default_interval() -> u64 {
+    900
+}
+
+const fn default_min_severity() -> Severity {
+    Severity::Minor
+}
+
+fn default_format() -> String {
+    String::from("⚠ {count}")
+}
+
+fn default_language() -> String {
+    String::from("en")
+}
+
+/// The CAP severity of an alert.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Severity {
+    Unknown,
+    Minor,
+    Moderate,
+    Severe,
+    Extreme,
+}
+
+impl From<&str> for Severity {
+    fn from(value: &str) -> Self {
+        match value.to_lowercase().as_str() {
+            "minor" => Self::Minor,
+            "moderate" => Self::Moderate,
+            "severe" => Self::Severe,
+            "extreme" => Self::Extreme,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+impl Severity {
+    const fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Minor => "minor",
+            Self::Moderate => "moderate",
+            Self::Severe => "severe",
+            Self::Extreme => "extreme",
+        }
+    }
+
+    fn class(self) -> String {
+        format!("severity-{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+pub struct Alert {
+    event: String,
+    headline: String,
+    description: String,
+    area: String,
+    severity: Severity,
+    /// RFC 3339 timestamp
+    expires: Option<String>,
+}
+
+impl Alert {
+    fn is_expired(&self) -> bool {
+        self.expires.as_deref().is_some_and(|expires| {
+            DateTime::parse_from_rfc3339(expires).is_ok_and(|expires| expires < Local::now())
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct AlertsUpdate {
+    /// Active alerts, most severe first.
+    alerts: Vec<Alert>,
+    /// Whether the last fetch failed,
+    /// meaning the alerts may be out of date.
+    stale: bool,
+}
+
+impl AlertSource {
+    fn url(&self) -> String {
+        match self {
+            Self::Nws {
+                latitude,
+                longitude,
+            } => {
+                format!("https://api.weather.gov/alerts/active?point={latitude:.4},{longitude:.4}")
+            }
+            Self::MeteoAlarm { country, .. } => {
+                format!("https://feeds.meteoalarm.org/api/v1/warnings/feeds-{country}")
+            }
+        }
+    }
+
+    /// Gets the path to the file the last fetched alerts are cached in.
+    fn cache_path(&self) -> Option<PathBuf> {
+        let name = match self {
+            Self::Nws {
+                latitude,
+                longitude,
+            } => format!("nws-{latitude:.4}-{longitude:.4}"),
+            Self::MeteoAlarm {
+                country, region, ..
+            } => match region {
+                Some(region) => format!("meteoalarm-{country}-{region}"),
+                None => format!("meteoalarm-{country}"),
+            },
+        };
+
+        dirs::cache_dir().map(|dir| {
+            dir.join("ironbar")
+                .join("weather_alerts")
+                .join(format!("{}.json", name.replace(['/', ' '], "_")))
+        })
+    }
+
+    async fn fetch(&self, client: &reqwest::Client) -> Result<Vec<Alert>> {
+        let res = client.get(self.url()).send().await?.error_for_status()?;
+        let body = res.text().await?;
+
+        match self {
+            Self::Nws { .. } => parse_nws(&body),
+            Self::MeteoAlarm {
+                region, language, ..
+            } => parse_meteoalarm(&body, region.as_deref(), language),
+        }
+    }
+}
+
+fn parse_nws(body: &str) -> Result<Vec<Alert>> {
+    #[derive(Deserialize)]
+    struct Response {
+        features: Vec<Feature>,
+    }
+
+    #[derive(Deserialize)]
+    struct Feature {
+        properties: Properties,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Properties {
+        event: String,
+        headline: Option<String>,
+        description: Option<String>,
+        area_desc: String,
+        severity: String,
+        expires: Option<String>,
+        ends: Option<String>,
+    }
+
+    let res = serde_json::from_str::<Response>(body)?;
+
+    Ok(res
+        .features
+        .into_iter()
+        .map(|feature| {
+            let alert = feature.properties;
+
+            Alert {
+                headline: alert.headline.unwrap_or_else(|| alert.event.clone()),
+                event: alert.event,
+                description: alert.description.unwrap_or_default(),
+                area: alert.area_desc,
+                severity: Severity::from(alert.severity.as_str()),
+                expires: alert.ends.or(alert.expires),
+            }
+        })
+        .collect())
+}
+
+fn parse_meteoalarm(body: &str, region: Option<&str>, language: &str) -> Result<Vec<Alert>> {
+    #[derive(Deserialize)]
+    struct Response {
+        warnings: Vec<Warning>,
+    }
+
+    #[derive(Deserialize)]
+    struct Warning {
+        alert: CapAlert,
+    }
+
+    #[derive(Deserialize)]
+    struct CapAlert {
+        info: Vec<Info>,
+    }
+
+    #[derive(Deserialize)]
+    struct Info {
+        #[serde(default)]
+        language: String,
+        event: String,
+        headline: Option<String>,
+        description: Option<String>,
+        severity: String,
+        expires: Option<String>,
+        #[serde(default)]
+        area: Vec<Area>,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Area {
+        area_desc: String,
+    }
+
+    let res = serde_json::from_str::<Response>(body)?;
+    let region = region.map(str::to_lowercase);
+
+    Ok(res
+        .warnings
+        .into_iter()
+        .filter_map(|warning| {
+            let mut info = warning.alert.info;
+
+            let index = info
+                .iter()
+                .position(|info| info.language.starts_with(language))
+                .unwrap_or_default();
+
+            if index >= info.len() {
+                return None;
+            }
+
+            let info = info.swap_remove(index);
+
+            let area = info
+                .area
+                .iter()
+                .map(|area| area.area_desc.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            if let Some(region) = &region {
+                if !area.to_lowercase().contains(region) {
+                    return None;
+                }
+            }
+
+            Some(Alert {
+                headline: info.headline.unwrap_or_else(|| info.event.clone()),
+                event: info.event,
+                description: info.description.unwrap_or_default(),
+                area,
+                severity: Severity::from(info.severity.as_str()),
+                expires: info.expires,
+            })
+        })
+        .collect())
+}
+
+fn load_cache(path: &Path) -> Option<Vec<Alert>> {
+    let contents = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+fn save_cache(path: &Path, alerts: &[Alert]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, serde_json::to_string(alerts)?)?;
+    Ok(())
+}
+
+impl Module<Button> for WeatherAlertsModule {
+    type SendMessage = AlertsUpdate;
+    type ReceiveMessage = ();
+
+    module_impl!("weather_alerts");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let source = self.source.clone();
+        let interval = Duration::from_secs(self.interval);
+        let min_severity = self.min_severity;
+
+        let client = reqwest::Client::builder()
+            .user_agent(concat!("ironbar/", env!("CARGO_PKG_VERSION")))
+            .build()?;
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            let cache_path = source.cache_path();
+
+            // show the last known alerts until the first fetch completes,
+            // or indefinitely while offline
+            let mut alerts = cache_path
+                .as_deref()
+                .and_then(load_cache)
+                .unwrap_or_default();
+
+            loop {
+                let stale = match source.fetch(&client).await {
+                    Ok(fetched) => {
+                        debug!("Fetched {} weather alerts", fetched.len());
+
+                        if let Some(path) = &cache_path {
+                            if let Err(err) = save_cache(path, &fetched) {
+                                warn!("{:?}", err.wrap_err("Failed to cache weather alerts"));
+                            }
+                        }
+
+                        alerts = fetched;
+                        false
+                    }
+                    Err(err) => {
+                        warn!("{:?}", err.wrap_err("Failed to fetch weather alerts"));
+                        true
+                    }
+                };
+
+                alerts.retain(|alert| !alert.is_expired());
+
+                let mut visible = alerts
+                    .iter()
+                    .filter(|alert| alert.severity >= min_severity)
+                    .cloned()
+                    .collect::<Vec<_>>();
+
+                visible.sort_by(|a, b| b.severity.cmp(&a.severity));
+
+                send_async!(
+                    tx,
+                    ModuleUpdateEvent::Update(AlertsUpdate {
+                        alerts: visible,
+                        stale,
+                    })
+                );
+
+                sleep(interval).await;
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        button.add(&label);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let format = self.format.clone();
+
+            glib_recv!(context.subscribe(), update => {
+                set_alert_classes(&button, &update);
+
+                let Some(most_severe) = update.alerts.first() else {
+                    button.hide();
+                    continue;
+                };
+
+                let text = format
+                    .replace("{count}", &update.alerts.len().to_string())
+                    .replace("{event}", &glib::markup_escape_text(&most_severe.event))
+                    .replace("{severity}", most_severe.severity.as_str());
+
+                label.set_markup(&text);
+                button.show();
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        _tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+        container.add_class("alerts");
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, update => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                if update.stale {
+                    let label = Label::new(Some("Offline - alerts may be out of date"));
+                    label.add_class("stale");
+                    container.add(&label);
+                }
+
+                for alert in &update.alerts {
+                    container.add(&alert_widget(alert));
+                }
+
+                container.show_all();
+            });
+        }
+
+        container.show_all();
+
+        Some(container)
+    }
+}
+
+/// Replaces the severity and stale classes on the button,
+/// using the most severe alert.
+fn set_alert_classes(button: &Button, update: &AlertsUpdate) {
+    let style_context = button.style_context();
+
+    for severity in [
+        Severity::Unknown,
+        Severity::Minor,
+        Severity::Moderate,
+        Severity::Severe,
+        Severity::Extreme,
+    ] {
+        style_context.remove_class(&severity.class());
+    }
+
+    if let Some(alert) = update.alerts.first() {
+        style_context.add_class(&alert.severity.class());
+    }
+
+    if update.stale {
+        style_context.add_class("stale");
+    } else {
+        style_context.remove_class("stale");
+    }
+}
+
+fn alert_widget(alert: &Alert) -> gtk::Box {
+    let container = gtk::Box::new(Orientation::Vertical, 0);
+    container.add_class("alert");
+    container.add_class(&alert.severity.class());
+
+    let title = Label::builder()
+        .label(&alert.headline)
+        .halign(gtk::Align::Start)
+        .wrap(true)
+        .max_width_chars(60)
+        .build();
+    title.add_class("title");
+    container.add(&title);
+
+    let area = Label::builder()
+        .label(&alert.area)
+        .halign(gtk::Align::Start)
+        .wrap(true)
+        .max_width_chars(60)
+        .build();
+    area.add_class("area");
+    container.add(&area);
+
+    if !alert.description.is_empty() {
+        let description = Label::builder()
+            .label(&alert.description)
+            .halign(gtk::Align::Start)
+            .wrap(true)
+            .max_width_chars(60)
+            .build();
+        description.add_class("description");
+        container.add(&description);
+    }
+
+    container
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_nws() {
+        let body = r#"{
+            "features": [{
+                "properties": {
+                    "event": "Flood Warning",
+                    "headline": "Flood Warning issued for Example County",
+                    "description": "Flooding is occurring.",
+                    "areaDesc": "Example County",
+                    "severity": "Severe",
+                    "expires": "2024-06-01T12:00:00-05:00",
+                    "ends": null
+                }
+            }]
+        }"#;
+
+        let alerts = parse_nws(body).expect("to parse");
+
+        assert_eq!(
+            alerts,
+            vec![Alert {
+                event: String::from("Flood Warning"),
+                headline: String::from("Flood Warning issued for Example County"),
+                description: String::from("Flooding is occurring."),
+                area: String::from("Example County"),
+                severity: Severity::Severe,
+                expires: Some(String::from("2024-06-01T12:00:00-05:00")),
+            }]
+        );
+        assert!(alerts[0].is_expired());
+    }
+
+    #[test]
+    fn test_parse_meteoalarm() {
+        let body = r#"{
+            "warnings": [
+                {
+                    "alert": {
+                        "info": [
+                            {
+                                "language": "de-DE",
+                                "event": "Sturmböen",
+                                "severity": "Moderate",
+                                "area": [{ "areaDesc": "Berlin" }]
+                            },
+                            {
+                                "language": "en-GB",
+                                "event": "Gale-force gusts",
+                                "headline": "Gale-force gusts in Berlin",
+                                "severity": "Moderate",
+                                "area": [{ "areaDesc": "Berlin" }]
+                            }
+                        ]
+                    }
+                },
+                {
+                    "alert": {
+                        "info": [
+                            {
+                                "language": "de-DE",
+                                "event": "Hitze",
+                                "severity": "Minor",
+                                "area": [{ "areaDesc": "Hamburg" }]
+                            }
+                        ]
+                    }
+                }
+            ]
+        }"#;
+
+        let alerts = parse_meteoalarm(body, None, "en").expect("to parse");
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].headline, "Gale-force gusts in Berlin");
+        assert_eq!(alerts[1].event, "Hitze");
+
+        let alerts = parse_meteoalarm(body, Some("hamburg"), "en").expect("to parse");
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].severity, Severity::Minor);
+    }
+}
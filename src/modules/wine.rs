@@ -0,0 +1,499 @@
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::image::new_icon_label;
+use crate::modules::{
+    Module, ModuleInfo, ModuleParts, ModulePopup, ModuleUpdateEvent, PopupButton, WidgetContext,
+};
+use crate::{glib_recv, module_impl, send_async, spawn, spawn_blocking, try_send};
+use color_eyre::eyre::{eyre, Result};
+use gtk::prelude::*;
+use gtk::{Button, Label, Orientation};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::sleep;
+use tracing::{debug, error};
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WineModule {
+    /// The icon to show on the bar widget button.
+    /// Supports [image](images) icons.
+    ///
+    /// **Default**: `󰡶`
+    #[serde(default = "default_icon")]
+    icon: String,
+
+    /// The size to render the icon at.
+    /// Note this only applies to image-type icons.
+    ///
+    /// **Default**: The bar's `icon_size` if set, otherwise `24`
+    icon_size: Option<i32>,
+
+    /// The format string to use for the widget button label.
+    /// For available tokens, see [below](#formatting-tokens).
+    ///
+    /// **Default**: `{count}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// Whether to show the widget while no Wine prefixes are running.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    show_when_empty: bool,
+
+    /// Time in seconds between checking for running Wine processes.
+    ///
+    /// **Default**: `5`
+    #[serde(default = "default_interval")]
+    interval: u64,
+
+    /// Names of Windows executables to leave out of the process list.
+    /// Matching is case-insensitive.
+    ///
+    /// By default, this contains the background services Wine starts in every prefix.
+    ///
+    /// **Default**: `["conhost.exe", "explorer.exe", "plugplay.exe", "rpcss.exe", "services.exe", "start.exe", "svchost.exe", "tabtip.exe", "winedevice.exe"]`
+    #[serde(default = "default_ignored")]
+    ignored: Vec<String>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_icon() -> String {
+    String::from("󰡶")
+}
+
+const fn default_icon_size() -> i32 {
+    24
+}
+
+fn default_format() -> String {
+    String::from("{count}")
+}
+
+const fn default_interval() -> u64 {
+    5
+}
+
+fn default_ignored() -> Vec<String> {
+    [
+        "conhost.exe",
+        "explorer.exe",
+        "plugplay.exe",
+        "rpcss.exe",
+        "services.exe",
+        "start.exe",
+        "svchost.exe",
+        "tabtip.exe",
+        "winedevice.exe",
+    ]
+    .into_iter()
+    .map(String::from)
+    .collect()
+}
+
+/// A Wine prefix with running processes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WinePrefix {
+    /// The prefix directory.
+    /// For Proton, this is the `pfx` directory inside the game's compatdata directory.
+    path: PathBuf,
+    /// The executable of the prefix's running `wineserver`.
+    /// Proton ships its own, so this is used to shut it down
+    /// rather than any `wineserver` on the path.
+    wineserver: Option<PathBuf>,
+    /// The names of the Windows executables running in the prefix, sorted.
+    processes: Vec<String>,
+}
+
+impl Module<Button> for WineModule {
+    type SendMessage = Vec<WinePrefix>;
+    /// The prefix to kill.
+    type ReceiveMessage = WinePrefix;
+
+    module_impl!("wine");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        mut rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let interval = Duration::from_secs(self.interval);
+        let ignored = self.ignored.clone();
+
+        let tx = context.tx.clone();
+        spawn(async move {
+            let mut previous = None;
+
+            loop {
+                let ignored = ignored.clone();
+                match spawn_blocking(move || scan(&ignored)).await {
+                    Ok(prefixes) => {
+                        if previous.as_ref() != Some(&prefixes) {
+                            previous = Some(prefixes.clone());
+                            send_async!(tx, ModuleUpdateEvent::Update(prefixes));
+                        }
+                    }
+                    Err(err) => error!("{err:?}"),
+                }
+
+                tokio::select! {
+                    () = sleep(interval) => {}
+                    Some(prefix) = rx.recv() => {
+                        // the loop rescans straight away, so the prefix disappears once killed
+                        if let Err(err) = kill(&prefix).await {
+                            error!("{err:?}");
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Button>> {
+        let button = Button::new();
+
+        let container = gtk::Box::new(Orientation::Horizontal, 5);
+
+        let icon = new_icon_label(
+            &self.icon,
+            info.icon_theme,
+            info.icon_size(self.icon_size, default_icon_size()),
+        );
+        container.add(&icon);
+
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+        label.add_class("label");
+        container.add(&label);
+
+        button.add(&container);
+
+        let tx = context.tx.clone();
+        button.connect_clicked(move |button| {
+            try_send!(tx, ModuleUpdateEvent::TogglePopup(button.popup_id()));
+        });
+
+        {
+            let button = button.clone();
+            let format = self.format.clone();
+            let show_when_empty = self.show_when_empty;
+
+            glib_recv!(context.subscribe(), prefixes => {
+                let processes = prefixes
+                    .iter()
+                    .map(|prefix| prefix.processes.len())
+                    .sum::<usize>();
+
+                label.set_markup(
+                    &format
+                        .replace("{count}", &prefixes.len().to_string())
+                        .replace("{processes}", &processes.to_string()),
+                );
+
+                let paths = prefixes
+                    .iter()
+                    .map(|prefix| display_path(&prefix.path))
+                    .collect::<Vec<_>>();
+                button.set_tooltip_text(Some(&paths.join("\n")));
+
+                button.set_visible(show_when_empty || !prefixes.is_empty());
+            });
+        }
+
+        let rx = context.subscribe();
+        let popup = self
+            .into_popup(context.controller_tx.clone(), rx, context, info)
+            .into_popup_parts(vec![&button]);
+
+        Ok(ModuleParts::new(button, popup))
+    }
+
+    fn into_popup(
+        self,
+        tx: mpsc::Sender<Self::ReceiveMessage>,
+        rx: broadcast::Receiver<Self::SendMessage>,
+        _context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _info: &ModuleInfo,
+    ) -> Option<gtk::Box>
+    where
+        Self: Sized,
+    {
+        let container = gtk::Box::new(Orientation::Vertical, 10);
+
+        {
+            let container = container.clone();
+
+            glib_recv!(rx, prefixes => {
+                for child in container.children() {
+                    container.remove(&child);
+                }
+
+                if prefixes.is_empty() {
+                    let label = Label::new(Some("No Wine prefixes running"));
+                    label.add_class("empty");
+                    container.add(&label);
+                }
+
+                for prefix in &prefixes {
+                    container.add(&prefix_row(prefix, &tx));
+                }
+
+                container.show_all();
+            });
+        }
+
+        Some(container)
+    }
+}
+
+/// Creates the popup section for a single prefix,
+/// listing its processes.
+fn prefix_row(prefix: &WinePrefix, tx: &mpsc::Sender<WinePrefix>) -> gtk::Box {
+    let container = gtk::Box::new(Orientation::Vertical, 5);
+    container.add_class("prefix");
+
+    let header = gtk::Box::new(Orientation::Horizontal, 5);
+
+    let path = Label::new(Some(&display_path(&prefix.path)));
+    path.add_class("path");
+    path.set_halign(gtk::Align::Start);
+    path.set_hexpand(true);
+    header.add(&path);
+
+    let kill = Button::with_label("Kill");
+    kill.add_class("btn-kill");
+
+    {
+        let tx = tx.clone();
+        let prefix = prefix.clone();
+        kill.connect_clicked(move |_| {
+            try_send!(tx, prefix.clone());
+        });
+    }
+
+    header.add(&kill);
+    container.add(&header);
+
+    for process in &prefix.processes {
+        let label = Label::new(Some(process));
+        label.add_class("process");
+        label.set_halign(gtk::Align::Start);
+        container.add(&label);
+    }
+
+    container
+}
+
+/// Shuts down all processes in the prefix using `wineserver -k`.
+async fn kill(prefix: &WinePrefix) -> Result<()> {
+    let wineserver = prefix
+        .wineserver
+        .as_deref()
+        .unwrap_or_else(|| Path::new("wineserver"));
+
+    debug!(
+        "Killing Wine prefix '{}' using '{}'",
+        prefix.path.display(),
+        wineserver.display()
+    );
+
+    let status = Command::new(wineserver)
+        .arg("-k")
+        .env("WINEPREFIX", &prefix.path)
+        .status()
+        .await?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "Failed to kill Wine prefix '{}': wineserver exited with {status}",
+            prefix.path.display()
+        ))
+    }
+}
+
+/// Finds the running Wine prefixes by reading `/proc`.
+///
+/// Only the current user's processes can be read,
+/// so other users' prefixes are not included.
+fn scan(ignored: &[String]) -> Vec<WinePrefix> {
+    let entries = match fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Failed to read processes: {err}");
+            return vec![];
+        }
+    };
+
+    let mut prefixes = BTreeMap::new();
+
+    for entry in entries.flatten() {
+        let is_pid = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.bytes().all(|byte| byte.is_ascii_digit()));
+
+        if !is_pid {
+            continue;
+        }
+
+        let dir = entry.path();
+
+        let comm = fs::read_to_string(dir.join("comm")).unwrap_or_default();
+        let is_wineserver = comm.trim_end() == "wineserver";
+
+        let name = fs::read(dir.join("cmdline"))
+            .ok()
+            .and_then(|cmdline| process_name(&cmdline));
+
+        if !is_wineserver && name.is_none() {
+            continue;
+        }
+
+        let Some(path) = fs::read(dir.join("environ"))
+            .ok()
+            .and_then(|environ| prefix_path(&environ))
+        else {
+            continue;
+        };
+
+        let prefix = prefixes.entry(path.clone()).or_insert_with(|| WinePrefix {
+            path,
+            wineserver: None,
+            processes: vec![],
+        });
+
+        if is_wineserver {
+            // the link gains a ` (deleted)` suffix if wine was updated while running
+            prefix.wineserver = fs::read_link(dir.join("exe"))
+                .ok()
+                .filter(|exe| exe.exists());
+        } else if let Some(name) = name.filter(|name| !is_ignored(name, ignored)) {
+            prefix.processes.push(name);
+        }
+    }
+
+    prefixes
+        .into_values()
+        .map(|mut prefix| {
+            prefix
+                .processes
+                .sort_unstable_by_key(|name| name.to_lowercase());
+            prefix
+        })
+        .collect()
+}
+
+/// Gets the name of a Windows executable from a process's `cmdline`.
+///
+/// Wine replaces the first argument with the Windows path of the executable,
+/// so only processes for which that ends in `.exe` are matched.
+fn process_name(cmdline: &[u8]) -> Option<String> {
+    let first = cmdline.split(|&byte| byte == 0).next()?;
+    let first = String::from_utf8_lossy(first);
+
+    let name = first.rsplit(['\\', '/']).next()?;
+
+    name.to_lowercase()
+        .ends_with(".exe")
+        .then(|| name.to_string())
+}
+
+/// Gets the prefix a process is using from its environment.
+///
+/// This is `WINEPREFIX`, which Proton also sets.
+/// When unset, Wine falls back to `~/.wine`.
+fn prefix_path(environ: &[u8]) -> Option<PathBuf> {
+    let var = |name: &str| {
+        environ
+            .split(|&byte| byte == 0)
+            .filter_map(|entry| std::str::from_utf8(entry).ok())
+            .find_map(|entry| entry.strip_prefix(name)?.strip_prefix('='))
+            .filter(|value| !value.is_empty())
+    };
+
+    var("WINEPREFIX")
+        .map(PathBuf::from)
+        .or_else(|| var("STEAM_COMPAT_DATA_PATH").map(|path| Path::new(path).join("pfx")))
+        .or_else(|| var("HOME").map(|home| Path::new(home).join(".wine")))
+}
+
+fn is_ignored(name: &str, ignored: &[String]) -> bool {
+    ignored
+        .iter()
+        .any(|ignored| ignored.eq_ignore_ascii_case(name))
+}
+
+/// Formats a path for display, replacing the home directory with `~`.
+fn display_path(path: &Path) -> String {
+    dirs::home_dir()
+        .and_then(|home| path.strip_prefix(home).ok())
+        .map_or_else(
+            || path.display().to_string(),
+            |relative| Path::new("~").join(relative).display().to_string(),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_name() {
+        assert_eq!(
+            process_name(b"C:\\Program Files\\Game\\Game.EXE\0-windowed\0"),
+            Some(String::from("Game.EXE"))
+        );
+        assert_eq!(
+            process_name(b"/usr/bin/wine\0setup.exe\0"),
+            None,
+            "wine launcher should not match before exec"
+        );
+        assert_eq!(process_name(b"/usr/bin/bash\0"), None);
+        assert_eq!(process_name(b""), None);
+    }
+
+    #[test]
+    fn test_prefix_path() {
+        assert_eq!(
+            prefix_path(b"HOME=/home/user\0WINEPREFIX=/games/prefix\0"),
+            Some(PathBuf::from("/games/prefix"))
+        );
+        assert_eq!(
+            prefix_path(b"STEAM_COMPAT_DATA_PATH=/steam/compatdata/10\0HOME=/home/user\0"),
+            Some(PathBuf::from("/steam/compatdata/10/pfx"))
+        );
+        assert_eq!(
+            prefix_path(b"WINEPREFIX=\0HOME=/home/user\0"),
+            Some(PathBuf::from("/home/user/.wine"))
+        );
+        assert_eq!(prefix_path(b"WINEPREFIXES=/other\0"), None);
+    }
+
+    #[test]
+    fn test_is_ignored() {
+        let ignored = default_ignored();
+
+        assert!(is_ignored("Services.exe", &ignored));
+        assert!(!is_ignored("Game.exe", &ignored));
+    }
+}
@@ -1,33 +1,129 @@
 use crate::clients::compositor::{Visibility, Workspace, WorkspaceClient, WorkspaceUpdate};
+use crate::clients::wayland::{self, Screenshot};
 use crate::config::CommonConfig;
 use crate::gtk_helpers::IronbarGtkExt;
 use crate::image::new_icon_button;
 use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
 use crate::{glib_recv, module_impl, send_async, spawn, try_send, Ironbar};
 use color_eyre::{Report, Result};
+use gtk::gdk_pixbuf::{Colorspace, InterpType, Pixbuf};
 use gtk::prelude::*;
-use gtk::{Button, IconTheme};
+use gtk::{Button, IconTheme, Orientation, Separator};
 use serde::Deserialize;
+use std::cell::{Cell, RefCell};
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet};
+use std::iter::Peekable;
+use std::rc::Rc;
+use std::str::Chars;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc::{Receiver, Sender};
 use tracing::{debug, trace, warn};
 
-#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq, Default)]
 #[serde(rename_all = "snake_case")]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum SortOrder {
-    /// Shows workspaces in the order they're added
+    /// Shows workspaces in the order the compositor provides them,
+    /// with new workspaces added to the end.
+    #[serde(alias = "none")]
     Added,
-    /// Shows workspaces in numeric order.
-    /// Named workspaces are added to the end in alphabetical order.
-    Alphanumeric,
+    /// Shows workspaces in order of the number at the start of their name.
+    /// Workspaces without a number are added to the end in natural order.
+    #[default]
+    #[serde(alias = "alphanumeric")]
+    Number,
+    /// Shows workspaces in natural order by name,
+    /// so that `2` comes before `10`.
+    Name,
+    /// Groups workspaces by output name, then by number within each output.
+    /// This only differs from `number` when showing all outputs.
+    Output,
 }
 
-impl Default for SortOrder {
-    fn default() -> Self {
-        Self::Alphanumeric
+impl SortOrder {
+    /// Whether workspaces should be re-ordered as they are added.
+    fn is_sorted(self) -> bool {
+        self != Self::Added
     }
+
+    /// Compares two workspace names.
+    fn compare(self, a: &str, b: &str) -> Ordering {
+        match self {
+            Self::Added => Ordering::Equal,
+            Self::Name => natural_cmp(a, b),
+            Self::Number | Self::Output => match (leading_number(a), leading_number(b)) {
+                (Some(num_a), Some(num_b)) => num_a.cmp(&num_b).then_with(|| natural_cmp(a, b)),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => natural_cmp(a, b),
+            },
+        }
+    }
+}
+
+/// Gets the number at the start of a workspace name, ie `1` for `1:web`.
+fn leading_number(name: &str) -> Option<u64> {
+    let end = name
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(name.len());
+
+    name[..end].parse().ok()
+}
+
+/// Compares two strings, treating each run of digits as a number
+/// so that `ws2` comes before `ws10`.
+/// Letters are compared case-insensitively, unless the strings are otherwise equal.
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    fn take_number(chars: &mut Peekable<Chars<'_>>) -> String {
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(char::is_ascii_digit) {
+            number.push(c);
+        }
+        number
+    }
+
+    let mut chars_a = a.chars().peekable();
+    let mut chars_b = b.chars().peekable();
+
+    loop {
+        let ordering = match (chars_a.peek(), chars_b.peek()) {
+            (None, None) => return a.cmp(b),
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(char_a), Some(char_b)) if char_a.is_ascii_digit() && char_b.is_ascii_digit() => {
+                let num_a = take_number(&mut chars_a);
+                let num_b = take_number(&mut chars_b);
+
+                // compare by length first to avoid overflowing on long numbers
+                let (num_a, num_b) = (num_a.trim_start_matches('0'), num_b.trim_start_matches('0'));
+                num_a.len().cmp(&num_b.len()).then_with(|| num_a.cmp(num_b))
+            }
+            (Some(char_a), Some(char_b)) => {
+                let ordering = char_a.to_lowercase().cmp(char_b.to_lowercase());
+                chars_a.next();
+                chars_b.next();
+                ordering
+            }
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq, Default)]
+#[serde(rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DisplayMode {
+    /// Shows a button for each workspace, labelled with its name.
+    #[default]
+    Buttons,
+    /// Shows a small unlabelled dot for each workspace,
+    /// styled through its state classes.
+    Dots,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -88,27 +184,64 @@ pub struct WorkspacesModule {
     #[serde(default)]
     hidden: Vec<String>,
 
-    /// Whether to display workspaces from all monitors.
-    /// When false, only shows workspaces on the current monitor.
+    /// Whether to display workspaces from all outputs.
+    /// When false, only shows workspaces on the bar's own output.
+    /// When true, workspaces are grouped by output,
+    /// with a separator between each group.
+    ///
+    /// `all_monitors` is accepted as an alias for backwards compatibility.
     ///
     /// **Default**: `false`
-    #[serde(default = "crate::config::default_false")]
-    all_monitors: bool,
+    #[serde(default = "crate::config::default_false", alias = "all_monitors")]
+    show_all_outputs: bool,
 
     /// The method used for sorting workspaces.
-    /// `added` always appends to the end, `alphanumeric` sorts by number/name.
+    /// `added` keeps the compositor's order and appends new workspaces to the end,
+    /// `number` sorts by the number at the start of the name,
+    /// `name` sorts by name,
+    /// and `output` sorts by output name then number.
     ///
-    /// **Valid options**: `added`, `alphanumeric`
+    /// Names are sorted naturally, so `10` comes after `2`.
+    /// `none` and `alphanumeric` are accepted as aliases of `added` and `number` respectively.
+    ///
+    /// **Valid options**: `added`, `number`, `name`, `output`
     /// <br>
-    /// **Default**: `alphanumeric`
+    /// **Default**: `number`
     #[serde(default)]
     sort: SortOrder,
 
+    /// How each workspace is rendered.
+    /// `buttons` shows a labelled button,
+    /// `dots` shows a minimal dot which can be styled
+    /// using the `focused`, `visible`, `inactive` and `urgent` classes.
+    ///
+    /// **Valid options**: `buttons`, `dots`
+    /// <br>
+    /// **Default**: `buttons`
+    #[serde(default)]
+    mode: DisplayMode,
+
     /// The size to render icons at (image icons only).
     ///
-    /// **Default**: `32`
-    #[serde(default = "default_icon_size")]
-    icon_size: i32,
+    /// **Default**: The bar's `icon_size` if set, otherwise `32`
+    icon_size: Option<i32>,
+
+    /// Whether to show a thumbnail preview of a workspace's windows
+    /// when hovering over its button.
+    ///
+    /// Previews are captured from the workspace's output while it is visible,
+    /// so are only available for workspaces which have been shown since Ironbar started.
+    /// This requires compositor support for the `wlr-screencopy` protocol.
+    ///
+    /// **Default**: `false`
+    #[serde(default)]
+    previews: bool,
+
+    /// The width to render workspace previews at, in pixels.
+    ///
+    /// **Default**: `256`
+    #[serde(default = "default_preview_size")]
+    preview_size: i32,
 
     /// See [common options](module-level-options#common-options).
     #[serde(flatten)]
@@ -119,6 +252,17 @@ const fn default_icon_size() -> i32 {
     32
 }
 
+const fn default_preview_size() -> i32 {
+    256
+}
+
+/// Time to wait after a workspace is shown before capturing it,
+/// giving its windows time to redraw.
+const PREVIEW_CAPTURE_DELAY: Duration = Duration::from_millis(500);
+
+/// Minimum time between capturing outputs on hover.
+const PREVIEW_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+
 /// Creates a button from a workspace
 fn create_button(
     name: &str,
@@ -126,11 +270,20 @@ fn create_button(
     name_map: &HashMap<String, String>,
     icon_theme: &IconTheme,
     icon_size: i32,
+    mode: DisplayMode,
     tx: &Sender<String>,
 ) -> Button {
     let label = name_map.get(name).map_or(name, String::as_str);
 
-    let button = new_icon_button(label, icon_theme, icon_size);
+    let button = match mode {
+        DisplayMode::Buttons => new_icon_button(label, icon_theme, icon_size),
+        DisplayMode::Dots => {
+            let button = Button::new();
+            button.add_class("dot");
+            button.set_tooltip_text(Some(label));
+            button
+        }
+    };
     button.set_widget_name(name);
 
     let style_context = button.style_context();
@@ -159,32 +312,273 @@ fn create_button(
     button
 }
 
-fn reorder_workspaces(container: &gtk::Box) {
+fn reorder_workspaces(container: &gtk::Box, sort: SortOrder) {
+    if !sort.is_sorted() {
+        return;
+    }
+
     let mut buttons = container
         .children()
         .into_iter()
         .map(|child| (child.widget_name().to_string(), child))
         .collect::<Vec<_>>();
 
-    buttons.sort_by(|(label_a, _), (label_b, _a)| {
-        match (label_a.parse::<i32>(), label_b.parse::<i32>()) {
-            (Ok(a), Ok(b)) => a.cmp(&b),
-            (Ok(_), Err(_)) => Ordering::Less,
-            (Err(_), Ok(_)) => Ordering::Greater,
-            (Err(_), Err(_)) => label_a.cmp(label_b),
-        }
-    });
+    buttons.sort_by(|(label_a, _), (label_b, _)| sort.compare(label_a, label_b));
 
     for (i, (_, button)) in buttons.into_iter().enumerate() {
         container.reorder_child(&button, i as i32);
     }
 }
 
+/// Removes a workspace button from whichever container it is in.
+fn remove_button(button: &Button) {
+    if let Some(parent) = button.parent().and_then(|p| p.downcast::<gtk::Box>().ok()) {
+        parent.remove(button);
+    }
+}
+
+/// Holds the workspace buttons.
+///
+/// When showing all outputs, each output gets its own box
+/// so its workspaces can be sorted independently,
+/// with separators placed between the output boxes.
+struct OutputGroups {
+    container: gtk::Box,
+    orientation: Orientation,
+    grouped: bool,
+    /// Whether to keep the groups in order of output name,
+    /// rather than the order they were created in.
+    sort_outputs: bool,
+    groups: Vec<OutputGroup>,
+}
+
+struct OutputGroup {
+    output: String,
+    separator: Separator,
+    container: gtk::Box,
+}
+
+impl OutputGroups {
+    fn new(container: gtk::Box, orientation: Orientation, grouped: bool, sort: SortOrder) -> Self {
+        Self {
+            container,
+            orientation,
+            grouped,
+            sort_outputs: sort == SortOrder::Output,
+            groups: vec![],
+        }
+    }
+
+    /// Gets the box workspaces on the given output should be added to,
+    /// creating it if necessary.
+    fn get(&mut self, output: &str) -> gtk::Box {
+        if !self.grouped {
+            return self.container.clone();
+        }
+
+        if let Some(group) = self.groups.iter().find(|group| group.output == output) {
+            return group.container.clone();
+        }
+
+        let separator_orientation = match self.orientation {
+            Orientation::Horizontal => Orientation::Vertical,
+            _ => Orientation::Horizontal,
+        };
+
+        let separator = Separator::new(separator_orientation);
+        separator.add_class("separator");
+        separator.set_no_show_all(true);
+
+        let container = gtk::Box::new(self.orientation, 0);
+        container.add_class("output");
+        container.set_widget_name(output);
+
+        self.container.add(&separator);
+        self.container.add(&container);
+        container.show();
+
+        let index = if self.sort_outputs {
+            self.groups
+                .iter()
+                .position(|group| natural_cmp(output, &group.output) == Ordering::Less)
+                .unwrap_or(self.groups.len())
+        } else {
+            self.groups.len()
+        };
+
+        // each group is a separator followed by its box
+        self.container.reorder_child(&separator, index as i32 * 2);
+        self.container
+            .reorder_child(&container, index as i32 * 2 + 1);
+
+        self.groups.insert(
+            index,
+            OutputGroup {
+                output: output.to_string(),
+                separator,
+                container: container.clone(),
+            },
+        );
+
+        self.update_separators();
+
+        container
+    }
+
+    /// Removes any output boxes which no longer contain any workspaces.
+    fn remove_empty(&mut self) {
+        let container = &self.container;
+
+        self.groups.retain(|group| {
+            let empty = group.container.children().is_empty();
+            if empty {
+                container.remove(&group.separator);
+                container.remove(&group.container);
+            }
+            !empty
+        });
+
+        self.update_separators();
+    }
+
+    /// Shows a separator before every group except the first.
+    fn update_separators(&self) {
+        for (i, group) in self.groups.iter().enumerate() {
+            group.separator.set_visible(i > 0);
+        }
+    }
+}
+
+/// Thumbnails of each workspace, shown as the tooltip of its button.
+///
+/// Outputs are captured shortly after a workspace is shown,
+/// and again when hovering over a button,
+/// with the thumbnail stored against the workspace visible on that output.
+#[derive(Clone)]
+struct Previews {
+    client: Arc<wayland::Client>,
+    /// Thumbnails against workspace names.
+    thumbnails: Rc<RefCell<HashMap<String, Pixbuf>>>,
+    /// The name of the workspace visible on each output.
+    visible: Rc<RefCell<HashMap<String, String>>>,
+    last_refresh: Rc<Cell<Option<Instant>>>,
+}
+
+impl Previews {
+    fn new(client: Arc<wayland::Client>, width: i32) -> Self {
+        let previews = Self {
+            client,
+            thumbnails: Rc::default(),
+            visible: Rc::default(),
+            last_refresh: Rc::default(),
+        };
+
+        {
+            let thumbnails = previews.thumbnails.clone();
+            let visible = previews.visible.clone();
+
+            glib_recv!(previews.client.subscribe_screencopy(), screenshot => {
+                let Some(workspace) = visible.borrow().get(&screenshot.output).cloned() else {
+                    continue;
+                };
+
+                if let Some(thumbnail) = create_thumbnail(&screenshot, width) {
+                    thumbnails.borrow_mut().insert(workspace, thumbnail);
+                }
+            });
+        }
+
+        previews
+    }
+
+    /// Shows the workspace's preview as the button's tooltip,
+    /// refreshing the visible workspaces' previews on hover.
+    fn attach(&self, button: &Button) {
+        button.set_has_tooltip(true);
+
+        {
+            let previews = self.clone();
+            button.connect_enter_notify_event(move |_, _| {
+                previews.refresh();
+                glib::Propagation::Proceed
+            });
+        }
+
+        let thumbnails = self.thumbnails.clone();
+        button.connect_query_tooltip(move |button, _, _, _, tooltip| {
+            let thumbnails = thumbnails.borrow();
+            let Some(thumbnail) = thumbnails.get(button.widget_name().as_str()) else {
+                return false;
+            };
+
+            tooltip.set_icon(Some(thumbnail));
+
+            // keep the label shown by dots mode
+            if let Some(text) = button.tooltip_text() {
+                tooltip.set_text(Some(&text));
+            }
+
+            true
+        });
+    }
+
+    /// Marks the workspace as visible on its output,
+    /// then captures the output once its windows have redrawn.
+    fn set_visible(&self, workspace: &Workspace) {
+        self.visible
+            .borrow_mut()
+            .insert(workspace.monitor.clone(), workspace.name.clone());
+
+        let client = self.client.clone();
+        let output = workspace.monitor.clone();
+        glib::timeout_add_local_once(PREVIEW_CAPTURE_DELAY, move || {
+            client.capture_output(&output);
+        });
+    }
+
+    /// Captures every output with a visible workspace,
+    /// unless they were captured recently.
+    fn refresh(&self) {
+        let now = Instant::now();
+        if self
+            .last_refresh
+            .get()
+            .is_some_and(|last| now.duration_since(last) < PREVIEW_REFRESH_INTERVAL)
+        {
+            return;
+        }
+
+        self.last_refresh.set(Some(now));
+
+        for output in self.visible.borrow().keys() {
+            self.client.capture_output(output);
+        }
+    }
+}
+
+/// Scales a screenshot down to the given width, keeping its aspect ratio.
+fn create_thumbnail(screenshot: &Screenshot, width: i32) -> Option<Pixbuf> {
+    let pixbuf = Pixbuf::from_bytes(
+        &glib::Bytes::from_owned(screenshot.data.clone()),
+        Colorspace::Rgb,
+        true,
+        8,
+        screenshot.width,
+        screenshot.height,
+        screenshot.stride,
+    );
+
+    let height = f64::from(screenshot.height) * f64::from(width) / f64::from(screenshot.width);
+    pixbuf.scale_simple(width, height.round() as i32, InterpType::Bilinear)
+}
+
 fn find_btn(map: &HashMap<i64, Button>, workspace: &Workspace) -> Option<Button> {
     map.get(&workspace.id)
         .or_else(|| {
-            map.values()
-                .find(|btn| btn.label().unwrap_or_default() == workspace.name)
+            map.values().find(|btn| {
+                btn.label().unwrap_or_default() == workspace.name
+                    || btn.widget_name() == workspace.name
+            })
         })
         .cloned()
 }
@@ -192,7 +586,7 @@ fn find_btn(map: &HashMap<i64, Button>, workspace: &Workspace) -> Option<Button>
 impl WorkspacesModule {
     fn show_workspace_check(&self, output: &String, work: &Workspace) -> bool {
         (work.visibility.is_focused() || !self.hidden.contains(&work.name))
-            && (self.all_monitors || output == &work.monitor)
+            && (self.show_all_outputs || output == &work.monitor)
     }
 }
 
@@ -253,9 +647,21 @@ impl Module<gtk::Box> for WorkspacesModule {
 
         {
             let container = container.clone();
+            let mut groups = OutputGroups::new(
+                container.clone(),
+                info.bar_position.orientation(),
+                self.show_all_outputs,
+                self.sort,
+            );
+
             let output_name = info.output_name.to_string();
             let icon_theme = info.icon_theme.clone();
-            let icon_size = self.icon_size;
+            let icon_size = info.icon_size(self.icon_size, default_icon_size());
+            let mode = self.mode;
+
+            let previews = self
+                .previews
+                .then(|| Previews::new(context.client::<wayland::Client>(), self.preview_size));
 
             // keep track of whether init event has fired previously
             // since it fires for every workspace subscriber
@@ -269,24 +675,33 @@ impl Module<gtk::Box> for WorkspacesModule {
 
                             let mut added = HashSet::new();
 
-                            let mut add_workspace = |id: i64, name: &str, visibility: Visibility| {
+                            let mut add_workspace = |id: i64, name: &str, visibility: Visibility, output: &str| {
                                 let item = create_button(
                                     name,
                                     visibility,
                                     &name_map,
                                     &icon_theme,
                                     icon_size,
+                                    mode,
                                     &context.controller_tx,
                                 );
 
-                                container.add(&item);
+                                if let Some(previews) = &previews {
+                                    previews.attach(&item);
+                                }
+
+                                groups.get(output).add(&item);
                                 button_map.insert(id, item);
                             };
 
                             // add workspaces from client
                             for workspace in &workspaces {
+                                if let Some(previews) = previews.as_ref().filter(|_| workspace.visibility.is_visible()) {
+                                    previews.set_visible(workspace);
+                                }
+
                                 if self.show_workspace_check(&output_name, workspace) {
-                                    add_workspace(workspace.id, &workspace.name, workspace.visibility);
+                                    add_workspace(workspace.id, &workspace.name, workspace.visibility, &workspace.monitor);
                                     added.insert(workspace.name.to_string());
                                 }
                             }
@@ -300,7 +715,7 @@ impl Module<gtk::Box> for WorkspacesModule {
                                         // as Hyprland will initialize them this way.
                                         // Since existing workspaces are added above,
                                         // this means there shouldn't be any issues with renaming.
-                                        add_workspace(-(Ironbar::unique_id() as i64), name, Visibility::Hidden);
+                                        add_workspace(-(Ironbar::unique_id() as i64), name, Visibility::Hidden, &output_name);
                                         added.insert(name.to_string());
                                     }
                                 }
@@ -316,8 +731,12 @@ impl Module<gtk::Box> for WorkspacesModule {
                                 }
                             }
 
-                            if self.sort == SortOrder::Alphanumeric {
-                                reorder_workspaces(&container);
+                            for group in &groups.groups {
+                                reorder_workspaces(&group.container, self.sort);
+                            }
+
+                            if !groups.grouped {
+                                reorder_workspaces(&container, self.sort);
                             }
 
                             container.show_all();
@@ -333,15 +752,35 @@ impl Module<gtk::Box> for WorkspacesModule {
                             btn.style_context().remove_class("focused");
                         }
 
+                        if let Some(previews) = &previews {
+                            previews.set_visible(&new);
+                        }
+
                         if let Some(btn) = find_btn(&button_map, &new) {
                             btn.add_class("visible");
                             btn.add_class("focused");
+                            btn.style_context().remove_class("urgent");
                         }
                     }
                     WorkspaceUpdate::Rename { id, name } => {
                         if let Some(btn) = button_map.get(&id) {
-                            let name = name_map.get(&name).unwrap_or(&name);
-                            btn.set_label(name);
+                            let label = name_map.get(&name).unwrap_or(&name);
+
+                            match mode {
+                                DisplayMode::Buttons => btn.set_label(label),
+                                DisplayMode::Dots => btn.set_tooltip_text(Some(label)),
+                            }
+
+                            btn.set_widget_name(&name);
+                        }
+                    }
+                    WorkspaceUpdate::Urgent { id, urgent } => {
+                        if let Some(btn) = button_map.get(&id) {
+                            if urgent && !btn.style_context().has_class("focused") {
+                                btn.add_class("urgent");
+                            } else {
+                                btn.style_context().remove_class("urgent");
+                            }
                         }
                     }
                     WorkspaceUpdate::Add(workspace) => {
@@ -358,14 +797,18 @@ impl Module<gtk::Box> for WorkspacesModule {
                                 &name_map,
                                 &icon_theme,
                                 icon_size,
+                                mode,
                                 &context.controller_tx,
                             );
 
-                            container.add(&item);
-                            if self.sort == SortOrder::Alphanumeric {
-                                reorder_workspaces(&container);
+                            if let Some(previews) = &previews {
+                                previews.attach(&item);
                             }
 
+                            let container = groups.get(&workspace.monitor);
+                            container.add(&item);
+                            reorder_workspaces(&container, self.sort);
+
                             item.show();
 
                             if !name.is_empty() {
@@ -374,7 +817,18 @@ impl Module<gtk::Box> for WorkspacesModule {
                         }
                     }
                     WorkspaceUpdate::Move(workspace) => {
-                        if !self.hidden.contains(&workspace.name) && !self.all_monitors {
+                        if self.show_all_outputs {
+                            if let Some(item) = button_map.get(&workspace.id) {
+                                remove_button(item);
+
+                                let container = groups.get(&workspace.monitor);
+                                container.add(item);
+
+                                reorder_workspaces(&container, self.sort);
+
+                                groups.remove_empty();
+                            }
+                        } else if !self.hidden.contains(&workspace.name) {
                             if workspace.monitor == output_name {
                                 let name = workspace.name;
                                 let item = create_button(
@@ -383,14 +837,17 @@ impl Module<gtk::Box> for WorkspacesModule {
                                     &name_map,
                                     &icon_theme,
                                     icon_size,
+                                    mode,
                                     &context.controller_tx,
                                 );
 
+                                if let Some(previews) = &previews {
+                                    previews.attach(&item);
+                                }
+
                                 container.add(&item);
 
-                                if self.sort == SortOrder::Alphanumeric {
-                                    reorder_workspaces(&container);
-                                }
+                                reorder_workspaces(&container, self.sort);
 
                                 item.show();
 
@@ -398,7 +855,7 @@ impl Module<gtk::Box> for WorkspacesModule {
                                     button_map.insert(workspace.id, item);
                                 }
                             } else if let Some(item) = button_map.get(&workspace.id) {
-                                container.remove(item);
+                                remove_button(item);
                             }
                         }
                     }
@@ -409,7 +866,8 @@ impl Module<gtk::Box> for WorkspacesModule {
                             // if fav_names.contains(&workspace) {
                                 item.style_context().add_class("inactive");
                             } else {
-                                container.remove(item);
+                                remove_button(item);
+                                groups.remove_empty();
                             }
                         }
                     }
@@ -424,3 +882,39 @@ impl Module<gtk::Box> for WorkspacesModule {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted(sort: SortOrder, names: &[&str]) -> Vec<String> {
+        let mut names = names.iter().map(ToString::to_string).collect::<Vec<_>>();
+        names.sort_by(|a, b| sort.compare(a, b));
+        names
+    }
+
+    #[test]
+    fn test_natural_cmp() {
+        assert_eq!(natural_cmp("2", "10"), Ordering::Less);
+        assert_eq!(natural_cmp("ws10", "ws2"), Ordering::Greater);
+        assert_eq!(natural_cmp("web", "Work"), Ordering::Less);
+        assert_eq!(natural_cmp("007", "7"), Ordering::Less);
+        assert_eq!(natural_cmp("a1", "a1"), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_sort_number() {
+        assert_eq!(
+            sorted(SortOrder::Number, &["chat", "10", "2:web", "1", "code"]),
+            ["1", "2:web", "10", "chat", "code"]
+        );
+    }
+
+    #[test]
+    fn test_sort_name() {
+        assert_eq!(
+            sorted(SortOrder::Name, &["ws10", "chat", "ws2", "10", "2"]),
+            ["2", "10", "chat", "ws2", "ws10"]
+        );
+    }
+}
@@ -0,0 +1,247 @@
+use crate::clients::wayland;
+use crate::config::CommonConfig;
+use crate::gtk_helpers::IronbarGtkExt;
+use crate::modules::{Module, ModuleInfo, ModuleParts, ModuleUpdateEvent, WidgetContext};
+use crate::{glib_recv, module_impl, send_async, spawn};
+use color_eyre::Result;
+use gtk::prelude::*;
+use gtk::Label;
+use serde::Deserialize;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tokio::time::interval;
+use tracing::warn;
+use zbus::dbus_proxy;
+
+#[dbus_proxy(
+    default_service = "org.freedesktop.login1",
+    interface = "org.freedesktop.login1.Session",
+    default_path = "/org/freedesktop/login1/session/auto"
+)]
+trait Login1Session {
+    /// The time the session started, in microseconds since the epoch.
+    #[dbus_proxy(property)]
+    fn timestamp(&self) -> zbus::Result<u64>;
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct WorktimeModule {
+    /// The format string to use for the widget label.
+    /// For available tokens, see [below](#formatting-tokens).
+    /// Pango markup is supported.
+    ///
+    /// **Default**: `{active}`
+    #[serde(default = "default_format")]
+    format: String,
+
+    /// The number of seconds without input
+    /// after which you are considered idle.
+    ///
+    /// **Default**: `300`
+    #[serde(default = "default_idle_timeout")]
+    idle_timeout: u32,
+
+    /// The number of minutes of continuous activity
+    /// after which the `break` class is added to the widget.
+    /// The counter is reset each time you go idle.
+    ///
+    /// Leave unset to disable.
+    ///
+    /// **Default**: `null`
+    break_after: Option<u64>,
+
+    /// See [common options](module-level-options#common-options).
+    #[serde(flatten)]
+    pub common: Option<CommonConfig>,
+}
+
+fn default_format() -> String {
+    String::from("{active}")
+}
+
+const fn default_idle_timeout() -> u32 {
+    300
+}
+
+#[derive(Debug, Clone)]
+pub struct Worktime {
+    total: Duration,
+    active: Duration,
+    session: Duration,
+    idle: bool,
+}
+
+/// Tracks active time based on idle notifications.
+struct Tracker {
+    start: Instant,
+    last_tick: Instant,
+    idle_timeout: Duration,
+
+    active: Duration,
+    session_start: Instant,
+    idle: bool,
+}
+
+impl Tracker {
+    /// Creates a tracker for a session which started `session_age` ago.
+    /// Only the time from now onwards is counted as active.
+    fn new(idle_timeout: Duration, session_age: Duration) -> Self {
+        let now = Instant::now();
+
+        Self {
+            start: now.checked_sub(session_age).unwrap_or(now),
+            last_tick: now,
+            idle_timeout,
+            active: Duration::ZERO,
+            session_start: now,
+            idle: false,
+        }
+    }
+
+    fn tick(&mut self) {
+        let now = Instant::now();
+        if !self.idle {
+            self.active += now - self.last_tick;
+        }
+        self.last_tick = now;
+    }
+
+    fn set_idle(&mut self, idle: bool) {
+        self.tick();
+
+        if idle {
+            // the idle period started before the compositor notified us
+            self.active = self.active.saturating_sub(self.idle_timeout);
+        } else {
+            self.session_start = Instant::now();
+        }
+
+        self.idle = idle;
+    }
+
+    fn state(&self) -> Worktime {
+        let session = if self.idle {
+            Duration::ZERO
+        } else {
+            self.session_start.elapsed()
+        };
+
+        Worktime {
+            total: self.start.elapsed(),
+            active: self.active,
+            session,
+            idle: self.idle,
+        }
+    }
+}
+
+impl Module<Label> for WorktimeModule {
+    type SendMessage = Worktime;
+    type ReceiveMessage = ();
+
+    module_impl!("worktime");
+
+    fn spawn_controller(
+        &self,
+        _info: &ModuleInfo,
+        context: &WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        _rx: mpsc::Receiver<Self::ReceiveMessage>,
+    ) -> Result<()> {
+        let tx = context.tx.clone();
+        let wl = context.client::<wayland::Client>();
+
+        let timeout = self.idle_timeout.saturating_mul(1000);
+
+        spawn(async move {
+            let mut idle_rx = wl.subscribe_idle();
+            wl.register_idle_notification(timeout);
+
+            let session_age = session_age().await.unwrap_or_else(|err| {
+                warn!(
+                    "{:?}",
+                    err.wrap_err("Failed to get login time, counting from startup")
+                );
+                Duration::ZERO
+            });
+
+            let mut tracker = Tracker::new(Duration::from_millis(u64::from(timeout)), session_age);
+            let mut interval = interval(Duration::from_secs(1));
+
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => tracker.tick(),
+                    Ok(event) = idle_rx.recv() => {
+                        if event.timeout == timeout {
+                            tracker.set_idle(event.idle);
+                        }
+                    }
+                }
+
+                send_async!(tx, ModuleUpdateEvent::Update(tracker.state()));
+            }
+        });
+
+        Ok(())
+    }
+
+    fn into_widget(
+        self,
+        context: WidgetContext<Self::SendMessage, Self::ReceiveMessage>,
+        info: &ModuleInfo,
+    ) -> Result<ModuleParts<Label>> {
+        let label = Label::builder()
+            .use_markup(true)
+            .angle(info.bar_position.get_angle())
+            .build();
+
+        {
+            let label = label.clone();
+            let break_after = self.break_after.map(|mins| Duration::from_secs(mins * 60));
+
+            glib_recv!(context.subscribe(), worktime => {
+                let text = self
+                    .format
+                    .replace("{total}", &format_duration(worktime.total))
+                    .replace("{active}", &format_duration(worktime.active))
+                    .replace("{session}", &format_duration(worktime.session));
+
+                label.set_markup(&text);
+
+                let style = label.style_context();
+
+                if worktime.idle {
+                    label.add_class("idle");
+                } else {
+                    style.remove_class("idle");
+                }
+
+                if break_after.map_or(false, |after| worktime.session >= after) {
+                    label.add_class("break");
+                } else {
+                    style.remove_class("break");
+                }
+            });
+        }
+
+        Ok(ModuleParts {
+            widget: label,
+            popup: None,
+        })
+    }
+}
+
+/// Gets how long ago the current login session started, according to logind.
+async fn session_age() -> Result<Duration> {
+    let dbus = Box::pin(zbus::Connection::system()).await?;
+    let proxy = Login1SessionProxy::new(&dbus).await?;
+
+    let started = UNIX_EPOCH + Duration::from_micros(proxy.timestamp().await?);
+    Ok(SystemTime::now().duration_since(started)?)
+}
+
+/// Formats a duration as hours and minutes, ie `1h 05m`.
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
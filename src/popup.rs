@@ -1,12 +1,12 @@
 use glib::Propagation;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
 
 use gtk::gdk::Monitor;
 use gtk::prelude::*;
-use gtk::{ApplicationWindow, Button, Orientation};
-use gtk_layer_shell::LayerShell;
+use gtk::{ApplicationWindow, Button, Label, Orientation, Overlay, ToggleButton};
+use gtk_layer_shell::{KeyboardMode, LayerShell};
 use tracing::{debug, trace};
 
 use crate::config::BarPosition;
@@ -18,6 +18,42 @@ use crate::rc_mut;
 pub struct PopupCacheValue {
     pub name: String,
     pub content: ModulePopupParts,
+    pub placement: PopupPlacement,
+    pub pin: PopupPin,
+    /// Wraps the content to overlay the pin button, if enabled.
+    overlay: Option<(Overlay, ToggleButton)>,
+}
+
+/// Per-module overrides for where the popup is placed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PopupPlacement {
+    /// The gap between the bar and the popup.
+    /// Uses the bar's gap if not set.
+    pub gap: Option<i32>,
+    /// The horizontal offset in pixels. Positive values move right.
+    pub offset_x: i32,
+    /// The vertical offset in pixels. Positive values move down.
+    pub offset_y: i32,
+}
+
+impl PopupPlacement {
+    /// Gets the offset along the bar,
+    /// in the direction the popup is positioned.
+    const fn along(self, orientation: Orientation) -> i32 {
+        match orientation {
+            Orientation::Vertical => self.offset_y,
+            _ => self.offset_x,
+        }
+    }
+}
+
+/// Per-module options for pinning the popup open.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PopupPin {
+    /// Whether to show a button to toggle pinning.
+    pub button: bool,
+    /// Whether the popup is pinned as soon as it opens.
+    pub pinned: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -28,13 +64,16 @@ pub struct Popup {
     monitor: Monitor,
     pos: BarPosition,
     current_widget: Rc<RefCell<Option<(usize, usize)>>>,
+    pinned: Rc<Cell<bool>>,
+    gap: i32,
+    close_others: bool,
 }
 
 impl Popup {
     /// Creates a new popup window.
     /// This includes setting up gtk-layer-shell
     /// and an empty `gtk::Box` container.
-    pub fn new(module_info: &ModuleInfo, gap: i32) -> Self {
+    pub fn new(module_info: &ModuleInfo, gap: i32, close_others: bool) -> Self {
         let pos = module_info.bar_position;
         let orientation = pos.orientation();
 
@@ -75,6 +114,9 @@ impl Popup {
         );
         win.set_anchor(gtk_layer_shell::Edge::Right, pos == BarPosition::Right);
 
+        let pinned = Rc::new(Cell::new(false));
+
+        let is_pinned = pinned.clone();
         win.connect_leave_notify_event(move |win, ev| {
             const THRESHOLD: f64 = 3.0;
 
@@ -98,7 +140,7 @@ impl Popup {
                 }
             };
 
-            if hide {
+            if hide && !is_pinned.get() {
                 win.hide();
             }
 
@@ -112,10 +154,20 @@ impl Popup {
             monitor: module_info.monitor.clone(),
             pos,
             current_widget: rc_mut!(None),
+            pinned,
+            gap,
+            close_others,
         }
     }
 
-    pub fn register_content(&self, key: usize, name: String, content: ModulePopupParts) {
+    pub fn register_content(
+        &self,
+        key: usize,
+        name: String,
+        content: ModulePopupParts,
+        placement: PopupPlacement,
+        pin: PopupPin,
+    ) {
         debug!("Registered popup content for #{}", key);
 
         for button in &content.buttons {
@@ -137,13 +189,16 @@ impl Popup {
                     trace!("Resized:  {}x{}", rect.width(), rect.height());
 
                     if let Some((widget_id, button_id)) = *current_widget.borrow() {
-                        if let Some(PopupCacheValue { .. }) = cache.borrow().get(&widget_id) {
+                        if let Some(PopupCacheValue { placement, .. }) =
+                            cache.borrow().get(&widget_id)
+                        {
                             Self::set_position(
                                 &button_cache.borrow(),
                                 button_id,
                                 orientation,
                                 &monitor,
                                 &window,
+                                placement.along(orientation),
                             );
                         }
                     }
@@ -154,21 +209,87 @@ impl Popup {
             .borrow_mut()
             .append(&mut content.buttons.clone());
 
-        self.container_cache
-            .borrow_mut()
-            .insert(key, PopupCacheValue { name, content });
+        let overlay = pin.button.then(|| self.create_pin_overlay(&content));
+
+        self.container_cache.borrow_mut().insert(
+            key,
+            PopupCacheValue {
+                name,
+                content,
+                placement,
+                pin,
+                overlay,
+            },
+        );
+    }
+
+    /// Wraps the popup content in an overlay,
+    /// with a button in the corner to toggle pinning.
+    fn create_pin_overlay(&self, content: &ModulePopupParts) -> (Overlay, ToggleButton) {
+        let overlay = Overlay::new();
+        overlay.add(&content.container);
+
+        let button = ToggleButton::new();
+        button.add_class("popup-pin");
+        button.set_halign(gtk::Align::End);
+        button.set_valign(gtk::Align::Start);
+
+        let label = Label::new(Some("󰐃"));
+        button.add(&label);
+
+        overlay.add_overlay(&button);
+
+        {
+            let pinned = self.pinned.clone();
+            let container = content.container.clone();
+
+            button.connect_toggled(move |button| {
+                set_pinned(&pinned, &container, button.is_active());
+            });
+        }
+
+        (overlay, button)
+    }
+
+    /// Adds the content to the window, and applies its default pinned state.
+    fn attach(&self, value: &PopupCacheValue) {
+        let container = &value.content.container;
+        container.add_class("popup");
+
+        match &value.overlay {
+            Some((overlay, button)) => {
+                self.window.add(overlay);
+                overlay.show();
+                button.show_all();
+
+                // toggling the button updates the pinned state
+                if button.is_active() == value.pin.pinned {
+                    set_pinned(&self.pinned, container, value.pin.pinned);
+                } else {
+                    button.set_active(value.pin.pinned);
+                }
+            }
+            None => {
+                self.window.add(container);
+                set_pinned(&self.pinned, container, value.pin.pinned);
+            }
+        }
     }
 
     pub fn show(&self, widget_id: usize, button_id: usize) {
         self.clear_window();
 
-        if let Some(PopupCacheValue { content, .. }) = self.container_cache.borrow().get(&widget_id)
-        {
+        if let Some(value) = self.container_cache.borrow().get(&widget_id) {
             *self.current_widget.borrow_mut() = Some((widget_id, button_id));
 
-            content.container.add_class("popup");
-            self.window.add(&content.container);
+            self.attach(value);
 
+            let PopupCacheValue {
+                content, placement, ..
+            } = value;
+
+            self.set_gap(*placement);
+            self.set_keyboard(content.keyboard);
             self.window.show();
 
             Self::set_position(
@@ -177,6 +298,7 @@ impl Popup {
                 self.pos.orientation(),
                 &self.monitor,
                 &self.window,
+                placement.along(self.pos.orientation()),
             );
         }
     }
@@ -184,27 +306,60 @@ impl Popup {
     pub fn show_at(&self, widget_id: usize, geometry: WidgetGeometry) {
         self.clear_window();
 
-        if let Some(PopupCacheValue { content, .. }) = self.container_cache.borrow().get(&widget_id)
-        {
-            content.container.add_class("popup");
-            self.window.add(&content.container);
+        if let Some(value) = self.container_cache.borrow().get(&widget_id) {
+            self.attach(value);
 
+            let PopupCacheValue {
+                content, placement, ..
+            } = value;
+
+            self.set_gap(*placement);
+            self.set_keyboard(content.keyboard);
             self.window.show();
+
             Self::set_pos(
                 geometry,
                 self.pos.orientation(),
                 &self.monitor,
                 &self.window,
+                placement.along(self.pos.orientation()),
             );
         }
     }
 
+    /// Sets the margin between the bar and the popup,
+    /// including any offset away from the bar.
+    fn set_gap(&self, placement: PopupPlacement) {
+        let gap = placement.gap.unwrap_or(self.gap);
+
+        let (edge, margin) = match self.pos {
+            BarPosition::Top => (gtk_layer_shell::Edge::Top, gap + placement.offset_y),
+            BarPosition::Bottom => (gtk_layer_shell::Edge::Bottom, gap - placement.offset_y),
+            BarPosition::Left => (gtk_layer_shell::Edge::Left, gap + placement.offset_x),
+            BarPosition::Right => (gtk_layer_shell::Edge::Right, gap - placement.offset_x),
+        };
+
+        self.window.set_layer_shell_margin(edge, margin);
+    }
+
+    /// Sets whether the popup window can take keyboard focus.
+    /// This is left off for most popups,
+    /// so that opening them does not steal focus from the current window.
+    fn set_keyboard(&self, keyboard: bool) {
+        self.window.set_keyboard_mode(if keyboard {
+            KeyboardMode::OnDemand
+        } else {
+            KeyboardMode::None
+        });
+    }
+
     fn set_position(
         buttons: &[Button],
         button_id: usize,
         orientation: Orientation,
         monitor: &Monitor,
         window: &ApplicationWindow,
+        offset: i32,
     ) {
         let button = buttons
             .iter()
@@ -212,7 +367,7 @@ impl Popup {
             .expect("to find valid button");
 
         let geometry = button.geometry(orientation);
-        Self::set_pos(geometry, orientation, monitor, window);
+        Self::set_pos(geometry, orientation, monitor, window, offset);
     }
 
     fn clear_window(&self) {
@@ -222,12 +377,25 @@ impl Popup {
         }
     }
 
-    /// Hides the popup
+    /// Hides the popup, even if it is pinned.
     pub fn hide(&self) {
         *self.current_widget.borrow_mut() = None;
+        self.pinned.set(false);
         self.window.hide();
     }
 
+    /// Hides the popup unless it is pinned open.
+    pub fn hide_unpinned(&self) {
+        if !self.pinned() {
+            self.hide();
+        }
+    }
+
+    /// Checks if the popup is currently pinned open.
+    pub fn pinned(&self) -> bool {
+        self.pinned.get()
+    }
+
     /// Checks if the popup is currently visible
     pub fn visible(&self) -> bool {
         self.window.is_visible()
@@ -237,13 +405,22 @@ impl Popup {
         self.current_widget.borrow().map(|w| w.0)
     }
 
+    /// Checks whether opening a menu on the bar
+    /// should close this popup.
+    pub fn closes_others(&self) -> bool {
+        self.close_others
+    }
+
     /// Sets the popup's X/Y position relative to the left or border of the screen
     /// (depending on orientation).
+    ///
+    /// The additional `offset` is applied after the popup is clamped to the screen.
     fn set_pos(
         geometry: WidgetGeometry,
         orientation: Orientation,
         monitor: &Monitor,
         window: &ApplicationWindow,
+        offset: i32,
     ) {
         let mon_workarea = monitor.workarea();
         let screen_size = if orientation == Orientation::Horizontal {
@@ -263,20 +440,35 @@ impl Popup {
 
         let bar_offset = (f64::from(screen_size) - f64::from(geometry.bar_size)) / 2.0;
 
-        let mut offset = bar_offset + (widget_center - (f64::from(popup_size) / 2.0)).round();
+        let mut position = bar_offset + (widget_center - (f64::from(popup_size) / 2.0)).round();
 
-        if offset < 5.0 {
-            offset = 5.0;
-        } else if offset > f64::from(screen_size - popup_size) - 5.0 {
-            offset = f64::from(screen_size - popup_size) - 5.0;
+        if position < 5.0 {
+            position = 5.0;
+        } else if position > f64::from(screen_size - popup_size) - 5.0 {
+            position = f64::from(screen_size - popup_size) - 5.0;
         }
 
+        let position =
+            (position + f64::from(offset)).clamp(0.0, f64::from(screen_size - popup_size).max(0.0));
+
         let edge = if orientation == Orientation::Horizontal {
             gtk_layer_shell::Edge::Left
         } else {
             gtk_layer_shell::Edge::Top
         };
 
-        window.set_layer_shell_margin(edge, offset as i32);
+        window.set_layer_shell_margin(edge, position as i32);
+    }
+}
+
+/// Updates whether the popup is pinned,
+/// tagging the content so pinned popups can be styled.
+fn set_pinned(pinned: &Cell<bool>, container: &gtk::Box, value: bool) {
+    pinned.set(value);
+
+    if value {
+        container.add_class("pinned");
+    } else {
+        container.style_context().remove_class("pinned");
     }
 }
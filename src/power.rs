@@ -0,0 +1,71 @@
+use crate::modules::WidgetContext;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Stretches polling intervals by a multiplier
+/// while the system is running on battery power,
+/// to reduce wakeups.
+#[derive(Debug, Clone)]
+pub struct BatteryScale {
+    multiplier: f64,
+    on_battery: Option<watch::Receiver<bool>>,
+}
+
+impl BatteryScale {
+    /// Creates a new scale using the given multiplier.
+    /// If `None`, intervals are never changed.
+    pub fn new<TSend, TReceive>(
+        multiplier: Option<f64>,
+        context: &WidgetContext<TSend, TReceive>,
+    ) -> Self
+    where
+        TSend: Clone,
+    {
+        // only connect to upower if actually needed
+        let on_battery = multiplier.and_then(|_| subscribe(context));
+
+        Self {
+            // shortening intervals on battery would defeat the point
+            multiplier: multiplier.unwrap_or(1.0).max(1.0),
+            on_battery,
+        }
+    }
+
+    /// Gets the interval to wait for,
+    /// based on the current power source.
+    pub fn apply(&self, interval: Duration) -> Duration {
+        match &self.on_battery {
+            Some(on_battery) if *on_battery.borrow() => interval.mul_f64(self.multiplier),
+            _ => interval,
+        }
+    }
+}
+
+#[cfg(feature = "upower")]
+fn subscribe<TSend, TReceive>(
+    context: &WidgetContext<TSend, TReceive>,
+) -> Option<watch::Receiver<bool>>
+where
+    TSend: Clone,
+{
+    use crate::clients::upower::BatteryWatcher;
+
+    match context.try_client::<BatteryWatcher>() {
+        Ok(client) => Some(client.subscribe()),
+        Err(err) => {
+            tracing::error!("{err:?}");
+            None
+        }
+    }
+}
+
+#[cfg(not(feature = "upower"))]
+fn subscribe<TSend, TReceive>(
+    _context: &WidgetContext<TSend, TReceive>,
+) -> Option<watch::Receiver<bool>>
+where
+    TSend: Clone,
+{
+    tracing::warn!("Battery-aware polling requires the `upower` feature");
+    None
+}
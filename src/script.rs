@@ -4,15 +4,15 @@ use color_eyre::{Report, Result};
 use serde::Deserialize;
 use std::cmp::min;
 use std::fmt::{Display, Formatter};
-use std::process::Stdio;
+use std::process::{ExitStatus, Stdio};
 use tokio::io::{AsyncBufReadExt, BufReader};
 use tokio::process::Command;
 use tokio::select;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::sleep;
 use tracing::{debug, error, trace, warn};
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[serde(untagged)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ScriptInput {
@@ -20,7 +20,7 @@ pub enum ScriptInput {
     Struct(Script),
 }
 
-#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq)]
+#[derive(Debug, Deserialize, Clone, Copy, Eq, PartialEq, Hash)]
 #[serde(rename_all = "snake_case")]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ScriptMode {
@@ -76,7 +76,7 @@ impl ScriptMode {
     }
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Script {
     #[serde(default = "ScriptMode::default")]
@@ -229,16 +229,24 @@ impl Script {
     /// Otherwise, an `Err` variant
     /// containing the `stderr` is returned.
     pub async fn get_output(&self, args: Option<&[String]>) -> Result<(OutputStream, bool)> {
-        let mut args_list = vec!["-c", &self.cmd];
-
-        if let Some(args) = args {
-            args_list.extend(args.iter().map(String::as_str));
-        }
-
-        debug!("Running sh with args: {args_list:?}");
+        self.get_output_status(args)
+            .await
+            .map(|(output, status)| (output, status.success()))
+    }
 
-        let output = Command::new("/bin/sh")
-            .args(&args_list)
+    /// Attempts to execute a given command,
+    /// waiting for it to finish.
+    ///
+    /// Behaves the same as [`Script::get_output`],
+    /// but returns the full exit status instead of a success flag.
+    pub async fn get_output_status(
+        &self,
+        args: Option<&[String]>,
+    ) -> Result<(OutputStream, ExitStatus)> {
+        debug!("Running sh with script '{}' and args: {args:?}", self.cmd);
+
+        let output = self
+            .command(args)
             .output()
             .await
             .wrap_err("Failed to get script output")?;
@@ -252,7 +260,7 @@ impl Script {
 
             debug!("sending stdout: '{stdout}'");
 
-            Ok((OutputStream::Stdout(stdout), true))
+            Ok((OutputStream::Stdout(stdout), output.status))
         } else {
             let stderr = String::from_utf8(output.stderr)
                 .map(|output| output.trim().to_string())
@@ -260,7 +268,7 @@ impl Script {
 
             debug!("sending stderr: '{stderr}'");
 
-            Ok((OutputStream::Stderr(stderr), false))
+            Ok((OutputStream::Stderr(stderr), output.status))
         }
     }
 
@@ -268,8 +276,19 @@ impl Script {
     /// Returns a `mpsc::Receiver` that sends a message
     /// every time a new line is written to `stdout` or `stderr`.
     pub fn spawn(&self) -> Result<mpsc::Receiver<OutputStream>> {
-        let mut handle = Command::new("/bin/sh")
-            .args(["-c", &self.cmd])
+        self.spawn_with_status().map(|(rx, _)| rx)
+    }
+
+    /// Spawns a long-running process.
+    ///
+    /// Behaves the same as [`Script::spawn`],
+    /// but additionally returns a `oneshot::Receiver`
+    /// which receives the exit status once the process ends.
+    pub fn spawn_with_status(
+        &self,
+    ) -> Result<(mpsc::Receiver<OutputStream>, oneshot::Receiver<ExitStatus>)> {
+        let mut handle = self
+            .command(None)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
             .stdin(Stdio::null())
@@ -295,11 +314,24 @@ impl Script {
         .lines();
 
         let (tx, rx) = mpsc::channel(32);
+        let (status_tx, status_rx) = oneshot::channel();
+
+        let cmd = self.cmd.clone();
 
         spawn(async move {
             loop {
                 select! {
-                    _ = handle.wait() => break,
+                    status = handle.wait() => {
+                        match status {
+                            Ok(status) => {
+                                debug!("Long-running process for '{cmd}' exited with {status}");
+                                status_tx.send(status).ok();
+                            }
+                            Err(err) => error!("{err:?}"),
+                        }
+
+                        break;
+                    },
                     Ok(Some(line)) = stdout_lines.next_line() => {
                         debug!("sending stdout line: '{line}'");
                         send_async!(tx, OutputStream::Stdout(line));
@@ -312,7 +344,23 @@ impl Script {
             }
         });
 
-        Ok(rx)
+        Ok((rx, status_rx))
+    }
+
+    /// Creates a command which runs the script using `sh`,
+    /// passing `args` if provided.
+    ///
+    /// This is for processes which need to be managed directly,
+    /// such as to be killed later.
+    pub fn command(&self, args: Option<&[String]>) -> Command {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(&self.cmd);
+
+        if let Some(args) = args {
+            command.args(args);
+        }
+
+        command
     }
 
     /// Executes the script in oneshot mode,
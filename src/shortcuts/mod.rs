@@ -0,0 +1,261 @@
+mod dbus;
+
+use crate::ipc::{BarCommand, BarCommandType, Command, Ipc, IronvarCommand, Response};
+use crate::{glib_recv_mpsc, send_async, spawn, Ironbar};
+use color_eyre::{Help, Report, Result};
+use dbus::{GlobalShortcutsProxy, RequestProxy, ResponseStream};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::rc::Rc;
+use tokio::sync::mpsc;
+use tracing::{debug, error, info};
+use zbus::export::ordered_stream::OrderedStreamExt;
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, OwnedValue, Value};
+
+/// A global keyboard shortcut,
+/// registered with the compositor through the desktop portal.
+#[derive(Debug, Deserialize, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ShortcutConfig {
+    /// A unique identifier for the shortcut.
+    /// The compositor uses this to remember the trigger assigned to it.
+    ///
+    /// **Required**
+    pub id: String,
+
+    /// A human-readable description of the shortcut,
+    /// shown by the compositor when assigning a trigger.
+    ///
+    /// **Default**: the `id`
+    description: Option<String>,
+
+    /// The preferred key combination to trigger the shortcut,
+    /// following the XDG shortcuts specification, such as `LOGO+b`.
+    /// The compositor is free to ignore this.
+    ///
+    /// **Default**: `null`
+    trigger: Option<String>,
+
+    /// The action to run when the shortcut is triggered.
+    #[serde(flatten)]
+    action: ShortcutAction,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "action", rename_all = "snake_case")]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ShortcutAction {
+    /// Toggles the visibility of a bar.
+    ToggleBar {
+        /// The name of the bar.
+        bar: String,
+    },
+    /// Toggles the popup for a widget on a bar.
+    TogglePopup {
+        /// The name of the bar.
+        bar: String,
+        /// The configured name of the widget.
+        widget: String,
+    },
+    /// Closes all open popups, across every bar.
+    HidePopups,
+    /// Sets an `ironvar` value.
+    SetVar {
+        /// Variable key.
+        key: Box<str>,
+        /// Variable value.
+        value: String,
+    },
+}
+
+impl From<ShortcutAction> for Command {
+    fn from(action: ShortcutAction) -> Self {
+        match action {
+            ShortcutAction::ToggleBar { bar } => Self::Bar(BarCommand {
+                name: bar,
+                subcommand: BarCommandType::ToggleVisible,
+            }),
+            ShortcutAction::TogglePopup { bar, widget } => Self::Bar(BarCommand {
+                name: bar,
+                subcommand: BarCommandType::TogglePopup {
+                    widget_name: widget,
+                },
+            }),
+            ShortcutAction::HidePopups => Self::HidePopups,
+            ShortcutAction::SetVar { key, value } => Self::Var(IronvarCommand::Set { key, value }),
+        }
+    }
+}
+
+/// Registers the configured shortcuts with the desktop portal,
+/// and runs their actions whenever they are triggered.
+///
+/// Shortcuts are only registered once on startup,
+/// so are not affected by reloading the config.
+pub fn start(ironbar: Rc<Ironbar>) {
+    let shortcuts = ironbar
+        .config
+        .borrow()
+        .shortcuts
+        .clone()
+        .unwrap_or_default();
+
+    if shortcuts.is_empty() {
+        return;
+    }
+
+    let (tx, rx) = mpsc::channel(8);
+
+    {
+        let shortcuts = shortcuts.clone();
+
+        spawn(async move {
+            if let Err(err) = listen(&shortcuts, tx).await {
+                error!(
+                    "{:?}",
+                    err.wrap_err("Failed to register global shortcuts")
+                        .suggestion(
+                            "Check your desktop portal supports the GlobalShortcuts interface"
+                        )
+                );
+            }
+        });
+    }
+
+    glib_recv_mpsc!(rx, id => {
+        let Some(shortcut) = shortcuts.iter().find(|shortcut| shortcut.id == id) else {
+            continue;
+        };
+
+        debug!("Running action for shortcut '{id}'");
+
+        let command = shortcut.action.clone().into();
+        if let Response::Err { message } = Ipc::handle_command(command, &ironbar) {
+            error!("Shortcut '{id}' failed: {}", message.unwrap_or_default());
+        }
+    });
+}
+
+/// Creates a portal session and binds the shortcuts to it,
+/// then sends the ID of each shortcut as it is activated.
+async fn listen(shortcuts: &[ShortcutConfig], tx: mpsc::Sender<String>) -> Result<()> {
+    let dbus = Box::pin(zbus::Connection::session()).await?;
+    let proxy = GlobalShortcutsProxy::new(&dbus).await?;
+
+    let session = {
+        let token = handle_token();
+        let responses = subscribe_response(&dbus, &token).await?;
+
+        let session_token = handle_token();
+        let options = HashMap::from([
+            ("handle_token", Value::from(token.as_str())),
+            ("session_handle_token", Value::from(session_token.as_str())),
+        ]);
+
+        proxy.create_session(options).await?;
+        let results = await_response(responses).await?;
+
+        results
+            .get("session_handle")
+            .and_then(|handle| match &**handle {
+                Value::Str(handle) => ObjectPath::try_from(handle.to_string())
+                    .ok()
+                    .map(OwnedObjectPath::from),
+                Value::ObjectPath(handle) => Some(OwnedObjectPath::from(handle.clone())),
+                _ => None,
+            })
+            .ok_or_else(|| Report::msg("Portal did not return a session handle"))?
+    };
+
+    debug!("Created global shortcuts session at '{}'", session.as_str());
+
+    let mut activated = proxy.receive_activated().await?;
+
+    {
+        let token = handle_token();
+        let responses = subscribe_response(&dbus, &token).await?;
+
+        let definitions = shortcuts
+            .iter()
+            .map(|shortcut| {
+                let description = shortcut.description.as_deref().unwrap_or(&shortcut.id);
+
+                let mut properties = HashMap::from([("description", Value::from(description))]);
+                if let Some(trigger) = &shortcut.trigger {
+                    properties.insert("preferred_trigger", Value::from(trigger.as_str()));
+                }
+
+                (shortcut.id.as_str(), properties)
+            })
+            .collect();
+
+        let options = HashMap::from([("handle_token", Value::from(token.as_str()))]);
+
+        proxy
+            .bind_shortcuts(&session, definitions, "", options)
+            .await?;
+        await_response(responses).await?;
+    }
+
+    info!("Registered {} global shortcuts", shortcuts.len());
+
+    while let Some(signal) = activated.next().await {
+        let args = signal.args()?;
+
+        if args.session_handle.as_str() == session.as_str() {
+            send_async!(tx, args.shortcut_id.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+/// Gets a unique token to identify a portal request or session.
+fn handle_token() -> String {
+    format!("ironbar_{}", Ironbar::unique_id())
+}
+
+/// Subscribes to the response for the portal request with the given token.
+///
+/// This must happen before the request is made,
+/// to avoid missing a response which arrives immediately.
+async fn subscribe_response(
+    dbus: &zbus::Connection,
+    token: &str,
+) -> Result<ResponseStream<'static>> {
+    let sender = dbus
+        .unique_name()
+        .ok_or_else(|| Report::msg("Session bus connection has no unique name"))?
+        .trim_start_matches(':')
+        .replace('.', "_");
+
+    let path = ObjectPath::try_from(format!(
+        "/org/freedesktop/portal/desktop/request/{sender}/{token}"
+    ))?;
+
+    let request = RequestProxy::builder(dbus).path(path)?.build().await?;
+    Ok(request.receive_response().await?)
+}
+
+/// Waits for a portal request to complete,
+/// returning its results if it succeeded.
+async fn await_response(mut responses: ResponseStream<'_>) -> Result<HashMap<String, OwnedValue>> {
+    let response = responses
+        .next()
+        .await
+        .ok_or_else(|| Report::msg("Portal request closed without a response"))?;
+
+    let args = response.args()?;
+
+    match args.response {
+        0 => Ok(args
+            .results
+            .into_iter()
+            .map(|(key, value)| (key.to_string(), value))
+            .collect()),
+        1 => Err(Report::msg("Portal request was cancelled")),
+        code => Err(Report::msg(format!(
+            "Portal request failed with code {code}"
+        ))),
+    }
+}
@@ -1,13 +1,14 @@
 use crate::{glib_recv_mpsc, spawn, try_send};
+use color_eyre::eyre::WrapErr;
 use color_eyre::{Help, Report};
 use gtk::ffi::GTK_STYLE_PROVIDER_PRIORITY_USER;
-use gtk::prelude::CssProviderExt;
+use gtk::prelude::*;
 use gtk::{gdk, gio, CssProvider, StyleContext};
 use notify::event::ModifyKind;
 use notify::{recommended_watcher, Event, EventKind, RecursiveMode, Result, Watcher};
-use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
+use std::{env, fs};
 use tokio::sync::mpsc;
 use tokio::time::sleep;
 use tracing::{debug, error, info};
@@ -18,6 +19,24 @@ use tracing::{debug, error, info};
 /// Installs a file watcher and reloads CSS when
 /// write changes are detected on the file.
 pub fn load_css(style_path: PathBuf) {
+    let provider = CssProvider::new();
+    attach_css(style_path, &provider, None);
+}
+
+/// Attempts to load a CSS file which only applies to descendants
+/// of widgets with the `scope` class, such as the windows for a single bar.
+///
+/// The file is watched and reloaded in the same way as [`load_css`].
+/// The returned provider should be removed from the screen
+/// once the scoped widgets are destroyed.
+/// The watcher is then stopped the next time the file changes.
+pub fn load_scoped_css(style_path: PathBuf, scope: &str) -> CssProvider {
+    let provider = CssProvider::new();
+    attach_css(style_path, &provider, Some(scope.to_string()));
+    provider
+}
+
+fn attach_css(style_path: PathBuf, provider: &CssProvider, scope: Option<String>) {
     // file watcher requires absolute path
     let style_path = if style_path.is_absolute() {
         style_path
@@ -25,21 +44,15 @@ pub fn load_css(style_path: PathBuf) {
         env::current_dir().expect("to exist").join(style_path)
     };
 
-    let provider = CssProvider::new();
-
-    match provider.load_from_file(&gio::File::for_path(&style_path)) {
+    match load_into(provider, &style_path, scope.as_deref()) {
         Ok(()) => debug!("Loaded css from '{}'", style_path.display()),
-        Err(err) => error!("{:?}", Report::new(err)
-                    .wrap_err("Failed to load CSS")
-                    .suggestion("Check the CSS file for errors")
-                    .suggestion("GTK CSS uses a subset of the full CSS spec and many properties are not available. Ensure you are not using any unsupported property.")
-                )
+        Err(err) => error!("{err:?}"),
     };
 
     let screen = gdk::Screen::default().expect("Failed to get default GTK screen");
     StyleContext::add_provider_for_screen(
         &screen,
-        &provider,
+        provider,
         GTK_STYLE_PROVIDER_PRIORITY_USER as u32,
     );
 
@@ -47,11 +60,12 @@ pub fn load_css(style_path: PathBuf) {
 
     spawn(async move {
         let style_path2 = style_path.clone();
+        let watcher_tx = tx.clone();
         let mut watcher = recommended_watcher(move |res: Result<Event>| match res {
             Ok(event) if matches!(event.kind, EventKind::Modify(ModifyKind::Data(_))) => {
                 debug!("{event:?}");
                 if event.paths.first().is_some_and(|p| p == &style_path2) {
-                    try_send!(tx, style_path2.clone());
+                    try_send!(watcher_tx, style_path2.clone());
                 }
             }
             Err(e) => error!("Error occurred when watching stylesheet: {:?}", e),
@@ -66,20 +80,195 @@ pub fn load_css(style_path: PathBuf) {
             .expect("Failed to start CSS file watcher");
         debug!("Installed CSS file watcher on '{}'", style_path.display());
 
-        // avoid watcher from dropping
-        loop {
+        // avoid watcher from dropping until the provider is gone
+        while !tx.is_closed() {
             sleep(Duration::from_secs(1)).await;
         }
+
+        debug!("Removed CSS file watcher on '{}'", style_path.display());
     });
 
+    let provider = provider.downgrade();
+
     glib_recv_mpsc!(rx, path => {
+        let Some(provider) = provider.upgrade() else {
+            break;
+        };
+
         info!("Reloading CSS");
-        if let Err(err) = provider.load_from_file(&gio::File::for_path(path)) {
-            error!("{:?}", Report::new(err)
-                .wrap_err("Failed to load CSS")
-                .suggestion("Check the CSS file for errors")
-                .suggestion("GTK CSS uses a subset of the full CSS spec and many properties are not available. Ensure you are not using any unsupported property.")
-            );
+        if let Err(err) = load_into(&provider, &path, scope.as_deref()) {
+            error!("{err:?}");
         }
     });
 }
+
+/// Loads the file into the provider,
+/// scoping its rules first if required.
+fn load_into(
+    provider: &CssProvider,
+    style_path: &Path,
+    scope: Option<&str>,
+) -> color_eyre::Result<()> {
+    let res = match scope {
+        Some(scope) => fs::read_to_string(style_path)
+            .map_err(Report::new)
+            .and_then(|css| {
+                provider
+                    .load_from_data(scope_css(&css, scope).as_bytes())
+                    .map_err(Report::new)
+            }),
+        None => provider
+            .load_from_file(&gio::File::for_path(style_path))
+            .map_err(Report::new),
+    };
+
+    res.wrap_err("Failed to load CSS")
+        .suggestion("Check the CSS file for errors")
+        .suggestion("GTK CSS uses a subset of the full CSS spec and many properties are not available. Ensure you are not using any unsupported property.")
+}
+
+/// Prefixes every selector in the stylesheet with the `.scope` class,
+/// so that its rules only apply to descendants of widgets with that class.
+///
+/// At-rules such as `@define-color` and `@keyframes` are left untouched.
+fn scope_css(css: &str, scope: &str) -> String {
+    let css = strip_comments(css);
+
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css.trim_start();
+
+    while !rest.is_empty() {
+        let Some(open) = rest.find('{') else {
+            output.push_str(rest);
+            break;
+        };
+
+        let end = if rest.starts_with('@') {
+            // statement at-rules end at the first semicolon,
+            // block at-rules are copied as a whole
+            let end = match rest.find(';') {
+                Some(semi) if semi < open => semi + 1,
+                _ => block_end(rest, open),
+            };
+
+            output.push_str(&rest[..end]);
+            end
+        } else {
+            let end = block_end(rest, open);
+
+            let selectors = split_selectors(&rest[..open])
+                .map(|selector| format!(".{scope} {selector}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            output.push_str(&selectors);
+            output.push(' ');
+            output.push_str(&rest[open..end]);
+            end
+        };
+
+        output.push('\n');
+        rest = rest[end..].trim_start();
+    }
+
+    output
+}
+
+/// Gets the index after the brace closing the block opened at `open`.
+fn block_end(css: &str, open: usize) -> usize {
+    let mut depth = 0;
+
+    for (i, c) in css[open..].char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return open + i + 1;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    css.len()
+}
+
+/// Splits a selector list on commas outside of brackets.
+fn split_selectors(selectors: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0;
+    let mut start = 0;
+    let mut parts = vec![];
+
+    for (i, c) in selectors.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(&selectors[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+
+    parts.push(&selectors[start..]);
+
+    parts
+        .into_iter()
+        .map(str::trim)
+        .filter(|selector| !selector.is_empty())
+}
+
+fn strip_comments(css: &str) -> String {
+    let mut output = String::with_capacity(css.len());
+    let mut rest = css;
+
+    while let Some(start) = rest.find("/*") {
+        output.push_str(&rest[..start]);
+
+        rest = rest[start + 2..]
+            .find("*/")
+            .map_or("", |end| &rest[start + 2 + end + 2..]);
+    }
+
+    output.push_str(rest);
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope_css() {
+        let css = r"
+@define-color accent #6699cc;
+
+/* comment { with braces } */
+#bar, .popup :not(.a, .b) {
+    color: @accent;
+}
+
+@keyframes pulse {
+    from { opacity: 1; }
+    to { opacity: 0.5; }
+}
+
+.clock:hover { color: red; }
+";
+
+        let expected = "@define-color accent #6699cc;
+.style-1 #bar, .style-1 .popup :not(.a, .b) {
+    color: @accent;
+}
+@keyframes pulse {
+    from { opacity: 1; }
+    to { opacity: 0.5; }
+}
+.style-1 .clock:hover { color: red; }
+";
+
+        assert_eq!(scope_css(css, "style-1"), expected);
+    }
+}